@@ -0,0 +1,23 @@
+struct ScreenDimensions {
+    width: f32,
+    height: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> screen: ScreenDimensions;
+
+@group(1) @binding(0)
+var capture_texture: texture_2d<f32>;
+
+@vertex
+fn vs_impostor(@location(0) position: vec2<f32>) -> @builtin(position) vec4<f32> {
+    let normalized_x = (position.x / (screen.width / 2.0)) - 1.0;
+    let normalized_y = 1.0 - (position.y / (screen.height / 2.0));
+    return vec4<f32>(normalized_x, normalized_y, 0.0, 1.0);
+}
+
+// The capture has the size of the target, so each pixel copies the one under it.
+@fragment
+fn fs_impostor(@builtin(position) frag_position: vec4<f32>) -> @location(0) vec4<f32> {
+    return textureLoad(capture_texture, vec2<i32>(frag_position.xy), 0);
+}
@@ -0,0 +1,137 @@
+// Clips hull sides for the portal traversal, one side per invocation: near-plane clipping in
+// camera space, projection to the screen, then Sutherland-Hodgman against the convex region the
+// side is seen through. Mirrors `visible_screen_polygon` in renderer.rs.
+
+struct Params {
+    width: f32,
+    height: f32,
+    znear: f32,
+    zfar: f32,
+    focal_x: f32,
+    focal_y: f32,
+    job_count: u32,
+}
+
+struct Job {
+    vertex_offset: u32,
+    vertex_count: u32,
+    clip_offset: u32,
+    clip_count: u32,
+}
+
+const MAX_POINTS: u32 = 96u;
+// Room for a side of `MAX_SIDE_VERTICES` plus the corner near clipping can add.
+const MAX_SIDE_POINTS: u32 = 33u;
+
+struct ClippedSide {
+    count: u32,
+    points: array<vec2<f32>, 96>,
+}
+
+@group(0) @binding(0)
+var<uniform> params: Params;
+
+@group(0) @binding(1)
+var<storage, read> jobs: array<Job>;
+
+@group(0) @binding(2)
+var<storage, read> side_vertices: array<vec4<f32>>;
+
+@group(0) @binding(3)
+var<storage, read> clip_points: array<vec2<f32>>;
+
+@group(0) @binding(4)
+var<storage, read_write> clipped: array<ClippedSide>;
+
+var<private> near_clipped: array<vec3<f32>, 33>;
+var<private> polygon: array<vec2<f32>, 96>;
+var<private> scratch: array<vec2<f32>, 96>;
+
+fn project(p: vec3<f32>) -> vec2<f32> {
+    let ndc = vec2<f32>(p.x * params.focal_x, p.y * params.focal_y) / -p.z;
+    return vec2<f32>((ndc.x + 1.0) * 0.5 * params.width, (1.0 - ndc.y) * 0.5 * params.height);
+}
+
+fn cross2(a: vec2<f32>, b: vec2<f32>) -> f32 {
+    return a.x * b.y - a.y * b.x;
+}
+
+@compute @workgroup_size(64)
+fn cs_clip_sides(@builtin(global_invocation_id) id: vec3<u32>) {
+    let job_index = id.x;
+    if (job_index >= params.job_count) {
+        return;
+    }
+    let job = jobs[job_index];
+    clipped[job_index].count = 0u;
+
+    // Keep the part in front of the near plane (z <= -znear).
+    var near_count = 0u;
+    for (var i = 0u; i < job.vertex_count; i++) {
+        let current = side_vertices[job.vertex_offset + i].xyz;
+        let next = side_vertices[job.vertex_offset + (i + 1u) % job.vertex_count].xyz;
+        let current_inside = current.z <= -params.znear;
+        let next_inside = next.z <= -params.znear;
+        if (current_inside && near_count < MAX_SIDE_POINTS) {
+            near_clipped[near_count] = current;
+            near_count++;
+        }
+        if (current_inside != next_inside && near_count < MAX_SIDE_POINTS) {
+            let t = (-params.znear - current.z) / (next.z - current.z);
+            near_clipped[near_count] = mix(current, next, t);
+            near_count++;
+        }
+    }
+
+    // Points past the far plane are dropped, as `Camera::project_camera_space_to_screen_direct` does.
+    var count = 0u;
+    for (var i = 0u; i < near_count; i++) {
+        let p = near_clipped[i];
+        if (p.z >= -params.zfar && -p.z >= 1e-6) {
+            polygon[count] = project(p);
+            count++;
+        }
+    }
+    if (count < 3u) {
+        return;
+    }
+
+    // The clip region's winding decides which side of its edges is inside.
+    var clip_area = 0.0;
+    for (var i = 0u; i < job.clip_count; i++) {
+        clip_area += cross2(clip_points[job.clip_offset + i], clip_points[job.clip_offset + (i + 1u) % job.clip_count]);
+    }
+    let orientation = sign(clip_area);
+
+    for (var edge = 0u; edge < job.clip_count; edge++) {
+        let a = clip_points[job.clip_offset + edge];
+        let b = clip_points[job.clip_offset + (edge + 1u) % job.clip_count];
+        var out_count = 0u;
+        for (var i = 0u; i < count; i++) {
+            let current = polygon[i];
+            let next = polygon[(i + 1u) % count];
+            let current_side = cross2(b - a, current - a) * orientation;
+            let next_side = cross2(b - a, next - a) * orientation;
+            if (current_side >= 0.0 && out_count < MAX_POINTS) {
+                scratch[out_count] = current;
+                out_count++;
+            }
+            if ((current_side >= 0.0) != (next_side >= 0.0) && out_count < MAX_POINTS) {
+                scratch[out_count] = mix(current, next, current_side / (current_side - next_side));
+                out_count++;
+            }
+        }
+        count = out_count;
+        if (count < 3u) {
+            return;
+        }
+        for (var i = 0u; i < count; i++) {
+            polygon[i] = scratch[i];
+        }
+    }
+
+    clipped[job_index].count = count;
+    for (var i = 0u; i < count; i++) {
+        clipped[job_index].points[i] = polygon[i];
+    }
+}
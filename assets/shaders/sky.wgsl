@@ -0,0 +1,45 @@
+struct ScreenDimensions {
+    width: f32,
+    height: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> screen: ScreenDimensions;
+
+struct SkyVertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) direction: vec3<f32>,
+    @location(2) zenith_color: vec4<f32>,
+    @location(3) horizon_color: vec4<f32>,
+    @location(4) nadir_color: vec4<f32>,
+}
+
+struct SkyVertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) direction: vec3<f32>,
+    @location(1) zenith_color: vec4<f32>,
+    @location(2) horizon_color: vec4<f32>,
+    @location(3) nadir_color: vec4<f32>,
+}
+
+@vertex
+fn vs_sky(model: SkyVertexInput) -> SkyVertexOutput {
+    var out: SkyVertexOutput;
+    let normalized_x = (model.position.x / (screen.width / 2.0)) - 1.0;
+    let normalized_y = 1.0 - (model.position.y / (screen.height / 2.0));
+    out.clip_position = vec4<f32>(normalized_x, normalized_y, 0.0, 1.0);
+    out.direction = model.direction;
+    out.zenith_color = model.zenith_color;
+    out.horizon_color = model.horizon_color;
+    out.nadir_color = model.nadir_color;
+    return out;
+}
+
+@fragment
+fn fs_sky(in: SkyVertexOutput) -> @location(0) vec4<f32> {
+    let elevation = normalize(in.direction).y;
+    if (elevation >= 0.0) {
+        return mix(in.horizon_color, in.zenith_color, pow(elevation, 0.6));
+    }
+    return mix(in.horizon_color, in.nadir_color, pow(-elevation, 0.6));
+}
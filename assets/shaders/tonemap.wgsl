@@ -0,0 +1,85 @@
+struct Tonemap {
+    exposure: f32,
+    curve: u32,
+    apply_gamma: u32,
+    bloom_threshold: f32,
+    bloom_intensity: f32,
+    bloom_step: f32,
+}
+
+@group(0) @binding(0)
+var hdr_texture: texture_2d<f32>;
+
+@group(0) @binding(1)
+var hdr_sampler: sampler;
+
+@group(0) @binding(2)
+var<uniform> tonemap: Tonemap;
+
+struct FullscreenOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+// One triangle covering the whole screen, generated from the vertex index.
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vertex_index: u32) -> FullscreenOutput {
+    var out: FullscreenOutput;
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+    out.clip_position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
+
+fn aces(x: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+// How far the texel at `coord`, clamped to the target, is over the bloom threshold.
+fn bloom_excess(coord: vec2<i32>, size: vec2<i32>) -> vec3<f32> {
+    let texel = textureLoad(hdr_texture, clamp(coord, vec2<i32>(0), size - vec2<i32>(1)), 0).rgb;
+    return max(texel - vec3<f32>(tonemap.bloom_threshold), vec3<f32>(0.0));
+}
+
+// `BloomSettings`: a 5x5 Gaussian of taps `bloom_step` texels apart over what is above the
+// threshold. Kept in step with `SoftwareBackend`'s `bloom`.
+fn bloom(uv: vec2<f32>) -> vec3<f32> {
+    let size = vec2<i32>(textureDimensions(hdr_texture));
+    let center = vec2<i32>(uv * vec2<f32>(size));
+    var total = vec3<f32>(0.0);
+    var total_weight = 0.0;
+    for (var j = -2; j <= 2; j++) {
+        for (var i = -2; i <= 2; i++) {
+            let weight = exp(-f32(i * i + j * j) / 2.0);
+            let offset = vec2<i32>(floor(vec2<f32>(f32(i), f32(j)) * tonemap.bloom_step + 0.5));
+            total += bloom_excess(center + offset, size) * weight;
+            total_weight += weight;
+        }
+    }
+    return total / total_weight * tonemap.bloom_intensity;
+}
+
+@fragment
+fn fs_tonemap(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let hdr = textureSample(hdr_texture, hdr_sampler, in.uv);
+    var color = hdr.rgb;
+    if (tonemap.bloom_intensity > 0.0) {
+        color += bloom(in.uv);
+    }
+    let exposed = color * tonemap.exposure;
+    var mapped: vec3<f32>;
+    switch tonemap.curve {
+        case 1u: { mapped = exposed / (vec3<f32>(1.0) + exposed); }
+        case 2u: { mapped = aces(exposed); }
+        default: { mapped = clamp(exposed, vec3<f32>(0.0), vec3<f32>(1.0)); }
+    }
+    if (tonemap.apply_gamma != 0u) {
+        mapped = pow(mapped, vec3<f32>(1.0 / 2.2));
+    }
+    return vec4<f32>(mapped, clamp(hdr.a, 0.0, 1.0));
+}
@@ -1,52 +1,141 @@
-// benches/generator.rs
-
-use rand::Rng;
-use engine3_refactored::rendering_lib::geometry::{ConvexPolygon, Point2};
-
-pub struct PolygonGenerator;
-
-impl PolygonGenerator {
-    pub fn generate_convex_polygon(
-        center_x: f32,
-        center_y: f32,
-        avg_radius: f32,
-        num_vertices: usize,
-    ) -> ConvexPolygon {
-        let mut rng = rand::thread_rng();
-
-        let mut angles = Vec::with_capacity(num_vertices);
-        for i in 0..num_vertices {
-            let base_angle = (i as f32) * 2.0 * std::f32::consts::PI / (num_vertices as f32);
-            angles.push(base_angle);
-        }
-
-        let max_perturbation = std::f32::consts::PI / (num_vertices as f32) * 0.3;
-
-        for i in 0..num_vertices {
-            let perturbation = rng.gen_range(-max_perturbation..max_perturbation);
-            angles[i] += perturbation;
-        }
-
-        for i in 1..num_vertices {
-            if angles[i] <= angles[i-1] {
-                angles[i] = angles[i-1] + 0.01;
-            }
-        }
-
-        let mut points = Vec::with_capacity(num_vertices);
-
-        let min_radius = avg_radius * 0.8;
-        let max_radius = avg_radius * 1.2;
-
-        for angle_rad in angles {
-            let current_radius = rng.gen_range(min_radius..max_radius);
-
-            points.push(Point2::new(
-                center_x + current_radius * angle_rad.cos(),
-                center_y + current_radius * angle_rad.sin(),
-            ));
-        }
-
-        ConvexPolygon::from_points(&points)
-    }
-}
\ No newline at end of file
+// benches/generator.rs
+
+use rand::Rng;
+use engine3_refactored::rendering_lib::geometry::{ConvexPolygon, Point2, MAX_VERTICES};
+use engine3_refactored::rendering_lib::intersection::ConvexIntersection;
+
+pub struct PolygonGenerator;
+
+impl PolygonGenerator {
+    // Draws from `rng`, so a seeded generator gives the same polygon every run.
+    pub fn generate_convex_polygon(
+        rng: &mut impl Rng,
+        center_x: f32,
+        center_y: f32,
+        avg_radius: f32,
+        num_vertices: usize,
+    ) -> ConvexPolygon {
+        let mut angles = Vec::with_capacity(num_vertices);
+        for i in 0..num_vertices {
+            let base_angle = (i as f32) * 2.0 * std::f32::consts::PI / (num_vertices as f32);
+            angles.push(base_angle);
+        }
+
+        let max_perturbation = std::f32::consts::PI / (num_vertices as f32) * 0.3;
+
+        for angle in angles.iter_mut() {
+            let perturbation = rng.gen_range(-max_perturbation..max_perturbation);
+            *angle += perturbation;
+        }
+
+        for i in 1..num_vertices {
+            if angles[i] <= angles[i-1] {
+                angles[i] = angles[i-1] + 0.01;
+            }
+        }
+
+        let mut points = Vec::with_capacity(num_vertices);
+
+        let min_radius = avg_radius * 0.8;
+        let max_radius = avg_radius * 1.2;
+
+        for angle_rad in angles {
+            let current_radius = rng.gen_range(min_radius..max_radius);
+
+            points.push(Point2::new(
+                center_x + current_radius * angle_rad.cos(),
+                center_y + current_radius * angle_rad.sin(),
+            ));
+        }
+
+        ConvexPolygon::from_points(&points)
+    }
+
+    // Pathological inputs for the clipper. Each is randomized from `rng` but always has the
+    // named property.
+
+    // A long, nearly zero-width quad through (center_x, center_y) at a random angle.
+    pub fn sliver(rng: &mut impl Rng, center_x: f32, center_y: f32, length: f32, thickness: f32) -> ConvexPolygon {
+        let angle = rng.gen_range(0.0..std::f32::consts::PI);
+        let (along, across) = (Point2::new(angle.cos(), angle.sin()), Point2::new(-angle.sin(), angle.cos()));
+        let corner = |l: f32, t: f32| Point2::new(
+            center_x + along.x * l * length / 2.0 + across.x * t * thickness / 2.0,
+            center_y + along.y * l * length / 2.0 + across.y * t * thickness / 2.0,
+        );
+        ConvexPolygon::from_points(&[corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)])
+    }
+
+    // A random convex polygon with extra vertices placed along its edges, up to `MAX_VERTICES`.
+    pub fn with_collinear_vertices(
+        rng: &mut impl Rng,
+        center_x: f32,
+        center_y: f32,
+        avg_radius: f32,
+        num_corners: usize,
+    ) -> ConvexPolygon {
+        let corners = Self::generate_convex_polygon(rng, center_x, center_y, avg_radius, num_corners);
+        let corners = corners.vertices();
+        let extra_per_edge = (MAX_VERTICES - corners.len()) / corners.len().max(1);
+        let mut points = Vec::with_capacity(MAX_VERTICES);
+        for (i, start) in corners.iter().enumerate() {
+            let end = corners[(i + 1) % corners.len()];
+            points.push(*start);
+            let mut steps: Vec<f32> = (0..rng.gen_range(0..=extra_per_edge)).map(|_| rng.gen_range(0.05..0.95)).collect();
+            steps.sort_by(f32::total_cmp);
+            points.extend(steps.iter().map(|t| Point2::new(start.x + (end.x - start.x) * t, start.y + (end.y - start.y) * t)));
+        }
+        ConvexPolygon::from_points(&points)
+    }
+
+    // Two polygons that meet at exactly one vertex: the second is the first mirrored through
+    // its rightmost vertex.
+    pub fn touching_at_vertex(rng: &mut impl Rng, avg_radius: f32, num_vertices: usize) -> (ConvexPolygon, ConvexPolygon) {
+        let first = Self::generate_convex_polygon(rng, 0.0, 0.0, avg_radius, num_vertices);
+        let pivot = first.vertices().iter().copied().max_by(|a, b| a.x.total_cmp(&b.x)).unwrap_or(Point2::new(0.0, 0.0));
+        let mirrored: Vec<Point2> = first.vertices().iter().map(|p| Point2::new(2.0 * pivot.x - p.x, 2.0 * pivot.y - p.y)).collect();
+        (first, ConvexPolygon::from_points(&mirrored))
+    }
+
+    // Two polygons that share one whole edge and nothing else: the first is cut by a vertical
+    // line, and the halves are returned.
+    pub fn touching_along_edge(rng: &mut impl Rng, avg_radius: f32, num_vertices: usize) -> (ConvexPolygon, ConvexPolygon) {
+        let whole = Self::generate_convex_polygon(rng, 0.0, 0.0, avg_radius, num_vertices);
+        let cut_x = rng.gen_range(-0.5..0.5) * avg_radius;
+        let half = |keep_right: bool| {
+            let (top, bottom) = (Point2::new(cut_x, avg_radius * 2.0), Point2::new(cut_x, -avg_radius * 2.0));
+            let half_plane: ConvexPolygon = if keep_right {
+                ConvexPolygon::from_points(&[bottom, Point2::new(avg_radius * 2.0, bottom.y), Point2::new(avg_radius * 2.0, top.y), top])
+            } else {
+                ConvexPolygon::from_points(&[bottom, top, Point2::new(-avg_radius * 2.0, top.y), Point2::new(-avg_radius * 2.0, bottom.y)])
+            };
+            let mut result = ConvexPolygon::new();
+            ConvexIntersection::find_intersection_into(&whole, &half_plane, &mut result);
+            result
+        };
+        (half(false), half(true))
+    }
+
+    // A random polygon and a copy of it shifted sideways until they overlap by `overlap_ratio`
+    // (0 to 1) of the first one's area, found by bisection.
+    pub fn pair_with_overlap_ratio(rng: &mut impl Rng, avg_radius: f32, num_vertices: usize, overlap_ratio: f32) -> (ConvexPolygon, ConvexPolygon) {
+        let first = Self::generate_convex_polygon(rng, 0.0, 0.0, avg_radius, num_vertices);
+        let area = first.area();
+        let shifted = |offset: f32| {
+            let points: Vec<Point2> = first.vertices().iter().map(|p| Point2::new(p.x + offset, p.y)).collect();
+            ConvexPolygon::from_points(&points)
+        };
+        let overlap_at = |offset: f32| {
+            let mut result = ConvexPolygon::new();
+            ConvexIntersection::find_intersection_into(&first, &shifted(offset), &mut result);
+            result.area() / area
+        };
+        // Overlap with a translated copy of a convex shape only shrinks as it moves away.
+        let (mut low, mut high) = (0.0, avg_radius * 3.0);
+        for _ in 0..40 {
+            let mid = (low + high) / 2.0;
+            if overlap_at(mid) > overlap_ratio { low = mid; } else { high = mid; }
+        }
+        let second = shifted((low + high) / 2.0);
+        (first, second)
+    }
+}
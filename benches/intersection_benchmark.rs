@@ -1,51 +1,94 @@
-// benches/intersection_benchmark.rs
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
-
-use engine3_refactored::rendering_lib::geometry::{ConvexPolygon, MAX_VERTICES};
-use engine3_refactored::rendering_lib::intersection::ConvexIntersection;
-
-use rand::Rng;
-
-mod generator;
-use generator::PolygonGenerator;
-
-fn create_test_pair(rng: &mut impl Rng) -> (ConvexPolygon, ConvexPolygon) {
-    let vertices1 = rng.gen_range(3..=MAX_VERTICES.min(8));
-    let radius1 = rng.gen_range(60.0..100.0);
-    let poly1 = PolygonGenerator::generate_convex_polygon(0.0, 0.0, radius1, vertices1);
-
-    let vertices2 = rng.gen_range(3..=MAX_VERTICES.min(8));
-    let radius2 = rng.gen_range(60.0..100.0);
-    let poly2 = PolygonGenerator::generate_convex_polygon(50.0, 0.0, radius2, vertices2);
-    (poly1, poly2)
-}
-
-fn intersection_benchmark_fn(c: &mut Criterion) {
-    let mut rng = rand::thread_rng();
-
-    const NUM_BENCH_PAIRS: usize = 100;
-    let mut pairs: Vec<(ConvexPolygon, ConvexPolygon)> = Vec::with_capacity(NUM_BENCH_PAIRS);
-    for _ in 0..NUM_BENCH_PAIRS {
-        pairs.push(create_test_pair(&mut rng));
-    }
-
-    let mut group = c.benchmark_group("IntersectionOperations");
-
-    group.bench_function("find_intersection_into_100_pairs_reused_result", |b| {
-        let mut result_poly = ConvexPolygon::new();
-        let mut pair_iter = pairs.iter().cycle();
-
-        b.iter(|| {
-            let (poly1, poly2) = pair_iter.next().unwrap();
-            ConvexIntersection::find_intersection_into(
-                black_box(poly1),
-                black_box(poly2),
-                black_box(&mut result_poly),
-            )
-        })
-    });
-    group.finish();
-}
-
-criterion_group!(benches, intersection_benchmark_fn);
+// benches/intersection_benchmark.rs
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use engine3_refactored::rendering_lib::geometry::{ConvexPolygon, MAX_VERTICES};
+use engine3_refactored::rendering_lib::intersection::ConvexIntersection;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+mod generator;
+use generator::PolygonGenerator;
+
+fn create_test_pair(rng: &mut impl Rng) -> (ConvexPolygon, ConvexPolygon) {
+    let vertices1 = rng.gen_range(3..=MAX_VERTICES.min(8));
+    let radius1 = rng.gen_range(60.0..100.0);
+    let poly1 = PolygonGenerator::generate_convex_polygon(rng, 0.0, 0.0, radius1, vertices1);
+
+    let vertices2 = rng.gen_range(3..=MAX_VERTICES.min(8));
+    let radius2 = rng.gen_range(60.0..100.0);
+    let poly2 = PolygonGenerator::generate_convex_polygon(rng, 50.0, 0.0, radius2, vertices2);
+    (poly1, poly2)
+}
+
+// One pair of each pathological shape the generator can make, with the benchmark's name for it.
+fn create_pathological_pairs(rng: &mut impl Rng) -> Vec<(&'static str, (ConvexPolygon, ConvexPolygon))> {
+    vec![
+        ("sliver", (
+            PolygonGenerator::sliver(rng, 0.0, 0.0, 150.0, 1e-3),
+            PolygonGenerator::generate_convex_polygon(rng, 20.0, 0.0, 80.0, 6),
+        )),
+        ("collinear_vertices", (
+            PolygonGenerator::with_collinear_vertices(rng, 0.0, 0.0, 80.0, 4),
+            PolygonGenerator::with_collinear_vertices(rng, 30.0, 0.0, 80.0, 5),
+        )),
+        ("touching_at_vertex", PolygonGenerator::touching_at_vertex(rng, 80.0, 6)),
+        ("touching_along_edge", PolygonGenerator::touching_along_edge(rng, 80.0, 6)),
+        ("half_overlap", PolygonGenerator::pair_with_overlap_ratio(rng, 80.0, 6, 0.5)),
+    ]
+}
+
+// Fixed so that every run benchmarks the same polygons.
+const BENCH_SEED: u64 = 0x5EED;
+
+fn intersection_benchmark_fn(c: &mut Criterion) {
+    let mut rng = StdRng::seed_from_u64(BENCH_SEED);
+
+    const NUM_BENCH_PAIRS: usize = 100;
+    let mut pairs: Vec<(ConvexPolygon, ConvexPolygon)> = Vec::with_capacity(NUM_BENCH_PAIRS);
+    for _ in 0..NUM_BENCH_PAIRS {
+        pairs.push(create_test_pair(&mut rng));
+    }
+
+    let mut group = c.benchmark_group("IntersectionOperations");
+
+    group.bench_function("find_intersection_into_100_pairs_reused_result", |b| {
+        let mut result_poly = ConvexPolygon::new();
+        let mut pair_iter = pairs.iter().cycle();
+
+        b.iter(|| {
+            let (poly1, poly2) = pair_iter.next().unwrap();
+            ConvexIntersection::find_intersection_into(
+                black_box(poly1),
+                black_box(poly2),
+                black_box(&mut result_poly),
+            )
+        })
+    });
+
+    group.bench_function("intersection_area_100_pairs", |b| {
+        let mut pair_iter = pairs.iter().cycle();
+
+        b.iter(|| {
+            let (poly1, poly2) = pair_iter.next().unwrap();
+            ConvexIntersection::intersection_area(black_box(poly1), black_box(poly2))
+        })
+    });
+
+    for (name, (poly1, poly2)) in create_pathological_pairs(&mut rng) {
+        group.bench_function(format!("find_intersection_into_{}", name), |b| {
+            let mut result_poly = ConvexPolygon::new();
+            b.iter(|| {
+                ConvexIntersection::find_intersection_into(
+                    black_box(&poly1),
+                    black_box(&poly2),
+                    black_box(&mut result_poly),
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, intersection_benchmark_fn);
 criterion_main!(benches);
\ No newline at end of file
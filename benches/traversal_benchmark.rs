@@ -0,0 +1,112 @@
+// benches/traversal_benchmark.rs
+use std::collections::HashMap;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use glam::{Mat4, Vec3};
+
+use engine3_refactored::demo_scene::{
+    create_mvp_scene, PORTAL_ID_BACK, PORTAL_ID_FRONT, PORTAL_ID_LEFT, PORTAL_ID_RIGHT,
+};
+use engine3_refactored::engine_lib::camera::Camera;
+use engine3_refactored::engine_lib::scene_types::{
+    Scene, HullInstance, HandlerConfig, PortalConnectionInfo, PortalId, InstanceId, SideIndex,
+    DEFAULT_CAMERA_NAME, RENDER_LAYER_ALL, DEFAULT_GRAVITY,
+};
+use engine3_refactored::engine_lib::audio::AcousticSettings;
+use engine3_refactored::rendering_lib::renderer::{SceneTraversal, SceneView, Viewport, RenderSettings};
+
+const HULL_COUNTS: [usize; 3] = [10, 100, 1000];
+const SCREEN_WIDTH: f32 = 1280.0;
+const SCREEN_HEIGHT: f32 = 720.0;
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+// Side indices of the demo's cuboid blueprint for the four wall portals.
+const FRONT_SIDE: SideIndex = 0;
+const BACK_SIDE: SideIndex = 1;
+const LEFT_SIDE: SideIndex = 2;
+const RIGHT_SIDE: SideIndex = 3;
+
+fn connect(instance: &mut HullInstance, side_index: SideIndex, portal_id: PortalId, target_instance_id: InstanceId, target_portal_id: PortalId) {
+    instance.instance_side_handler_configs.insert(side_index, HandlerConfig::portal(target_instance_id, target_portal_id));
+    instance.portal_connections.insert(portal_id, PortalConnectionInfo { target_instance_id, target_portal_id });
+}
+
+// The demo scene with its rooms replaced by `hull_count` cuboid rooms laid out in a square-ish
+// grid, each wall between neighbours a portal. The camera stands in the corner room looking
+// diagonally across the grid, so most frames see deep into it.
+fn create_grid_scene(hull_count: usize) -> Scene {
+    let mut scene = create_mvp_scene();
+    let template = scene.instances[&0].clone();
+    let columns = (hull_count as f64).sqrt().ceil() as usize;
+
+    scene.instances.clear();
+    scene.entities.clear();
+    scene.debug_labels.clear();
+    scene.sound_emitters.clear();
+    for index in 0..hull_count {
+        let (row, column) = (index / columns, index % columns);
+        let mut instance = HullInstance {
+            id: index as InstanceId,
+            name: format!("Room{}_{}", row, column),
+            initial_transform: if index == 0 { Some(Mat4::IDENTITY) } else { None },
+            portal_connections: HashMap::new(),
+            instance_side_handler_configs: HashMap::new(),
+            render_layers: RENDER_LAYER_ALL,
+            side_animations: HashMap::new(),
+            triggers: Vec::new(),
+            acoustics: AcousticSettings::default(),
+            liquid: None,
+            gravity: DEFAULT_GRAVITY,
+            ..template.clone()
+        };
+        if index + columns < hull_count {
+            connect(&mut instance, FRONT_SIDE, PORTAL_ID_FRONT, (index + columns) as InstanceId, PORTAL_ID_BACK);
+        }
+        if row > 0 {
+            connect(&mut instance, BACK_SIDE, PORTAL_ID_BACK, (index - columns) as InstanceId, PORTAL_ID_FRONT);
+        }
+        if column > 0 {
+            connect(&mut instance, LEFT_SIDE, PORTAL_ID_LEFT, (index - 1) as InstanceId, PORTAL_ID_RIGHT);
+        }
+        if column + 1 < columns && index + 1 < hull_count {
+            connect(&mut instance, RIGHT_SIDE, PORTAL_ID_RIGHT, (index + 1) as InstanceId, PORTAL_ID_LEFT);
+        }
+        scene.instances.insert(instance.id, instance);
+    }
+
+    let camera = scene.camera_mut(DEFAULT_CAMERA_NAME).expect("demo scene has a main camera");
+    camera.host_instance_id = 0;
+    camera.local_transform = Mat4::from_translation(Vec3::new(-1.0, 0.0, -1.0))
+        * Mat4::from_rotation_y(-0.75 * std::f32::consts::PI);
+    scene
+}
+
+fn traversal_benchmark_fn(c: &mut Criterion) {
+    let camera = Camera::new(70.0, 0.1, 100.0);
+    let mut group = c.benchmark_group("SceneTraversal");
+
+    for hull_count in HULL_COUNTS {
+        let scene = create_grid_scene(hull_count);
+        let view = SceneView::from_scene_camera(&scene, DEFAULT_CAMERA_NAME, &camera, Viewport::full(SCREEN_WIDTH, SCREEN_HEIGHT))
+            .expect("grid scene has a main camera");
+        for parallel_traversal in [false, true] {
+            let settings = RenderSettings { parallel_traversal, ..RenderSettings::default() };
+            let name = if parallel_traversal { "build_frame_parallel" } else { "build_frame_serial" };
+            let mut traversal = SceneTraversal::new();
+            group.bench_with_input(BenchmarkId::new(name, hull_count), &scene, |b, scene| {
+                b.iter(|| {
+                    traversal.build_frame(
+                        black_box(scene),
+                        std::slice::from_ref(&view),
+                        &settings,
+                        BACKGROUND_COLOR,
+                        RENDER_LAYER_ALL,
+                    )
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, traversal_benchmark_fn);
+criterion_main!(benches);
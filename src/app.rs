@@ -1,195 +1,1014 @@
-// src/app.rs
-
-use winit::{
-    event::{WindowEvent, DeviceEvent},
-    window::{Window, CursorGrabMode},
-};
-use crate::ui::build_ui;
-use crate::rendering_lib::shader::WGSL_SHADER_SOURCE;
-use crate::rendering_lib::renderer::Renderer;
-use crate::engine_lib::camera::Camera;
-use crate::engine_lib::controller::CameraController;
-use crate::engine_lib::scene_types::Scene;
-use crate::demo_scene;
-
-pub struct PolygonApp {
-    surface: wgpu::Surface<'static>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
-    size: winit::dpi::PhysicalSize<u32>,
-    renderer: Renderer,
-    scene: Scene,
-    camera: Camera,
-    camera_controller: CameraController,
-    egui_ctx: egui::Context,
-    egui_state: egui_winit::State,
-    egui_renderer: egui_wgpu::Renderer,
-    is_focused: bool,
-}
-
-impl PolygonApp {
-    pub async fn new(window: std::sync::Arc<Window>) -> Self {
-        let size = window.inner_size();
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
-        let surface = instance.create_surface(window.clone()).unwrap();
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
-                    label: None,
-                },
-                None,
-            )
-            .await
-            .unwrap();
-
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = surface_caps.formats.iter().copied()
-            .find(|f| f.is_srgb()).unwrap_or(surface_caps.formats[0]);
-        
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: wgpu::PresentMode::Fifo,
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![],
-            desired_maximum_frame_latency: 2,
-        };
-        surface.configure(&device, &config);
-
-        let renderer = Renderer::new(
-            &device, config.format, WGSL_SHADER_SOURCE,
-            size.width as f32, size.height as f32,
-        );
-
-        let egui_ctx = egui::Context::default();
-        let egui_state = egui_winit::State::new(
-            egui_ctx.clone(), egui::ViewportId::ROOT, &window,
-            Some(window.scale_factor() as f32),
-            None, 
-        );
-        let egui_renderer = egui_wgpu::Renderer::new(
-            &device, config.format, None, 1,
-        );
-
-        let scene = demo_scene::create_mvp_scene();
-        let camera = Camera::new(75.0, 0.01, 100.0); // Changed znear from 0.1 to 0.01
-
-        let initial_focus = window.has_focus();
-        let mut initial_grab = false;
-        if initial_focus {
-            if window.set_cursor_grab(CursorGrabMode::Confined)
-                .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
-                .is_ok() {
-                window.set_cursor_visible(false);
-                initial_grab = true;
-            } else { eprintln!("Could not grab cursor on init."); }
-        }
-        
-        let initial_cam_yaw_from_scene = std::f32::consts::PI;
-        let initial_cam_pitch_from_scene = 0.0;
-
-        let camera_controller = CameraController::new(
-            initial_cam_yaw_from_scene, 
-            initial_cam_pitch_from_scene, 
-            initial_grab, 
-            0.002
-        );
-
-        Self {
-            surface, device, queue, config, size,
-            renderer, scene, camera, camera_controller,
-            egui_ctx, egui_state, egui_renderer,
-            is_focused: initial_focus,
-        }
-    }
-
-    pub fn get_size(&self) -> winit::dpi::PhysicalSize<u32> { self.size }
-    
-    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.config.width = new_size.width;
-            self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
-        }
-    }
-
-    pub fn set_focused(&mut self, focused: bool) {
-        self.is_focused = focused;
-    }
-
-    pub fn update(&mut self, dt: f32) {
-        // Pass &mut self.scene to apply_to_transform
-        self.camera_controller.apply_to_transform(&mut self.scene, dt);
-    }
-
-    pub fn render(&mut self, window: &Window) -> Result<(), wgpu::SurfaceError> {
-        let output_texture = self.surface.get_current_texture()?;
-        let view = output_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
-        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-            label: Some("Main Command Encoder"),
-        });
-
-        self.renderer.render_scene(
-            &self.device, &self.queue, &mut encoder, &view,
-            &self.scene, &self.camera,
-            self.size.width as f32, self.size.height as f32,
-            wgpu::Color { r: 0.05, g: 0.05, b: 0.1, a: 1.0 }, 
-        );
-
-        let raw_input = self.egui_state.take_egui_input(window);
-        let full_output = self.egui_ctx.run(raw_input, |ctx| { build_ui(ctx); });
-        self.egui_state.handle_platform_output(window, full_output.platform_output);
-        let tris = self.egui_ctx.tessellate(full_output.shapes, self.egui_ctx.pixels_per_point());
-        for (id, image_delta) in &full_output.textures_delta.set {
-            self.egui_renderer.update_texture(&self.device, &self.queue, *id, image_delta);
-        }
-        let screen_descriptor = egui_wgpu::ScreenDescriptor {
-            size_in_pixels: [self.config.width, self.config.height],
-            pixels_per_point: window.scale_factor() as f32,
-        };
-        self.egui_renderer.update_buffers(&self.device, &self.queue, &mut encoder, &tris, &screen_descriptor);
-        { 
-            let mut gui_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("GUI Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view, resolve_target: None,
-                    ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store }, 
-                })],
-                depth_stencil_attachment: None, occlusion_query_set: None, timestamp_writes: None,
-            });
-            self.egui_renderer.render(&mut gui_render_pass, &tris, &screen_descriptor);
-        }
-        for tex_id in &full_output.textures_delta.free { self.egui_renderer.free_texture(tex_id); }
-
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output_texture.present();
-        Ok(())
-    }
-    
-    pub fn handle_window_event(&mut self, event: &WindowEvent, window: &Window) -> bool {
-        if self.egui_state.on_window_event(window, event).consumed { return true; }
-        if self.camera_controller.handle_window_event(event, window) { return true; }
-        match event {
-            WindowEvent::Focused(focused) => { self.is_focused = *focused; false }
-            _ => false,
-        }
-    }
-
-    pub fn handle_device_event(&mut self, event: &DeviceEvent, _window: &Window) {
-        self.camera_controller.handle_device_event(event);
-    }
-}
\ No newline at end of file
+// src/app.rs
+
+use winit::{
+    event::{WindowEvent, DeviceEvent, ElementState, MouseButton},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Window, CursorGrabMode},
+};
+use crate::ui::{build_automap_window, build_profiler_window, build_settings_panel, build_ui, draw_debug_labels, draw_hud, DebugStats, HudInfo, PickedSide};
+use crate::scene_editor::build_scene_editor;
+use crate::rendering_lib::shader::{WGSL_SHADER_SOURCE, SCENE_SHADER_PATH, SKY_SHADER_PATH};
+use crate::rendering_lib::renderer::{Renderer, RenderStats, SceneView, Viewport};
+use crate::rendering_lib::gpu_timing::GpuPass;
+use crate::rendering_lib::offscreen::OffscreenTarget;
+use crate::rendering_lib::software::{SoftwareBlitter, SoftwareRenderer};
+use crate::rendering_lib::recording::{FrameRecorder, FrameSink};
+use crate::engine_lib::camera::Camera;
+use crate::engine_lib::controller::CameraController;
+use crate::engine_lib::animation::update_side_animations;
+use crate::engine_lib::liquid::update_liquids;
+use crate::engine_lib::events::EngineEvent;
+use crate::engine_lib::picking::{raycast_scene, RayHit};
+use crate::engine_lib::automap::{Automap, AutomapLayout};
+use crate::engine_lib::interaction::{interaction_at, Interaction, INTERACTION_REACH};
+use crate::engine_lib::chase_camera::ChaseCamera;
+use crate::engine_lib::scene_export::{export_scene, ExportOptions};
+use crate::engine_lib::entity::{Entity, EntityId, ConvexMesh};
+use crate::engine_lib::hot_reload::{carry_over_runtime_state, FileWatcher, SceneLoader};
+use crate::engine_lib::side_handler::MAX_PORTAL_RECURSION_DEPTH;
+use crate::rendering_lib::geometry::Point2;
+use crate::engine_lib::scene_types::{Scene, RENDER_LAYER_ALL};
+use crate::engine_lib::scene_loading::{repair_missing_references, split_concave_sides, validate_blueprints, SceneDiagnostic};
+use crate::settings::{Settings, SETTINGS_PATH};
+use crate::profiler::{FrameTimeHistory, FrameTimings};
+use crate::demo_scene;
+#[cfg(feature = "audio")]
+use crate::audio::AudioOutput;
+
+// Display options for `PolygonApp`, set at startup and adjustable from the UI.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AppConfig {
+    // Falls back to Fifo (always supported) if the surface doesn't support the requested mode.
+    pub present_mode: wgpu::PresentMode,
+    // Upper bound on frames per second enforced by sleeping on the CPU; None for uncapped.
+    pub frame_rate_cap: Option<f32>,
+    // Vertical field of view of the main camera when not zoomed.
+    pub fov_y_deg: f32,
+    // Draw the main view with `SoftwareRenderer` instead of the GPU.
+    pub software_rendering: bool,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self { present_mode: wgpu::PresentMode::Fifo, frame_rate_cap: None, fov_y_deg: 75.0, software_rendering: false }
+    }
+}
+
+// Stands in for the player in third-person view.
+const PAWN_ENTITY_ID: EntityId = EntityId::MAX;
+const PAWN_HALF_EXTENTS: glam::Vec3 = glam::Vec3::new(0.15, 0.15, 0.25);
+const PAWN_COLOR: [f32; 4] = [0.9, 0.6, 0.2, 1.0];
+
+// Holding the zoom key narrows the field of view to this fraction of `AppConfig::fov_y_deg`.
+const ZOOM_FOV_FACTOR: f32 = 0.3;
+// Fastest the simulation can be run with `PolygonApp::set_time_scale`.
+pub const MAX_TIME_SCALE: f32 = 4.0;
+// Where F6 writes the scene's geometry, relative to the working directory.
+const SCENE_EXPORT_PATH: &str = "scene_export.glb";
+
+pub struct PolygonApp {
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    size: winit::dpi::PhysicalSize<u32>,
+    renderer: Renderer,
+    // Used with `AppConfig::software_rendering`; the blitter is created on first use.
+    software_renderer: SoftwareRenderer,
+    software_blitter: Option<SoftwareBlitter>,
+    scene: Scene,
+    camera: Camera,
+    camera_controller: CameraController,
+    egui_ctx: egui::Context,
+    egui_state: egui_winit::State,
+    egui_renderer: egui_wgpu::Renderer,
+    is_focused: bool,
+    scene_diagnostics: Vec<SceneDiagnostic>,
+    last_render_stats: RenderStats,
+    smoothed_frame_time: f32,
+    // Recent frames' stage timings, graphed in the profiler window; `frame_timings` is filled
+    // in over the current frame and pushed when it is presented.
+    frame_history: FrameTimeHistory,
+    frame_timings: FrameTimings,
+    profiler_open: bool,
+    // Rooms the camera has been in, drawn in the automap window.
+    automap: Automap,
+    automap_open: bool,
+    // Multiplies the simulation's timestep; 0 pauses it. `resume_time_scale` is what P restores.
+    time_scale: f32,
+    resume_time_scale: f32,
+    recorder: Option<FrameRecorder>,
+    recording_target: Option<OffscreenTarget>,
+    split_screen_camera: Option<String>,
+    picture_in_picture_camera: Option<String>,
+    app_config: AppConfig,
+    // Loaded from `SETTINGS_PATH` at startup and written back by `save_settings`.
+    settings: Settings,
+    settings_open: bool,
+    // Edited in the settings panel since it was last saved.
+    settings_unsaved: bool,
+    supported_present_modes: Vec<wgpu::PresentMode>,
+    // Last cursor position in physical pixels, for picking.
+    cursor_position: Option<Point2>,
+    picked_side: Option<PickedSide>,
+    scene_editor_open: bool,
+    // Third-person view of the active camera's pose, which is then drawn as a pawn.
+    chase_camera: Option<ChaseCamera>,
+    // The scene file being reloaded on change, with its parser.
+    scene_watch: Option<(FileWatcher, SceneLoader)>,
+    // Scene and sky shader files, watched in shader development mode.
+    shader_watchers: Option<[FileWatcher; 2]>,
+    #[cfg(feature = "audio")]
+    audio: Option<AudioOutput>,
+}
+
+impl PolygonApp {
+    // The settings file at `SETTINGS_PATH` (created with defaults if there is none) takes
+    // precedence over `app_config` for the field of view and vsync, and sets the window size.
+    pub async fn new(window: std::sync::Arc<Window>, app_config: AppConfig) -> Self {
+        let settings = Settings::load_or_create(SETTINGS_PATH);
+        let app_config = AppConfig {
+            fov_y_deg: settings.fov_y_deg,
+            present_mode: if settings.vsync { wgpu::PresentMode::Fifo } else { wgpu::PresentMode::Immediate },
+            ..app_config
+        };
+        let [window_width, window_height] = settings.window_size;
+        let size = window.request_inner_size(winit::dpi::LogicalSize::new(window_width, window_height))
+            .unwrap_or_else(|| window.inner_size());
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let surface = instance.create_surface(window.clone()).unwrap();
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: Some(&surface),
+                force_fallback_adapter: false,
+            })
+            .await
+            .unwrap();
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    // Timestamp queries, where available, for `RenderSettings::gpu_timing`.
+                    required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+                    required_limits: wgpu::Limits::default(),
+                    label: None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let surface_caps = surface.get_capabilities(&adapter);
+        let surface_format = surface_caps.formats.iter().copied()
+            .find(|f| f.is_srgb()).unwrap_or(surface_caps.formats[0]);
+        
+        let present_mode = pick_present_mode(app_config.present_mode, &surface_caps.present_modes);
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width,
+            height: size.height,
+            present_mode,
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+        surface.configure(&device, &config);
+
+        let renderer = Renderer::new(
+            &device, config.format, WGSL_SHADER_SOURCE,
+            size.width as f32, size.height as f32,
+        );
+
+        let egui_ctx = egui::Context::default();
+        let egui_state = egui_winit::State::new(
+            egui_ctx.clone(), egui::ViewportId::ROOT, &window,
+            Some(window.scale_factor() as f32),
+            None, 
+        );
+        let egui_renderer = egui_wgpu::Renderer::new(
+            &device, config.format, None, 1,
+        );
+
+        let mut scene = demo_scene::create_mvp_scene();
+        let mut scene_diagnostics = repair_missing_references(&mut scene);
+        split_concave_sides(&mut scene);
+        scene_diagnostics.extend(validate_blueprints(&scene));
+        for diagnostic in &scene_diagnostics {
+            log::warn!("Scene: {}", diagnostic);
+        }
+        scene.events.subscribe(|event| log::debug!("Event: {:?}", event));
+        scene.events.publish(EngineEvent::SceneLoaded { diagnostic_count: scene_diagnostics.len() });
+        let camera = Camera::new(app_config.fov_y_deg, 0.01, 100.0); // Changed znear from 0.1 to 0.01
+
+        let initial_focus = window.has_focus();
+        let mut initial_grab = false;
+        if initial_focus {
+            if window.set_cursor_grab(CursorGrabMode::Confined)
+                .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
+                .is_ok() {
+                window.set_cursor_visible(false);
+                initial_grab = true;
+            } else { eprintln!("Could not grab cursor on init."); }
+        }
+        
+        let initial_cam_yaw_from_scene = std::f32::consts::PI;
+        let initial_cam_pitch_from_scene = 0.0;
+
+        let mut camera_controller = CameraController::new(
+            initial_cam_yaw_from_scene, 
+            initial_cam_pitch_from_scene, 
+            initial_grab, 
+            settings.mouse_sensitivity,
+        );
+        camera_controller.walking = true;
+
+        let mut app = Self {
+            surface, device, queue, config, size,
+            renderer, scene, camera, camera_controller,
+            software_renderer: SoftwareRenderer::new(),
+            software_blitter: None,
+            egui_ctx, egui_state, egui_renderer,
+            is_focused: initial_focus,
+            scene_diagnostics,
+            last_render_stats: RenderStats::default(),
+            smoothed_frame_time: 0.0,
+            frame_history: FrameTimeHistory::default(),
+            frame_timings: FrameTimings::default(),
+            profiler_open: false,
+            automap: Automap::new(),
+            automap_open: false,
+            time_scale: 1.0,
+            resume_time_scale: 1.0,
+            recorder: recorder_from_env(),
+            recording_target: None,
+            split_screen_camera: None,
+            picture_in_picture_camera: None,
+            cursor_position: None,
+            picked_side: None,
+            scene_editor_open: false,
+            chase_camera: None,
+            scene_watch: None,
+            shader_watchers: std::env::var_os("ENGINE3_SHADER_HOT_RELOAD").map(|_| shader_watchers()),
+            #[cfg(feature = "audio")]
+            audio: AudioOutput::new()
+                .map_err(|e| log::warn!("Audio disabled: {}", e))
+                .ok(),
+            supported_present_modes: surface_caps.present_modes.clone(),
+            app_config: AppConfig { present_mode, ..app_config },
+            settings: settings.clone(),
+            settings_open: false,
+            settings_unsaved: false,
+        };
+        app.apply_settings(settings);
+        app
+    }
+
+    pub fn get_size(&self) -> winit::dpi::PhysicalSize<u32> { self.size }
+    
+    pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        if new_size.width > 0 && new_size.height > 0 {
+            self.size = new_size;
+            self.config.width = new_size.width;
+            self.config.height = new_size.height;
+            self.surface.configure(&self.device, &self.config);
+        }
+    }
+
+    pub fn scene_diagnostics(&self) -> &[SceneDiagnostic] { &self.scene_diagnostics }
+    pub fn last_render_stats(&self) -> &RenderStats { &self.last_render_stats }
+    pub fn frame_history(&self) -> &FrameTimeHistory { &self.frame_history }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.is_focused = focused;
+    }
+
+    // Starts dumping every rendered frame to `sink`; the simulation then advances by a fixed
+    // 1/fps step per frame instead of wall-clock time.
+    pub fn start_recording(&mut self, sink: FrameSink, frames_per_second: f32) -> std::io::Result<()> {
+        self.recorder = Some(FrameRecorder::new(sink, frames_per_second)?);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recorder.take() {
+            log::info!("Recording stopped after {} frames", recorder.frames_written());
+        }
+        self.recording_target = None;
+    }
+
+    pub fn is_recording(&self) -> bool { self.recorder.is_some() }
+
+    pub fn scene(&self) -> &Scene { &self.scene }
+
+    pub fn app_config(&self) -> &AppConfig { &self.app_config }
+
+    // Applies new display options, reconfiguring the surface if the present mode changed.
+    pub fn set_app_config(&mut self, app_config: AppConfig) {
+        let present_mode = pick_present_mode(app_config.present_mode, &self.supported_present_modes);
+        if present_mode != self.config.present_mode {
+            self.config.present_mode = present_mode;
+            self.surface.configure(&self.device, &self.config);
+        }
+        self.app_config = AppConfig { present_mode, ..app_config };
+    }
+
+    pub fn settings(&self) -> &Settings { &self.settings }
+
+    // Puts `settings` into effect: input, field of view, vsync and the renderer's scale and
+    // debug views.
+    pub fn apply_settings(&mut self, settings: Settings) {
+        self.camera_controller.mouse_sensitivity = settings.mouse_sensitivity;
+        self.camera_controller.invert_y = settings.invert_y;
+        self.camera_controller.move_speed = settings.move_speed;
+        self.renderer.settings.render_scale = settings.render_scale;
+        self.renderer.settings.wireframe = settings.wireframe;
+        self.renderer.settings.show_clip_regions = settings.show_clip_regions;
+        self.renderer.settings.show_debug_labels = settings.show_debug_labels;
+        let vsync_changed = settings.vsync != self.settings.vsync;
+        let app_config = AppConfig {
+            fov_y_deg: settings.fov_y_deg,
+            present_mode: if vsync_changed {
+                if settings.vsync { wgpu::PresentMode::Fifo } else { wgpu::PresentMode::Immediate }
+            } else {
+                self.app_config.present_mode
+            },
+            ..self.app_config.clone()
+        };
+        self.settings = settings;
+        self.set_app_config(app_config);
+    }
+
+    // Writes the settings, with the current vsync and window size, back to `SETTINGS_PATH` for
+    // the next run.
+    pub fn save_settings(&mut self, window: &Window) {
+        let logical_size = window.inner_size().to_logical::<u32>(window.scale_factor());
+        self.settings.vsync = self.app_config.present_mode != wgpu::PresentMode::Immediate;
+        self.settings.window_size = [logical_size.width, logical_size.height];
+        if let Err(e) = self.settings.save(SETTINGS_PATH) {
+            log::error!("Cannot save {}: {}", SETTINGS_PATH, e);
+        }
+        self.settings_unsaved = false;
+    }
+
+    // Minimum time a frame should take under the configured frame-rate cap.
+    pub fn min_frame_time(&self) -> Option<std::time::Duration> {
+        self.app_config.frame_rate_cap
+            .filter(|fps| *fps > 0.0)
+            .map(|fps| std::time::Duration::from_secs_f32(1.0 / fps))
+    }
+
+    // Switches input and the main view to another of the scene's cameras.
+    pub fn set_active_camera(&mut self, name: &str) -> bool {
+        if !self.scene.set_active_camera(name) {
+            return false;
+        }
+        if let Some(camera) = self.scene.active_camera() {
+            self.camera_controller.sync_orientation_from(&camera.local_transform);
+        }
+        true
+    }
+
+    // Shows `camera_name` in the right half of the window next to the active camera, or
+    // returns to a single full-window view with None.
+    pub fn set_split_screen(&mut self, camera_name: Option<String>) {
+        self.split_screen_camera = camera_name;
+    }
+
+    pub fn split_screen_camera(&self) -> Option<&str> { self.split_screen_camera.as_deref() }
+
+    // Shows `camera_name` in a small inset in the top-right corner, drawn over the main view.
+    pub fn set_picture_in_picture(&mut self, camera_name: Option<String>) {
+        self.picture_in_picture_camera = camera_name;
+    }
+
+    pub fn picture_in_picture_camera(&self) -> Option<&str> { self.picture_in_picture_camera.as_deref() }
+
+    // Writes the scene's walls, laid out through the portals, for viewing in other 3D tools.
+    fn export_scene_geometry(&self) {
+        let path = std::path::Path::new(SCENE_EXPORT_PATH);
+        match export_scene(&self.scene, path, &ExportOptions::default()) {
+            Ok(()) => log::info!("Exported scene geometry to {}", path.display()),
+            Err(e) => log::warn!("Cannot export scene geometry to {}: {}", path.display(), e),
+        }
+    }
+
+    // First camera other than the active one, for the secondary-view toggles.
+    fn secondary_camera_name(&self) -> Option<String> {
+        let name = self.scene.camera_names().into_iter()
+            .find(|name| *name != self.scene.active_camera)
+            .map(str::to_string);
+        if name.is_none() {
+            log::info!("Secondary views need a second camera in the scene");
+        }
+        name
+    }
+
+    pub fn picked_side(&self) -> Option<&PickedSide> { self.picked_side.as_ref() }
+
+    pub fn time_scale(&self) -> f32 { self.time_scale }
+
+    // Runs side animations and walking physics at `time_scale` times real time, from 0 (paused)
+    // to `MAX_TIME_SCALE`, e.g. 0.1 to watch moving geometry in slow motion. The camera still
+    // moves and looks around in real time.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.clamp(0.0, MAX_TIME_SCALE);
+    }
+
+    pub fn is_paused(&self) -> bool { self.time_scale == 0.0 }
+
+    // Pauses, or resumes at the time scale in effect before the pause.
+    pub fn toggle_pause(&mut self) {
+        if self.is_paused() {
+            self.time_scale = if self.resume_time_scale > 0.0 { self.resume_time_scale } else { 1.0 };
+        } else {
+            self.resume_time_scale = self.time_scale;
+            self.time_scale = 0.0;
+        }
+    }
+
+    // Replaces the scene, keeping camera poses and event subscriptions where the new scene allows.
+    pub fn replace_scene(&mut self, mut scene: Scene) {
+        self.scene_diagnostics = repair_missing_references(&mut scene);
+        split_concave_sides(&mut scene);
+        self.scene_diagnostics.extend(validate_blueprints(&scene));
+        for diagnostic in &self.scene_diagnostics {
+            log::warn!("Scene: {}", diagnostic);
+        }
+        carry_over_runtime_state(&mut self.scene, &mut scene);
+        scene.events.publish(EngineEvent::SceneLoaded { diagnostic_count: self.scene_diagnostics.len() });
+        self.scene = scene;
+        self.picked_side = None;
+        if let Some(camera) = self.scene.active_camera() {
+            self.camera_controller.sync_orientation_from(&camera.local_transform);
+        }
+    }
+
+    // Loads the scene at `path` with `loader` and reloads it whenever the file changes. A reload
+    // that fails is logged and the current scene kept, so a half-saved file does no harm.
+    pub fn watch_scene_file(&mut self, path: impl Into<std::path::PathBuf>, loader: SceneLoader) -> Result<(), String> {
+        let watcher = FileWatcher::new(path);
+        let scene = loader(watcher.path())?;
+        self.replace_scene(scene);
+        self.scene_watch = Some((watcher, loader));
+        Ok(())
+    }
+
+    pub fn stop_watching_scene_file(&mut self) {
+        self.scene_watch = None;
+    }
+
+    fn reload_changed_scene_file(&mut self) {
+        let (watcher, loader) = match &mut self.scene_watch {
+            Some(watch) => watch,
+            None => return,
+        };
+        if !watcher.poll() {
+            return;
+        }
+        match loader(watcher.path()) {
+            Ok(scene) => {
+                log::info!("Reloaded scene from {}", watcher.path().display());
+                self.replace_scene(scene);
+            }
+            Err(e) => log::warn!("Cannot reload scene from {}: {}", watcher.path().display(), e),
+        }
+    }
+
+    // Shader development mode: while on, the scene and sky shaders are rebuilt from
+    // assets/shaders whenever one of the files changes.
+    pub fn set_shader_hot_reload(&mut self, enabled: bool) {
+        self.shader_watchers = enabled.then(shader_watchers);
+    }
+
+    pub fn is_shader_hot_reload_enabled(&self) -> bool { self.shader_watchers.is_some() }
+
+    fn reload_changed_shaders(&mut self) {
+        let watchers = match &mut self.shader_watchers {
+            Some(watchers) => watchers,
+            None => return,
+        };
+        // Poll both so one change is not reported again next frame.
+        let changed = watchers.iter_mut().fold(false, |changed, watcher| watcher.poll() | changed);
+        if !changed {
+            return;
+        }
+        let sources = std::fs::read_to_string(SCENE_SHADER_PATH)
+            .and_then(|scene_source| Ok((scene_source, std::fs::read_to_string(SKY_SHADER_PATH)?)));
+        let result = match sources {
+            Ok((scene_source, sky_source)) => self.renderer.reload_shaders(&self.device, &scene_source, &sky_source),
+            Err(e) => Err(e.to_string()),
+        };
+        match result {
+            Ok(()) => log::info!("Reloaded shaders"),
+            Err(e) => log::warn!("Keeping previous shaders: {}", e),
+        }
+    }
+
+    pub fn set_chase_camera(&mut self, chase_camera: Option<ChaseCamera>) {
+        self.chase_camera = chase_camera;
+        self.sync_pawn_entity();
+    }
+
+    pub fn chase_camera(&self) -> Option<&ChaseCamera> { self.chase_camera.as_ref() }
+
+    // Keeps the pawn entity on the active camera's pose while in third person.
+    fn sync_pawn_entity(&mut self) {
+        let pawn = match (self.chase_camera, self.scene.active_camera()) {
+            (Some(_), Some(camera)) => camera,
+            _ => {
+                self.scene.entities.remove(&PAWN_ENTITY_ID);
+                return;
+            }
+        };
+        let (host_instance_id, local_transform) = (pawn.host_instance_id, pawn.local_transform);
+        let entity = self.scene.entities.entry(PAWN_ENTITY_ID).or_insert_with(|| {
+            Entity::new(PAWN_ENTITY_ID, "Pawn", host_instance_id, local_transform, ConvexMesh::cuboid(PAWN_HALF_EXTENTS, PAWN_COLOR))
+        });
+        entity.host_instance_id = host_instance_id;
+        entity.local_transform = local_transform;
+    }
+
+    pub fn is_scene_editor_open(&self) -> bool { self.scene_editor_open }
+
+    pub fn set_scene_editor_open(&mut self, open: bool) { self.scene_editor_open = open; }
+
+    // Center of the main view in physical pixels, where the crosshair is.
+    fn main_view_center(&self) -> Option<Point2<f32>> {
+        let views = build_views(
+            &self.scene, &self.camera, self.size, self.chase_camera.as_ref(),
+            self.split_screen_camera.as_deref(), self.picture_in_picture_camera.as_deref(),
+        );
+        let viewport = views.first()?.viewport;
+        Some(Point2::new(viewport.x + viewport.width * 0.5, viewport.y + viewport.height * 0.5))
+    }
+
+    // Casts a ray through a point on the window, following portals like the renderer does.
+    fn raycast_at(&self, point: Point2<f32>) -> Option<RayHit> {
+        let views = build_views(
+            &self.scene, &self.camera, self.size, self.chase_camera.as_ref(),
+            self.split_screen_camera.as_deref(), self.picture_in_picture_camera.as_deref(),
+        );
+        // Later views are drawn on top.
+        views.iter().rev()
+            .find(|view| {
+                let viewport = view.viewport;
+                point.x >= viewport.x && point.x < viewport.x + viewport.width
+                    && point.y >= viewport.y && point.y < viewport.y + viewport.height
+            })
+            .and_then(|view| {
+                let viewport = view.viewport;
+                let point_in_view = Point2::new(point.x - viewport.x, point.y - viewport.y);
+                let ray = view.camera.screen_to_camera_ray(&point_in_view, viewport.width, viewport.height);
+                raycast_scene(
+                    &self.scene, view.camera_instance_id,
+                    view.camera_local_transform.w_axis.truncate(),
+                    view.camera_local_transform.transform_vector3(ray),
+                    MAX_PORTAL_RECURSION_DEPTH,
+                )
+            })
+    }
+
+    // Casts a ray through the cursor (or the main view's center while the cursor is grabbed)
+    // and remembers the side it hits.
+    fn pick_side(&mut self) {
+        let point = match (self.camera_controller.cursor_grabbed, self.cursor_position) {
+            (true, _) => self.main_view_center(),
+            (false, cursor) => cursor,
+        };
+        let hit = point.and_then(|point| self.raycast_at(point));
+        self.picked_side = hit.and_then(|hit| {
+            let instance = self.scene.instances.get(&hit.instance_id)?;
+            let blueprint = self.scene.blueprints.get(&instance.blueprint_id)?;
+            let handler_type = instance.instance_side_handler_configs.get(&hit.side_index)
+                .unwrap_or(&blueprint.sides.get(hit.side_index)?.default_handler_config)
+                .get_intended_handler_type();
+            Some(PickedSide { hit, instance_name: instance.name.clone(), handler_type })
+        });
+        if let Some(picked) = &self.picked_side {
+            log::info!("Picked {} side {} ({:?})", picked.instance_name, picked.hit.side_index, picked.handler_type);
+        }
+    }
+
+    // What the crosshair is on, if it is close enough to act on.
+    fn interaction_under_crosshair(&self) -> Option<Interaction> {
+        let hit = self.raycast_at(self.main_view_center()?)?;
+        interaction_at(&self.scene, &hit, INTERACTION_REACH)
+    }
+
+    fn interact(&mut self) {
+        if let Some(interaction) = self.interaction_under_crosshair() {
+            if interaction.perform(&mut self.scene) {
+                log::info!("{}", interaction.prompt());
+            }
+        }
+    }
+
+    fn hud_info(&self) -> HudInfo {
+        if !self.settings.show_hud {
+            return HudInfo::default();
+        }
+        HudInfo {
+            crosshair: self.main_view_center().map(|center| [center.x, center.y]),
+            prompt: self.interaction_under_crosshair().map(|interaction| interaction.prompt().to_string()),
+            room_name: self.scene.active_instance().map(|instance| instance.name.clone()),
+        }
+    }
+
+    fn toggle_split_screen(&mut self) {
+        self.split_screen_camera = match self.split_screen_camera {
+            Some(_) => None,
+            None => self.secondary_camera_name(),
+        };
+    }
+
+    fn toggle_picture_in_picture(&mut self) {
+        self.picture_in_picture_camera = match self.picture_in_picture_camera {
+            Some(_) => None,
+            None => self.secondary_camera_name(),
+        };
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let _span = tracing::info_span!("update").entered();
+        self.frame_timings.frame = std::time::Duration::from_secs_f32(dt.max(0.0));
+        let dt = self.recorder.as_ref().map_or(dt, |recorder| recorder.timestep());
+        self.reload_changed_scene_file();
+        self.reload_changed_shaders();
+        let fov_y_deg = if self.camera_controller.zoom_held {
+            self.app_config.fov_y_deg * ZOOM_FOV_FACTOR
+        } else {
+            self.app_config.fov_y_deg
+        };
+        self.camera.zoom_to_fov_y_deg(fov_y_deg);
+        self.camera.update_zoom(dt);
+        update_side_animations(&mut self.scene, dt * self.time_scale);
+        update_liquids(&mut self.scene, dt * self.time_scale);
+        // Pass &mut self.scene to apply_to_transform
+        self.camera_controller.time_scale = self.time_scale;
+        self.camera_controller.apply_to_transform(&mut self.scene, dt);
+        self.sync_pawn_entity();
+        self.automap.update(&self.scene);
+        self.scene.events.dispatch();
+        #[cfg(feature = "audio")]
+        if let Some(audio) = &mut self.audio {
+            audio.update(&self.scene);
+        }
+
+        // Exponential moving average keeps the FPS readout legible.
+        const FRAME_TIME_SMOOTHING: f32 = 0.1;
+        self.smoothed_frame_time = if self.smoothed_frame_time > 0.0 {
+            self.smoothed_frame_time + (dt - self.smoothed_frame_time) * FRAME_TIME_SMOOTHING
+        } else {
+            dt
+        };
+        self.renderer.adjust_render_scale(std::time::Duration::from_secs_f32(self.smoothed_frame_time));
+    }
+
+    // Renders the scene (without UI) off-screen and hands it to the active recorder.
+    fn record_frame(&mut self) {
+        let recorder = match self.recorder.as_mut() {
+            Some(recorder) => recorder,
+            None => return,
+        };
+        let target_matches = self.recording_target.as_ref().is_some_and(|target| {
+            target.width() == self.config.width && target.height() == self.config.height
+        });
+        if !target_matches {
+            self.recording_target = Some(OffscreenTarget::new(
+                &self.device, self.config.width, self.config.height, self.config.format,
+            ));
+        }
+        let target = match self.recording_target.as_ref() {
+            Some(target) => target,
+            None => return,
+        };
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Recording Command Encoder"),
+        });
+        self.renderer.render_scene(
+            &self.device, &self.queue, &mut encoder, target.view(),
+            &self.scene, &self.camera,
+            target.width() as f32, target.height() as f32,
+            self.settings.wgpu_clear_color(),
+            RENDER_LAYER_ALL,
+        );
+        let result = target.read_pixels(&self.device, &self.queue, encoder)
+            .map_err(std::io::Error::other)
+            .and_then(|rgba| recorder.write_frame(target.width(), target.height(), &rgba));
+        if let Err(e) = result {
+            log::error!("Recording failed, stopping: {}", e);
+            self.stop_recording();
+        }
+    }
+
+    pub fn render(&mut self, window: &Window) -> Result<(), wgpu::SurfaceError> {
+        let _span = tracing::info_span!("render").entered();
+        self.renderer.begin_gpu_frame(&self.device, &self.queue);
+        self.record_frame();
+
+        let output_texture = {
+            let _span = tracing::info_span!("acquire_surface").entered();
+            let acquire_start = std::time::Instant::now();
+            let output_texture = self.surface.get_current_texture()?;
+            self.frame_timings.gpu_wait = acquire_start.elapsed();
+            output_texture
+        };
+        let view = output_texture.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Main Command Encoder"),
+        });
+
+        let views = build_views(
+            &self.scene, &self.camera, self.size, self.chase_camera.as_ref(),
+            self.split_screen_camera.as_deref(), self.picture_in_picture_camera.as_deref(),
+        );
+        let clear_color = self.settings.wgpu_clear_color();
+        let frame_stats = if self.app_config.software_rendering {
+            self.software_renderer.settings = self.renderer.settings.clone();
+            let stats = self.software_renderer.render_views(
+                &self.scene, &views,
+                self.size.width as f32, self.size.height as f32,
+                clear_color, RENDER_LAYER_ALL,
+            );
+            let blitter = self.software_blitter.get_or_insert_with(|| SoftwareBlitter::new(&self.device, self.config.format));
+            blitter.blit(&self.device, &self.queue, &mut encoder, &view, &self.software_renderer);
+            stats
+        } else {
+            self.renderer.render_views(
+                &self.device, &self.queue, &mut encoder, &view,
+                &self.scene, &views,
+                self.size.width as f32, self.size.height as f32,
+                clear_color, RENDER_LAYER_ALL,
+            )
+        };
+        self.last_render_stats = frame_stats.into_iter().next().unwrap_or_default();
+        self.frame_timings.traversal = self.last_render_stats.traversal_time;
+
+        let egui_span = tracing::info_span!("egui").entered();
+        let ui_start = std::time::Instant::now();
+        let raw_input = self.egui_state.take_egui_input(window);
+        let debug_stats = DebugStats {
+            fps: if self.smoothed_frame_time > 0.0 { 1.0 / self.smoothed_frame_time } else { 0.0 },
+            frame_time_ms: self.smoothed_frame_time * 1000.0,
+            render: self.last_render_stats,
+        };
+        let mut app_config = self.app_config.clone();
+        let labels = if self.app_config.software_rendering {
+            self.software_renderer.frame_labels().to_vec()
+        } else {
+            self.renderer.frame_labels().to_vec()
+        };
+        let picked = self.picked_side.as_ref().map(|picked| (picked.hit.instance_id, picked.hit.side_index));
+        let hud = self.hud_info();
+        let automap = if self.automap_open { self.automap.layout(&self.scene) } else { AutomapLayout::default() };
+        let mut settings = self.settings.clone();
+        let mut time_scale = self.time_scale;
+        let full_output = self.egui_ctx.run(raw_input, |ctx| {
+            draw_debug_labels(ctx, &labels);
+            draw_hud(ctx, &hud);
+            build_ui(
+                ctx, &debug_stats, &mut self.renderer.settings, &mut app_config, &self.supported_present_modes,
+                self.picked_side.as_ref(), &mut time_scale,
+            );
+            if self.scene_editor_open {
+                build_scene_editor(ctx, &mut self.scene, &mut self.scene_editor_open, picked);
+            }
+            if self.settings_open {
+                build_settings_panel(ctx, &mut settings, &mut self.settings_open);
+            }
+            if self.profiler_open {
+                build_profiler_window(ctx, &self.frame_history, &mut self.profiler_open);
+            }
+            if self.automap_open {
+                build_automap_window(ctx, &automap, &mut self.automap_open);
+            }
+        });
+        if app_config != self.app_config {
+            self.set_app_config(app_config);
+        }
+        if time_scale != self.time_scale {
+            self.set_time_scale(time_scale);
+        }
+        if settings != self.settings {
+            self.apply_settings(settings);
+            self.settings_unsaved = true;
+        }
+        // Saved once a slider is let go rather than on every step of the drag.
+        if self.settings_unsaved && !self.egui_ctx.input(|input| input.pointer.any_down()) {
+            self.save_settings(window);
+        }
+        self.egui_state.handle_platform_output(window, full_output.platform_output);
+        let tris = self.egui_ctx.tessellate(full_output.shapes, self.egui_ctx.pixels_per_point());
+        for (id, image_delta) in &full_output.textures_delta.set {
+            self.egui_renderer.update_texture(&self.device, &self.queue, *id, image_delta);
+        }
+        let screen_descriptor = egui_wgpu::ScreenDescriptor {
+            size_in_pixels: [self.config.width, self.config.height],
+            pixels_per_point: window.scale_factor() as f32,
+        };
+        self.egui_renderer.update_buffers(&self.device, &self.queue, &mut encoder, &tris, &screen_descriptor);
+        { 
+            let mut gui_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("GUI Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view, resolve_target: None,
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store }, 
+                })],
+                depth_stencil_attachment: None, occlusion_query_set: None,
+                timestamp_writes: self.renderer.gpu_timestamp_writes(GpuPass::Ui),
+            });
+            self.egui_renderer.render(&mut gui_render_pass, &tris, &screen_descriptor);
+        }
+        for tex_id in &full_output.textures_delta.free { self.egui_renderer.free_texture(tex_id); }
+        self.frame_timings.ui = ui_start.elapsed();
+        drop(egui_span);
+        self.frame_history.push(self.frame_timings);
+
+        let _span = tracing::info_span!("submit_present").entered();
+        self.renderer.end_gpu_frame(&mut encoder);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        output_texture.present();
+        Ok(())
+    }
+    
+    pub fn handle_window_event(&mut self, event: &WindowEvent, window: &Window) -> bool {
+        if self.egui_state.on_window_event(window, event).consumed { return true; }
+        if self.camera_controller.handle_window_event(event, window) { return true; }
+        match event {
+            WindowEvent::Focused(focused) => { self.is_focused = *focused; false }
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = Some(Point2::new(position.x as f32, position.y as f32));
+                false
+            }
+            WindowEvent::MouseInput { state: ElementState::Pressed, button: MouseButton::Right, .. } => {
+                self.pick_side();
+                true
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. }
+                if key_event.state == ElementState::Pressed
+                    && !key_event.repeat
+                    && key_event.physical_key == PhysicalKey::Code(KeyCode::F2) => {
+                self.toggle_split_screen();
+                true
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. }
+                if key_event.state == ElementState::Pressed
+                    && !key_event.repeat
+                    && key_event.physical_key == PhysicalKey::Code(KeyCode::F3) => {
+                self.toggle_picture_in_picture();
+                true
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. }
+                if key_event.state == ElementState::Pressed
+                    && !key_event.repeat
+                    && key_event.physical_key == PhysicalKey::Code(KeyCode::F1) => {
+                self.settings_open = !self.settings_open;
+                true
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. }
+                if key_event.state == ElementState::Pressed
+                    && !key_event.repeat
+                    && key_event.physical_key == PhysicalKey::Code(KeyCode::F4) => {
+                self.scene_editor_open = !self.scene_editor_open;
+                true
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. }
+                if key_event.state == ElementState::Pressed
+                    && !key_event.repeat
+                    && key_event.physical_key == PhysicalKey::Code(KeyCode::F5) => {
+                let chase_camera = match self.chase_camera {
+                    Some(_) => None,
+                    None => Some(ChaseCamera::default()),
+                };
+                self.set_chase_camera(chase_camera);
+                true
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. }
+                if key_event.state == ElementState::Pressed
+                    && !key_event.repeat
+                    && key_event.physical_key == PhysicalKey::Code(KeyCode::F6) => {
+                self.export_scene_geometry();
+                true
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. }
+                if key_event.state == ElementState::Pressed
+                    && !key_event.repeat
+                    && key_event.physical_key == PhysicalKey::Code(KeyCode::F7) => {
+                self.profiler_open = !self.profiler_open;
+                true
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. }
+                if key_event.state == ElementState::Pressed
+                    && !key_event.repeat
+                    && key_event.physical_key == PhysicalKey::Code(KeyCode::F8) => {
+                self.automap_open = !self.automap_open;
+                true
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. }
+                if key_event.state == ElementState::Pressed
+                    && !key_event.repeat
+                    && key_event.physical_key == PhysicalKey::Code(KeyCode::KeyP) => {
+                self.toggle_pause();
+                true
+            }
+            WindowEvent::KeyboardInput { event: key_event, .. }
+                if key_event.state == ElementState::Pressed
+                    && !key_event.repeat
+                    && key_event.physical_key == PhysicalKey::Code(KeyCode::KeyE) => {
+                self.interact();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent, _window: &Window) {
+        self.camera_controller.handle_device_event(event);
+    }
+}
+
+// Recording can be enabled at startup with ENGINE3_RECORD=<directory> (numbered PNGs) or
+// ENGINE3_RECORD=- (raw RGBA frames on stdout), and ENGINE3_RECORD_FPS (default 60).
+fn recorder_from_env() -> Option<FrameRecorder> {
+    let destination = std::env::var("ENGINE3_RECORD").ok()?;
+    let frames_per_second = std::env::var("ENGINE3_RECORD_FPS").ok()
+        .and_then(|fps| fps.parse::<f32>().ok())
+        .unwrap_or(60.0);
+    let sink = if destination == "-" {
+        FrameSink::Raw(Box::new(std::io::BufWriter::new(std::io::stdout())))
+    } else {
+        FrameSink::PngSequence(destination.into())
+    };
+    match FrameRecorder::new(sink, frames_per_second) {
+        Ok(recorder) => Some(recorder),
+        Err(e) => {
+            log::error!("Could not start recording: {}", e);
+            None
+        }
+    }
+}
+
+// Set ENGINE3_SHADER_HOT_RELOAD to start in shader development mode.
+fn shader_watchers() -> [FileWatcher; 2] {
+    [FileWatcher::new(SCENE_SHADER_PATH), FileWatcher::new(SKY_SHADER_PATH)]
+}
+
+// Main view (left half in split-screen), optional split-screen view, then optional inset. The
+// main view looks over the active camera's shoulder if `chase_camera` is set.
+fn build_views<'a>(
+    scene: &'a Scene,
+    camera: &'a Camera,
+    size: winit::dpi::PhysicalSize<u32>,
+    chase_camera: Option<&ChaseCamera>,
+    split_screen_camera: Option<&str>,
+    picture_in_picture_camera: Option<&str>,
+) -> Vec<SceneView<'a>> {
+    const INSET_FRACTION: f32 = 0.3;
+    const INSET_MARGIN: f32 = 16.0;
+
+    let screen_width = size.width as f32;
+    let screen_height = size.height as f32;
+    let mut views = Vec::with_capacity(3);
+
+    let half_width = (screen_width * 0.5).floor();
+    let split_view = split_screen_camera.and_then(|name| {
+        let viewport = Viewport { x: half_width, y: 0.0, width: screen_width - half_width, height: screen_height };
+        SceneView::from_scene_camera(scene, name, camera, viewport)
+    });
+    let main_viewport = if split_view.is_some() {
+        Viewport::full(half_width, screen_height)
+    } else {
+        Viewport::full(screen_width, screen_height)
+    };
+    views.extend(scene.active_camera().map(|pawn| {
+        let view_pose = chase_camera.map_or_else(|| pawn.clone(), |chase_camera| chase_camera.view_pose(scene, pawn));
+        SceneView {
+            camera,
+            camera_instance_id: view_pose.host_instance_id,
+            camera_local_transform: view_pose.local_transform,
+            viewport: main_viewport,
+        }
+    }));
+    views.extend(split_view);
+
+    if let Some(name) = picture_in_picture_camera {
+        let width = (screen_width * INSET_FRACTION).floor();
+        let height = (screen_height * INSET_FRACTION).floor();
+        let viewport = Viewport { x: screen_width - width - INSET_MARGIN, y: INSET_MARGIN, width, height };
+        if width >= 1.0 && height >= 1.0 && viewport.x >= 0.0 {
+            views.extend(SceneView::from_scene_camera(scene, name, camera, viewport));
+        }
+    }
+    views
+}
+
+fn pick_present_mode(requested: wgpu::PresentMode, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+    if supported.contains(&requested) {
+        requested
+    } else {
+        log::warn!("Present mode {:?} not supported, using Fifo", requested);
+        wgpu::PresentMode::Fifo
+    }
+}
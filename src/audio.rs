@@ -0,0 +1,185 @@
+// src/audio.rs
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use glam::{Vec3, Vec4Swizzles};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Source, SpatialSink};
+use rodio::source::SineWave;
+use crate::engine_lib::audio::{audible_path, listener_acoustics, AcousticSettings, EmitterId, SoundClip, SoundEmitter};
+use crate::engine_lib::scene_types::Scene;
+
+// Half the distance between the listener's ears, in listener space.
+const EAR_OFFSET: f32 = 0.1;
+// Peak level of test tones before the emitter's gain.
+const TONE_AMPLITUDE: f32 = 0.5;
+// Longest echo delay the reverb buffers can hold, in seconds.
+const MAX_REVERB_DELAY: f32 = 0.5;
+// Reverb above this would feed back without decaying.
+const MAX_REVERB_FEEDBACK: f32 = 0.9;
+
+// The listener's reverb, shared with every playing source so that walking into another zone
+// changes sounds that are already playing. Stored as f32 bits.
+#[derive(Default)]
+struct ReverbControl {
+    feedback: AtomicU32,
+    delay: AtomicU32,
+}
+
+impl ReverbControl {
+    fn set(&self, acoustics: &AcousticSettings) {
+        self.feedback.store(acoustics.reverb.clamp(0.0, MAX_REVERB_FEEDBACK).to_bits(), Ordering::Relaxed);
+        self.delay.store(acoustics.reverb_delay.clamp(0.0, MAX_REVERB_DELAY).to_bits(), Ordering::Relaxed);
+    }
+
+    fn feedback(&self) -> f32 {
+        f32::from_bits(self.feedback.load(Ordering::Relaxed))
+    }
+
+    fn delay(&self) -> f32 {
+        f32::from_bits(self.delay.load(Ordering::Relaxed))
+    }
+}
+
+// A feedback echo over `inner`, using the shared reverb parameters.
+struct ZoneReverb<S> {
+    inner: S,
+    control: Arc<ReverbControl>,
+    // Recent output samples, interleaved like `inner`.
+    history: Vec<f32>,
+    position: usize,
+}
+
+impl<S: Source<Item = f32>> ZoneReverb<S> {
+    fn new(inner: S, control: Arc<ReverbControl>) -> Self {
+        let samples_per_second = inner.sample_rate() as usize * inner.channels() as usize;
+        let history = vec![0.0; (samples_per_second as f32 * MAX_REVERB_DELAY) as usize + 1];
+        Self { inner, control, history, position: 0 }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for ZoneReverb<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let dry = self.inner.next()?;
+        let channels = self.inner.channels().max(1) as usize;
+        // Whole frames, so each channel echoes into itself.
+        let frames = (self.control.delay() * self.inner.sample_rate() as f32) as usize;
+        let delay = (frames.max(1) * channels).min(self.history.len() - 1);
+        let len = self.history.len();
+        let wet = dry + self.control.feedback() * self.history[(self.position + len - delay) % len];
+        self.history[self.position] = wet;
+        self.position = (self.position + 1) % len;
+        Some(wet)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for ZoneReverb<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.inner.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.inner.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.inner.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.inner.total_duration()
+    }
+}
+
+// Plays the scene's sound emitters on the default output device. Each frame they are re-aimed
+// along their portal paths, so a sound in the next room comes from the doorway, and given the
+// reverb of the listener's instance.
+pub struct AudioOutput {
+    _stream: OutputStream,
+    handle: OutputStreamHandle,
+    sinks: HashMap<EmitterId, SpatialSink>,
+    reverb: Arc<ReverbControl>,
+}
+
+impl AudioOutput {
+    pub fn new() -> Result<Self, String> {
+        let (stream, handle) = OutputStream::try_default().map_err(|e| e.to_string())?;
+        Ok(Self { _stream: stream, handle, sinks: HashMap::new(), reverb: Arc::default() })
+    }
+
+    // Starts new emitters, stops removed ones and updates every sink's direction and volume,
+    // and the reverb, for the active camera.
+    pub fn update(&mut self, scene: &Scene) {
+        self.reverb.set(&listener_acoustics(scene));
+        self.sinks.retain(|id, sink| {
+            let keep = scene.sound_emitters.contains_key(id);
+            if !keep {
+                sink.stop();
+            }
+            keep
+        });
+        let listener = match scene.active_camera() {
+            Some(camera) => camera,
+            None => return,
+        };
+        let listener_position = listener.local_transform.w_axis.xyz();
+        let to_listener_space = listener.local_transform.inverse();
+
+        for (id, emitter) in &scene.sound_emitters {
+            if !self.sinks.contains_key(id) {
+                match self.start(emitter) {
+                    Ok(sink) => { self.sinks.insert(*id, sink); }
+                    Err(e) => {
+                        log::warn!("Cannot play sound emitter {}: {}", emitter.name, e);
+                        continue;
+                    }
+                }
+            }
+            let sink = &self.sinks[id];
+            match audible_path(scene, listener.host_instance_id, listener_position, emitter) {
+                Some(path) => {
+                    // rodio attenuates by distance itself, so the sound is placed at unit
+                    // distance and only its direction is left to the panner.
+                    let direction = to_listener_space.transform_vector3(path.apparent_position - listener_position)
+                        .try_normalize()
+                        .unwrap_or(Vec3::NEG_Z);
+                    sink.set_emitter_position(direction.to_array());
+                    sink.set_volume(path.gain);
+                }
+                None => sink.set_volume(0.0),
+            }
+        }
+    }
+
+    fn start(&self, emitter: &SoundEmitter) -> Result<SpatialSink, String> {
+        let sink = SpatialSink::try_new(
+            &self.handle,
+            Vec3::NEG_Z.to_array(),
+            [-EAR_OFFSET, 0.0, 0.0],
+            [EAR_OFFSET, 0.0, 0.0],
+        ).map_err(|e| e.to_string())?;
+        sink.set_volume(0.0);
+        match &emitter.clip {
+            SoundClip::File(path) => {
+                let file = BufReader::new(File::open(path).map_err(|e| e.to_string())?);
+                if emitter.looping {
+                    sink.append(self.with_reverb(Decoder::new_looped(file).map_err(|e| e.to_string())?.convert_samples()));
+                } else {
+                    sink.append(self.with_reverb(Decoder::new(file).map_err(|e| e.to_string())?.convert_samples()));
+                }
+            }
+            // Tones never end, looping or not.
+            SoundClip::Tone { frequency } => sink.append(self.with_reverb(SineWave::new(*frequency).amplify(TONE_AMPLITUDE))),
+        }
+        Ok(sink)
+    }
+
+    fn with_reverb<S: Source<Item = f32>>(&self, source: S) -> ZoneReverb<S> {
+        ZoneReverb::new(source, Arc::clone(&self.reverb))
+    }
+}
@@ -0,0 +1,104 @@
+// src/engine_lib/animation.rs
+
+use glam::{Mat4, Quat, Vec3};
+use crate::engine_lib::scene_types::{Scene, HullBlueprint, HullInstance, InstanceId, SideIndex};
+
+// Motion of one side of one instance, e.g. a sliding or swinging door. The side moves rigidly
+// from its blueprint pose (progress 0) to the open pose (progress 1) and is driven towards
+// `target_progress` at `speed` by `update_side_animations`. The side's handler runs on the moved
+// side, so an animated portal carries the view through it along. The hull should stay convex
+// over the whole motion; traversal and collision assume it.
+#[derive(Clone, Debug)]
+pub struct SideAnimation {
+    // Offset of the side at progress 1, in blueprint space.
+    pub open_translation: Vec3,
+    // Rotation of the side at progress 1 about `pivot`.
+    pub open_rotation: Quat,
+    pub pivot: Vec3,
+    pub progress: f32,
+    pub target_progress: f32,
+    // Progress per second.
+    pub speed: f32,
+}
+
+impl SideAnimation {
+    pub fn sliding(open_translation: Vec3, speed: f32) -> Self {
+        Self {
+            open_translation,
+            open_rotation: Quat::IDENTITY,
+            pivot: Vec3::ZERO,
+            progress: 0.0,
+            target_progress: 0.0,
+            speed,
+        }
+    }
+
+    pub fn swinging(pivot: Vec3, axis: Vec3, open_angle: f32, speed: f32) -> Self {
+        Self {
+            open_translation: Vec3::ZERO,
+            open_rotation: Quat::from_axis_angle(axis.normalize_or_zero(), open_angle),
+            pivot,
+            progress: 0.0,
+            target_progress: 0.0,
+            speed,
+        }
+    }
+
+    // Maps the side's blueprint-space vertices to where they are at the current progress.
+    pub fn transform(&self) -> Mat4 {
+        let t = self.progress.clamp(0.0, 1.0);
+        let rotation = Quat::IDENTITY.slerp(self.open_rotation, t);
+        Mat4::from_translation(self.pivot + self.open_translation * t)
+            * Mat4::from_quat(rotation)
+            * Mat4::from_translation(-self.pivot)
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.progress != self.target_progress
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        let step = self.speed.max(0.0) * dt;
+        let remaining = self.target_progress - self.progress;
+        self.progress = if remaining.abs() <= step { self.target_progress } else { self.progress + step * remaining.signum() };
+    }
+}
+
+// Blueprint-space transform applied to a side of `instance`; the identity if it is not animated.
+pub fn side_transform(instance: &HullInstance, side_index: SideIndex) -> Mat4 {
+    instance.side_animations.get(&side_index).map_or(Mat4::IDENTITY, SideAnimation::transform)
+}
+
+// The side's current plane in blueprint space as (inward normal, point on plane).
+pub fn side_plane(blueprint: &HullBlueprint, instance: &HullInstance, side_index: SideIndex) -> Option<(Vec3, Vec3)> {
+    let side = blueprint.sides.get(side_index)?;
+    let point = *blueprint.local_vertices.get(*side.vertex_indices.first()?)?;
+    match instance.side_animations.get(&side_index) {
+        Some(animation) => {
+            let transform = animation.transform();
+            Some((transform.transform_vector3(side.local_normal), transform.transform_point3(point)))
+        }
+        None => Some((side.local_normal, point)),
+    }
+}
+
+// Moves every side animation towards its target.
+pub fn update_side_animations(scene: &mut Scene, dt: f32) {
+    for instance in scene.instances.values_mut() {
+        for animation in instance.side_animations.values_mut() {
+            animation.advance(dt);
+        }
+    }
+}
+
+// Sets where a side animation is heading (0 = blueprint pose, 1 = open). Returns false if the
+// side has no animation.
+pub fn set_side_animation_target(scene: &mut Scene, instance_id: InstanceId, side_index: SideIndex, target_progress: f32) -> bool {
+    match scene.instances.get_mut(&instance_id).and_then(|instance| instance.side_animations.get_mut(&side_index)) {
+        Some(animation) => {
+            animation.target_progress = target_progress.clamp(0.0, 1.0);
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,193 @@
+// src/engine_lib/audio.rs
+
+use std::collections::HashSet;
+use std::path::PathBuf;
+use glam::Vec3;
+use crate::engine_lib::scene_types::{Scene, HullBlueprint, InstanceId, SideIndex};
+use crate::engine_lib::animation::side_transform;
+use crate::engine_lib::placement::side_portal_transform;
+use crate::engine_lib::side_handler::MAX_PORTAL_RECURSION_DEPTH;
+
+pub type EmitterId = u32;
+
+// Sounds closer than this play at full volume; further away they fall off with 1 / distance.
+const REFERENCE_DISTANCE: f32 = 1.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SoundClip {
+    // An audio file, decoded by the output backend.
+    File(PathBuf),
+    // A plain sine tone, handy for testing without assets.
+    Tone { frequency: f32 },
+}
+
+// How a hull instance sounds to a listener inside it, and how much of a sound crossing it is lost.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AcousticSettings {
+    // Strength of the echo added to everything heard inside the instance; 0 is a dry room.
+    pub reverb: f32,
+    // Time between echoes, in seconds.
+    pub reverb_delay: f32,
+    // Fraction of a sound's gain lost each time it leaves this instance through a portal on its
+    // way to the listener.
+    pub occlusion: f32,
+}
+
+impl Default for AcousticSettings {
+    fn default() -> Self {
+        Self { reverb: 0.0, reverb_delay: 0.05, occlusion: 0.0 }
+    }
+}
+
+// The acoustics of the instance holding the active camera. They change as the camera walks
+// through portals.
+pub fn listener_acoustics(scene: &Scene) -> AcousticSettings {
+    scene.active_camera()
+        .and_then(|camera| scene.instances.get(&camera.host_instance_id))
+        .map_or_else(AcousticSettings::default, |instance| instance.acoustics)
+}
+
+// A sound source placed inside a hull instance.
+#[derive(Clone, Debug)]
+pub struct SoundEmitter {
+    pub id: EmitterId,
+    pub name: String,
+    pub host_instance_id: InstanceId,
+    // Position in the host instance's blueprint space.
+    pub local_position: Vec3,
+    pub clip: SoundClip,
+    pub volume: f32,
+    // Beyond this path length the emitter is silent.
+    pub range: f32,
+    pub looping: bool,
+}
+
+// How an emitter is heard from the listener's position.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AudiblePath {
+    // Where the sound seems to come from, in the listener instance's blueprint space: in the
+    // direction of the first portal on the path, as far away as the whole path is long.
+    pub apparent_position: Vec3,
+    pub distance: f32,
+    pub portals_crossed: u32,
+    // Volume after distance falloff and the occlusion of every instance the sound left.
+    pub gain: f32,
+}
+
+// A point on a path search, in the blueprint space of `instance_id`.
+struct PathNode {
+    instance_id: InstanceId,
+    entered_through: Option<SideIndex>,
+    position: Vec3,
+    distance: f32,
+    // First portal center on the path, in the listener instance's space.
+    first_waypoint: Option<Vec3>,
+    portals_crossed: u32,
+    // Share of the emitter's gain left after occlusion along the way.
+    transmission: f32,
+}
+
+fn side_center(scene: &Scene, blueprint: &HullBlueprint, instance_id: InstanceId, side_index: SideIndex) -> Option<Vec3> {
+    let instance = scene.instances.get(&instance_id)?;
+    let side = blueprint.sides.get(side_index)?;
+    let points: Vec<Vec3> = side.vertex_indices.iter()
+        .filter_map(|&v_idx| blueprint.local_vertices.get(v_idx).copied())
+        .collect();
+    if points.is_empty() {
+        return None;
+    }
+    let center = points.iter().copied().sum::<Vec3>() / points.len() as f32;
+    Some(side_transform(instance, side_index).transform_point3(center))
+}
+
+// Finds the shortest way sound can travel from `emitter` to a listener at `listener_position`
+// in `listener_instance_id`, going straight across each hull and through the centers of open
+// portals. None if there is no such path within the emitter's range.
+pub fn audible_path(scene: &Scene, listener_instance_id: InstanceId, listener_position: Vec3, emitter: &SoundEmitter) -> Option<AudiblePath> {
+    let mut frontier = vec![PathNode {
+        instance_id: listener_instance_id,
+        entered_through: None,
+        position: listener_position,
+        distance: 0.0,
+        first_waypoint: None,
+        portals_crossed: 0,
+        transmission: 1.0,
+    }];
+    let mut visited: HashSet<(InstanceId, Option<SideIndex>)> = HashSet::new();
+    let mut best: Option<AudiblePath> = None;
+
+    // Dijkstra over portal sides; hull counts are small enough for a linear minimum search.
+    while let Some(next_index) = frontier.iter().enumerate()
+        .min_by(|a, b| a.1.distance.total_cmp(&b.1.distance))
+        .map(|(index, _)| index)
+    {
+        let node = frontier.swap_remove(next_index);
+        if best.is_some_and(|best| best.distance <= node.distance) || node.distance > emitter.range {
+            break;
+        }
+        if !visited.insert((node.instance_id, node.entered_through)) {
+            continue;
+        }
+
+        if node.instance_id == emitter.host_instance_id {
+            let distance = node.distance + node.position.distance(emitter.local_position);
+            if distance <= emitter.range && best.is_none_or(|best| distance < best.distance) {
+                let toward = node.first_waypoint.unwrap_or(emitter.local_position);
+                let direction = (toward - listener_position).try_normalize().unwrap_or(Vec3::NEG_Z);
+                best = Some(AudiblePath {
+                    apparent_position: listener_position + direction * distance,
+                    distance,
+                    portals_crossed: node.portals_crossed,
+                    gain: emitter.volume * node.transmission * REFERENCE_DISTANCE / distance.max(REFERENCE_DISTANCE),
+                });
+            }
+        }
+        if node.portals_crossed >= MAX_PORTAL_RECURSION_DEPTH {
+            continue;
+        }
+
+        let instance = match scene.instances.get(&node.instance_id) {
+            Some(instance) => instance,
+            None => continue,
+        };
+        let blueprint = match scene.blueprints.get(&instance.blueprint_id) {
+            Some(blueprint) => blueprint,
+            None => continue,
+        };
+        for (side_index, side) in blueprint.sides.iter().enumerate() {
+            if node.entered_through == Some(side_index) {
+                continue;
+            }
+            let config = instance.instance_side_handler_configs.get(&side_index).unwrap_or(&side.default_handler_config);
+            let (target_instance_id, target_portal_id) = match config.passable_portal_target().filter(|_| side.local_portal_id.is_some()) {
+                Some(portal) => portal,
+                None => continue,
+            };
+            let (target_instance, target_blueprint) = match scene.instances.get(&target_instance_id)
+                .and_then(|target| Some((target, scene.blueprints.get(&target.blueprint_id)?)))
+            {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let center = match side_center(scene, blueprint, node.instance_id, side_index) {
+                Some(center) => center,
+                None => continue,
+            };
+            let target_to_current = match side_portal_transform(scene, node.instance_id, side_index, target_instance_id, target_portal_id) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            frontier.push(PathNode {
+                instance_id: target_instance_id,
+                entered_through: target_blueprint.sides.iter().position(|target_side| target_side.local_portal_id == Some(target_portal_id)),
+                position: target_to_current.inverse().transform_point3(center),
+                distance: node.distance + node.position.distance(center),
+                first_waypoint: node.first_waypoint.or(Some(center)),
+                portals_crossed: node.portals_crossed + 1,
+                // The search runs from the listener, so the sound leaves the instance entered here.
+                transmission: node.transmission * (1.0 - target_instance.acoustics.occlusion.clamp(0.0, 1.0)),
+            });
+        }
+    }
+    best
+}
@@ -0,0 +1,190 @@
+// src/engine_lib/automap.rs
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use glam::{Mat4, Vec2, Vec3, Vec4Swizzles};
+use crate::engine_lib::placement::portal_transform;
+use crate::engine_lib::portal_graph::PortalGraph;
+use crate::engine_lib::scene_types::{Scene, InstanceId};
+
+// The instances the camera has been in, which are all the automap shows.
+#[derive(Clone, Debug, Default)]
+pub struct Automap {
+    visited: HashSet<InstanceId>,
+}
+
+impl Automap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns true the first time an instance is visited.
+    pub fn visit(&mut self, instance_id: InstanceId) -> bool {
+        self.visited.insert(instance_id)
+    }
+
+    // Marks the active camera's instance as visited; call once per frame.
+    pub fn update(&mut self, scene: &Scene) {
+        if let Some(camera) = scene.active_camera() {
+            self.visit(camera.host_instance_id);
+        }
+    }
+
+    pub fn is_visited(&self, instance_id: InstanceId) -> bool {
+        self.visited.contains(&instance_id)
+    }
+
+    pub fn clear(&mut self) {
+        self.visited.clear();
+    }
+
+    // Lays the visited instances out around the active camera's instance. See `AutomapLayout`.
+    pub fn layout(&self, scene: &Scene) -> AutomapLayout {
+        let Some(camera) = scene.active_camera() else {
+            return AutomapLayout::default();
+        };
+        let mut layout = self.layout_from(scene, camera.host_instance_id);
+        let position = camera.local_transform.w_axis.xyz();
+        let forward = camera.local_transform.transform_vector3(Vec3::NEG_Z);
+        layout.camera = Some(AutomapMarker {
+            position: top_down(position),
+            forward: top_down(forward).try_normalize().unwrap_or(Vec2::Y),
+        });
+        layout
+    }
+
+    // Lays the visited instances out in the blueprint space of `root_instance_id`, walking out
+    // from it through passable portals and accumulating each portal's transform. Each instance
+    // is placed once, where the walk with the fewest portals first reaches it, so a room seen
+    // through a portal loop or a mirror shows up once rather than over and over. Visited rooms
+    // only reachable through unvisited ones are left out.
+    pub fn layout_from(&self, scene: &Scene, root_instance_id: InstanceId) -> AutomapLayout {
+        let mut layout = AutomapLayout::default();
+        if !self.is_visited(root_instance_id) {
+            return layout;
+        }
+        let graph = PortalGraph::from_scene(scene);
+        let mut placements: HashMap<InstanceId, Mat4> = HashMap::from([(root_instance_id, Mat4::IDENTITY)]);
+        let mut queue = VecDeque::from([root_instance_id]);
+        while let Some(instance_id) = queue.pop_front() {
+            let (Some(instance), Some(&placement)) = (scene.instances.get(&instance_id), placements.get(&instance_id)) else {
+                continue;
+            };
+            let Some(blueprint) = scene.blueprints.get(&instance.blueprint_id) else {
+                continue;
+            };
+            let corners: Vec<Vec2> = blueprint.local_vertices.iter()
+                .map(|&vertex| top_down(placement.transform_point3(vertex)))
+                .collect();
+            let outline = convex_outline(&corners);
+            let center = outline.iter().copied().sum::<Vec2>() / outline.len().max(1) as f32;
+            layout.rooms.push(AutomapRoom { instance_id, name: instance.name.clone(), outline, center });
+
+            for edge in graph.edges_from(instance_id) {
+                let Some(side) = blueprint.sides.get(edge.side_index) else {
+                    continue;
+                };
+                let doorway = side.vertex_indices.iter()
+                    .filter_map(|&index| blueprint.local_vertices.get(index))
+                    .copied()
+                    .sum::<Vec3>() / side.vertex_indices.len().max(1) as f32;
+                let explored = self.is_visited(edge.to_instance_id);
+                layout.links.push(AutomapLink {
+                    from_instance_id: instance_id,
+                    to_instance_id: edge.to_instance_id,
+                    doorway: top_down(placement.transform_point3(doorway)),
+                    explored,
+                });
+                if explored && !placements.contains_key(&edge.to_instance_id) {
+                    let across = portal_transform(scene, instance_id, edge.portal_id, edge.to_instance_id, edge.target_portal_id);
+                    placements.insert(edge.to_instance_id, placement * across);
+                    queue.push_back(edge.to_instance_id);
+                }
+            }
+        }
+        layout
+    }
+}
+
+// A visited instance seen from above: the outline of its hull and the middle of that outline.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutomapRoom {
+    pub instance_id: InstanceId,
+    pub name: String,
+    // Counter-clockwise in map coordinates.
+    pub outline: Vec<Vec2>,
+    pub center: Vec2,
+}
+
+// A passable portal out of a visited room. `explored` if the camera has been through to the
+// other side.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutomapLink {
+    pub from_instance_id: InstanceId,
+    pub to_instance_id: InstanceId,
+    pub doorway: Vec2,
+    pub explored: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutomapMarker {
+    pub position: Vec2,
+    pub forward: Vec2,
+}
+
+// A top-down schematic of the visited rooms in one instance's blueprint space, looking down -Y
+// with map x along +X and map y along +Z. Portals join rooms however the scene is wired, so
+// rooms may overlap where the space is not Euclidean.
+#[derive(Clone, Debug, Default)]
+pub struct AutomapLayout {
+    // The root instance first.
+    pub rooms: Vec<AutomapRoom>,
+    pub links: Vec<AutomapLink>,
+    // The active camera, when laid out with `Automap::layout`.
+    pub camera: Option<AutomapMarker>,
+}
+
+impl AutomapLayout {
+    pub fn room(&self, instance_id: InstanceId) -> Option<&AutomapRoom> {
+        self.rooms.iter().find(|room| room.instance_id == instance_id)
+    }
+
+    // Smallest and largest corner over every room, or None if there are none.
+    pub fn bounds(&self) -> Option<(Vec2, Vec2)> {
+        let mut points = self.rooms.iter().flat_map(|room| room.outline.iter().copied());
+        let first = points.next()?;
+        Some(points.fold((first, first), |(min, max), point| (min.min(point), max.max(point))))
+    }
+}
+
+fn top_down(point: Vec3) -> Vec2 {
+    Vec2::new(point.x, point.z)
+}
+
+// Convex hull of points in the plane, counter-clockwise in map coordinates, by Andrew's
+// monotone chain as in `ConvexPolygon::convex_hull_of`, which is limited to clip-polygon sizes.
+fn convex_outline(points: &[Vec2]) -> Vec<Vec2> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    sorted.dedup_by(|a, b| a.distance_squared(*b) < 1e-8);
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let turns_left = |o: Vec2, a: Vec2, b: Vec2| (a - o).perp_dot(b - o) > 1e-6;
+    let mut hull: Vec<Vec2> = Vec::with_capacity(sorted.len() + 1);
+    // Lower chain left to right, then upper chain back; each ends where the other starts.
+    for &point in &sorted {
+        while hull.len() >= 2 && !turns_left(hull[hull.len() - 2], hull[hull.len() - 1], point) {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    let lower_len = hull.len() + 1;
+    for &point in sorted.iter().rev().skip(1) {
+        while hull.len() >= lower_len && !turns_left(hull[hull.len() - 2], hull[hull.len() - 1], point) {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    hull.pop();
+    hull
+}
@@ -0,0 +1,195 @@
+// src/engine_lib/blueprint_validation.rs
+
+use std::collections::HashMap;
+use std::fmt;
+use glam::Vec3;
+use crate::engine_lib::scene_types::{HullBlueprint, SideIndex};
+
+// Distances below this fraction of the blueprint's size count as zero.
+const RELATIVE_TOLERANCE: f32 = 1e-4;
+
+// Something wrong with a hull blueprint's geometry. The renderer and collision assume none of
+// these, and mostly skip or misdraw the side involved rather than fail.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlueprintError {
+    TooFewVertices { side_index: SideIndex },
+    VertexIndexOutOfBounds { side_index: SideIndex, vertex_index: usize },
+    // The side's outline encloses no area.
+    DegenerateSide { side_index: SideIndex },
+    NonPlanarSide { side_index: SideIndex, vertex_index: usize },
+    // The stored normal points out of the hull instead of into it.
+    OutwardNormal { side_index: SideIndex },
+    // Vertices should wind counter-clockwise seen from outside, i.e. clockwise around the
+    // inward normal; this side winds the other way.
+    WindingAgainstNormal { side_index: SideIndex },
+    // A blueprint vertex lies outside the plane of this side, so the hull is not convex.
+    NotConvex { side_index: SideIndex, vertex_index: usize },
+    // An edge used by only one side: the hull has a hole there.
+    OpenEdge { side_index: SideIndex, edge: (usize, usize) },
+    // An edge shared by more than two sides.
+    NonManifoldEdge { edge: (usize, usize) },
+    // Two sides run along a shared edge in the same direction, so one is wound the wrong way.
+    InconsistentWinding { side_index: SideIndex, other_side_index: SideIndex, edge: (usize, usize) },
+}
+
+impl fmt::Display for BlueprintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlueprintError::TooFewVertices { side_index } =>
+                write!(f, "side {} has fewer than 3 vertices", side_index),
+            BlueprintError::VertexIndexOutOfBounds { side_index, vertex_index } =>
+                write!(f, "side {} references missing vertex {}", side_index, vertex_index),
+            BlueprintError::DegenerateSide { side_index } =>
+                write!(f, "side {} has no area", side_index),
+            BlueprintError::NonPlanarSide { side_index, vertex_index } =>
+                write!(f, "vertex {} of side {} is off the side's plane", vertex_index, side_index),
+            BlueprintError::OutwardNormal { side_index } =>
+                write!(f, "side {} has a normal pointing out of the hull", side_index),
+            BlueprintError::WindingAgainstNormal { side_index } =>
+                write!(f, "side {} winds against its normal", side_index),
+            BlueprintError::NotConvex { side_index, vertex_index } =>
+                write!(f, "vertex {} is outside the plane of side {}; the hull is not convex", vertex_index, side_index),
+            BlueprintError::OpenEdge { side_index, edge } =>
+                write!(f, "edge {}-{} of side {} is not shared with another side", edge.0, edge.1, side_index),
+            BlueprintError::NonManifoldEdge { edge } =>
+                write!(f, "edge {}-{} is shared by more than two sides", edge.0, edge.1),
+            BlueprintError::InconsistentWinding { side_index, other_side_index, edge } =>
+                write!(f, "sides {} and {} both run along edge {}-{} in the same direction", side_index, other_side_index, edge.0, edge.1),
+        }
+    }
+}
+
+impl HullBlueprint {
+    // Checks that the blueprint is a closed convex hull the engine can use: every side indexes
+    // existing vertices, is flat, has an inward normal that agrees with its winding, and no
+    // vertex lies outside any side's plane; and every edge is shared by exactly two sides that
+    // run along it in opposite directions. An edge with another side's vertex partway along it is
+    // matched piece by piece, so split sides still count as closed. Concave side outlines are
+    // allowed, since `split_concave_sides` turns them into convex pieces at load time.
+    pub fn validate(&self) -> Result<(), Vec<BlueprintError>> {
+        let mut errors = Vec::new();
+        let tolerance = RELATIVE_TOLERANCE * self.local_vertices.iter().fold(1.0f32, |size, v| size.max(v.abs().max_element()));
+        let hull_center = if self.local_vertices.is_empty() {
+            Vec3::ZERO
+        } else {
+            self.local_vertices.iter().copied().sum::<Vec3>() / self.local_vertices.len() as f32
+        };
+
+        // Sides whose own geometry is broken are left out of the checks that build on it.
+        let mut usable_sides = Vec::with_capacity(self.sides.len());
+        for (side_index, side) in self.sides.iter().enumerate() {
+            if side.vertex_indices.len() < 3 {
+                errors.push(BlueprintError::TooFewVertices { side_index });
+                continue;
+            }
+            let out_of_bounds: Vec<usize> = side.vertex_indices.iter().copied()
+                .filter(|&vertex_index| vertex_index >= self.local_vertices.len())
+                .collect();
+            if !out_of_bounds.is_empty() {
+                errors.extend(out_of_bounds.into_iter().map(|vertex_index| BlueprintError::VertexIndexOutOfBounds { side_index, vertex_index }));
+                continue;
+            }
+            let points: Vec<Vec3> = side.vertex_indices.iter().map(|&vertex_index| self.local_vertices[vertex_index]).collect();
+            // Newell's method, as for entity faces: points out of the hull for a correctly wound side.
+            let mut area_normal = Vec3::ZERO;
+            for (i, current) in points.iter().enumerate() {
+                area_normal += current.cross(points[(i + 1) % points.len()]);
+            }
+            // Its length is twice the side's area.
+            if area_normal.length() * 0.5 <= tolerance * tolerance {
+                errors.push(BlueprintError::DegenerateSide { side_index });
+                continue;
+            }
+            let winding_normal = area_normal.normalize();
+            let side_center = points.iter().copied().sum::<Vec3>() / points.len() as f32;
+            if let Some(&vertex_index) = side.vertex_indices.iter()
+                .find(|&&vertex_index| winding_normal.dot(self.local_vertices[vertex_index] - side_center).abs() > tolerance)
+            {
+                errors.push(BlueprintError::NonPlanarSide { side_index, vertex_index });
+            }
+
+            let inward_normal = match side.local_normal.try_normalize() {
+                Some(normal) => normal,
+                None => {
+                    errors.push(BlueprintError::OutwardNormal { side_index });
+                    continue;
+                }
+            };
+            if inward_normal.dot(hull_center - side_center) <= 0.0 {
+                errors.push(BlueprintError::OutwardNormal { side_index });
+                continue;
+            }
+            if winding_normal.dot(inward_normal) >= 0.0 {
+                errors.push(BlueprintError::WindingAgainstNormal { side_index });
+            }
+            if let Some(vertex_index) = (0..self.local_vertices.len())
+                .find(|&vertex_index| inward_normal.dot(self.local_vertices[vertex_index] - side_center) < -tolerance)
+            {
+                errors.push(BlueprintError::NotConvex { side_index, vertex_index });
+            }
+            usable_sides.push(side_index);
+        }
+
+        // Directed edges, split wherever another vertex lies on them, and the sides using them.
+        let mut edge_users: HashMap<(usize, usize), Vec<SideIndex>> = HashMap::new();
+        for &side_index in &usable_sides {
+            let vertex_indices = &self.sides[side_index].vertex_indices;
+            for (i, &start) in vertex_indices.iter().enumerate() {
+                let end = vertex_indices[(i + 1) % vertex_indices.len()];
+                for piece in self.edge_pieces(start, end, tolerance) {
+                    edge_users.entry(piece).or_default().push(side_index);
+                }
+            }
+        }
+        let mut edges: Vec<(usize, usize)> = edge_users.keys().copied().collect();
+        edges.sort_unstable();
+        for edge in edges {
+            let (start, end) = edge;
+            let forward = &edge_users[&edge];
+            let backward = edge_users.get(&(end, start)).map_or(&[][..], Vec::as_slice);
+            // Each undirected edge is reported once, from its smaller-first direction if present.
+            if start > end && !backward.is_empty() {
+                continue;
+            }
+            if forward.len() + backward.len() > 2 {
+                errors.push(BlueprintError::NonManifoldEdge { edge });
+            } else if forward.len() == 2 {
+                errors.push(BlueprintError::InconsistentWinding { side_index: forward[0], other_side_index: forward[1], edge });
+            } else if backward.is_empty() {
+                errors.push(BlueprintError::OpenEdge { side_index: forward[0], edge });
+            }
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    // The edge from `start` to `end` cut at every other blueprint vertex lying on it, as
+    // consecutive vertex index pairs.
+    fn edge_pieces(&self, start: usize, end: usize, tolerance: f32) -> Vec<(usize, usize)> {
+        let (a, b) = (self.local_vertices[start], self.local_vertices[end]);
+        let direction = b - a;
+        let length_squared = direction.length_squared();
+        let length = length_squared.sqrt();
+        let mut along: Vec<(f32, usize)> = Vec::new();
+        if length_squared > 0.0 {
+            for (vertex_index, &point) in self.local_vertices.iter().enumerate() {
+                if vertex_index == start || vertex_index == end {
+                    continue;
+                }
+                let t = (point - a).dot(direction) / length_squared;
+                if t * length > tolerance && (1.0 - t) * length > tolerance && (a + direction * t).distance(point) <= tolerance {
+                    along.push((t, vertex_index));
+                }
+            }
+        }
+        along.sort_by(|x, y| x.0.total_cmp(&y.0));
+        let mut pieces = Vec::with_capacity(along.len() + 1);
+        let mut previous = start;
+        for (_, vertex_index) in along {
+            pieces.push((previous, vertex_index));
+            previous = vertex_index;
+        }
+        pieces.push((previous, end));
+        pieces
+    }
+}
@@ -1,64 +1,119 @@
-// src/engine_lib/camera.rs
-
-use glam::{Mat4, Vec3}; // Changed
-use crate::rendering_lib::geometry::Point2;
-
-#[derive(Debug)]
-pub struct Camera {
-    pub fov_y_rad: f32,
-    pub znear: f32,
-    pub zfar: f32,
-}
-
-impl Camera {
-    pub fn new(
-        fov_y_deg: f32,
-        znear: f32,
-        zfar: f32,
-    ) -> Self {
-        Self {
-            fov_y_rad: fov_y_deg.to_radians(),
-            znear,
-            zfar,
-        }
-    }
-
-    // Constructs the view matrix that transforms points from the
-    // camera's host hull's blueprint space into the camera's view space.
-    // `camera_pose_in_host_hull` is the transform from CamLocal -> HostHullBlueprint.
-    // The view matrix is its inverse: HostHullBlueprint -> CamLocal.
-    pub fn get_view_matrix_from_host_hull(&self, camera_pose_in_host_hull: &Mat4) -> Mat4 {
-        camera_pose_in_host_hull.inverse() // glam::Mat4 has inverse()
-    }
-
-    // Projects points that are ALREADY in camera view space to screen space.
-    pub fn project_camera_space_to_screen_direct(
-        &self,
-        p_cam: &Vec3, // Changed from Point3
-        screen_width: f32,
-        screen_height: f32,
-    ) -> Option<Point2> {
-        // glam::Vec3 uses .x, .y, .z directly
-        if p_cam.z > -self.znear + 1e-6 { // Cull if z is greater (less negative / more positive) than -znear
-            return None;
-        }
-        if p_cam.z < -self.zfar { // Far plane check
-            return None;
-        }
-        if -p_cam.z < 1e-6 { // Avoid division by zero if p_cam.z is too close to 0 from negative side
-            return None;
-        }
-
-        let aspect_ratio = screen_width / screen_height;
-        let focal_length_y = 1.0 / (self.fov_y_rad / 2.0).tan();
-        let focal_length_x = focal_length_y / aspect_ratio;
-
-        let ndc_x = (p_cam.x * focal_length_x) / -p_cam.z;
-        let ndc_y = (p_cam.y * focal_length_y) / -p_cam.z;
-
-        let screen_x = (ndc_x + 1.0) * 0.5 * screen_width;
-        let screen_y = (1.0 - ndc_y) * 0.5 * screen_height; // Invert Y for screen space
-
-        Some(Point2::new(screen_x, screen_y))
-    }
+// src/engine_lib/camera.rs
+
+use glam::{Mat4, Vec3}; // Changed
+use crate::rendering_lib::geometry::Point2;
+
+pub const MIN_FOV_Y_DEG: f32 = 5.0;
+pub const MAX_FOV_Y_DEG: f32 = 150.0;
+// How fast `update_zoom` closes the gap to the target field of view, per second. The gap shrinks
+// exponentially, so zooming feels the same at any frame rate.
+const ZOOM_RATE: f32 = 12.0;
+
+#[derive(Debug)]
+pub struct Camera {
+    // Field of view used for projection. The projection helpers read it on every call, so it
+    // can change from frame to frame.
+    pub fov_y_rad: f32,
+    pub znear: f32,
+    pub zfar: f32,
+    // Field of view `update_zoom` eases `fov_y_rad` towards.
+    pub target_fov_y_rad: f32,
+}
+
+impl Camera {
+    pub fn new(
+        fov_y_deg: f32,
+        znear: f32,
+        zfar: f32,
+    ) -> Self {
+        let fov_y_rad = fov_y_deg.clamp(MIN_FOV_Y_DEG, MAX_FOV_Y_DEG).to_radians();
+        Self {
+            fov_y_rad,
+            znear,
+            zfar,
+            target_fov_y_rad: fov_y_rad,
+        }
+    }
+
+    // Changes the field of view at once, cancelling any zoom in progress.
+    pub fn set_fov_y_deg(&mut self, fov_y_deg: f32) {
+        self.fov_y_rad = fov_y_deg.clamp(MIN_FOV_Y_DEG, MAX_FOV_Y_DEG).to_radians();
+        self.target_fov_y_rad = self.fov_y_rad;
+    }
+
+    // Starts a smooth zoom towards `fov_y_deg`; see `update_zoom`.
+    pub fn zoom_to_fov_y_deg(&mut self, fov_y_deg: f32) {
+        self.target_fov_y_rad = fov_y_deg.clamp(MIN_FOV_Y_DEG, MAX_FOV_Y_DEG).to_radians();
+    }
+
+    pub fn is_zooming(&self) -> bool {
+        self.fov_y_rad != self.target_fov_y_rad
+    }
+
+    // Moves the field of view towards the zoom target. Call once per frame, before rendering.
+    pub fn update_zoom(&mut self, dt: f32) {
+        let remaining = self.target_fov_y_rad - self.fov_y_rad;
+        if remaining.abs() < 1e-4 {
+            self.fov_y_rad = self.target_fov_y_rad;
+            return;
+        }
+        self.fov_y_rad += remaining * (1.0 - (-ZOOM_RATE * dt.max(0.0)).exp());
+    }
+
+    // Constructs the view matrix that transforms points from the
+    // camera's host hull's blueprint space into the camera's view space.
+    // `camera_pose_in_host_hull` is the transform from CamLocal -> HostHullBlueprint.
+    // The view matrix is its inverse: HostHullBlueprint -> CamLocal.
+    pub fn get_view_matrix_from_host_hull(&self, camera_pose_in_host_hull: &Mat4) -> Mat4 {
+        camera_pose_in_host_hull.inverse() // glam::Mat4 has inverse()
+    }
+
+    // Projects points that are ALREADY in camera view space to screen space.
+    pub fn project_camera_space_to_screen_direct(
+        &self,
+        p_cam: &Vec3, // Changed from Point3
+        screen_width: f32,
+        screen_height: f32,
+    ) -> Option<Point2> {
+        // glam::Vec3 uses .x, .y, .z directly
+        if p_cam.z > -self.znear + 1e-6 { // Cull if z is greater (less negative / more positive) than -znear
+            return None;
+        }
+        if p_cam.z < -self.zfar { // Far plane check
+            return None;
+        }
+        if -p_cam.z < 1e-6 { // Avoid division by zero if p_cam.z is too close to 0 from negative side
+            return None;
+        }
+
+        let aspect_ratio = screen_width / screen_height;
+        let focal_length_y = 1.0 / (self.fov_y_rad / 2.0).tan();
+        let focal_length_x = focal_length_y / aspect_ratio;
+
+        let ndc_x = (p_cam.x * focal_length_x) / -p_cam.z;
+        let ndc_y = (p_cam.y * focal_length_y) / -p_cam.z;
+
+        let screen_x = (ndc_x + 1.0) * 0.5 * screen_width;
+        let screen_y = (1.0 - ndc_y) * 0.5 * screen_height; // Invert Y for screen space
+
+        Some(Point2::new(screen_x, screen_y))
+    }
+
+    // Inverse of `project_camera_space_to_screen_direct`: returns the camera-space
+    // direction through a screen point, scaled so that its z component is -1.
+    pub fn screen_to_camera_ray(
+        &self,
+        p_screen: &Point2,
+        screen_width: f32,
+        screen_height: f32,
+    ) -> Vec3 {
+        let aspect_ratio = screen_width / screen_height;
+        let focal_length_y = 1.0 / (self.fov_y_rad / 2.0).tan();
+        let focal_length_x = focal_length_y / aspect_ratio;
+
+        let ndc_x = (p_screen.x / screen_width) * 2.0 - 1.0;
+        let ndc_y = 1.0 - (p_screen.y / screen_height) * 2.0;
+
+        Vec3::new(ndc_x / focal_length_x, ndc_y / focal_length_y, -1.0)
+    }
 }
\ No newline at end of file
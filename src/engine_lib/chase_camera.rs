@@ -0,0 +1,58 @@
+// src/engine_lib/chase_camera.rs
+
+use glam::{Vec3, Vec4Swizzles};
+use crate::engine_lib::scene_types::{Scene, SceneCamera};
+use crate::engine_lib::picking::trace_ray;
+use crate::engine_lib::side_handler::MAX_PORTAL_RECURSION_DEPTH;
+
+// A third-person view that follows a pawn from behind. The offset is traced through the scene
+// like a ray, so the view follows portals instead of poking through them and stays clear of
+// walls.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChaseCamera {
+    // How far behind the pawn the view sits, in the pawn's host units.
+    pub distance: f32,
+    // How far above the pawn, along the pawn's up axis.
+    pub height: f32,
+    // Closest the view comes to a side that blocks the offset.
+    pub collision_margin: f32,
+}
+
+impl Default for ChaseCamera {
+    fn default() -> Self {
+        Self { distance: 2.0, height: 0.4, collision_margin: 0.15 }
+    }
+}
+
+impl ChaseCamera {
+    // Where to view `pawn` from: its pose moved back along the offset as far as the scene allows,
+    // in whichever instance that ends up in. The pawn's own pose is returned if the offset
+    // cannot be traced.
+    pub fn view_pose(&self, scene: &Scene, pawn: &SceneCamera) -> SceneCamera {
+        let origin = pawn.local_transform.w_axis.xyz();
+        // The camera looks down its local -Z, so behind is +Z.
+        let offset = pawn.local_transform.transform_vector3(Vec3::new(0.0, self.height, self.distance));
+        let wanted = offset.length();
+        let trace = |max_distance: f32| trace_ray(scene, pawn.host_instance_id, origin, offset, max_distance, MAX_PORTAL_RECURSION_DEPTH);
+
+        let first = match trace(wanted) {
+            Some(first) => first,
+            None => return pawn.clone(),
+        };
+        // Blocked: back off from the side, which can put the view on the near side of a portal.
+        let end = match first.hit {
+            Some(hit) => match trace((hit.distance - self.collision_margin).max(0.0)) {
+                Some(end) => end,
+                None => return pawn.clone(),
+            },
+            None => first,
+        };
+        let mut local_transform = end.start_to_end * pawn.local_transform;
+        local_transform.w_axis = end.local_point.extend(1.0);
+        SceneCamera {
+            host_instance_id: end.instance_id,
+            local_transform,
+            collision_radius: pawn.collision_radius,
+        }
+    }
+}
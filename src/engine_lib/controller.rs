@@ -1,167 +1,376 @@
-// src/engine_lib/controller.rs
-
-use winit::{
-    event::{WindowEvent, DeviceEvent, ElementState},
-    keyboard::{KeyCode, PhysicalKey},
-    window::{Window, CursorGrabMode},
-};
-use glam::{Mat4, Vec3, Vec4Swizzles};
-use crate::engine_lib::scene_types::Scene;
-use crate::engine_lib::scene_logic::update_camera_in_scene;
-
-
-pub struct CameraController {
-    pub camera_pos_delta: Vec3,
-    pub camera_yaw_delta_keyboard: f32,
-    pub camera_pitch_delta_keyboard: f32,
-
-    pub mouse_dx_accum: f32,
-    pub mouse_dy_accum: f32,
-
-    current_yaw: f32,
-    current_pitch: f32,
-
-    pub mouse_sensitivity: f32,
-    pub cursor_grabbed: bool,
-}
-
-impl CameraController {
-    pub fn new(initial_yaw_rad: f32, initial_pitch_rad: f32, initial_grab: bool, sensitivity: f32) -> Self {
-        Self {
-            camera_pos_delta: Vec3::ZERO,
-            camera_yaw_delta_keyboard: 0.0,
-            camera_pitch_delta_keyboard: 0.0,
-            mouse_dx_accum: 0.0,
-            mouse_dy_accum: 0.0,
-            current_yaw: initial_yaw_rad,
-            current_pitch: initial_pitch_rad,
-            mouse_sensitivity: sensitivity,
-            cursor_grabbed: initial_grab,
-        }
-    }
-
-    pub fn handle_window_event(&mut self, event: &WindowEvent, window: &Window) -> bool {
-        match event {
-            WindowEvent::KeyboardInput { event: key_event, .. } => {
-                if key_event.state == ElementState::Pressed && key_event.physical_key == PhysicalKey::Code(KeyCode::Escape) {
-                    self.toggle_cursor_grab(window);
-                    return true;
-                }
-                let pressed = key_event.state == ElementState::Pressed;
-                match key_event.physical_key {
-                    PhysicalKey::Code(KeyCode::KeyW) => { self.camera_pos_delta.z = if pressed { -1.0 } else { 0.0 }; true }
-                    PhysicalKey::Code(KeyCode::KeyS) => { self.camera_pos_delta.z = if pressed { 1.0 } else { 0.0 }; true }
-                    PhysicalKey::Code(KeyCode::KeyA) => { self.camera_pos_delta.x = if pressed { -1.0 } else { 0.0 }; true }
-                    PhysicalKey::Code(KeyCode::KeyD) => { self.camera_pos_delta.x = if pressed { 1.0 } else { 0.0 }; true }
-                    PhysicalKey::Code(KeyCode::Space) => { self.camera_pos_delta.y = if pressed { 1.0 } else { 0.0 }; true }
-                    PhysicalKey::Code(KeyCode::ShiftLeft) | PhysicalKey::Code(KeyCode::ControlLeft) => {
-                        self.camera_pos_delta.y = if pressed { -1.0 } else { 0.0 }; true
-                    }
-                    PhysicalKey::Code(KeyCode::ArrowLeft) => { self.camera_yaw_delta_keyboard = if pressed { 1.0 } else { 0.0 }; true }
-                    PhysicalKey::Code(KeyCode::ArrowRight) => { self.camera_yaw_delta_keyboard = if pressed { -1.0 } else { 0.0 }; true }
-                    PhysicalKey::Code(KeyCode::ArrowUp) => { self.camera_pitch_delta_keyboard = if pressed { 1.0 } else { 0.0 }; true }
-                    PhysicalKey::Code(KeyCode::ArrowDown) => { self.camera_pitch_delta_keyboard = if pressed { -1.0 } else { 0.0 }; true }
-                    _ => false,
-                }
-            }
-            WindowEvent::MouseInput { state, button, .. } => {
-                if !self.cursor_grabbed && *state == ElementState::Pressed && *button == winit::event::MouseButton::Left {
-                    self.grab_cursor(window, true);
-                    return true;
-                }
-                false
-            }
-            WindowEvent::Focused(focused) => {
-                if !*focused && self.cursor_grabbed {
-                    self.grab_cursor(window, false);
-                }
-                false
-            }
-            _ => false,
-        }
-    }
-
-    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
-        if !self.cursor_grabbed {
-            self.mouse_dx_accum = 0.0;
-            self.mouse_dy_accum = 0.0;
-            return;
-        }
-        match event {
-            DeviceEvent::MouseMotion { delta: (dx, dy) } => {
-                self.mouse_dx_accum += *dx as f32;
-                self.mouse_dy_accum += *dy as f32;
-            }
-            _ => {}
-        }
-    }
-
-    pub fn toggle_cursor_grab(&mut self, window: &Window) {
-        self.grab_cursor(window, !self.cursor_grabbed);
-    }
-
-    fn grab_cursor(&mut self, window: &Window, grab: bool) {
-        if grab {
-            if !self.cursor_grabbed {
-                if window.set_cursor_grab(CursorGrabMode::Confined)
-                    .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
-                    .is_ok() {
-                    window.set_cursor_visible(false);
-                    self.cursor_grabbed = true;
-                } else {eprintln!("Could not grab cursor");}
-            }
-        } else {
-            if self.cursor_grabbed {
-                if window.set_cursor_grab(CursorGrabMode::None).is_ok() {
-                    window.set_cursor_visible(true);
-                    self.cursor_grabbed = false;
-                    self.mouse_dx_accum = 0.0;
-                    self.mouse_dy_accum = 0.0;
-                } else {eprintln!("Could not ungrab cursor");}
-            }
-        }
-    }
-
-    pub fn apply_to_transform(
-        &mut self,
-        scene: &mut Scene, // Changed from &mut Mat4
-        dt: f32
-    ) {
-        let move_speed = 3.0 * dt;
-        let rot_speed_keyboard = 1.5 * dt;
-
-        self.current_yaw -= self.mouse_dx_accum * self.mouse_sensitivity;
-        self.current_yaw -= self.camera_yaw_delta_keyboard * rot_speed_keyboard;
-
-        self.current_pitch -= self.mouse_dy_accum * self.mouse_sensitivity;
-        self.current_pitch += self.camera_pitch_delta_keyboard * rot_speed_keyboard;
-
-        self.mouse_dx_accum = 0.0;
-        self.mouse_dy_accum = 0.0;
-
-        let pitch_limit = std::f32::consts::FRAC_PI_2 - 0.01;
-        self.current_pitch = self.current_pitch.clamp(-pitch_limit, pitch_limit);
-
-        let rotation_y = Mat4::from_rotation_y(self.current_yaw);
-        let rotation_x = Mat4::from_rotation_x(self.current_pitch);
-        let new_rotation_matrix = rotation_y * rotation_x;
-
-        let local_move_delta = Vec3::new(
-            self.camera_pos_delta.x * move_speed,
-            self.camera_pos_delta.y * move_speed,
-            self.camera_pos_delta.z * move_speed,
-        );
-        
-        let move_delta_in_host_space = new_rotation_matrix.transform_vector3(local_move_delta);
-        
-        let current_local_position = scene.active_camera_local_transform.w_axis.xyz();
-        let potential_new_local_pos = current_local_position + move_delta_in_host_space;
-
-        update_camera_in_scene(
-            scene,
-            potential_new_local_pos,
-            new_rotation_matrix,
-            dt
-        );
-    }
-}
\ No newline at end of file
+// src/engine_lib/controller.rs
+
+#[cfg(feature = "render")]
+use winit::{
+    event::{WindowEvent, DeviceEvent, ElementState},
+    keyboard::{KeyCode, PhysicalKey},
+    window::{Window, CursorGrabMode},
+};
+use glam::{EulerRot, Mat3, Mat4, Quat, Vec3, Vec4Swizzles};
+use crate::engine_lib::scene_types::{Scene, HullBlueprint, HullInstance, InstanceId, BoundaryCheckResult};
+use crate::engine_lib::scene_logic::{update_camera_in_scene, check_camera_hull_boundary};
+use crate::engine_lib::animation::side_plane;
+use crate::engine_lib::placement::{is_mirroring, side_portal_transform};
+use crate::engine_lib::liquid::submerged_liquid;
+
+// Walking mode. Up is against the host instance's gravity; see `HullInstance::up`.
+const JUMP_SPEED: f32 = 4.0;
+const DEFAULT_EYE_HEIGHT: f32 = 1.2;
+const DEFAULT_STEP_HEIGHT: f32 = 0.35;
+const DEFAULT_MOVE_SPEED: f32 = 3.0;
+// Fraction of the vertical speed a liquid takes away per second.
+const LIQUID_DRAG: f32 = 2.0;
+// Sides whose inward normal points at least this much upwards count as floor.
+const FLOOR_MIN_NORMAL_Y: f32 = 0.7;
+
+pub struct CameraController {
+    pub camera_pos_delta: Vec3,
+    pub camera_yaw_delta_keyboard: f32,
+    pub camera_pitch_delta_keyboard: f32,
+
+    pub mouse_dx_accum: f32,
+    pub mouse_dy_accum: f32,
+
+    current_yaw: f32,
+    current_pitch: f32,
+    // Set after crossing a mirror portal (one whose transform flips handedness): the camera then
+    // looks around a world reflected in the host's X, so the pose is that reflection followed by
+    // yaw and pitch, and turning or strafing right still goes right on screen.
+    mirrored: bool,
+    // Turns +Y onto the host instance's up. Yaw turns about that up and pitch tilts away from
+    // it, so the view stays level in hulls whose gravity points elsewhere.
+    up_frame: Mat3,
+
+    pub mouse_sensitivity: f32,
+    // Moving the mouse forward looks down instead of up.
+    pub invert_y: bool,
+    // Units per second, flying or walking.
+    pub move_speed: f32,
+    pub cursor_grabbed: bool,
+
+    // Walk on floors under gravity instead of flying; Space jumps, or swims up in a liquid. Toggled with G.
+    pub walking: bool,
+    // Height of the camera above the floor it stands on.
+    pub eye_height: f32,
+    // Highest floor discontinuity, e.g. between hulls joined by a portal, that walking climbs
+    // on its own. Higher ones block like a wall.
+    pub step_height: f32,
+    vertical_velocity: f32,
+    grounded: bool,
+    // Scales time for gravity and jumps while walking, so they pause and slow down with the rest
+    // of the simulation. Moving and looking around always run in real time.
+    pub time_scale: f32,
+    // True while the zoom key (Z) is held.
+    pub zoom_held: bool,
+}
+
+impl CameraController {
+    pub fn new(initial_yaw_rad: f32, initial_pitch_rad: f32, initial_grab: bool, sensitivity: f32) -> Self {
+        Self {
+            camera_pos_delta: Vec3::ZERO,
+            camera_yaw_delta_keyboard: 0.0,
+            camera_pitch_delta_keyboard: 0.0,
+            mouse_dx_accum: 0.0,
+            mouse_dy_accum: 0.0,
+            current_yaw: initial_yaw_rad,
+            current_pitch: initial_pitch_rad,
+            mirrored: false,
+            up_frame: Mat3::IDENTITY,
+            mouse_sensitivity: sensitivity,
+            invert_y: false,
+            move_speed: DEFAULT_MOVE_SPEED,
+            cursor_grabbed: initial_grab,
+            walking: false,
+            eye_height: DEFAULT_EYE_HEIGHT,
+            step_height: DEFAULT_STEP_HEIGHT,
+            vertical_velocity: 0.0,
+            grounded: false,
+            time_scale: 1.0,
+            zoom_held: false,
+        }
+    }
+
+    // Whether the camera stood on a floor after the last update (walking mode only).
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    pub fn toggle_walking(&mut self) {
+        self.walking = !self.walking;
+        self.vertical_velocity = 0.0;
+        self.grounded = false;
+    }
+
+    // Picks up yaw and pitch from a camera pose, e.g. after switching to another camera,
+    // so the next update doesn't snap the view back to the previous camera's orientation.
+    pub fn sync_orientation_from(&mut self, local_transform: &Mat4) {
+        self.sync_look_from(local_transform);
+        self.vertical_velocity = 0.0;
+    }
+
+    // True while the camera's pose flips handedness; see `mirrored`.
+    pub fn is_mirrored(&self) -> bool {
+        self.mirrored
+    }
+
+    // Yaw, pitch and handedness of a camera pose, relative to `up_frame`. Roll is dropped.
+    fn sync_look_from(&mut self, local_transform: &Mat4) {
+        self.mirrored = is_mirroring(local_transform);
+        let basis = Mat3::from_mat4(*local_transform);
+        let basis = Mat3::from_cols(basis.x_axis.normalize_or_zero(), basis.y_axis.normalize_or_zero(), basis.z_axis.normalize_or_zero());
+        let rotation = Quat::from_mat3(&(self.mirror() * self.up_frame.transpose() * basis));
+        let (yaw, pitch, _roll) = rotation.to_euler(EulerRot::YXZ);
+        self.current_yaw = yaw;
+        self.current_pitch = pitch;
+    }
+
+    // The reflection the camera's yaw and pitch are applied after.
+    fn mirror(&self) -> Mat3 {
+        if self.mirrored { Mat3::from_diagonal(Vec3::new(-1.0, 1.0, 1.0)) } else { Mat3::IDENTITY }
+    }
+
+    pub fn apply_to_transform(
+        &mut self,
+        scene: &mut Scene, // Changed from &mut Mat4
+        dt: f32
+    ) {
+        let (current_local_position, host_instance_id, up) = match scene.active_camera() {
+            Some(camera) => (camera.local_transform.w_axis.xyz(), camera.host_instance_id, host_up(scene, camera.host_instance_id)),
+            None => return,
+        };
+        // The host's gravity changed, or another camera was switched to: yaw and pitch are
+        // taken again about the new up.
+        if self.rebase_up_frame(up) {
+            let local_transform = scene.active_camera().map_or(Mat4::IDENTITY, |camera| camera.local_transform);
+            self.sync_look_from(&local_transform);
+        }
+        // A liquid slows moving, flying or walking, but not looking around. Its gravity and drag
+        // only act on walking cameras, below.
+        let liquid = submerged_liquid(scene).copied();
+        let move_speed = self.move_speed * liquid.map_or(1.0, |liquid| liquid.movement_scale) * dt;
+        let rot_speed_keyboard = 1.5 * dt;
+
+        self.current_yaw -= self.mouse_dx_accum * self.mouse_sensitivity;
+        self.current_yaw -= self.camera_yaw_delta_keyboard * rot_speed_keyboard;
+
+        let mouse_pitch_sign = if self.invert_y { -1.0 } else { 1.0 };
+        self.current_pitch -= mouse_pitch_sign * self.mouse_dy_accum * self.mouse_sensitivity;
+        self.current_pitch += self.camera_pitch_delta_keyboard * rot_speed_keyboard;
+
+        self.mouse_dx_accum = 0.0;
+        self.mouse_dy_accum = 0.0;
+
+        let pitch_limit = std::f32::consts::FRAC_PI_2 - 0.01;
+        self.current_pitch = self.current_pitch.clamp(-pitch_limit, pitch_limit);
+
+        let frame = Mat4::from_mat3(self.up_frame * self.mirror());
+        let rotation_y = frame * Mat4::from_rotation_y(self.current_yaw);
+        let rotation_x = Mat4::from_rotation_x(self.current_pitch);
+        let new_rotation_matrix = rotation_y * rotation_x;
+
+        let local_move_delta = Vec3::new(
+            self.camera_pos_delta.x * move_speed,
+            self.camera_pos_delta.y * move_speed,
+            self.camera_pos_delta.z * move_speed,
+        );
+
+
+        let potential_new_local_pos = if self.walking {
+            // Pitch doesn't tilt walking, and Space jumps instead of flying up, or swims up in a
+            // liquid.
+            let physics_dt = dt * self.time_scale.max(0.0);
+            let gravity = scene.instances.get(&host_instance_id).map_or(0.0, |instance| instance.gravity.length());
+            match liquid {
+                Some(liquid) => {
+                    if self.camera_pos_delta.y > 0.0 {
+                        self.vertical_velocity = JUMP_SPEED * liquid.movement_scale;
+                    }
+                    self.vertical_velocity -= gravity * liquid.gravity_scale * physics_dt;
+                    self.vertical_velocity *= (1.0 - LIQUID_DRAG * physics_dt).max(0.0);
+                }
+                None => {
+                    if self.grounded && self.camera_pos_delta.y > 0.0 {
+                        self.vertical_velocity = JUMP_SPEED;
+                    }
+                    self.vertical_velocity -= gravity * physics_dt;
+                }
+            }
+            let horizontal_delta = rotation_y.transform_vector3(Vec3::new(local_move_delta.x, 0.0, local_move_delta.z));
+            let vertical_delta = up * self.vertical_velocity * physics_dt;
+
+            let mut position = current_local_position + horizontal_delta + vertical_delta;
+            let mut lift = self.floor_lift(scene, position, up);
+            // Whatever the fall itself sinks the feet in is not a step.
+            let fall_depth = (-self.vertical_velocity * physics_dt).max(0.0);
+            if lift.is_some_and(|lift| lift.length() > self.step_height + fall_depth) {
+                // A ledge too high to step onto: only the vertical part of the move goes ahead.
+                position = current_local_position + vertical_delta;
+                lift = self.floor_lift(scene, position, up);
+            }
+            self.grounded = lift.is_some();
+            if self.grounded {
+                self.vertical_velocity = self.vertical_velocity.max(0.0);
+            }
+            position + lift.unwrap_or(Vec3::ZERO)
+        } else {
+            current_local_position + new_rotation_matrix.transform_vector3(local_move_delta)
+        };
+
+        update_camera_in_scene(
+            scene,
+            potential_new_local_pos,
+            new_rotation_matrix,
+            dt
+        );
+        // A portal that rotates or mirrors the camera leaves yaw and pitch (and handedness)
+        // describing the old hull, and the new hull may have another up; pick them up from the
+        // pose it was carried into.
+        if let Some(camera) = scene.active_camera().filter(|camera| camera.host_instance_id != host_instance_id) {
+            let local_transform = camera.local_transform;
+            self.rebase_up_frame(host_up(scene, camera.host_instance_id));
+            self.sync_look_from(&local_transform);
+        }
+    }
+
+    // Points `up_frame` at `up`. Returns true if it moved.
+    fn rebase_up_frame(&mut self, up: Vec3) -> bool {
+        let up_frame = Mat3::from_quat(Quat::from_rotation_arc(Vec3::Y, up));
+        if up_frame.abs_diff_eq(self.up_frame, 1e-6) {
+            return false;
+        }
+        self.up_frame = up_frame;
+        true
+    }
+
+    // How far `position` (host blueprint space) has to rise along `up` for the feet, `eye_height`
+    // below it, to rest on the floor they sink into, or None if they are not in a floor. The
+    // floor is looked up in the hull the move ends in, so a step up into the next hull is seen
+    // before the camera goes through the portal.
+    fn floor_lift(&self, scene: &Scene, position: Vec3, up: Vec3) -> Option<Vec3> {
+        let host_instance_id = scene.active_camera()?.host_instance_id;
+        let (host_instance, host_blueprint) = instance_and_blueprint(scene, host_instance_id)?;
+        let (instance_id, host_to_instance) = match check_camera_hull_boundary(&position, host_blueprint, host_instance, 0.0, &scene.held_keys) {
+            BoundaryCheckResult::Traverse { crossed_side_index, target_instance_id, target_portal_id } => {
+                let target_to_host = side_portal_transform(scene, host_instance_id, crossed_side_index, target_instance_id, target_portal_id)?;
+                (target_instance_id, target_to_host.inverse())
+            }
+            _ => (host_instance_id, Mat4::IDENTITY),
+        };
+        let (instance, blueprint) = instance_and_blueprint(scene, instance_id)?;
+
+        let up = host_to_instance.transform_vector3(up).normalize_or_zero();
+        let feet = host_to_instance.transform_point3(position) - up * self.eye_height;
+        let side_index = match check_camera_hull_boundary(&feet, blueprint, instance, 0.0, &scene.held_keys) {
+            BoundaryCheckResult::Collision { collided_side_index, .. } => collided_side_index,
+            // Open floors (portals) let the camera fall through.
+            _ => return None,
+        };
+        let (normal, point_on_plane) = side_plane(blueprint, instance, side_index)
+            .map(|(normal, point)| (normal.normalize_or_zero(), point))?;
+        let normal_up = normal.dot(up);
+        if normal_up < FLOOR_MIN_NORMAL_Y {
+            return None;
+        }
+        // Straight up until the feet are back on the plane.
+        let depth_below_floor = normal.dot(point_on_plane - feet);
+        Some(host_to_instance.inverse().transform_vector3(up * depth_below_floor / normal_up))
+    }
+}
+
+// Window input, mapped onto the movement state above.
+#[cfg(feature = "render")]
+impl CameraController {
+    pub fn handle_window_event(&mut self, event: &WindowEvent, window: &Window) -> bool {
+        match event {
+            WindowEvent::KeyboardInput { event: key_event, .. } => {
+                if key_event.state == ElementState::Pressed && key_event.physical_key == PhysicalKey::Code(KeyCode::Escape) {
+                    self.toggle_cursor_grab(window);
+                    return true;
+                }
+                if key_event.state == ElementState::Pressed && !key_event.repeat && key_event.physical_key == PhysicalKey::Code(KeyCode::KeyG) {
+                    self.toggle_walking();
+                    return true;
+                }
+                let pressed = key_event.state == ElementState::Pressed;
+                match key_event.physical_key {
+                    PhysicalKey::Code(KeyCode::KeyW) => { self.camera_pos_delta.z = if pressed { -1.0 } else { 0.0 }; true }
+                    PhysicalKey::Code(KeyCode::KeyS) => { self.camera_pos_delta.z = if pressed { 1.0 } else { 0.0 }; true }
+                    PhysicalKey::Code(KeyCode::KeyA) => { self.camera_pos_delta.x = if pressed { -1.0 } else { 0.0 }; true }
+                    PhysicalKey::Code(KeyCode::KeyD) => { self.camera_pos_delta.x = if pressed { 1.0 } else { 0.0 }; true }
+                    PhysicalKey::Code(KeyCode::Space) => { self.camera_pos_delta.y = if pressed { 1.0 } else { 0.0 }; true }
+                    PhysicalKey::Code(KeyCode::ShiftLeft) | PhysicalKey::Code(KeyCode::ControlLeft) => {
+                        self.camera_pos_delta.y = if pressed { -1.0 } else { 0.0 }; true
+                    }
+                    PhysicalKey::Code(KeyCode::ArrowLeft) => { self.camera_yaw_delta_keyboard = if pressed { 1.0 } else { 0.0 }; true }
+                    PhysicalKey::Code(KeyCode::ArrowRight) => { self.camera_yaw_delta_keyboard = if pressed { -1.0 } else { 0.0 }; true }
+                    PhysicalKey::Code(KeyCode::ArrowUp) => { self.camera_pitch_delta_keyboard = if pressed { 1.0 } else { 0.0 }; true }
+                    PhysicalKey::Code(KeyCode::ArrowDown) => { self.camera_pitch_delta_keyboard = if pressed { -1.0 } else { 0.0 }; true }
+                    PhysicalKey::Code(KeyCode::KeyZ) => { self.zoom_held = pressed; true }
+                    _ => false,
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                if !self.cursor_grabbed && *state == ElementState::Pressed && *button == winit::event::MouseButton::Left {
+                    self.grab_cursor(window, true);
+                    return true;
+                }
+                false
+            }
+            WindowEvent::Focused(focused) => {
+                if !*focused && self.cursor_grabbed {
+                    self.grab_cursor(window, false);
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    pub fn handle_device_event(&mut self, event: &DeviceEvent) {
+        if !self.cursor_grabbed {
+            self.mouse_dx_accum = 0.0;
+            self.mouse_dy_accum = 0.0;
+            return;
+        }
+        if let DeviceEvent::MouseMotion { delta: (dx, dy) } = event {
+            self.mouse_dx_accum += *dx as f32;
+            self.mouse_dy_accum += *dy as f32;
+        }
+    }
+
+    pub fn toggle_cursor_grab(&mut self, window: &Window) {
+        self.grab_cursor(window, !self.cursor_grabbed);
+    }
+
+    fn grab_cursor(&mut self, window: &Window, grab: bool) {
+        if grab {
+            if !self.cursor_grabbed {
+                if window.set_cursor_grab(CursorGrabMode::Confined)
+                    .or_else(|_e| window.set_cursor_grab(CursorGrabMode::Locked))
+                    .is_ok() {
+                    window.set_cursor_visible(false);
+                    self.cursor_grabbed = true;
+                } else {eprintln!("Could not grab cursor");}
+            }
+        } else {
+            if self.cursor_grabbed {
+                if window.set_cursor_grab(CursorGrabMode::None).is_ok() {
+                    window.set_cursor_visible(true);
+                    self.cursor_grabbed = false;
+                    self.mouse_dx_accum = 0.0;
+                    self.mouse_dy_accum = 0.0;
+                } else {eprintln!("Could not ungrab cursor");}
+            }
+        }
+    }
+}
+
+// Up in the blueprint space of `instance_id`; +Y if there is no such instance.
+fn host_up(scene: &Scene, instance_id: InstanceId) -> Vec3 {
+    scene.instances.get(&instance_id).map_or(Vec3::Y, HullInstance::up)
+}
+
+fn instance_and_blueprint(scene: &Scene, instance_id: InstanceId) -> Option<(&HullInstance, &HullBlueprint)> {
+    let instance = scene.instances.get(&instance_id)?;
+    Some((instance, scene.blueprints.get(&instance.blueprint_id)?))
+}
@@ -0,0 +1,304 @@
+// src/engine_lib/convex_cells.rs
+
+use std::collections::{HashMap, HashSet};
+use glam::{DVec3, Mat4, Vec3};
+use crate::engine_lib::audio::AcousticSettings;
+use crate::engine_lib::events::EventBus;
+use crate::engine_lib::scene_types::{
+    Scene, SceneCamera, DEFAULT_CAMERA_NAME, HullBlueprint, BlueprintSide, HullInstance, HandlerConfig,
+    PortalConnectionInfo, BlueprintId, InstanceId, PortalId, RENDER_LAYER_DEFAULT, RENDER_LAYER_ALL, DEFAULT_GRAVITY,
+};
+use crate::rendering_lib::geometry::clip_polygon_3d_by_plane;
+
+// Shared face regions smaller than this, in squared engine units, are treated as touching edges.
+const MIN_REGION_AREA: f32 = 1e-6;
+// Cosine tolerance for two faces counting as back to back.
+const OPPOSITE_NORMAL_TOLERANCE: f32 = 1e-4;
+
+pub type Vec3Polygon = Vec<Vec3>;
+
+// A face of a convex cell in world space, wound counter-clockwise around `outward`. Whatever
+// part of it no neighbouring cell touches gets `config`.
+#[derive(Clone, Debug)]
+pub struct CellFace {
+    pub outward: Vec3,
+    pub polygon: Vec3Polygon,
+    pub config: HandlerConfig,
+}
+
+// A convex volume of open space that becomes one hull.
+#[derive(Clone, Debug)]
+pub struct ConvexCell {
+    pub name: String,
+    pub faces: Vec<CellFace>,
+}
+
+// The hulls built from a set of cells; blueprint and instance ids are the cells' indices.
+#[derive(Clone, Debug, Default)]
+pub struct CellHulls {
+    pub blueprints: HashMap<BlueprintId, HullBlueprint>,
+    pub instances: HashMap<InstanceId, HullInstance>,
+}
+
+// The corners of the convex volume inside all `planes` (outward normal, distance: points with
+// `normal.dot(p) > distance` are outside), and for each plane that bounds it, its face as
+// corner indices wound counter-clockwise seen from outside. Planes that only touch the volume
+// in an edge or corner get no face.
+pub fn polyhedron_from_planes(planes: &[(DVec3, f64)], epsilon: f64) -> (Vec<DVec3>, Vec<(usize, Vec<usize>)>) {
+    let contains = |point: DVec3| planes.iter().all(|&(normal, distance)| normal.dot(point) - distance <= epsilon);
+    let mut corners: Vec<DVec3> = Vec::new();
+    for (i, &(a, a_distance)) in planes.iter().enumerate() {
+        for (j, &(b, b_distance)) in planes.iter().enumerate().skip(i + 1) {
+            for &(c, c_distance) in planes.iter().skip(j + 1) {
+                let denominator = a.dot(b.cross(c));
+                if denominator.abs() < 1e-9 {
+                    continue;
+                }
+                let point = (a_distance * b.cross(c) + b_distance * c.cross(a) + c_distance * a.cross(b)) / denominator;
+                if contains(point) && !corners.iter().any(|corner| corner.distance(point) <= epsilon) {
+                    corners.push(point);
+                }
+            }
+        }
+    }
+    let mut faces = Vec::new();
+    for (plane_index, &(normal, distance)) in planes.iter().enumerate() {
+        let mut on_plane: Vec<usize> = (0..corners.len())
+            .filter(|&i| (normal.dot(corners[i]) - distance).abs() <= epsilon)
+            .collect();
+        if on_plane.len() < 3 {
+            continue;
+        }
+        let center = on_plane.iter().map(|&i| corners[i]).sum::<DVec3>() / on_plane.len() as f64;
+        let u = (corners[on_plane[0]] - center).normalize();
+        let v = normal.cross(u);
+        let angle = |i: usize| {
+            let offset = corners[i] - center;
+            offset.dot(v).atan2(offset.dot(u))
+        };
+        on_plane.sort_by(|&a, &b| angle(a).total_cmp(&angle(b)));
+        faces.push((plane_index, on_plane));
+    }
+    (corners, faces)
+}
+
+fn polygon_area(polygon: &[Vec3]) -> f32 {
+    let mut normal = Vec3::ZERO;
+    for (i, current) in polygon.iter().enumerate() {
+        normal += current.cross(polygon[(i + 1) % polygon.len()]);
+    }
+    normal.length() * 0.5
+}
+
+// Planes through each edge of `polygon`, facing its inside, for clipping coplanar polygons.
+fn inward_edge_planes(polygon: &[Vec3], normal: Vec3) -> Vec<(Vec3, Vec3)> {
+    let center = polygon.iter().copied().sum::<Vec3>() / polygon.len() as f32;
+    (0..polygon.len())
+        .filter_map(|i| {
+            let (start, end) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            let edge_normal = normal.cross(end - start).try_normalize()?;
+            let edge_normal = if edge_normal.dot(center - start) < 0.0 { -edge_normal } else { edge_normal };
+            Some((edge_normal, start))
+        })
+        .collect()
+}
+
+fn clip_to(polygon: &[Vec3], planes: &[(Vec3, Vec3)]) -> Vec<Vec3> {
+    planes.iter().fold(polygon.to_vec(), |clipped, plane| clip_polygon_3d_by_plane(&clipped, plane))
+}
+
+// `polygon` minus the convex `hole` lying in the same plane, as convex pieces.
+fn subtract(polygon: &[Vec3], hole_planes: &[(Vec3, Vec3)]) -> Vec<Vec3Polygon> {
+    let mut pieces = Vec::new();
+    let mut remaining = polygon.to_vec();
+    for &(normal, point) in hole_planes {
+        let outside = clip_polygon_3d_by_plane(&remaining, &(-normal, point));
+        if outside.len() >= 3 && polygon_area(&outside) > MIN_REGION_AREA {
+            pieces.push(outside);
+        }
+        remaining = clip_polygon_3d_by_plane(&remaining, &(normal, point));
+        if remaining.len() < 3 {
+            break;
+        }
+    }
+    pieces
+}
+
+// Drops points that repeat their predecessor, as clipping through a corner leaves behind.
+fn without_repeats(polygon: Vec3Polygon, tolerance: f32) -> Vec3Polygon {
+    let mut cleaned: Vec3Polygon = Vec::with_capacity(polygon.len());
+    for point in polygon {
+        if cleaned.last().is_none_or(|last| last.distance(point) > tolerance) {
+            cleaned.push(point);
+        }
+    }
+    while cleaned.len() > 1 && cleaned[0].distance(cleaned[cleaned.len() - 1]) <= tolerance {
+        cleaned.pop();
+    }
+    cleaned
+}
+
+// Where two cells meet: the shared region as seen from each face, given as (cell, face) pairs.
+struct SharedRegion {
+    faces: [(usize, usize); 2],
+    polygons: [Vec3Polygon; 2],
+}
+
+fn shared_regions(cells: &[ConvexCell], weld_tolerance: f32) -> Vec<SharedRegion> {
+    let faces: Vec<(usize, usize, &CellFace)> = cells.iter().enumerate()
+        .flat_map(|(cell_index, cell)| cell.faces.iter().enumerate().map(move |(face_index, face)| (cell_index, face_index, face)))
+        .collect();
+    let mut regions = Vec::new();
+    for (a_index, &(a_cell, a_face, a)) in faces.iter().enumerate() {
+        for &(b_cell, b_face, b) in faces.iter().skip(a_index + 1) {
+            if a_cell == b_cell || a.outward.dot(b.outward) > -1.0 + OPPOSITE_NORMAL_TOLERANCE {
+                continue;
+            }
+            if a.outward.dot(b.polygon[0] - a.polygon[0]).abs() > weld_tolerance {
+                continue;
+            }
+            let in_a = without_repeats(clip_to(&a.polygon, &inward_edge_planes(&b.polygon, b.outward)), weld_tolerance);
+            if in_a.len() < 3 || polygon_area(&in_a) <= MIN_REGION_AREA {
+                continue;
+            }
+            let in_b = without_repeats(clip_to(&b.polygon, &inward_edge_planes(&a.polygon, a.outward)), weld_tolerance);
+            if in_b.len() < 3 {
+                continue;
+            }
+            regions.push(SharedRegion { faces: [(a_cell, a_face), (b_cell, b_face)], polygons: [in_a, in_b] });
+        }
+    }
+    regions
+}
+
+// Finds `point` among `vertices` within `tolerance`, adding it if it is new.
+fn weld(vertices: &mut Vec<Vec3>, point: Vec3, tolerance: f32) -> usize {
+    match vertices.iter().position(|vertex| vertex.distance(point) <= tolerance) {
+        Some(index) => index,
+        None => {
+            vertices.push(point);
+            vertices.len() - 1
+        }
+    }
+}
+
+// Turns cells of open space into hulls placed in world space at their centers. Wherever two
+// cells touch face to face, the shared region becomes a pair of connected portals and the rest
+// of each face is cut into convex pieces with the face's config. Cells without faces are kept,
+// empty, so ids still match cell indices.
+pub fn hulls_from_cells(cells: &[ConvexCell], weld_tolerance: f32) -> CellHulls {
+    let centers: Vec<Vec3> = cells.iter()
+        .map(|cell| {
+            let mut corners: Vec<Vec3> = Vec::new();
+            for point in cell.faces.iter().flat_map(|face| &face.polygon) {
+                weld(&mut corners, *point, weld_tolerance);
+            }
+            if corners.is_empty() { Vec3::ZERO } else { corners.iter().copied().sum::<Vec3>() / corners.len() as f32 }
+        })
+        .collect();
+
+    let regions = shared_regions(cells, weld_tolerance);
+    let mut regions_of_face: HashMap<(usize, usize), Vec<(usize, usize)>> = HashMap::new();
+    for (region_index, region) in regions.iter().enumerate() {
+        for end in 0..2 {
+            regions_of_face.entry(region.faces[end]).or_default().push((region_index, end));
+        }
+    }
+
+    // Sides per cell; each shared region end records the portal it became.
+    let mut blueprint_parts: Vec<(Vec<Vec3>, Vec<BlueprintSide>)> = vec![(Vec::new(), Vec::new()); cells.len()];
+    let mut region_portals: Vec<[PortalId; 2]> = vec![[0; 2]; regions.len()];
+    let mut next_portal_ids: Vec<PortalId> = vec![0; cells.len()];
+    for (cell_index, cell) in cells.iter().enumerate() {
+        let center = centers[cell_index];
+        let (vertices, sides) = &mut blueprint_parts[cell_index];
+        for (face_index, face) in cell.faces.iter().enumerate() {
+            let mut push_side = |polygon: &[Vec3], config: HandlerConfig, portal_id: Option<PortalId>| {
+                let vertex_indices = polygon.iter().map(|&point| weld(vertices, point - center, weld_tolerance)).collect();
+                sides.push(BlueprintSide {
+                    vertex_indices,
+                    local_normal: -face.outward,
+                    handler_type: config.get_intended_handler_type(),
+                    default_handler_config: config,
+                    local_portal_id: portal_id,
+                    render_layers: RENDER_LAYER_DEFAULT,
+                });
+            };
+
+            let mut wall_pieces = vec![face.polygon.clone()];
+            for &(region_index, end) in regions_of_face.get(&(cell_index, face_index)).map_or(&[][..], Vec::as_slice) {
+                let region = &regions[region_index].polygons[end];
+                let hole_planes = inward_edge_planes(region, face.outward);
+                wall_pieces = wall_pieces.iter().flat_map(|piece| subtract(piece, &hole_planes)).collect();
+                let portal_id = next_portal_ids[cell_index];
+                next_portal_ids[cell_index] += 1;
+                region_portals[region_index][end] = portal_id;
+                // Connected below, once both ends have portal ids.
+                push_side(region, HandlerConfig::None, Some(portal_id));
+            }
+            for piece in wall_pieces {
+                let piece = without_repeats(piece, weld_tolerance);
+                if piece.len() >= 3 {
+                    push_side(&piece, face.config.clone(), None);
+                }
+            }
+        }
+    }
+
+    let mut hulls = CellHulls::default();
+    for (cell_index, (local_vertices, sides)) in blueprint_parts.into_iter().enumerate() {
+        let id = cell_index as BlueprintId;
+        let name = cells[cell_index].name.clone();
+        hulls.blueprints.insert(id, HullBlueprint { id, name: name.clone(), local_vertices, sides });
+        hulls.instances.insert(id as InstanceId, HullInstance {
+            id: id as InstanceId,
+            name,
+            blueprint_id: id,
+            initial_transform: Some(Mat4::from_translation(centers[cell_index])),
+            parent_instance_id: None,
+            portal_connections: HashMap::new(),
+            instance_side_handler_configs: HashMap::new(),
+            render_layers: RENDER_LAYER_ALL,
+            side_animations: HashMap::new(),
+            triggers: Vec::new(),
+            acoustics: AcousticSettings::default(),
+            liquid: None,
+            gravity: DEFAULT_GRAVITY,
+        });
+    }
+    for (region, portals) in regions.iter().zip(&region_portals) {
+        for end in 0..2 {
+            let (cell, other_cell) = (region.faces[end].0, region.faces[1 - end].0);
+            let (target_instance_id, target_portal_id) = (other_cell as InstanceId, portals[1 - end]);
+            if let Some(side) = hulls.blueprints.get_mut(&(cell as BlueprintId))
+                .and_then(|blueprint| blueprint.sides.iter_mut().find(|side| side.local_portal_id == Some(portals[end])))
+            {
+                side.default_handler_config = HandlerConfig::portal(target_instance_id, target_portal_id);
+                side.handler_type = side.default_handler_config.get_intended_handler_type();
+            }
+            if let Some(instance) = hulls.instances.get_mut(&(cell as InstanceId)) {
+                instance.portal_connections.insert(portals[end], PortalConnectionInfo { target_instance_id, target_portal_id });
+            }
+        }
+    }
+    hulls
+}
+
+// A scene made of just `hulls`, seen through `camera` as the main camera.
+pub fn scene_from_hulls(hulls: CellHulls, camera: SceneCamera) -> Scene {
+    Scene {
+        blueprints: hulls.blueprints,
+        instances: hulls.instances,
+        cameras: HashMap::from([(DEFAULT_CAMERA_NAME.to_string(), camera)]),
+        active_camera: DEFAULT_CAMERA_NAME.to_string(),
+        fog: None,
+        entities: HashMap::new(),
+        debug_labels: Vec::new(),
+        sound_emitters: HashMap::new(),
+        decals: HashMap::new(),
+        events: EventBus::default(),
+        occupied_triggers: Vec::new(),
+        held_keys: HashSet::new(),
+        instance_id_floor: 0,
+    }
+}
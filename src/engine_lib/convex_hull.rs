@@ -0,0 +1,219 @@
+// src/engine_lib/convex_hull.rs
+
+use std::collections::HashMap;
+use std::fmt;
+use glam::{DVec3, Vec3};
+use crate::engine_lib::blueprint_validation::BlueprintError;
+use crate::engine_lib::gltf_import::{blueprint_from_triangles, ColoredTriangle, GltfImportError, GltfImportOptions};
+use crate::engine_lib::scene_types::{HullBlueprint, BlueprintId};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConvexHullOptions {
+    // Points within this distance of a face's plane count as on it, so nearly coplanar faces
+    // come out as one side and near-duplicate points as one corner.
+    pub tolerance: f32,
+    // Wall color of every side.
+    pub color: [f32; 4],
+}
+
+impl Default for ConvexHullOptions {
+    fn default() -> Self {
+        Self { tolerance: 1e-4, color: [0.8, 0.8, 0.8, 1.0] }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConvexHullError {
+    TooFewPoints,
+    // The points all lie on one plane (or line, or point) and enclose no volume.
+    Flat,
+    // Reported by `HullBlueprint::validate`; only expected from points at the edge of the
+    // tolerance, where faces are not quite planar.
+    InvalidHull(Vec<BlueprintError>),
+}
+
+impl fmt::Display for ConvexHullError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvexHullError::TooFewPoints => write!(f, "a convex hull needs at least 4 points"),
+            ConvexHullError::Flat => write!(f, "points are coplanar and enclose no volume"),
+            ConvexHullError::InvalidHull(errors) => {
+                write!(f, "hull is not a valid blueprint: ")?;
+                let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", messages.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConvexHullError {}
+
+struct HullFace {
+    corners: [usize; 3],
+    normal: DVec3,
+    offset: f64,
+    // Points above this face, waiting to be added.
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+impl HullFace {
+    fn new(points: &[DVec3], corners: [usize; 3]) -> Self {
+        let [a, b, c] = corners.map(|i| points[i]);
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        Self { corners, normal, offset: normal.dot(a), outside: Vec::new(), alive: true }
+    }
+
+    fn height(&self, point: DVec3) -> f64 {
+        self.normal.dot(point) - self.offset
+    }
+
+    fn edges(&self) -> [(usize, usize); 3] {
+        let [a, b, c] = self.corners;
+        [(a, b), (b, c), (c, a)]
+    }
+}
+
+// Hands each point to the face it is furthest above, if it is above any.
+fn assign_outside(faces: &mut [HullFace], face_indices: &[usize], points: &[DVec3], candidates: impl IntoIterator<Item = usize>, epsilon: f64) {
+    for point_index in candidates {
+        let best = face_indices.iter()
+            .map(|&face_index| (face_index, faces[face_index].height(points[point_index])))
+            .filter(|&(_, height)| height > epsilon)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((face_index, _)) = best {
+            faces[face_index].outside.push(point_index);
+        }
+    }
+}
+
+// The triangles of the convex hull of `points`, as indices into it, wound counter-clockwise
+// seen from outside. Points within `tolerance` of the hull's surface may be left out of it.
+pub fn quickhull(points: &[Vec3], tolerance: f32) -> Result<Vec<[usize; 3]>, ConvexHullError> {
+    if points.len() < 4 {
+        return Err(ConvexHullError::TooFewPoints);
+    }
+    let points: Vec<DVec3> = points.iter().map(|point| point.as_dvec3()).collect();
+    let epsilon = tolerance as f64;
+
+    // The starting tetrahedron: the two extreme points furthest apart, the point furthest from
+    // the line through them, and the point furthest from their plane.
+    let mut extremes = Vec::with_capacity(6);
+    for axis in 0..3 {
+        let by_axis = |a: &&DVec3, b: &&DVec3| a[axis].total_cmp(&b[axis]);
+        let min = points.iter().enumerate().min_by(|a, b| by_axis(&a.1, &b.1)).map(|(i, _)| i);
+        let max = points.iter().enumerate().max_by(|a, b| by_axis(&a.1, &b.1)).map(|(i, _)| i);
+        extremes.extend(min.into_iter().chain(max));
+    }
+    let (p0, p1) = extremes.iter()
+        .flat_map(|&a| extremes.iter().map(move |&b| (a, b)))
+        .max_by(|a, b| points[a.0].distance_squared(points[a.1]).total_cmp(&points[b.0].distance_squared(points[b.1])))
+        .ok_or(ConvexHullError::Flat)?;
+    let line = (points[p1] - points[p0]).normalize_or_zero();
+    let from_line = |i: usize| {
+        let offset = points[i] - points[p0];
+        (offset - line * offset.dot(line)).length()
+    };
+    let p2 = (0..points.len()).max_by(|&a, &b| from_line(a).total_cmp(&from_line(b))).ok_or(ConvexHullError::Flat)?;
+    if points[p0].distance(points[p1]) <= epsilon || from_line(p2) <= epsilon {
+        return Err(ConvexHullError::Flat);
+    }
+    let base = HullFace::new(&points, [p0, p1, p2]);
+    let p3 = (0..points.len()).max_by(|&a, &b| base.height(points[a]).abs().total_cmp(&base.height(points[b]).abs())).ok_or(ConvexHullError::Flat)?;
+    if base.height(points[p3]).abs() <= epsilon {
+        return Err(ConvexHullError::Flat);
+    }
+
+    let centroid = (points[p0] + points[p1] + points[p2] + points[p3]) / 4.0;
+    let mut faces: Vec<HullFace> = [[p0, p1, p2], [p0, p3, p1], [p1, p3, p2], [p2, p3, p0]].into_iter()
+        .map(|[a, b, c]| {
+            let face = HullFace::new(&points, [a, b, c]);
+            if face.height(centroid) > 0.0 { HullFace::new(&points, [a, c, b]) } else { face }
+        })
+        .collect();
+    // Each directed edge, to the face it runs counter-clockwise around.
+    let mut edge_faces: HashMap<(usize, usize), usize> = HashMap::new();
+    for (face_index, face) in faces.iter().enumerate() {
+        for edge in face.edges() {
+            edge_faces.insert(edge, face_index);
+        }
+    }
+    let start_faces: Vec<usize> = (0..faces.len()).collect();
+    let candidates = (0..points.len()).filter(|i| ![p0, p1, p2, p3].contains(i));
+    assign_outside(&mut faces, &start_faces, &points, candidates, epsilon);
+
+    while let Some(face_index) = faces.iter().position(|face| face.alive && !face.outside.is_empty()) {
+        let apex = *faces[face_index].outside.iter()
+            .max_by(|&&a, &&b| faces[face_index].height(points[a]).total_cmp(&faces[face_index].height(points[b])))
+            .expect("outside set is not empty");
+
+        // The faces the apex sees, grown out from this one across shared edges so they form
+        // one patch, and the loop of edges around that patch.
+        let mut visible = vec![face_index];
+        let mut horizon: Vec<(usize, usize)> = Vec::new();
+        faces[face_index].alive = false;
+        let mut next = 0;
+        while next < visible.len() {
+            let current = visible[next];
+            next += 1;
+            for (a, b) in faces[current].edges() {
+                let neighbor = edge_faces[&(b, a)];
+                if !faces[neighbor].alive {
+                    continue;
+                }
+                // Visibility is decided without tolerance: leaving a face the apex is barely above
+                // would fold the surface inwards along the horizon.
+                if faces[neighbor].height(points[apex]) > 0.0 {
+                    faces[neighbor].alive = false;
+                    visible.push(neighbor);
+                } else {
+                    horizon.push((a, b));
+                }
+            }
+        }
+
+        let orphans: Vec<usize> = visible.iter()
+            .flat_map(|&dead| std::mem::take(&mut faces[dead].outside))
+            .filter(|&point_index| point_index != apex)
+            .collect();
+        for &dead in &visible {
+            for edge in faces[dead].edges() {
+                edge_faces.remove(&edge);
+            }
+        }
+        let mut new_faces = Vec::with_capacity(horizon.len());
+        for (a, b) in horizon {
+            let face = HullFace::new(&points, [a, b, apex]);
+            let new_index = faces.len();
+            for edge in face.edges() {
+                edge_faces.insert(edge, new_index);
+            }
+            faces.push(face);
+            new_faces.push(new_index);
+        }
+        assign_outside(&mut faces, &new_faces, &points, orphans, epsilon);
+    }
+
+    Ok(faces.into_iter().filter(|face| face.alive).map(|face| face.corners).collect())
+}
+
+// The convex hull of `points` as a hull blueprint: coplanar triangles merged into polygonal
+// sides, inward normals, walls of `options.color`, and each side's index as its portal id.
+// Points inside the hull are ignored.
+pub fn convex_hull_blueprint(
+    id: BlueprintId,
+    name: impl Into<String>,
+    points: &[Vec3],
+    options: &ConvexHullOptions,
+) -> Result<HullBlueprint, ConvexHullError> {
+    let triangles: Vec<ColoredTriangle> = quickhull(points, options.tolerance)?.into_iter()
+        .map(|corners| ColoredTriangle { corners: corners.map(|i| points[i]), color: options.color })
+        .collect();
+    let import_options = GltfImportOptions { mesh_name: None, weld_tolerance: options.tolerance, coplanar_tolerance: options.tolerance };
+    blueprint_from_triangles(id, name, &triangles, &import_options).map_err(|error| match error {
+        GltfImportError::InvalidHull(errors) => ConvexHullError::InvalidHull(errors),
+        // Only slivers were left. No corner can stick out of a merged side: every point is within
+        // tolerance below the plane of each hull triangle.
+        _ => ConvexHullError::Flat,
+    })
+}
@@ -0,0 +1,194 @@
+// src/engine_lib/csg.rs
+
+use glam::{DVec3, Mat4, Vec3};
+use crate::engine_lib::convex_cells::{polyhedron_from_planes, hulls_from_cells, scene_from_hulls, CellFace, CellHulls, ConvexCell};
+use crate::engine_lib::scene_types::{Scene, SceneCamera, HandlerConfig, InstanceId, DEFAULT_CAMERA_COLLISION_RADIUS};
+
+// Distance within which planes coincide and corners are the same point.
+const CSG_EPSILON: f64 = 1e-5;
+// Corners of a hull closer than this are merged into one vertex.
+const CSG_WELD_TOLERANCE: f32 = 1e-4;
+
+// A bounding plane of a convex solid: points with `normal.dot(p) > distance` are outside. The
+// face lying in it gets `config` where it does not open onto another cell.
+#[derive(Clone, Debug)]
+pub struct CsgPlane {
+    pub normal: DVec3,
+    pub distance: f64,
+    pub config: HandlerConfig,
+}
+
+impl CsgPlane {
+    // The plane through `point` facing out along `normal`.
+    pub fn new(normal: Vec3, point: Vec3, config: HandlerConfig) -> Self {
+        let normal = normal.as_dvec3().normalize();
+        Self { normal, distance: normal.dot(point.as_dvec3()), config }
+    }
+
+    // The same plane bounding the other side, for the solid's complement.
+    fn flipped(&self) -> Self {
+        Self { normal: -self.normal, distance: -self.distance, config: self.config.clone() }
+    }
+
+    fn coincides_with(&self, other: &CsgPlane) -> bool {
+        self.normal.dot(other.normal) >= 1.0 - CSG_EPSILON && (self.distance - other.distance).abs() <= CSG_EPSILON
+    }
+}
+
+// The intersection of a set of half-spaces.
+#[derive(Clone, Debug, Default)]
+pub struct ConvexSolid {
+    pub planes: Vec<CsgPlane>,
+}
+
+impl ConvexSolid {
+    pub fn from_planes(planes: Vec<CsgPlane>) -> Self {
+        planes.into_iter().fold(Self::default(), |solid, plane| solid.with_plane(plane))
+    }
+
+    // An axis-aligned box with every face configured as `config`.
+    pub fn cuboid(min: Vec3, max: Vec3, config: HandlerConfig) -> Self {
+        Self::from_planes(vec![
+            CsgPlane::new(Vec3::X, max, config.clone()),
+            CsgPlane::new(Vec3::NEG_X, min, config.clone()),
+            CsgPlane::new(Vec3::Y, max, config.clone()),
+            CsgPlane::new(Vec3::NEG_Y, min, config.clone()),
+            CsgPlane::new(Vec3::Z, max, config.clone()),
+            CsgPlane::new(Vec3::NEG_Z, min, config),
+        ])
+    }
+
+    // The solid cut down by one more half-space. A plane it already has is not added twice,
+    // and the existing one keeps its config.
+    fn with_plane(mut self, plane: CsgPlane) -> Self {
+        if !self.planes.iter().any(|existing| existing.coincides_with(&plane)) {
+            self.planes.push(plane);
+        }
+        self
+    }
+
+    pub fn contains(&self, point: Vec3) -> bool {
+        let point = point.as_dvec3();
+        self.planes.iter().all(|plane| plane.normal.dot(point) - plane.distance <= CSG_EPSILON)
+    }
+
+    fn polyhedron(&self) -> (Vec<DVec3>, Vec<(usize, Vec<usize>)>) {
+        let planes: Vec<(DVec3, f64)> = self.planes.iter().map(|plane| (plane.normal, plane.distance)).collect();
+        polyhedron_from_planes(&planes, CSG_EPSILON)
+    }
+
+    // True if the solid encloses some volume: it is closed, and no face has every corner on
+    // its plane, as happens for the flat slivers cutting along an existing face leaves.
+    pub fn has_volume(&self) -> bool {
+        let (corners, faces) = self.polyhedron();
+        faces.len() >= 4 && faces.iter().all(|&(plane_index, _)| {
+            let plane = &self.planes[plane_index];
+            corners.iter().any(|corner| plane.normal.dot(*corner) - plane.distance < -CSG_EPSILON)
+        })
+    }
+
+    // The parts of the solid outside `other`, as disjoint convex pieces: one per plane of
+    // `other`, each outside that plane and inside the ones before it. A solid `other` does not
+    // overlap is left whole rather than cut along planes that never reach it.
+    fn minus(&self, other: &ConvexSolid) -> Vec<ConvexSolid> {
+        if self.intersection(other).is_none() {
+            return vec![self.clone()];
+        }
+        let mut pieces = Vec::new();
+        let mut remaining = self.clone();
+        for plane in &other.planes {
+            let outside = remaining.clone().with_plane(plane.flipped());
+            if outside.has_volume() {
+                pieces.push(outside);
+            }
+            remaining = remaining.with_plane(plane.clone());
+            if !remaining.has_volume() {
+                return pieces;
+            }
+        }
+        // `remaining` is now inside all of `other`: the part that is removed.
+        pieces
+    }
+
+    fn intersection(&self, other: &ConvexSolid) -> Option<ConvexSolid> {
+        let solid = other.planes.iter().cloned().fold(self.clone(), ConvexSolid::with_plane);
+        solid.has_volume().then_some(solid)
+    }
+
+    pub fn to_cell(&self, name: String) -> ConvexCell {
+        let (corners, faces) = self.polyhedron();
+        let faces = faces.into_iter()
+            .map(|(plane_index, corner_indices)| {
+                let plane = &self.planes[plane_index];
+                CellFace {
+                    outward: plane.normal.as_vec3().normalize(),
+                    polygon: corner_indices.iter().map(|&i| corners[i].as_vec3()).collect(),
+                    config: plane.config.clone(),
+                }
+            })
+            .collect();
+        ConvexCell { name, faces }
+    }
+}
+
+// Open space built by adding, carving and clipping convex solids, kept as disjoint convex
+// cells. Each cell becomes one hull, and where cells touch they are joined by portals, so a
+// room with an alcove or two rooms with a doorway between them come out as several connected
+// hulls. Faces take the config of the solid that made them: a room's walls from the room, the
+// walls of a pillar carved out of it from the pillar.
+#[derive(Clone, Debug, Default)]
+pub struct CsgModel {
+    cells: Vec<ConvexSolid>,
+}
+
+impl CsgModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cells(&self) -> &[ConvexSolid] {
+        &self.cells
+    }
+
+    // Adds the space inside `solid`; only the parts not already open become new cells.
+    pub fn add(&mut self, solid: &ConvexSolid) {
+        let mut pieces = if solid.has_volume() { vec![solid.clone()] } else { Vec::new() };
+        for cell in &self.cells {
+            pieces = pieces.iter().flat_map(|piece| piece.minus(cell)).collect();
+        }
+        self.cells.extend(pieces);
+    }
+
+    // Fills in the space inside `solid`, splitting the cells it cuts into.
+    pub fn subtract(&mut self, solid: &ConvexSolid) {
+        self.cells = self.cells.iter().flat_map(|cell| cell.minus(solid)).collect();
+    }
+
+    // Keeps only the space inside `solid`.
+    pub fn intersect(&mut self, solid: &ConvexSolid) {
+        self.cells = self.cells.iter().filter_map(|cell| cell.intersection(solid)).collect();
+    }
+
+    // The cells as hulls placed in world space, joined by portals wherever they touch.
+    // Blueprint and instance ids are the cells' indices.
+    pub fn build_hulls(&self) -> CellHulls {
+        let cells: Vec<ConvexCell> = self.cells.iter().enumerate()
+            .map(|(index, cell)| cell.to_cell(format!("CsgCell{}", index)))
+            .collect();
+        hulls_from_cells(&cells, CSG_WELD_TOLERANCE)
+    }
+
+    // A scene of the model's hulls with the main camera at `camera_position`, looking down -Z.
+    // None if that point is not in open space.
+    pub fn to_scene(&self, camera_position: Vec3) -> Option<Scene> {
+        let cell_index = self.cells.iter().position(|cell| cell.contains(camera_position))?;
+        let hulls = self.build_hulls();
+        let center = hulls.instances.get(&(cell_index as InstanceId))?.initial_transform?.transform_point3(Vec3::ZERO);
+        let camera = SceneCamera {
+            host_instance_id: cell_index as InstanceId,
+            local_transform: Mat4::from_translation(camera_position - center),
+            collision_radius: DEFAULT_CAMERA_COLLISION_RADIUS,
+        };
+        Some(scene_from_hulls(hulls, camera))
+    }
+}
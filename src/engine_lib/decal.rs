@@ -0,0 +1,76 @@
+// src/engine_lib/decal.rs
+
+use glam::{Quat, Vec3};
+use crate::engine_lib::scene_types::{Scene, InstanceId, SideIndex};
+use crate::engine_lib::animation::side_transform;
+use crate::engine_lib::picking::RayHit;
+
+pub type DecalId = u32;
+
+// A small square stuck flat on a side, such as a bullet hole, a scorch mark or a sign. It is drawn
+// over the side, clipped to it and to the portal region it is seen through, and moves with the
+// side if the side is animated. Decals on sides that show another hull are not drawn.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Decal {
+    pub id: DecalId,
+    pub instance_id: InstanceId,
+    pub side_index: SideIndex,
+    // Center on the side, in the instance's blueprint space with the side at rest.
+    pub local_position: Vec3,
+    // Edge length of the square.
+    pub size: f32,
+    // Turn about the side's normal, in radians.
+    pub rotation: f32,
+    pub color: [f32; 4],
+    // Like a wall's `texture_id`, kept for when sides are textured; drawn in `color` until then.
+    pub texture_id: Option<String>,
+}
+
+impl Decal {
+    // An unturned decal; its id is given by `Scene::add_decal`.
+    pub fn new(instance_id: InstanceId, side_index: SideIndex, local_position: Vec3, size: f32, color: [f32; 4]) -> Self {
+        Self { id: 0, instance_id, side_index, local_position, size, rotation: 0.0, color, texture_id: None }
+    }
+
+    // Corners in blueprint space with the side at rest, on the plane through `local_position`
+    // facing `side_normal`. Unturned, its edges run along the side's horizontal and the
+    // blueprint's +Y as projected onto it (+Z on floors and ceilings).
+    pub fn corners(&self, side_normal: Vec3) -> [Vec3; 4] {
+        let normal = side_normal.normalize_or_zero();
+        let up_hint = if normal.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y };
+        let up = (up_hint - normal * normal.dot(up_hint)).normalize_or_zero();
+        let turn = Quat::from_axis_angle(normal, self.rotation);
+        let up = turn * up * (self.size * 0.5);
+        let right = turn * up_hint.cross(normal).normalize_or_zero() * (self.size * 0.5);
+        let center = self.local_position;
+        [center - right - up, center + right - up, center + right + up, center - right + up]
+    }
+}
+
+// The decals on the sides of `instance_id`.
+pub fn decals_in_instance(scene: &Scene, instance_id: InstanceId) -> Vec<&Decal> {
+    scene.decals.values().filter(|decal| decal.instance_id == instance_id).collect()
+}
+
+impl Scene {
+    // Adds `decal` under a fresh id, which is returned.
+    pub fn add_decal(&mut self, mut decal: Decal) -> DecalId {
+        decal.id = self.decals.keys().max().map_or(0, |id| id + 1);
+        let id = decal.id;
+        self.decals.insert(id, decal);
+        id
+    }
+
+    pub fn remove_decal(&mut self, id: DecalId) -> Option<Decal> {
+        self.decals.remove(&id)
+    }
+
+    // Sticks a decal of `size` and `color` where a ray hit, e.g. from `raycast_scene`. None if
+    // the hit instance is gone.
+    pub fn place_decal_at_hit(&mut self, hit: &RayHit, size: f32, color: [f32; 4]) -> Option<DecalId> {
+        let instance = self.instances.get(&hit.instance_id)?;
+        // Hits are where the side is now; decals are kept where it is at rest.
+        let local_position = side_transform(instance, hit.side_index).inverse().transform_point3(hit.local_point);
+        Some(self.add_decal(Decal::new(hit.instance_id, hit.side_index, local_position, size, color)))
+    }
+}
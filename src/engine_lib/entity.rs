@@ -0,0 +1,116 @@
+// src/engine_lib/entity.rs
+
+use glam::{Mat4, Vec3};
+use crate::engine_lib::scene_types::{Scene, InstanceId, RenderLayerMask, RENDER_LAYER_DEFAULT};
+
+pub type EntityId = u32;
+
+// One flat face of a convex mesh. Vertices wind counter-clockwise seen from outside.
+#[derive(Clone, Debug)]
+pub struct MeshFace {
+    pub vertex_indices: Vec<usize>,
+    pub color: [f32; 4],
+}
+
+// A closed convex mesh in the entity's local space. Unlike hull blueprints it is seen from
+// outside, so only faces turned towards the camera are drawn.
+#[derive(Clone, Debug)]
+pub struct ConvexMesh {
+    pub vertices: Vec<Vec3>,
+    pub faces: Vec<MeshFace>,
+}
+
+impl ConvexMesh {
+    // An axis-aligned box centered on the origin, every face in `color`.
+    pub fn cuboid(half_extents: Vec3, color: [f32; 4]) -> Self {
+        let Vec3 { x, y, z } = half_extents;
+        let vertices = vec![
+            Vec3::new(-x, -y, -z), Vec3::new( x, -y, -z), Vec3::new( x,  y, -z), Vec3::new(-x,  y, -z),
+            Vec3::new(-x, -y,  z), Vec3::new( x, -y,  z), Vec3::new( x,  y,  z), Vec3::new(-x,  y,  z),
+        ];
+        let face = |vertex_indices: Vec<usize>| MeshFace { vertex_indices, color };
+        let faces = vec![
+            face(vec![4, 5, 6, 7]), // +Z
+            face(vec![1, 0, 3, 2]), // -Z
+            face(vec![0, 4, 7, 3]), // -X
+            face(vec![5, 1, 2, 6]), // +X
+            face(vec![3, 7, 6, 2]), // +Y
+            face(vec![0, 1, 5, 4]), // -Y
+        ];
+        Self { vertices, faces }
+    }
+
+    // Outward normal of a face from its winding, or None if it is degenerate.
+    pub fn face_normal(&self, face: &MeshFace) -> Option<Vec3> {
+        let points: Option<Vec<Vec3>> = face.vertex_indices.iter().map(|&i| self.vertices.get(i).copied()).collect();
+        let points = points?;
+        if points.len() < 3 {
+            return None;
+        }
+        // Sum of edge cross products (Newell's method), robust to slightly non-planar faces.
+        let mut normal = Vec3::ZERO;
+        for (i, current) in points.iter().enumerate() {
+            normal += current.cross(points[(i + 1) % points.len()]);
+        }
+        normal.try_normalize()
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        if self.vertices.is_empty() {
+            return Vec3::ZERO;
+        }
+        self.vertices.iter().copied().sum::<Vec3>() / self.vertices.len() as f32
+    }
+}
+
+// A flat rectangle that always faces the camera, centered on the entity's origin. Its size is
+// in the host hull's units and ignores the entity's rotation and scale.
+#[derive(Clone, Debug)]
+pub struct Sprite {
+    pub width: f32,
+    pub height: f32,
+    pub color: [f32; 4],
+}
+
+#[derive(Clone, Debug)]
+pub enum EntityShape {
+    Mesh(ConvexMesh),
+    Sprite(Sprite),
+}
+
+// A prop placed inside a hull instance. It is drawn over the hull's walls and anything seen
+// through its portals, and is not part of traversal or collision.
+#[derive(Clone, Debug)]
+pub struct Entity {
+    pub id: EntityId,
+    pub name: String,
+    pub host_instance_id: InstanceId,
+    // Pose in the host instance's blueprint space.
+    pub local_transform: Mat4,
+    pub shape: EntityShape,
+    pub render_layers: RenderLayerMask,
+}
+
+impl Entity {
+    pub fn new(id: EntityId, name: impl Into<String>, host_instance_id: InstanceId, local_transform: Mat4, mesh: ConvexMesh) -> Self {
+        Self::with_shape(id, name, host_instance_id, local_transform, EntityShape::Mesh(mesh))
+    }
+
+    // A billboard for pickups, markers and the like, standing at `position` in the host's space.
+    pub fn sprite(id: EntityId, name: impl Into<String>, host_instance_id: InstanceId, position: Vec3, sprite: Sprite) -> Self {
+        Self::with_shape(id, name, host_instance_id, Mat4::from_translation(position), EntityShape::Sprite(sprite))
+    }
+
+    pub fn with_shape(id: EntityId, name: impl Into<String>, host_instance_id: InstanceId, local_transform: Mat4, shape: EntityShape) -> Self {
+        Self { id, name: name.into(), host_instance_id, local_transform, shape, render_layers: RENDER_LAYER_DEFAULT }
+    }
+}
+
+// Entities hosted by `instance_id`, in id order.
+pub fn entities_in_instance(scene: &Scene, instance_id: InstanceId) -> Vec<&Entity> {
+    let mut entities: Vec<&Entity> = scene.entities.values()
+        .filter(|entity| entity.host_instance_id == instance_id)
+        .collect();
+    entities.sort_unstable_by_key(|entity| entity.id);
+    entities
+}
@@ -0,0 +1,80 @@
+// src/engine_lib/events.rs
+
+use crate::engine_lib::scene_types::{InstanceId, SideIndex};
+
+pub type SubscriptionId = u32;
+
+// Something that happened in the scene that application code may want to react to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EngineEvent {
+    // The active camera moved from one instance into another, through a portal or into a
+    // free-standing instance.
+    PortalTraversed { from_instance_id: InstanceId, to_instance_id: InstanceId },
+    // The active camera was stopped by a side. Sent every frame the camera keeps pushing into it.
+    Collision { instance_id: InstanceId, side_index: SideIndex },
+    TriggerEntered { instance_id: InstanceId, trigger_index: usize, name: String },
+    TriggerExited { instance_id: InstanceId, trigger_index: usize, name: String },
+    // A scene was set up; `diagnostic_count` references had to be repaired on the way.
+    SceneLoaded { diagnostic_count: usize },
+    // An instance was added or taken out at runtime; see `Scene::spawn_instance`.
+    InstanceSpawned { instance_id: InstanceId },
+    InstanceRemoved { instance_id: InstanceId },
+}
+
+// Sync so that a scene can be shared with the renderer's traversal threads.
+type Subscriber = Box<dyn FnMut(&EngineEvent) + Send + Sync>;
+
+// Queues events as the engine publishes them and hands them to subscribers on `dispatch`, so
+// subscribers run outside scene updates. Callers that need to change the scene in response
+// can use the events `dispatch` returns instead.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<(SubscriptionId, Subscriber)>,
+    next_subscription_id: SubscriptionId,
+    pending: Vec<EngineEvent>,
+}
+
+impl EventBus {
+    pub fn subscribe(&mut self, subscriber: impl FnMut(&EngineEvent) + Send + Sync + 'static) -> SubscriptionId {
+        let id = self.next_subscription_id;
+        self.next_subscription_id += 1;
+        self.subscribers.push((id, Box::new(subscriber)));
+        id
+    }
+
+    // Returns false if there is no such subscription.
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let count_before = self.subscribers.len();
+        self.subscribers.retain(|(subscription_id, _)| *subscription_id != id);
+        self.subscribers.len() != count_before
+    }
+
+    pub fn publish(&mut self, event: EngineEvent) {
+        self.pending.push(event);
+    }
+
+    // Events published since the last dispatch, oldest first.
+    pub fn pending(&self) -> &[EngineEvent] {
+        &self.pending
+    }
+
+    // Delivers the queued events to every subscriber, in subscription order, and returns them.
+    pub fn dispatch(&mut self) -> Vec<EngineEvent> {
+        let events = std::mem::take(&mut self.pending);
+        for event in &events {
+            for (_, subscriber) in &mut self.subscribers {
+                subscriber(event);
+            }
+        }
+        events
+    }
+}
+
+impl std::fmt::Debug for EventBus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventBus")
+            .field("subscribers", &self.subscribers.len())
+            .field("pending", &self.pending)
+            .finish()
+    }
+}
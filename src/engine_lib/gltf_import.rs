@@ -0,0 +1,290 @@
+// src/engine_lib/gltf_import.rs
+
+use std::fmt;
+use std::path::Path;
+use glam::{Mat4, Vec3};
+use crate::engine_lib::blueprint_validation::BlueprintError;
+use crate::engine_lib::scene_types::{
+    HullBlueprint, BlueprintSide, HandlerConfig, BlueprintId, PortalId, RENDER_LAYER_DEFAULT,
+};
+
+// Color of sides whose triangles have no material.
+const DEFAULT_SIDE_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GltfImportOptions {
+    // Mesh to import; the first mesh in the scene if None.
+    pub mesh_name: Option<String>,
+    // Corners closer than this are merged into one vertex.
+    pub weld_tolerance: f32,
+    // Triangles whose corners are within this distance of a side's plane, facing the same way,
+    // are merged into that side.
+    pub coplanar_tolerance: f32,
+}
+
+impl Default for GltfImportOptions {
+    fn default() -> Self {
+        Self { mesh_name: None, weld_tolerance: 1e-4, coplanar_tolerance: 1e-3 }
+    }
+}
+
+#[derive(Debug)]
+pub enum GltfImportError {
+    Gltf(gltf::Error),
+    // No mesh at all, or none with the requested name.
+    MeshNotFound(Option<String>),
+    NoTriangles,
+    // A corner lies outside the plane of one of the mesh's faces.
+    NotConvex { point: Vec3 },
+    // The mesh is convex but does not close into a hull, e.g. it has holes.
+    InvalidHull(Vec<BlueprintError>),
+}
+
+impl fmt::Display for GltfImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfImportError::Gltf(e) => write!(f, "cannot read glTF: {}", e),
+            GltfImportError::MeshNotFound(None) => write!(f, "glTF file has no mesh"),
+            GltfImportError::MeshNotFound(Some(name)) => write!(f, "glTF file has no mesh named {:?}", name),
+            GltfImportError::NoTriangles => write!(f, "mesh has no triangles"),
+            GltfImportError::NotConvex { point } => write!(f, "mesh is not convex: corner {} is outside one of its faces", point),
+            GltfImportError::InvalidHull(errors) => {
+                write!(f, "mesh is not a closed hull: ")?;
+                let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                write!(f, "{}", messages.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for GltfImportError {}
+
+impl From<gltf::Error> for GltfImportError {
+    fn from(e: gltf::Error) -> Self {
+        GltfImportError::Gltf(e)
+    }
+}
+
+// A mesh triangle, counter-clockwise seen from outside as glTF front faces are.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColoredTriangle {
+    pub corners: [Vec3; 3],
+    pub color: [f32; 4],
+}
+
+// Every node using a mesh in the default scene (or the first one), with its transform into
+// scene space, depth first.
+fn mesh_nodes(document: &gltf::Document) -> Vec<(gltf::Mesh<'_>, Mat4)> {
+    let mut stack: Vec<(gltf::Node, Mat4)> = document.default_scene().or_else(|| document.scenes().next())
+        .map(|scene| scene.nodes().map(|node| (node, Mat4::IDENTITY)).collect())
+        .unwrap_or_default();
+    stack.reverse();
+    let mut found = Vec::new();
+    while let Some((node, parent_transform)) = stack.pop() {
+        let transform = parent_transform * Mat4::from_cols_array_2d(&node.transform().matrix());
+        if let Some(mesh) = node.mesh() {
+            found.push((mesh, transform));
+        }
+        let children: Vec<gltf::Node> = node.children().collect();
+        stack.extend(children.into_iter().rev().map(|child| (child, transform)));
+    }
+    found
+}
+
+fn mesh_triangles(mesh: &gltf::Mesh, transform: Mat4, buffers: &[gltf::buffer::Data]) -> Vec<ColoredTriangle> {
+    // A mirroring node transform turns front faces into back faces.
+    let mirrored = transform.determinant() < 0.0;
+    let mut triangles = Vec::new();
+    for primitive in mesh.primitives().filter(|primitive| primitive.mode() == gltf::mesh::Mode::Triangles) {
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+        let positions: Vec<Vec3> = match reader.read_positions() {
+            Some(positions) => positions.map(|p| transform.transform_point3(Vec3::from_array(p))).collect(),
+            None => continue,
+        };
+        let indices: Vec<u32> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        let color = primitive.material().index()
+            .map_or(DEFAULT_SIDE_COLOR, |_| primitive.material().pbr_metallic_roughness().base_color_factor());
+        for triangle in indices.chunks_exact(3) {
+            let corner = |i: usize| positions.get(triangle[i] as usize).copied();
+            if let (Some(a), Some(b), Some(c)) = (corner(0), corner(1), corner(2)) {
+                let corners = if mirrored { [a, c, b] } else { [a, b, c] };
+                triangles.push(ColoredTriangle { corners, color });
+            }
+        }
+    }
+    triangles
+}
+
+// The wanted mesh's name and triangles, in the scene's space: the first node using it, or the
+// bare mesh in files without scenes.
+fn collect_triangles(
+    document: &gltf::Document,
+    buffers: &[gltf::buffer::Data],
+    mesh_name: Option<&str>,
+) -> Result<(Option<String>, Vec<ColoredTriangle>), GltfImportError> {
+    let wanted = |mesh: &gltf::Mesh| mesh_name.is_none_or(|name| mesh.name() == Some(name));
+    let (mesh, transform) = match mesh_nodes(document).into_iter().find(|(mesh, _)| wanted(mesh))
+        .or_else(|| document.meshes().find(|mesh| wanted(mesh)).map(|mesh| (mesh, Mat4::IDENTITY)))
+    {
+        Some(found) => found,
+        None => return Err(GltfImportError::MeshNotFound(mesh_name.map(str::to_string))),
+    };
+    Ok((mesh.name().map(str::to_string), mesh_triangles(&mesh, transform, buffers)))
+}
+
+fn weld(vertices: &mut Vec<Vec3>, point: Vec3, tolerance: f32) -> usize {
+    match vertices.iter().position(|vertex| vertex.distance(point) <= tolerance) {
+        Some(index) => index,
+        None => {
+            vertices.push(point);
+            vertices.len() - 1
+        }
+    }
+}
+
+// A side being assembled from coplanar triangles: its outward plane and the vertices on it.
+struct FaceGroup {
+    outward: Vec3,
+    distance: f32,
+    vertex_indices: Vec<usize>,
+    color: [f32; 4],
+}
+
+// Vertex indices of the convex outline of `group`, counter-clockwise around its outward normal.
+// Vertices partway along an edge are dropped; validation matches edges across them anyway.
+fn face_outline(vertices: &[Vec3], group: &FaceGroup, tolerance: f32) -> Vec<usize> {
+    let u = group.outward.any_orthonormal_vector();
+    let v = group.outward.cross(u);
+    let mut points: Vec<(f32, f32, usize)> = group.vertex_indices.iter()
+        .map(|&index| (vertices[index].dot(u), vertices[index].dot(v), index))
+        .collect();
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    points.dedup_by_key(|point| point.2);
+
+    // Andrew's monotone chain. (u, v, outward) is right-handed, so counter-clockwise in the
+    // plane is counter-clockwise around `outward`.
+    let min_turn = tolerance * tolerance;
+    let extend_chain = |chain: &mut Vec<(f32, f32, usize)>, point: (f32, f32, usize)| {
+        while let [.., o, a] = chain[..] {
+            if (a.0 - o.0) * (point.1 - o.1) - (a.1 - o.1) * (point.0 - o.0) > min_turn {
+                break;
+            }
+            chain.pop();
+        }
+        chain.push(point);
+    };
+    let mut hull = Vec::with_capacity(points.len() + 1);
+    let mut upper = Vec::with_capacity(points.len());
+    for &point in &points {
+        extend_chain(&mut hull, point);
+    }
+    for &point in points.iter().rev() {
+        extend_chain(&mut upper, point);
+    }
+    // Each chain ends where the other starts.
+    hull.pop();
+    upper.pop();
+    hull.extend(upper);
+    hull.into_iter().map(|point| point.2).collect()
+}
+
+// Builds a hull blueprint from the triangles of a closed convex mesh. Coplanar triangles are
+// merged into one polygonal side with the color of the first of them; every side gets an
+// inward normal and its side index as portal id, so instances can connect through any of them.
+pub fn blueprint_from_triangles(
+    id: BlueprintId,
+    name: impl Into<String>,
+    triangles: &[ColoredTriangle],
+    options: &GltfImportOptions,
+) -> Result<HullBlueprint, GltfImportError> {
+    let mut vertices: Vec<Vec3> = Vec::new();
+    let mut groups: Vec<FaceGroup> = Vec::new();
+    for triangle in triangles {
+        let [a, b, c] = triangle.corners;
+        // Slivers have no reliable normal; their corners still belong to the neighbouring sides.
+        let outward = match (b - a).cross(c - a).try_normalize() {
+            Some(normal) if (b - a).cross(c - a).length() * 0.5 > options.weld_tolerance * options.weld_tolerance => normal,
+            _ => continue,
+        };
+        let corners = [a, b, c].map(|corner| weld(&mut vertices, corner, options.weld_tolerance));
+        let existing = groups.iter_mut().find(|group| {
+            group.outward.dot(outward) > 1.0 - options.coplanar_tolerance
+                && [a, b, c].iter().all(|corner| (group.outward.dot(*corner) - group.distance).abs() <= options.coplanar_tolerance)
+        });
+        match existing {
+            Some(group) => group.vertex_indices.extend(corners),
+            None => groups.push(FaceGroup { outward, distance: outward.dot(a), vertex_indices: corners.to_vec(), color: triangle.color }),
+        }
+    }
+    if groups.is_empty() {
+        return Err(GltfImportError::NoTriangles);
+    }
+    if let Some(&point) = vertices.iter()
+        .find(|vertex| groups.iter().any(|group| group.outward.dot(**vertex) - group.distance > options.coplanar_tolerance))
+    {
+        return Err(GltfImportError::NotConvex { point });
+    }
+
+    // Only corners that end up on some outline are kept.
+    let outlines: Vec<Vec<usize>> = groups.iter().map(|group| face_outline(&vertices, group, options.weld_tolerance)).collect();
+    let mut remap: Vec<Option<usize>> = vec![None; vertices.len()];
+    let mut local_vertices = Vec::new();
+    let mut sides = Vec::with_capacity(groups.len());
+    for (group, outline) in groups.iter().zip(&outlines) {
+        if outline.len() < 3 {
+            continue;
+        }
+        // Blueprint sides wind clockwise around their inward normal, i.e. counter-clockwise
+        // seen from outside, which is how the outline runs.
+        let vertex_indices = outline.iter().map(|&index| {
+            *remap[index].get_or_insert_with(|| {
+                local_vertices.push(vertices[index]);
+                local_vertices.len() - 1
+            })
+        }).collect();
+        let config = HandlerConfig::StandardWall { color: group.color, texture_id: None, emissive: 0.0, render_priority: 0 };
+        sides.push(BlueprintSide {
+            vertex_indices,
+            local_normal: -group.outward,
+            handler_type: config.get_intended_handler_type(),
+            default_handler_config: config,
+            local_portal_id: Some(sides.len() as PortalId),
+            render_layers: RENDER_LAYER_DEFAULT,
+        });
+    }
+
+    let blueprint = HullBlueprint { id, name: name.into(), local_vertices, sides };
+    blueprint.validate().map_err(GltfImportError::InvalidHull)?;
+    Ok(blueprint)
+}
+
+// Reads a convex mesh from a .gltf or .glb file, with the transforms of the node using it
+// applied, as a hull blueprint named after the mesh (or the file, for unnamed meshes). Textures are not loaded; sides take their
+// material's base color.
+pub fn load_gltf_blueprint(path: &Path, id: BlueprintId, options: &GltfImportOptions) -> Result<HullBlueprint, GltfImportError> {
+    let gltf::Gltf { document, blob } = gltf::Gltf::open(path)?;
+    let buffers = gltf::import_buffers(&document, path.parent(), blob)?;
+    let (mesh_name, triangles) = collect_triangles(&document, &buffers, options.mesh_name.as_deref())?;
+    let name = mesh_name
+        .or_else(|| path.file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+        .unwrap_or_else(|| "GltfBlueprint".to_string());
+    blueprint_from_triangles(id, name, &triangles, options)
+}
+
+// The triangles of every mesh in a .gltf or .glb file, placed by the nodes using them; in files
+// without scenes, of every mesh as stored.
+pub fn load_gltf_triangles(path: &Path) -> Result<Vec<ColoredTriangle>, GltfImportError> {
+    let gltf::Gltf { document, blob } = gltf::Gltf::open(path)?;
+    let buffers = gltf::import_buffers(&document, path.parent(), blob)?;
+    let mut meshes = mesh_nodes(&document);
+    if meshes.is_empty() {
+        meshes = document.meshes().map(|mesh| (mesh, Mat4::IDENTITY)).collect();
+    }
+    if meshes.is_empty() {
+        return Err(GltfImportError::MeshNotFound(None));
+    }
+    Ok(meshes.iter().flat_map(|(mesh, transform)| mesh_triangles(mesh, *transform, &buffers)).collect())
+}
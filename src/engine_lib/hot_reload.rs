@@ -0,0 +1,71 @@
+// src/engine_lib/hot_reload.rs
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use crate::engine_lib::scene_types::Scene;
+
+// Builds a scene from a file. The engine has no scene format of its own, so whoever watches a
+// file supplies the parser.
+pub type SceneLoader = fn(&Path) -> Result<Scene, String>;
+
+// Notices when a file on disk changes by polling its modification time, which is cheap enough to
+// do every frame and needs no platform watcher.
+#[derive(Clone, Debug)]
+pub struct FileWatcher {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl FileWatcher {
+    // Starts watching `path`; its current state does not count as a change.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let last_modified = modified_time(&path);
+        Self { path, last_modified }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // True once for each change since the last poll. A file that is missing is not reported
+    // until it is written again, so editors that save by deleting and recreating are fine.
+    pub fn poll(&mut self) -> bool {
+        let modified = modified_time(&self.path);
+        if modified == self.last_modified {
+            return false;
+        }
+        self.last_modified = modified;
+        modified.is_some()
+    }
+}
+
+// Carries what should survive a reload from `old` into a freshly loaded `new` scene: event
+// subscriptions, and where each camera is, as long as its host instance still exists. Cameras
+// the new scene defines keep their settings from the file and only take over the old pose;
+// cameras it does not define, such as ones added at runtime, are carried over whole. The
+// active camera stays active if the new scene still has it.
+pub fn carry_over_runtime_state(old: &mut Scene, new: &mut Scene) {
+    new.events = std::mem::take(&mut old.events);
+    for (name, camera) in &old.cameras {
+        if !new.instances.contains_key(&camera.host_instance_id) {
+            continue;
+        }
+        match new.cameras.get_mut(name) {
+            Some(reloaded) => {
+                reloaded.host_instance_id = camera.host_instance_id;
+                reloaded.local_transform = camera.local_transform;
+            }
+            None => {
+                new.cameras.insert(name.clone(), camera.clone());
+            }
+        }
+    }
+    if new.cameras.contains_key(&old.active_camera) {
+        new.active_camera = old.active_camera.clone();
+    }
+}
@@ -0,0 +1,352 @@
+// src/engine_lib/hull_split.rs
+
+use std::fmt;
+use glam::Vec3;
+use crate::engine_lib::events::EngineEvent;
+use crate::engine_lib::scene_types::{
+    Scene, HullBlueprint, BlueprintSide, HullInstance, HandlerConfig, LabelAnchor, PortalConnectionInfo,
+    BlueprintId, InstanceId, PortalId, SideIndex, RENDER_LAYER_DEFAULT,
+};
+use crate::rendering_lib::geometry::clip_polygon_3d_by_plane;
+
+// Points closer than this to the cutting plane lie on it, and corners closer than this to each
+// other are one vertex.
+const SPLIT_EPSILON: f32 = 1e-4;
+// Pieces of sides with less area than this, in squared engine units, are dropped.
+const MIN_PIECE_AREA: f32 = 1e-6;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SplitError {
+    MissingInstance(InstanceId),
+    MissingBlueprint(BlueprintId),
+    // The plane does not pass through the hull's interior, so one part would be empty.
+    MissesHull,
+    // The plane crosses a side leading into another hull. A portal cannot be cut, because the
+    // side it leads to would have to be cut to match.
+    CutsPortal { side_index: SideIndex },
+    // Every portal id of the blueprint is taken, leaving none for the new portal.
+    NoFreePortalId,
+    NoFreeInstanceId,
+    NoFreeBlueprintId,
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitError::MissingInstance(instance_id) => write!(f, "instance {} does not exist", instance_id),
+            SplitError::MissingBlueprint(blueprint_id) => write!(f, "blueprint {} does not exist", blueprint_id),
+            SplitError::MissesHull => write!(f, "plane does not pass through the hull"),
+            SplitError::CutsPortal { side_index } => write!(f, "plane cuts through portal side {}", side_index),
+            SplitError::NoFreePortalId => write!(f, "no free portal id"),
+            SplitError::NoFreeInstanceId => write!(f, "no free instance id"),
+            SplitError::NoFreeBlueprintId => write!(f, "no free blueprint id"),
+        }
+    }
+}
+
+impl std::error::Error for SplitError {}
+
+// The two parts of a split blueprint, behind the plane and in front of it (where its normal
+// points), with the side each of their sides was cut from; None for the new side on the plane.
+struct SplitParts {
+    parts: [HullBlueprint; 2],
+    origins: [Vec<Option<SideIndex>>; 2],
+    portal_id: PortalId,
+}
+
+// Configs that lead into another hull, which a cut would leave leading into half a portal.
+fn leads_elsewhere(config: &HandlerConfig) -> bool {
+    matches!(config, HandlerConfig::StandardPortal { .. } | HandlerConfig::Door { .. } | HandlerConfig::NonEuclideanPortal { .. } | HandlerConfig::LiquidSurface { .. })
+}
+
+fn polygon_area(polygon: &[Vec3]) -> f32 {
+    let mut normal = Vec3::ZERO;
+    for (i, current) in polygon.iter().enumerate() {
+        normal += current.cross(polygon[(i + 1) % polygon.len()]);
+    }
+    normal.length() * 0.5
+}
+
+fn weld(vertices: &mut Vec<Vec3>, point: Vec3) -> usize {
+    match vertices.iter().position(|vertex| vertex.distance(point) <= SPLIT_EPSILON) {
+        Some(index) => index,
+        None => {
+            vertices.push(point);
+            vertices.len() - 1
+        }
+    }
+}
+
+// Drops corners repeating the one before, as clipping through a corner leaves behind.
+fn without_repeats(polygon: Vec<Vec3>) -> Vec<Vec3> {
+    let mut cleaned: Vec<Vec3> = Vec::with_capacity(polygon.len());
+    for point in polygon {
+        if cleaned.last().is_none_or(|last| last.distance(point) > SPLIT_EPSILON) {
+            cleaned.push(point);
+        }
+    }
+    while cleaned.len() > 1 && cleaned[0].distance(cleaned[cleaned.len() - 1]) <= SPLIT_EPSILON {
+        cleaned.pop();
+    }
+    cleaned
+}
+
+// Cuts `blueprint` along the plane through `point` facing `normal`, both in blueprint space.
+// `configs` are the configs in effect for its sides, checked for portals the plane crosses.
+fn split_parts(blueprint: &HullBlueprint, normal: Vec3, point: Vec3, configs: &[&HandlerConfig]) -> Result<SplitParts, SplitError> {
+    let normal = normal.normalize_or_zero();
+    if normal == Vec3::ZERO {
+        return Err(SplitError::MissesHull);
+    }
+    let distance = |p: Vec3| normal.dot(p - point);
+    let vertex_distances: Vec<f32> = blueprint.local_vertices.iter().map(|&v| distance(v)).collect();
+    if !vertex_distances.iter().any(|&d| d > SPLIT_EPSILON) || !vertex_distances.iter().any(|&d| d < -SPLIT_EPSILON) {
+        return Err(SplitError::MissesHull);
+    }
+    for (side_index, side) in blueprint.sides.iter().enumerate() {
+        let side_distances = || side.vertex_indices.iter().filter_map(|&i| vertex_distances.get(i));
+        let crossed = side_distances().any(|&d| d > SPLIT_EPSILON) && side_distances().any(|&d| d < -SPLIT_EPSILON);
+        if crossed && configs.get(side_index).is_some_and(|config| leads_elsewhere(config)) {
+            return Err(SplitError::CutsPortal { side_index });
+        }
+    }
+    let portal_id = blueprint.sides.iter().filter_map(|side| side.local_portal_id).max()
+        .map_or(Some(0), |max_id| max_id.checked_add(1))
+        .ok_or(SplitError::NoFreePortalId)?;
+
+    let mut parts: [HullBlueprint; 2] = ["back", "front"].map(|suffix| HullBlueprint {
+        id: blueprint.id,
+        name: format!("{}_{}", blueprint.name, suffix),
+        local_vertices: Vec::new(),
+        sides: Vec::new(),
+    });
+    let mut origins: [Vec<Option<SideIndex>>; 2] = [Vec::new(), Vec::new()];
+    for (part, keep_toward) in [(0, -normal), (1, normal)] {
+        let mut cap_corners: Vec<Vec3> = Vec::new();
+        for (side_index, side) in blueprint.sides.iter().enumerate() {
+            let polygon: Vec<Vec3> = side.vertex_indices.iter().filter_map(|&i| blueprint.local_vertices.get(i).copied()).collect();
+            let piece = without_repeats(clip_polygon_3d_by_plane(&polygon, &(keep_toward, point)));
+            if piece.len() < 3 || polygon_area(&piece) <= MIN_PIECE_AREA {
+                continue;
+            }
+            for &corner in piece.iter().filter(|&&corner| distance(corner).abs() <= SPLIT_EPSILON) {
+                weld(&mut cap_corners, corner);
+            }
+            let blueprint_part = &mut parts[part];
+            let vertex_indices = piece.iter().map(|&corner| weld(&mut blueprint_part.local_vertices, corner)).collect();
+            blueprint_part.sides.push(BlueprintSide { vertex_indices, ..side.clone() });
+            origins[part].push(Some(side_index));
+        }
+        if cap_corners.len() < 3 {
+            return Err(SplitError::MissesHull);
+        }
+
+        // The new side wound counter-clockwise seen from outside, which is along `keep_toward`
+        // flipped: the other part.
+        let center = cap_corners.iter().copied().sum::<Vec3>() / cap_corners.len() as f32;
+        let outward = -keep_toward;
+        let u = (cap_corners[0] - center).normalize_or_zero();
+        let v = outward.cross(u);
+        let angle = |corner: &Vec3| (*corner - center).dot(v).atan2((*corner - center).dot(u));
+        cap_corners.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+        let blueprint_part = &mut parts[part];
+        let vertex_indices = cap_corners.iter().map(|&corner| weld(&mut blueprint_part.local_vertices, corner)).collect();
+        blueprint_part.sides.push(BlueprintSide {
+            vertex_indices,
+            local_normal: keep_toward,
+            handler_type: HandlerConfig::None.get_intended_handler_type(),
+            default_handler_config: HandlerConfig::None,
+            local_portal_id: Some(portal_id),
+            render_layers: RENDER_LAYER_DEFAULT,
+        });
+        origins[part].push(None);
+    }
+    Ok(SplitParts { parts, origins, portal_id })
+}
+
+// Cuts a blueprint in two along the plane through `point` facing `normal` (in blueprint
+// space), returning the part behind the plane and the part in front of it, both in the
+// original's space. Each part closes the cut with a new side carrying the same fresh portal
+// id and no config, ready to be connected; every other side keeps its config and portal id,
+// and sides the plane crosses keep them on both pieces. Both parts keep the original's id.
+pub fn split_blueprint(blueprint: &HullBlueprint, normal: Vec3, point: Vec3) -> Result<(HullBlueprint, HullBlueprint), SplitError> {
+    let configs: Vec<&HandlerConfig> = blueprint.sides.iter().map(|side| &side.default_handler_config).collect();
+    let [back, front] = split_parts(blueprint, normal, point, &configs)?.parts;
+    Ok((back, front))
+}
+
+// Points the config at another instance, keeping everything else about it.
+fn redirected(config: &HandlerConfig, instance_id: InstanceId) -> HandlerConfig {
+    let mut config = config.clone();
+    match &mut config {
+        HandlerConfig::StandardPortal { target_instance_id, .. }
+        | HandlerConfig::Door { target_instance_id, .. }
+        | HandlerConfig::LiquidSurface { target_instance_id, .. }
+        | HandlerConfig::NonEuclideanPortal { target_instance_id, .. } => *target_instance_id = instance_id,
+        _ => {}
+    }
+    config
+}
+
+fn config_target(config: &HandlerConfig) -> Option<(InstanceId, PortalId)> {
+    match config {
+        HandlerConfig::StandardPortal { target_instance_id, target_portal_id, .. }
+        | HandlerConfig::Door { target_instance_id, target_portal_id, .. }
+        | HandlerConfig::LiquidSurface { target_instance_id, target_portal_id, .. }
+        | HandlerConfig::NonEuclideanPortal { target_instance_id, target_portal_id, .. } => Some((*target_instance_id, *target_portal_id)),
+        _ => None,
+    }
+}
+
+impl Scene {
+    // Cuts an instance in two along the plane through `point` facing `normal`, both in its
+    // blueprint space, and joins the parts with a pair of portals on the cut. The instance keeps
+    // the part behind the plane; the part in front becomes a new instance with the same
+    // placement and parent, whose id is returned. Each part gets a blueprint of its own, so
+    // other instances of the old blueprint are untouched.
+    //
+    // Portals into the front part are redirected to the new instance. Cameras, entities, sound
+    // emitters and labels in front of the plane move across with it, and trigger volumes are
+    // copied to both parts. Sides the plane crosses cannot lead into other hulls. Nothing
+    // changes on error.
+    pub fn split_instance(&mut self, instance_id: InstanceId, normal: Vec3, point: Vec3) -> Result<InstanceId, SplitError> {
+        let instance = self.instances.get(&instance_id).ok_or(SplitError::MissingInstance(instance_id))?;
+        let blueprint = self.blueprints.get(&instance.blueprint_id).ok_or(SplitError::MissingBlueprint(instance.blueprint_id))?;
+        let configs: Vec<&HandlerConfig> = (0..blueprint.sides.len())
+            .map(|side_index| self.side_config(instance_id, side_index).expect("side exists"))
+            .collect();
+        let SplitParts { parts, origins, portal_id } = split_parts(blueprint, normal, point, &configs)?;
+
+        let new_id = self.next_instance_id().ok_or(SplitError::NoFreeInstanceId)?;
+        let back_blueprint_id = self.blueprints.keys().max().map_or(Some(0), |max_id| max_id.checked_add(1)).ok_or(SplitError::NoFreeBlueprintId)?;
+        let front_blueprint_id = back_blueprint_id.checked_add(1).ok_or(SplitError::NoFreeBlueprintId)?;
+        let normal = normal.normalize();
+        let in_front = |position: Vec3| normal.dot(position - point) > 0.0;
+
+        // The part and side each old side ended up as; a crossed side ends up in both parts and
+        // counts as the back piece.
+        let mut new_sides: Vec<Vec<(usize, SideIndex)>> = vec![Vec::new(); blueprint.sides.len()];
+        for (part, part_origins) in origins.iter().enumerate() {
+            for (new_index, origin) in part_origins.iter().enumerate() {
+                if let Some(old_index) = origin {
+                    new_sides[*old_index].push((part, new_index));
+                }
+            }
+        }
+        let front_portals: Vec<PortalId> = blueprint.sides.iter().zip(&new_sides)
+            .filter(|(_, placed)| !placed.is_empty() && placed.iter().all(|&(part, _)| part == 1))
+            .filter_map(|(side, _)| side.local_portal_id)
+            .collect();
+
+        let old_instance = self.instances.remove(&instance_id).expect("checked above");
+        let mut halves: [HullInstance; 2] = [
+            HullInstance { blueprint_id: back_blueprint_id, ..old_instance.clone() },
+            HullInstance { id: new_id, name: format!("{}_{}", old_instance.name, new_id), blueprint_id: front_blueprint_id, ..old_instance.clone() },
+        ];
+        for half in &mut halves {
+            half.portal_connections.clear();
+            half.instance_side_handler_configs.clear();
+            half.side_animations.clear();
+        }
+        for (old_index, placed) in new_sides.iter().enumerate() {
+            for &(part, new_index) in placed {
+                if let Some(config) = old_instance.instance_side_handler_configs.get(&old_index) {
+                    halves[part].instance_side_handler_configs.insert(new_index, config.clone());
+                }
+                if let Some(animation) = old_instance.side_animations.get(&old_index) {
+                    halves[part].side_animations.insert(new_index, animation.clone());
+                }
+            }
+        }
+        for (&old_portal_id, connection) in &old_instance.portal_connections {
+            let part = if front_portals.contains(&old_portal_id) { 1 } else { 0 };
+            halves[part].portal_connections.insert(old_portal_id, connection.clone());
+        }
+
+        let [mut back, mut front] = parts;
+        back.id = back_blueprint_id;
+        front.id = front_blueprint_id;
+        let half_ids = [instance_id, new_id];
+        for (part, part_blueprint) in [&mut back, &mut front].into_iter().enumerate() {
+            let other_id = half_ids[1 - part];
+            let cap = part_blueprint.sides.last_mut().expect("split always adds a side");
+            cap.default_handler_config = HandlerConfig::portal(other_id, portal_id);
+            cap.handler_type = cap.default_handler_config.get_intended_handler_type();
+            halves[part].portal_connections.insert(portal_id, PortalConnectionInfo { target_instance_id: other_id, target_portal_id: portal_id });
+        }
+        self.blueprints.insert(back_blueprint_id, back);
+        self.blueprints.insert(front_blueprint_id, front);
+        let [back_instance, front_instance] = halves;
+        self.instances.insert(instance_id, back_instance);
+        self.instances.insert(new_id, front_instance);
+        self.retire_instance_id(new_id);
+
+        // Everything leading into a portal that moved to the front part, including portals of
+        // this instance leading back into itself.
+        let mut redirects: Vec<(InstanceId, SideIndex, HandlerConfig)> = Vec::new();
+        for instance in self.instances.values() {
+            let sides = self.blueprints.get(&instance.blueprint_id).map_or(0, |blueprint| blueprint.sides.len());
+            for side_index in 0..sides {
+                let config = self.side_config(instance.id, side_index).expect("side exists");
+                if config_target(config).is_some_and(|(target, target_portal)| target == instance_id && front_portals.contains(&target_portal)) {
+                    redirects.push((instance.id, side_index, redirected(config, new_id)));
+                }
+            }
+        }
+        for (redirected_id, side_index, config) in redirects {
+            let instance = self.instances.get_mut(&redirected_id).expect("collected above");
+            instance.instance_side_handler_configs.insert(side_index, config);
+        }
+        for instance in self.instances.values_mut() {
+            for connection in instance.portal_connections.values_mut() {
+                if connection.target_instance_id == instance_id && front_portals.contains(&connection.target_portal_id) {
+                    connection.target_instance_id = new_id;
+                }
+            }
+        }
+
+        for camera in self.cameras.values_mut() {
+            if camera.host_instance_id == instance_id && in_front(camera.local_transform.w_axis.truncate()) {
+                camera.host_instance_id = new_id;
+            }
+        }
+        for entity in self.entities.values_mut() {
+            if entity.host_instance_id == instance_id && in_front(entity.local_transform.w_axis.truncate()) {
+                entity.host_instance_id = new_id;
+            }
+        }
+        for emitter in self.sound_emitters.values_mut() {
+            if emitter.host_instance_id == instance_id && in_front(emitter.local_position) {
+                emitter.host_instance_id = new_id;
+            }
+        }
+        for decal in self.decals.values_mut() {
+            if decal.instance_id != instance_id {
+                continue;
+            }
+            // A side cut in two keeps its decal on the half it is centered on.
+            let part = usize::from(in_front(decal.local_position));
+            let placed = new_sides.get(decal.side_index).map_or(&[][..], Vec::as_slice);
+            if let Some(&(part, new_index)) = placed.iter().find(|(placed_part, _)| *placed_part == part).or(placed.first()) {
+                decal.instance_id = half_ids[part];
+                decal.side_index = new_index;
+            }
+        }
+        for label in &mut self.debug_labels {
+            match &mut label.anchor {
+                LabelAnchor::Side { instance_id: anchor_id, side_index } if *anchor_id == instance_id => {
+                    if let Some(&(part, new_index)) = new_sides.get(*side_index).and_then(|placed| placed.first()) {
+                        *anchor_id = half_ids[part];
+                        *side_index = new_index;
+                    }
+                }
+                LabelAnchor::Point { instance_id: anchor_id, local_position } if *anchor_id == instance_id && in_front(*local_position) => {
+                    *anchor_id = new_id;
+                }
+                _ => {}
+            }
+        }
+        self.events.publish(EngineEvent::InstanceSpawned { instance_id: new_id });
+        Ok(new_id)
+    }
+}
@@ -0,0 +1,45 @@
+// src/engine_lib/interaction.rs
+
+use crate::engine_lib::picking::RayHit;
+use crate::engine_lib::scene_types::{Scene, HandlerConfig, InstanceId, SideIndex};
+
+// How close, in the camera's units, something has to be to be acted on.
+pub const INTERACTION_REACH: f32 = 2.0;
+
+// Something the camera is looking at that it can act on, offered by the HUD's prompt.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interaction {
+    OpenDoor { instance_id: InstanceId, side_index: SideIndex },
+}
+
+impl Interaction {
+    pub fn prompt(&self) -> &'static str {
+        match self {
+            Interaction::OpenDoor { .. } => "Open door",
+        }
+    }
+
+    // Returns false if it no longer applies, e.g. the door has gone.
+    pub fn perform(&self, scene: &mut Scene) -> bool {
+        match *self {
+            Interaction::OpenDoor { instance_id, side_index } => scene.set_door_state(instance_id, side_index, true),
+        }
+    }
+}
+
+// What can be done with the side a ray from the camera hit, if it is within `reach`. Open doors
+// are not hit, as rays pass through them.
+pub fn interaction_at(scene: &Scene, hit: &RayHit, reach: f32) -> Option<Interaction> {
+    if hit.distance > reach {
+        return None;
+    }
+    let instance = scene.instances.get(&hit.instance_id)?;
+    let config = match instance.instance_side_handler_configs.get(&hit.side_index) {
+        Some(config) => config,
+        None => &scene.blueprints.get(&instance.blueprint_id)?.sides.get(hit.side_index)?.default_handler_config,
+    };
+    match config {
+        HandlerConfig::Door { open: false, .. } => Some(Interaction::OpenDoor { instance_id: hit.instance_id, side_index: hit.side_index }),
+        _ => None,
+    }
+}
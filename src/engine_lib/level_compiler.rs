@@ -0,0 +1,277 @@
+// src/engine_lib/level_compiler.rs
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use glam::{DVec3, Mat4, Vec3};
+use crate::engine_lib::convex_cells::{hulls_from_cells, scene_from_hulls, CellHulls, ConvexCell};
+use crate::engine_lib::csg::{ConvexSolid, CsgPlane};
+use crate::engine_lib::gltf_import::{load_gltf_triangles, ColoredTriangle};
+use crate::engine_lib::scene_types::{Scene, SceneCamera, HandlerConfig, InstanceId, DEFAULT_CAMERA_COLLISION_RADIUS};
+
+// How many of a node's planes are tried as its splitter. More gives fewer cells but compiles
+// slower on big meshes.
+const MAX_SPLITTER_CANDIDATES: usize = 32;
+// A polygon cut by a splitter costs this much more than one more polygon on the heavier side.
+const SPLIT_COST: usize = 8;
+// The region the cells are cut from is the mesh's bounding box grown by this much.
+const BOUNDS_MARGIN: f64 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelCompileOptions {
+    // Distance within which points count as on a plane, and planes as the same.
+    pub plane_epsilon: f64,
+    // Corners of a hull closer than this are merged into one vertex.
+    pub weld_tolerance: f32,
+}
+
+impl Default for LevelCompileOptions {
+    fn default() -> Self {
+        Self { plane_epsilon: 1e-4, weld_tolerance: 1e-4 }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LevelCompileError {
+    NoTriangles,
+    // Edges used by only one triangle, or not matched by a triangle running the other way, so
+    // the mesh does not separate inside from outside.
+    OpenMesh { open_edges: usize },
+    // The mesh is closed but faces inwards, or encloses no volume.
+    NoCells,
+}
+
+impl fmt::Display for LevelCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelCompileError::NoTriangles => write!(f, "level mesh has no triangles"),
+            LevelCompileError::OpenMesh { open_edges } => write!(f, "level mesh is not closed: {} edges have no matching edge", open_edges),
+            LevelCompileError::NoCells => write!(f, "level mesh encloses no space; are its faces pointing inwards?"),
+        }
+    }
+}
+
+impl std::error::Error for LevelCompileError {}
+
+// A convex piece of the mesh surface lying in `planes[plane]`.
+struct BspPolygon {
+    points: Vec<DVec3>,
+    plane: usize,
+}
+
+// One mesh plane: all triangles within epsilon of it that face the same way share it.
+struct MeshPlane {
+    normal: DVec3,
+    distance: f64,
+    config: HandlerConfig,
+}
+
+enum Side {
+    Front,
+    Back,
+    On,
+    Spanning,
+}
+
+fn classify(polygon: &BspPolygon, normal: DVec3, distance: f64, epsilon: f64) -> Side {
+    let (mut front, mut back) = (false, false);
+    for point in &polygon.points {
+        let offset = normal.dot(*point) - distance;
+        front |= offset > epsilon;
+        back |= offset < -epsilon;
+    }
+    match (front, back) {
+        (true, true) => Side::Spanning,
+        (true, false) => Side::Front,
+        (false, true) => Side::Back,
+        (false, false) => Side::On,
+    }
+}
+
+// Cuts a convex polygon into its parts in front of and behind the plane.
+fn split(polygon: &BspPolygon, normal: DVec3, distance: f64, epsilon: f64) -> (BspPolygon, BspPolygon) {
+    let mut front = Vec::new();
+    let mut back = Vec::new();
+    let count = polygon.points.len();
+    for i in 0..count {
+        let (current, next) = (polygon.points[i], polygon.points[(i + 1) % count]);
+        let (current_offset, next_offset) = (normal.dot(current) - distance, normal.dot(next) - distance);
+        if current_offset >= -epsilon {
+            front.push(current);
+        }
+        if current_offset <= epsilon {
+            back.push(current);
+        }
+        if (current_offset > epsilon && next_offset < -epsilon) || (current_offset < -epsilon && next_offset > epsilon) {
+            let crossing = current + (next - current) * (current_offset / (current_offset - next_offset));
+            front.push(crossing);
+            back.push(crossing);
+        }
+    }
+    (BspPolygon { points: front, plane: polygon.plane }, BspPolygon { points: back, plane: polygon.plane })
+}
+
+// Merges coplanar, same-facing triangles onto shared planes. Slivers are dropped.
+fn mesh_polygons(triangles: &[ColoredTriangle], epsilon: f64) -> (Vec<MeshPlane>, Vec<BspPolygon>) {
+    let mut planes: Vec<MeshPlane> = Vec::new();
+    let mut polygons = Vec::with_capacity(triangles.len());
+    for triangle in triangles {
+        let [a, b, c] = triangle.corners.map(|corner| corner.as_dvec3());
+        let normal = match (b - a).cross(c - a).try_normalize() {
+            Some(normal) if (b - a).cross(c - a).length() * 0.5 > epsilon * epsilon => normal,
+            _ => continue,
+        };
+        let distance = normal.dot(a);
+        let plane = match planes.iter().position(|plane| plane.normal.dot(normal) >= 1.0 - epsilon && (plane.distance - distance).abs() <= epsilon) {
+            Some(index) => index,
+            None => {
+                planes.push(MeshPlane { normal, distance, config: HandlerConfig::StandardWall { color: triangle.color, texture_id: None, emissive: 0.0, render_priority: 0 } });
+                planes.len() - 1
+            }
+        };
+        polygons.push(BspPolygon { points: vec![a, b, c], plane });
+    }
+    (planes, polygons)
+}
+
+// Number of directed edges not matched by one running the other way, with corners compared on
+// a grid of `tolerance`.
+fn open_edge_count(triangles: &[ColoredTriangle], tolerance: f32) -> usize {
+    let key = |point: Vec3| (point / tolerance).round().as_ivec3().to_array();
+    let mut edges: HashMap<([i32; 3], [i32; 3]), i32> = HashMap::new();
+    for triangle in triangles {
+        let corners = triangle.corners.map(key);
+        for i in 0..3 {
+            let (start, end) = (corners[i], corners[(i + 1) % 3]);
+            if start == end {
+                continue;
+            }
+            // +1 one way, -1 the other; a closed surface cancels out.
+            if start < end {
+                *edges.entry((start, end)).or_default() += 1;
+            } else {
+                *edges.entry((end, start)).or_default() -= 1;
+            }
+        }
+    }
+    edges.values().map(|count| count.unsigned_abs() as usize).sum()
+}
+
+// The splitter leaving the fewest polygons cut and the two sides most even, among a spread of
+// the planes still in play.
+fn choose_splitter(planes: &[MeshPlane], polygons: &[BspPolygon], epsilon: f64) -> usize {
+    let mut candidates: Vec<usize> = polygons.iter().map(|polygon| polygon.plane).collect();
+    candidates.sort_unstable();
+    candidates.dedup();
+    let step = candidates.len().div_ceil(MAX_SPLITTER_CANDIDATES).max(1);
+    candidates.iter().step_by(step)
+        .copied()
+        .min_by_key(|&candidate| {
+            let plane = &planes[candidate];
+            let (mut front, mut back, mut spanning) = (0usize, 0usize, 0usize);
+            for polygon in polygons {
+                match classify(polygon, plane.normal, plane.distance, epsilon) {
+                    Side::Front => front += 1,
+                    Side::Back => back += 1,
+                    Side::Spanning => spanning += 1,
+                    Side::On => {}
+                }
+            }
+            spanning * SPLIT_COST + front.abs_diff(back)
+        })
+        .unwrap_or(polygons[0].plane)
+}
+
+// Splits space by the mesh's own planes until no surface is left in any region (a solid-leaf
+// BSP). A region reached from behind its last plane is inside the mesh and becomes a cell,
+// bounded by the planes on its path; one reached from in front is outside and dropped.
+fn inside_regions(planes: &[MeshPlane], polygons: Vec<BspPolygon>, bounds: ConvexSolid, epsilon: f64) -> Vec<ConvexSolid> {
+    let mut regions = Vec::new();
+    let mut stack = vec![(polygons, bounds, false)];
+    while let Some((polygons, region, inside)) = stack.pop() {
+        if polygons.is_empty() {
+            if inside && region.has_volume() {
+                regions.push(region);
+            }
+            continue;
+        }
+        let splitter = choose_splitter(planes, &polygons, epsilon);
+        let plane = &planes[splitter];
+        let (mut front, mut back) = (Vec::new(), Vec::new());
+        for polygon in polygons {
+            match classify(&polygon, plane.normal, plane.distance, epsilon) {
+                Side::Front => front.push(polygon),
+                Side::Back => back.push(polygon),
+                // Faces in the splitter's plane, either way round, are accounted for by it.
+                Side::On => {}
+                Side::Spanning => {
+                    let (front_part, back_part) = split(&polygon, plane.normal, plane.distance, epsilon);
+                    front.push(front_part);
+                    back.push(back_part);
+                }
+            }
+        }
+        let behind = CsgPlane { normal: plane.normal, distance: plane.distance, config: plane.config.clone() };
+        let in_front = CsgPlane { normal: -plane.normal, distance: -plane.distance, config: plane.config.clone() };
+        let back_region = ConvexSolid::from_planes(region.planes.iter().cloned().chain([behind]).collect());
+        let front_region = ConvexSolid::from_planes(region.planes.into_iter().chain([in_front]).collect());
+        if back_region.has_volume() {
+            stack.push((back, back_region, true));
+        }
+        if front_region.has_volume() {
+            stack.push((front, front_region, false));
+        }
+    }
+    regions
+}
+
+// Compiles a closed mesh, wound counter-clockwise seen from outside like a glTF solid, into
+// convex cells of the space it encloses, with portals wherever cells touch. Each wall keeps the
+// color of the triangles it came from. The mesh is the boundary of the open space, so a level
+// is modeled as the inside of its rooms, as with .map brushes.
+pub fn compile_level(triangles: &[ColoredTriangle], options: &LevelCompileOptions) -> Result<CellHulls, LevelCompileError> {
+    if triangles.is_empty() {
+        return Err(LevelCompileError::NoTriangles);
+    }
+    let open_edges = open_edge_count(triangles, options.weld_tolerance);
+    if open_edges > 0 {
+        return Err(LevelCompileError::OpenMesh { open_edges });
+    }
+    let (planes, polygons) = mesh_polygons(triangles, options.plane_epsilon);
+    if polygons.is_empty() {
+        return Err(LevelCompileError::NoTriangles);
+    }
+
+    let (min, max) = triangles.iter().flat_map(|triangle| triangle.corners)
+        .fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), point| (min.min(point), max.max(point)));
+    let margin = Vec3::splat(BOUNDS_MARGIN as f32);
+    let bounds = ConvexSolid::cuboid(min - margin, max + margin, HandlerConfig::None);
+
+    let cells: Vec<ConvexCell> = inside_regions(&planes, polygons, bounds, options.plane_epsilon)
+        .iter()
+        .enumerate()
+        .map(|(index, region)| region.to_cell(format!("Cell{}", index)))
+        .collect();
+    if cells.is_empty() {
+        return Err(LevelCompileError::NoCells);
+    }
+    Ok(hulls_from_cells(&cells, options.weld_tolerance))
+}
+
+// Compiles every mesh of a .gltf or .glb file into a level, with the main camera at the center
+// of the biggest cell; in the shape `hot_reload::SceneLoader` expects.
+pub fn load_gltf_level(path: &Path) -> Result<Scene, String> {
+    let triangles = load_gltf_triangles(path).map_err(|e| e.to_string())?;
+    let hulls = compile_level(&triangles, &LevelCompileOptions::default()).map_err(|e| e.to_string())?;
+    let extent = |id: &InstanceId| {
+        let vertices = &hulls.blueprints[id].local_vertices;
+        let (min, max) = vertices.iter().fold((Vec3::INFINITY, Vec3::NEG_INFINITY), |(min, max), &v| (min.min(v), max.max(v)));
+        (max - min).max(Vec3::ZERO).element_product()
+    };
+    let host_instance_id = hulls.instances.keys()
+        .max_by(|a, b| extent(a).total_cmp(&extent(b)).then(b.cmp(a)))
+        .copied()
+        .unwrap_or(0);
+    let camera = SceneCamera { host_instance_id, local_transform: Mat4::IDENTITY, collision_radius: DEFAULT_CAMERA_COLLISION_RADIUS };
+    Ok(scene_from_hulls(hulls, camera))
+}
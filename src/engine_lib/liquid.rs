@@ -0,0 +1,45 @@
+// src/engine_lib/liquid.rs
+
+use crate::engine_lib::scene_types::{Scene, InstanceId};
+
+// A hull instance filled with water or another liquid. A camera inside it is submerged: the view
+// is seen through `tint`, moving is slowed and gravity weakened. The liquid's surface is a side
+// with `HandlerConfig::LiquidSurface` leading out of it, and the hull's own walls are drawn as
+// usual.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LiquidVolume {
+    // Color laid over the view while submerged; its alpha is how murky the liquid is.
+    pub tint: [f32; 4],
+    // Fraction of the camera's speed it keeps in the liquid, walking or flying.
+    pub movement_scale: f32,
+    // Multiplies gravity while walking: below 1 sinks slowly, below 0 floats up. Flying cameras
+    // have no gravity, so this, the drag and swimming up only affect walking ones.
+    pub gravity_scale: f32,
+    // Radians per second the surface ripples at; see `ripple_phase`.
+    pub ripple_speed: f32,
+    // Advanced by `update_liquids`, and read by `LiquidSurfaceHandler` to shimmer the surface tint.
+    pub ripple_phase: f32,
+}
+
+impl LiquidVolume {
+    pub fn water() -> Self {
+        Self { tint: [0.1, 0.35, 0.5, 0.45], movement_scale: 0.5, gravity_scale: 0.2, ripple_speed: 1.5, ripple_phase: 0.0 }
+    }
+}
+
+// Advances every liquid's ripples.
+pub fn update_liquids(scene: &mut Scene, dt: f32) {
+    for liquid in scene.instances.values_mut().filter_map(|instance| instance.liquid.as_mut()) {
+        liquid.ripple_phase = (liquid.ripple_phase + liquid.ripple_speed * dt).rem_euclid(std::f32::consts::TAU);
+    }
+}
+
+// The liquid filling `instance_id`, if any.
+pub fn liquid_in(scene: &Scene, instance_id: InstanceId) -> Option<&LiquidVolume> {
+    scene.instances.get(&instance_id)?.liquid.as_ref()
+}
+
+// The liquid the active camera is in, if any. It changes as the camera walks through portals.
+pub fn submerged_liquid(scene: &Scene) -> Option<&LiquidVolume> {
+    liquid_in(scene, scene.active_camera()?.host_instance_id)
+}
@@ -0,0 +1,355 @@
+// src/engine_lib/map_import.rs
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use glam::{DVec3, Mat4, Vec3};
+use crate::engine_lib::convex_cells::{polyhedron_from_planes, hulls_from_cells, scene_from_hulls, CellFace, CellHulls, ConvexCell};
+use crate::engine_lib::scene_types::{Scene, SceneCamera, DEFAULT_CAMERA_COLLISION_RADIUS, HandlerConfig, InstanceId};
+
+// Distance, in map units, within which brush planes meet and corners are the same point.
+const MAP_EPSILON: f64 = 1e-3;
+
+const SKY_CONFIG: HandlerConfig = HandlerConfig::Skybox {
+    zenith_color: [0.15, 0.35, 0.8, 1.0], horizon_color: [0.75, 0.85, 0.95, 1.0], nadir_color: [0.3, 0.3, 0.35, 1.0],
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapImportOptions {
+    // Engine units per map unit. Quake-scale maps use about 32 units per meter.
+    pub scale: f32,
+    // Corners of a hull closer than this, in engine units, are merged into one vertex.
+    pub weld_tolerance: f32,
+}
+
+impl Default for MapImportOptions {
+    fn default() -> Self {
+        Self { scale: 1.0 / 32.0, weld_tolerance: 1e-4 }
+    }
+}
+
+#[derive(Debug)]
+pub enum MapImportError {
+    Io(std::io::Error),
+    Syntax { line: usize, message: String },
+    // The map parsed but none of its brushes enclose a volume.
+    NoBrushes,
+}
+
+impl fmt::Display for MapImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapImportError::Io(e) => write!(f, "cannot read map: {}", e),
+            MapImportError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            MapImportError::NoBrushes => write!(f, "map has no usable brushes"),
+        }
+    }
+}
+
+impl std::error::Error for MapImportError {}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Symbol(char),
+    Quoted(String),
+    Word(String),
+}
+
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, MapImportError> {
+    let mut tokens = Vec::new();
+    for (line_index, line) in source.lines().enumerate() {
+        let line_number = line_index + 1;
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            match c {
+                _ if c.is_whitespace() => {}
+                '/' if line[start..].starts_with("//") => break,
+                '{' | '}' | '(' | ')' | '[' | ']' => tokens.push((Token::Symbol(c), line_number)),
+                '"' => {
+                    let text: String = chars.by_ref().map(|(_, c)| c).take_while(|&c| c != '"').collect();
+                    if !line[start + 1..].contains('"') {
+                        return Err(MapImportError::Syntax { line: line_number, message: "unterminated string".to_string() });
+                    }
+                    tokens.push((Token::Quoted(text), line_number));
+                }
+                _ => {
+                    let mut end = start + c.len_utf8();
+                    while let Some(&(index, next)) = chars.peek() {
+                        if next.is_whitespace() || "{}()[]\"".contains(next) {
+                            break;
+                        }
+                        end = index + next.len_utf8();
+                        chars.next();
+                    }
+                    tokens.push((Token::Word(line[start..end].to_string()), line_number));
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// A brush face's plane, in map space. Points with `normal.dot(p) > distance` are outside.
+struct BrushPlane {
+    normal: DVec3,
+    distance: f64,
+    texture: String,
+}
+
+struct MapBrush {
+    planes: Vec<BrushPlane>,
+    line: usize,
+}
+
+struct MapEntity {
+    properties: HashMap<String, String>,
+    brushes: Vec<MapBrush>,
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+}
+
+impl Parser {
+    fn line(&self) -> usize {
+        self.tokens.get(self.position).or(self.tokens.last()).map_or(1, |(_, line)| *line)
+    }
+
+    fn error(&self, message: impl Into<String>) -> MapImportError {
+        MapImportError::Syntax { line: self.line(), message: message.into() }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).map(|(token, _)| token.clone());
+        self.position += 1;
+        token
+    }
+
+    fn expect_symbol(&mut self, symbol: char) -> Result<(), MapImportError> {
+        match self.next() {
+            Some(Token::Symbol(c)) if c == symbol => Ok(()),
+            Some(other) => {
+                self.position -= 1;
+                Err(self.error(format!("expected '{}', found {:?}", symbol, other)))
+            }
+            None => Err(self.error(format!("expected '{}', found end of file", symbol))),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, MapImportError> {
+        match self.next() {
+            Some(Token::Word(word)) => word.parse().map_err(|_| {
+                self.position -= 1;
+                self.error(format!("expected a number, found '{}'", word))
+            }),
+            _ => {
+                self.position -= 1;
+                Err(self.error("expected a number"))
+            }
+        }
+    }
+
+    fn entity(&mut self) -> Result<MapEntity, MapImportError> {
+        self.expect_symbol('{')?;
+        let mut entity = MapEntity { properties: HashMap::new(), brushes: Vec::new() };
+        loop {
+            match self.peek() {
+                Some(Token::Symbol('}')) => {
+                    self.position += 1;
+                    return Ok(entity);
+                }
+                Some(Token::Symbol('{')) => entity.brushes.push(self.brush()?),
+                Some(Token::Quoted(key)) => {
+                    let key = key.clone();
+                    self.position += 1;
+                    match self.next() {
+                        Some(Token::Quoted(value)) => { entity.properties.insert(key, value); }
+                        _ => {
+                            self.position -= 1;
+                            return Err(self.error(format!("property '{}' has no value", key)));
+                        }
+                    }
+                }
+                Some(other) => return Err(self.error(format!("unexpected {:?} in entity", other))),
+                None => return Err(self.error("entity is not closed")),
+            }
+        }
+    }
+
+    fn brush(&mut self) -> Result<MapBrush, MapImportError> {
+        self.expect_symbol('{')?;
+        let line = self.line();
+        let mut planes = Vec::new();
+        loop {
+            match self.peek() {
+                Some(Token::Symbol('}')) => {
+                    self.position += 1;
+                    return Ok(MapBrush { planes, line });
+                }
+                Some(Token::Symbol('(')) => planes.push(self.face()?),
+                Some(Token::Word(word)) => return Err(self.error(format!("'{}' brushes are not supported", word))),
+                Some(other) => return Err(self.error(format!("unexpected {:?} in brush", other))),
+                None => return Err(self.error("brush is not closed")),
+            }
+        }
+    }
+
+    // `( x y z ) ( x y z ) ( x y z ) texture ...`. Texture alignment, in either the standard or
+    // the Valve 220 layout, is skipped.
+    fn face(&mut self) -> Result<BrushPlane, MapImportError> {
+        let line = self.line();
+        let mut points = [DVec3::ZERO; 3];
+        for point in &mut points {
+            self.expect_symbol('(')?;
+            *point = DVec3::new(self.number()?, self.number()?, self.number()?);
+            self.expect_symbol(')')?;
+        }
+        let texture = match self.next() {
+            Some(Token::Word(word)) | Some(Token::Quoted(word)) => word,
+            _ => {
+                self.position -= 1;
+                return Err(self.error("face has no texture"));
+            }
+        };
+        while !matches!(self.peek(), Some(Token::Symbol('(')) | Some(Token::Symbol('}')) | None) {
+            self.position += 1;
+        }
+        // The points run clockwise seen from outside the brush.
+        let normal = (points[2] - points[0]).cross(points[1] - points[0]).try_normalize()
+            .ok_or(MapImportError::Syntax { line, message: "face points are collinear".to_string() })?;
+        Ok(BrushPlane { normal, distance: normal.dot(points[0]), texture })
+    }
+}
+
+fn parse_map(source: &str) -> Result<Vec<MapEntity>, MapImportError> {
+    let mut parser = Parser { tokens: tokenize(source)?, position: 0 };
+    let mut entities = Vec::new();
+    while parser.peek().is_some() {
+        entities.push(parser.entity()?);
+    }
+    Ok(entities)
+}
+
+impl MapBrush {
+    fn contains(&self, point: DVec3) -> bool {
+        self.planes.iter().all(|plane| plane.normal.dot(point) - plane.distance <= MAP_EPSILON)
+    }
+
+    // The brush's corners and faces; see `polyhedron_from_planes`.
+    fn polyhedron(&self) -> (Vec<DVec3>, Vec<(usize, Vec<usize>)>) {
+        let planes: Vec<(DVec3, f64)> = self.planes.iter().map(|plane| (plane.normal, plane.distance)).collect();
+        polyhedron_from_planes(&planes, MAP_EPSILON)
+    }
+}
+
+// Map space is Z-up; the engine is Y-up with -Z forward.
+fn to_engine(point: DVec3, scale: f32) -> Vec3 {
+    Vec3::new(point.x as f32, point.z as f32, -point.y as f32) * scale
+}
+
+// Muted, stable color for a texture name, so surfaces that share a texture look alike.
+fn texture_color(texture: &str) -> [f32; 4] {
+    let hash = texture.bytes().fold(0x811c_9dc5u32, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x0100_0193));
+    let channel = |shift: u32| 0.35 + 0.5 * ((hash >> shift) & 0xff) as f32 / 255.0;
+    [channel(0), channel(8), channel(16), 1.0]
+}
+
+fn wall_config(texture: &str) -> HandlerConfig {
+    if texture.to_ascii_lowercase().starts_with("sky") {
+        SKY_CONFIG
+    } else {
+        HandlerConfig::StandardWall { color: texture_color(texture), texture_id: Some(texture.to_string()), emissive: 0.0, render_priority: 0 }
+    }
+}
+
+// Builds a scene from the text of a brush-based .map file (Quake, Half-Life/Valve 220 and
+// TrenchBroom's variants). Each brush of `worldspawn` and of `func_group`s becomes a hull
+// instance placed in world space, so a map is modeled as room volumes rather than solid walls.
+// Wherever two brushes touch face to face, the shared region becomes a pair of connected
+// portals and the rest of each face is cut into convex wall pieces; sky textures become
+// skybox sides. The main camera starts at `info_player_start`, or in the first brush.
+pub fn import_map(source: &str, options: &MapImportOptions) -> Result<Scene, MapImportError> {
+    let entities = parse_map(source)?;
+    let mut brushes: Vec<&MapBrush> = Vec::new();
+    for entity in &entities {
+        match entity.properties.get("classname").map(String::as_str) {
+            Some("worldspawn") | Some("func_group") => brushes.extend(&entity.brushes),
+            Some(classname) if !entity.brushes.is_empty() => log::debug!("Map: skipping brushes of '{}'", classname),
+            _ => {}
+        }
+    }
+
+    let mut cells: Vec<ConvexCell> = Vec::new();
+    let mut kept_brushes: Vec<&MapBrush> = Vec::new();
+    for brush in brushes {
+        let (corners, brush_faces) = brush.polyhedron();
+        if brush_faces.len() < 4 {
+            log::warn!("Map: brush at line {} encloses no volume", brush.line);
+            continue;
+        }
+        let faces = brush_faces.into_iter()
+            .map(|(plane_index, corner_indices)| {
+                let plane = &brush.planes[plane_index];
+                CellFace {
+                    outward: to_engine(plane.normal, 1.0).normalize(),
+                    polygon: corner_indices.iter().map(|&i| to_engine(corners[i], options.scale)).collect(),
+                    config: wall_config(&plane.texture),
+                }
+            })
+            .collect();
+        cells.push(ConvexCell { name: format!("Brush{}", kept_brushes.len()), faces });
+        kept_brushes.push(brush);
+    }
+    if kept_brushes.is_empty() {
+        return Err(MapImportError::NoBrushes);
+    }
+
+    let hulls = hulls_from_cells(&cells, options.weld_tolerance);
+    let camera = player_start_camera(&entities, &kept_brushes, &hulls, options)
+        .unwrap_or(SceneCamera {
+            host_instance_id: 0,
+            local_transform: Mat4::IDENTITY,
+            collision_radius: DEFAULT_CAMERA_COLLISION_RADIUS,
+        });
+    Ok(scene_from_hulls(hulls, camera))
+}
+
+// The first `info_player_start` inside a brush, facing its `angle` (degrees counter-clockwise
+// from map +X, seen from above).
+fn player_start_camera(entities: &[MapEntity], brushes: &[&MapBrush], hulls: &CellHulls, options: &MapImportOptions) -> Option<SceneCamera> {
+    entities.iter()
+        .filter(|entity| entity.properties.get("classname").is_some_and(|classname| classname == "info_player_start"))
+        .find_map(|entity| {
+            let coordinates: Vec<f64> = entity.properties.get("origin")?
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .ok()?;
+            let origin = match coordinates[..] {
+                [x, y, z] => DVec3::new(x, y, z),
+                _ => return None,
+            };
+            let brush_index = brushes.iter().position(|brush| brush.contains(origin))?;
+            let angle: f32 = entity.properties.get("angle").and_then(|angle| angle.parse().ok()).unwrap_or(0.0);
+            // Map angle 90 looks along map +Y, which is the camera's default -Z.
+            let rotation = Mat4::from_rotation_y((angle - 90.0).to_radians());
+            let center = hulls.instances.get(&(brush_index as InstanceId))?.initial_transform?.transform_point3(Vec3::ZERO);
+            let position = to_engine(origin, options.scale) - center;
+            Some(SceneCamera {
+                host_instance_id: brush_index as InstanceId,
+                local_transform: Mat4::from_translation(position) * rotation,
+                collision_radius: DEFAULT_CAMERA_COLLISION_RADIUS,
+            })
+        })
+}
+
+// `import_map` with default options on a file, in the shape `hot_reload::SceneLoader` expects.
+pub fn load_map_file(path: &Path) -> Result<Scene, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| MapImportError::Io(e).to_string())?;
+    import_map(&source, &MapImportOptions::default()).map_err(|e| e.to_string())
+}
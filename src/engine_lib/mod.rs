@@ -1,20 +1,79 @@
-// src/engine_lib/mod.rs
-
-pub mod scene_types;
-pub mod camera;
-pub mod controller;
-pub mod side_handler;
-pub mod scene_logic; // Added new module
-
-pub use scene_types::{
-    Scene, HullBlueprint, HullInstance, BlueprintSide,
-    HandlerConfig, SideHandlerTypeId, PortalConnectionInfo, TraversalState, BoundaryCheckResult,
-    InstanceId, BlueprintId, PortalId, SideIndex,
-};
-pub use camera::Camera;
-pub use controller::CameraController;
-pub use side_handler::{
-    SideHandler, StandardWallHandler, StandardPortalHandler, HandlerContext,
-    MAX_PORTAL_RECURSION_DEPTH, get_portal_alignment_transform,
-};
-pub use scene_logic::{update_camera_in_scene, check_camera_hull_boundary}; // Re-export new functions
\ No newline at end of file
+// src/engine_lib/mod.rs
+
+pub mod scene_types;
+pub mod camera;
+pub mod controller;
+pub mod side_handler;
+pub mod scene_logic; // Added new module
+pub mod scene_loading;
+pub mod portal_pairing;
+pub mod placement;
+pub mod entity;
+pub mod animation;
+pub mod trigger;
+pub mod events;
+pub mod picking;
+pub mod portal_graph;
+pub mod spawning;
+pub mod scene_builder;
+pub mod audio;
+pub mod hot_reload;
+pub mod chase_camera;
+pub mod blueprint_validation;
+pub mod convex_cells;
+pub mod map_import;
+pub mod csg;
+pub mod level_compiler;
+pub mod scene_export;
+pub mod gltf_import;
+pub mod convex_hull;
+pub mod hull_split;
+pub mod liquid;
+pub mod decal;
+pub mod interaction;
+pub mod automap;
+
+pub use scene_types::{
+    Scene, HullBlueprint, HullInstance, BlueprintSide,
+    HandlerConfig, PortalAccess, SideHandlerTypeId, PortalConnectionInfo, TraversalState, BoundaryCheckResult,
+    SceneCamera, DEFAULT_CAMERA_NAME, DEFAULT_CAMERA_COLLISION_RADIUS, DEFAULT_GRAVITY, LabelAnchor, DebugLabel, DEFAULT_LABEL_COLOR, FogSettings, RenderLayerMask, RENDER_LAYER_DEFAULT, RENDER_LAYER_ALL,
+    InstanceId, BlueprintId, PortalId, SideIndex,
+};
+pub use camera::Camera;
+pub use controller::CameraController;
+pub use side_handler::{
+    SideHandler, SideHandlerRegistry, StandardWallHandler, StandardPortalHandler, SkyboxHandler, DoorHandler, LiquidSurfaceHandler,
+    HandlerContext, SideOverlay,
+    MAX_PORTAL_RECURSION_DEPTH, get_portal_alignment_transform, emissive_color, queue_portal_traversal,
+};
+pub use scene_logic::{update_camera_in_scene, check_camera_hull_boundary, move_entity_in_scene, EntityState, MoveResult}; // Re-export new functions
+pub use scene_loading::{repair_missing_references, split_concave_sides, validate_blueprints, SceneDiagnostic};
+pub use portal_pairing::{pair_coincident_portals, PortalPairing, DEFAULT_PAIRING_TOLERANCE};
+pub use placement::{
+    instance_world_transform, relative_instance_transform, portal_transform, side_portal_transform, portal_side_motion, is_mirroring, contained_instances,
+    child_instances, set_instance_parent, set_instance_world_transform, blueprint_bounding_sphere,
+};
+pub use entity::{Entity, EntityId, EntityShape, ConvexMesh, MeshFace, Sprite, entities_in_instance};
+pub use animation::{SideAnimation, side_transform, side_plane, update_side_animations, set_side_animation_target};
+pub use trigger::{TriggerVolume, update_trigger_occupancy};
+pub use events::{EventBus, EngineEvent, SubscriptionId};
+pub use picking::{raycast_scene, trace_ray, RayHit, RayTrace};
+pub use portal_graph::{PortalGraph, PortalEdge};
+pub use spawning::{SpawnError, RemoveInstanceError, SEALED_PORTAL_COLOR};
+pub use scene_builder::{SceneBuilder, BlueprintHandle, InstanceHandle, SideRef, SceneBuildError};
+pub use audio::{SoundEmitter, SoundClip, EmitterId, AudiblePath, AcousticSettings, audible_path, listener_acoustics};
+pub use liquid::{LiquidVolume, update_liquids, liquid_in, submerged_liquid};
+pub use decal::{Decal, DecalId, decals_in_instance};
+pub use interaction::{Interaction, interaction_at, INTERACTION_REACH};
+pub use automap::{Automap, AutomapLayout, AutomapRoom, AutomapLink, AutomapMarker};
+pub use hot_reload::{FileWatcher, SceneLoader, carry_over_runtime_state};
+pub use chase_camera::ChaseCamera;
+pub use blueprint_validation::BlueprintError;
+pub use convex_cells::{hulls_from_cells, polyhedron_from_planes, scene_from_hulls, CellFace, CellHulls, ConvexCell};
+pub use csg::{ConvexSolid, CsgModel, CsgPlane};
+pub use level_compiler::{compile_level, load_gltf_level, LevelCompileOptions, LevelCompileError};
+pub use map_import::{import_map, load_map_file, MapImportOptions, MapImportError};
+pub use scene_export::{export_scene, expanded_geometry, global_instance_transforms, write_obj, write_glb, ExportObject, ExportFace, ExportOptions};
+pub use gltf_import::{blueprint_from_triangles, load_gltf_blueprint, load_gltf_triangles, ColoredTriangle, GltfImportOptions, GltfImportError};
+pub use convex_hull::{quickhull, convex_hull_blueprint, ConvexHullOptions, ConvexHullError};
+pub use hull_split::{split_blueprint, SplitError};
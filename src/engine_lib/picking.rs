@@ -0,0 +1,187 @@
+// src/engine_lib/picking.rs
+
+use glam::{Mat4, Vec3};
+use crate::engine_lib::scene_types::{Scene, HullBlueprint, HullInstance, HandlerConfig, InstanceId, SideIndex};
+use crate::engine_lib::animation::side_plane;
+use crate::engine_lib::placement::{side_portal_transform, relative_instance_transform, contained_instances};
+
+// Rays starting exactly on a portal must not hit it again.
+const RAY_EPSILON: f32 = 1e-5;
+
+// The first side a ray stops at: anything that is not an open portal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub instance_id: InstanceId,
+    pub side_index: SideIndex,
+    // Where the ray hit, in the hit instance's blueprint space.
+    pub local_point: Vec3,
+    // Length of the ray up to the hit, in the starting instance's units.
+    pub distance: f32,
+    pub portals_crossed: u32,
+}
+
+fn instance_and_blueprint(scene: &Scene, instance_id: InstanceId) -> Option<(&HullInstance, &HullBlueprint)> {
+    let instance = scene.instances.get(&instance_id)?;
+    Some((instance, scene.blueprints.get(&instance.blueprint_id)?))
+}
+
+fn effective_config<'a>(instance: &'a HullInstance, blueprint: &'a HullBlueprint, side_index: SideIndex) -> &'a HandlerConfig {
+    instance.instance_side_handler_configs
+        .get(&side_index)
+        .unwrap_or(&blueprint.sides[side_index].default_handler_config)
+}
+
+// The side a ray from inside the hull leaves through, with the ray parameter there.
+fn exit_side(blueprint: &HullBlueprint, instance: &HullInstance, origin: Vec3, direction: Vec3) -> Option<(SideIndex, f32)> {
+    (0..blueprint.sides.len())
+        .filter_map(|side_index| {
+            let (normal, point_on_plane) = side_plane(blueprint, instance, side_index)?;
+            let denom = normal.dot(direction);
+            // Normals point inwards, so only sides the ray moves against can be left through.
+            if denom >= -f32::EPSILON {
+                return None;
+            }
+            Some((side_index, (normal.dot(point_on_plane - origin) / denom).max(0.0)))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+// The side a ray from outside the hull enters through, with the ray parameter there.
+fn entry_side(blueprint: &HullBlueprint, instance: &HullInstance, origin: Vec3, direction: Vec3) -> Option<(SideIndex, f32)> {
+    let mut entry: Option<(SideIndex, f32)> = None;
+    let mut t_exit = f32::INFINITY;
+    for side_index in 0..blueprint.sides.len() {
+        let (normal, point_on_plane) = side_plane(blueprint, instance, side_index)?;
+        let denom = normal.dot(direction);
+        let distance = normal.dot(origin - point_on_plane);
+        if denom.abs() <= f32::EPSILON {
+            if distance < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = -distance / denom;
+        if denom > 0.0 {
+            if entry.is_none_or(|(_, t_entry)| t > t_entry) {
+                entry = Some((side_index, t));
+            }
+        } else {
+            t_exit = t_exit.min(t);
+        }
+    }
+    entry.filter(|&(_, t_entry)| t_entry > RAY_EPSILON && t_entry <= t_exit)
+}
+
+// Where a ray traced for a limited distance ended up.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayTrace {
+    // The instance the ray ends in, and the end point in its blueprint space.
+    pub instance_id: InstanceId,
+    pub local_point: Vec3,
+    // Maps the starting instance's blueprint space to the end instance's, through every portal
+    // and free-standing instance the ray entered.
+    pub start_to_end: Mat4,
+    pub distance: f32,
+    // The side that stopped the ray, if it did not reach `max_distance`.
+    pub hit: Option<RayHit>,
+}
+
+// Follows a ray from `origin` in `direction` (blueprint space of `instance_id`) through open
+// portals and into free-standing instances until it hits a side or has gone `max_distance`.
+// None if it leaves the scene through a broken reference or crosses more than `max_portals`
+// portals.
+pub fn trace_ray(scene: &Scene, instance_id: InstanceId, origin: Vec3, direction: Vec3, max_distance: f32, max_portals: u32) -> Option<RayTrace> {
+    // Portal transforms are rigid, so the direction stays unit length and ray parameters add up
+    // to a distance in the starting instance's units.
+    let mut direction = direction.try_normalize()?;
+    let mut origin = origin;
+    let mut current_id = instance_id;
+    let mut start_to_current = Mat4::IDENTITY;
+    let mut distance = 0.0;
+
+    for portals_crossed in 0..=max_portals {
+        let (instance, blueprint) = instance_and_blueprint(scene, current_id)?;
+        let (mut side_index, mut t) = exit_side(blueprint, instance, origin, direction)?;
+        let mut next_instance: Option<(InstanceId, Mat4)> = None;
+
+        // Free-standing instances are in the way if the ray enters one before leaving the hull.
+        for inner_id in contained_instances(scene, current_id) {
+            let (inner_instance, inner_blueprint) = match instance_and_blueprint(scene, inner_id) {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let host_to_inner = match relative_instance_transform(scene, current_id, inner_id) {
+                Some(inner_to_host) => inner_to_host.inverse(),
+                None => continue,
+            };
+            let entry = entry_side(
+                inner_blueprint, inner_instance,
+                host_to_inner.transform_point3(origin), host_to_inner.transform_vector3(direction),
+            );
+            if let Some((inner_side_index, t_entry)) = entry.filter(|&(_, t_entry)| t_entry < t) {
+                side_index = inner_side_index;
+                t = t_entry;
+                next_instance = Some((inner_id, host_to_inner));
+            }
+        }
+
+        if distance + t >= max_distance {
+            return Some(RayTrace {
+                instance_id: current_id,
+                local_point: origin + direction * (max_distance - distance),
+                start_to_end: start_to_current,
+                distance: max_distance,
+                hit: None,
+            });
+        }
+
+        let (hit_instance_id, hit_instance, hit_blueprint, to_hit_space) = match next_instance {
+            Some((inner_id, host_to_inner)) => {
+                let (inner_instance, inner_blueprint) = instance_and_blueprint(scene, inner_id)?;
+                (inner_id, inner_instance, inner_blueprint, host_to_inner)
+            }
+            None => (current_id, instance, blueprint, Mat4::IDENTITY),
+        };
+        let hit_point = to_hit_space.transform_point3(origin + direction * t);
+        distance += t;
+
+        let portal_target = effective_config(hit_instance, hit_blueprint, side_index).visible_portal_target();
+        let source_is_portal = hit_blueprint.sides[side_index].local_portal_id.is_some();
+        let (next_id, current_to_next) = match (next_instance, portal_target, source_is_portal) {
+            // An open side of a free-standing instance: carry on inside it.
+            (Some(_), Some(_), _) => (hit_instance_id, to_hit_space),
+            (None, Some((target_instance_id, target_portal_id)), true) => {
+                if !scene.instances.contains_key(&target_instance_id) {
+                    return None;
+                }
+                let current_to_target = side_portal_transform(scene, current_id, side_index, target_instance_id, target_portal_id)?.inverse();
+                (target_instance_id, current_to_target)
+            }
+            _ => {
+                return Some(RayTrace {
+                    instance_id: hit_instance_id,
+                    local_point: hit_point,
+                    start_to_end: to_hit_space * start_to_current,
+                    distance,
+                    hit: Some(RayHit {
+                        instance_id: hit_instance_id,
+                        side_index,
+                        local_point: hit_point,
+                        distance,
+                        portals_crossed,
+                    }),
+                });
+            }
+        };
+        origin = current_to_next.transform_point3(origin + direction * t);
+        direction = current_to_next.transform_vector3(direction);
+        start_to_current = current_to_next * start_to_current;
+        current_id = next_id;
+    }
+    None
+}
+
+// The first side a ray hits; see `trace_ray`.
+pub fn raycast_scene(scene: &Scene, instance_id: InstanceId, origin: Vec3, direction: Vec3, max_portals: u32) -> Option<RayHit> {
+    trace_ray(scene, instance_id, origin, direction, f32::INFINITY, max_portals)?.hit
+}
@@ -0,0 +1,218 @@
+// src/engine_lib/placement.rs
+
+use glam::{Mat4, Vec3};
+use crate::engine_lib::scene_types::{Scene, HullBlueprint, InstanceId, PortalId, SideIndex};
+use crate::engine_lib::animation::side_transform;
+use crate::engine_lib::side_handler::get_portal_alignment_transform;
+
+// Slack used when deciding whether a point lies inside a hull.
+const CONTAINMENT_EPSILON: f32 = 1e-4;
+
+// An instance's world transform: its `initial_transform` composed with those of its ancestors.
+// A child without one sits at its parent's origin. None if the chain ends at a root without
+// an `initial_transform`, references a missing instance or loops.
+pub fn instance_world_transform(scene: &Scene, instance_id: InstanceId) -> Option<Mat4> {
+    let mut transform = Mat4::IDENTITY;
+    let mut current_id = instance_id;
+    // Any chain longer than the instance count has a cycle in it.
+    for _ in 0..=scene.instances.len() {
+        let instance = scene.instances.get(&current_id)?;
+        match instance.parent_instance_id {
+            Some(parent_id) => {
+                transform = instance.initial_transform.unwrap_or(Mat4::IDENTITY) * transform;
+                current_id = parent_id;
+            }
+            None => return Some(instance.initial_transform? * transform),
+        }
+    }
+    None
+}
+
+// Maps `to`'s blueprint space into `from`'s, if both instances are placed in world space.
+pub fn relative_instance_transform(scene: &Scene, from: InstanceId, to: InstanceId) -> Option<Mat4> {
+    let from_world = instance_world_transform(scene, from)?;
+    let to_world = instance_world_transform(scene, to)?;
+    Some(from_world.inverse() * to_world)
+}
+
+// Direct children of an instance, in id order.
+pub fn child_instances(scene: &Scene, parent_id: InstanceId) -> Vec<InstanceId> {
+    let mut children: Vec<InstanceId> = scene.instances.values()
+        .filter(|instance| instance.parent_instance_id == Some(parent_id))
+        .map(|instance| instance.id)
+        .collect();
+    children.sort_unstable();
+    children
+}
+
+// Re-parents `child_id` under `parent_id` (or makes it a root), keeping its world placement
+// when both it and the new parent have one. Returns false, changing nothing, if either
+// instance is missing or the parent is the child itself or one of its descendants.
+pub fn set_instance_parent(scene: &mut Scene, child_id: InstanceId, parent_id: Option<InstanceId>) -> bool {
+    if !scene.instances.contains_key(&child_id) {
+        return false;
+    }
+    if let Some(parent_id) = parent_id {
+        if !scene.instances.contains_key(&parent_id) {
+            return false;
+        }
+        let mut ancestor = Some(parent_id);
+        let mut steps = 0;
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == child_id || steps > scene.instances.len() {
+                return false;
+            }
+            ancestor = scene.instances.get(&ancestor_id).and_then(|instance| instance.parent_instance_id);
+            steps += 1;
+        }
+    }
+
+    let child_world = instance_world_transform(scene, child_id);
+    let new_local = match (child_world, parent_id) {
+        (Some(world), Some(parent_id)) => instance_world_transform(scene, parent_id).map(|parent_world| parent_world.inverse() * world),
+        (Some(world), None) => Some(world),
+        (None, _) => None,
+    };
+    let child = scene.instances.get_mut(&child_id).expect("checked above");
+    if let Some(local) = new_local {
+        child.initial_transform = Some(local);
+    }
+    child.parent_instance_id = parent_id;
+    true
+}
+
+// Places an instance (and with it all its descendants) at `world_transform`, expressed
+// relative to its parent if it has one. Returns false if the instance is missing or its parent
+// has no world placement.
+pub fn set_instance_world_transform(scene: &mut Scene, instance_id: InstanceId, world_transform: Mat4) -> bool {
+    let parent_id = match scene.instances.get(&instance_id) {
+        Some(instance) => instance.parent_instance_id,
+        None => return false,
+    };
+    let local_transform = match parent_id {
+        Some(parent_id) => match instance_world_transform(scene, parent_id) {
+            Some(parent_world) => parent_world.inverse() * world_transform,
+            None => return false,
+        },
+        None => world_transform,
+    };
+    if let Some(instance) = scene.instances.get_mut(&instance_id) {
+        instance.initial_transform = Some(local_transform);
+    }
+    true
+}
+
+// Transform from the target instance's blueprint space into the source instance's across a
+// portal. World placement wins when both ends have one; otherwise the portal ids decide.
+pub fn portal_transform(
+    scene: &Scene,
+    source_instance_id: InstanceId,
+    source_portal_id: PortalId,
+    target_instance_id: InstanceId,
+    target_portal_id: PortalId,
+) -> Mat4 {
+    relative_instance_transform(scene, source_instance_id, target_instance_id)
+        .unwrap_or_else(|| get_portal_alignment_transform(source_portal_id, target_portal_id))
+}
+
+// Where the side carrying `portal_id` has been moved by its animation, in the instance's
+// blueprint space; the identity if it is not animated (or not found).
+pub fn portal_side_motion(scene: &Scene, instance_id: InstanceId, portal_id: PortalId) -> Mat4 {
+    scene.instances.get(&instance_id)
+        .and_then(|instance| {
+            let blueprint = scene.blueprints.get(&instance.blueprint_id)?;
+            let side_index = blueprint.sides.iter().position(|side| side.local_portal_id == Some(portal_id))?;
+            Some(side_transform(instance, side_index))
+        })
+        .unwrap_or(Mat4::IDENTITY)
+}
+
+// `portal_transform` through the portal on `source_side_index` as both its sides stand right
+// now: the target is carried along by the source side's animation and brought back by its own,
+// so moving portals (an elevator door, a revolving one) line up wherever they are. Depends on
+// animation progress, so it is worked out again each time rather than kept. None if the side
+// does not exist or has no portal id.
+pub fn side_portal_transform(
+    scene: &Scene,
+    source_instance_id: InstanceId,
+    source_side_index: SideIndex,
+    target_instance_id: InstanceId,
+    target_portal_id: PortalId,
+) -> Option<Mat4> {
+    let source_instance = scene.instances.get(&source_instance_id)?;
+    let source_portal_id = scene.blueprints.get(&source_instance.blueprint_id)?
+        .sides.get(source_side_index)?
+        .local_portal_id?;
+    Some(
+        side_transform(source_instance, source_side_index)
+            * portal_transform(scene, source_instance_id, source_portal_id, target_instance_id, target_portal_id)
+            * portal_side_motion(scene, target_instance_id, target_portal_id).inverse(),
+    )
+}
+
+// True if `transform` turns right-handed geometry left-handed, as a portal into a mirror-image
+// world does. Screen polygons are rebuilt with a fixed winding after projection, so the renderer
+// draws such worlds as they are; the camera controller needs to know to keep its controls upright.
+pub fn is_mirroring(transform: &Mat4) -> bool {
+    transform.determinant() < 0.0
+}
+
+// Signed distance of `point` from each side's plane, positive on the inner side.
+fn side_distances<'a>(blueprint: &'a HullBlueprint, point: Vec3) -> impl Iterator<Item = (usize, f32)> + 'a {
+    blueprint.sides.iter().enumerate().filter_map(move |(side_idx, side)| {
+        let p0 = *blueprint.local_vertices.get(*side.vertex_indices.first()?)?;
+        Some((side_idx, side.local_normal.dot(point - p0)))
+    })
+}
+
+// True if `point` (in the blueprint's space) is on the inner side of every side.
+pub fn hull_contains_point(blueprint: &HullBlueprint, point: Vec3) -> bool {
+    side_distances(blueprint, point).all(|(_, distance)| distance >= -CONTAINMENT_EPSILON)
+}
+
+// A sphere around every vertex of the blueprint, as (center, radius) in its space: centered on
+// the vertices' bounding box, so not the smallest one, but cheap. None for a blueprint without
+// vertices.
+pub fn blueprint_bounding_sphere(blueprint: &HullBlueprint) -> Option<(Vec3, f32)> {
+    let first = *blueprint.local_vertices.first()?;
+    let (min, max) = blueprint.local_vertices.iter().fold((first, first), |(min, max), v| (min.min(*v), max.max(*v)));
+    let center = (min + max) * 0.5;
+    let radius = blueprint.local_vertices.iter().map(|v| v.distance(center)).fold(0.0, f32::max);
+    Some((center, radius))
+}
+
+// The side `point` is furthest outside of, with its (negative) signed distance.
+pub fn most_violated_side(blueprint: &HullBlueprint, point: Vec3) -> Option<(usize, f32)> {
+    side_distances(blueprint, point)
+        .filter(|&(_, distance)| distance < -CONTAINMENT_EPSILON)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+}
+
+// World-placed instances standing free inside `outer_id`: every vertex of theirs lies inside
+// the outer hull. These are not reachable through the outer hull's sides, so they are drawn
+// and entered from it directly. Both instances need a world placement.
+pub fn contained_instances(scene: &Scene, outer_id: InstanceId) -> Vec<InstanceId> {
+    if instance_world_transform(scene, outer_id).is_none() {
+        return Vec::new();
+    }
+    let outer_blueprint = match scene.instances.get(&outer_id)
+        .and_then(|outer| scene.blueprints.get(&outer.blueprint_id))
+    {
+        Some(bp) => bp,
+        None => return Vec::new(),
+    };
+
+    let mut contained: Vec<InstanceId> = scene.instances.values()
+        .filter(|inner| inner.id != outer_id)
+        .filter_map(|inner| {
+            let inner_to_outer = relative_instance_transform(scene, outer_id, inner.id)?;
+            let inner_blueprint = scene.blueprints.get(&inner.blueprint_id)?;
+            let inside = !inner_blueprint.local_vertices.is_empty()
+                && inner_blueprint.local_vertices.iter()
+                    .all(|v| hull_contains_point(outer_blueprint, inner_to_outer.transform_point3(*v)));
+            inside.then_some(inner.id)
+        })
+        .collect();
+    contained.sort_unstable();
+    contained
+}
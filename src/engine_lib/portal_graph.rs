@@ -0,0 +1,132 @@
+// src/engine_lib/portal_graph.rs
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use crate::engine_lib::scene_types::{Scene, InstanceId, PortalId, SideIndex};
+
+// A way from one instance into another: through `side_index` of `from_instance_id`, arriving
+// at `target_portal_id` of `to_instance_id`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PortalEdge {
+    pub from_instance_id: InstanceId,
+    pub side_index: SideIndex,
+    pub portal_id: PortalId,
+    pub to_instance_id: InstanceId,
+    pub target_portal_id: PortalId,
+}
+
+// Which instances lead into which, through the portals that can currently be passed: standard
+// portals and open doors. It is a snapshot, so build a new one after doors open or close or
+// instances come and go.
+#[derive(Clone, Debug, Default)]
+pub struct PortalGraph {
+    edges: HashMap<InstanceId, Vec<PortalEdge>>,
+}
+
+impl PortalGraph {
+    pub fn from_scene(scene: &Scene) -> Self {
+        let mut edges: HashMap<InstanceId, Vec<PortalEdge>> = HashMap::new();
+        for instance in scene.instances.values() {
+            let blueprint = match scene.blueprints.get(&instance.blueprint_id) {
+                Some(blueprint) => blueprint,
+                None => continue,
+            };
+            let outgoing: Vec<PortalEdge> = blueprint.sides.iter().enumerate()
+                .filter_map(|(side_index, side)| {
+                    let config = instance.instance_side_handler_configs.get(&side_index).unwrap_or(&side.default_handler_config);
+                    let ((to_instance_id, target_portal_id), portal_id) = config.passable_portal_target().zip(side.local_portal_id)?;
+                    scene.instances.contains_key(&to_instance_id).then_some(PortalEdge {
+                        from_instance_id: instance.id,
+                        side_index,
+                        portal_id,
+                        to_instance_id,
+                        target_portal_id,
+                    })
+                })
+                .collect();
+            edges.insert(instance.id, outgoing);
+        }
+        Self { edges }
+    }
+
+    // Portals leading out of an instance, in side order.
+    pub fn edges_from(&self, instance_id: InstanceId) -> &[PortalEdge] {
+        self.edges.get(&instance_id).map_or(&[], Vec::as_slice)
+    }
+
+    // Instances one portal away, each once, in side order.
+    pub fn neighbors(&self, instance_id: InstanceId) -> Vec<InstanceId> {
+        let mut neighbors: Vec<InstanceId> = Vec::new();
+        for edge in self.edges_from(instance_id) {
+            if !neighbors.contains(&edge.to_instance_id) {
+                neighbors.push(edge.to_instance_id);
+            }
+        }
+        neighbors
+    }
+
+    // The portals to pass through, in order, to get from one instance to another crossing as
+    // few as possible. Empty if they are the same instance; None if `to` cannot be reached.
+    pub fn shortest_path(&self, from: InstanceId, to: InstanceId) -> Option<Vec<PortalEdge>> {
+        if !self.edges.contains_key(&from) || !self.edges.contains_key(&to) {
+            return None;
+        }
+        let mut arrived_by: HashMap<InstanceId, Option<PortalEdge>> = HashMap::from([(from, None)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(instance_id) = queue.pop_front() {
+            if instance_id == to {
+                let mut path = Vec::new();
+                let mut current = to;
+                while let Some(edge) = arrived_by[&current] {
+                    path.push(edge);
+                    current = edge.from_instance_id;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for edge in self.edges_from(instance_id) {
+                if let Entry::Vacant(entry) = arrived_by.entry(edge.to_instance_id) {
+                    entry.insert(Some(*edge));
+                    queue.push_back(edge.to_instance_id);
+                }
+            }
+        }
+        None
+    }
+
+    // Every instance reachable from `from` through at most `max_portals` portals, with the
+    // fewest portals it takes, `from` itself at 0.
+    pub fn reachable_within(&self, from: InstanceId, max_portals: u32) -> HashMap<InstanceId, u32> {
+        let mut portals_crossed: HashMap<InstanceId, u32> = HashMap::new();
+        if !self.edges.contains_key(&from) {
+            return portals_crossed;
+        }
+        portals_crossed.insert(from, 0);
+        let mut queue = VecDeque::from([from]);
+        while let Some(instance_id) = queue.pop_front() {
+            let crossed = portals_crossed[&instance_id];
+            if crossed >= max_portals {
+                continue;
+            }
+            for edge in self.edges_from(instance_id) {
+                if let Entry::Vacant(entry) = portals_crossed.entry(edge.to_instance_id) {
+                    entry.insert(crossed + 1);
+                    queue.push_back(edge.to_instance_id);
+                }
+            }
+        }
+        portals_crossed
+    }
+}
+
+impl Scene {
+    pub fn portal_graph(&self) -> PortalGraph {
+        PortalGraph::from_scene(self)
+    }
+
+    // See `PortalGraph::shortest_path`. Builds the graph each call; keep a `PortalGraph` around
+    // for repeated queries.
+    pub fn shortest_portal_path(&self, from: InstanceId, to: InstanceId) -> Option<Vec<PortalEdge>> {
+        self.portal_graph().shortest_path(from, to)
+    }
+}
@@ -0,0 +1,158 @@
+// src/engine_lib/portal_pairing.rs
+
+use glam::{Mat4, Vec3};
+use crate::engine_lib::placement::instance_world_transform;
+use crate::engine_lib::scene_types::{
+    Scene, HandlerConfig, PortalConnectionInfo, InstanceId, PortalId, SideIndex,
+};
+
+// Default distance (in world units) within which two face vertices are considered coincident.
+pub const DEFAULT_PAIRING_TOLERANCE: f32 = 1e-3;
+
+// A portal connection created by `pair_coincident_portals`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortalPairing {
+    pub instance_a: InstanceId,
+    pub side_a: SideIndex,
+    pub portal_a: PortalId,
+    pub instance_b: InstanceId,
+    pub side_b: SideIndex,
+    pub portal_b: PortalId,
+}
+
+// A side of a world-space instance that is eligible for pairing.
+struct CandidateFace {
+    instance_id: InstanceId,
+    side_index: SideIndex,
+    portal_id: PortalId,
+    world_vertices: Vec<Vec3>,
+    world_normal: Vec3,
+}
+
+fn collect_candidate_faces(scene: &Scene) -> Vec<CandidateFace> {
+    let mut instance_ids: Vec<InstanceId> = scene.instances.keys().copied().collect();
+    instance_ids.sort_unstable();
+
+    let mut faces = Vec::new();
+    for instance_id in instance_ids {
+        let instance = &scene.instances[&instance_id];
+        // Only instances placed in world space have a position to compare against.
+        let world_transform: Mat4 = match instance_world_transform(scene, instance_id) {
+            Some(transform) => transform,
+            None => continue,
+        };
+        let blueprint = match scene.blueprints.get(&instance.blueprint_id) {
+            Some(bp) => bp,
+            None => continue,
+        };
+        for (side_index, side) in blueprint.sides.iter().enumerate() {
+            let portal_id = match side.local_portal_id {
+                Some(id) => id,
+                None => continue,
+            };
+            let config = instance.instance_side_handler_configs
+                .get(&side_index)
+                .unwrap_or(&side.default_handler_config);
+            // Sides that are already wired are left alone.
+            if matches!(config, HandlerConfig::StandardPortal { .. } | HandlerConfig::NonEuclideanPortal { .. } | HandlerConfig::Door { .. } | HandlerConfig::LiquidSurface { .. }) {
+                continue;
+            }
+            let world_vertices: Option<Vec<Vec3>> = side.vertex_indices.iter()
+                .map(|&v_idx| blueprint.local_vertices.get(v_idx).map(|v| world_transform.transform_point3(*v)))
+                .collect();
+            let world_vertices = match world_vertices {
+                Some(vertices) if vertices.len() >= 3 => vertices,
+                _ => continue,
+            };
+            let world_normal = match world_transform.transform_vector3(side.local_normal).try_normalize() {
+                Some(n) => n,
+                None => continue,
+            };
+            faces.push(CandidateFace { instance_id, side_index, portal_id, world_vertices, world_normal });
+        }
+    }
+    faces
+}
+
+// Two faces coincide if they have the same vertex count and every vertex of one lies within
+// `tolerance` of a distinct vertex of the other. Winding order is not compared since the two
+// sides of a shared wall wind in opposite directions.
+fn faces_coincide(a: &CandidateFace, b: &CandidateFace, tolerance: f32) -> bool {
+    if a.world_vertices.len() != b.world_vertices.len() {
+        return false;
+    }
+    // Side normals point into their hull, so the two sides of a shared wall face opposite ways.
+    if a.world_normal.dot(b.world_normal) > -1.0 + tolerance.max(1e-4) {
+        return false;
+    }
+    let mut used = vec![false; b.world_vertices.len()];
+    for va in &a.world_vertices {
+        let found = b.world_vertices.iter().enumerate()
+            .position(|(i, vb)| !used[i] && va.distance(*vb) <= tolerance);
+        match found {
+            Some(i) => used[i] = true,
+            None => return false,
+        }
+    }
+    true
+}
+
+// Scans instances placed in world space (see `instance_world_transform`) for coincident,
+// opposite-facing sides and wires each pair up as mutual `StandardPortal`s, filling in both
+// the side handler configs and `portal_connections`. Sides without a `local_portal_id` or
+// that are already portals are skipped. Returns the connections that were created.
+pub fn pair_coincident_portals(scene: &mut Scene, tolerance: f32) -> Vec<PortalPairing> {
+    let faces = collect_candidate_faces(scene);
+    let mut paired = vec![false; faces.len()];
+    let mut pairings = Vec::new();
+
+    for i in 0..faces.len() {
+        if paired[i] {
+            continue;
+        }
+        for j in (i + 1)..faces.len() {
+            if paired[j] || faces[i].instance_id == faces[j].instance_id {
+                continue;
+            }
+            if faces_coincide(&faces[i], &faces[j], tolerance) {
+                paired[i] = true;
+                paired[j] = true;
+                pairings.push(PortalPairing {
+                    instance_a: faces[i].instance_id,
+                    side_a: faces[i].side_index,
+                    portal_a: faces[i].portal_id,
+                    instance_b: faces[j].instance_id,
+                    side_b: faces[j].side_index,
+                    portal_b: faces[j].portal_id,
+                });
+                break;
+            }
+        }
+    }
+
+    for pairing in &pairings {
+        connect_side(scene, pairing.instance_a, pairing.side_a, pairing.portal_a, pairing.instance_b, pairing.portal_b);
+        connect_side(scene, pairing.instance_b, pairing.side_b, pairing.portal_b, pairing.instance_a, pairing.portal_a);
+    }
+    pairings
+}
+
+fn connect_side(
+    scene: &mut Scene,
+    instance_id: InstanceId,
+    side_index: SideIndex,
+    portal_id: PortalId,
+    target_instance_id: InstanceId,
+    target_portal_id: PortalId,
+) {
+    if let Some(instance) = scene.instances.get_mut(&instance_id) {
+        instance.instance_side_handler_configs.insert(
+            side_index,
+            HandlerConfig::portal(target_instance_id, target_portal_id),
+        );
+        instance.portal_connections.insert(
+            portal_id,
+            PortalConnectionInfo { target_instance_id, target_portal_id },
+        );
+    }
+}
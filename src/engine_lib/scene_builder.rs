@@ -0,0 +1,202 @@
+// src/engine_lib/scene_builder.rs
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use glam::Mat4;
+use crate::engine_lib::audio::AcousticSettings;
+use crate::engine_lib::events::EventBus;
+use crate::engine_lib::scene_types::{
+    Scene, SceneCamera, HullBlueprint, HullInstance, HandlerConfig, FogSettings, PortalConnectionInfo,
+    BlueprintId, InstanceId, SideIndex, DEFAULT_CAMERA_NAME, DEFAULT_CAMERA_COLLISION_RADIUS, RENDER_LAYER_ALL, DEFAULT_GRAVITY,
+};
+
+// A blueprint added to a `SceneBuilder`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlueprintHandle(BlueprintId);
+
+impl BlueprintHandle {
+    pub fn id(self) -> BlueprintId {
+        self.0
+    }
+}
+
+// An instance added to a `SceneBuilder`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InstanceHandle(InstanceId);
+
+impl InstanceHandle {
+    pub fn id(self) -> InstanceId {
+        self.0
+    }
+
+    pub fn side(self, side_index: SideIndex) -> SideRef {
+        SideRef { instance_id: self.0, side_index }
+    }
+}
+
+// One side of one instance, as passed to `SceneBuilder::connect`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SideRef {
+    pub instance_id: InstanceId,
+    pub side_index: SideIndex,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneBuildError {
+    // The instance's blueprint has no such side.
+    MissingSide(SideRef),
+    // Portals are matched up by portal id, so a side without one cannot be connected.
+    SideWithoutPortalId(SideRef),
+    SideConnectedTwice(SideRef),
+}
+
+impl fmt::Display for SceneBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneBuildError::MissingSide(side) =>
+                write!(f, "instance {} has no side {}", side.instance_id, side.side_index),
+            SceneBuildError::SideWithoutPortalId(side) =>
+                write!(f, "side {} of instance {} has no portal id", side.side_index, side.instance_id),
+            SceneBuildError::SideConnectedTwice(side) =>
+                write!(f, "side {} of instance {} is connected twice", side.side_index, side.instance_id),
+        }
+    }
+}
+
+impl std::error::Error for SceneBuildError {}
+
+// Assembles a scene without hand-picked ids: blueprints and instances are numbered from 0 in
+// the order they are added, and `connect` fills in the portal configs and connections on both
+// sides. Connection mistakes are reported by `build`.
+//
+//     let mut builder = SceneBuilder::new();
+//     let room = builder.blueprint(room_blueprint);
+//     let (a, b) = (builder.instance(room, "A"), builder.instance(room, "B"));
+//     builder.connect(a.side(0), b.side(1)).camera("main", a, Mat4::IDENTITY);
+//     let scene = builder.build()?;
+#[derive(Clone, Debug, Default)]
+pub struct SceneBuilder {
+    blueprints: Vec<HullBlueprint>,
+    instances: Vec<HullInstance>,
+    connections: Vec<(SideRef, SideRef)>,
+    cameras: Vec<(String, SceneCamera)>,
+    fog: Option<FogSettings>,
+}
+
+impl SceneBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds a blueprint under the next free id, replacing the id it comes with.
+    pub fn blueprint(&mut self, mut blueprint: HullBlueprint) -> BlueprintHandle {
+        blueprint.id = self.blueprints.len() as BlueprintId;
+        self.blueprints.push(blueprint);
+        BlueprintHandle(self.blueprints.len() as BlueprintId - 1)
+    }
+
+    // Adds an instance of a blueprint under the next free id, with no placement of its own.
+    pub fn instance(&mut self, blueprint: BlueprintHandle, name: impl Into<String>) -> InstanceHandle {
+        let id = self.instances.len() as InstanceId;
+        self.instances.push(HullInstance {
+            id,
+            name: name.into(),
+            blueprint_id: blueprint.0,
+            initial_transform: None,
+            parent_instance_id: None,
+            portal_connections: HashMap::new(),
+            instance_side_handler_configs: HashMap::new(),
+            render_layers: RENDER_LAYER_ALL,
+            side_animations: HashMap::new(),
+            triggers: Vec::new(),
+            acoustics: AcousticSettings::default(),
+            liquid: None,
+            gravity: DEFAULT_GRAVITY,
+        });
+        InstanceHandle(id)
+    }
+
+    // For everything about an instance the builder has no shortcut for.
+    pub fn instance_mut(&mut self, instance: InstanceHandle) -> &mut HullInstance {
+        &mut self.instances[instance.0 as usize]
+    }
+
+    pub fn place(&mut self, instance: InstanceHandle, transform: Mat4) -> &mut Self {
+        self.instance_mut(instance).initial_transform = Some(transform);
+        self
+    }
+
+    // Overrides a side's blueprint config on this instance. A door set up here before the side
+    // is connected stays a door, pointed at the other side.
+    pub fn side_config(&mut self, side: SideRef, config: HandlerConfig) -> &mut Self {
+        self.instance_mut(InstanceHandle(side.instance_id)).instance_side_handler_configs.insert(side.side_index, config);
+        self
+    }
+
+    // Joins two sides into a pair of portals leading into each other.
+    pub fn connect(&mut self, a: SideRef, b: SideRef) -> &mut Self {
+        self.connections.push((a, b));
+        self
+    }
+
+    // Adds a camera with the default collision radius. The first one added is the active camera.
+    pub fn camera(&mut self, name: impl Into<String>, host: InstanceHandle, local_transform: Mat4) -> &mut Self {
+        let camera = SceneCamera { host_instance_id: host.0, local_transform, collision_radius: DEFAULT_CAMERA_COLLISION_RADIUS };
+        self.cameras.push((name.into(), camera));
+        self
+    }
+
+    pub fn fog(&mut self, fog: FogSettings) -> &mut Self {
+        self.fog = Some(fog);
+        self
+    }
+
+    pub fn build(self) -> Result<Scene, SceneBuildError> {
+        let mut instances = self.instances;
+        let mut connected: Vec<SideRef> = Vec::new();
+        for &(a, b) in &self.connections {
+            let mut ends = Vec::with_capacity(2);
+            for side in [a, b] {
+                if connected.contains(&side) {
+                    return Err(SceneBuildError::SideConnectedTwice(side));
+                }
+                let instance = &instances[side.instance_id as usize];
+                let blueprint_side = self.blueprints[instance.blueprint_id as usize].sides.get(side.side_index)
+                    .ok_or(SceneBuildError::MissingSide(side))?;
+                let portal_id = blueprint_side.local_portal_id.ok_or(SceneBuildError::SideWithoutPortalId(side))?;
+                let config = instance.instance_side_handler_configs.get(&side.side_index)
+                    .unwrap_or(&blueprint_side.default_handler_config)
+                    .clone();
+                ends.push((side, portal_id, config));
+                connected.push(side);
+            }
+            for (end, other) in [(0, 1), (1, 0)] {
+                let (side, portal_id, config) = &ends[end];
+                let (other_side, other_portal_id, _) = &ends[other];
+                let instance = &mut instances[side.instance_id as usize];
+                instance.instance_side_handler_configs.insert(side.side_index, config.retargeted(other_side.instance_id, *other_portal_id));
+                instance.portal_connections.insert(*portal_id, PortalConnectionInfo {
+                    target_instance_id: other_side.instance_id,
+                    target_portal_id: *other_portal_id,
+                });
+            }
+        }
+
+        let active_camera = self.cameras.first().map_or_else(|| DEFAULT_CAMERA_NAME.to_string(), |(name, _)| name.clone());
+        Ok(Scene {
+            blueprints: self.blueprints.into_iter().map(|blueprint| (blueprint.id, blueprint)).collect(),
+            instances: instances.into_iter().map(|instance| (instance.id, instance)).collect(),
+            cameras: self.cameras.into_iter().collect(),
+            active_camera,
+            fog: self.fog,
+            entities: HashMap::new(),
+            debug_labels: Vec::new(),
+            sound_emitters: HashMap::new(),
+            decals: HashMap::new(),
+            events: EventBus::default(),
+            occupied_triggers: Vec::new(),
+            held_keys: HashSet::new(),
+            instance_id_floor: 0,
+        })
+    }
+}
@@ -0,0 +1,330 @@
+// src/engine_lib/scene_export.rs
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use glam::{Mat4, Vec3};
+use crate::engine_lib::scene_types::{Scene, HullInstance, HullBlueprint, HandlerConfig, InstanceId, PortalId, SideIndex};
+use crate::engine_lib::placement::{instance_world_transform, portal_transform};
+use crate::engine_lib::animation::side_transform;
+use crate::rendering_lib::triangulation::push_fan_indices;
+
+// Space left between groups of instances that no portal chain connects.
+const GROUP_GAP: f32 = 1.0;
+// Portal sides are exported in this color when asked for.
+const PORTAL_COLOR: [f32; 4] = [0.2, 0.8, 1.0, 0.5];
+
+const GLB_MAGIC: u32 = 0x4654_6C67; // "glTF"
+const GLB_VERSION: u32 = 2;
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A; // "JSON"
+const GLB_CHUNK_BIN: u32 = 0x004E_4942; // "BIN\0"
+const GL_ARRAY_BUFFER: u32 = 34962;
+const GL_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+const GL_FLOAT: u32 = 5126;
+const GL_UNSIGNED_INT: u32 = 5125;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ExportOptions {
+    // Also write the sides the camera can walk through, so the connections can be checked.
+    pub include_portals: bool,
+}
+
+// One side in the export frame, wound counter-clockwise around `normal`, which points into
+// the hull as in the engine: a room exported this way is seen from inside.
+#[derive(Clone, Debug)]
+pub struct ExportFace {
+    pub points: Vec<Vec3>,
+    pub normal: Vec3,
+    pub color: [f32; 4],
+}
+
+#[derive(Clone, Debug)]
+pub struct ExportObject {
+    pub instance_id: InstanceId,
+    pub name: String,
+    pub faces: Vec<ExportFace>,
+}
+
+// Where `config` leads regardless of whether it can be passed right now; closed doors still
+// fix where the room behind them goes.
+fn portal_link(config: &HandlerConfig) -> Option<(InstanceId, PortalId)> {
+    match config {
+        HandlerConfig::StandardPortal { target_instance_id, target_portal_id, .. }
+        | HandlerConfig::Door { target_instance_id, target_portal_id, .. }
+        | HandlerConfig::LiquidSurface { target_instance_id, target_portal_id, .. } => Some((*target_instance_id, *target_portal_id)),
+        _ => None,
+    }
+}
+
+fn effective_config<'a>(instance: &'a HullInstance, blueprint: &'a HullBlueprint, side_index: SideIndex) -> &'a HandlerConfig {
+    instance.instance_side_handler_configs
+        .get(&side_index)
+        .unwrap_or(&blueprint.sides[side_index].default_handler_config)
+}
+
+// Bounding box of an instance's blueprint vertices under `transform`.
+fn placed_bounds(scene: &Scene, instance_id: InstanceId, transform: Mat4) -> Option<(Vec3, Vec3)> {
+    let blueprint = scene.instances.get(&instance_id).and_then(|instance| scene.blueprints.get(&instance.blueprint_id))?;
+    blueprint.local_vertices.iter()
+        .map(|&v| transform.transform_point3(v))
+        .fold(None, |bounds, p| match bounds {
+            None => Some((p, p)),
+            Some((min, max)) => Some((min.min(p), max.max(p))),
+        })
+}
+
+// A transform for every instance into one shared frame. Instances with a world placement keep
+// it; the rest are reached by walking portals from placed ones, so each room lands against the
+// room it connects to. Where portals loop back inconsistently, as in non-Euclidean layouts,
+// the first placement found wins and the overlap shows in the export. Groups no portal chain
+// reaches start from the active camera's hull, then in id order, each set beside what is
+// already placed along +X.
+pub fn global_instance_transforms(scene: &Scene) -> HashMap<InstanceId, Mat4> {
+    let mut ids: Vec<InstanceId> = scene.instances.keys().copied().collect();
+    ids.sort_unstable();
+    let mut placed: HashMap<InstanceId, Mat4> = HashMap::new();
+    let mut queue: VecDeque<InstanceId> = VecDeque::new();
+    for &id in &ids {
+        if let Some(world) = instance_world_transform(scene, id) {
+            placed.insert(id, world);
+            queue.push_back(id);
+        }
+    }
+
+    let mut roots: Vec<InstanceId> = scene.active_camera().map(|camera| camera.host_instance_id).into_iter().collect();
+    roots.extend(ids.iter().copied());
+    let mut roots = roots.into_iter();
+    loop {
+        while let Some(source_id) = queue.pop_front() {
+            let source_transform = placed[&source_id];
+            let (instance, blueprint) = match scene.instances.get(&source_id)
+                .and_then(|instance| Some((instance, scene.blueprints.get(&instance.blueprint_id)?)))
+            {
+                Some(pair) => pair,
+                None => continue,
+            };
+            for (side_index, side) in blueprint.sides.iter().enumerate() {
+                let ((target_id, target_portal_id), source_portal_id) =
+                    match portal_link(effective_config(instance, blueprint, side_index)).zip(side.local_portal_id) {
+                        Some(link) => link,
+                        None => continue,
+                    };
+                if placed.contains_key(&target_id) || !scene.instances.contains_key(&target_id) {
+                    continue;
+                }
+                let transform = source_transform * portal_transform(scene, source_id, source_portal_id, target_id, target_portal_id);
+                placed.insert(target_id, transform);
+                queue.push_back(target_id);
+            }
+        }
+
+        let root_id = match roots.find(|id| scene.instances.contains_key(id) && !placed.contains_key(id)) {
+            Some(id) => id,
+            None => break,
+        };
+        let placed_max_x = placed.iter()
+            .filter_map(|(&id, &transform)| placed_bounds(scene, id, transform))
+            .map(|(_, max)| max.x)
+            .fold(None, |m: Option<f32>, x| Some(m.map_or(x, |m| m.max(x))));
+        let offset = match (placed_max_x, placed_bounds(scene, root_id, Mat4::IDENTITY)) {
+            (Some(max_x), Some((root_min, _))) => Vec3::new(max_x + GROUP_GAP - root_min.x, 0.0, 0.0),
+            _ => Vec3::ZERO,
+        };
+        placed.insert(root_id, Mat4::from_translation(offset));
+        queue.push_back(root_id);
+    }
+    placed
+}
+
+// The scene's wall geometry placed by `global_instance_transforms`, one object per instance
+// in id order. Skyboxes and sides other than walls and closed doors are left out, as are
+// portals unless `include_portals` is set. Animated sides are exported where they are now.
+pub fn expanded_geometry(scene: &Scene, options: &ExportOptions) -> Vec<ExportObject> {
+    let transforms = global_instance_transforms(scene);
+    let mut ids: Vec<InstanceId> = transforms.keys().copied().collect();
+    ids.sort_unstable();
+    ids.into_iter()
+        .filter_map(|id| {
+            let instance = scene.instances.get(&id)?;
+            let blueprint = scene.blueprints.get(&instance.blueprint_id)?;
+            let instance_transform = transforms[&id];
+            let faces = blueprint.sides.iter().enumerate()
+                .filter_map(|(side_index, side)| {
+                    let color = match effective_config(instance, blueprint, side_index) {
+                        HandlerConfig::StandardWall { color, .. } | HandlerConfig::Door { color, open: false, .. } => *color,
+                        HandlerConfig::None => [1.0; 4],
+                        config if options.include_portals && config.passable_portal_target().is_some() => PORTAL_COLOR,
+                        _ => return None,
+                    };
+                    let transform = instance_transform * side_transform(instance, side_index);
+                    // Blueprint sides wind clockwise around their inward normal; reversed, they
+                    // face into the hull.
+                    let points: Vec<Vec3> = side.vertex_indices.iter().rev()
+                        .map(|&vertex_index| blueprint.local_vertices.get(vertex_index).map(|&v| transform.transform_point3(v)))
+                        .collect::<Option<_>>()?;
+                    let normal = transform.transform_vector3(side.local_normal).try_normalize()?;
+                    (points.len() >= 3).then_some(ExportFace { points, normal, color })
+                })
+                .collect();
+            Some(ExportObject { instance_id: id, name: instance.name.clone(), faces })
+        })
+        .collect()
+}
+
+// Wavefront OBJ with one object per instance and vertex colors after the positions, which
+// Blender and MeshLab read. Faces are polygons, not triangles.
+pub fn write_obj<W: Write>(objects: &[ExportObject], out: &mut W) -> io::Result<()> {
+    writeln!(out, "# Exported from engine3 scene")?;
+    let mut vertex_count = 0usize;
+    let mut normal_count = 0usize;
+    for object in objects {
+        writeln!(out, "o {}", obj_name(object))?;
+        for face in &object.faces {
+            for p in &face.points {
+                writeln!(out, "v {} {} {} {} {} {}", p.x, p.y, p.z, face.color[0], face.color[1], face.color[2])?;
+            }
+            writeln!(out, "vn {} {} {}", face.normal.x, face.normal.y, face.normal.z)?;
+            normal_count += 1;
+            write!(out, "f")?;
+            for i in 0..face.points.len() {
+                write!(out, " {}//{}", vertex_count + i + 1, normal_count)?;
+            }
+            writeln!(out)?;
+            vertex_count += face.points.len();
+        }
+    }
+    Ok(())
+}
+
+// OBJ names end at whitespace.
+fn obj_name(object: &ExportObject) -> String {
+    let name: String = object.name.chars().map(|c| if c.is_whitespace() { '_' } else { c }).collect();
+    format!("{}_{}", name, object.instance_id)
+}
+
+fn json_string(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len() + 2);
+    escaped.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn push_f32s(buffer: &mut Vec<u8>, values: &[f32]) {
+    for value in values {
+        buffer.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+// Binary glTF 2.0 with one node and mesh per instance: flat-shaded triangles with positions,
+// normals and vertex colors, all in a single buffer.
+pub fn write_glb<W: Write>(objects: &[ExportObject], out: &mut W) -> io::Result<()> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut buffer_views: Vec<String> = Vec::new();
+    let mut accessors: Vec<String> = Vec::new();
+    let mut meshes: Vec<String> = Vec::new();
+    let mut nodes: Vec<String> = Vec::new();
+
+    for object in objects.iter().filter(|object| !object.faces.is_empty()) {
+        let mut positions: Vec<f32> = Vec::new();
+        let mut normals: Vec<f32> = Vec::new();
+        let mut colors: Vec<f32> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+        let (mut min, mut max) = (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY));
+        for face in &object.faces {
+            push_fan_indices(&mut indices, (positions.len() / 3) as u32, face.points.len());
+            for p in &face.points {
+                positions.extend_from_slice(&p.to_array());
+                normals.extend_from_slice(&face.normal.to_array());
+                colors.extend_from_slice(&face.color);
+                min = min.min(*p);
+                max = max.max(*p);
+            }
+        }
+        let vertex_count = positions.len() / 3;
+
+        let mut attribute_accessor = |data: &[f32], target: u32, accessor_type: &str, bounds: Option<(Vec3, Vec3)>| {
+            let offset = buffer.len();
+            push_f32s(&mut buffer, data);
+            buffer_views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#, offset, buffer.len() - offset, target));
+            let bounds = bounds.map_or(String::new(), |(min, max)| {
+                format!(r#","min":[{},{},{}],"max":[{},{},{}]"#, min.x, min.y, min.z, max.x, max.y, max.z)
+            });
+            accessors.push(format!(
+                r#"{{"bufferView":{},"componentType":{},"count":{},"type":"{}"{}}}"#,
+                buffer_views.len() - 1, GL_FLOAT, vertex_count, accessor_type, bounds,
+            ));
+            accessors.len() - 1
+        };
+        let position_accessor = attribute_accessor(&positions, GL_ARRAY_BUFFER, "VEC3", Some((min, max)));
+        let normal_accessor = attribute_accessor(&normals, GL_ARRAY_BUFFER, "VEC3", None);
+        let color_accessor = attribute_accessor(&colors, GL_ARRAY_BUFFER, "VEC4", None);
+
+        let offset = buffer.len();
+        for index in &indices {
+            buffer.extend_from_slice(&index.to_le_bytes());
+        }
+        buffer_views.push(format!(r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#, offset, buffer.len() - offset, GL_ELEMENT_ARRAY_BUFFER));
+        accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":{},"count":{},"type":"SCALAR"}}"#,
+            buffer_views.len() - 1, GL_UNSIGNED_INT, indices.len(),
+        ));
+        let index_accessor = accessors.len() - 1;
+
+        let name = json_string(&obj_name(object));
+        meshes.push(format!(
+            r#"{{"name":{},"primitives":[{{"attributes":{{"POSITION":{},"NORMAL":{},"COLOR_0":{}}},"indices":{}}}]}}"#,
+            name, position_accessor, normal_accessor, color_accessor, index_accessor,
+        ));
+        nodes.push(format!(r#"{{"name":{},"mesh":{}}}"#, name, meshes.len() - 1));
+    }
+
+    let node_list: Vec<String> = (0..nodes.len()).map(|i| i.to_string()).collect();
+    let mut json = format!(
+        r#"{{"asset":{{"version":"2.0","generator":"engine3"}},"scene":0,"scenes":[{{"nodes":[{}]}}],"nodes":[{}],"meshes":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]}}"#,
+        node_list.join(","), nodes.join(","), meshes.join(","), accessors.join(","), buffer_views.join(","), buffer.len(),
+    ).into_bytes();
+    // Chunks are 4-byte aligned: JSON is padded with spaces, binary data with zeros.
+    while !json.len().is_multiple_of(4) {
+        json.push(b' ');
+    }
+    while !buffer.len().is_multiple_of(4) {
+        buffer.push(0);
+    }
+
+    let total_length = 12 + 8 + json.len() + if buffer.is_empty() { 0 } else { 8 + buffer.len() };
+    out.write_all(&GLB_MAGIC.to_le_bytes())?;
+    out.write_all(&GLB_VERSION.to_le_bytes())?;
+    out.write_all(&(total_length as u32).to_le_bytes())?;
+    out.write_all(&(json.len() as u32).to_le_bytes())?;
+    out.write_all(&GLB_CHUNK_JSON.to_le_bytes())?;
+    out.write_all(&json)?;
+    if !buffer.is_empty() {
+        out.write_all(&(buffer.len() as u32).to_le_bytes())?;
+        out.write_all(&GLB_CHUNK_BIN.to_le_bytes())?;
+        out.write_all(&buffer)?;
+    }
+    Ok(())
+}
+
+// Writes the scene's expanded geometry to `path`, as OBJ or binary glTF by its extension.
+pub fn export_scene(scene: &Scene, path: &Path, options: &ExportOptions) -> io::Result<()> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase);
+    let write: fn(&[ExportObject], &mut BufWriter<File>) -> io::Result<()> = match extension.as_deref() {
+        Some("obj") => write_obj,
+        Some("glb") => write_glb,
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("can't export to {}: use .obj or .glb", path.display()))),
+    };
+    let objects = expanded_geometry(scene, options);
+    let mut out = BufWriter::new(File::create(path)?);
+    write(&objects, &mut out)?;
+    out.flush()
+}
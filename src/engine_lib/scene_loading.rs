@@ -0,0 +1,313 @@
+// src/engine_lib/scene_loading.rs
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::fmt;
+use glam::{Mat4, Vec3};
+use crate::engine_lib::entity::EntityId;
+use crate::engine_lib::audio::AcousticSettings;
+use crate::engine_lib::blueprint_validation::BlueprintError;
+use crate::engine_lib::scene_types::{
+    Scene, SceneCamera, DEFAULT_CAMERA_COLLISION_RADIUS, HullBlueprint, BlueprintSide, HullInstance, HandlerConfig, SideHandlerTypeId,
+    BlueprintId, InstanceId, SideIndex, RENDER_LAYER_DEFAULT, RENDER_LAYER_ALL, DEFAULT_GRAVITY,
+};
+use crate::rendering_lib::geometry::Point2;
+use crate::rendering_lib::triangulation::convex_decomposition;
+use crate::demo_scene::{
+    PORTAL_ID_FRONT, PORTAL_ID_BACK, PORTAL_ID_LEFT, PORTAL_ID_RIGHT, PORTAL_ID_TOP, PORTAL_ID_BOTTOM,
+};
+
+pub const ERROR_WALL_COLOR: [f32; 4] = [1.0, 0.0, 1.0, 1.0];
+
+// A broken reference found (and patched over) while preparing a scene.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SceneDiagnostic {
+    MissingBlueprint { instance_id: InstanceId, blueprint_id: BlueprintId },
+    MissingPortalTarget { instance_id: InstanceId, side_index: SideIndex, target_instance_id: InstanceId },
+    MissingConnectionTarget { instance_id: InstanceId, target_instance_id: InstanceId },
+    MissingCameraInstance { camera: String, instance_id: InstanceId },
+    MissingActiveCamera { camera: String },
+    MissingParentInstance { instance_id: InstanceId, parent_instance_id: InstanceId },
+    ParentCycle { instance_id: InstanceId },
+    MissingEntityInstance { entity_id: EntityId, instance_id: InstanceId },
+    // Reported, not repaired.
+    InvalidBlueprint { blueprint_id: BlueprintId, error: BlueprintError },
+}
+
+impl fmt::Display for SceneDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneDiagnostic::MissingBlueprint { instance_id, blueprint_id } =>
+                write!(f, "instance {} references missing blueprint {}", instance_id, blueprint_id),
+            SceneDiagnostic::MissingPortalTarget { instance_id, side_index, target_instance_id } =>
+                write!(f, "portal on instance {} side {} targets missing instance {}", instance_id, side_index, target_instance_id),
+            SceneDiagnostic::MissingConnectionTarget { instance_id, target_instance_id } =>
+                write!(f, "portal connection on instance {} targets missing instance {}", instance_id, target_instance_id),
+            SceneDiagnostic::MissingCameraInstance { camera, instance_id } =>
+                write!(f, "camera '{}' is in missing instance {}", camera, instance_id),
+            SceneDiagnostic::MissingActiveCamera { camera } =>
+                write!(f, "active camera '{}' does not exist", camera),
+            SceneDiagnostic::MissingParentInstance { instance_id, parent_instance_id } =>
+                write!(f, "instance {} has missing parent instance {}", instance_id, parent_instance_id),
+            SceneDiagnostic::ParentCycle { instance_id } =>
+                write!(f, "instance {} is its own ancestor", instance_id),
+            SceneDiagnostic::MissingEntityInstance { entity_id, instance_id } =>
+                write!(f, "entity {} is in missing instance {}", entity_id, instance_id),
+            SceneDiagnostic::InvalidBlueprint { blueprint_id, error } =>
+                write!(f, "blueprint {}: {}", blueprint_id, error),
+        }
+    }
+}
+
+// A cuboid with magenta walls, laid out like the demo cuboid so portal alignment still works
+// when a placeholder stands in for a missing portal target.
+pub fn error_placeholder_blueprint(id: BlueprintId) -> HullBlueprint {
+    let half_size = 1.5;
+    let local_vertices = vec![
+        Vec3::new(-half_size, -half_size, -half_size), Vec3::new( half_size, -half_size, -half_size),
+        Vec3::new( half_size,  half_size, -half_size), Vec3::new(-half_size,  half_size, -half_size),
+        Vec3::new(-half_size, -half_size,  half_size), Vec3::new( half_size, -half_size,  half_size),
+        Vec3::new( half_size,  half_size,  half_size), Vec3::new(-half_size,  half_size,  half_size),
+    ];
+    let side = |vertex_indices: Vec<usize>, local_normal: Vec3, portal_id| BlueprintSide {
+        vertex_indices,
+        local_normal,
+        handler_type: SideHandlerTypeId::StandardWall,
+        default_handler_config: HandlerConfig::StandardWall { color: ERROR_WALL_COLOR, texture_id: None, emissive: 0.0, render_priority: 0 },
+        local_portal_id: Some(portal_id),
+        render_layers: RENDER_LAYER_DEFAULT,
+    };
+    let sides = vec![
+        side(vec![4, 5, 6, 7], Vec3::new(0.0, 0.0, -1.0), PORTAL_ID_FRONT),
+        side(vec![1, 0, 3, 2], Vec3::new(0.0, 0.0, 1.0), PORTAL_ID_BACK),
+        side(vec![0, 4, 7, 3], Vec3::new(1.0, 0.0, 0.0), PORTAL_ID_LEFT),
+        side(vec![5, 1, 2, 6], Vec3::new(-1.0, 0.0, 0.0), PORTAL_ID_RIGHT),
+        side(vec![7, 6, 2, 3], Vec3::new(0.0, -1.0, 0.0), PORTAL_ID_TOP),
+        side(vec![0, 1, 5, 4], Vec3::new(0.0, 1.0, 0.0), PORTAL_ID_BOTTOM),
+    ];
+    HullBlueprint { id, name: format!("ErrorPlaceholder_{}", id), local_vertices, sides }
+}
+
+fn placeholder_instance(id: InstanceId, blueprint_id: BlueprintId) -> HullInstance {
+    HullInstance {
+        id,
+        name: format!("MissingInstance_{}", id),
+        blueprint_id,
+        initial_transform: None,
+        parent_instance_id: None,
+        portal_connections: HashMap::new(),
+        instance_side_handler_configs: HashMap::new(),
+        render_layers: RENDER_LAYER_ALL,
+        side_animations: HashMap::new(),
+        triggers: Vec::new(),
+        acoustics: AcousticSettings::default(),
+        liquid: None,
+        gravity: DEFAULT_GRAVITY,
+    }
+}
+
+// Replaces every dangling blueprint/instance reference in `scene` with a magenta placeholder
+// hull so the scene can still be rendered and edited, and reports what was patched.
+pub fn repair_missing_references(scene: &mut Scene) -> Vec<SceneDiagnostic> {
+    let mut diagnostics = Vec::new();
+
+    // Missing blueprints get a placeholder under the referenced id, so the instance resolves as-is.
+    let mut instance_ids: Vec<InstanceId> = scene.instances.keys().copied().collect();
+    instance_ids.sort_unstable();
+    for instance_id in &instance_ids {
+        let blueprint_id = scene.instances[instance_id].blueprint_id;
+        if let Entry::Vacant(entry) = scene.blueprints.entry(blueprint_id) {
+            diagnostics.push(SceneDiagnostic::MissingBlueprint { instance_id: *instance_id, blueprint_id });
+            entry.insert(error_placeholder_blueprint(blueprint_id));
+        }
+    }
+
+    // Instances with a missing parent, or caught in a parent cycle, become roots. They keep
+    // their `initial_transform`, now read as a world placement.
+    for instance_id in &instance_ids {
+        let parent_instance_id = match scene.instances[instance_id].parent_instance_id {
+            Some(parent_id) => parent_id,
+            None => continue,
+        };
+        if !scene.instances.contains_key(&parent_instance_id) {
+            diagnostics.push(SceneDiagnostic::MissingParentInstance { instance_id: *instance_id, parent_instance_id });
+            if let Some(instance) = scene.instances.get_mut(instance_id) {
+                instance.parent_instance_id = None;
+            }
+        }
+    }
+    for instance_id in &instance_ids {
+        let mut ancestor = scene.instances[instance_id].parent_instance_id;
+        let mut steps = 0;
+        while let Some(ancestor_id) = ancestor {
+            if ancestor_id == *instance_id || steps > instance_ids.len() {
+                diagnostics.push(SceneDiagnostic::ParentCycle { instance_id: *instance_id });
+                if let Some(instance) = scene.instances.get_mut(instance_id) {
+                    instance.parent_instance_id = None;
+                }
+                break;
+            }
+            ancestor = scene.instances.get(&ancestor_id).and_then(|instance| instance.parent_instance_id);
+            steps += 1;
+        }
+    }
+
+    // Collect instance ids that are referenced but don't exist.
+    let mut missing_instances: Vec<InstanceId> = Vec::new();
+    fn note_missing(id: InstanceId, missing: &mut Vec<InstanceId>) {
+        if !missing.contains(&id) { missing.push(id); }
+    }
+    for instance_id in &instance_ids {
+        let instance = &scene.instances[instance_id];
+        let blueprint = &scene.blueprints[&instance.blueprint_id];
+        for side_index in 0..blueprint.sides.len() {
+            let config = instance.instance_side_handler_configs
+                .get(&side_index)
+                .unwrap_or(&blueprint.sides[side_index].default_handler_config);
+            if let HandlerConfig::StandardPortal { target_instance_id, .. }
+                | HandlerConfig::Door { target_instance_id, .. }
+                | HandlerConfig::LiquidSurface { target_instance_id, .. } = config
+            {
+                if !scene.instances.contains_key(target_instance_id) {
+                    diagnostics.push(SceneDiagnostic::MissingPortalTarget {
+                        instance_id: *instance_id, side_index, target_instance_id: *target_instance_id,
+                    });
+                    note_missing(*target_instance_id, &mut missing_instances);
+                }
+            }
+        }
+        let mut connection_targets: Vec<InstanceId> = instance.portal_connections.values()
+            .map(|connection| connection.target_instance_id)
+            .collect();
+        connection_targets.sort_unstable();
+        for target_instance_id in connection_targets {
+            if !scene.instances.contains_key(&target_instance_id) {
+                diagnostics.push(SceneDiagnostic::MissingConnectionTarget { instance_id: *instance_id, target_instance_id });
+                note_missing(target_instance_id, &mut missing_instances);
+            }
+        }
+    }
+    let mut entity_ids: Vec<EntityId> = scene.entities.keys().copied().collect();
+    entity_ids.sort_unstable();
+    for entity_id in entity_ids {
+        let host_instance_id = scene.entities[&entity_id].host_instance_id;
+        if !scene.instances.contains_key(&host_instance_id) {
+            diagnostics.push(SceneDiagnostic::MissingEntityInstance { entity_id, instance_id: host_instance_id });
+            note_missing(host_instance_id, &mut missing_instances);
+        }
+    }
+    let mut camera_names: Vec<String> = scene.cameras.keys().cloned().collect();
+    camera_names.sort_unstable();
+    let mut cameras_to_recenter = Vec::new();
+    for camera_name in camera_names {
+        let host_instance_id = scene.cameras[&camera_name].host_instance_id;
+        if !scene.instances.contains_key(&host_instance_id) {
+            diagnostics.push(SceneDiagnostic::MissingCameraInstance { camera: camera_name.clone(), instance_id: host_instance_id });
+            note_missing(host_instance_id, &mut missing_instances);
+            cameras_to_recenter.push(camera_name);
+        }
+    }
+
+    if !missing_instances.is_empty() {
+        let error_blueprint_id = scene.blueprints.keys().max().map_or(0, |id| id + 1);
+        scene.blueprints.insert(error_blueprint_id, error_placeholder_blueprint(error_blueprint_id));
+        for instance_id in missing_instances {
+            scene.instances.insert(instance_id, placeholder_instance(instance_id, error_blueprint_id));
+        }
+    }
+
+    // A camera dropped into a placeholder starts at its center.
+    for camera_name in cameras_to_recenter {
+        if let Some(camera) = scene.camera_mut(&camera_name) {
+            camera.local_transform = Mat4::IDENTITY;
+        }
+    }
+
+    // Without an active camera nothing can be rendered or controlled; put one in the lowest-id instance.
+    if scene.active_camera().is_none() {
+        diagnostics.push(SceneDiagnostic::MissingActiveCamera { camera: scene.active_camera.clone() });
+        if let Some(&host_instance_id) = scene.instances.keys().min() {
+            let name = scene.active_camera.clone();
+            scene.insert_camera(name, SceneCamera {
+                host_instance_id, local_transform: Mat4::IDENTITY, collision_radius: DEFAULT_CAMERA_COLLISION_RADIUS,
+            });
+        }
+    }
+
+    diagnostics
+}
+
+// Replaces every side with a concave outline by its convex pieces, which the renderer,
+// collision and picking all expect. The first piece keeps the side's index, so instance
+// overrides and animations still find it; the rest are appended to the blueprint and given
+// copies of the original side's overrides and animations. Returns how many sides were split.
+pub fn split_concave_sides(scene: &mut Scene) -> usize {
+    let mut split_count = 0;
+    // Per blueprint: (original side, the indices its extra pieces were appended at).
+    let mut added_pieces: HashMap<BlueprintId, Vec<(SideIndex, Vec<SideIndex>)>> = HashMap::new();
+    for blueprint in scene.blueprints.values_mut() {
+        for side_index in 0..blueprint.sides.len() {
+            let side = &blueprint.sides[side_index];
+            let points: Vec<Vec3> = match side.vertex_indices.iter()
+                .map(|&v_idx| blueprint.local_vertices.get(v_idx).copied())
+                .collect::<Option<Vec<Vec3>>>()
+            {
+                Some(points) if points.len() > 3 => points,
+                _ => continue,
+            };
+            // Newell's normal holds for any simple outline, convex or not.
+            let normal = (0..points.len()).fold(Vec3::ZERO, |normal, i| {
+                let (a, b) = (points[i], points[(i + 1) % points.len()]);
+                normal + Vec3::new((a.y - b.y) * (a.z + b.z), (a.z - b.z) * (a.x + b.x), (a.x - b.x) * (a.y + b.y))
+            });
+            let normal = match normal.try_normalize() {
+                Some(normal) => normal,
+                None => continue,
+            };
+            let u_axis = normal.any_orthonormal_vector();
+            let v_axis = normal.cross(u_axis);
+            let outline: Vec<Point2> = points.iter().map(|p| Point2::new(p.dot(u_axis), p.dot(v_axis))).collect();
+            let mut pieces = convex_decomposition(&outline).into_iter();
+            let first_piece = match pieces.next() {
+                Some(piece) if piece.len() < points.len() => piece,
+                _ => continue,
+            };
+
+            let original = side.clone();
+            let to_vertex_indices = |piece: Vec<usize>| piece.into_iter().map(|i| original.vertex_indices[i]).collect();
+            blueprint.sides[side_index].vertex_indices = to_vertex_indices(first_piece);
+            let mut appended = Vec::new();
+            for piece in pieces {
+                appended.push(blueprint.sides.len());
+                blueprint.sides.push(BlueprintSide { vertex_indices: to_vertex_indices(piece), ..original.clone() });
+            }
+            added_pieces.entry(blueprint.id).or_default().push((side_index, appended));
+            split_count += 1;
+        }
+    }
+
+    for instance in scene.instances.values_mut() {
+        for (side_index, appended) in added_pieces.get(&instance.blueprint_id).into_iter().flatten() {
+            for piece_index in appended {
+                if let Some(config) = instance.instance_side_handler_configs.get(side_index).cloned() {
+                    instance.instance_side_handler_configs.insert(*piece_index, config);
+                }
+                if let Some(animation) = instance.side_animations.get(side_index).cloned() {
+                    instance.side_animations.insert(*piece_index, animation);
+                }
+            }
+        }
+    }
+    split_count
+}
+
+// Runs `HullBlueprint::validate` on every blueprint, in id order. Nothing is changed: a
+// malformed blueprint still loads, but this says why it draws or collides oddly.
+pub fn validate_blueprints(scene: &Scene) -> Vec<SceneDiagnostic> {
+    let mut blueprint_ids: Vec<BlueprintId> = scene.blueprints.keys().copied().collect();
+    blueprint_ids.sort_unstable();
+    blueprint_ids.into_iter()
+        .filter_map(|blueprint_id| scene.blueprints[&blueprint_id].validate().err().map(|errors| (blueprint_id, errors)))
+        .flat_map(|(blueprint_id, errors)| errors.into_iter().map(move |error| SceneDiagnostic::InvalidBlueprint { blueprint_id, error }))
+        .collect()
+}
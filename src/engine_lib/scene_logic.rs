@@ -1,176 +1,318 @@
-// src/engine_lib/scene_logic.rs
-use glam::{Mat4, Vec3, Vec4Swizzles}; // Added Vec4Swizzles
-use crate::engine_lib::scene_types::{
-    Scene, HullBlueprint, HullInstance, HandlerConfig,
-    SideIndex, BoundaryCheckResult, // Removed unused InstanceId, PortalId
-};
-use crate::engine_lib::side_handler::get_portal_alignment_transform;
-
-const COLLISION_EPSILON: f32 = 1e-4; // Small epsilon for plane distance
-
-pub fn check_camera_hull_boundary(
-    new_camera_pos_in_blueprint_space: &Vec3,
-    current_hull_blueprint: &HullBlueprint,
-    current_hull_instance: &HullInstance,
-) -> BoundaryCheckResult {
-    for (side_idx, blueprint_side) in current_hull_blueprint.sides.iter().enumerate() {
-        if blueprint_side.vertex_indices.is_empty() {
-            continue;
-        }
-        let point_on_plane = current_hull_blueprint.local_vertices[blueprint_side.vertex_indices[0]];
-        let normal = blueprint_side.local_normal;
-
-        let d_plane_constant = -normal.dot(point_on_plane);
-        let signed_distance = normal.dot(*new_camera_pos_in_blueprint_space) + d_plane_constant;
-
-        if signed_distance < -COLLISION_EPSILON {
-            let handler_config = current_hull_instance
-                .instance_side_handler_configs
-                .get(&(side_idx as SideIndex))
-                .unwrap_or(&blueprint_side.default_handler_config);
-
-            match handler_config {
-                HandlerConfig::StandardPortal { target_instance_id, target_portal_id } => {
-                    if blueprint_side.local_portal_id.is_some() {
-                         return BoundaryCheckResult::Traverse {
-                            crossed_side_index: side_idx as SideIndex,
-                            target_instance_id: *target_instance_id,
-                            target_portal_id: *target_portal_id,
-                        };
-                    } else {
-                        return BoundaryCheckResult::Collision {
-                            collided_side_index: side_idx as SideIndex,
-                            collision_point: *new_camera_pos_in_blueprint_space,
-                        };
-                    }
-                }
-                _ => {
-                    return BoundaryCheckResult::Collision {
-                        collided_side_index: side_idx as SideIndex,
-                        collision_point: *new_camera_pos_in_blueprint_space,
-                    };
-                }
-            }
-        }
-    }
-    BoundaryCheckResult::Inside
-}
-
-pub fn update_camera_in_scene(
-    scene: &mut Scene,
-    potential_new_local_pos: Vec3,
-    new_rotation_matrix: Mat4,
-    _dt: f32,
-) {
-    let current_instance_id = scene.active_camera_instance_id;
-    let current_instance_clone = scene.instances.get(&current_instance_id)
-         .expect("Active camera instance not found for clone.")
-         .clone();
-    let current_hull_blueprint = scene.blueprints.get(&current_instance_clone.blueprint_id)
-        .expect("Blueprint for active camera instance not found.").clone();
-
-    let boundary_check_result = check_camera_hull_boundary(
-        &potential_new_local_pos,
-        &current_hull_blueprint,
-        &current_instance_clone,
-    );
-
-    match boundary_check_result {
-        BoundaryCheckResult::Inside => {
-            scene.active_camera_local_transform = Mat4::from_translation(potential_new_local_pos) * new_rotation_matrix;
-        }
-        BoundaryCheckResult::Collision { collided_side_index, collision_point: _ } => { // collision_point is potential_new_local_pos
-            let old_position = scene.active_camera_local_transform.w_axis.xyz();
-            
-            // --- Implement Push Out ---
-            let collided_side_normal = current_hull_blueprint.sides[collided_side_index].local_normal;
-            
-            // We want to move the potential_new_local_pos back along the collided_side_normal
-            // so that its distance to the plane is a small positive value (e.g., PUSH_OUT_DISTANCE).
-            // Original signed distance for potential_new_local_pos was < -COLLISION_EPSILON.
-            // Let current signed_distance = normal.dot(potential_new_local_pos) + d_plane_constant
-            // We want new_signed_distance = PUSH_OUT_DISTANCE.
-            // The change in position is along 'collided_side_normal'.
-            // Let new_pos = potential_new_local_pos + k * collided_side_normal.
-            // normal.dot(potential_new_local_pos + k * collided_side_normal) + d_plane_constant = PUSH_OUT_DISTANCE
-            // normal.dot(potential_new_local_pos) + d_plane_constant + k * normal.dot(collided_side_normal) = PUSH_OUT_DISTANCE
-            // signed_distance + k * (normal.length_squared()) = PUSH_OUT_DISTANCE
-            // k = (PUSH_OUT_DISTANCE - signed_distance) / normal.length_squared()
-
-            // Let's recalculate signed_distance for clarity here, or pass it from check_camera_hull_boundary
-            let point_on_plane = current_hull_blueprint.local_vertices[current_hull_blueprint.sides[collided_side_index].vertex_indices[0]];
-            let d_plane_constant = -collided_side_normal.dot(point_on_plane);
-            let signed_distance_at_potential_pos = collided_side_normal.dot(potential_new_local_pos) + d_plane_constant;
-
-            const PUSH_OUT_DISTANCE: f32 = 1e-3; // Small distance to be outside the plane
-
-            let mut corrected_position = potential_new_local_pos;
-            if collided_side_normal.length_squared() > 1e-6 { // Avoid division by zero if normal is zero
-                // We know signed_distance_at_potential_pos is negative (e.g. -0.001)
-                // We want it to be PUSH_OUT_DISTANCE (e.g. 0.001)
-                // k = (0.001 - (-0.001)) / len_sq = 0.002 / len_sq
-                let k = (PUSH_OUT_DISTANCE - signed_distance_at_potential_pos) / collided_side_normal.length_squared();
-                corrected_position = potential_new_local_pos + k * collided_side_normal;
-            } else {
-                // Normal is zero, unusual. Fallback to old position.
-                corrected_position = old_position;
-            }
-            
-            // Sanity check: ensure corrected_position is not further than old_position if movement was small
-            // This logic can get complex if multiple collisions happen or if k is very large.
-            // For now, a simple push: If the camera intended to move into a wall,
-            // place it just outside the wall, but allow rotation.
-            // A simpler push: just use the old_position for position component.
-            // The more precise push might be better though.
-
-            // Check if the corrected position is "better" than just staying at old_position.
-            // If the original movement was tiny, this push might overshoot.
-            // A simpler approach for now: just project potential_new_local_pos onto the plane and add a small offset.
-            // Projected_pos = P - (N.P + d) * N / N.length_squared()
-            // projected_on_plane = potential_new_local_pos - signed_distance_at_potential_pos * collided_side_normal / collided_side_normal.length_squared();
-            // corrected_position = projected_on_plane + PUSH_OUT_DISTANCE * collided_side_normal.normalize_or_zero();
-
-            // Sticking to the k-based correction for now:
-            scene.active_camera_local_transform = Mat4::from_translation(corrected_position) * new_rotation_matrix;
-            
-            // Fallback to simpler "just don't move position" if push-out is problematic:
-            // scene.active_camera_local_transform = Mat4::from_translation(old_position) * new_rotation_matrix;
-        }
-        BoundaryCheckResult::Traverse { crossed_side_index, target_instance_id, target_portal_id } => {
-            // ... (existing traversal logic) ...
-            // Consider adding a PUSH_OUT_DISTANCE equivalent for portal traversal too,
-            // to ensure the camera starts slightly *inside* the new room, not exactly on the plane.
-            let source_portal_id_on_current_bp = current_hull_blueprint.sides[crossed_side_index]
-                .local_portal_id
-                .expect("Traversal initiated but source blueprint side has no local_portal_id.");
-
-            let portal_alignment_transform_target_to_current = get_portal_alignment_transform(
-                source_portal_id_on_current_bp,
-                target_portal_id,
-            );
-
-            let camera_pose_if_crossed_in_old_bp = Mat4::from_translation(potential_new_local_pos) * new_rotation_matrix;
-            let mut new_camera_pose_in_new_bp = portal_alignment_transform_target_to_current.inverse() * camera_pose_if_crossed_in_old_bp;
-
-            // --- Experimental push into new room ---
-            // The "forward" direction for the camera in its new local space is -Z.
-            // We want to push it slightly along its new local -Z axis.
-            const TRAVERSAL_PUSH_DISTANCE: f32 = 1e-3; // Small push
-            let local_push_vec = Vec3::new(0.0, 0.0, -TRAVERSAL_PUSH_DISTANCE); // Along local -Z
-            
-            // Extract rotation and translation from the new pose
-            let (scale, rot_quat, trans) = new_camera_pose_in_new_bp.to_scale_rotation_translation();
-            let rotation_matrix_of_new_pose = Mat4::from_quat(rot_quat); // Assuming uniform scale Mat4::from_rotation_translation also works
-            
-            let world_ish_push_offset = rotation_matrix_of_new_pose.transform_vector3(local_push_vec);
-            let pushed_translation = trans + world_ish_push_offset;
-            
-            new_camera_pose_in_new_bp = Mat4::from_scale_rotation_translation(scale, rot_quat, pushed_translation);
-            // --- End experimental push ---
-
-
-            scene.active_camera_instance_id = target_instance_id;
-            scene.active_camera_local_transform = new_camera_pose_in_new_bp;
-        }
-    }
-}
\ No newline at end of file
+// src/engine_lib/scene_logic.rs
+use std::collections::HashSet;
+use glam::{Mat4, Vec3, Vec4, Vec4Swizzles}; // Added Vec4Swizzles
+use crate::engine_lib::scene_types::{
+    Scene, HullBlueprint, HullInstance,
+    SideIndex, InstanceId, BoundaryCheckResult,
+};
+use crate::engine_lib::animation::side_plane;
+use crate::engine_lib::trigger::update_trigger_occupancy;
+use crate::engine_lib::events::EngineEvent;
+use crate::engine_lib::placement::{
+    side_portal_transform, relative_instance_transform, contained_instances, hull_contains_point, most_violated_side,
+};
+
+const COLLISION_EPSILON: f32 = 1e-4; // Small epsilon for plane distance
+
+// Checks a camera of `collision_radius` moving to `new_camera_pos_in_blueprint_space`. Walls
+// stop the sphere as soon as it touches them; portals are crossed once its center is through,
+// unless they are see-only or gated by a key not in `held_keys`, when they are walls too.
+pub fn check_camera_hull_boundary(
+    new_camera_pos_in_blueprint_space: &Vec3,
+    current_hull_blueprint: &HullBlueprint,
+    current_hull_instance: &HullInstance,
+    collision_radius: f32,
+    held_keys: &HashSet<String>,
+) -> BoundaryCheckResult {
+    for (side_idx, blueprint_side) in current_hull_blueprint.sides.iter().enumerate() {
+        // Animated sides collide where they currently are.
+        let (normal, point_on_plane) = match side_plane(current_hull_blueprint, current_hull_instance, side_idx) {
+            Some((normal, point)) => (normal.normalize_or_zero(), point),
+            None => continue,
+        };
+
+        let d_plane_constant = -normal.dot(point_on_plane);
+        let signed_distance = normal.dot(*new_camera_pos_in_blueprint_space) + d_plane_constant;
+
+        let handler_config = current_hull_instance
+            .instance_side_handler_configs
+            .get(&(side_idx as SideIndex))
+            .unwrap_or(&blueprint_side.default_handler_config);
+
+        match handler_config.walkable_portal_target(held_keys) {
+            Some((target_instance_id, target_portal_id)) if blueprint_side.local_portal_id.is_some() => {
+                if signed_distance < -COLLISION_EPSILON {
+                    return BoundaryCheckResult::Traverse {
+                        crossed_side_index: side_idx as SideIndex,
+                        target_instance_id,
+                        target_portal_id,
+                    };
+                }
+            }
+            _ => {
+                if signed_distance < collision_radius - COLLISION_EPSILON {
+                    return BoundaryCheckResult::Collision {
+                        collided_side_index: side_idx as SideIndex,
+                        collision_point: *new_camera_pos_in_blueprint_space,
+                    };
+                }
+            }
+        }
+    }
+    BoundaryCheckResult::Inside
+}
+
+// Where a moving body is and how big it is, for the camera as well as NPCs and projectiles.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EntityState {
+    pub host_instance_id: InstanceId,
+    // Pose in the host instance's blueprint space.
+    pub local_transform: Mat4,
+    pub collision_radius: f32,
+}
+
+// What stopped or carried a body during one move.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MoveResult {
+    Moved,
+    Collided { instance_id: InstanceId, side_index: SideIndex },
+    Traversed { from_instance_id: InstanceId, to_instance_id: InstanceId },
+}
+
+// Moves a body by `velocity * dt` (host blueprint space), keeping its orientation. It collides
+// and crosses portals exactly like the camera.
+pub fn move_entity_in_scene(scene: &Scene, state: &mut EntityState, velocity: Vec3, dt: f32) -> MoveResult {
+    let mut rotation = state.local_transform;
+    rotation.w_axis = Vec4::W;
+    let potential_new_local_pos = state.local_transform.w_axis.xyz() + velocity * dt;
+    resolve_movement(scene, state, potential_new_local_pos, rotation)
+}
+
+// Moves the active camera towards `potential_new_local_pos` (host blueprint space), colliding
+// with walls and crossing portals. What happened on the way is published on `scene.events`.
+pub fn update_camera_in_scene(
+    scene: &mut Scene,
+    potential_new_local_pos: Vec3,
+    new_rotation_matrix: Mat4,
+    _dt: f32,
+) {
+    let mut state = match scene.active_camera() {
+        Some(camera) => EntityState {
+            host_instance_id: camera.host_instance_id,
+            local_transform: camera.local_transform,
+            collision_radius: camera.collision_radius,
+        },
+        None => return,
+    };
+    let result = resolve_movement(scene, &mut state, potential_new_local_pos, new_rotation_matrix);
+    if let Some(camera) = scene.active_camera_mut() {
+        camera.host_instance_id = state.host_instance_id;
+        camera.local_transform = state.local_transform;
+    }
+    match result {
+        MoveResult::Moved => {}
+        MoveResult::Collided { instance_id, side_index } => {
+            scene.events.publish(EngineEvent::Collision { instance_id, side_index });
+        }
+        MoveResult::Traversed { from_instance_id, to_instance_id } => {
+            scene.events.publish(EngineEvent::PortalTraversed { from_instance_id, to_instance_id });
+        }
+    }
+    update_trigger_occupancy(scene);
+}
+
+// Moves `state` to `potential_new_local_pos` with `new_rotation_matrix`, or as far as the
+// hull it is in allows.
+fn resolve_movement(
+    scene: &Scene,
+    state: &mut EntityState,
+    potential_new_local_pos: Vec3,
+    new_rotation_matrix: Mat4,
+) -> MoveResult {
+    let current_instance_id = state.host_instance_id;
+    let old_local_transform = state.local_transform;
+    let collision_radius = state.collision_radius;
+    let (current_instance_clone, current_hull_blueprint) = match scene.instances.get(&current_instance_id)
+        .and_then(|instance| scene.blueprints.get(&instance.blueprint_id).map(|bp| (instance.clone(), bp.clone())))
+    {
+        Some(pair) => pair,
+        None => {
+            // Broken scene (see scene_loading::repair_missing_references): move freely without collision.
+            set_pose(state, current_instance_id, Mat4::from_translation(potential_new_local_pos) * new_rotation_matrix);
+            return MoveResult::Moved;
+        }
+    };
+
+    let boundary_check_result = check_camera_hull_boundary(
+        &potential_new_local_pos,
+        &current_hull_blueprint,
+        &current_instance_clone,
+        collision_radius,
+        &scene.held_keys,
+    );
+
+    match boundary_check_result {
+        BoundaryCheckResult::Inside => {
+            let old_position = old_local_transform.w_axis.xyz();
+            move_against_free_standing_instances(scene, state, old_position, potential_new_local_pos, new_rotation_matrix)
+                .unwrap_or_else(|| {
+                    set_pose(state, current_instance_id, Mat4::from_translation(potential_new_local_pos) * new_rotation_matrix);
+                    MoveResult::Moved
+                })
+        }
+        BoundaryCheckResult::Collision { collided_side_index, collision_point: _ } => { // collision_point is potential_new_local_pos
+            let old_position = old_local_transform.w_axis.xyz();
+            
+            // --- Implement Push Out ---
+            // A side with no vertices has no plane to push out of: stay put, but still turn.
+            let Some((collided_side_normal, point_on_plane)) = side_plane(&current_hull_blueprint, &current_instance_clone, collided_side_index)
+                .map(|(normal, point)| (normal.normalize_or_zero(), point))
+            else {
+                set_pose(state, current_instance_id, Mat4::from_translation(old_position) * new_rotation_matrix);
+                return MoveResult::Collided { instance_id: current_instance_id, side_index: collided_side_index };
+            };
+
+            // Moving along the normal by k changes the signed distance by k * |normal|^2.
+            let d_plane_constant = -collided_side_normal.dot(point_on_plane);
+            let signed_distance_at_potential_pos = collided_side_normal.dot(potential_new_local_pos) + d_plane_constant;
+
+            const PUSH_OUT_DISTANCE: f32 = 1e-3; // Small distance to be outside the plane
+
+            let corrected_position = if collided_side_normal.length_squared() > 1e-6 { // Avoid division by zero if normal is zero
+                // The sphere's surface, not its center, ends up PUSH_OUT_DISTANCE from the plane.
+                let k = (collision_radius + PUSH_OUT_DISTANCE - signed_distance_at_potential_pos) / collided_side_normal.length_squared();
+                potential_new_local_pos + k * collided_side_normal
+            } else {
+                // Normal is zero, unusual. Fallback to old position.
+                old_position
+            };
+            
+            // Sanity check: ensure corrected_position is not further than old_position if movement was small
+            // This logic can get complex if multiple collisions happen or if k is very large.
+            // For now, a simple push: If the camera intended to move into a wall,
+            // place it just outside the wall, but allow rotation.
+            // A simpler push: just use the old_position for position component.
+            // The more precise push might be better though.
+
+            // Check if the corrected position is "better" than just staying at old_position.
+            // If the original movement was tiny, this push might overshoot.
+            // A simpler approach for now: just project potential_new_local_pos onto the plane and add a small offset.
+            // Projected_pos = P - (N.P + d) * N / N.length_squared()
+            // projected_on_plane = potential_new_local_pos - signed_distance_at_potential_pos * collided_side_normal / collided_side_normal.length_squared();
+            // corrected_position = projected_on_plane + PUSH_OUT_DISTANCE * collided_side_normal.normalize_or_zero();
+
+            // Sticking to the k-based correction for now:
+            set_pose(state, current_instance_id, Mat4::from_translation(corrected_position) * new_rotation_matrix);
+            
+            // Fallback to simpler "just don't move position" if push-out is problematic:
+            // set_pose(state, current_instance_id, Mat4::from_translation(old_position) * new_rotation_matrix);
+            MoveResult::Collided { instance_id: current_instance_id, side_index: collided_side_index }
+        }
+        BoundaryCheckResult::Traverse { crossed_side_index, target_instance_id, target_portal_id } => {
+            // Consider adding a PUSH_OUT_DISTANCE equivalent for portal traversal too,
+            // to ensure the camera starts slightly *inside* the new room, not exactly on the plane.
+
+            // Where the portal sides are this frame, so a camera crossing a moving portal lands
+            // where the view through it showed.
+            let portal_alignment_transform_target_to_current = side_portal_transform(
+                scene,
+                current_instance_id,
+                crossed_side_index,
+                target_instance_id,
+                target_portal_id,
+            ).expect("Traversal initiated but source blueprint side has no local_portal_id.");
+
+            let camera_pose_if_crossed_in_old_bp = Mat4::from_translation(potential_new_local_pos) * new_rotation_matrix;
+            let mut new_camera_pose_in_new_bp = portal_alignment_transform_target_to_current.inverse() * camera_pose_if_crossed_in_old_bp;
+
+            // --- Experimental push into new room ---
+            // The "forward" direction for the camera in its new local space is -Z.
+            // We want to push it slightly along its new local -Z axis.
+            const TRAVERSAL_PUSH_DISTANCE: f32 = 1e-3; // Small push
+            let local_push_vec = Vec3::new(0.0, 0.0, -TRAVERSAL_PUSH_DISTANCE); // Along local -Z
+            
+            // Extract rotation and translation from the new pose
+            let (scale, rot_quat, trans) = new_camera_pose_in_new_bp.to_scale_rotation_translation();
+            let rotation_matrix_of_new_pose = Mat4::from_quat(rot_quat); // Assuming uniform scale Mat4::from_rotation_translation also works
+            
+            let world_ish_push_offset = rotation_matrix_of_new_pose.transform_vector3(local_push_vec);
+            let pushed_translation = trans + world_ish_push_offset;
+            
+            new_camera_pose_in_new_bp = Mat4::from_scale_rotation_translation(scale, rot_quat, pushed_translation);
+            // --- End experimental push ---
+
+
+            set_pose(state, target_instance_id, new_camera_pose_in_new_bp);
+            MoveResult::Traversed { from_instance_id: current_instance_id, to_instance_id: target_instance_id }
+        }
+    }
+}
+
+fn set_pose(state: &mut EntityState, host_instance_id: InstanceId, local_transform: Mat4) {
+    state.host_instance_id = host_instance_id;
+    state.local_transform = local_transform;
+}
+
+// Handles a body moving from `old_position` to `new_position` (host blueprint space) into one
+// of the free-standing instances inside its host hull. Crossing one of the instance's portal
+// sides moves the body into it; any other side stops it just outside. Returns None if the
+// move does not enter a free-standing instance.
+fn move_against_free_standing_instances(
+    scene: &Scene,
+    state: &mut EntityState,
+    old_position: Vec3,
+    new_position: Vec3,
+    rotation: Mat4,
+) -> Option<MoveResult> {
+    const PUSH_OUT_DISTANCE: f32 = 1e-3;
+    let host_instance_id = state.host_instance_id;
+
+    for inner_id in contained_instances(scene, host_instance_id) {
+        let inner_to_host = match relative_instance_transform(scene, host_instance_id, inner_id) {
+            Some(transform) => transform,
+            None => continue,
+        };
+        let (inner_instance, inner_blueprint) = match scene.instances.get(&inner_id)
+            .and_then(|instance| scene.blueprints.get(&instance.blueprint_id).map(|bp| (instance, bp)))
+        {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let host_to_inner = inner_to_host.inverse();
+        let new_position_inner = host_to_inner.transform_point3(new_position);
+        if !hull_contains_point(inner_blueprint, new_position_inner) {
+            continue;
+        }
+        // The side the camera came through is the one its old position was furthest outside of.
+        // If it was already inside (e.g. placed there), leave the move alone.
+        let crossed_side_index = match most_violated_side(inner_blueprint, host_to_inner.transform_point3(old_position)) {
+            Some((side_idx, _)) => side_idx,
+            None => continue,
+        };
+        let crossed_side = &inner_blueprint.sides[crossed_side_index];
+        let handler_config = inner_instance
+            .instance_side_handler_configs
+            .get(&(crossed_side_index as SideIndex))
+            .unwrap_or(&crossed_side.default_handler_config);
+
+        if handler_config.walkable_portal_target(&scene.held_keys).is_some() {
+            let new_pose_in_inner = host_to_inner * Mat4::from_translation(new_position) * rotation;
+            set_pose(state, inner_id, new_pose_in_inner);
+            return Some(MoveResult::Traversed { from_instance_id: host_instance_id, to_instance_id: inner_id });
+        }
+
+        // Side normals point into the instance, so push back against the normal.
+        let normal = crossed_side.local_normal;
+        if normal.length_squared() <= 1e-6 {
+            set_pose(state, host_instance_id, Mat4::from_translation(old_position) * rotation);
+            return Some(MoveResult::Collided { instance_id: inner_id, side_index: crossed_side_index });
+        }
+        let point_on_plane = inner_blueprint.local_vertices[crossed_side.vertex_indices[0]];
+        let signed_distance = normal.dot(new_position_inner - point_on_plane);
+        let corrected_inner = new_position_inner - (signed_distance + PUSH_OUT_DISTANCE) * normal / normal.length_squared();
+        let corrected_position = inner_to_host.transform_point3(corrected_inner);
+        set_pose(state, host_instance_id, Mat4::from_translation(corrected_position) * rotation);
+        return Some(MoveResult::Collided { instance_id: inner_id, side_index: crossed_side_index });
+    }
+    None
+}
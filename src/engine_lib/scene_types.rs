@@ -1,108 +1,489 @@
-// src/engine_lib/scene_types.rs
-use glam::{Mat4, Vec3};
-use crate::rendering_lib::geometry::ConvexPolygon;
-
-// Type aliases for IDs
-pub type BlueprintId = u32;
-pub type InstanceId = u32;
-pub type PortalId = u32;
-pub type SideIndex = usize;
-
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub enum SideHandlerTypeId {
-    StandardWall,
-    StandardPortal,
-    Mirror,
-    CameraDisplay,
-    NonEuclideanPortal,
-    TransparentWall,
-}
-
-#[derive(Clone, Debug)]
-pub enum HandlerConfig {
-    StandardWall { color: [f32; 4], texture_id: Option<String> },
-    StandardPortal { target_instance_id: InstanceId, target_portal_id: PortalId },
-    Mirror { recursion_limit: u8, surface_reflectivity: f32 },
-    CameraDisplay { source_camera_id: String, refresh_rate: f32 },
-    NonEuclideanPortal { target_instance_id: InstanceId, target_portal_id: PortalId, transform_params: String },
-    TransparentWall { tint: [f32; 4], opacity: f32, ior: f32 },
-    None,
-}
-
-impl HandlerConfig {
-    pub fn get_intended_handler_type(&self) -> SideHandlerTypeId {
-        match self {
-            HandlerConfig::StandardWall { .. } => SideHandlerTypeId::StandardWall,
-            HandlerConfig::StandardPortal { .. } => SideHandlerTypeId::StandardPortal,
-            HandlerConfig::Mirror { .. } => SideHandlerTypeId::Mirror,
-            HandlerConfig::CameraDisplay { .. } => SideHandlerTypeId::CameraDisplay,
-            HandlerConfig::NonEuclideanPortal { .. } => SideHandlerTypeId::NonEuclideanPortal,
-            HandlerConfig::TransparentWall { .. } => SideHandlerTypeId::TransparentWall,
-            HandlerConfig::None => SideHandlerTypeId::StandardWall, // Default to wall if None
-        }
-    }
-}
-
-#[derive(Clone, Debug)]
-pub struct BlueprintSide {
-    pub vertex_indices: Vec<usize>,
-    pub local_normal: Vec3,
-    pub handler_type: SideHandlerTypeId,
-    pub default_handler_config: HandlerConfig,
-    pub local_portal_id: Option<PortalId>,
-}
-
-#[derive(Clone, Debug)]
-pub struct HullBlueprint {
-    pub id: BlueprintId,
-    pub name: String,
-    pub local_vertices: Vec<Vec3>,
-    pub sides: Vec<BlueprintSide>,
-}
-
-#[derive(Clone, Debug)]
-pub struct PortalConnectionInfo {
-    pub target_instance_id: InstanceId,
-    pub target_portal_id: PortalId,
-}
-
-#[derive(Clone, Debug)]
-pub struct HullInstance {
-    pub id: InstanceId,
-    pub name: String,
-    pub blueprint_id: BlueprintId,
-    pub initial_transform: Option<Mat4>,
-    pub portal_connections: std::collections::HashMap<PortalId, PortalConnectionInfo>,
-    pub instance_side_handler_configs: std::collections::HashMap<SideIndex, HandlerConfig>,
-}
-
-#[derive(Debug)]
-pub struct Scene {
-    pub blueprints: std::collections::HashMap<BlueprintId, HullBlueprint>,
-    pub instances: std::collections::HashMap<InstanceId, HullInstance>,
-    pub active_camera_instance_id: InstanceId,
-    pub active_camera_local_transform: Mat4,
-}
-
-#[derive(Clone)]
-pub struct TraversalState {
-    pub current_instance_id: InstanceId,
-    pub accumulated_transform: Mat4,
-    pub screen_space_clip_polygon: ConvexPolygon,
-    pub recursion_depth: u32,
-}
-
-// ADDED BoundaryCheckResult Enum
-#[derive(Debug, Clone, PartialEq)]
-pub enum BoundaryCheckResult {
-    Inside,
-    Collision {
-        collided_side_index: SideIndex,
-        collision_point: Vec3,
-    },
-    Traverse {
-        crossed_side_index: SideIndex,
-        target_instance_id: InstanceId,
-        target_portal_id: PortalId,
-    },
+// src/engine_lib/scene_types.rs
+use std::collections::HashSet;
+use glam::{Mat4, Vec3};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use crate::engine_lib::animation::SideAnimation;
+use crate::engine_lib::entity::{Entity, EntityId};
+use crate::engine_lib::trigger::TriggerVolume;
+use crate::engine_lib::events::EventBus;
+use crate::engine_lib::audio::{AcousticSettings, EmitterId, SoundEmitter};
+use crate::engine_lib::liquid::LiquidVolume;
+use crate::engine_lib::decal::{Decal, DecalId};
+use crate::rendering_lib::geometry::ScreenPolygon;
+
+// Type aliases for IDs
+pub type BlueprintId = u32;
+pub type InstanceId = u32;
+pub type PortalId = u32;
+pub type SideIndex = usize;
+
+// Bitmask of render layers. A side is drawn only if its layers, its instance's layers and the
+// render call's layer filter all share at least one bit.
+pub type RenderLayerMask = u32;
+pub const RENDER_LAYER_DEFAULT: RenderLayerMask = 1;
+pub const RENDER_LAYER_ALL: RenderLayerMask = u32::MAX;
+
+// Gravity of a hull instance that sets none of its own: down the blueprint's -Y.
+pub const DEFAULT_GRAVITY: Vec3 = Vec3::new(0.0, -9.8, 0.0);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SideHandlerTypeId {
+    StandardWall,
+    StandardPortal,
+    Mirror,
+    CameraDisplay,
+    NonEuclideanPortal,
+    TransparentWall,
+    Skybox,
+    Door,
+    LiquidSurface,
+    // A handler registered under this name; see `side_handler::SideHandlerRegistry`.
+    Custom(String),
+}
+
+// Who can see and who can walk through a standard portal. The default is open both ways.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PortalAccess {
+    // Drawn as a view into the target; otherwise drawn as a wall of `wall_color`.
+    pub visible: bool,
+    pub walkable: bool,
+    // Walking through also needs this key (or any condition the game names), see `Scene::grant_key`.
+    pub required_key: Option<String>,
+    pub wall_color: [f32; 4],
+}
+
+impl PortalAccess {
+    pub const OPEN: PortalAccess = PortalAccess { visible: true, walkable: true, required_key: None, wall_color: [0.7, 0.7, 0.7, 1.0] };
+
+    // Seen through like a window, but blocks like a wall.
+    pub fn visual_only() -> Self {
+        Self { walkable: false, ..Self::OPEN }
+    }
+
+    // Looks like a wall of `wall_color`, but can be walked through.
+    pub fn walk_only(wall_color: [f32; 4]) -> Self {
+        Self { visible: false, wall_color, ..Self::OPEN }
+    }
+
+    // Open to the eye, but only walkable while `key` is held.
+    pub fn gated(key: impl Into<String>) -> Self {
+        Self { required_key: Some(key.into()), ..Self::OPEN }
+    }
+
+    pub fn admits(&self, held_keys: &HashSet<String>) -> bool {
+        self.walkable && self.required_key.as_ref().is_none_or(|key| held_keys.contains(key))
+    }
+}
+
+impl Default for PortalAccess {
+    fn default() -> Self {
+        Self::OPEN
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum HandlerConfig {
+    // `emissive` is the light the wall gives off, as a multiple of its color added over it. Above
+    // 0 the wall is drawn brighter than white allows, so it stands out in dark rooms and bleeds
+    // into `RenderSettings::bloom`. `render_priority` is the draw-order bias of the wall's
+    // translucent or free-standing outside; see `HandlerConfig::render_priority`.
+    StandardWall { color: [f32; 4], texture_id: Option<String>, emissive: f32, render_priority: i32 },
+    StandardPortal { target_instance_id: InstanceId, target_portal_id: PortalId, access: PortalAccess },
+    Mirror { recursion_limit: u8, surface_reflectivity: f32 },
+    CameraDisplay { source_camera_id: String, refresh_rate: f32 },
+    NonEuclideanPortal { target_instance_id: InstanceId, target_portal_id: PortalId, transform_params: String },
+    TransparentWall { tint: [f32; 4], opacity: f32, ior: f32 },
+    // Procedural sky gradient seen "through" the side; up is the blueprint's +Y.
+    Skybox { zenith_color: [f32; 4], horizon_color: [f32; 4], nadir_color: [f32; 4] },
+    // A portal while open, a wall of `color` while closed. See `Scene::set_door_state`.
+    Door { target_instance_id: InstanceId, target_portal_id: PortalId, color: [f32; 4], open: bool, render_priority: i32 },
+    // The surface of a liquid: a portal to the other side, seen through a rippling `tint` from
+    // either side. See `liquid::LiquidVolume`.
+    LiquidSurface { target_instance_id: InstanceId, target_portal_id: PortalId, tint: [f32; 4], render_priority: i32 },
+    // Drawn by the handler registered under `type_id`, which reads its own settings from
+    // `params`; see `HandlerConfig::custom` and `HandlerConfig::custom_params`.
+    Custom { type_id: String, params: serde_json::Value },
+    None,
+}
+
+impl HandlerConfig {
+    pub fn get_intended_handler_type(&self) -> SideHandlerTypeId {
+        match self {
+            HandlerConfig::StandardWall { .. } => SideHandlerTypeId::StandardWall,
+            HandlerConfig::StandardPortal { .. } => SideHandlerTypeId::StandardPortal,
+            HandlerConfig::Mirror { .. } => SideHandlerTypeId::Mirror,
+            HandlerConfig::CameraDisplay { .. } => SideHandlerTypeId::CameraDisplay,
+            HandlerConfig::NonEuclideanPortal { .. } => SideHandlerTypeId::NonEuclideanPortal,
+            HandlerConfig::TransparentWall { .. } => SideHandlerTypeId::TransparentWall,
+            HandlerConfig::Skybox { .. } => SideHandlerTypeId::Skybox,
+            HandlerConfig::Door { .. } => SideHandlerTypeId::Door,
+            HandlerConfig::LiquidSurface { .. } => SideHandlerTypeId::LiquidSurface,
+            HandlerConfig::Custom { type_id, .. } => SideHandlerTypeId::Custom(type_id.clone()),
+            HandlerConfig::None => SideHandlerTypeId::StandardWall, // Default to wall if None
+        }
+    }
+
+    // A standard portal open both ways.
+    pub fn portal(target_instance_id: InstanceId, target_portal_id: PortalId) -> Self {
+        HandlerConfig::StandardPortal { target_instance_id, target_portal_id, access: PortalAccess::OPEN }
+    }
+
+    // A custom config with `params` serialized from any serde type.
+    pub fn custom(type_id: impl Into<String>, params: &impl Serialize) -> Result<Self, serde_json::Error> {
+        Ok(HandlerConfig::Custom { type_id: type_id.into(), params: serde_json::to_value(params)? })
+    }
+
+    // The params of a custom config read back as `T`. None for other configs, or if the
+    // params do not have `T`'s shape.
+    pub fn custom_params<T: DeserializeOwned>(&self) -> Option<T> {
+        match self {
+            HandlerConfig::Custom { params, .. } => serde_json::from_value(params.clone()).ok(),
+            _ => None,
+        }
+    }
+
+    // This config leading to a new target: a door stays a door with its color and state, a
+    // portal keeps its access, a liquid surface its tint, and anything else becomes an open
+    // standard portal.
+    pub fn retargeted(&self, target_instance_id: InstanceId, target_portal_id: PortalId) -> HandlerConfig {
+        match self {
+            HandlerConfig::Door { color, open, render_priority, .. } => HandlerConfig::Door { target_instance_id, target_portal_id, color: *color, open: *open, render_priority: *render_priority },
+            HandlerConfig::StandardPortal { access, .. } => HandlerConfig::StandardPortal { target_instance_id, target_portal_id, access: access.clone() },
+            HandlerConfig::LiquidSurface { tint, render_priority, .. } => HandlerConfig::LiquidSurface { target_instance_id, target_portal_id, tint: *tint, render_priority: *render_priority },
+            _ => HandlerConfig::portal(target_instance_id, target_portal_id),
+        }
+    }
+
+    // Draw-order bias of what the side composites in front of other things: overlays,
+    // translucent polygons and the outsides of free-standing instances, such as portal frames.
+    // Among the deferred draws of a hull, higher priorities go on top whatever their distance.
+    // 0 for configs that carry none.
+    pub fn render_priority(&self) -> i32 {
+        match self {
+            HandlerConfig::StandardWall { render_priority, .. }
+            | HandlerConfig::Door { render_priority, .. }
+            | HandlerConfig::LiquidSurface { render_priority, .. } => *render_priority,
+            _ => 0,
+        }
+    }
+
+    pub fn render_priority_mut(&mut self) -> Option<&mut i32> {
+        match self {
+            HandlerConfig::StandardWall { render_priority, .. }
+            | HandlerConfig::Door { render_priority, .. }
+            | HandlerConfig::LiquidSurface { render_priority, .. } => Some(render_priority),
+            _ => None,
+        }
+    }
+
+    // Where the side currently leads: a standard portal, however it can be passed, a liquid
+    // surface or an open door.
+    pub fn passable_portal_target(&self) -> Option<(InstanceId, PortalId)> {
+        match self {
+            HandlerConfig::StandardPortal { target_instance_id, target_portal_id, .. }
+            | HandlerConfig::LiquidSurface { target_instance_id, target_portal_id, .. }
+            | HandlerConfig::Door { target_instance_id, target_portal_id, open: true, .. } => {
+                Some((*target_instance_id, *target_portal_id))
+            }
+            _ => None,
+        }
+    }
+
+    // Where the side leads, if what is there can be seen through it.
+    pub fn visible_portal_target(&self) -> Option<(InstanceId, PortalId)> {
+        match self {
+            HandlerConfig::StandardPortal { access, .. } if !access.visible => None,
+            _ => self.passable_portal_target(),
+        }
+    }
+
+    // Where the side leads, if it can be walked through by someone holding `held_keys`.
+    pub fn walkable_portal_target(&self, held_keys: &HashSet<String>) -> Option<(InstanceId, PortalId)> {
+        match self {
+            HandlerConfig::StandardPortal { access, .. } if !access.admits(held_keys) => None,
+            _ => self.passable_portal_target(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BlueprintSide {
+    pub vertex_indices: Vec<usize>,
+    pub local_normal: Vec3,
+    pub handler_type: SideHandlerTypeId,
+    pub default_handler_config: HandlerConfig,
+    pub local_portal_id: Option<PortalId>,
+    pub render_layers: RenderLayerMask,
+}
+
+#[derive(Clone, Debug)]
+pub struct HullBlueprint {
+    pub id: BlueprintId,
+    pub name: String,
+    pub local_vertices: Vec<Vec3>,
+    pub sides: Vec<BlueprintSide>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PortalConnectionInfo {
+    pub target_instance_id: InstanceId,
+    pub target_portal_id: PortalId,
+}
+
+#[derive(Clone, Debug)]
+pub struct HullInstance {
+    pub id: InstanceId,
+    pub name: String,
+    pub blueprint_id: BlueprintId,
+    // World placement, or placement in the parent's blueprint space if `parent_instance_id` is
+    // set. When both ends of a portal are placed it replaces the portal-id based alignment, and
+    // placed instances inside another one are drawn and entered from it.
+    pub initial_transform: Option<Mat4>,
+    // Moving the parent moves its children with it. See `placement::instance_world_transform`.
+    pub parent_instance_id: Option<InstanceId>,
+    pub portal_connections: std::collections::HashMap<PortalId, PortalConnectionInfo>,
+    pub instance_side_handler_configs: std::collections::HashMap<SideIndex, HandlerConfig>,
+    pub render_layers: RenderLayerMask,
+    // Sides that move on this instance; see `animation::side_transform`.
+    pub side_animations: std::collections::HashMap<SideIndex, SideAnimation>,
+    pub triggers: Vec<TriggerVolume>,
+    pub acoustics: AcousticSettings,
+    pub liquid: Option<LiquidVolume>,
+    // Acceleration of walking cameras in the instance, in its blueprint space. They stand
+    // upright against it, so hulls joined by portals can each have their own "down"; zero is
+    // weightless.
+    pub gravity: Vec3,
+}
+
+impl HullInstance {
+    // Blueprint-space up: against gravity, or +Y when weightless.
+    pub fn up(&self) -> Vec3 {
+        (-self.gravity).try_normalize().unwrap_or(Vec3::Y)
+    }
+}
+
+// Exponential-squared distance fog, evaluated per fragment from camera-space depth.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FogSettings {
+    pub color: [f32; 4],
+    pub density: f32,
+}
+
+// What a debug label is attached to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LabelAnchor {
+    // The centroid of the instance's blueprint vertices.
+    Instance(InstanceId),
+    // The center of one of the instance's sides.
+    Side { instance_id: InstanceId, side_index: SideIndex },
+    // A point in the instance's blueprint space.
+    Point { instance_id: InstanceId, local_position: Vec3 },
+}
+
+impl LabelAnchor {
+    pub fn instance_id(&self) -> InstanceId {
+        match self {
+            LabelAnchor::Instance(instance_id) => *instance_id,
+            LabelAnchor::Side { instance_id, .. } | LabelAnchor::Point { instance_id, .. } => *instance_id,
+        }
+    }
+}
+
+// Development annotation, drawn at its anchor wherever that is visible, including through portals.
+#[derive(Clone, Debug)]
+pub struct DebugLabel {
+    pub anchor: LabelAnchor,
+    pub text: String,
+    pub color: [f32; 4],
+}
+
+pub const DEFAULT_LABEL_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+
+pub const DEFAULT_CAMERA_NAME: &str = "main";
+pub const DEFAULT_CAMERA_COLLISION_RADIUS: f32 = 0.1;
+
+// A named viewpoint: the hull instance it sits in and its pose in that instance's blueprint space.
+#[derive(Clone, Debug)]
+pub struct SceneCamera {
+    pub host_instance_id: InstanceId,
+    pub local_transform: Mat4,
+    // The camera collides as a sphere of this radius, so walls never come closer than the near plane.
+    pub collision_radius: f32,
+}
+
+#[derive(Debug)]
+pub struct Scene {
+    pub blueprints: std::collections::HashMap<BlueprintId, HullBlueprint>,
+    pub instances: std::collections::HashMap<InstanceId, HullInstance>,
+    pub cameras: std::collections::HashMap<String, SceneCamera>,
+    // Name of the camera driven by input and rendered to the main view.
+    pub active_camera: String,
+    pub fog: Option<FogSettings>,
+    // Props placed inside hull instances.
+    pub entities: std::collections::HashMap<EntityId, Entity>,
+    pub debug_labels: Vec<DebugLabel>,
+    pub sound_emitters: std::collections::HashMap<EmitterId, SoundEmitter>,
+    pub decals: std::collections::HashMap<DecalId, Decal>,
+    pub events: EventBus,
+    // Triggers holding the active camera as (instance, trigger index); see `trigger::update_trigger_occupancy`.
+    pub occupied_triggers: Vec<(InstanceId, usize)>,
+    // Keys, or any named conditions the game sets, that open gated portals; see `PortalAccess`.
+    pub held_keys: HashSet<String>,
+    // No instance id below this is handed out again: it is raised past every id given out or
+    // removed, so nothing still holding a removed instance's id ends up pointing at a new one.
+    // See `Scene::next_instance_id`.
+    pub instance_id_floor: u64,
+}
+
+impl Scene {
+    pub fn camera(&self, name: &str) -> Option<&SceneCamera> {
+        self.cameras.get(name)
+    }
+
+    pub fn camera_mut(&mut self, name: &str) -> Option<&mut SceneCamera> {
+        self.cameras.get_mut(name)
+    }
+
+    pub fn active_camera(&self) -> Option<&SceneCamera> {
+        self.cameras.get(&self.active_camera)
+    }
+
+    pub fn active_camera_mut(&mut self) -> Option<&mut SceneCamera> {
+        self.cameras.get_mut(&self.active_camera)
+    }
+
+    // The hull instance the active camera is in, whose `name` is shown as the current room.
+    pub fn active_instance(&self) -> Option<&HullInstance> {
+        self.instances.get(&self.active_camera()?.host_instance_id)
+    }
+
+    // Makes `name` the active camera. Returns false (and changes nothing) if there is no such camera.
+    pub fn set_active_camera(&mut self, name: &str) -> bool {
+        if !self.cameras.contains_key(name) {
+            return false;
+        }
+        self.active_camera = name.to_string();
+        true
+    }
+
+    // Adds or replaces a camera.
+    pub fn insert_camera(&mut self, name: impl Into<String>, camera: SceneCamera) {
+        self.cameras.insert(name.into(), camera);
+    }
+
+    pub fn add_debug_label(&mut self, anchor: LabelAnchor, text: impl Into<String>) {
+        self.debug_labels.push(DebugLabel { anchor, text: text.into(), color: DEFAULT_LABEL_COLOR });
+    }
+
+    pub fn clear_debug_labels(&mut self) {
+        self.debug_labels.clear();
+    }
+
+    // Opens or closes the door on a side. A door configured on the blueprint gets an instance
+    // override so other instances of it are unaffected. Returns false if the side has no door.
+    pub fn set_door_state(&mut self, instance_id: InstanceId, side_index: SideIndex, open: bool) -> bool {
+        let instance = match self.instances.get_mut(&instance_id) {
+            Some(instance) => instance,
+            None => return false,
+        };
+        if !instance.instance_side_handler_configs.contains_key(&side_index) {
+            let blueprint_config = self.blueprints.get(&instance.blueprint_id)
+                .and_then(|blueprint| blueprint.sides.get(side_index))
+                .map(|side| &side.default_handler_config);
+            match blueprint_config {
+                Some(config @ HandlerConfig::Door { .. }) => {
+                    instance.instance_side_handler_configs.insert(side_index, config.clone());
+                }
+                _ => return false,
+            }
+        }
+        match instance.instance_side_handler_configs.get_mut(&side_index) {
+            Some(HandlerConfig::Door { open: door_open, .. }) => {
+                *door_open = open;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn grant_key(&mut self, key: impl Into<String>) {
+        self.held_keys.insert(key.into());
+    }
+
+    // Returns true if the key was held.
+    pub fn revoke_key(&mut self, key: &str) -> bool {
+        self.held_keys.remove(key)
+    }
+
+    pub fn has_key(&self, key: &str) -> bool {
+        self.held_keys.contains(key)
+    }
+
+    // Camera names in sorted order, for stable UI listings.
+    pub fn camera_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.cameras.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    // Names need not be unique; the lookups by name return the match with the lowest id.
+    pub fn instance_by_name(&self, name: &str) -> Option<&HullInstance> {
+        self.instances.values().filter(|instance| instance.name == name).min_by_key(|instance| instance.id)
+    }
+
+    pub fn instance_by_name_mut(&mut self, name: &str) -> Option<&mut HullInstance> {
+        self.instances.values_mut().filter(|instance| instance.name == name).min_by_key(|instance| instance.id)
+    }
+
+    pub fn blueprint_by_name(&self, name: &str) -> Option<&HullBlueprint> {
+        self.blueprints.values().filter(|blueprint| blueprint.name == name).min_by_key(|blueprint| blueprint.id)
+    }
+
+    // The config a side of an instance actually uses: its instance override, else the blueprint's.
+    pub fn side_config(&self, instance_id: InstanceId, side_index: SideIndex) -> Option<&HandlerConfig> {
+        let instance = self.instances.get(&instance_id)?;
+        instance.instance_side_handler_configs.get(&side_index)
+            .or_else(|| self.blueprints.get(&instance.blueprint_id)?.sides.get(side_index).map(|side| &side.default_handler_config))
+    }
+
+    // Every instance side whose effective config is of `handler_type`, as (instance, side,
+    // config), in instance id then side order.
+    pub fn sides_with_handler(&self, handler_type: SideHandlerTypeId) -> impl Iterator<Item = (InstanceId, SideIndex, &HandlerConfig)> + '_ {
+        let mut instance_ids: Vec<InstanceId> = self.instances.keys().copied().collect();
+        instance_ids.sort_unstable();
+        instance_ids.into_iter().flat_map(move |instance_id| {
+            let side_count = self.instances.get(&instance_id)
+                .and_then(|instance| self.blueprints.get(&instance.blueprint_id))
+                .map_or(0, |blueprint| blueprint.sides.len());
+            (0..side_count).filter_map(move |side_index| self.side_config(instance_id, side_index).map(|config| (instance_id, side_index, config)))
+        })
+        .filter(move |(_, _, config)| config.get_intended_handler_type() == handler_type)
+    }
+}
+
+#[derive(Clone)]
+pub struct TraversalState {
+    pub current_instance_id: InstanceId,
+    pub accumulated_transform: Mat4,
+    pub screen_space_clip_polygon: ScreenPolygon,
+    pub recursion_depth: u32,
+    // Instance the traversal came from, if any. A free-standing instance is not drawn from the
+    // hull around it when that is where the view just left it.
+    pub entered_from_instance_id: Option<InstanceId>,
+}
+
+// ADDED BoundaryCheckResult Enum
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoundaryCheckResult {
+    Inside,
+    Collision {
+        collided_side_index: SideIndex,
+        collision_point: Vec3,
+    },
+    Traverse {
+        crossed_side_index: SideIndex,
+        target_instance_id: InstanceId,
+        target_portal_id: PortalId,
+    },
 }
\ No newline at end of file
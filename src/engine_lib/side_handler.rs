@@ -1,163 +1,396 @@
-// src/engine_lib/side_handler.rs
-
-use std::collections::VecDeque;
-use glam::{Mat4, Vec3};
-use crate::engine_lib::scene_types::{
-    Scene, HandlerConfig,
-    HullInstance, BlueprintSide, TraversalState, PortalId,
-};
-use crate::engine_lib::camera::Camera;
-use crate::rendering_lib::geometry::ConvexPolygon;
-use crate::rendering_lib::vertex::Vertex;
-use crate::demo_scene::{
-    PORTAL_ID_FRONT, PORTAL_ID_BACK, PORTAL_ID_LEFT, PORTAL_ID_RIGHT, PORTAL_ID_TOP, PORTAL_ID_BOTTOM,
-};
-
-pub const MAX_PORTAL_RECURSION_DEPTH: u32 = 10;
-
-pub struct HandlerContext<'a> {
-    pub frame_vertices: &'a mut Vec<Vertex>,
-    pub frame_indices: &'a mut Vec<u16>,
-    pub scene: &'a Scene,
-    pub camera: &'a Camera,
-    pub current_instance: &'a HullInstance,
-    pub blueprint_side: &'a BlueprintSide,
-    pub side_config: &'a HandlerConfig,
-    pub transform_to_camera_host_hull: &'a Mat4,
-    pub camera_view_from_host_hull: &'a Mat4,
-    pub screen_width: f32,
-    pub screen_height: f32,
-    pub visible_screen_polygon: ConvexPolygon,
-    pub traversal_queue: &'a mut VecDeque<TraversalState>,
-    pub current_recursion_depth: u32,
-}
-
-pub trait SideHandler: Send + Sync {
-    fn process_render(&self, ctx: &mut HandlerContext);
-}
-
-pub struct StandardWallHandler;
-impl SideHandler for StandardWallHandler {
-    fn process_render(&self, ctx: &mut HandlerContext) {
-        let wall_color = match ctx.side_config {
-            HandlerConfig::StandardWall { color, .. } => *color,
-            _ => [0.7, 0.7, 0.7, 1.0],
-        };
-        if ctx.visible_screen_polygon.count() >= 3 {
-            let start_vertex_index = ctx.frame_vertices.len() as u16;
-            for point in ctx.visible_screen_polygon.vertices() {
-                ctx.frame_vertices.push(Vertex::new([point.x, point.y], wall_color));
-            }
-            for i in 1..(ctx.visible_screen_polygon.count() as u16 - 1) {
-                ctx.frame_indices.push(start_vertex_index);
-                ctx.frame_indices.push(start_vertex_index + i);
-                ctx.frame_indices.push(start_vertex_index + i + 1);
-            }
-        }
-    }
-}
-
-pub fn get_portal_alignment_transform(
-    source_portal_id_on_current_bp: PortalId,
-    target_portal_id_on_target_bp: PortalId,
-) -> Mat4 {
-    let room_half_size = 1.5;
-
-    match (source_portal_id_on_current_bp, target_portal_id_on_target_bp) {
-        (PORTAL_ID_FRONT, PORTAL_ID_BACK) => {
-            Mat4::from_translation(Vec3::new(0.0, 0.0, room_half_size * 2.0))
-        }
-        (PORTAL_ID_BACK, PORTAL_ID_FRONT) => {
-            Mat4::from_translation(Vec3::new(0.0, 0.0, -room_half_size * 2.0))
-        }
-        (PORTAL_ID_RIGHT, PORTAL_ID_LEFT) => {
-            Mat4::from_translation(Vec3::new(room_half_size * 2.0, 0.0, 0.0))
-        }
-        (PORTAL_ID_LEFT, PORTAL_ID_RIGHT) => {
-            Mat4::from_translation(Vec3::new(-room_half_size * 2.0, 0.0, 0.0))
-        }
-        (PORTAL_ID_TOP, PORTAL_ID_BOTTOM) => {
-            Mat4::from_translation(Vec3::new(0.0, room_half_size * 2.0, 0.0))
-        }
-        (PORTAL_ID_BOTTOM, PORTAL_ID_TOP) => {
-            Mat4::from_translation(Vec3::new(0.0, -room_half_size * 2.0, 0.0))
-        }
-        _ => {
-            Mat4::IDENTITY
-        }
-    }
-}
-
-pub struct StandardPortalHandler;
-impl SideHandler for StandardPortalHandler {
-    fn process_render(&self, ctx: &mut HandlerContext) {
-        let (target_instance_id_from_config, target_portal_id_on_target_bp_from_config) = match ctx.side_config {
-            HandlerConfig::StandardPortal { target_instance_id, target_portal_id } => (target_instance_id, target_portal_id),
-            _ => { return; }
-        };
-
-        let portal_local_normal_vec = ctx.blueprint_side.local_normal;
-
-        // Calculate normal_in_cam_space
-        let normal_in_host_bp_space = ctx.transform_to_camera_host_hull.transform_vector3(portal_local_normal_vec).normalize_or_zero();
-        let normal_in_cam_space = ctx.camera_view_from_host_hull.transform_vector3(normal_in_host_bp_space).normalize_or_zero();
-
-        // --- New Culling Logic ---
-        // Get a point on the portal plane in blueprint local space
-        if ctx.blueprint_side.vertex_indices.is_empty() {
-            // This side has no vertices, cannot be a portal plane
-            return;
-        }
-        let p0_bp_local_idx = ctx.blueprint_side.vertex_indices[0];
-        
-        // Access blueprint through scene context to get local vertices
-        let p0_bp_local = match ctx.scene.blueprints.get(&ctx.current_instance.blueprint_id) {
-            Some(blueprint) => {
-                if p0_bp_local_idx < blueprint.local_vertices.len() {
-                    blueprint.local_vertices[p0_bp_local_idx]
-                } else {
-                    // Invalid vertex index for blueprint
-                    return; 
-                }
-            }
-            None => {
-                // Blueprint not found in scene, should not happen
-                return; 
-            }
-        };
-
-        // Transform P0 to camera space
-        let p0_host_hull_space = ctx.transform_to_camera_host_hull.transform_point3(p0_bp_local);
-        let p0_cam_space = ctx.camera_view_from_host_hull.transform_point3(p0_host_hull_space);
-
-        let d_plane_constant = -normal_in_cam_space.dot(p0_cam_space);
-
-        let culling_epsilon = 1e-5; 
-        if d_plane_constant < -culling_epsilon {
-            return; // Cull
-        }
-
-        // Original culling logic (for reference, now replaced):
-        // let cull_threshold_z = 1e-3;
-        // if normal_in_cam_space.z <= cull_threshold_z {
-        //     return;
-        // }
-
-        if ctx.current_recursion_depth >= MAX_PORTAL_RECURSION_DEPTH { return; }
-        if !ctx.scene.instances.contains_key(target_instance_id_from_config) { return; }
-
-        let portal_alignment_transform = get_portal_alignment_transform(
-            ctx.blueprint_side.local_portal_id.expect("Portal handler on side with no local_portal_id"),
-            *target_portal_id_on_target_bp_from_config
-        );
-        
-        let next_transform_to_camera_host_hull = *ctx.transform_to_camera_host_hull * portal_alignment_transform;
-
-        ctx.traversal_queue.push_back(TraversalState {
-            current_instance_id: *target_instance_id_from_config,
-            accumulated_transform: next_transform_to_camera_host_hull,
-            screen_space_clip_polygon: ctx.visible_screen_polygon.clone(),
-            recursion_depth: ctx.current_recursion_depth + 1,
-        });
-    }
-}
\ No newline at end of file
+// src/engine_lib/side_handler.rs
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use glam::{Mat4, Vec3};
+use crate::engine_lib::scene_types::{
+    Scene, HandlerConfig,
+    HullInstance, BlueprintSide, TraversalState, InstanceId, PortalId,
+};
+use crate::engine_lib::camera::Camera;
+use crate::engine_lib::placement::{portal_side_motion, portal_transform};
+use crate::engine_lib::liquid::liquid_in;
+use crate::rendering_lib::geometry::{Point2, ScreenPolygon};
+use crate::rendering_lib::material::{BlendMode, Material};
+use crate::rendering_lib::triangulation::push_fan_indices;
+use crate::rendering_lib::vertex::{Vertex, SkyVertex};
+use crate::demo_scene::{
+    PORTAL_ID_FRONT, PORTAL_ID_BACK, PORTAL_ID_LEFT, PORTAL_ID_RIGHT, PORTAL_ID_TOP, PORTAL_ID_BOTTOM,
+};
+
+pub const MAX_PORTAL_RECURSION_DEPTH: u32 = 10;
+
+pub struct HandlerContext<'a> {
+    pub frame_vertices: &'a mut Vec<Vertex>,
+    pub frame_indices: &'a mut Vec<u32>,
+    pub frame_sky_vertices: &'a mut Vec<SkyVertex>,
+    pub frame_sky_indices: &'a mut Vec<u32>,
+    pub scene: &'a Scene,
+    pub camera: &'a Camera,
+    pub current_instance: &'a HullInstance,
+    pub blueprint_side: &'a BlueprintSide,
+    pub side_config: &'a HandlerConfig,
+    pub transform_to_camera_host_hull: &'a Mat4,
+    pub camera_view_from_host_hull: &'a Mat4,
+    pub screen_width: f32,
+    pub screen_height: f32,
+    pub visible_screen_polygon: ScreenPolygon,
+    pub traversal_queue: &'a mut VecDeque<TraversalState>,
+    pub current_recursion_depth: u32,
+    // How everything the handler emits into `frame_indices` is drawn. Starts opaque.
+    pub material: Material,
+    // Drawn over what the side lets the view through to; see `emit_overlay`.
+    pub overlays: &'a mut Vec<SideOverlay>,
+}
+
+// A polygon to draw, alpha blended, over whatever a side lets the view through to once that has
+// been drawn, such as the tint of a liquid surface over the world under it.
+#[derive(Clone, Debug)]
+pub struct SideOverlay {
+    pub polygon: ScreenPolygon,
+    // The side's plane in camera space, which the corners' depths are taken from.
+    pub plane: Option<(Vec3, Vec3)>,
+    // One per corner of `polygon`.
+    pub colors: Vec<[f32; 4]>,
+}
+
+impl HandlerContext<'_> {
+    // Returns the plane of the current side in camera view space as (normal, point on plane).
+    pub fn side_plane_in_camera_space(&self) -> Option<(Vec3, Vec3)> {
+        let blueprint = self.scene.blueprints.get(&self.current_instance.blueprint_id)?;
+        let p0_bp_local = *blueprint.local_vertices.get(*self.blueprint_side.vertex_indices.first()?)?;
+
+        let normal_in_host_bp_space = self.transform_to_camera_host_hull.transform_vector3(self.blueprint_side.local_normal);
+        let normal_in_cam_space = self.camera_view_from_host_hull.transform_vector3(normal_in_host_bp_space).normalize_or_zero();
+        let p0_host_hull_space = self.transform_to_camera_host_hull.transform_point3(p0_bp_local);
+        let p0_cam_space = self.camera_view_from_host_hull.transform_point3(p0_host_hull_space);
+        Some((normal_in_cam_space, p0_cam_space))
+    }
+
+    // Camera-space depth (-z) of the point on `plane` seen through `p_screen`.
+    pub fn depth_on_plane(&self, plane: &(Vec3, Vec3), p_screen: &Point2) -> f32 {
+        depth_on_plane(self.camera, self.screen_width, self.screen_height, plane, p_screen)
+    }
+
+    // Appends the visible screen polygon as a triangle fan with per-vertex depth. A see-through
+    // `color` switches the side's material to alpha blending.
+    pub fn emit_visible_polygon(&mut self, color: [f32; 4]) {
+        if Material::for_color(color).blend == BlendMode::Alpha {
+            self.material.blend = BlendMode::Alpha;
+        }
+        let plane = self.side_plane_in_camera_space();
+        emit_polygon_on_plane(
+            self.frame_vertices, self.frame_indices, self.camera, self.screen_width, self.screen_height,
+            &self.visible_screen_polygon, plane.as_ref(), color,
+        );
+    }
+
+    // Queues the visible polygon as an overlay, with `color_at` giving each corner's color from
+    // its screen position.
+    pub fn emit_overlay(&mut self, color_at: impl Fn(Point2<f32>) -> [f32; 4]) {
+        if self.visible_screen_polygon.count() < 3 {
+            return;
+        }
+        let colors = self.visible_screen_polygon.vertices().iter().map(Point2::cast::<f32>).map(color_at).collect();
+        self.overlays.push(SideOverlay {
+            polygon: self.visible_screen_polygon.clone(),
+            plane: self.side_plane_in_camera_space(),
+            colors,
+        });
+    }
+}
+
+// Camera-space depth (-z) of the point on `plane` (normal, point on plane) seen through
+// `p_screen`. Falls back to the depth of the plane's reference point when the ray is edge-on.
+pub fn depth_on_plane(camera: &Camera, screen_width: f32, screen_height: f32, plane: &(Vec3, Vec3), p_screen: &Point2) -> f32 {
+    let (normal, point_on_plane) = plane;
+    let ray = camera.screen_to_camera_ray(p_screen, screen_width, screen_height);
+    let denom = normal.dot(ray);
+    if denom.abs() < 1e-6 {
+        return -point_on_plane.z;
+    }
+    // The ray has z == -1, so the ray parameter is the depth itself.
+    (normal.dot(*point_on_plane) / denom).max(camera.znear)
+}
+
+// Appends `polygon` as a triangle fan, with each vertex's depth taken from the camera-space
+// `plane` it lies on (or the near plane if there is none).
+#[allow(clippy::too_many_arguments)]
+pub fn emit_polygon_on_plane(
+    frame_vertices: &mut Vec<Vertex>,
+    frame_indices: &mut Vec<u32>,
+    camera: &Camera,
+    screen_width: f32,
+    screen_height: f32,
+    polygon: &ScreenPolygon,
+    plane: Option<&(Vec3, Vec3)>,
+    color: [f32; 4],
+) {
+    if polygon.count() < 3 {
+        return;
+    }
+    let start_vertex_index = frame_vertices.len() as u32;
+    for point in polygon.vertices().iter().map(Point2::cast::<f32>) {
+        let depth = match plane {
+            Some(plane) => depth_on_plane(camera, screen_width, screen_height, plane, &point),
+            None => camera.znear,
+        };
+        frame_vertices.push(Vertex::with_depth([point.x, point.y], color, depth));
+    }
+    push_fan_indices(frame_indices, start_vertex_index, polygon.count());
+}
+
+pub trait SideHandler: Send + Sync {
+    fn process_render(&self, ctx: &mut HandlerContext);
+}
+
+// User-defined handlers, looked up by the `type_id` of `HandlerConfig::Custom` sides. A side
+// whose type has no handler registered draws nothing.
+#[derive(Clone, Default)]
+pub struct SideHandlerRegistry {
+    handlers: HashMap<String, Arc<dyn SideHandler>>,
+}
+
+impl SideHandlerRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds or replaces the handler for `type_id`.
+    pub fn register(&mut self, type_id: impl Into<String>, handler: impl SideHandler + 'static) {
+        self.handlers.insert(type_id.into(), Arc::new(handler));
+    }
+
+    // Returns true if a handler was registered under `type_id`.
+    pub fn unregister(&mut self, type_id: &str) -> bool {
+        self.handlers.remove(type_id).is_some()
+    }
+
+    pub fn get(&self, type_id: &str) -> Option<&dyn SideHandler> {
+        self.handlers.get(type_id).map(|handler| handler.as_ref())
+    }
+
+    // Registered type ids in sorted order.
+    pub fn type_ids(&self) -> Vec<&str> {
+        let mut type_ids: Vec<&str> = self.handlers.keys().map(String::as_str).collect();
+        type_ids.sort_unstable();
+        type_ids
+    }
+}
+
+pub struct StandardWallHandler;
+impl SideHandler for StandardWallHandler {
+    fn process_render(&self, ctx: &mut HandlerContext) {
+        let wall_color = match ctx.side_config {
+            HandlerConfig::StandardWall { color, emissive, .. } => emissive_color(*color, *emissive),
+            _ => [0.7, 0.7, 0.7, 1.0],
+        };
+        ctx.emit_visible_polygon(wall_color);
+    }
+}
+
+// `color` with `emissive` times itself added, as an HDR color; alpha is kept.
+pub fn emissive_color(color: [f32; 4], emissive: f32) -> [f32; 4] {
+    let scale = 1.0 + emissive.max(0.0);
+    [color[0] * scale, color[1] * scale, color[2] * scale, color[3]]
+}
+
+pub fn get_portal_alignment_transform(
+    source_portal_id_on_current_bp: PortalId,
+    target_portal_id_on_target_bp: PortalId,
+) -> Mat4 {
+    let room_half_size = 1.5;
+
+    match (source_portal_id_on_current_bp, target_portal_id_on_target_bp) {
+        (PORTAL_ID_FRONT, PORTAL_ID_BACK) => {
+            Mat4::from_translation(Vec3::new(0.0, 0.0, room_half_size * 2.0))
+        }
+        (PORTAL_ID_BACK, PORTAL_ID_FRONT) => {
+            Mat4::from_translation(Vec3::new(0.0, 0.0, -room_half_size * 2.0))
+        }
+        (PORTAL_ID_RIGHT, PORTAL_ID_LEFT) => {
+            Mat4::from_translation(Vec3::new(room_half_size * 2.0, 0.0, 0.0))
+        }
+        (PORTAL_ID_LEFT, PORTAL_ID_RIGHT) => {
+            Mat4::from_translation(Vec3::new(-room_half_size * 2.0, 0.0, 0.0))
+        }
+        (PORTAL_ID_TOP, PORTAL_ID_BOTTOM) => {
+            Mat4::from_translation(Vec3::new(0.0, room_half_size * 2.0, 0.0))
+        }
+        (PORTAL_ID_BOTTOM, PORTAL_ID_TOP) => {
+            Mat4::from_translation(Vec3::new(0.0, -room_half_size * 2.0, 0.0))
+        }
+        _ => {
+            Mat4::IDENTITY
+        }
+    }
+}
+
+pub struct StandardPortalHandler;
+impl SideHandler for StandardPortalHandler {
+    fn process_render(&self, ctx: &mut HandlerContext) {
+        match ctx.side_config {
+            HandlerConfig::StandardPortal { target_instance_id, target_portal_id, access } if access.visible => {
+                queue_portal_traversal(ctx, *target_instance_id, *target_portal_id);
+            }
+            // Walk-only: passes for a wall.
+            HandlerConfig::StandardPortal { access, .. } => {
+                let color = access.wall_color;
+                ctx.emit_visible_polygon(color);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Queues the instance behind the current side for traversal, unless the side faces away from
+// the camera or the recursion limit is reached. Custom handlers of see-through sides, such as
+// tinted glass portals, call it before emitting their translucent polygon.
+pub fn queue_portal_traversal(
+    ctx: &mut HandlerContext,
+    target_instance_id_from_config: InstanceId,
+    target_portal_id_on_target_bp_from_config: PortalId,
+) {
+    let portal_local_normal_vec = ctx.blueprint_side.local_normal;
+
+    // Calculate normal_in_cam_space
+    let normal_in_host_bp_space = ctx.transform_to_camera_host_hull.transform_vector3(portal_local_normal_vec).normalize_or_zero();
+    let normal_in_cam_space = ctx.camera_view_from_host_hull.transform_vector3(normal_in_host_bp_space).normalize_or_zero();
+
+    // --- New Culling Logic ---
+    // Get a point on the portal plane in blueprint local space
+    if ctx.blueprint_side.vertex_indices.is_empty() {
+        // This side has no vertices, cannot be a portal plane
+        return;
+    }
+    let p0_bp_local_idx = ctx.blueprint_side.vertex_indices[0];
+    
+    // Access blueprint through scene context to get local vertices
+    let p0_bp_local = match ctx.scene.blueprints.get(&ctx.current_instance.blueprint_id) {
+        Some(blueprint) if p0_bp_local_idx < blueprint.local_vertices.len() => {
+            blueprint.local_vertices[p0_bp_local_idx]
+        }
+        Some(_) => {
+            // Invalid vertex index for blueprint
+            return;
+        }
+        None => {
+            // Blueprint not found in scene, should not happen
+            return; 
+        }
+    };
+
+    // Transform P0 to camera space
+    let p0_host_hull_space = ctx.transform_to_camera_host_hull.transform_point3(p0_bp_local);
+    let p0_cam_space = ctx.camera_view_from_host_hull.transform_point3(p0_host_hull_space);
+
+    let d_plane_constant = -normal_in_cam_space.dot(p0_cam_space);
+
+    let culling_epsilon = 1e-5; 
+    if d_plane_constant < -culling_epsilon {
+        return; // Cull
+    }
+
+    // Original culling logic (for reference, now replaced):
+    // let cull_threshold_z = 1e-3;
+    // if normal_in_cam_space.z <= cull_threshold_z {
+    //     return;
+    // }
+
+    if ctx.current_recursion_depth >= MAX_PORTAL_RECURSION_DEPTH { return; }
+    if !ctx.scene.instances.contains_key(&target_instance_id_from_config) { return; }
+    // A side with no portal id of its own has nothing for the target to line up with.
+    let Some(portal_id) = ctx.blueprint_side.local_portal_id else { return; };
+
+    let portal_alignment_transform = portal_transform(
+        ctx.scene,
+        ctx.current_instance.id,
+        portal_id,
+        target_instance_id_from_config,
+        target_portal_id_on_target_bp_from_config,
+    );
+    
+    // The side's own motion is already in `transform_to_camera_host_hull`; the target's portal
+    // side has to be brought back onto it.
+    let target_side_motion = portal_side_motion(ctx.scene, target_instance_id_from_config, target_portal_id_on_target_bp_from_config);
+    let next_transform_to_camera_host_hull = *ctx.transform_to_camera_host_hull * portal_alignment_transform * target_side_motion.inverse();
+
+    ctx.traversal_queue.push_back(TraversalState {
+        current_instance_id: target_instance_id_from_config,
+        accumulated_transform: next_transform_to_camera_host_hull,
+        screen_space_clip_polygon: ctx.visible_screen_polygon.clone(),
+        recursion_depth: ctx.current_recursion_depth + 1,
+        entered_from_instance_id: Some(ctx.current_instance.id),
+    });
+}
+
+pub struct DoorHandler;
+impl SideHandler for DoorHandler {
+    fn process_render(&self, ctx: &mut HandlerContext) {
+        match ctx.side_config {
+            HandlerConfig::Door { target_instance_id, target_portal_id, open: true, .. } => {
+                queue_portal_traversal(ctx, *target_instance_id, *target_portal_id);
+            }
+            HandlerConfig::Door { color, open: false, .. } => {
+                let color = *color;
+                ctx.emit_visible_polygon(color);
+            }
+            _ => {}
+        }
+    }
+}
+
+// Screen pixels per radian of the ripple bands across a liquid surface, and how much they
+// brighten and darken its tint.
+const RIPPLE_WAVELENGTH: f32 = 24.0;
+const RIPPLE_AMPLITUDE: f32 = 0.15;
+
+pub struct LiquidSurfaceHandler;
+impl SideHandler for LiquidSurfaceHandler {
+    fn process_render(&self, ctx: &mut HandlerContext) {
+        let (target_instance_id, target_portal_id, tint) = match ctx.side_config {
+            HandlerConfig::LiquidSurface { target_instance_id, target_portal_id, tint, .. } => (*target_instance_id, *target_portal_id, *tint),
+            _ => return,
+        };
+        queue_portal_traversal(ctx, target_instance_id, target_portal_id);
+        // The ripples move with whichever side of the surface holds the liquid.
+        let phase = [ctx.current_instance.id, target_instance_id].into_iter()
+            .find_map(|instance_id| liquid_in(ctx.scene, instance_id))
+            .map_or(0.0, |liquid| liquid.ripple_phase);
+        ctx.emit_overlay(|point| {
+            let shimmer = 1.0 + RIPPLE_AMPLITUDE * (phase + (point.x + 0.5 * point.y) / RIPPLE_WAVELENGTH).sin();
+            [tint[0] * shimmer, tint[1] * shimmer, tint[2] * shimmer, tint[3]]
+        });
+    }
+}
+
+pub struct SkyboxHandler;
+impl SideHandler for SkyboxHandler {
+    fn process_render(&self, ctx: &mut HandlerContext) {
+        let (zenith_color, horizon_color, nadir_color) = match ctx.side_config {
+            HandlerConfig::Skybox { zenith_color, horizon_color, nadir_color } => (*zenith_color, *horizon_color, *nadir_color),
+            _ => { return; }
+        };
+        if ctx.visible_screen_polygon.count() < 3 {
+            return;
+        }
+
+        // Camera view space -> this hull's blueprint space. The sky is infinitely far away,
+        // so only the rotation part matters (transform_vector3 ignores translation).
+        let camera_to_hull_bp = (*ctx.camera_view_from_host_hull * *ctx.transform_to_camera_host_hull).inverse();
+
+        let start_vertex_index = ctx.frame_sky_vertices.len() as u32;
+        for point in ctx.visible_screen_polygon.vertices().iter().map(Point2::cast::<f32>) {
+            let ray_cam_space = ctx.camera.screen_to_camera_ray(&point, ctx.screen_width, ctx.screen_height);
+            let direction = camera_to_hull_bp.transform_vector3(ray_cam_space);
+            ctx.frame_sky_vertices.push(SkyVertex {
+                position: [point.x, point.y],
+                direction: direction.to_array(),
+                zenith_color,
+                horizon_color,
+                nadir_color,
+            });
+        }
+        push_fan_indices(ctx.frame_sky_indices, start_vertex_index, ctx.visible_screen_polygon.count());
+    }
+}
@@ -0,0 +1,223 @@
+// src/engine_lib/spawning.rs
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::engine_lib::audio::AcousticSettings;
+use crate::engine_lib::events::EngineEvent;
+use crate::engine_lib::placement::set_instance_parent;
+use crate::engine_lib::scene_types::{
+    Scene, HullInstance, HandlerConfig, PortalConnectionInfo, BlueprintId, InstanceId, PortalId, SideIndex, RENDER_LAYER_ALL, DEFAULT_GRAVITY,
+};
+
+// Color of the wall left where a portal led into a removed instance and the blueprint has no
+// wall of its own there.
+pub const SEALED_PORTAL_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpawnError {
+    MissingBlueprint(BlueprintId),
+    // The blueprint has no side with this portal id.
+    MissingPortal(PortalId),
+    // The same portal of the new instance was given two connections.
+    DuplicatePortal(PortalId),
+    MissingTargetInstance(InstanceId),
+    MissingTargetPortal { instance_id: InstanceId, portal_id: PortalId },
+    // The target portal already leads into another instance.
+    TargetPortalInUse { instance_id: InstanceId, portal_id: PortalId },
+    // Every instance id is taken.
+    NoFreeInstanceId,
+}
+
+impl fmt::Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpawnError::MissingBlueprint(blueprint_id) => write!(f, "blueprint {} does not exist", blueprint_id),
+            SpawnError::MissingPortal(portal_id) => write!(f, "blueprint has no portal {}", portal_id),
+            SpawnError::DuplicatePortal(portal_id) => write!(f, "portal {} is connected twice", portal_id),
+            SpawnError::MissingTargetInstance(instance_id) => write!(f, "target instance {} does not exist", instance_id),
+            SpawnError::MissingTargetPortal { instance_id, portal_id } =>
+                write!(f, "instance {} has no portal {}", instance_id, portal_id),
+            SpawnError::TargetPortalInUse { instance_id, portal_id } =>
+                write!(f, "portal {} of instance {} is already connected", portal_id, instance_id),
+            SpawnError::NoFreeInstanceId => write!(f, "no free instance id"),
+        }
+    }
+}
+
+impl std::error::Error for SpawnError {}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RemoveInstanceError {
+    MissingInstance(InstanceId),
+    // Cameras are never left without a hull; move them out first.
+    HostsCamera { camera: String },
+}
+
+impl fmt::Display for RemoveInstanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RemoveInstanceError::MissingInstance(instance_id) => write!(f, "instance {} does not exist", instance_id),
+            RemoveInstanceError::HostsCamera { camera } => write!(f, "camera '{}' is inside the instance", camera),
+        }
+    }
+}
+
+impl std::error::Error for RemoveInstanceError {}
+
+fn portal_target(config: &HandlerConfig) -> Option<InstanceId> {
+    match config {
+        HandlerConfig::StandardPortal { target_instance_id, .. }
+        | HandlerConfig::Door { target_instance_id, .. }
+        | HandlerConfig::LiquidSurface { target_instance_id, .. } => Some(*target_instance_id),
+        _ => None,
+    }
+}
+
+impl Scene {
+    // The side of an instance carrying `portal_id`, with its effective config.
+    fn portal_side(&self, instance_id: InstanceId, portal_id: PortalId) -> Option<(SideIndex, &HandlerConfig)> {
+        let instance = self.instances.get(&instance_id)?;
+        let blueprint = self.blueprints.get(&instance.blueprint_id)?;
+        let side_index = blueprint.sides.iter().position(|side| side.local_portal_id == Some(portal_id))?;
+        Some((side_index, self.side_config(instance_id, side_index)?))
+    }
+
+    // The id the next new instance gets: the lowest above every instance's and `instance_id_floor`,
+    // so no id is ever handed out twice. None once the ids run out.
+    pub fn next_instance_id(&self) -> Option<InstanceId> {
+        let above_instances = self.instances.keys().max().map_or(0, |&max_id| max_id as u64 + 1);
+        InstanceId::try_from(self.instance_id_floor.max(above_instances)).ok()
+    }
+
+    // Keeps `instance_id` from being handed out again.
+    pub(crate) fn retire_instance_id(&mut self, instance_id: InstanceId) {
+        self.instance_id_floor = self.instance_id_floor.max(instance_id as u64 + 1);
+    }
+
+    // Adds an instance of a blueprint, joined through each of its portals in `connections` to
+    // the given portal of an existing instance, both ways round. The new instance has no
+    // placement of its own: it is put in place by its portals. Nothing changes on error.
+    pub fn spawn_instance(
+        &mut self,
+        blueprint_id: BlueprintId,
+        connections: impl IntoIterator<Item = (PortalId, PortalConnectionInfo)>,
+    ) -> Result<InstanceId, SpawnError> {
+        let blueprint = self.blueprints.get(&blueprint_id).ok_or(SpawnError::MissingBlueprint(blueprint_id))?;
+        let mut links: Vec<(SideIndex, PortalId, PortalConnectionInfo, SideIndex)> = Vec::new();
+        for (portal_id, connection) in connections {
+            let side_index = blueprint.sides.iter().position(|side| side.local_portal_id == Some(portal_id))
+                .ok_or(SpawnError::MissingPortal(portal_id))?;
+            if links.iter().any(|&(_, linked_portal_id, ..)| linked_portal_id == portal_id) {
+                return Err(SpawnError::DuplicatePortal(portal_id));
+            }
+            let (instance_id, target_portal_id) = (connection.target_instance_id, connection.target_portal_id);
+            if !self.instances.contains_key(&instance_id) {
+                return Err(SpawnError::MissingTargetInstance(instance_id));
+            }
+            let (target_side_index, target_config) = self.portal_side(instance_id, target_portal_id)
+                .ok_or(SpawnError::MissingTargetPortal { instance_id, portal_id: target_portal_id })?;
+            let taken_by_link = links.iter().any(|(_, _, other, _)| other.target_instance_id == instance_id && other.target_portal_id == target_portal_id);
+            if taken_by_link || portal_target(target_config).is_some_and(|target| self.instances.contains_key(&target)) {
+                return Err(SpawnError::TargetPortalInUse { instance_id, portal_id: target_portal_id });
+            }
+            links.push((side_index, portal_id, connection, target_side_index));
+        }
+        let id = self.next_instance_id().ok_or(SpawnError::NoFreeInstanceId)?;
+
+        let mut instance = HullInstance {
+            id,
+            name: format!("{}_{}", blueprint.name, id),
+            blueprint_id,
+            initial_transform: None,
+            parent_instance_id: None,
+            portal_connections: HashMap::new(),
+            instance_side_handler_configs: HashMap::new(),
+            render_layers: RENDER_LAYER_ALL,
+            side_animations: HashMap::new(),
+            triggers: Vec::new(),
+            acoustics: AcousticSettings::default(),
+            liquid: None,
+            gravity: DEFAULT_GRAVITY,
+        };
+        for (side_index, portal_id, connection, target_side_index) in links {
+            let config = blueprint.sides[side_index].default_handler_config.retargeted(connection.target_instance_id, connection.target_portal_id);
+            instance.instance_side_handler_configs.insert(side_index, config);
+
+            let (_, target_config) = self.portal_side(connection.target_instance_id, connection.target_portal_id).expect("checked above");
+            let target_config = target_config.retargeted(id, portal_id);
+            let target = self.instances.get_mut(&connection.target_instance_id).expect("checked above");
+            target.instance_side_handler_configs.insert(target_side_index, target_config);
+            target.portal_connections.insert(connection.target_portal_id, PortalConnectionInfo { target_instance_id: id, target_portal_id: portal_id });
+            instance.portal_connections.insert(portal_id, connection);
+        }
+        self.instances.insert(id, instance);
+        self.retire_instance_id(id);
+        self.events.publish(EngineEvent::InstanceSpawned { instance_id: id });
+        Ok(id)
+    }
+
+    // Takes an instance out of the scene. Portals leading into it become walls again (the
+    // blueprint's own side where that does not lead there too), its children become roots that
+    // keep their world placement, and the entities, sound emitters, labels and trigger
+    // occupancy it held go with it. Cameras must be moved out first.
+    pub fn remove_instance(&mut self, instance_id: InstanceId) -> Result<HullInstance, RemoveInstanceError> {
+        if !self.instances.contains_key(&instance_id) {
+            return Err(RemoveInstanceError::MissingInstance(instance_id));
+        }
+        let mut camera_names: Vec<&String> = self.cameras.iter()
+            .filter(|(_, camera)| camera.host_instance_id == instance_id)
+            .map(|(name, _)| name)
+            .collect();
+        camera_names.sort_unstable();
+        if let Some(camera) = camera_names.first() {
+            return Err(RemoveInstanceError::HostsCamera { camera: camera.to_string() });
+        }
+
+        let children: Vec<InstanceId> = self.instances.values()
+            .filter(|instance| instance.parent_instance_id == Some(instance_id))
+            .map(|instance| instance.id)
+            .collect();
+        for child_id in children {
+            set_instance_parent(self, child_id, None);
+        }
+        let removed = self.instances.remove(&instance_id).expect("checked above");
+        self.retire_instance_id(instance_id);
+
+        let blueprints = &self.blueprints;
+        for instance in self.instances.values_mut() {
+            instance.portal_connections.retain(|_, connection| connection.target_instance_id != instance_id);
+            let blueprint = match blueprints.get(&instance.blueprint_id) {
+                Some(blueprint) => blueprint,
+                None => continue,
+            };
+            for (side_index, config) in instance.instance_side_handler_configs.iter_mut() {
+                if portal_target(config) != Some(instance_id) {
+                    continue;
+                }
+                let blueprint_config = blueprint.sides.get(*side_index).map(|side| &side.default_handler_config);
+                *config = match (blueprint_config, &*config) {
+                    (Some(default), _) if portal_target(default) != Some(instance_id) => default.clone(),
+                    (_, HandlerConfig::Door { color, render_priority, .. }) => HandlerConfig::StandardWall { color: *color, texture_id: None, emissive: 0.0, render_priority: *render_priority },
+                    _ => HandlerConfig::StandardWall { color: SEALED_PORTAL_COLOR, texture_id: None, emissive: 0.0, render_priority: 0 },
+                };
+            }
+            for (side_index, side) in blueprint.sides.iter().enumerate() {
+                if portal_target(&side.default_handler_config) == Some(instance_id) && !instance.instance_side_handler_configs.contains_key(&side_index) {
+                    let wall_color = match side.default_handler_config {
+                        HandlerConfig::Door { color, .. } => color,
+                        _ => SEALED_PORTAL_COLOR,
+                    };
+                    instance.instance_side_handler_configs.insert(side_index, HandlerConfig::StandardWall { color: wall_color, texture_id: None, emissive: 0.0, render_priority: 0 });
+                }
+            }
+        }
+
+        self.entities.retain(|_, entity| entity.host_instance_id != instance_id);
+        self.sound_emitters.retain(|_, emitter| emitter.host_instance_id != instance_id);
+        self.decals.retain(|_, decal| decal.instance_id != instance_id);
+        self.debug_labels.retain(|label| label.anchor.instance_id() != instance_id);
+        self.occupied_triggers.retain(|&(occupied_instance_id, _)| occupied_instance_id != instance_id);
+        self.events.publish(EngineEvent::InstanceRemoved { instance_id });
+        Ok(removed)
+    }
+}
@@ -0,0 +1,69 @@
+// src/engine_lib/trigger.rs
+
+use glam::{Vec3, Vec4Swizzles};
+use crate::engine_lib::scene_types::{Scene, InstanceId};
+use crate::engine_lib::events::EngineEvent;
+
+// A convex region inside a hull instance that reports the active camera entering and leaving
+// it, for scripted doors, elevators and level transitions. It has no effect on rendering or
+// collision.
+#[derive(Clone, Debug)]
+pub struct TriggerVolume {
+    pub name: String,
+    // Bounding planes in the host instance's blueprint space as (inward normal, point on plane),
+    // like hull sides.
+    pub planes: Vec<(Vec3, Vec3)>,
+}
+
+impl TriggerVolume {
+    // An axis-aligned box between `min` and `max`.
+    pub fn cuboid(name: impl Into<String>, min: Vec3, max: Vec3) -> Self {
+        let planes = vec![
+            (Vec3::X, min), (Vec3::NEG_X, max),
+            (Vec3::Y, min), (Vec3::NEG_Y, max),
+            (Vec3::Z, min), (Vec3::NEG_Z, max),
+        ];
+        Self { name: name.into(), planes }
+    }
+
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes.iter().all(|(normal, point_on_plane)| normal.dot(point - *point_on_plane) >= 0.0)
+    }
+}
+
+// Recomputes which triggers hold the active camera and publishes an event for each one it
+// entered or left since the last call. Only triggers of the camera's host instance can hold it, so
+// walking through a portal leaves every trigger on the near side.
+pub fn update_trigger_occupancy(scene: &mut Scene) {
+    let mut occupied: Vec<(InstanceId, usize)> = match scene.active_camera() {
+        Some(camera) => {
+            let position = camera.local_transform.w_axis.xyz();
+            scene.instances.get(&camera.host_instance_id).map_or_else(Vec::new, |instance| {
+                instance.triggers.iter().enumerate()
+                    .filter(|(_, trigger)| trigger.contains_point(position))
+                    .map(|(trigger_index, _)| (instance.id, trigger_index))
+                    .collect()
+            })
+        }
+        None => Vec::new(),
+    };
+    occupied.sort_unstable();
+
+    let trigger_name = |scene: &Scene, (instance_id, trigger_index): (InstanceId, usize)| {
+        scene.instances.get(&instance_id)
+            .and_then(|instance| instance.triggers.get(trigger_index))
+            .map_or_else(String::new, |trigger| trigger.name.clone())
+    };
+    // Exits first, so a script reacting to both sees the camera leave one region before entering the next.
+    let exited = scene.occupied_triggers.iter().filter(|key| !occupied.contains(key)).map(|&key| {
+        EngineEvent::TriggerExited { instance_id: key.0, trigger_index: key.1, name: trigger_name(scene, key) }
+    });
+    let entered = occupied.iter().filter(|key| !scene.occupied_triggers.contains(key)).map(|&key| {
+        EngineEvent::TriggerEntered { instance_id: key.0, trigger_index: key.1, name: trigger_name(scene, key) }
+    });
+    let events: Vec<EngineEvent> = exited.chain(entered).collect();
+    for event in events {
+        scene.events.publish(event);
+    }
+    scene.occupied_triggers = occupied;
+}
@@ -0,0 +1,15 @@
+// src/lib.rs
+
+#[cfg(feature = "render")]
+pub mod app;
+#[cfg(feature = "render")]
+pub mod ui;
+#[cfg(feature = "render")]
+pub mod scene_editor;
+pub mod settings;
+pub mod profiler;
+pub mod engine_lib;
+pub mod rendering_lib;
+pub mod demo_scene;
+#[cfg(feature = "audio")]
+pub mod audio;
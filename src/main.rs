@@ -1,113 +1,144 @@
-// src/main.rs
-
-pub mod app;
-pub mod ui;
-pub mod engine_lib;
-pub mod rendering_lib;
-pub mod demo_scene;
-
-use winit::{
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoopWindowTarget, EventLoop}, 
-    window::WindowBuilder,
-};
-use app::PolygonApp;
-
-#[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
-pub async fn run() {
-    cfg_if::cfg_if! {
-        if #[cfg(target_arch = "wasm32")] {
-            std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-            console_log::init_with_level(log::Level::Warn).expect("Couldn't initialize logger");
-        } else {
-            env_logger::init();
-        }
-    }
-
-    let event_loop = EventLoop::new().unwrap();
-    let window = std::sync::Arc::new(
-        WindowBuilder::new()
-            .with_title("Portal Rendering - Refactored")
-            .with_inner_size(winit::dpi::LogicalSize::new(1024, 768))
-            .build(&event_loop)
-            .unwrap(),
-    );
-
-    #[cfg(target_arch = "wasm32")]
-    {
-        use winit::platform::web::WindowExtWebSys;
-        web_sys::window()
-            .and_then(|win| win.document())
-            .and_then(|doc| {
-                let dst = doc.get_element_by_id("wasm-viewport")?;
-                let canvas = web_sys::Element::from(window.canvas().ok()?); 
-                dst.append_child(&canvas).ok()?;
-                Some(())
-            })
-            .expect("Couldn't append canvas to document body.");
-    }
-
-    let mut app_state = PolygonApp::new(window.clone()).await;
-    let mut last_time = std::time::Instant::now();
-
-    event_loop
-        .run(move |event, target: &EventLoopWindowTarget<()>| { 
-            target.set_control_flow(ControlFlow::Poll);
-
-            match event {
-                Event::WindowEvent {
-                    ref event,
-                    window_id,
-                } if window_id == window.id() => {
-                    if !app_state.handle_window_event(event, &window) {
-                        match event {
-                            WindowEvent::CloseRequested => {
-                                target.exit();
-                            }
-                            WindowEvent::Resized(physical_size) => {
-                                app_state.resize(*physical_size);
-                            }
-                            WindowEvent::RedrawRequested => { /* In AboutToWait */ }
-                            WindowEvent::Focused(is_focused) => {
-                                app_state.set_focused(*is_focused);
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                Event::DeviceEvent { event: device_event, .. } => {
-                    app_state.handle_device_event(&device_event, &window);
-                }
-                Event::AboutToWait => {
-                    let now = std::time::Instant::now();
-                    let dt = (now - last_time).as_secs_f32();
-                    last_time = now;
-
-                    app_state.update(dt);
-                    match app_state.render(&window) {
-                        Ok(_) => {}
-                        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
-                            app_state.resize(app_state.get_size());
-                        }
-                        Err(wgpu::SurfaceError::OutOfMemory) => {
-                            eprintln!("WGPU Out Of Memory! Exiting.");
-                            target.exit(); 
-                        }
-                        Err(e) => eprintln!("Surface error: {:?}", e),
-                    }
-                    
-                    if !target.exiting() { 
-                        window.request_redraw();
-                    }
-                }
-                Event::LoopExiting => {}
-                _ => {}
-            }
-        })
-        .unwrap();
-}
-
-#[tokio::main]
-async fn main() {
-    run().await;
+// src/main.rs
+
+
+use winit::{
+    event::{Event, WindowEvent},
+    event_loop::{ControlFlow, EventLoopWindowTarget, EventLoop}, 
+    window::WindowBuilder,
+};
+use engine3_refactored::app::{AppConfig, PolygonApp};
+#[cfg(not(target_arch = "wasm32"))]
+use engine3_refactored::engine_lib::map_import::load_map_file;
+#[cfg(not(target_arch = "wasm32"))]
+use engine3_refactored::engine_lib::level_compiler::load_gltf_level;
+#[cfg(not(target_arch = "wasm32"))]
+use engine3_refactored::engine_lib::hot_reload::SceneLoader;
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(start))]
+pub async fn run() {
+    cfg_if::cfg_if! {
+        if #[cfg(target_arch = "wasm32")] {
+            std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+            console_log::init_with_level(log::Level::Warn).expect("Couldn't initialize logger");
+        } else {
+            env_logger::init();
+        }
+    }
+    // With the `trace-chrome` feature, ENGINE3_TRACE=<file.json> records the frame's tracing
+    // spans for chrome://tracing or Perfetto. The file is finished when the window closes.
+    #[cfg(all(feature = "trace-chrome", not(target_arch = "wasm32")))]
+    let _trace_guard = std::env::var_os("ENGINE3_TRACE").map(|path| {
+        use tracing_subscriber::layer::SubscriberExt;
+        use tracing_subscriber::util::SubscriberInitExt;
+        let (chrome_layer, guard) = tracing_chrome::ChromeLayerBuilder::new().file(path).build();
+        tracing_subscriber::registry().with(chrome_layer).init();
+        guard
+    });
+
+    let event_loop = EventLoop::new().unwrap();
+    let window = std::sync::Arc::new(
+        WindowBuilder::new()
+            .with_title("Portal Rendering - Refactored")
+            .build(&event_loop)
+            .unwrap(),
+    );
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::WindowExtWebSys;
+        web_sys::window()
+            .and_then(|win| win.document())
+            .and_then(|doc| {
+                let dst = doc.get_element_by_id("wasm-viewport")?;
+                let canvas = web_sys::Element::from(window.canvas().ok()?); 
+                dst.append_child(&canvas).ok()?;
+                Some(())
+            })
+            .expect("Couldn't append canvas to document body.");
+    }
+
+    let mut app_state = PolygonApp::new(window.clone(), AppConfig::default()).await;
+    // A .map or glTF level given on the command line replaces the demo and is reloaded when it
+    // is saved.
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(path) = std::env::args().nth(1) {
+        let is_gltf = std::path::Path::new(&path).extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gltf") || extension.eq_ignore_ascii_case("glb"));
+        let loader: SceneLoader = if is_gltf { load_gltf_level } else { load_map_file };
+        if let Err(e) = app_state.watch_scene_file(&path, loader) {
+            log::error!("Cannot load {}: {}", path, e);
+        }
+    }
+    let mut last_time = std::time::Instant::now();
+
+    event_loop
+        .run(move |event, target: &EventLoopWindowTarget<()>| { 
+            target.set_control_flow(ControlFlow::Poll);
+
+            match event {
+                Event::WindowEvent {
+                    ref event,
+                    window_id,
+                } if window_id == window.id() => {
+                    if app_state.handle_window_event(event, &window) {
+                        return;
+                    }
+                    match event {
+                        WindowEvent::CloseRequested => {
+                            app_state.save_settings(&window);
+                            target.exit();
+                        }
+                        WindowEvent::Resized(physical_size) => {
+                            app_state.resize(*physical_size);
+                        }
+                        WindowEvent::RedrawRequested => { /* In AboutToWait */ }
+                        WindowEvent::Focused(is_focused) => {
+                            app_state.set_focused(*is_focused);
+                        }
+                        _ => {}
+                    }
+                }
+                Event::DeviceEvent { event: device_event, .. } => {
+                    app_state.handle_device_event(&device_event, &window);
+                }
+                Event::AboutToWait => {
+                    let now = std::time::Instant::now();
+                    let dt = (now - last_time).as_secs_f32();
+                    last_time = now;
+
+                    app_state.update(dt);
+                    match app_state.render(&window) {
+                        Ok(_) => {}
+                        Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                            app_state.resize(app_state.get_size());
+                        }
+                        Err(wgpu::SurfaceError::OutOfMemory) => {
+                            eprintln!("WGPU Out Of Memory! Exiting.");
+                            target.exit(); 
+                        }
+                        Err(e) => eprintln!("Surface error: {:?}", e),
+                    }
+                    
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(min_frame_time) = app_state.min_frame_time() {
+                        let frame_elapsed = now.elapsed();
+                        if frame_elapsed < min_frame_time {
+                            std::thread::sleep(min_frame_time - frame_elapsed);
+                        }
+                    }
+
+                    if !target.exiting() { 
+                        window.request_redraw();
+                    }
+                }
+                Event::LoopExiting => {}
+                _ => {}
+            }
+        })
+        .unwrap();
+}
+
+#[tokio::main]
+async fn main() {
+    run().await;
 }
\ No newline at end of file
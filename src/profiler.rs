@@ -0,0 +1,89 @@
+// src/profiler.rs
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+// Frames kept by `FrameTimeHistory::default`, a few seconds at typical frame rates.
+pub const DEFAULT_FRAME_HISTORY_LEN: usize = 240;
+
+// Where one frame's time went, as measured by the app on the CPU.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTimings {
+    // Wall-clock time since the previous frame started.
+    pub frame: Duration,
+    // Portal traversal of the main view; see `RenderStats::traversal_time`.
+    pub traversal: Duration,
+    // Blocked acquiring the next surface texture, which is where the CPU waits for the GPU to
+    // catch up (and for vsync).
+    pub gpu_wait: Duration,
+    // Building, tessellating and recording the egui windows.
+    pub ui: Duration,
+}
+
+impl FrameTimings {
+    // Time not accounted for by the measured stages: simulation, buffer upload, submission.
+    pub fn other(&self) -> Duration {
+        self.frame.saturating_sub(self.traversal + self.gpu_wait + self.ui)
+    }
+}
+
+// The timings of the most recent frames, oldest first, for the profiler graph.
+#[derive(Clone, Debug)]
+pub struct FrameTimeHistory {
+    frames: VecDeque<FrameTimings>,
+    capacity: usize,
+}
+
+impl Default for FrameTimeHistory {
+    fn default() -> Self { Self::new(DEFAULT_FRAME_HISTORY_LEN) }
+}
+
+impl FrameTimeHistory {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self { frames: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    // Adds the latest frame, dropping the oldest once the history is full.
+    pub fn push(&mut self, timings: FrameTimings) {
+        if self.frames.len() == self.capacity {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(timings);
+    }
+
+    pub fn len(&self) -> usize { self.frames.len() }
+
+    pub fn is_empty(&self) -> bool { self.frames.is_empty() }
+
+    pub fn capacity(&self) -> usize { self.capacity }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &FrameTimings> + '_ { self.frames.iter() }
+
+    pub fn latest(&self) -> Option<&FrameTimings> { self.frames.back() }
+
+    // Mean of each stage over the kept frames; all zero when empty.
+    pub fn average(&self) -> FrameTimings {
+        let count = self.frames.len() as u32;
+        if count == 0 {
+            return FrameTimings::default();
+        }
+        let sum = self.frames.iter().fold(FrameTimings::default(), |sum, frame| FrameTimings {
+            frame: sum.frame + frame.frame,
+            traversal: sum.traversal + frame.traversal,
+            gpu_wait: sum.gpu_wait + frame.gpu_wait,
+            ui: sum.ui + frame.ui,
+        });
+        FrameTimings {
+            frame: sum.frame / count,
+            traversal: sum.traversal / count,
+            gpu_wait: sum.gpu_wait / count,
+            ui: sum.ui / count,
+        }
+    }
+
+    // Longest frame kept, for scaling the graph and spotting hitches.
+    pub fn max_frame_time(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.frame).max().unwrap_or_default()
+    }
+}
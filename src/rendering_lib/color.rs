@@ -0,0 +1,21 @@
+// src/rendering_lib/color.rs
+
+// Rotates the hue of an RGBA color by `degrees`, keeping alpha. Rotation is done around the
+// RGB gray axis (Rodrigues' formula), which approximately preserves brightness and is cheap
+// enough to run per vertex. Channels are only kept from going negative, so HDR colors above 1,
+// such as emissive walls, stay as bright.
+pub fn rotate_hue(color: [f32; 4], degrees: f32) -> [f32; 4] {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let one_third: f32 = 1.0 / 3.0;
+    let sqrt_third = one_third.sqrt();
+    let a = cos + (1.0 - cos) * one_third;
+    let b = one_third * (1.0 - cos) - sqrt_third * sin;
+    let c = one_third * (1.0 - cos) + sqrt_third * sin;
+    let [r, g, bl, alpha] = color;
+    [
+        (r * a + g * b + bl * c).max(0.0),
+        (r * c + g * a + bl * b).max(0.0),
+        (r * b + g * c + bl * a).max(0.0),
+        alpha,
+    ]
+}
@@ -1,83 +1,314 @@
-// src/geometry.rs
-
-use bytemuck::{Pod, Zeroable};
-
-pub const MAX_VERTICES: usize = 16;
-
-#[repr(C)]
-#[derive(Clone, Copy, Debug, Pod, Zeroable, PartialEq)]
-pub struct Point2 {
-    pub x: f32,
-    pub y: f32,
-}
-
-impl Point2 {
-    pub fn new(x: f32, y: f32) -> Self {
-        Self { x, y }
-    }
-
-    pub fn dot(&self, other: &Point2) -> f32 {
-        self.x * other.x + self.y * other.y
-    }
-}
-
-#[derive(Clone, Debug)]
-pub struct ConvexPolygon {
-    vertices: [Point2; MAX_VERTICES], // Kept private for controlled access
-    count: usize,
-}
-
-impl ConvexPolygon {
-    pub fn new() -> Self {
-        Self {
-            vertices: [Point2::new(0.0, 0.0); MAX_VERTICES],
-            count: 0,
-        }
-    }
-
-    pub fn from_points(points: &[Point2]) -> Self {
-        let mut polygon = Self::new();
-        let num_to_copy = points.len().min(MAX_VERTICES);
-        // Ensure we only copy if there are points to prevent panic on empty slice with [..num_to_copy]
-        if num_to_copy > 0 {
-             polygon.vertices[..num_to_copy].copy_from_slice(&points[..num_to_copy]);
-        }
-        polygon.count = num_to_copy;
-        polygon
-    }
-
-    pub fn vertices(&self) -> &[Point2] {
-        &self.vertices[..self.count]
-    }
-    
-    pub fn count(&self) -> usize {
-        self.count
-    }
-
-    pub fn set_count(&mut self, count: usize) {
-        self.count = count.min(MAX_VERTICES);
-    }
-
-    pub fn copy_vertices_from_slice(&mut self, slice: &[Point2]) {
-        let num_to_copy = slice.len().min(MAX_VERTICES);
-        if num_to_copy > 0 {
-            self.vertices[..num_to_copy].copy_from_slice(&slice[..num_to_copy]);
-        } else {
-            // If the slice is empty, ensure the polygon is also empty
-        }
-        self.count = num_to_copy; // Set count regardless, could be 0
-    }
-
-    pub fn area(&self) -> f32 {
-        if self.count < 3 {
-            return 0.0;
-        }
-        let mut area = 0.0;
-        for i in 0..self.count {
-            let j = (i + 1) % self.count;
-            area += self.vertices[i].x * self.vertices[j].y;
-            area -= self.vertices[j].x * self.vertices[i].y;
-        }
-        area.abs() / 2.0
-    }
-}
\ No newline at end of file
+// src/geometry.rs
+
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::ops::{Add, Div, Mul, Neg, Sub};
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+use super::triangulation::signed_area_doubled;
+
+// Default vertex capacity of a `ConvexPolygon`.
+pub const MAX_VERTICES: usize = 16;
+// Capacity of screen regions seen through portals. Clipping a region by a side can add as many
+// vertices as the side has, so these need more room than the default.
+pub const SCREEN_POLYGON_VERTICES: usize = 64;
+
+// The float type geometry is computed in. f32 is the default everywhere; f64 keeps repeated
+// clipping accurate, e.g. for screen regions seen through many portals.
+pub trait Scalar:
+    Copy + Debug + Default + PartialOrd + Send + Sync + 'static
+    + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self>
+{
+    const ZERO: Self;
+    // Tolerance for a point counting as on an edge.
+    const EDGE_EPSILON: Self;
+    // Below this, two lines are treated as parallel.
+    const PARALLEL_EPSILON: Self;
+
+    fn from_f64(value: f64) -> Self;
+    fn to_f64(self) -> f64;
+    fn abs(self) -> Self;
+    fn sqrt(self) -> Self;
+}
+
+impl Scalar for f32 {
+    const ZERO: Self = 0.0;
+    const EDGE_EPSILON: Self = 1e-5;
+    const PARALLEL_EPSILON: Self = 1e-10;
+
+    fn from_f64(value: f64) -> Self { value as f32 }
+    fn to_f64(self) -> f64 { self as f64 }
+    fn abs(self) -> Self { f32::abs(self) }
+    fn sqrt(self) -> Self { f32::sqrt(self) }
+}
+
+impl Scalar for f64 {
+    const ZERO: Self = 0.0;
+    const EDGE_EPSILON: Self = 1e-9;
+    const PARALLEL_EPSILON: Self = 1e-18;
+
+    fn from_f64(value: f64) -> Self { value }
+    fn to_f64(self) -> f64 { self }
+    fn abs(self) -> Self { f64::abs(self) }
+    fn sqrt(self) -> Self { f64::sqrt(self) }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point2<T: Scalar = f32> {
+    pub x: T,
+    pub y: T,
+}
+
+// Plain pairs of floats, so safe to view as bytes.
+unsafe impl Zeroable for Point2<f32> {}
+unsafe impl Pod for Point2<f32> {}
+unsafe impl Zeroable for Point2<f64> {}
+unsafe impl Pod for Point2<f64> {}
+
+impl<T: Scalar> Point2<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Self { x, y }
+    }
+
+    pub fn dot(&self, other: &Point2<T>) -> T {
+        self.x * other.x + self.y * other.y
+    }
+
+    // The same point in another precision.
+    pub fn cast<U: Scalar>(&self) -> Point2<U> {
+        Point2::new(U::from_f64(self.x.to_f64()), U::from_f64(self.y.to_f64()))
+    }
+}
+
+// Screen regions seen through portals. Each portal clips the region it was seen through again,
+// so these are kept in f64 to stop error building up over deep recursion.
+pub type ScreenPolygon = ConvexPolygon<f64, SCREEN_POLYGON_VERTICES>;
+
+// A convex polygon of at most `N` vertices, stored inline.
+#[derive(Clone, Debug)]
+pub struct ConvexPolygon<T: Scalar = f32, const N: usize = MAX_VERTICES> {
+    vertices: [Point2<T>; N], // Kept private for controlled access
+    count: usize,
+}
+
+impl<T: Scalar, const N: usize> Default for ConvexPolygon<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Scalar, const N: usize> ConvexPolygon<T, N> {
+    pub const CAPACITY: usize = N;
+
+    pub fn new() -> Self {
+        Self {
+            vertices: [Point2::new(T::ZERO, T::ZERO); N],
+            count: 0,
+        }
+    }
+
+    // Keeps the first `N` points; see `try_from_points` for a version that refuses more.
+    pub fn from_points(points: &[Point2<T>]) -> Self {
+        let mut polygon = Self::new();
+        let num_to_copy = points.len().min(N);
+        // Ensure we only copy if there are points to prevent panic on empty slice with [..num_to_copy]
+        if num_to_copy > 0 {
+             polygon.vertices[..num_to_copy].copy_from_slice(&points[..num_to_copy]);
+        }
+        polygon.count = num_to_copy;
+        polygon
+    }
+
+    // None if `points` does not fit in `N` vertices.
+    pub fn try_from_points(points: &[Point2<T>]) -> Option<Self> {
+        (points.len() <= N).then(|| Self::from_points(points))
+    }
+
+    // The smallest convex polygon around `points`, which may come in any order, built with
+    // Andrew's monotone chain. Duplicates and points on the hull's edges are dropped, and the
+    // result winds the way `ConvexIntersection` expects of a clip polygon (positive cross
+    // products). None if the hull has more than `N` vertices.
+    pub fn convex_hull_of(points: &[Point2<T>]) -> Option<Self> {
+        let mut sorted = points.to_vec();
+        sorted.sort_by(|a, b| {
+            a.x.partial_cmp(&b.x).unwrap_or(Ordering::Equal)
+                .then(a.y.partial_cmp(&b.y).unwrap_or(Ordering::Equal))
+        });
+        sorted.dedup();
+        if sorted.len() < 3 {
+            return Self::try_from_points(&sorted);
+        }
+
+        let turns_left = |o: &Point2<T>, a: &Point2<T>, b: &Point2<T>| {
+            (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x) > T::ZERO
+        };
+        let mut hull: Vec<Point2<T>> = Vec::with_capacity(sorted.len() + 1);
+        // Lower chain left to right, then upper chain back; each ends where the other starts.
+        for point in &sorted {
+            while hull.len() >= 2 && !turns_left(&hull[hull.len() - 2], &hull[hull.len() - 1], point) {
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        let lower_len = hull.len() + 1;
+        for point in sorted.iter().rev().skip(1) {
+            while hull.len() >= lower_len && !turns_left(&hull[hull.len() - 2], &hull[hull.len() - 1], point) {
+                hull.pop();
+            }
+            hull.push(*point);
+        }
+        hull.pop();
+        Self::try_from_points(&hull)
+    }
+
+    pub fn vertices(&self) -> &[Point2<T>] {
+        &self.vertices[..self.count]
+    }
+    
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn set_count(&mut self, count: usize) {
+        self.count = count.min(N);
+    }
+
+    pub fn copy_vertices_from_slice(&mut self, slice: &[Point2<T>]) {
+        let num_to_copy = slice.len().min(N);
+        if num_to_copy > 0 {
+            self.vertices[..num_to_copy].copy_from_slice(&slice[..num_to_copy]);
+        } else {
+            // If the slice is empty, ensure the polygon is also empty
+        }
+        self.count = num_to_copy; // Set count regardless, could be 0
+    }
+
+    // The same polygon in another precision or capacity. None if it has more than `M` vertices,
+    // rather than a different polygon made of the first `M`.
+    pub fn cast<U: Scalar, const M: usize>(&self) -> Option<ConvexPolygon<U, M>> {
+        if self.count > M {
+            return None;
+        }
+        let mut polygon = ConvexPolygon::new();
+        for (to, from) in polygon.vertices.iter_mut().zip(self.vertices()) {
+            *to = from.cast();
+        }
+        polygon.count = self.count;
+        Some(polygon)
+    }
+
+    // True if `point` is inside or on the boundary, for either winding.
+    pub fn contains_point(&self, point: &Point2<T>) -> bool {
+        if self.count < 3 {
+            return false;
+        }
+        let (mut has_positive, mut has_negative) = (false, false);
+        for i in 0..self.count {
+            let a = self.vertices[i];
+            let b = self.vertices[(i + 1) % self.count];
+            let cross = (b.x - a.x) * (point.y - a.y) - (b.y - a.y) * (point.x - a.x);
+            has_positive |= cross > T::EDGE_EPSILON;
+            has_negative |= cross < -T::EDGE_EPSILON;
+        }
+        !(has_positive && has_negative)
+    }
+
+    pub fn area(&self) -> T {
+        if self.count < 3 {
+            return T::ZERO;
+        }
+        let mut area = T::ZERO;
+        for i in 0..self.count {
+            let j = (i + 1) % self.count;
+            area = area + self.vertices[i].x * self.vertices[j].y;
+            area = area - self.vertices[j].x * self.vertices[i].y;
+        }
+        area.abs() / T::from_f64(2.0)
+    }
+
+    // The polygon grown outward by `radius` (shrunk for a negative one): every edge moves out by
+    // `radius` and the corners are mitred, so the result holds everything within `radius` of
+    // the polygon and keeps its vertex count. Meant for conservative regions, e.g. a portal's clip
+    // region widened to cover rounding. Shrinking by more than the polygon's inradius gives
+    // nonsense.
+    pub fn inflated(&self, radius: T) -> Self {
+        let points = self.vertices();
+        if points.len() < 3 {
+            return self.clone();
+        }
+        // Outward is to the right of each edge for positive winding, to the left otherwise.
+        let outward = if signed_area_doubled(points) < T::ZERO { -T::from_f64(1.0) } else { T::from_f64(1.0) };
+        let edge_normal = |i: usize| {
+            let (a, b) = (points[i], points[(i + 1) % points.len()]);
+            let (dx, dy) = (b.x - a.x, b.y - a.y);
+            let length = (dx * dx + dy * dy).sqrt();
+            (length > T::ZERO).then(|| Point2::new(outward * dy / length, -outward * dx / length))
+        };
+        let normals: Vec<Option<Point2<T>>> = (0..points.len()).map(edge_normal).collect();
+
+        let mut inflated = self.clone();
+        for i in 0..points.len() {
+            // Zero-length edges have no direction; use the nearest real edge on each side.
+            let incoming = (1..=points.len()).find_map(|back| normals[(i + points.len() - back) % points.len()]);
+            let outgoing = (0..points.len()).find_map(|ahead| normals[(i + ahead) % points.len()]);
+            let (n0, n1) = match incoming.zip(outgoing) {
+                Some(pair) => pair,
+                None => return self.clone(),
+            };
+            // The point `radius` away from both edges' lines.
+            let denominator = T::from_f64(1.0) + n0.dot(&n1);
+            let offset = if denominator > T::EDGE_EPSILON {
+                Point2::new((n0.x + n1.x) / denominator, (n0.y + n1.y) / denominator)
+            } else {
+                n0
+            };
+            inflated.vertices[i] = Point2::new(points[i].x + offset.x * radius, points[i].y + offset.y * radius);
+        }
+        inflated
+    }
+
+    // Minkowski sum: every point of `self` plus every point of `other`, i.e. `self` swept over
+    // `other`. Built as the convex hull of all vertex sums. None if it has more than `N` vertices.
+    pub fn minkowski_sum<const M: usize>(&self, other: &ConvexPolygon<T, M>) -> Option<Self> {
+        let sums: Vec<Point2<T>> = self.vertices().iter()
+            .flat_map(|a| other.vertices().iter().map(move |b| Point2::new(a.x + b.x, a.y + b.y)))
+            .collect();
+        Self::convex_hull_of(&sums)
+    }
+}
+
+// Points closer to a clipping plane than this count as on it, and so as kept.
+const PLANE_EPSILON: f32 = 1e-6;
+
+// Clips a 3D polygon to the side of `plane` (normal, point on plane) that the normal points
+// toward, with Sutherland-Hodgman. Works for any plane: the camera's near plane, a mirror or
+// water surface, or a hull side. The normal should be unit length. Returns the remaining
+// vertices, fewer than 3 if none are left.
+pub fn clip_polygon_3d_by_plane(points: &[Vec3], plane: &(Vec3, Vec3)) -> Vec<Vec3> {
+    let mut output_list = Vec::with_capacity(points.len() + 1);
+    let (normal, point_on_plane) = plane;
+    let signed_distance = |p: Vec3| normal.dot(p - *point_on_plane);
+    let mut s = match points.last() {
+        Some(last) => *last,
+        None => return output_list,
+    };
+    for &p in points {
+        let s_distance = signed_distance(s);
+        let p_distance = signed_distance(p);
+        let s_is_inside = s_distance > -PLANE_EPSILON;
+        let p_is_inside = p_distance > -PLANE_EPSILON;
+
+        if s_is_inside != p_is_inside && (s_distance - p_distance).abs() > PLANE_EPSILON {
+            let t = s_distance / (s_distance - p_distance);
+            if (0.0..=1.0).contains(&t) {
+                output_list.push(s + t * (p - s));
+            }
+        }
+        if p_is_inside {
+            output_list.push(p);
+        }
+        s = p;
+    }
+    output_list
+}
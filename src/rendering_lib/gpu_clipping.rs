@@ -0,0 +1,251 @@
+// src/rendering_lib/gpu_clipping.rs
+
+use bytemuck::{Pod, Zeroable};
+use glam::Vec3;
+
+use super::geometry::{Point2, ScreenPolygon};
+use super::renderer::{GrowableBuffer, SideClipBatch, SideClipper};
+use super::shader::WGSL_SIDE_CLIP_SHADER_SOURCE;
+use crate::engine_lib::camera::Camera;
+
+// Largest side the compute shader takes; bigger ones are clipped on the CPU.
+pub const MAX_GPU_SIDE_VERTICES: usize = 32;
+// Must match `MAX_POINTS` in side_clip.wgsl.
+const MAX_CLIPPED_POINTS: usize = 96;
+const WORKGROUP_SIZE: u32 = 64;
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+struct SideClipParams {
+    width: f32,
+    height: f32,
+    znear: f32,
+    zfar: f32,
+    focal_x: f32,
+    focal_y: f32,
+    job_count: u32,
+    _padding: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+struct SideClipJob {
+    vertex_offset: u32,
+    vertex_count: u32,
+    clip_offset: u32,
+    clip_count: u32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+struct ClippedSide {
+    count: u32,
+    _padding: u32,
+    points: [[f32; 2]; MAX_CLIPPED_POINTS],
+}
+
+// Clips the hull sides of a traversal wave in a compute shader, for scenes with thousands of
+// sides. Each batch is a round trip to the GPU, so it only pays off for large waves.
+pub struct GpuSideClipper {
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: Option<wgpu::BindGroup>,
+    params_buffer: wgpu::Buffer,
+    job_buffer: GrowableBuffer,
+    vertex_buffer: GrowableBuffer,
+    clip_point_buffer: GrowableBuffer,
+    output_buffer: GrowableBuffer,
+    readback_buffer: GrowableBuffer,
+}
+
+impl GpuSideClipper {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Side Clip Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(WGSL_SIDE_CLIP_SHADER_SOURCE.into()),
+        });
+        let storage_entry = |binding, read_only| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("side_clip_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                storage_entry(1, true),
+                storage_entry(2, true),
+                storage_entry(3, true),
+                storage_entry(4, false),
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Side Clip Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Side Clip Pipeline"),
+            layout: Some(&layout),
+            module: &module,
+            entry_point: "cs_clip_sides",
+        });
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Side Clip Params"),
+            size: std::mem::size_of::<SideClipParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let storage = wgpu::BufferUsages::STORAGE;
+        Self {
+            pipeline,
+            bind_group_layout,
+            bind_group: None,
+            params_buffer,
+            job_buffer: GrowableBuffer::new(device, "Side Clip Jobs", storage, 1024),
+            vertex_buffer: GrowableBuffer::new(device, "Side Clip Vertices", storage, 1024),
+            clip_point_buffer: GrowableBuffer::new(device, "Side Clip Regions", storage, 1024),
+            output_buffer: GrowableBuffer::new(device, "Side Clip Output", storage | wgpu::BufferUsages::COPY_SRC, 1024),
+            readback_buffer: GrowableBuffer::new(device, "Side Clip Readback", wgpu::BufferUsages::MAP_READ, 1024),
+        }
+    }
+
+    // Clips `batch` and waits for the result. None if it could not be read back.
+    pub fn clip(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+        screen_width: f32,
+        screen_height: f32,
+        batch: &SideClipBatch,
+    ) -> Option<Vec<Option<ScreenPolygon>>> {
+        let _span = tracing::debug_span!("gpu_clip_sides", sides = batch.sides.len()).entered();
+        let mut jobs = Vec::with_capacity(batch.sides.len());
+        let mut vertices: Vec<[f32; 4]> = Vec::new();
+        let mut clip_offsets = Vec::with_capacity(batch.clip_polygons.len());
+        let mut clip_points: Vec<[f32; 2]> = Vec::new();
+        for polygon in &batch.clip_polygons {
+            clip_offsets.push(clip_points.len() as u32);
+            clip_points.extend(polygon.vertices().iter().map(|point| [point.x as f32, point.y as f32]));
+        }
+        for side in &batch.sides {
+            let vertex_count = if side.vertices_cam_space.len() <= MAX_GPU_SIDE_VERTICES { side.vertices_cam_space.len() } else { 0 };
+            jobs.push(SideClipJob {
+                vertex_offset: vertices.len() as u32,
+                vertex_count: vertex_count as u32,
+                clip_offset: clip_offsets[side.clip_polygon],
+                clip_count: batch.clip_polygons[side.clip_polygon].count() as u32,
+            });
+            vertices.extend(side.vertices_cam_space[..vertex_count].iter().map(|v: &Vec3| [v.x, v.y, v.z, 1.0]));
+        }
+        if jobs.is_empty() {
+            return Some(Vec::new());
+        }
+        // Bindings need something to point at even when a batch has no vertices or regions.
+        vertices.push([0.0; 4]);
+        clip_points.push([0.0; 2]);
+
+        let focal_y = 1.0 / (camera.fov_y_rad / 2.0).tan();
+        let params = SideClipParams {
+            width: screen_width,
+            height: screen_height,
+            znear: camera.znear,
+            zfar: camera.zfar,
+            focal_x: focal_y / (screen_width / screen_height),
+            focal_y,
+            job_count: jobs.len() as u32,
+            _padding: 0,
+        };
+        let output_size = (jobs.len() * std::mem::size_of::<ClippedSide>()) as u64;
+        let mut replaced = self.job_buffer.write(device, queue, bytemuck::cast_slice(&jobs));
+        replaced |= self.vertex_buffer.write(device, queue, bytemuck::cast_slice(&vertices));
+        replaced |= self.clip_point_buffer.write(device, queue, bytemuck::cast_slice(&clip_points));
+        replaced |= self.output_buffer.reserve(device, output_size);
+        self.readback_buffer.reserve(device, output_size);
+        if replaced || self.bind_group.is_none() {
+            self.bind_group = Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("side_clip_bind_group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: self.params_buffer.as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 1, resource: self.job_buffer.buffer().as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 2, resource: self.vertex_buffer.buffer().as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 3, resource: self.clip_point_buffer.buffer().as_entire_binding() },
+                    wgpu::BindGroupEntry { binding: 4, resource: self.output_buffer.buffer().as_entire_binding() },
+                ],
+            }));
+        }
+        queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: Some("Side Clip Encoder") });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: Some("Side Clip Pass"), timestamp_writes: None });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, self.bind_group.as_ref()?, &[]);
+            pass.dispatch_workgroups((jobs.len() as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(self.output_buffer.buffer(), 0, self.readback_buffer.buffer(), 0, output_size);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = self.readback_buffer.slice(output_size);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv().ok()?.ok()?;
+        let clipped: Vec<Option<ScreenPolygon>> = {
+            let mapped = slice.get_mapped_range();
+            let outputs: &[ClippedSide] = bytemuck::cast_slice(&mapped);
+            outputs.iter().map(|output| {
+                let points: Vec<Point2<f64>> = output.points[..(output.count as usize).min(MAX_CLIPPED_POINTS)].iter()
+                    .map(|&[x, y]| Point2::new(x as f64, y as f64))
+                    .collect();
+                // Also gives the result the winding clip regions need, and drops repeated points.
+                ScreenPolygon::convex_hull_of(&points).filter(|polygon| polygon.count() >= 3)
+            }).collect()
+        };
+        self.readback_buffer.buffer().unmap();
+
+        // Sides too big for the shader came back empty.
+        Some(clipped.into_iter().zip(&batch.sides).map(|(polygon, side)| {
+            if side.vertices_cam_space.len() > MAX_GPU_SIDE_VERTICES {
+                batch.clip_on_cpu(side, camera, screen_width, screen_height)
+            } else {
+                polygon
+            }
+        }).collect())
+    }
+
+    // A `SideClipper` for `SceneTraversal`, clipping on this device.
+    pub fn with_device<'a>(&'a mut self, device: &'a wgpu::Device, queue: &'a wgpu::Queue) -> GpuSideClipperContext<'a> {
+        GpuSideClipperContext { clipper: self, device, queue }
+    }
+}
+
+pub struct GpuSideClipperContext<'a> {
+    clipper: &'a mut GpuSideClipper,
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+}
+
+impl SideClipper for GpuSideClipperContext<'_> {
+    fn clip_sides(&mut self, camera: &Camera, screen_width: f32, screen_height: f32, batch: &SideClipBatch) -> Option<Vec<Option<ScreenPolygon>>> {
+        self.clipper.clip(self.device, self.queue, camera, screen_width, screen_height, batch)
+    }
+}
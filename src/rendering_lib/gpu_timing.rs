@@ -0,0 +1,189 @@
+// src/rendering_lib/gpu_timing.rs
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+// A render pass timed by `GpuTimer`, with a begin and an end timestamp each.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GpuPass {
+    Scene,
+    Tonemap,
+    Ui,
+}
+
+const GPU_PASSES: [GpuPass; 3] = [GpuPass::Scene, GpuPass::Tonemap, GpuPass::Ui];
+const QUERY_COUNT: u32 = GPU_PASSES.len() as u32 * 2;
+const TIMESTAMP_SIZE: u64 = std::mem::size_of::<u64>() as u64;
+
+impl GpuPass {
+    fn query_index(self) -> u32 {
+        self as u32 * 2
+    }
+}
+
+// GPU execution time of a frame's render passes, from timestamp queries. A pass is None if it
+// was not timed in that frame, e.g. the UI pass when the app did not ask for it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct GpuPassTimes {
+    pub scene: Option<Duration>,
+    pub tonemap: Option<Duration>,
+    pub ui: Option<Duration>,
+}
+
+impl GpuPassTimes {
+    pub fn get(&self, pass: GpuPass) -> Option<Duration> {
+        match pass {
+            GpuPass::Scene => self.scene,
+            GpuPass::Tonemap => self.tonemap,
+            GpuPass::Ui => self.ui,
+        }
+    }
+
+    fn set(&mut self, pass: GpuPass, time: Option<Duration>) {
+        match pass {
+            GpuPass::Scene => self.scene = time,
+            GpuPass::Tonemap => self.tonemap = time,
+            GpuPass::Ui => self.ui = time,
+        }
+    }
+
+    // Sum of the passes that were timed.
+    pub fn total(&self) -> Duration {
+        GPU_PASSES.iter().filter_map(|pass| self.get(*pass)).sum()
+    }
+}
+
+enum Readback {
+    Idle,
+    // The timestamps were copied into the readback buffer by a frame not yet known to be submitted.
+    Copied,
+    Mapping(mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>),
+}
+
+// Writes timestamps at the start and end of the frame's render passes and reads them back
+// without stalling: a frame's results arrive a couple of frames later, and frames recorded while
+// the readback buffer is busy are not timed. Needs `wgpu::Features::TIMESTAMP_QUERY`.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    // Nanoseconds per timestamp tick.
+    timestamp_period: f32,
+    readback: Readback,
+    // Whether the current frame is being timed, and which of its passes were.
+    frame_timed: bool,
+    passes_written: [bool; GPU_PASSES.len()],
+    // Passes timed in the frame being read back.
+    passes_copied: [bool; GPU_PASSES.len()],
+    latest: Option<GpuPassTimes>,
+}
+
+impl GpuTimer {
+    // None if the device was created without timestamp queries.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("GPU Pass Timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: QUERY_COUNT,
+        });
+        let size = QUERY_COUNT as u64 * TIMESTAMP_SIZE;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Timestamp Resolve"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("GPU Timestamp Readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            timestamp_period: queue.get_timestamp_period(),
+            readback: Readback::Idle,
+            frame_timed: false,
+            passes_written: [false; GPU_PASSES.len()],
+            passes_copied: [false; GPU_PASSES.len()],
+            latest: None,
+        })
+    }
+
+    // Call once per frame before recording it: picks up finished readbacks and decides whether
+    // the frame can be timed. The previous frame must have been submitted.
+    pub fn begin_frame(&mut self, device: &wgpu::Device) {
+        if matches!(self.readback, Readback::Copied) {
+            let (sender, receiver) = mpsc::channel();
+            self.readback_buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+            self.readback = Readback::Mapping(receiver);
+        }
+        if let Readback::Mapping(receiver) = &self.readback {
+            device.poll(wgpu::Maintain::Poll);
+            match receiver.try_recv() {
+                Ok(Ok(())) => {
+                    self.latest = Some(self.read_mapped());
+                    self.readback_buffer.unmap();
+                    self.readback = Readback::Idle;
+                }
+                Ok(Err(e)) => {
+                    log::warn!("GPU timestamps could not be read back: {}", e);
+                    self.readback = Readback::Idle;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => self.readback = Readback::Idle,
+            }
+        }
+        self.frame_timed = matches!(self.readback, Readback::Idle);
+        self.passes_written = [false; GPU_PASSES.len()];
+    }
+
+    // Timestamp writes for `pass`'s descriptor, or None if this frame is not being timed.
+    pub fn timestamp_writes(&mut self, pass: GpuPass) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        if !self.frame_timed {
+            return None;
+        }
+        self.passes_written[pass as usize] = true;
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &self.query_set,
+            beginning_of_pass_write_index: Some(pass.query_index()),
+            end_of_pass_write_index: Some(pass.query_index() + 1),
+        })
+    }
+
+    // Call after the frame's last timed pass, on the encoder that is submitted last.
+    pub fn end_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.frame_timed || !self.passes_written.contains(&true) {
+            return;
+        }
+        encoder.resolve_query_set(&self.query_set, 0..QUERY_COUNT, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, QUERY_COUNT as u64 * TIMESTAMP_SIZE);
+        self.passes_copied = self.passes_written;
+        self.readback = Readback::Copied;
+        self.frame_timed = false;
+    }
+
+    // The most recent frame whose timestamps have been read back.
+    pub fn latest(&self) -> Option<GpuPassTimes> {
+        self.latest
+    }
+
+    fn read_mapped(&self) -> GpuPassTimes {
+        let mapped = self.readback_buffer.slice(..).get_mapped_range();
+        let timestamps: &[u64] = bytemuck::cast_slice(&mapped);
+        let mut times = GpuPassTimes::default();
+        for pass in GPU_PASSES.into_iter().filter(|pass| self.passes_copied[*pass as usize]) {
+            let index = pass.query_index() as usize;
+            let ticks = timestamps[index + 1].saturating_sub(timestamps[index]);
+            times.set(pass, Some(Duration::from_nanos((ticks as f64 * self.timestamp_period as f64) as u64)));
+        }
+        times
+    }
+}
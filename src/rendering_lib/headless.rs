@@ -0,0 +1,115 @@
+// src/rendering_lib/headless.rs
+
+use std::fmt;
+
+use super::offscreen::OffscreenTarget;
+use super::renderer::{Renderer, RenderStats};
+use super::shader::WGSL_SHADER_SOURCE;
+use crate::engine_lib::camera::Camera;
+use crate::engine_lib::scene_types::{Scene, RenderLayerMask};
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+    Readback(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::NoAdapter => write!(f, "no suitable GPU adapter found"),
+            HeadlessError::RequestDevice(e) => write!(f, "failed to create device: {}", e),
+            HeadlessError::Readback(e) => write!(f, "failed to read back rendered frame: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+// A renderer with its own device and no window surface. Frames go to an offscreen texture and
+// are read back as RGBA8, for CI, servers and image-comparison tests.
+pub struct HeadlessRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    renderer: Renderer,
+    target: OffscreenTarget,
+}
+
+impl HeadlessRenderer {
+    // Falls back to a software adapter when no hardware adapter is available.
+    pub async fn new(width: u32, height: u32) -> Result<Self, HeadlessError> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let mut adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            })
+            .await;
+        if adapter.is_none() {
+            adapter = instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    compatible_surface: None,
+                    force_fallback_adapter: true,
+                })
+                .await;
+        }
+        let adapter = adapter.ok_or(HeadlessError::NoAdapter)?;
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits::downlevel_defaults(),
+                    label: Some("Headless Device"),
+                },
+                None,
+            )
+            .await
+            .map_err(HeadlessError::RequestDevice)?;
+
+        let target = OffscreenTarget::new(&device, width, height, OffscreenTarget::DEFAULT_FORMAT);
+        let renderer = Renderer::new(
+            &device, target.format(), WGSL_SHADER_SOURCE,
+            target.width() as f32, target.height() as f32,
+        );
+        Ok(Self { device, queue, renderer, target })
+    }
+
+    pub fn device(&self) -> &wgpu::Device { &self.device }
+    pub fn queue(&self) -> &wgpu::Queue { &self.queue }
+    pub fn renderer(&self) -> &Renderer { &self.renderer }
+    pub fn renderer_mut(&mut self) -> &mut Renderer { &mut self.renderer }
+    pub fn width(&self) -> u32 { self.target.width() }
+    pub fn height(&self) -> u32 { self.target.height() }
+
+    // See `Renderer::reload_shaders`.
+    pub fn reload_shaders(&mut self, scene_source: &str, sky_source: &str) -> Result<(), String> {
+        self.renderer.reload_shaders(&self.device, scene_source, sky_source)
+    }
+
+    // Renders `scene` from its active camera and returns the frame as tightly packed RGBA8
+    // rows, top row first, along with the frame's render statistics.
+    pub fn render(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        clear_color: wgpu::Color,
+        layer_filter: RenderLayerMask,
+    ) -> Result<(Vec<u8>, RenderStats), HeadlessError> {
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Headless Command Encoder"),
+        });
+        let stats = self.renderer.render_scene(
+            &self.device, &self.queue, &mut encoder, self.target.view(),
+            scene, camera,
+            self.target.width() as f32, self.target.height() as f32,
+            clear_color, layer_filter,
+        );
+        let rgba = self.target
+            .read_pixels(&self.device, &self.queue, encoder)
+            .map_err(HeadlessError::Readback)?;
+        Ok((rgba, stats))
+    }
+}
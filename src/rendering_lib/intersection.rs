@@ -1,154 +1,202 @@
-// src/intersection.rs
-
-use super::geometry::{ConvexPolygon, Point2, MAX_VERTICES};
-
-pub struct ConvexIntersection;
-
-impl ConvexIntersection {
-    #[inline(always)]
-    fn is_inside(point: &Point2, edge_start: &Point2, edge_end: &Point2) -> bool {
-        ((edge_end.x - edge_start.x) * (point.y - edge_start.y) -
-         (edge_end.y - edge_start.y) * (point.x - edge_start.x)) >= -1e-5
-    }
-
-    fn line_intersection(p1: &Point2, p2: &Point2, clip_edge_p1: &Point2, clip_edge_p2: &Point2) -> Option<Point2> {
-        let dx_line = p2.x - p1.x;
-        let dy_line = p2.y - p1.y;
-        let dx_clip = clip_edge_p2.x - clip_edge_p1.x;
-        let dy_clip = clip_edge_p2.y - clip_edge_p1.y;
-
-        let denominator = dy_clip * dx_line - dx_clip * dy_line;
-
-        if denominator.abs() < 1e-10 {
-            return None;
-        }
-
-        let t = (dx_clip * (p1.y - clip_edge_p1.y) - dy_clip * (p1.x - clip_edge_p1.x)) / denominator;
-        
-        Some(Point2::new(p1.x + t * dx_line, p1.y + t * dy_line))
-    }
-    
-    fn clip_polygon_by_edge(
-        subject_vertices: &[Point2],
-        clip_edge_start: &Point2,
-        clip_edge_end: &Point2,
-        output_buffer: &mut [Point2; MAX_VERTICES],
-    ) -> usize {
-        if subject_vertices.is_empty() {
-            return 0;
-        }
-
-        let mut output_count = 0;
-        let mut prev_vertex = subject_vertices[subject_vertices.len() - 1];
-        
-        // This is the version of clip_polygon_by_edge that performed best previously
-        // (calling is_inside twice per iteration).
-        for &current_vertex in subject_vertices {
-            let prev_is_inside = Self::is_inside(&prev_vertex, clip_edge_start, clip_edge_end);
-            let current_is_inside = Self::is_inside(&current_vertex, clip_edge_start, clip_edge_end);
-
-            if prev_is_inside && current_is_inside {
-                if output_count < MAX_VERTICES {
-                    output_buffer[output_count] = current_vertex;
-                    output_count += 1;
-                } else { break; }
-            } else if prev_is_inside && !current_is_inside {
-                if let Some(intersection) = Self::line_intersection(&prev_vertex, &current_vertex, clip_edge_start, clip_edge_end) {
-                    if output_count < MAX_VERTICES {
-                        output_buffer[output_count] = intersection;
-                        output_count += 1;
-                    } else { break; }
-                }
-            } else if !prev_is_inside && current_is_inside {
-                if let Some(intersection) = Self::line_intersection(&prev_vertex, &current_vertex, clip_edge_start, clip_edge_end) {
-                     if output_count < MAX_VERTICES {
-                        output_buffer[output_count] = intersection;
-                        output_count += 1;
-                    } else { break; }
-                }
-                if output_count < MAX_VERTICES {
-                    output_buffer[output_count] = current_vertex;
-                    output_count += 1;
-                } else { break; }
-            }
-            prev_vertex = current_vertex;
-        }
-        output_count
-    }
-
-    pub fn find_intersection_into(
-        poly1: &ConvexPolygon,
-        poly2: &ConvexPolygon,
-        result_poly: &mut ConvexPolygon,
-    ) {
-        let mut buffer_a = [Point2::new(0.0, 0.0); MAX_VERTICES];
-        let mut buffer_b = [Point2::new(0.0, 0.0); MAX_VERTICES];
-        let mut subject_count;
-
-        subject_count = poly1.count();
-        if subject_count == 0 {
-            result_poly.set_count(0);
-            return;
-        }
-        // This early exit can be important if poly2 is empty
-        if poly2.count() < 3 { // A clipper polygon needs at least 3 vertices to define clip edges
-            if subject_count > 0 { // If poly1 has vertices, it's the result (no clipping performed)
-                result_poly.copy_vertices_from_slice(poly1.vertices());
-            } else {
-                result_poly.set_count(0);
-            }
-            return;
-        }
-
-        buffer_a[..subject_count].copy_from_slice(poly1.vertices());
-
-        let mut input_is_buffer_a = true;
-
-        for i in 0..poly2.count() {
-            if subject_count == 0 { break; }
-
-            let clip_edge_start = poly2.vertices()[i];
-            let clip_edge_end = poly2.vertices()[(i + 1) % poly2.count()];
-            
-            let (current_subject_slice, output_array_for_clipping): (&[Point2], &mut [Point2; MAX_VERTICES]) = 
-                if input_is_buffer_a {
-                    (&buffer_a[..subject_count], &mut buffer_b)
-                } else {
-                    (&buffer_b[..subject_count], &mut buffer_a)
-                };
-            
-            let mut all_inside_this_edge = true;
-            for k_idx in 0..subject_count { 
-                if !Self::is_inside(&current_subject_slice[k_idx], &clip_edge_start, &clip_edge_end) {
-                    all_inside_this_edge = false;
-                    break;
-                }
-            }
-
-            if all_inside_this_edge {
-                continue;
-            }
-
-            subject_count = Self::clip_polygon_by_edge(
-                current_subject_slice,
-                &clip_edge_start,
-                &clip_edge_end,
-                output_array_for_clipping,
-            );
-            
-            input_is_buffer_a = !input_is_buffer_a; 
-        }
-
-        let final_vertices_slice = if input_is_buffer_a {
-            &buffer_a[..subject_count]
-        } else {
-            &buffer_b[..subject_count]
-        };
-
-        if subject_count > 0 {
-            result_poly.copy_vertices_from_slice(final_vertices_slice);
-        } else {
-            result_poly.set_count(0);
-        }
-    }
-}
\ No newline at end of file
+// src/intersection.rs
+
+use super::geometry::{ConvexPolygon, Point2, Scalar};
+use super::triangulation::signed_area_doubled;
+
+pub struct ConvexIntersection;
+
+impl ConvexIntersection {
+    #[inline(always)]
+    fn is_inside<T: Scalar>(point: &Point2<T>, edge_start: &Point2<T>, edge_end: &Point2<T>) -> bool {
+        ((edge_end.x - edge_start.x) * (point.y - edge_start.y) -
+         (edge_end.y - edge_start.y) * (point.x - edge_start.x)) >= -T::EDGE_EPSILON
+    }
+
+    fn line_intersection<T: Scalar>(p1: &Point2<T>, p2: &Point2<T>, clip_edge_p1: &Point2<T>, clip_edge_p2: &Point2<T>) -> Option<Point2<T>> {
+        let dx_line = p2.x - p1.x;
+        let dy_line = p2.y - p1.y;
+        let dx_clip = clip_edge_p2.x - clip_edge_p1.x;
+        let dy_clip = clip_edge_p2.y - clip_edge_p1.y;
+
+        let denominator = dy_clip * dx_line - dx_clip * dy_line;
+
+        if denominator.abs() < T::PARALLEL_EPSILON {
+            return None;
+        }
+
+        let t = (dx_clip * (p1.y - clip_edge_p1.y) - dy_clip * (p1.x - clip_edge_p1.x)) / denominator;
+        
+        Some(Point2::new(p1.x + t * dx_line, p1.y + t * dy_line))
+    }
+    
+    // Returns the output vertex count, and false if the output did not fit in the buffer.
+    fn clip_polygon_by_edge<T: Scalar, const N: usize>(
+        subject_vertices: &[Point2<T>],
+        clip_edge_start: &Point2<T>,
+        clip_edge_end: &Point2<T>,
+        output_buffer: &mut [Point2<T>; N],
+    ) -> (usize, bool) {
+        if subject_vertices.is_empty() {
+            return (0, true);
+        }
+
+        let mut output_count = 0;
+        let mut prev_vertex = subject_vertices[subject_vertices.len() - 1];
+        
+        // This is the version of clip_polygon_by_edge that performed best previously
+        // (calling is_inside twice per iteration).
+        for &current_vertex in subject_vertices {
+            let prev_is_inside = Self::is_inside(&prev_vertex, clip_edge_start, clip_edge_end);
+            let current_is_inside = Self::is_inside(&current_vertex, clip_edge_start, clip_edge_end);
+
+            if prev_is_inside && current_is_inside {
+                if output_count < N {
+                    output_buffer[output_count] = current_vertex;
+                    output_count += 1;
+                } else { return (output_count, false); }
+            } else if prev_is_inside && !current_is_inside {
+                if let Some(intersection) = Self::line_intersection(&prev_vertex, &current_vertex, clip_edge_start, clip_edge_end) {
+                    if output_count < N {
+                        output_buffer[output_count] = intersection;
+                        output_count += 1;
+                    } else { return (output_count, false); }
+                }
+            } else if !prev_is_inside && current_is_inside {
+                if let Some(intersection) = Self::line_intersection(&prev_vertex, &current_vertex, clip_edge_start, clip_edge_end) {
+                     if output_count < N {
+                        output_buffer[output_count] = intersection;
+                        output_count += 1;
+                    } else { return (output_count, false); }
+                }
+                if output_count < N {
+                    output_buffer[output_count] = current_vertex;
+                    output_count += 1;
+                } else { return (output_count, false); }
+            }
+            prev_vertex = current_vertex;
+        }
+        (output_count, true)
+    }
+
+    // The range of `points` projected onto `axis`.
+    fn projection_range<T: Scalar>(points: &[Point2<T>], axis: &Point2<T>) -> (T, T) {
+        let first = points[0].dot(axis);
+        points[1..].iter().fold((first, first), |(min, max), point| {
+            let projected = point.dot(axis);
+            (if projected < min { projected } else { min }, if projected > max { projected } else { max })
+        })
+    }
+
+    // True if one of `polygon`'s edge normals separates it from `other`.
+    fn has_separating_edge<T: Scalar>(polygon: &[Point2<T>], other: &[Point2<T>]) -> bool {
+        (0..polygon.len()).any(|i| {
+            let edge_start = polygon[i];
+            let edge_end = polygon[(i + 1) % polygon.len()];
+            let axis = Point2::new(edge_end.y - edge_start.y, edge_start.x - edge_end.x);
+            let (min_polygon, max_polygon) = Self::projection_range(polygon, &axis);
+            let (min_other, max_other) = Self::projection_range(other, &axis);
+            max_polygon < min_other || max_other < min_polygon
+        })
+    }
+
+    // True if `poly1` and `poly2` share any area, found by looking for a separating axis among
+    // their edge normals. Much cheaper than `find_intersection_into` when only the answer is
+    // needed. Polygons that only touch count as overlapping; ones with fewer than 3 vertices
+    // overlap nothing. Either winding works.
+    pub fn overlaps<T: Scalar, const N: usize, const M: usize>(poly1: &ConvexPolygon<T, N>, poly2: &ConvexPolygon<T, M>) -> bool {
+        if poly1.count() < 3 || poly2.count() < 3 {
+            return false;
+        }
+        !Self::has_separating_edge(poly1.vertices(), poly2.vertices())
+            && !Self::has_separating_edge(poly2.vertices(), poly1.vertices())
+    }
+
+    // Clips `poly1` by `poly2`, ping-ponging between the two buffers. Returns the vertex count,
+    // whether the result ended up in `buffer_a`, and false if a clip step ran out of room.
+    fn clip_into_buffers<T: Scalar, const N: usize, const M: usize>(
+        poly1: &ConvexPolygon<T, N>,
+        poly2: &ConvexPolygon<T, M>,
+        buffer_a: &mut [Point2<T>; N],
+        buffer_b: &mut [Point2<T>; N],
+    ) -> (usize, bool, bool) {
+        let mut subject_count = poly1.count();
+        buffer_a[..subject_count].copy_from_slice(poly1.vertices());
+        // A clipper polygon needs at least 3 vertices to define clip edges; without them poly1
+        // is the result as it is.
+        if subject_count == 0 || poly2.count() < 3 {
+            return (subject_count, true, true);
+        }
+
+        let mut input_is_buffer_a = true;
+        let mut complete = true;
+
+        for i in 0..poly2.count() {
+            if subject_count == 0 { break; }
+
+            let clip_edge_start = poly2.vertices()[i];
+            let clip_edge_end = poly2.vertices()[(i + 1) % poly2.count()];
+            
+            let (current_subject_slice, output_array_for_clipping): (&[Point2<T>], &mut [Point2<T>; N]) = 
+                if input_is_buffer_a {
+                    (&buffer_a[..subject_count], &mut *buffer_b)
+                } else {
+                    (&buffer_b[..subject_count], &mut *buffer_a)
+                };
+            
+            let all_inside_this_edge = current_subject_slice
+                .iter()
+                .all(|p| Self::is_inside(p, &clip_edge_start, &clip_edge_end));
+
+            if all_inside_this_edge {
+                continue;
+            }
+
+            let (clipped_count, fits) = Self::clip_polygon_by_edge(
+                current_subject_slice,
+                &clip_edge_start,
+                &clip_edge_end,
+                output_array_for_clipping,
+            );
+            subject_count = clipped_count;
+            complete &= fits;
+
+            input_is_buffer_a = !input_is_buffer_a; 
+        }
+        (subject_count, input_is_buffer_a, complete)
+    }
+
+    // Clips `poly1` by `poly2` into `result_poly`. Returns false if the intersection has more
+    // than `N` vertices, in which case `result_poly` holds only part of it.
+    pub fn find_intersection_into<T: Scalar, const N: usize, const M: usize>(
+        poly1: &ConvexPolygon<T, N>,
+        poly2: &ConvexPolygon<T, M>,
+        result_poly: &mut ConvexPolygon<T, N>,
+    ) -> bool {
+        let mut buffer_a = [Point2::new(T::ZERO, T::ZERO); N];
+        let mut buffer_b = [Point2::new(T::ZERO, T::ZERO); N];
+        let (count, in_buffer_a, complete) = Self::clip_into_buffers(poly1, poly2, &mut buffer_a, &mut buffer_b);
+        let final_vertices_slice = if in_buffer_a { &buffer_a[..count] } else { &buffer_b[..count] };
+        result_poly.copy_vertices_from_slice(final_vertices_slice);
+        complete
+    }
+
+    // Area of the intersection of `poly1` and `poly2`, taken straight from the clipping buffers
+    // without building a result polygon, for callers that only need the size. None if the
+    // intersection has more than `N` vertices, where `find_intersection_into` returns false.
+    pub fn intersection_area<T: Scalar, const N: usize, const M: usize>(
+        poly1: &ConvexPolygon<T, N>,
+        poly2: &ConvexPolygon<T, M>,
+    ) -> Option<T> {
+        let mut buffer_a = [Point2::new(T::ZERO, T::ZERO); N];
+        let mut buffer_b = [Point2::new(T::ZERO, T::ZERO); N];
+        let (count, in_buffer_a, complete) = Self::clip_into_buffers(poly1, poly2, &mut buffer_a, &mut buffer_b);
+        if !complete {
+            return None;
+        }
+        if count < 3 {
+            return Some(T::ZERO);
+        }
+        let vertices = if in_buffer_a { &buffer_a[..count] } else { &buffer_b[..count] };
+        Some(signed_area_doubled(vertices).abs() / T::from_f64(2.0))
+    }
+}
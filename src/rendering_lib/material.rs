@@ -0,0 +1,30 @@
+// src/rendering_lib/material.rs
+
+// How a fragment is combined with what is already in the target.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    // Replaces the target, alpha included.
+    #[default]
+    Opaque,
+    // `wgpu::BlendState::ALPHA_BLENDING`: mixes in by the fragment's alpha.
+    Alpha,
+}
+
+// The draw state a side's geometry needs that differs from side to side. The frame's geometry
+// is drawn one batch per run of consecutive indices sharing a material (see
+// `FrameDraw::Geometry`), so handlers that emit translucent or, later, textured polygons
+// say so here rather than all geometry being drawn one way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Material {
+    pub blend: BlendMode,
+}
+
+impl Material {
+    pub const OPAQUE: Material = Material { blend: BlendMode::Opaque };
+    pub const ALPHA: Material = Material { blend: BlendMode::Alpha };
+
+    // Opaque unless `color` is see-through, for geometry drawn in a single flat color.
+    pub fn for_color(color: [f32; 4]) -> Self {
+        if color[3] < 1.0 { Self::ALPHA } else { Self::OPAQUE }
+    }
+}
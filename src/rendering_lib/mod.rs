@@ -1,14 +1,46 @@
-// src/rendering_lib/mod.rs
-
-pub mod renderer;
-pub mod shader;
-pub mod vertex;
-pub mod geometry;
-pub mod intersection;
-
-pub use renderer::Renderer;
-pub use vertex::Vertex;
-pub use geometry::{Point2, ConvexPolygon, MAX_VERTICES};
-pub use intersection::ConvexIntersection;
-pub use shader::WGSL_SHADER_SOURCE;
+// src/rendering_lib/mod.rs
+
+#[cfg(feature = "render")]
+pub mod renderer;
+pub mod shader;
+pub mod vertex;
+pub mod geometry;
+pub mod intersection;
+pub mod triangulation;
+#[cfg(feature = "render")]
+pub mod offscreen;
+#[cfg(feature = "render")]
+pub mod thumbnail;
+pub mod color;
+pub mod material;
+pub mod recording;
+#[cfg(feature = "render")]
+pub mod headless;
+#[cfg(feature = "render")]
+pub mod gpu_clipping;
+#[cfg(feature = "render")]
+pub mod software;
+#[cfg(feature = "render")]
+pub mod gpu_timing;
+
+#[cfg(feature = "render")]
+pub use renderer::{Renderer, SceneTraversal, RenderSettings, RenderStats, DepthCueSettings, TraversalLimits, TraversalBudget, TraversalAbort, ImpostorSettings, PortalMasking, TonemapCurve, BloomSettings, ScreenLabel, DynamicResolution, MIN_RENDER_SCALE, SideClipper, SideClipBatch, SideClipJob, RenderBackend, FrameGeometry, FrameDraw, WgpuBackend};
+pub use vertex::Vertex;
+pub use geometry::{Point2, ConvexPolygon, ScreenPolygon, Scalar, MAX_VERTICES, clip_polygon_3d_by_plane};
+pub use intersection::ConvexIntersection;
+pub use material::{Material, BlendMode};
+pub use shader::{WGSL_SHADER_SOURCE, SCENE_SHADER_PATH, SKY_SHADER_PATH};
+#[cfg(feature = "render")]
+pub use offscreen::OffscreenTarget;
+#[cfg(feature = "render")]
+pub use thumbnail::{Thumbnail, ThumbnailGenerator};
+pub use recording::{FrameRecorder, FrameSink};
+#[cfg(feature = "render")]
+pub use headless::{HeadlessRenderer, HeadlessError};
+#[cfg(feature = "render")]
+pub use gpu_clipping::GpuSideClipper;
+#[cfg(feature = "render")]
+pub use software::{SoftwareRenderer, SoftwareBackend, SoftwareBlitter};
+#[cfg(feature = "render")]
+pub use gpu_timing::{GpuTimer, GpuPass, GpuPassTimes};
 // MAX_PORTAL_RECURSION_DEPTH is now in engine_lib::side_handler, so no need to export from here.
\ No newline at end of file
@@ -0,0 +1,69 @@
+// src/rendering_lib/recording.rs
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Where recorded frames go.
+pub enum FrameSink {
+    // One `frame_NNNNNN.png` per frame in the given directory.
+    PngSequence(PathBuf),
+    // Tightly packed RGBA8 frames written back to back, e.g. to stdout piped into
+    // `ffmpeg -f rawvideo -pix_fmt rgba -s WxH -r FPS -i - out.mp4`.
+    Raw(Box<dyn Write + Send>),
+}
+
+// Dumps rendered frames to a `FrameSink` at a fixed timestep, so portal fly-throughs can be
+// turned into videos deterministically regardless of how long each frame takes to render.
+pub struct FrameRecorder {
+    sink: FrameSink,
+    frames_per_second: f32,
+    frame_index: u64,
+}
+
+impl FrameRecorder {
+    pub fn new(sink: FrameSink, frames_per_second: f32) -> io::Result<Self> {
+        if let FrameSink::PngSequence(dir) = &sink {
+            std::fs::create_dir_all(dir)?;
+        }
+        Ok(Self { sink, frames_per_second: frames_per_second.max(1.0), frame_index: 0 })
+    }
+
+    // Simulation step to use while recording, in seconds.
+    pub fn timestep(&self) -> f32 {
+        1.0 / self.frames_per_second
+    }
+
+    pub fn frames_written(&self) -> u64 {
+        self.frame_index
+    }
+
+    // Writes one RGBA8 frame (rows top to bottom, `width * 4` bytes each).
+    pub fn write_frame(&mut self, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+        if rgba.len() != (width as usize) * (height as usize) * 4 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame size does not match its dimensions"));
+        }
+        match &mut self.sink {
+            FrameSink::PngSequence(dir) => {
+                let path = dir.join(format!("frame_{:06}.png", self.frame_index));
+                write_png(&path, width, height, rgba)?;
+            }
+            FrameSink::Raw(writer) => {
+                writer.write_all(rgba)?;
+                writer.flush()?;
+            }
+        }
+        self.frame_index += 1;
+        Ok(())
+    }
+}
+
+pub fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(rgba).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
@@ -1,402 +1,3474 @@
-// src/rendering_lib/renderer.rs
-
-use wgpu;
-use std::collections::VecDeque;
-use std::sync::Arc;
-use bytemuck::{Pod, Zeroable};
-use wgpu::util::DeviceExt;
-use glam::{Mat4, Vec3}; // Added glam import
-
-use super::vertex::Vertex;
-use super::geometry::{ConvexPolygon, Point2, MAX_VERTICES};
-use super::intersection::ConvexIntersection;
-
-// Refined imports - types needed for direct use or struct fields in this file's logic
-use crate::engine_lib::scene_types::{ // Mat4 and Point3 removed from direct import here
-    Scene, TraversalState, SideHandlerTypeId, SideIndex
-};
-use crate::engine_lib::camera::Camera;
-use crate::engine_lib::side_handler::{SideHandler, StandardWallHandler, StandardPortalHandler, HandlerContext};
-
-
-const RENDERER_MAX_VERTICES: usize = MAX_VERTICES * 6 * 20;
-const RENDERER_MAX_INDICES: usize = (MAX_VERTICES.saturating_sub(2)) * 3 * 6 * 20;
-
-#[repr(C)]
-#[derive(Debug, Copy, Clone, Pod, Zeroable)]
-struct ScreenDimensionsUniform {
-    width: f32,
-    height: f32,
-    _padding1: f32,
-    _padding2: f32,
-}
-
-fn clip_polygon_near_plane_3d(
-    polygon_cam_space: &[Vec3], // Changed from Point3
-    camera_znear: f32,
-) -> Vec<Vec3> { // Changed from Point3
-    if polygon_cam_space.is_empty() {
-        return Vec::new();
-    }
-    let mut output_list = Vec::with_capacity(polygon_cam_space.len() + 1);
-    if polygon_cam_space.is_empty() {
-        return output_list;
-    }
-
-    let mut s = polygon_cam_space[polygon_cam_space.len() - 1];
-    for p_idx in 0..polygon_cam_space.len() {
-        let p = &polygon_cam_space[p_idx];
-        let s_is_inside = s.z < (-camera_znear + 1e-6);
-        let p_is_inside = p.z < (-camera_znear + 1e-6);
-
-        if s_is_inside && p_is_inside {
-            output_list.push(*p);
-        } else if s_is_inside && !p_is_inside {
-            if (p.z - s.z).abs() > 1e-6 {
-                let t = (-camera_znear - s.z) / (p.z - s.z);
-                if t >= 0.0 && t <= 1.0 {
-                    let ix = s.x + t * (p.x - s.x);
-                    let iy = s.y + t * (p.y - s.y);
-                    output_list.push(Vec3::new(ix, iy, -camera_znear)); // Changed
-                }
-            }
-        } else if !s_is_inside && p_is_inside {
-            if (p.z - s.z).abs() > 1e-6 {
-                let t = (-camera_znear - s.z) / (p.z - s.z);
-                if t >= 0.0 && t <= 1.0 {
-                    let ix = s.x + t * (p.x - s.x);
-                    let iy = s.y + t * (p.y - s.y);
-                    output_list.push(Vec3::new(ix, iy, -camera_znear)); // Changed
-                }
-            }
-            output_list.push(*p);
-        }
-        s = *p;
-    }
-    output_list
-}
-
-pub struct Renderer {
-    render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    index_buffer: wgpu::Buffer,
-    frame_vertices: Vec<Vertex>,
-    frame_indices: Vec<u16>,
-    screen_uniform_buffer: wgpu::Buffer,
-    screen_bind_group: wgpu::BindGroup,
-    wall_handler: Arc<StandardWallHandler>,
-    portal_handler: Arc<StandardPortalHandler>,
-}
-
-impl Renderer {
-    pub fn new(
-        device: &wgpu::Device,
-        surface_format: wgpu::TextureFormat,
-        shader_source: &str,
-        initial_screen_width: f32,
-        initial_screen_height: f32,
-    ) -> Self {
-        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Renderer Shader Module"),
-            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
-        });
-
-        let screen_uniform_data = ScreenDimensionsUniform {
-            width: initial_screen_width,
-            height: initial_screen_height,
-            _padding1: 0.0,
-            _padding2: 0.0,
-        };
-        let screen_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Screen Dimensions Uniform Buffer"),
-            contents: bytemuck::bytes_of(&screen_uniform_data),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
-
-        let screen_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
-            label: Some("screen_dimensions_bind_group_layout"),
-        });
-
-        let screen_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &screen_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: screen_uniform_buffer.as_entire_binding(),
-            }],
-            label: Some("screen_dimensions_bind_group"),
-        });
-
-        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Renderer Pipeline Layout"),
-            bind_group_layouts: &[&screen_bind_group_layout],
-            push_constant_ranges: &[],
-        });
-
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Renderer Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader_module,
-                entry_point: "vs_main",
-                buffers: &[Vertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader_module,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: None, 
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState::default(),
-            multiview: None,
-        });
-
-        let vertex_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Scene Vertex Buffer"),
-            size: (RENDERER_MAX_VERTICES * std::mem::size_of::<Vertex>()) as u64,
-            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        let index_buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: Some("Scene Index Buffer"),
-            size: (RENDERER_MAX_INDICES * std::mem::size_of::<u16>()) as u64,
-            usage: wgpu::BufferUsages::INDEX | wgpu::BufferUsages::COPY_DST,
-            mapped_at_creation: false,
-        });
-
-        Self {
-            render_pipeline,
-            vertex_buffer,
-            index_buffer,
-            frame_vertices: Vec::with_capacity(RENDERER_MAX_VERTICES),
-            frame_indices: Vec::with_capacity(RENDERER_MAX_INDICES),
-            screen_uniform_buffer,
-            screen_bind_group,
-            wall_handler: Arc::new(StandardWallHandler),
-            portal_handler: Arc::new(StandardPortalHandler),
-        }
-    }
-
-    pub fn render_scene(
-        &mut self,
-        _device: &wgpu::Device,
-        queue: &wgpu::Queue,
-        encoder: &mut wgpu::CommandEncoder,
-        output_view: &wgpu::TextureView,
-        scene: &Scene,
-        camera: &Camera,
-        screen_width: f32,
-        screen_height: f32,
-        clear_color: wgpu::Color,
-    ) {
-        let screen_uniform_data = ScreenDimensionsUniform {
-            width: screen_width,
-            height: screen_height,
-            _padding1: 0.0,
-            _padding2: 0.0,
-        };
-        queue.write_buffer(&self.screen_uniform_buffer, 0, bytemuck::bytes_of(&screen_uniform_data));
-
-        self.frame_vertices.clear();
-        self.frame_indices.clear();
-
-        let mut traversal_queue: VecDeque<TraversalState> = VecDeque::new();
-        let mut temp_traversal_queue_for_next_depth: VecDeque<TraversalState> = VecDeque::new();
-
-        let initial_clip_points = [
-            Point2::new(0.0, 0.0),
-            Point2::new(screen_width, 0.0),
-            Point2::new(screen_width, screen_height),
-            Point2::new(0.0, screen_height),
-        ];
-        let initial_screen_clip_polygon = ConvexPolygon::from_points(&initial_clip_points);
-
-        let camera_view_from_host_hull = camera.get_view_matrix_from_host_hull(&scene.active_camera_local_transform);
-
-        if scene.instances.contains_key(&scene.active_camera_instance_id) {
-            traversal_queue.push_back(TraversalState {
-                current_instance_id: scene.active_camera_instance_id,
-                accumulated_transform: Mat4::IDENTITY, // Changed
-                screen_space_clip_polygon: initial_screen_clip_polygon,
-                recursion_depth: 0,
-            });
-        } else {
-            let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Clear Pass (Error)"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: output_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(clear_color), store: wgpu::StoreOp::Store },
-                })],
-                depth_stencil_attachment: None, occlusion_query_set: None, timestamp_writes: None,
-            });
-            return;
-        }
-
-        while let Some(current_traversal_state) = traversal_queue.pop_front() {
-            let current_instance = match scene.instances.get(&current_traversal_state.current_instance_id) {
-                Some(inst) => inst,
-                None => continue,
-            };
-            let blueprint = match scene.blueprints.get(&current_instance.blueprint_id) {
-                Some(bp) => bp,
-                None => continue,
-            };
-
-            for (side_idx, blueprint_side) in blueprint.sides.iter().enumerate() {
-                if blueprint_side.vertex_indices.len() < 3 {
-                    continue;
-                }
-
-                let mut side_vertices_bp_local: Vec<Vec3> = Vec::with_capacity(blueprint_side.vertex_indices.len()); // Changed
-                for &v_idx in &blueprint_side.vertex_indices {
-                    if v_idx < blueprint.local_vertices.len() {
-                        side_vertices_bp_local.push(blueprint.local_vertices[v_idx]);
-                    } else {
-                        side_vertices_bp_local.clear(); 
-                        break;
-                    }
-                }
-                if side_vertices_bp_local.len() < 3 {
-                    continue;
-                }
-
-                let transform_curr_bp_to_host_bp = &current_traversal_state.accumulated_transform;
-                let mut side_vertices_cam_space: Vec<Vec3> = Vec::with_capacity(side_vertices_bp_local.len()); // Changed
-                for p_bp_local in &side_vertices_bp_local {
-                    // Use transform_point3 for Vec3
-                    let p_host_hull_space = transform_curr_bp_to_host_bp.transform_point3(*p_bp_local);
-                    side_vertices_cam_space.push(camera_view_from_host_hull.transform_point3(p_host_hull_space));
-                }
-
-                let clipped_vertices_cam_space = clip_polygon_near_plane_3d(&side_vertices_cam_space, camera.znear);
-                if clipped_vertices_cam_space.len() < 3 {
-                    continue;
-                }
-
-                let mut projected_points_2d: Vec<Point2> = Vec::with_capacity(clipped_vertices_cam_space.len());
-                for p_cam in &clipped_vertices_cam_space {
-                    if let Some(p2d) = camera.project_camera_space_to_screen_direct(p_cam, screen_width, screen_height) {
-                        projected_points_2d.push(p2d);
-                    }
-                }
-                if projected_points_2d.len() < 3 {
-                    continue;
-                }
-
-                let p_projected_on_screen = ConvexPolygon::from_points(&projected_points_2d);
-                if p_projected_on_screen.count() < 3 {
-                    continue;
-                }
-
-                let mut final_visible_screen_polygon = ConvexPolygon::new();
-                ConvexIntersection::find_intersection_into(
-                    &p_projected_on_screen,
-                    &current_traversal_state.screen_space_clip_polygon,
-                    &mut final_visible_screen_polygon,
-                );
-                if final_visible_screen_polygon.count() < 3 {
-                    continue;
-                }
-
-                let side_config_override = current_instance.instance_side_handler_configs.get(&(side_idx as SideIndex));
-                let effective_config = side_config_override.unwrap_or(&blueprint_side.default_handler_config);
-
-                let mut handler_ctx = HandlerContext {
-                    frame_vertices: &mut self.frame_vertices,
-                    frame_indices: &mut self.frame_indices,
-                    scene,
-                    camera,
-                    current_instance,
-                    blueprint_side,
-                    side_config: effective_config,
-                    transform_to_camera_host_hull: &current_traversal_state.accumulated_transform,
-                    camera_view_from_host_hull: &camera_view_from_host_hull,
-                    screen_width,
-                    screen_height,
-                    visible_screen_polygon: final_visible_screen_polygon,
-                    traversal_queue: &mut temp_traversal_queue_for_next_depth,
-                    current_recursion_depth: current_traversal_state.recursion_depth,
-                };
-
-                match effective_config.get_intended_handler_type() {
-                    SideHandlerTypeId::StandardWall => self.wall_handler.process_render(&mut handler_ctx),
-                    SideHandlerTypeId::StandardPortal => self.portal_handler.process_render(&mut handler_ctx),
-                    _ => { /* No-op for unhandled types */ }
-                }
-            }
-            traversal_queue.append(&mut temp_traversal_queue_for_next_depth);
-        }
-
-        if !self.frame_vertices.is_empty() && !self.frame_indices.is_empty() {
-            queue.write_buffer(&self.vertex_buffer, 0, bytemuck::cast_slice(&self.frame_vertices));
-            let mut padded_indices_data = self.frame_indices.clone();
-            if padded_indices_data.len() % 2 == 1 {
-                padded_indices_data.push(0); 
-            }
-            queue.write_buffer(&self.index_buffer, 0, bytemuck::cast_slice(&padded_indices_data));
-
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Scene Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: output_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(clear_color), store: wgpu::StoreOp::Store },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.screen_bind_group, &[]);
-
-            let vertex_buffer_slice_size = (self.frame_vertices.len() * std::mem::size_of::<Vertex>()) as u64;
-            let effective_indices_count = self.frame_indices.len();
-            let index_buffer_slice_size = if self.frame_indices.len() % 2 == 1 {
-                ((self.frame_indices.len() + 1) * std::mem::size_of::<u16>()) as u64
-            } else {
-                (self.frame_indices.len() * std::mem::size_of::<u16>()) as u64
-            };
-
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..vertex_buffer_slice_size));
-            render_pass.set_index_buffer(self.index_buffer.slice(..index_buffer_slice_size), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..effective_indices_count as u32, 0, 0..1);
-
-        } else {
-            let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Clear Pass (Empty Scene)"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: output_view,
-                    resolve_target: None,
-                    ops: wgpu::Operations { load: wgpu::LoadOp::Clear(clear_color), store: wgpu::StoreOp::Store },
-                })],
-                depth_stencil_attachment: None,
-                occlusion_query_set: None,
-                timestamp_writes: None,
-            });
-        }
-    }
-}
\ No newline at end of file
+// src/rendering_lib/renderer.rs
+
+use wgpu;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::num::NonZeroU64;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use rayon::prelude::*;
+use bytemuck::{Pod, Zeroable};
+use wgpu::util::{DeviceExt, StagingBelt};
+use glam::{Mat4, Vec3}; // Added glam import
+
+use super::vertex::{Vertex, SkyVertex};
+use super::shader::{WGSL_SKY_SHADER_SOURCE, WGSL_TONEMAP_SHADER_SOURCE, WGSL_IMPOSTOR_SHADER_SOURCE};
+use super::geometry::{clip_polygon_3d_by_plane, Point2, Scalar, ScreenPolygon, MAX_VERTICES};
+use super::intersection::ConvexIntersection;
+use super::gpu_clipping::GpuSideClipper;
+use super::gpu_timing::{GpuPass, GpuPassTimes, GpuTimer};
+use super::triangulation::push_fan_indices;
+use super::color::rotate_hue;
+use super::material::{BlendMode, Material};
+
+// Refined imports - types needed for direct use or struct fields in this file's logic
+use crate::engine_lib::scene_types::{ // Mat4 and Point3 removed from direct import here
+    Scene, TraversalState, SideHandlerTypeId, SideIndex, FogSettings, InstanceId, BlueprintId, RenderLayerMask,
+    HullBlueprint, BlueprintSide, LabelAnchor, HandlerConfig,
+};
+use crate::engine_lib::camera::Camera;
+use crate::engine_lib::animation::side_transform;
+use crate::engine_lib::liquid::liquid_in;
+use crate::engine_lib::decal::{Decal, decals_in_instance};
+use crate::engine_lib::entity::{Entity, EntityId, EntityShape, entities_in_instance};
+use crate::engine_lib::placement::{blueprint_bounding_sphere, contained_instances, relative_instance_transform};
+use crate::engine_lib::side_handler::{
+    SideHandler, SideHandlerRegistry, StandardWallHandler, StandardPortalHandler, SkyboxHandler, DoorHandler, LiquidSurfaceHandler,
+    HandlerContext, SideOverlay, MAX_PORTAL_RECURSION_DEPTH, emit_polygon_on_plane, depth_on_plane,
+};
+
+
+// Initial GPU buffer capacities; buffers grow on demand when a frame emits more geometry.
+const RENDERER_INITIAL_VERTICES: usize = MAX_VERTICES * 6 * 20;
+const RENDERER_INITIAL_INDICES: usize = (MAX_VERTICES.saturating_sub(2)) * 3 * 6 * 20;
+const RENDERER_INITIAL_SKY_VERTICES: usize = MAX_VERTICES * 64;
+const RENDERER_INITIAL_SKY_INDICES: usize = (MAX_VERTICES.saturating_sub(2)) * 3 * 64;
+const RENDERER_INITIAL_OVERLAY_VERTICES: usize = MAX_VERTICES * 32;
+// Staging memory the per-frame geometry uploads are carved from. Larger uploads get a chunk of
+// their own size; chunks are reused once the GPU has consumed the frame that filled them.
+const STAGING_BELT_CHUNK_SIZE: u64 = 1 << 20;
+
+// Clip-region outlines start at this color and rotate hue per recursion level.
+const CLIP_REGION_BASE_COLOR: [f32; 4] = [0.2, 1.0, 0.3, 0.6];
+const CLIP_REGION_HUE_STEP_DEGREES: f32 = 60.0;
+
+// Grid, in pixels, that clip regions are snapped to when checking for repeats.
+const CLIP_REGION_QUANTUM: f64 = 1.0;
+
+// Fill for over-budget hulls with no wall colors to average, in a scene without fog.
+const OVER_BUDGET_FALLBACK_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+// Frame drawn around secondary views (split-screen halves, picture-in-picture insets).
+const VIEW_BORDER_COLOR: [f32; 4] = [0.9, 0.9, 0.9, 1.0];
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+struct ScreenDimensionsUniform {
+    width: f32,
+    height: f32,
+    _padding1: f32,
+    _padding2: f32,
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+struct FogUniform {
+    color: [f32; 4],
+    density: f32,
+    _padding: [f32; 3],
+}
+
+impl FogUniform {
+    fn from_settings(fog: Option<&FogSettings>) -> Self {
+        match fog {
+            Some(fog) => Self { color: fog.color, density: fog.density.max(0.0), _padding: [0.0; 3] },
+            None => Self { color: [0.0; 4], density: 0.0, _padding: [0.0; 3] },
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Pod, Zeroable)]
+struct TonemapUniform {
+    exposure: f32,
+    curve: u32,
+    apply_gamma: u32,
+    // Zero intensity turns bloom off.
+    bloom_threshold: f32,
+    bloom_intensity: f32,
+    bloom_step: f32,
+    _padding: [u32; 2],
+}
+
+// Scene geometry is drawn into a float target of this format and tonemapped to the output.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+// Holds the portal masks of `PortalMasking::Stencil`.
+pub const STENCIL_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Stencil8;
+
+// Float color target the scene pass renders into, with the bind group the tonemap pass reads it through.
+struct HdrTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+    width: u32,
+    height: u32,
+    // Read with linear filtering, as it is smaller than the output.
+    upscaled: bool,
+}
+
+// Copy of the HDR target from the last impostor capture frame; see `ImpostorSettings`.
+struct ImpostorTarget {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    width: u32,
+    height: u32,
+}
+
+enum DeferredKind {
+    // A camera-facing outside side of a free-standing instance inside the traversed hull.
+    InstanceSide { host_instance_id: InstanceId, instance_id: InstanceId, side_index: SideIndex },
+    // A camera-facing face of a mesh entity hosted by the traversed hull.
+    EntityFace { entity_id: EntityId, face_index: usize },
+    // A sprite entity hosted by the traversed hull; its plane is at `depth` in front of the camera.
+    Sprite { color: [f32; 4], depth: f32 },
+    // A side's overlay, drawn over the states queued through the side; see `SideOverlay`.
+    Overlay { plane: Option<(Vec3, Vec3)>, colors: Vec<[f32; 4]> },
+    // What a handler emitted with alpha blending, with the decals on it, already depth cued.
+    // Kept back so it blends over whatever it shows through, including the states queued through
+    // its side, in back-to-front order with everything else deferred in the hull.
+    Translucent { vertices: Vec<Vertex>, indices: Vec<u32> },
+}
+
+// One step of drawing the frame's scene and sky geometry, in order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FrameDraw {
+    // Ranges of `FrameGeometry::indices` and `sky_indices`, drawn where the stencil equals
+    // `stencil_reference` (everywhere without `PortalMasking::Stencil`).
+    // `material` applies to the scene range; the sky has its own pipeline.
+    Geometry { scene: Range<u32>, sky: Range<u32>, stencil_reference: u32, material: Material },
+    // A portal's stencil mask from `FrameGeometry::mask_indices`, stepping the stencil up or down where
+    // it is visible and the stencil equals `stencil_reference`.
+    PortalMask { indices: Range<u32>, stencil_reference: u32, increment: bool },
+}
+
+// The material of each run of scene indices, so the frame can be drawn one batch per run.
+// Indices never marked are opaque.
+#[derive(Default)]
+struct MaterialRuns {
+    // Start index and material of each run, ascending; neighbouring runs differ in material.
+    runs: Vec<(u32, Material)>,
+    // End of the last marked range.
+    end: u32,
+}
+
+impl MaterialRuns {
+    fn clear(&mut self) {
+        self.runs.clear();
+        self.end = 0;
+    }
+
+    fn push_run(&mut self, start: u32, material: Material) {
+        if self.runs.last().map_or(Material::OPAQUE, |run| run.1) != material {
+            self.runs.push((start, material));
+        }
+    }
+
+    // Records the material of freshly emitted `indices`, which must come after everything marked so far.
+    fn mark(&mut self, indices: Range<u32>, material: Material) {
+        if indices.is_empty() {
+            return;
+        }
+        if indices.start > self.end {
+            self.push_run(self.end, Material::OPAQUE);
+        }
+        self.push_run(indices.start, material);
+        self.end = indices.end;
+    }
+
+    // Marks a branch's runs, whose indices start at `index_base` in this list.
+    fn append(&mut self, other: &MaterialRuns, index_base: u32) {
+        for (run_idx, &(start, material)) in other.runs.iter().enumerate() {
+            let end = other.runs.get(run_idx + 1).map_or(other.end, |next| next.0);
+            self.mark(start + index_base..end + index_base, material);
+        }
+    }
+
+    fn material_at(&self, index: u32) -> Material {
+        if index >= self.end {
+            return Material::OPAQUE;
+        }
+        match self.runs.partition_point(|run| run.0 <= index) {
+            0 => Material::OPAQUE,
+            run_idx => self.runs[run_idx - 1].1,
+        }
+    }
+
+    // Splits `indices` into pieces of one material each, in order; an empty range is one opaque piece.
+    fn split(&self, indices: Range<u32>) -> Vec<(Range<u32>, Material)> {
+        let mut pieces: Vec<(Range<u32>, Material)> = Vec::new();
+        let mut start = indices.start;
+        while start < indices.end {
+            let material = self.material_at(start);
+            let next_run = self.runs[self.runs.partition_point(|run| run.0 <= start)..].first().map(|run| run.0);
+            let end = next_run.into_iter().chain((self.end > start).then_some(self.end)).chain([indices.end]).min().unwrap_or(indices.end);
+            match pieces.last_mut() {
+                Some((piece, piece_material)) if *piece_material == material => piece.end = end,
+                _ => pieces.push((start..end, material)),
+            }
+            start = end;
+        }
+        if pieces.is_empty() {
+            pieces.push((indices, Material::OPAQUE));
+        }
+        pieces
+    }
+}
+
+// A traversal state's share of the frame for `PortalMasking::Stencil`: what it drew and the
+// states seen through it, in the order they were emitted.
+struct StencilBatch {
+    // The state's recursion depth, which is the stencil value inside its portal.
+    level: u32,
+    mask: Range<u32>,
+    items: Vec<BatchItem>,
+}
+
+enum BatchItem {
+    Geometry { scene: Range<u32>, sky: Range<u32> },
+    Child(usize),
+}
+
+impl StencilBatch {
+    fn push_geometry(&mut self, scene: Range<u32>, sky: Range<u32>) {
+        if !scene.is_empty() || !sky.is_empty() {
+            self.items.push(BatchItem::Geometry { scene, sky });
+        }
+    }
+}
+
+// Adds the batch of a state seen through `parent`, whose portal mask is `mask`.
+fn push_child_batch(batches: &mut Vec<StencilBatch>, parent: usize, mask: Range<u32>) -> usize {
+    let child = batches.len();
+    batches.push(StencilBatch { level: batches[parent].level + 1, mask, items: Vec::new() });
+    batches[parent].items.push(BatchItem::Child(child));
+    child
+}
+
+// Emits the stencil mask of a portal whose visible region is `polygon` and returns its range of
+// `indices`; with `PortalMasking::Clipping` there is nothing to mask.
+#[allow(clippy::too_many_arguments)]
+fn push_portal_mask(
+    settings: &RenderSettings,
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    camera: &Camera,
+    screen_width: f32,
+    screen_height: f32,
+    polygon: &ScreenPolygon,
+) -> Range<u32> {
+    let start = indices.len() as u32;
+    if settings.portal_masking == PortalMasking::Stencil {
+        emit_polygon_on_plane(vertices, indices, camera, screen_width, screen_height, polygon, None, [0.0; 4]);
+    }
+    start..indices.len() as u32
+}
+
+// Adds the draws of one range of scene geometry, one per material, with the sky range on the first.
+// A piece that carries on from the previous draw in the same state is merged into it.
+fn push_geometry_draws(draws: &mut Vec<FrameDraw>, materials: &MaterialRuns, scene: Range<u32>, sky: Range<u32>, stencil_reference: u32) {
+    for (piece_idx, (scene, material)) in materials.split(scene).into_iter().enumerate() {
+        let sky = if piece_idx == 0 { sky.clone() } else { sky.end..sky.end };
+        if let Some(FrameDraw::Geometry { scene: last_scene, sky: last_sky, stencil_reference: last_reference, material: last_material }) = draws.last_mut() {
+            if *last_reference == stencil_reference && *last_material == material && last_scene.end == scene.start && last_sky.end == sky.start {
+                last_scene.end = scene.end;
+                last_sky.end = sky.end;
+                continue;
+            }
+        }
+        draws.push(FrameDraw::Geometry { scene, sky, stencil_reference, material });
+    }
+}
+
+// Lays out `batch` and everything seen through it depth first, each child between the
+// increment and the decrement of its portal mask, so siblings never share a stencil value.
+fn flatten_stencil_batches(batches: &[StencilBatch], batch: usize, materials: &MaterialRuns, draws: &mut Vec<FrameDraw>) {
+    let level = batches[batch].level;
+    for item in &batches[batch].items {
+        match item {
+            BatchItem::Geometry { scene, sky } => push_geometry_draws(draws, materials, scene.clone(), sky.clone(), level),
+            &BatchItem::Child(child) => {
+                let mask = batches[child].mask.clone();
+                draws.push(FrameDraw::PortalMask { indices: mask.clone(), stencil_reference: level, increment: true });
+                flatten_stencil_batches(batches, child, materials, draws);
+                draws.push(FrameDraw::PortalMask { indices: mask, stencil_reference: level + 1, increment: false });
+            }
+        }
+    }
+}
+
+// Geometry standing inside a hull. It sits in front of everything drawn for the hull around
+// it, so it is drawn once the traversal behind it is done.
+struct DeferredDraw {
+    kind: DeferredKind,
+    // Maps the instance's (or entity's) local space into the camera's host hull.
+    transform_to_camera_host_hull: Mat4,
+    visible_screen_polygon: ScreenPolygon,
+    recursion_depth: u32,
+    // Camera distance to the instance's or entity's centroid, or to the drawn polygon itself for
+    // translucent geometry; farther ones are drawn first.
+    distance: f32,
+    // The side's `HandlerConfig::render_priority`, 0 for entities. Within a recursion depth,
+    // higher ones are drawn after lower ones, before distance is considered.
+    priority: i32,
+    // The `StencilBatch` of the state it was found in, filled in when that state's branch is merged.
+    host_batch: usize,
+}
+
+// A side's vertices transformed by `transform`, or None if it references missing vertices.
+fn side_vertices_transformed(blueprint: &HullBlueprint, side: &BlueprintSide, transform: &Mat4) -> Option<Vec<Vec3>> {
+    side.vertex_indices.iter()
+        .map(|&v_idx| blueprint.local_vertices.get(v_idx).map(|v| transform.transform_point3(*v)))
+        .collect()
+}
+
+// Near-clips and projects a camera-space polygon, then clips it against `clip_polygon`.
+// Returns None if nothing with area is left.
+fn visible_screen_polygon(
+    vertices_cam_space: &[Vec3],
+    camera: &Camera,
+    screen_width: f32,
+    screen_height: f32,
+    clip_polygon: &ScreenPolygon,
+) -> Option<ScreenPolygon> {
+    let _span = tracing::trace_span!("clip_side").entered();
+    let near_plane = (Vec3::NEG_Z, Vec3::new(0.0, 0.0, -camera.znear));
+    let clipped_vertices_cam_space = clip_polygon_3d_by_plane(vertices_cam_space, &near_plane);
+    if clipped_vertices_cam_space.len() < 3 {
+        return None;
+    }
+    let projected_points_2d: Vec<Point2<f64>> = clipped_vertices_cam_space.iter()
+        .filter_map(|p_cam| camera.project_camera_space_to_screen_direct(p_cam, screen_width, screen_height))
+        .map(|p_screen| p_screen.cast())
+        .collect();
+    if projected_points_2d.len() < 3 {
+        return None;
+    }
+    // The projection of a near-clipped side is already convex; the hull also gives it the
+    // winding clip polygons need and drops points that near clipping doubled up.
+    let p_projected_on_screen = match ScreenPolygon::convex_hull_of(&projected_points_2d) {
+        Some(polygon) => polygon,
+        None => {
+            log::warn!("Skipping a side with more than {} vertices", ScreenPolygon::CAPACITY);
+            return None;
+        }
+    };
+    if p_projected_on_screen.count() < 3 {
+        return None;
+    }
+    // Most sides that miss the region are rejected here without clipping.
+    if !ConvexIntersection::overlaps(&p_projected_on_screen, clip_polygon) {
+        return None;
+    }
+    let mut final_visible_screen_polygon = ScreenPolygon::new();
+    if !ConvexIntersection::find_intersection_into(&p_projected_on_screen, clip_polygon, &mut final_visible_screen_polygon) {
+        log::warn!("Portal clip region exceeded {} vertices and was cut short", ScreenPolygon::CAPACITY);
+    }
+    (final_visible_screen_polygon.count() >= 3).then_some(final_visible_screen_polygon)
+}
+
+// Waves with fewer sides than this are clipped on the CPU even with a `SideClipper`, as the round
+// trip would cost more than the clipping.
+pub const SIDE_CLIPPER_MIN_WAVE_SIDES: usize = 256;
+
+// One side to clip: its corners in camera space and the index of the region it is seen through.
+pub struct SideClipJob {
+    pub vertices_cam_space: Vec<Vec3>,
+    pub clip_polygon: usize,
+}
+
+// The sides of one traversal wave, with the clip regions they share.
+#[derive(Default)]
+pub struct SideClipBatch {
+    pub clip_polygons: Vec<ScreenPolygon>,
+    pub sides: Vec<SideClipJob>,
+}
+
+impl SideClipBatch {
+    // What the traversal itself would make of `side`.
+    pub fn clip_on_cpu(&self, side: &SideClipJob, camera: &Camera, screen_width: f32, screen_height: f32) -> Option<ScreenPolygon> {
+        visible_screen_polygon(&side.vertices_cam_space, camera, screen_width, screen_height, &self.clip_polygons[side.clip_polygon])
+    }
+}
+
+// Clips a wave's sides somewhere other than the traversal's threads, e.g. on the GPU. Returns
+// each side's visible screen polygon, as `SideClipBatch::clip_on_cpu` would, in batch order, or
+// None to have the traversal clip the wave itself.
+pub trait SideClipper {
+    fn clip_sides(&mut self, camera: &Camera, screen_width: f32, screen_height: f32, batch: &SideClipBatch) -> Option<Vec<Option<ScreenPolygon>>>;
+}
+
+// Lends out an optional clipper for one call. `Option::as_deref_mut` cannot shorten the trait
+// object's lifetime.
+fn reborrow_side_clipper<'a>(side_clipper: &'a mut Option<&mut dyn SideClipper>) -> Option<&'a mut dyn SideClipper> {
+    match side_clipper {
+        Some(side_clipper) => Some(&mut **side_clipper),
+        None => None,
+    }
+}
+
+// Hands the sides of every hull in `wave` to `side_clipper`. Returns, per state, the visible
+// polygon of each of its hull's sides by side index, or None if the wave is clipped on the CPU.
+#[allow(clippy::too_many_arguments)]
+fn clip_wave_sides(
+    scene: &Scene,
+    camera: &Camera,
+    camera_view_from_host_hull: &Mat4,
+    screen_width: f32,
+    screen_height: f32,
+    viewport_clip_polygon: &ScreenPolygon,
+    portal_masking: PortalMasking,
+    wave: &[TraversalState],
+    side_clipper: &mut dyn SideClipper,
+) -> Option<Vec<Vec<Option<ScreenPolygon>>>> {
+    let _span = tracing::debug_span!("clip_wave_sides", states = wave.len()).entered();
+    let mut batch = SideClipBatch::default();
+    // Per state, the batch index of each side, if it was added.
+    let mut jobs_by_state: Vec<Vec<Option<usize>>> = Vec::with_capacity(wave.len());
+    if portal_masking == PortalMasking::Stencil {
+        batch.clip_polygons.push(viewport_clip_polygon.clone());
+    }
+    for state in wave {
+        let mut jobs = Vec::new();
+        let instance = scene.instances.get(&state.current_instance_id);
+        let blueprint = instance.and_then(|instance| scene.blueprints.get(&instance.blueprint_id));
+        if let (Some(instance), Some(blueprint)) = (instance, blueprint) {
+            let clip_polygon = match portal_masking {
+                PortalMasking::Clipping => {
+                    batch.clip_polygons.push(state.screen_space_clip_polygon.clone());
+                    batch.clip_polygons.len() - 1
+                }
+                PortalMasking::Stencil => 0,
+            };
+            for (side_idx, side) in blueprint.sides.iter().enumerate() {
+                let transform = *camera_view_from_host_hull * state.accumulated_transform * side_transform(instance, side_idx);
+                let vertices = side_vertices_transformed(blueprint, side, &transform).filter(|vertices| vertices.len() >= 3);
+                jobs.push(vertices.map(|vertices_cam_space| {
+                    batch.sides.push(SideClipJob { vertices_cam_space, clip_polygon });
+                    batch.sides.len() - 1
+                }));
+            }
+        }
+        jobs_by_state.push(jobs);
+    }
+    if batch.sides.len() < SIDE_CLIPPER_MIN_WAVE_SIDES {
+        return None;
+    }
+    let mut clipped = side_clipper.clip_sides(camera, screen_width, screen_height, &batch)?;
+    if clipped.len() != batch.sides.len() {
+        return None;
+    }
+    Some(jobs_by_state.into_iter().map(|jobs| {
+        jobs.into_iter().map(|job| job.and_then(|job| clipped[job].take())).collect()
+    }).collect())
+}
+
+// Bounding spheres of the blueprints a view's traversal has reached so far; see
+// `blueprint_bounding_sphere`.
+type BlueprintBounds = HashMap<BlueprintId, Option<(Vec3, f32)>>;
+
+// False if a hull with bounding sphere `bounds`, mapped into view space by `view_from_local`,
+// cannot show anywhere in `clip_polygon`. Conservative: a sphere reaching the near plane, or
+// one whose projection cannot be bounded, may show.
+fn bounds_may_overlap(
+    camera: &Camera,
+    view_from_local: &Mat4,
+    bounds: (Vec3, f32),
+    screen_width: f32,
+    screen_height: f32,
+    clip_polygon: &ScreenPolygon,
+) -> bool {
+    let center = view_from_local.transform_point3(bounds.0);
+    let scale = view_from_local.x_axis.truncate().length()
+        .max(view_from_local.y_axis.truncate().length())
+        .max(view_from_local.z_axis.truncate().length());
+    let radius = bounds.1 * scale;
+    if -center.z + radius < camera.znear {
+        return false;
+    }
+    if -center.z - radius <= camera.znear {
+        return true;
+    }
+    // The corners of the cube around the sphere project to a region holding the sphere's.
+    let mut corners: Vec<Point2<f64>> = Vec::with_capacity(8);
+    for corner in 0..8 {
+        let offset = Vec3::new(
+            if corner & 1 == 0 { -radius } else { radius },
+            if corner & 2 == 0 { -radius } else { radius },
+            if corner & 4 == 0 { -radius } else { radius },
+        );
+        match camera.project_camera_space_to_screen_direct(&(center + offset), screen_width, screen_height) {
+            Some(point) => corners.push(point.cast()),
+            None => return true,
+        }
+    }
+    ScreenPolygon::convex_hull_of(&corners).is_none_or(|region| ConvexIntersection::overlaps(&region, clip_polygon))
+}
+
+// Finds the outside sides of the free-standing instances in `contained` that face the camera
+// and overlap the state's clip region. The instance the traversal came from is skipped: the
+// view is looking out of it. Returns how many instances were skipped on their bounds alone.
+#[allow(clippy::too_many_arguments)]
+fn collect_free_standing_sides(
+    scene: &Scene,
+    camera: &Camera,
+    state: &TraversalState,
+    camera_view_from_host_hull: &Mat4,
+    screen_width: f32,
+    screen_height: f32,
+    contained: &[InstanceId],
+    bounds: &BlueprintBounds,
+    layer_filter: RenderLayerMask,
+    deferred_draws: &mut Vec<DeferredDraw>,
+) -> u32 {
+    let mut culled = 0;
+    for &inner_id in contained {
+        if state.entered_from_instance_id == Some(inner_id) {
+            continue;
+        }
+        let (instance, blueprint) = match scene.instances.get(&inner_id)
+            .and_then(|instance| scene.blueprints.get(&instance.blueprint_id).map(|bp| (instance, bp)))
+        {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let instance_layer_filter = instance.render_layers & layer_filter;
+        if instance_layer_filter == 0 || blueprint.local_vertices.is_empty() {
+            continue;
+        }
+        let inner_to_host = match relative_instance_transform(scene, state.current_instance_id, inner_id) {
+            Some(transform) => transform,
+            None => continue,
+        };
+        let transform_to_camera_host_hull = state.accumulated_transform * inner_to_host;
+        let camera_view_from_inner = *camera_view_from_host_hull * transform_to_camera_host_hull;
+        // Animated sides can move out of the blueprint's bounds.
+        let inner_bounds = bounds.get(&instance.blueprint_id).copied().flatten();
+        if let Some(inner_bounds) = inner_bounds.filter(|_| instance.side_animations.is_empty()) {
+            if !bounds_may_overlap(camera, &camera_view_from_inner, inner_bounds, screen_width, screen_height, &state.screen_space_clip_polygon) {
+                culled += 1;
+                continue;
+            }
+        }
+        let centroid = blueprint.local_vertices.iter().copied().sum::<Vec3>() / blueprint.local_vertices.len() as f32;
+        let distance = camera_view_from_inner.transform_point3(centroid).length();
+
+        for (side_idx, side) in blueprint.sides.iter().enumerate() {
+            if side.vertex_indices.len() < 3 || side.render_layers & instance_layer_filter == 0 {
+                continue;
+            }
+            let camera_view_from_side = camera_view_from_inner * side_transform(instance, side_idx);
+            let mut vertices_cam_space = match side_vertices_transformed(blueprint, side, &camera_view_from_side) {
+                Some(vertices) => vertices,
+                None => continue,
+            };
+            // Normals point into the instance; the camera (at the origin) sees the outside of
+            // the side when it is behind the plane.
+            let normal_cam_space = camera_view_from_side.transform_vector3(side.local_normal);
+            if normal_cam_space.dot(vertices_cam_space[0]) <= 1e-5 {
+                continue;
+            }
+            // Seen from outside, the side winds the other way on screen than sides seen from inside.
+            vertices_cam_space.reverse();
+            if let Some(visible) = visible_screen_polygon(
+                &vertices_cam_space, camera, screen_width, screen_height, &state.screen_space_clip_polygon,
+            ) {
+                deferred_draws.push(DeferredDraw {
+                    kind: DeferredKind::InstanceSide {
+                        host_instance_id: state.current_instance_id,
+                        instance_id: inner_id,
+                        side_index: side_idx as SideIndex,
+                    },
+                    transform_to_camera_host_hull,
+                    visible_screen_polygon: visible,
+                    recursion_depth: state.recursion_depth,
+                    distance,
+                    priority: instance.instance_side_handler_configs.get(&side_idx)
+                        .unwrap_or(&side.default_handler_config)
+                        .render_priority(),
+                    host_batch: 0,
+                });
+            }
+        }
+    }
+    culled
+}
+
+// Finds the faces of the hull's mesh entities that face the camera, and the hull's sprites,
+// where they overlap the state's clip region.
+#[allow(clippy::too_many_arguments)]
+fn collect_entity_faces(
+    camera: &Camera,
+    state: &TraversalState,
+    camera_view_from_host_hull: &Mat4,
+    screen_width: f32,
+    screen_height: f32,
+    entities: &[&Entity],
+    instance_layer_filter: RenderLayerMask,
+    deferred_draws: &mut Vec<DeferredDraw>,
+) {
+    for entity in entities {
+        if entity.render_layers & instance_layer_filter == 0 {
+            continue;
+        }
+        let transform_to_camera_host_hull = state.accumulated_transform * entity.local_transform;
+        let camera_view_from_entity = *camera_view_from_host_hull * transform_to_camera_host_hull;
+        let mesh = match &entity.shape {
+            EntityShape::Mesh(mesh) => mesh,
+            EntityShape::Sprite(sprite) => {
+                let center = camera_view_from_entity.transform_point3(Vec3::ZERO);
+                let (half_width, half_height) = (sprite.width * 0.5, sprite.height * 0.5);
+                // Camera-space corners: the quad lies in the plane z = center.z.
+                let corners = [
+                    center + Vec3::new(-half_width, -half_height, 0.0),
+                    center + Vec3::new(half_width, -half_height, 0.0),
+                    center + Vec3::new(half_width, half_height, 0.0),
+                    center + Vec3::new(-half_width, half_height, 0.0),
+                ];
+                if let Some(visible) = visible_screen_polygon(
+                    &corners, camera, screen_width, screen_height, &state.screen_space_clip_polygon,
+                ) {
+                    deferred_draws.push(DeferredDraw {
+                        kind: DeferredKind::Sprite { color: sprite.color, depth: -center.z },
+                        transform_to_camera_host_hull,
+                        visible_screen_polygon: visible,
+                        recursion_depth: state.recursion_depth,
+                        distance: center.length(),
+                        priority: 0,
+                        host_batch: 0,
+                    });
+                }
+                continue;
+            }
+        };
+        let distance = camera_view_from_entity.transform_point3(mesh.centroid()).length();
+
+        for (face_index, face) in mesh.faces.iter().enumerate() {
+            let normal = match mesh.face_normal(face) {
+                Some(normal) => normal,
+                None => continue,
+            };
+            let vertices_cam_space: Option<Vec<Vec3>> = face.vertex_indices.iter()
+                .map(|&v_idx| mesh.vertices.get(v_idx).map(|v| camera_view_from_entity.transform_point3(*v)))
+                .collect();
+            let vertices_cam_space = match vertices_cam_space {
+                Some(vertices) => vertices,
+                None => continue,
+            };
+            // Normals point out of the mesh; the camera (at the origin) must be on their side.
+            let normal_cam_space = camera_view_from_entity.transform_vector3(normal);
+            if normal_cam_space.dot(vertices_cam_space[0]) >= -1e-5 {
+                continue;
+            }
+            // See-through faces are sorted by their own depth, as what is behind them shows.
+            let distance = match Material::for_color(face.color).blend {
+                BlendMode::Alpha => (vertices_cam_space.iter().copied().sum::<Vec3>() / vertices_cam_space.len() as f32).length(),
+                BlendMode::Opaque => distance,
+            };
+            if let Some(visible) = visible_screen_polygon(
+                &vertices_cam_space, camera, screen_width, screen_height, &state.screen_space_clip_polygon,
+            ) {
+                deferred_draws.push(DeferredDraw {
+                    kind: DeferredKind::EntityFace { entity_id: entity.id, face_index },
+                    transform_to_camera_host_hull,
+                    visible_screen_polygon: visible,
+                    recursion_depth: state.recursion_depth,
+                    distance,
+                    priority: 0,
+                    host_batch: 0,
+                });
+            }
+        }
+    }
+}
+
+// Draws decals over the side they are on, each clipped to `side_visible`: the side's screen
+// polygon within the region it is seen through.
+#[allow(clippy::too_many_arguments)]
+fn emit_side_decals<'d>(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    materials: &mut MaterialRuns,
+    camera: &Camera,
+    screen_width: f32,
+    screen_height: f32,
+    camera_view_from_side: &Mat4,
+    side_normal: Vec3,
+    side_visible: &ScreenPolygon,
+    decals: impl Iterator<Item = &'d Decal>,
+) {
+    for decal in decals {
+        let corners = decal.corners(side_normal).map(|corner| camera_view_from_side.transform_point3(corner));
+        let visible = match visible_screen_polygon(&corners, camera, screen_width, screen_height, side_visible) {
+            Some(visible) => visible,
+            None => continue,
+        };
+        let plane = (camera_view_from_side.transform_vector3(side_normal).normalize_or_zero(), corners[0]);
+        let first_index = indices.len() as u32;
+        emit_polygon_on_plane(vertices, indices, camera, screen_width, screen_height, &visible, Some(&plane), decal.color);
+        materials.mark(first_index..indices.len() as u32, Material::for_color(decal.color));
+    }
+}
+
+// Defers a side's overlay until what is behind the side has been drawn. It is sorted among the
+// state's other deferred draws by its camera distance, taken at the middle of its corners.
+fn overlay_deferred_draw(
+    overlay: SideOverlay,
+    camera: &Camera,
+    screen_width: f32,
+    screen_height: f32,
+    transform_to_camera_host_hull: Mat4,
+    recursion_depth: u32,
+    priority: i32,
+) -> DeferredDraw {
+    let corners: Vec<Vec3> = overlay.polygon.vertices().iter().map(Point2::cast::<f32>).map(|point| {
+        let depth = overlay.plane.as_ref().map_or(camera.znear, |plane| depth_on_plane(camera, screen_width, screen_height, plane, &point));
+        camera.screen_to_camera_ray(&point, screen_width, screen_height) * depth
+    }).collect();
+    let distance = (corners.iter().copied().sum::<Vec3>() / corners.len().max(1) as f32).length();
+    DeferredDraw {
+        kind: DeferredKind::Overlay { plane: overlay.plane, colors: overlay.colors },
+        transform_to_camera_host_hull,
+        visible_screen_polygon: overlay.polygon,
+        recursion_depth,
+        distance,
+        priority,
+        host_batch: 0,
+    }
+}
+
+// Takes a translucent side's geometry, everything in `vertices` and `indices` from
+// `first_vertex` and `first_index` on, back out to be drawn later. It is sorted among the
+// other deferred draws by the camera distance of the middle of its vertices.
+#[allow(clippy::too_many_arguments)]
+fn translucent_deferred_draw(
+    vertices: &mut Vec<Vertex>,
+    indices: &mut Vec<u32>,
+    first_vertex: usize,
+    first_index: u32,
+    camera: &Camera,
+    screen_width: f32,
+    screen_height: f32,
+    visible_screen_polygon: ScreenPolygon,
+    transform_to_camera_host_hull: Mat4,
+    recursion_depth: u32,
+    priority: i32,
+) -> DeferredDraw {
+    let vertices = vertices.split_off(first_vertex);
+    let indices: Vec<u32> = indices.split_off(first_index as usize).into_iter().map(|index| index - first_vertex as u32).collect();
+    let points: Vec<Vec3> = vertices.iter().map(|vertex| {
+        let point = Point2::new(vertex.position[0], vertex.position[1]);
+        camera.screen_to_camera_ray(&point, screen_width, screen_height) * vertex.depth
+    }).collect();
+    let distance = (points.iter().copied().sum::<Vec3>() / points.len().max(1) as f32).length();
+    DeferredDraw {
+        kind: DeferredKind::Translucent { vertices, indices },
+        transform_to_camera_host_hull,
+        visible_screen_polygon,
+        recursion_depth,
+        distance,
+        priority,
+        host_batch: 0,
+    }
+}
+
+// Where a label's anchor sits in its instance's blueprint space.
+fn label_anchor_position(scene: &Scene, anchor: &LabelAnchor) -> Option<Vec3> {
+    let instance = scene.instances.get(&anchor.instance_id())?;
+    let blueprint = scene.blueprints.get(&instance.blueprint_id)?;
+    let centroid = |vertex_indices: &mut dyn Iterator<Item = usize>| -> Option<Vec3> {
+        let points: Vec<Vec3> = vertex_indices.filter_map(|v_idx| blueprint.local_vertices.get(v_idx).copied()).collect();
+        (!points.is_empty()).then(|| points.iter().copied().sum::<Vec3>() / points.len() as f32)
+    };
+    match anchor {
+        LabelAnchor::Instance(_) => centroid(&mut (0..blueprint.local_vertices.len())),
+        LabelAnchor::Side { side_index, .. } => {
+            let side = blueprint.sides.get(*side_index)?;
+            centroid(&mut side.vertex_indices.iter().copied())
+                .map(|center| side_transform(instance, *side_index).transform_point3(center))
+        }
+        LabelAnchor::Point { local_position, .. } => Some(*local_position),
+    }
+}
+
+// The color a hull reads as from afar: its walls' colors averaged by area, ignoring sides that
+// lead elsewhere or draw something of their own. None if it has no such sides.
+fn hull_average_color(scene: &Scene, instance_id: InstanceId) -> Option<[f32; 4]> {
+    let instance = scene.instances.get(&instance_id)?;
+    let blueprint = scene.blueprints.get(&instance.blueprint_id)?;
+    let mut sum = [0.0f32; 4];
+    let mut total_area = 0.0f32;
+    for (side_index, side) in blueprint.sides.iter().enumerate() {
+        let color = match instance.instance_side_handler_configs.get(&side_index).unwrap_or(&side.default_handler_config) {
+            HandlerConfig::StandardWall { color, .. } | HandlerConfig::Door { color, open: false, .. } => *color,
+            HandlerConfig::TransparentWall { tint, .. } => *tint,
+            HandlerConfig::Skybox { horizon_color, .. } => *horizon_color,
+            _ => continue,
+        };
+        let corners: Vec<Vec3> = side.vertex_indices.iter().filter_map(|&i| blueprint.local_vertices.get(i).copied()).collect();
+        let area = (0..corners.len())
+            .map(|i| corners[i].cross(corners[(i + 1) % corners.len()]))
+            .sum::<Vec3>()
+            .length() * 0.5;
+        for (channel, value) in sum.iter_mut().zip(color) {
+            *channel += value * area;
+        }
+        total_area += area;
+    }
+    (total_area > 0.0).then(|| sum.map(|channel| channel / total_area))
+}
+
+// Covers a state's clip region in the average color of its hull, at the depth of the hull's
+// center so it is fogged about as much as the hull would have been.
+#[allow(clippy::too_many_arguments)]
+fn fill_over_budget(
+    frame_vertices: &mut Vec<Vertex>,
+    frame_indices: &mut Vec<u32>,
+    scene: &Scene,
+    camera: &Camera,
+    camera_view_from_host_hull: &Mat4,
+    screen_width: f32,
+    screen_height: f32,
+    state: &TraversalState,
+    depth_cue: Option<&DepthCueSettings>,
+) {
+    let color = hull_average_color(scene, state.current_instance_id)
+        .or_else(|| scene.fog.as_ref().map(|fog| fog.color))
+        .unwrap_or(OVER_BUDGET_FALLBACK_COLOR);
+    let center = scene.instances.get(&state.current_instance_id)
+        .and_then(|instance| scene.blueprints.get(&instance.blueprint_id))
+        .filter(|blueprint| !blueprint.local_vertices.is_empty())
+        .map_or(Vec3::ZERO, |blueprint| blueprint.local_vertices.iter().copied().sum::<Vec3>() / blueprint.local_vertices.len() as f32);
+    let depth = -(*camera_view_from_host_hull * state.accumulated_transform).transform_point3(center).z;
+    let plane = (Vec3::Z, Vec3::new(0.0, 0.0, -depth.max(camera.znear)));
+    let first_new_vertex = frame_vertices.len();
+    emit_polygon_on_plane(
+        frame_vertices, frame_indices, camera, screen_width, screen_height,
+        &state.screen_space_clip_polygon, Some(&plane), color,
+    );
+    apply_depth_cue(depth_cue, &mut frame_vertices[first_new_vertex..], &mut [], state.recursion_depth);
+}
+
+// Identifies what a traversal state would draw: its hull and its clip region, snapped to a grid
+// of `CLIP_REGION_QUANTUM` pixels and started from its lowest corner so that the same region
+// reached another way gives the same key.
+fn visited_region_key(state: &TraversalState) -> (InstanceId, u64) {
+    let corners: Vec<(i64, i64)> = state.screen_space_clip_polygon.vertices().iter()
+        .map(|point| ((point.x / CLIP_REGION_QUANTUM).round() as i64, (point.y / CLIP_REGION_QUANTUM).round() as i64))
+        .collect();
+    let first = (0..corners.len()).min_by_key(|&i| corners[i]).unwrap_or(0);
+    let mut hasher = DefaultHasher::new();
+    for i in 0..corners.len() {
+        corners[(first + i) % corners.len()].hash(&mut hasher);
+    }
+    (state.current_instance_id, hasher.finish())
+}
+
+// What traversing one state produced. Indices are relative to the branch's own vertex lists, so
+// branches can be built on separate threads and appended to the frame in queue order.
+#[derive(Default)]
+struct BranchOutput {
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+    sky_vertices: Vec<SkyVertex>,
+    sky_indices: Vec<u32>,
+    overlay_vertices: Vec<Vertex>,
+    overlay_indices: Vec<u32>,
+    materials: MaterialRuns,
+    labels: Vec<ScreenLabel>,
+    // States queued through this state's portals, in side order.
+    children: VecDeque<TraversalState>,
+    deferred_draws: Vec<DeferredDraw>,
+    stats: RenderStats,
+}
+
+// Read-only inputs shared by every branch of a view's traversal.
+struct WaveContext<'a> {
+    scene: &'a Scene,
+    camera: &'a Camera,
+    camera_view_from_host_hull: Mat4,
+    screen_width: f32,
+    screen_height: f32,
+    layer_filter: RenderLayerMask,
+    settings: &'a RenderSettings,
+    // What sides are clipped against with `PortalMasking::Stencil`.
+    viewport_clip_polygon: &'a ScreenPolygon,
+    wall_handler: &'a StandardWallHandler,
+    portal_handler: &'a StandardPortalHandler,
+    skybox_handler: &'a SkyboxHandler,
+    door_handler: &'a DoorHandler,
+    liquid_surface_handler: &'a LiquidSurfaceHandler,
+    custom_handlers: &'a SideHandlerRegistry,
+    traversal_start: Instant,
+}
+
+impl WaveContext<'_> {
+    // Clips each side of the state's hull against its portal region and lets the side's handler
+    // emit geometry, and collects what stands inside the hull for later. Stops early, with
+    // `stats.aborted` set, after `sides_budget` sides or when the frame runs out of CPU time.
+    fn traverse_state(
+        &self,
+        current_traversal_state: &TraversalState,
+        sides_budget: u32,
+        contained: &[InstanceId],
+        entities: &[&Entity],
+        bounds: &BlueprintBounds,
+        clipped_sides: Option<&[Option<ScreenPolygon>]>,
+    ) -> BranchOutput {
+        let (scene, camera) = (self.scene, self.camera);
+        let (screen_width, screen_height) = (self.screen_width, self.screen_height);
+        let camera_view_from_host_hull = self.camera_view_from_host_hull;
+        let mut branch = BranchOutput::default();
+
+        let current_instance = match scene.instances.get(&current_traversal_state.current_instance_id) {
+            Some(inst) => inst,
+            None => return branch,
+        };
+        let blueprint = match scene.blueprints.get(&current_instance.blueprint_id) {
+            Some(bp) => bp,
+            None => return branch,
+        };
+        // A hull entered through a portal normally surrounds it, but one whose portal is not
+        // where its placement puts it can be entirely out of view.
+        let hull_bounds = bounds.get(&current_instance.blueprint_id).copied().flatten();
+        if let Some(hull_bounds) = hull_bounds.filter(|_| current_instance.side_animations.is_empty()) {
+            let camera_view_from_hull = camera_view_from_host_hull * current_traversal_state.accumulated_transform;
+            if !bounds_may_overlap(camera, &camera_view_from_hull, hull_bounds, screen_width, screen_height, &current_traversal_state.screen_space_clip_polygon) {
+                branch.stats.hulls_culled_by_bounds += 1;
+                return branch;
+            }
+        }
+        branch.stats.hulls_visited += 1;
+        branch.stats.max_recursion_depth = current_traversal_state.recursion_depth;
+
+        // The root state's region is the whole screen, so only portal regions are outlined.
+        if self.settings.show_clip_regions && current_traversal_state.recursion_depth > 0 {
+            let color = rotate_hue(
+                CLIP_REGION_BASE_COLOR,
+                CLIP_REGION_HUE_STEP_DEGREES * current_traversal_state.recursion_depth as f32,
+            );
+            push_outline(
+                &mut branch.overlay_vertices,
+                &mut branch.overlay_indices,
+                current_traversal_state.screen_space_clip_polygon.vertices(),
+                color,
+            );
+        }
+
+        let instance_layer_filter = current_instance.render_layers & self.layer_filter;
+        if instance_layer_filter == 0 {
+            return branch;
+        }
+
+        let camera_view_from_current_bp = camera_view_from_host_hull * current_traversal_state.accumulated_transform;
+        let side_clip_polygon = match self.settings.portal_masking {
+            PortalMasking::Clipping => &current_traversal_state.screen_space_clip_polygon,
+            PortalMasking::Stencil => self.viewport_clip_polygon,
+        };
+        if self.settings.show_debug_labels {
+            for label in scene.debug_labels.iter().filter(|label| label.anchor.instance_id() == current_instance.id) {
+                let anchor_cam_space = match label_anchor_position(scene, &label.anchor) {
+                    Some(local_position) => camera_view_from_current_bp.transform_point3(local_position),
+                    None => continue,
+                };
+                if -anchor_cam_space.z < camera.znear {
+                    continue;
+                }
+                let on_screen = camera.project_camera_space_to_screen_direct(&anchor_cam_space, screen_width, screen_height)
+                    .filter(|point| current_traversal_state.screen_space_clip_polygon.contains_point(&point.cast()));
+                if let Some(point) = on_screen {
+                    branch.labels.push(ScreenLabel {
+                        text: label.text.clone(),
+                        position: [point.x, point.y],
+                        color: label.color,
+                        depth: -anchor_cam_space.z,
+                        recursion_depth: current_traversal_state.recursion_depth,
+                    });
+                }
+            }
+        }
+        let mut overlays: Vec<SideOverlay> = Vec::new();
+        let decals = decals_in_instance(scene, current_instance.id);
+        for (side_idx, blueprint_side) in blueprint.sides.iter().enumerate() {
+            if branch.stats.sides_processed >= sides_budget {
+                branch.stats.aborted = Some(TraversalAbort::ProcessedSides);
+                return branch;
+            }
+            if self.traversal_start.elapsed() >= self.settings.limits.max_cpu_time {
+                branch.stats.aborted = Some(TraversalAbort::CpuTime);
+                return branch;
+            }
+            branch.stats.sides_processed += 1;
+
+            if blueprint_side.vertex_indices.len() < 3 {
+                continue;
+            }
+            // Filtered sides are skipped entirely, including portal traversal.
+            if blueprint_side.render_layers & instance_layer_filter == 0 {
+                continue;
+            }
+
+            // Animated sides are drawn, and their handlers run, where they currently are.
+            let side_to_camera_host_hull = current_traversal_state.accumulated_transform * side_transform(current_instance, side_idx);
+            let visible = match clipped_sides {
+                Some(clipped_sides) => clipped_sides.get(side_idx).cloned().flatten(),
+                None => side_vertices_transformed(blueprint, blueprint_side, &(camera_view_from_host_hull * side_to_camera_host_hull))
+                    .and_then(|side_vertices_cam_space| visible_screen_polygon(
+                        &side_vertices_cam_space, camera, screen_width, screen_height, side_clip_polygon,
+                    )),
+            };
+            let final_visible_screen_polygon = match visible {
+                Some(polygon) => polygon,
+                None => continue,
+            };
+
+            let side_config_override = current_instance.instance_side_handler_configs.get(&(side_idx as SideIndex));
+            let effective_config = side_config_override.unwrap_or(&blueprint_side.default_handler_config);
+
+            let first_new_vertex = branch.vertices.len();
+            let first_new_sky_vertex = branch.sky_vertices.len();
+            let first_new_child = branch.children.len();
+            let first_new_index = branch.indices.len() as u32;
+            let visible_area = final_visible_screen_polygon.area() as f32;
+
+            let mut handler_ctx = HandlerContext {
+                frame_vertices: &mut branch.vertices,
+                frame_indices: &mut branch.indices,
+                frame_sky_vertices: &mut branch.sky_vertices,
+                frame_sky_indices: &mut branch.sky_indices,
+                scene,
+                camera,
+                current_instance,
+                blueprint_side,
+                side_config: effective_config,
+                transform_to_camera_host_hull: &side_to_camera_host_hull,
+                camera_view_from_host_hull: &camera_view_from_host_hull,
+                screen_width,
+                screen_height,
+                visible_screen_polygon: final_visible_screen_polygon,
+                traversal_queue: &mut branch.children,
+                current_recursion_depth: current_traversal_state.recursion_depth,
+                material: Material::OPAQUE,
+                overlays: &mut overlays,
+            };
+            dispatch_side_handler(
+                self.wall_handler, self.portal_handler, self.skybox_handler, self.door_handler, self.liquid_surface_handler,
+                self.custom_handlers, &mut handler_ctx,
+            );
+            let material = handler_ctx.material;
+            let side_visible = handler_ctx.visible_screen_polygon;
+            let translucent = material.blend == BlendMode::Alpha;
+            // A translucent side's geometry is all drawn blended once deferred, so its runs are not kept.
+            let mut translucent_materials = MaterialRuns::default();
+            let side_materials = if translucent { &mut translucent_materials } else { &mut branch.materials };
+            side_materials.mark(first_new_index..branch.indices.len() as u32, material);
+            branch.deferred_draws.extend(overlays.drain(..).map(|overlay| overlay_deferred_draw(
+                overlay, camera, screen_width, screen_height, side_to_camera_host_hull, current_traversal_state.recursion_depth,
+                effective_config.render_priority(),
+            )));
+            if effective_config.visible_portal_target().is_none() {
+                emit_side_decals(
+                    &mut branch.vertices, &mut branch.indices, side_materials, camera, screen_width, screen_height,
+                    &(camera_view_from_host_hull * side_to_camera_host_hull), blueprint_side.local_normal, &side_visible,
+                    decals.iter().filter(|decal| decal.side_index == side_idx).copied(),
+                );
+            }
+
+            // Whatever a portal this small would show covers less than a pixel.
+            if visible_area < self.settings.min_portal_area && branch.children.len() > first_new_child {
+                branch.stats.portals_culled_small += (branch.children.len() - first_new_child) as u32;
+                branch.children.truncate(first_new_child);
+            }
+            if branch.vertices.len() > first_new_vertex || branch.sky_vertices.len() > first_new_sky_vertex {
+                branch.stats.sides_drawn += 1;
+            }
+            apply_depth_cue(
+                self.settings.depth_cue.as_ref(),
+                &mut branch.vertices[first_new_vertex..], &mut branch.sky_vertices[first_new_sky_vertex..],
+                current_traversal_state.recursion_depth,
+            );
+            if translucent {
+                let deferred = translucent_deferred_draw(
+                    &mut branch.vertices, &mut branch.indices, first_new_vertex, first_new_index, camera, screen_width, screen_height,
+                    side_visible, side_to_camera_host_hull, current_traversal_state.recursion_depth,
+                    effective_config.render_priority(),
+                );
+                branch.deferred_draws.push(deferred);
+            }
+        }
+
+        if !contained.is_empty() {
+            branch.stats.hulls_culled_by_bounds += collect_free_standing_sides(
+                scene, camera, current_traversal_state, &camera_view_from_host_hull,
+                screen_width, screen_height, contained, bounds, self.layer_filter, &mut branch.deferred_draws,
+            );
+        }
+        if !entities.is_empty() {
+            collect_entity_faces(
+                camera, current_traversal_state, &camera_view_from_host_hull,
+                screen_width, screen_height, entities, instance_layer_filter, &mut branch.deferred_draws,
+            );
+        }
+        branch
+    }
+}
+
+// Hue-shifts freshly emitted geometry by the recursion depth it was seen at.
+fn apply_depth_cue(depth_cue: Option<&DepthCueSettings>, vertices: &mut [Vertex], sky_vertices: &mut [SkyVertex], recursion_depth: u32) {
+    let depth_cue = match depth_cue {
+        Some(depth_cue) => depth_cue,
+        None => return,
+    };
+    let hue_shift = depth_cue.hue_shift_degrees_per_level * recursion_depth as f32;
+    if hue_shift == 0.0 {
+        return;
+    }
+    for vertex in vertices {
+        vertex.color = rotate_hue(vertex.color, hue_shift);
+    }
+    for vertex in sky_vertices {
+        vertex.zenith_color = rotate_hue(vertex.zenith_color, hue_shift);
+        vertex.horizon_color = rotate_hue(vertex.horizon_color, hue_shift);
+        vertex.nadir_color = rotate_hue(vertex.nadir_color, hue_shift);
+    }
+}
+
+fn dispatch_side_handler(
+    wall_handler: &StandardWallHandler,
+    portal_handler: &StandardPortalHandler,
+    skybox_handler: &SkyboxHandler,
+    door_handler: &DoorHandler,
+    liquid_surface_handler: &LiquidSurfaceHandler,
+    custom_handlers: &SideHandlerRegistry,
+    handler_ctx: &mut HandlerContext,
+) {
+    match handler_ctx.side_config.get_intended_handler_type() {
+        SideHandlerTypeId::StandardWall => wall_handler.process_render(handler_ctx),
+        SideHandlerTypeId::StandardPortal => portal_handler.process_render(handler_ctx),
+        SideHandlerTypeId::Skybox => skybox_handler.process_render(handler_ctx),
+        SideHandlerTypeId::Door => door_handler.process_render(handler_ctx),
+        SideHandlerTypeId::LiquidSurface => liquid_surface_handler.process_render(handler_ctx),
+        SideHandlerTypeId::Custom(type_id) => {
+            if let Some(handler) = custom_handlers.get(&type_id) {
+                handler.process_render(handler_ctx);
+            }
+        }
+        _ => { /* No-op for unhandled types */ }
+    }
+}
+
+// Appends a closed line-list outline of `points` in a single color.
+fn push_outline<T: Scalar>(vertices: &mut Vec<Vertex>, indices: &mut Vec<u32>, points: &[Point2<T>], color: [f32; 4]) {
+    if points.len() < 2 {
+        return;
+    }
+    let start = vertices.len() as u32;
+    let count = points.len() as u32;
+    vertices.extend(points.iter().map(Point2::cast::<f32>).map(|p| Vertex::new([p.x, p.y], color)));
+    for i in 0..count {
+        indices.push(start + i);
+        indices.push(start + (i + 1) % count);
+    }
+}
+
+// Expands a triangle list into a line list of each triangle's edges.
+pub fn triangle_edges_into(triangle_indices: &[u32], line_indices: &mut Vec<u32>) {
+    line_indices.clear();
+    for tri in triangle_indices.chunks_exact(3) {
+        line_indices.extend_from_slice(&[tri[0], tri[1], tri[1], tri[2], tri[2], tri[0]]);
+    }
+}
+
+// How a scene pass pipeline uses the stencil attachment of `PortalMasking::Stencil`. Geometry
+// is drawn where the stencil equals the reference; portal masks also step the value and leave
+// the color alone.
+#[derive(Clone, Copy)]
+struct PipelineStencil {
+    pass_op: wgpu::StencilOperation,
+    writes_color: bool,
+}
+
+impl PipelineStencil {
+    const GEOMETRY: Self = Self { pass_op: wgpu::StencilOperation::Keep, writes_color: true };
+
+    fn depth_stencil_state(self) -> wgpu::DepthStencilState {
+        let face = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Equal,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: self.pass_op,
+        };
+        wgpu::DepthStencilState {
+            format: STENCIL_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState { front: face, back: face, read_mask: 0xff, write_mask: 0xff },
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn create_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: &wgpu::PipelineLayout,
+    module: &wgpu::ShaderModule,
+    vs_entry_point: &str,
+    fs_entry_point: &str,
+    vertex_layout: wgpu::VertexBufferLayout<'static>,
+    target_format: wgpu::TextureFormat,
+    topology: wgpu::PrimitiveTopology,
+    blend: wgpu::BlendState,
+    sample_count: u32,
+    stencil: Option<PipelineStencil>,
+) -> wgpu::RenderPipeline {
+    let write_mask = if stencil.is_some_and(|stencil| !stencil.writes_color) { wgpu::ColorWrites::empty() } else { wgpu::ColorWrites::ALL };
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module,
+            entry_point: vs_entry_point,
+            buffers: &[vertex_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module,
+            entry_point: fs_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: target_format,
+                blend: Some(blend),
+                write_mask,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: stencil.map(PipelineStencil::depth_stencil_state),
+        multisample: wgpu::MultisampleState { count: sample_count, ..Default::default() },
+        multiview: None,
+    })
+}
+
+// Every pipeline the scene pass can use, all built for the same sample count and, with
+// `PortalMasking::Stencil`, the stencil attachment.
+struct ScenePipelines {
+    // Scene geometry by `BlendMode`; the wireframe pipeline blends, whatever the material.
+    scene: wgpu::RenderPipeline,
+    scene_alpha: wgpu::RenderPipeline,
+    scene_wireframe: wgpu::RenderPipeline,
+    sky: wgpu::RenderPipeline,
+    sky_wireframe: wgpu::RenderPipeline,
+    impostor: wgpu::RenderPipeline,
+    // Increment and decrement of a portal's stencil mask; only built for the stencil attachment.
+    portal_masks: Option<[wgpu::RenderPipeline; 2]>,
+    sample_count: u32,
+}
+
+fn create_impostor_target(device: &wgpu::Device, layout: &wgpu::BindGroupLayout, width: u32, height: u32) -> ImpostorTarget {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Impostor Capture"),
+        size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: HDR_FORMAT,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("impostor_bind_group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) }],
+    });
+    ImpostorTarget { texture, bind_group, width, height }
+}
+
+impl ScenePipelines {
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        device: &wgpu::Device,
+        layout: &wgpu::PipelineLayout,
+        shader_module: &wgpu::ShaderModule,
+        sky_shader_module: &wgpu::ShaderModule,
+        impostor_layout: &wgpu::PipelineLayout,
+        impostor_shader_module: &wgpu::ShaderModule,
+        target_format: wgpu::TextureFormat,
+        sample_count: u32,
+        stencil: bool,
+    ) -> Self {
+        let geometry_stencil = stencil.then_some(PipelineStencil::GEOMETRY);
+        let portal_mask = |label, pass_op| create_pipeline(
+            device, label, layout, shader_module, "vs_main", "fs_main",
+            Vertex::desc(), target_format, wgpu::PrimitiveTopology::TriangleList, wgpu::BlendState::ALPHA_BLENDING, sample_count,
+            Some(PipelineStencil { pass_op, writes_color: false }),
+        );
+        Self {
+            scene: create_pipeline(
+                device, "Renderer Pipeline", layout, shader_module, "vs_main", "fs_main",
+                Vertex::desc(), target_format, wgpu::PrimitiveTopology::TriangleList, wgpu::BlendState::REPLACE, sample_count, geometry_stencil,
+            ),
+            scene_alpha: create_pipeline(
+                device, "Renderer Alpha Pipeline", layout, shader_module, "vs_main", "fs_main",
+                Vertex::desc(), target_format, wgpu::PrimitiveTopology::TriangleList, wgpu::BlendState::ALPHA_BLENDING, sample_count, geometry_stencil,
+            ),
+            scene_wireframe: create_pipeline(
+                device, "Renderer Wireframe Pipeline", layout, shader_module, "vs_main", "fs_main",
+                Vertex::desc(), target_format, wgpu::PrimitiveTopology::LineList, wgpu::BlendState::ALPHA_BLENDING, sample_count, geometry_stencil,
+            ),
+            sky: create_pipeline(
+                device, "Sky Pipeline", layout, sky_shader_module, "vs_sky", "fs_sky",
+                SkyVertex::desc(), target_format, wgpu::PrimitiveTopology::TriangleList, wgpu::BlendState::ALPHA_BLENDING, sample_count, geometry_stencil,
+            ),
+            sky_wireframe: create_pipeline(
+                device, "Sky Wireframe Pipeline", layout, sky_shader_module, "vs_sky", "fs_sky",
+                SkyVertex::desc(), target_format, wgpu::PrimitiveTopology::LineList, wgpu::BlendState::ALPHA_BLENDING, sample_count, geometry_stencil,
+            ),
+            impostor: create_pipeline(
+                device, "Impostor Pipeline", impostor_layout, impostor_shader_module, "vs_impostor", "fs_impostor",
+                Vertex::desc(), target_format, wgpu::PrimitiveTopology::TriangleList, wgpu::BlendState::ALPHA_BLENDING, sample_count, geometry_stencil,
+            ),
+            portal_masks: stencil.then(|| [
+                portal_mask("Portal Mask Increment Pipeline", wgpu::StencilOperation::IncrementClamp),
+                portal_mask("Portal Mask Decrement Pipeline", wgpu::StencilOperation::DecrementClamp),
+            ]),
+            sample_count,
+        }
+    }
+
+    fn stencil(&self) -> bool {
+        self.portal_masks.is_some()
+    }
+}
+
+// Native backends validate synchronously, so the error scope has its answer on the first poll.
+// On the web the answer may come later and is treated as no error.
+fn pop_error_scope_now(device: &wgpu::Device) -> Option<wgpu::Error> {
+    let mut future = std::pin::pin!(device.pop_error_scope());
+    match std::future::Future::poll(future.as_mut(), &mut std::task::Context::from_waker(std::task::Waker::noop())) {
+        std::task::Poll::Ready(error) => error,
+        std::task::Poll::Pending => None,
+    }
+}
+
+// Multisampled color texture the scene pass renders into before resolving to the output.
+struct MsaaTarget {
+    view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+}
+
+impl MsaaTarget {
+    fn new(device: &wgpu::Device, format: wgpu::TextureFormat, width: u32, height: u32, sample_count: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("MSAA Color Target"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { view, width, height, sample_count }
+    }
+}
+
+// Stencil attachment of the scene pass for `PortalMasking::Stencil`, matching the color target.
+struct StencilTarget {
+    view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+}
+
+impl StencilTarget {
+    fn new(device: &wgpu::Device, width: u32, height: u32, sample_count: u32) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Portal Stencil Target"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: STENCIL_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Self { view, width, height, sample_count }
+    }
+}
+
+// Sample counts every wgpu backend supports for color targets.
+fn supported_sample_count(requested: u32) -> u32 {
+    if requested >= 4 { 4 } else { 1 }
+}
+
+// A GPU buffer that is reallocated (at least doubling) whenever a write exceeds its capacity.
+pub struct GrowableBuffer {
+    buffer: wgpu::Buffer,
+    label: &'static str,
+    usage: wgpu::BufferUsages,
+}
+
+impl GrowableBuffer {
+    pub fn new(device: &wgpu::Device, label: &'static str, usage: wgpu::BufferUsages, capacity_bytes: u64) -> Self {
+        let usage = usage | wgpu::BufferUsages::COPY_DST;
+        let buffer = Self::create(device, label, usage, capacity_bytes);
+        Self { buffer, label, usage }
+    }
+
+    fn create(device: &wgpu::Device, label: &'static str, usage: wgpu::BufferUsages, size: u64) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: wgpu::util::align_to(size.max(wgpu::COPY_BUFFER_ALIGNMENT), wgpu::COPY_BUFFER_ALIGNMENT),
+            usage,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.buffer.size()
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    // Grows the buffer to hold `required` bytes if it cannot already. Returns true if it was
+    // replaced, so bind groups using it have to be recreated.
+    pub fn reserve(&mut self, device: &wgpu::Device, required: u64) -> bool {
+        if required <= self.capacity() {
+            return false;
+        }
+        let new_capacity = required.max(self.capacity() * 2);
+        log::debug!("Growing {} from {} to {} bytes", self.label, self.capacity(), new_capacity);
+        self.buffer = Self::create(device, self.label, self.usage, new_capacity);
+        true
+    }
+
+    // Uploads `data` at offset 0, growing the buffer first if needed. `data.len()` must be a
+    // multiple of 4 (true for the u32 indices and f32 vertex types uploaded here).
+    pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[u8]) -> bool {
+        let replaced = self.reserve(device, data.len() as u64);
+        queue.write_buffer(&self.buffer, 0, data);
+        replaced
+    }
+
+    // Same as `write`, but the upload is a copy recorded into `encoder` from memory sub-allocated
+    // from `belt`, which is reused from frame to frame instead of being allocated per write.
+    // The belt must be finished before `encoder` is submitted.
+    pub fn write_staged(&mut self, device: &wgpu::Device, encoder: &mut wgpu::CommandEncoder, belt: &mut StagingBelt, data: &[u8]) -> bool {
+        let replaced = self.reserve(device, data.len() as u64);
+        if let Some(size) = NonZeroU64::new(data.len() as u64) {
+            belt.write_buffer(encoder, &self.buffer, 0, size, device).copy_from_slice(data);
+        }
+        replaced
+    }
+
+    pub fn slice(&self, len_bytes: u64) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(..len_bytes)
+    }
+}
+
+// Tints geometry by how many portals it is seen through, rotating its hue by a fixed
+// amount per recursion level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthCueSettings {
+    pub hue_shift_degrees_per_level: f32,
+}
+
+impl Default for DepthCueSettings {
+    fn default() -> Self {
+        Self { hue_shift_degrees_per_level: 25.0 }
+    }
+}
+
+// Hard per-frame caps on the portal traversal. When one is hit the traversal stops and the
+// frame is drawn with whatever geometry was emitted so far, so malformed scenes or
+// self-referential portals can't stall the render thread.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TraversalLimits {
+    pub max_traversal_states: u32,
+    pub max_processed_sides: u32,
+    pub max_cpu_time: Duration,
+}
+
+impl Default for TraversalLimits {
+    fn default() -> Self {
+        Self {
+            max_traversal_states: 4096,
+            max_processed_sides: 65536,
+            max_cpu_time: Duration::from_millis(50),
+        }
+    }
+}
+
+// Soft per-frame limits on the portal traversal, off by default. Unlike `TraversalLimits`,
+// running out does not cut the frame short: hulls still waiting to be traversed are filled in
+// their average wall color over the region their portal covers, so frame times stay bounded
+// in portal mazes without leaving holes on screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TraversalBudget {
+    pub max_hulls: Option<u32>,
+    pub max_time: Option<Duration>,
+}
+
+// Draws deep portals from a capture of an earlier frame instead of traversing them, off by
+// default. A capture frame renders everything and keeps a copy of the result; the frames after
+// it stop at portals `min_recursion_depth` deep and fill their regions from the copy, until the
+// camera has moved or turned too far or the copy is `max_age_frames` old. Changes to the scene
+// behind those portals show up only when the copy is refreshed. Single-view frames only.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ImpostorSettings {
+    pub min_recursion_depth: u32,
+    pub max_age_frames: u32,
+    // Largest camera movement, in its host hull's units, and turn that reuse the copy.
+    pub max_camera_move: f32,
+    pub max_camera_turn_degrees: f32,
+}
+
+impl Default for ImpostorSettings {
+    fn default() -> Self {
+        Self { min_recursion_depth: 3, max_age_frames: 8, max_camera_move: 0.05, max_camera_turn_degrees: 1.0 }
+    }
+}
+
+// The smallest `RenderSettings::render_scale`.
+pub const MIN_RENDER_SCALE: f32 = 0.25;
+
+// Steers `RenderSettings::render_scale` towards the scale that renders a frame in
+// `target_frame_time`; see `Renderer::adjust_render_scale`. The scale stays put while frame
+// times are within `tolerance` (a fraction of the target) of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DynamicResolution {
+    pub target_frame_time: Duration,
+    pub min_scale: f32,
+    pub max_scale: f32,
+    pub tolerance: f32,
+}
+
+impl Default for DynamicResolution {
+    fn default() -> Self {
+        Self { target_frame_time: Duration::from_secs_f64(1.0 / 60.0), min_scale: 0.5, max_scale: 1.0, tolerance: 0.1 }
+    }
+}
+
+impl DynamicResolution {
+    // The scale to use after a frame at `scale` took `frame_time`. Fill cost goes with the pixel
+    // count, the square of the scale, and each step is small so one slow frame does not show.
+    pub fn next_scale(&self, scale: f32, frame_time: Duration) -> f32 {
+        let target = self.target_frame_time.as_secs_f32();
+        let frame_time = frame_time.as_secs_f32();
+        if target <= 0.0 || frame_time <= 0.0 || (frame_time - target).abs() <= target * self.tolerance {
+            return scale;
+        }
+        let step = (target / frame_time).sqrt().clamp(0.95, 1.02);
+        let min_scale = self.min_scale.max(MIN_RENDER_SCALE);
+        (scale * step).clamp(min_scale, self.max_scale.max(min_scale))
+    }
+}
+
+// The camera pose a frame was captured from for `ImpostorSettings`, and the hulls seen at the
+// impostor depth in it.
+struct ImpostorCapture {
+    settings: ImpostorSettings,
+    camera_instance_id: InstanceId,
+    camera_local_transform: Mat4,
+    viewport: Viewport,
+    age_frames: u32,
+    // Set on the frames that draw from the capture rather than refresh it.
+    reusing: bool,
+    hulls: HashSet<(InstanceId, Option<InstanceId>)>,
+}
+
+impl ImpostorCapture {
+    fn new(settings: ImpostorSettings, view: &SceneView) -> Self {
+        Self {
+            settings,
+            camera_instance_id: view.camera_instance_id,
+            camera_local_transform: view.camera_local_transform,
+            viewport: view.viewport,
+            age_frames: 0,
+            reusing: false,
+            hulls: HashSet::new(),
+        }
+    }
+
+    fn still_valid(&self, settings: &ImpostorSettings, view: &SceneView) -> bool {
+        if self.settings != *settings || self.camera_instance_id != view.camera_instance_id
+            || self.viewport != view.viewport || self.age_frames + 1 >= settings.max_age_frames
+        {
+            return false;
+        }
+        let (_, old_rotation, old_position) = self.camera_local_transform.to_scale_rotation_translation();
+        let (_, rotation, position) = view.camera_local_transform.to_scale_rotation_translation();
+        old_position.distance(position) <= settings.max_camera_move
+            && old_rotation.angle_between(rotation).to_degrees() <= settings.max_camera_turn_degrees
+    }
+
+    // Whether the capture stands in for traversing `state`. On capture frames it never does, but
+    // records the state's hull as captured.
+    fn stands_in_for(&mut self, state: &TraversalState) -> bool {
+        if state.recursion_depth < self.settings.min_recursion_depth {
+            return false;
+        }
+        let key = (state.current_instance_id, state.entered_from_instance_id);
+        if self.reusing {
+            self.hulls.contains(&key)
+        } else {
+            self.hulls.insert(key);
+            false
+        }
+    }
+}
+
+// Which `TraversalLimits` cap cut a frame's traversal short.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraversalAbort {
+    TraversalStates,
+    ProcessedSides,
+    CpuTime,
+}
+
+// Per-frame counters returned by `Renderer::render_scene`, for diagnosing portal-culling cost.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    pub hulls_visited: u32,
+    pub sides_processed: u32,
+    pub sides_drawn: u32,
+    pub entity_faces_drawn: u32,
+    pub portals_traversed: u32,
+    // Portals not looked through because they covered less than
+    // `RenderSettings::min_portal_area`.
+    pub portals_culled_small: u32,
+    // Portals not looked through because the same hull had already been queued with the same
+    // clip region; see `RenderSettings::skip_repeated_regions`.
+    pub repeated_regions_skipped: u32,
+    // Hulls and free-standing instances skipped because their bounding sphere falls outside
+    // the clip region they would be drawn in.
+    pub hulls_culled_by_bounds: u32,
+    // Hulls filled with their average color because the `TraversalBudget` ran out.
+    pub hulls_over_budget: u32,
+    // Portals drawn from the impostor capture instead of traversed; see `ImpostorSettings`.
+    pub impostors_drawn: u32,
+    pub max_recursion_depth: u32,
+    pub vertices_emitted: u32,
+    pub indices_emitted: u32,
+    pub traversal_time: Duration,
+    // GPU time of the render passes, from the latest frame whose timestamps have been read back
+    // (a couple of frames old), the same for every view. None unless `RenderSettings::gpu_timing`
+    // is on and the device supports timestamp queries.
+    pub gpu_pass_times: Option<GpuPassTimes>,
+    // Set when the traversal watchdog aborted the frame.
+    pub aborted: Option<TraversalAbort>,
+}
+
+// A rectangle of the output target, in pixels from its top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Viewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Viewport {
+    pub fn full(width: f32, height: f32) -> Self {
+        Self { x: 0.0, y: 0.0, width, height }
+    }
+}
+
+// One camera's view of the scene, drawn into `viewport` of a multi-view frame.
+#[derive(Clone, Copy, Debug)]
+pub struct SceneView<'a> {
+    pub camera: &'a Camera,
+    pub camera_instance_id: InstanceId,
+    pub camera_local_transform: Mat4,
+    pub viewport: Viewport,
+}
+
+impl<'a> SceneView<'a> {
+    // A view from one of the scene's named cameras, or None if there is no such camera.
+    pub fn from_scene_camera(scene: &Scene, camera_name: &str, camera: &'a Camera, viewport: Viewport) -> Option<Self> {
+        scene.camera(camera_name).map(|scene_camera| Self {
+            camera,
+            camera_instance_id: scene_camera.host_instance_id,
+            camera_local_transform: scene_camera.local_transform,
+            viewport,
+        })
+    }
+}
+
+// How the region seen through a portal is cut out of the screen.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortalMasking {
+    // Every side is clipped on the CPU against the polygon of the portals it is seen through.
+    Clipping,
+    // Sides are only clipped to the viewport, and the GPU keeps them inside their portals: each
+    // portal steps up the stencil value where it is visible, and a hull is drawn where the value
+    // equals its recursion depth. Without the accumulated clip polygon the traversal cannot tell
+    // a portal hidden behind nearer walls from a visible one, so it visits more hulls. Portal
+    // impostors are not drawn in this mode.
+    Stencil,
+}
+
+// How HDR scene colors are mapped to the displayable range in the final pass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TonemapCurve {
+    // Clip to [0, 1]; identical to rendering straight to an LDR target.
+    Clamp,
+    Reinhard,
+    Aces,
+}
+
+// Glow around HDR colors brighter than `threshold`, such as emissive walls, added in the
+// tonemap pass before exposure. What each pixel is over the threshold is spread with a 5x5
+// Gaussian of taps `radius / 2` pixels of the scene target apart.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BloomSettings {
+    pub threshold: f32,
+    pub intensity: f32,
+    pub radius: f32,
+}
+
+impl Default for BloomSettings {
+    fn default() -> Self {
+        Self { threshold: 1.0, intensity: 0.8, radius: 8.0 }
+    }
+}
+
+// Taps each side of the center along each axis of the bloom kernel.
+pub const BLOOM_TAP_REACH: i32 = 2;
+
+impl BloomSettings {
+    // Pixels between neighbouring taps.
+    pub fn tap_step(&self) -> f32 {
+        self.radius.max(0.0) / BLOOM_TAP_REACH as f32
+    }
+
+    // Weight of the tap `(i, j)` taps from the center: a Gaussian one tap wide.
+    pub fn tap_weight(i: i32, j: i32) -> f32 {
+        (-((i * i + j * j) as f32) / 2.0).exp()
+    }
+}
+
+// A debug label as seen in the last rendered frame, once per place it is visible.
+#[derive(Clone, Debug)]
+pub struct ScreenLabel {
+    pub text: String,
+    // Pixels from the output target's top-left corner.
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+    // Camera-space depth of the anchor, accumulated through portals.
+    pub depth: f32,
+    pub recursion_depth: u32,
+}
+
+// Runtime-adjustable rendering options.
+#[derive(Clone, Debug)]
+pub struct RenderSettings {
+    pub depth_cue: Option<DepthCueSettings>,
+    // Draw triangle edges instead of filled polygons, for inspecting portal clipping.
+    pub wireframe: bool,
+    // Outline the screen-space clip polygon of every portal traversal state.
+    pub show_clip_regions: bool,
+    // Project the scene's debug labels into `Renderer::frame_labels`.
+    pub show_debug_labels: bool,
+    pub limits: TraversalLimits,
+    pub budget: TraversalBudget,
+    // Look through a portal only if its hull has not already been queued with the same clip
+    // region this frame, as happens between portals and mirrors facing each other. The first
+    // state to reach a region, the one seen through the fewest portals, draws it.
+    pub skip_repeated_regions: bool,
+    // Portals whose visible region covers less than this many square pixels are not looked
+    // through. 0 looks through every portal, however small.
+    pub min_portal_area: f32,
+    pub impostors: Option<ImpostorSettings>,
+    pub portal_masking: PortalMasking,
+    // Traverse the states of each portal wave on the rayon thread pool. The output is the same
+    // either way; turning it off helps when profiling a single branch.
+    pub parallel_traversal: bool,
+    // Clip the sides of large portal waves in a compute shader instead of on the CPU; see
+    // `GpuSideClipper`. Each wave then waits on a GPU round trip, so this only pays off in scenes
+    // with thousands of sides.
+    pub gpu_side_clipping: bool,
+    // Time the scene, tonemap and UI passes on the GPU with timestamp queries, reported in
+    // `RenderStats::gpu_pass_times`. Needs a device created with `wgpu::Features::TIMESTAMP_QUERY`.
+    pub gpu_timing: bool,
+    // Fraction of the output resolution the scene is rendered at before being scaled up to it,
+    // from `MIN_RENDER_SCALE` to 1.
+    pub render_scale: f32,
+    // Adjusts `render_scale` to the frame time; see `Renderer::adjust_render_scale`.
+    pub dynamic_resolution: Option<DynamicResolution>,
+    // Multisample anti-aliasing: 1 (off) or 4. Other values are rounded down to one of those.
+    pub msaa_samples: u32,
+    // Linear scale applied to HDR colors before tonemapping.
+    pub exposure: f32,
+    pub tonemap: TonemapCurve,
+    // None draws no glow, however bright the scene.
+    pub bloom: Option<BloomSettings>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            depth_cue: None,
+            wireframe: false,
+            show_clip_regions: false,
+            show_debug_labels: true,
+            limits: TraversalLimits::default(),
+            budget: TraversalBudget::default(),
+            skip_repeated_regions: true,
+            min_portal_area: 1.0,
+            impostors: None,
+            portal_masking: PortalMasking::Clipping,
+            parallel_traversal: true,
+            gpu_side_clipping: false,
+            gpu_timing: false,
+            render_scale: 1.0,
+            dynamic_resolution: None,
+            msaa_samples: 4,
+            exposure: 1.0,
+            tonemap: TonemapCurve::Clamp,
+            bloom: None,
+        }
+    }
+}
+
+// A traversed frame as `SceneTraversal` leaves it, ready to be drawn. Positions are in pixels of
+// a `width` by `height` target.
+pub struct FrameGeometry<'a> {
+    pub width: f32,
+    pub height: f32,
+    // What the target is cleared to; the fog color in scenes with fog.
+    pub clear_color: [f32; 4],
+    pub fog: Option<&'a FogSettings>,
+    pub vertices: &'a [Vertex],
+    pub indices: &'a [u32],
+    pub sky_vertices: &'a [SkyVertex],
+    pub sky_indices: &'a [u32],
+    pub draws: &'a [FrameDraw],
+    // Portal stencil masks, for `FrameDraw::PortalMask`.
+    pub mask_vertices: &'a [Vertex],
+    pub mask_indices: &'a [u32],
+    // Regions to fill from the last impostor capture, before anything else.
+    pub impostor_vertices: &'a [Vertex],
+    pub impostor_indices: &'a [u32],
+    // Whether this frame's result should be kept as the next impostor capture.
+    pub capture_impostor: bool,
+    // Debug outlines drawn over everything as a line list.
+    pub overlay_vertices: &'a [Vertex],
+    pub overlay_indices: &'a [u32],
+}
+
+// Where traversed frames are drawn: `Renderer` submits them to the GPU (`WgpuBackend`) and
+// `SoftwareRenderer` rasterizes them on the CPU. The traversal knows nothing of either, so tests and
+// tools can plug in a backend that only records what it is given.
+pub trait RenderBackend {
+    fn submit_frame(&mut self, frame: &FrameGeometry, settings: &RenderSettings);
+}
+
+pub struct Renderer {
+    pub settings: RenderSettings,
+    backend: WgpuBackend,
+    traversal: SceneTraversal,
+    // Created the first time `RenderSettings::gpu_side_clipping` is on.
+    gpu_side_clipper: Option<GpuSideClipper>,
+    // Present while `RenderSettings::gpu_timing` is on; see `Renderer::begin_gpu_frame`.
+    gpu_timer: Option<GpuTimer>,
+}
+
+// The GPU side of `Renderer`: pipelines, buffers and render targets. Draws a traversed frame
+// through `WgpuBackend::with_target`.
+pub struct WgpuBackend {
+    pipelines: ScenePipelines,
+    pipeline_layout: wgpu::PipelineLayout,
+    shader_module: wgpu::ShaderModule,
+    sky_shader_module: wgpu::ShaderModule,
+    target_format: wgpu::TextureFormat,
+    msaa_target: Option<MsaaTarget>,
+    stencil_target: Option<StencilTarget>,
+    hdr_target: Option<HdrTarget>,
+    tonemap_pipeline: wgpu::RenderPipeline,
+    tonemap_bind_group_layout: wgpu::BindGroupLayout,
+    tonemap_sampler: wgpu::Sampler,
+    tonemap_upscale_sampler: wgpu::Sampler,
+    tonemap_uniform_buffer: wgpu::Buffer,
+    impostor_pipeline_layout: wgpu::PipelineLayout,
+    impostor_shader_module: wgpu::ShaderModule,
+    impostor_bind_group_layout: wgpu::BindGroupLayout,
+    impostor_target: Option<ImpostorTarget>,
+    impostor_vertex_buffer: GrowableBuffer,
+    impostor_index_buffer: GrowableBuffer,
+    vertex_buffer: GrowableBuffer,
+    index_buffer: GrowableBuffer,
+    sky_vertex_buffer: GrowableBuffer,
+    sky_index_buffer: GrowableBuffer,
+    line_index_buffer: GrowableBuffer,
+    sky_line_index_buffer: GrowableBuffer,
+    frame_line_indices: Vec<u32>,
+    overlay_vertex_buffer: GrowableBuffer,
+    overlay_index_buffer: GrowableBuffer,
+    mask_vertex_buffer: GrowableBuffer,
+    mask_index_buffer: GrowableBuffer,
+    // Uploads the geometry buffers above; recalled at the start of each frame.
+    staging_belt: StagingBelt,
+    screen_uniform_buffer: wgpu::Buffer,
+    fog_uniform_buffer: wgpu::Buffer,
+    screen_bind_group: wgpu::BindGroup,
+}
+
+// The CPU side of drawing a frame: portal traversal, clipping and the side handlers, producing
+// the vertex and index lists (`SceneTraversal::frame`) that a `RenderBackend` draws. Needs no GPU,
+// so it can be driven directly by tests, benchmarks and tools.
+pub struct SceneTraversal {
+    frame_vertices: Vec<Vertex>,
+    frame_indices: Vec<u32>,
+    frame_sky_vertices: Vec<SkyVertex>,
+    frame_sky_indices: Vec<u32>,
+    frame_overlay_vertices: Vec<Vertex>,
+    frame_overlay_indices: Vec<u32>,
+    frame_labels: Vec<ScreenLabel>,
+    frame_draws: Vec<FrameDraw>,
+    frame_materials: MaterialRuns,
+    frame_mask_vertices: Vec<Vertex>,
+    frame_mask_indices: Vec<u32>,
+    // Regions drawn from the impostor capture. Only their positions matter.
+    frame_impostor_vertices: Vec<Vertex>,
+    frame_impostor_indices: Vec<u32>,
+    impostor_capture: Option<ImpostorCapture>,
+    wall_handler: Arc<StandardWallHandler>,
+    portal_handler: Arc<StandardPortalHandler>,
+    skybox_handler: Arc<SkyboxHandler>,
+    door_handler: Arc<DoorHandler>,
+    liquid_surface_handler: Arc<LiquidSurfaceHandler>,
+    custom_handlers: SideHandlerRegistry,
+}
+
+impl Default for SceneTraversal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SceneTraversal {
+    pub fn new() -> Self {
+        Self {
+            frame_vertices: Vec::with_capacity(RENDERER_INITIAL_VERTICES),
+            frame_indices: Vec::with_capacity(RENDERER_INITIAL_INDICES),
+            frame_sky_vertices: Vec::new(),
+            frame_sky_indices: Vec::new(),
+            frame_overlay_vertices: Vec::new(),
+            frame_overlay_indices: Vec::new(),
+            frame_labels: Vec::new(),
+            frame_draws: Vec::new(),
+            frame_materials: MaterialRuns::default(),
+            frame_mask_vertices: Vec::new(),
+            frame_mask_indices: Vec::new(),
+            frame_impostor_vertices: Vec::new(),
+            frame_impostor_indices: Vec::new(),
+            impostor_capture: None,
+            wall_handler: Arc::new(StandardWallHandler),
+            portal_handler: Arc::new(StandardPortalHandler),
+            skybox_handler: Arc::new(SkyboxHandler),
+            door_handler: Arc::new(DoorHandler),
+            liquid_surface_handler: Arc::new(LiquidSurfaceHandler),
+            custom_handlers: SideHandlerRegistry::new(),
+        }
+    }
+
+    pub fn vertices(&self) -> &[Vertex] {
+        &self.frame_vertices
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.frame_indices
+    }
+
+    pub fn sky_vertices(&self) -> &[SkyVertex] {
+        &self.frame_sky_vertices
+    }
+
+    pub fn sky_indices(&self) -> &[u32] {
+        &self.frame_sky_indices
+    }
+
+    pub fn labels(&self) -> &[ScreenLabel] {
+        &self.frame_labels
+    }
+
+    // The last frame's geometry, to hand to a `RenderBackend`. `clear_color` should be the
+    // background color it was built with.
+    pub fn frame<'a>(&'a self, width: f32, height: f32, clear_color: [f32; 4], fog: Option<&'a FogSettings>) -> FrameGeometry<'a> {
+        FrameGeometry {
+            width,
+            height,
+            clear_color,
+            fog,
+            vertices: &self.frame_vertices,
+            indices: &self.frame_indices,
+            sky_vertices: &self.frame_sky_vertices,
+            sky_indices: &self.frame_sky_indices,
+            draws: &self.frame_draws,
+            mask_vertices: &self.frame_mask_vertices,
+            mask_indices: &self.frame_mask_indices,
+            impostor_vertices: &self.frame_impostor_vertices,
+            impostor_indices: &self.frame_impostor_indices,
+            capture_impostor: self.impostor_capture_requested(),
+            overlay_vertices: &self.frame_overlay_vertices,
+            overlay_indices: &self.frame_overlay_indices,
+        }
+    }
+
+    // Whether the last frame was an impostor capture frame, whose result has to be kept for
+    // the frames after it.
+    pub fn impostor_capture_requested(&self) -> bool {
+        self.impostor_capture.as_ref().is_some_and(|capture| !capture.reusing)
+    }
+
+    // Handlers for `HandlerConfig::Custom` sides, by type id.
+    pub fn side_handlers(&self) -> &SideHandlerRegistry {
+        &self.custom_handlers
+    }
+
+    pub fn side_handlers_mut(&mut self) -> &mut SideHandlerRegistry {
+        &mut self.custom_handlers
+    }
+
+    // Replaces the frame's geometry with `views`, traversed in order. Views after the first start
+    // with a quad in `background_color` so they cover what is underneath. Returns one
+    // `RenderStats` per view.
+    pub fn build_frame(
+        &mut self,
+        scene: &Scene,
+        views: &[SceneView],
+        settings: &RenderSettings,
+        background_color: [f32; 4],
+        layer_filter: RenderLayerMask,
+    ) -> Vec<RenderStats> {
+        self.build_frame_with_side_clipper(scene, views, settings, background_color, layer_filter, None)
+    }
+
+    // `build_frame`, handing the sides of large waves to `side_clipper` to clip.
+    pub fn build_frame_with_side_clipper(
+        &mut self,
+        scene: &Scene,
+        views: &[SceneView],
+        settings: &RenderSettings,
+        background_color: [f32; 4],
+        layer_filter: RenderLayerMask,
+        mut side_clipper: Option<&mut dyn SideClipper>,
+    ) -> Vec<RenderStats> {
+        let _span = tracing::info_span!("traversal", views = views.len()).entered();
+        self.frame_vertices.clear();
+        self.frame_indices.clear();
+        self.frame_sky_vertices.clear();
+        self.frame_sky_indices.clear();
+        self.frame_overlay_vertices.clear();
+        self.frame_overlay_indices.clear();
+        self.frame_labels.clear();
+        self.frame_draws.clear();
+        self.frame_materials.clear();
+        self.frame_mask_vertices.clear();
+        self.frame_mask_indices.clear();
+        self.frame_impostor_vertices.clear();
+        self.frame_impostor_indices.clear();
+
+        self.impostor_capture = match (settings.impostors, views) {
+            (Some(impostors), [view]) if settings.portal_masking == PortalMasking::Clipping => match self.impostor_capture.take() {
+                Some(mut capture) if capture.still_valid(&impostors, view) => {
+                    capture.age_frames += 1;
+                    capture.reusing = true;
+                    Some(capture)
+                }
+                _ => Some(ImpostorCapture::new(impostors, view)),
+            },
+            _ => None,
+        };
+
+        let mut stats = Vec::with_capacity(views.len());
+        for (view_idx, view) in views.iter().enumerate() {
+            let first_index = self.frame_indices.len() as u32;
+            let first_sky_index = self.frame_sky_indices.len() as u32;
+            let viewport = &view.viewport;
+            let corners = [
+                Point2::new(viewport.x, viewport.y),
+                Point2::new(viewport.x + viewport.width, viewport.y),
+                Point2::new(viewport.x + viewport.width, viewport.y + viewport.height),
+                Point2::new(viewport.x, viewport.y + viewport.height),
+            ];
+            if view_idx > 0 {
+                let start = self.frame_vertices.len() as u32;
+                self.frame_vertices.extend(corners.iter().map(|p| Vertex::new([p.x, p.y], background_color)));
+                push_fan_indices(&mut self.frame_indices, start, corners.len());
+                self.frame_materials.mark(first_index..self.frame_indices.len() as u32, Material::for_color(background_color));
+                push_outline(&mut self.frame_overlay_vertices, &mut self.frame_overlay_indices, &corners, VIEW_BORDER_COLOR);
+            }
+            if settings.portal_masking == PortalMasking::Stencil {
+                // The stencil is back at 0 after every view, so the background covers the views below.
+                push_geometry_draws(&mut self.frame_draws, &self.frame_materials, first_index..self.frame_indices.len() as u32, first_sky_index..first_sky_index, 0);
+                stats.push(self.traverse_view(scene, view, settings, layer_filter, reborrow_side_clipper(&mut side_clipper)));
+            } else {
+                stats.push(self.traverse_view(scene, view, settings, layer_filter, reborrow_side_clipper(&mut side_clipper)));
+                push_geometry_draws(
+                    &mut self.frame_draws, &self.frame_materials,
+                    first_index..self.frame_indices.len() as u32, first_sky_index..self.frame_sky_indices.len() as u32, 0,
+                );
+            }
+        }
+        stats
+    }
+
+    // CPU side of the frame: walks the portal graph breadth-first from the view's camera hull,
+    // clipping each side against the current portal region (or, with `PortalMasking::Stencil`,
+    // the viewport) and letting its handler emit geometry, which is appended to the frame
+    // buffers and offset into the view's viewport.
+    // Free-standing instances inside a world-placed hull are drawn over it afterwards.
+    fn traverse_view(
+        &mut self,
+        scene: &Scene,
+        view: &SceneView,
+        settings: &RenderSettings,
+        layer_filter: RenderLayerMask,
+        mut side_clipper: Option<&mut dyn SideClipper>,
+    ) -> RenderStats {
+        let camera = view.camera;
+        let screen_width = view.viewport.width;
+        let screen_height = view.viewport.height;
+        let traversal_start = Instant::now();
+        let mut stats = RenderStats::default();
+        let first_view_vertex = self.frame_vertices.len();
+        let first_view_sky_vertex = self.frame_sky_vertices.len();
+        let first_view_overlay_vertex = self.frame_overlay_vertices.len();
+        let first_view_label = self.frame_labels.len();
+        let first_view_mask_vertex = self.frame_mask_vertices.len();
+        let first_view_index = self.frame_indices.len() + self.frame_sky_indices.len();
+
+        let mut traversal_queue: VecDeque<TraversalState> = VecDeque::new();
+        // The `StencilBatch` of each queued state, in the same order.
+        let mut batch_queue: VecDeque<usize> = VecDeque::new();
+        let mut batches = vec![StencilBatch { level: 0, mask: 0..0, items: Vec::new() }];
+
+        let (clip_width, clip_height) = (screen_width as f64, screen_height as f64);
+        let initial_clip_points = [
+            Point2::new(0.0, 0.0),
+            Point2::new(clip_width, 0.0),
+            Point2::new(clip_width, clip_height),
+            Point2::new(0.0, clip_height),
+        ];
+        let initial_screen_clip_polygon = ScreenPolygon::from_points(&initial_clip_points);
+
+        let camera_instance_id = view.camera_instance_id;
+        let camera_view_from_host_hull = camera.get_view_matrix_from_host_hull(&view.camera_local_transform);
+        let limits = settings.limits;
+        let depth_cue = settings.depth_cue.as_ref();
+        let mut traversal_states_processed: u32 = 0;
+
+        // Hulls and clip regions already queued, when `skip_repeated_regions` is on.
+        let mut visited_regions: HashSet<(InstanceId, u64)> = HashSet::new();
+        if scene.instances.contains_key(&camera_instance_id) {
+            let root_state = TraversalState {
+                current_instance_id: camera_instance_id,
+                accumulated_transform: Mat4::IDENTITY, // Changed
+                screen_space_clip_polygon: initial_screen_clip_polygon.clone(),
+                recursion_depth: 0,
+                entered_from_instance_id: None,
+            };
+            visited_regions.insert(visited_region_key(&root_state));
+            traversal_queue.push_back(root_state);
+            batch_queue.push_back(0);
+        }
+
+        // Free-standing instance sides and entity faces waiting to be drawn, used as a stack: each
+        // traversal pass pushes its batch on top so it finishes before older, shallower ones.
+        let mut deferred_draws: Vec<DeferredDraw> = Vec::new();
+        let mut new_deferred_draws: Vec<DeferredDraw> = Vec::new();
+        let mut deferred_children: VecDeque<TraversalState> = VecDeque::new();
+        let mut overlays: Vec<SideOverlay> = Vec::new();
+        // The batch and first scene and sky index of the deferred geometry emitted since the last wave.
+        let mut open_deferred: Option<(usize, u32, u32)> = None;
+        let mut contained_by_instance: HashMap<InstanceId, Vec<InstanceId>> = HashMap::new();
+        let mut entities_by_instance: HashMap<InstanceId, Vec<&Entity>> = HashMap::new();
+        let mut bounds_by_blueprint: BlueprintBounds = HashMap::new();
+        let wave_context = WaveContext {
+            scene,
+            camera,
+            camera_view_from_host_hull,
+            screen_width,
+            screen_height,
+            layer_filter,
+            settings,
+            viewport_clip_polygon: &initial_screen_clip_polygon,
+            wall_handler: &self.wall_handler,
+            portal_handler: &self.portal_handler,
+            skybox_handler: &self.skybox_handler,
+            door_handler: &self.door_handler,
+            liquid_surface_handler: &self.liquid_surface_handler,
+            custom_handlers: &self.custom_handlers,
+            traversal_start,
+        };
+
+        'traversal: loop {
+            if let Some((batch, first_index, first_sky_index)) = open_deferred.take() {
+                batches[batch].push_geometry(first_index..self.frame_indices.len() as u32, first_sky_index..self.frame_sky_indices.len() as u32);
+            }
+            // Everything queued is one wave: the states it adds come after all of them, so the
+            // wave's states can be traversed independently and merged back in queue order.
+            while !traversal_queue.is_empty() {
+                let remaining_states = limits.max_traversal_states.saturating_sub(traversal_states_processed) as usize;
+                if remaining_states == 0 {
+                    stats.aborted = Some(TraversalAbort::TraversalStates);
+                    break 'traversal;
+                }
+                let budget_hulls = settings.budget.max_hulls.map_or(usize::MAX, |max_hulls| max_hulls.saturating_sub(traversal_states_processed) as usize);
+                let out_of_time = settings.budget.max_time.is_some_and(|max_time| traversal_start.elapsed() >= max_time);
+                if budget_hulls == 0 || out_of_time {
+                    for (state, batch) in traversal_queue.drain(..).zip(batch_queue.drain(..)) {
+                        let first_index = self.frame_indices.len() as u32;
+                        fill_over_budget(
+                            &mut self.frame_vertices, &mut self.frame_indices, scene, camera, &camera_view_from_host_hull,
+                            screen_width, screen_height, &state, depth_cue,
+                        );
+                        batches[batch].push_geometry(first_index..self.frame_indices.len() as u32, 0..0);
+                        stats.hulls_over_budget += 1;
+                    }
+                    break;
+                }
+                let wave: Vec<TraversalState> = traversal_queue.drain(..traversal_queue.len().min(remaining_states).min(budget_hulls)).collect();
+                let wave_span = tracing::debug_span!("wave", states = wave.len());
+                let _wave_guard = wave_span.enter();
+                let wave_batches: Vec<usize> = batch_queue.drain(..wave.len()).collect();
+                traversal_states_processed += wave.len() as u32;
+                for state in &wave {
+                    contained_by_instance
+                        .entry(state.current_instance_id)
+                        .or_insert_with(|| contained_instances(scene, state.current_instance_id));
+                    entities_by_instance
+                        .entry(state.current_instance_id)
+                        .or_insert_with(|| entities_in_instance(scene, state.current_instance_id));
+                    let hull_ids = std::iter::once(&state.current_instance_id).chain(&contained_by_instance[&state.current_instance_id]);
+                    for blueprint_id in hull_ids.filter_map(|instance_id| scene.instances.get(instance_id)).map(|instance| instance.blueprint_id) {
+                        bounds_by_blueprint
+                            .entry(blueprint_id)
+                            .or_insert_with(|| scene.blueprints.get(&blueprint_id).and_then(blueprint_bounding_sphere));
+                    }
+                }
+
+                let clipped_sides = reborrow_side_clipper(&mut side_clipper).and_then(|side_clipper| clip_wave_sides(
+                    scene, camera, &camera_view_from_host_hull, screen_width, screen_height,
+                    &initial_screen_clip_polygon, settings.portal_masking, &wave, side_clipper,
+                ));
+                let sides_budget = limits.max_processed_sides.saturating_sub(stats.sides_processed);
+                let traverse = |(state_idx, state): (usize, &TraversalState)| {
+                    // Parented explicitly, as rayon's threads do not share the wave's span.
+                    let _span = tracing::debug_span!(parent: &wave_span, "traverse_state", instance = state.current_instance_id).entered();
+                    wave_context.traverse_state(
+                        state, sides_budget,
+                        &contained_by_instance[&state.current_instance_id],
+                        &entities_by_instance[&state.current_instance_id],
+                        &bounds_by_blueprint,
+                        clipped_sides.as_ref().map(|clipped_sides| clipped_sides[state_idx].as_slice()),
+                    )
+                };
+                let branches: Vec<BranchOutput> = if settings.parallel_traversal && wave.len() > 1 {
+                    wave.par_iter().enumerate().map(traverse).collect()
+                } else {
+                    wave.iter().enumerate().map(traverse).collect()
+                };
+
+                for (mut branch, &batch) in branches.into_iter().zip(&wave_batches) {
+                    if stats.sides_processed + branch.stats.sides_processed > limits.max_processed_sides {
+                        stats.aborted = Some(TraversalAbort::ProcessedSides);
+                        break 'traversal;
+                    }
+                    let (first_index, first_sky_index) = (self.frame_indices.len() as u32, self.frame_sky_indices.len() as u32);
+                    let vertex_base = self.frame_vertices.len() as u32;
+                    self.frame_vertices.append(&mut branch.vertices);
+                    self.frame_indices.extend(branch.indices.iter().map(|index| index + vertex_base));
+                    self.frame_materials.append(&branch.materials, first_index);
+                    let sky_vertex_base = self.frame_sky_vertices.len() as u32;
+                    self.frame_sky_vertices.append(&mut branch.sky_vertices);
+                    self.frame_sky_indices.extend(branch.sky_indices.iter().map(|index| index + sky_vertex_base));
+                    batches[batch].push_geometry(first_index..self.frame_indices.len() as u32, first_sky_index..self.frame_sky_indices.len() as u32);
+                    for deferred in &mut branch.deferred_draws {
+                        deferred.host_batch = batch;
+                    }
+                    let overlay_vertex_base = self.frame_overlay_vertices.len() as u32;
+                    self.frame_overlay_vertices.append(&mut branch.overlay_vertices);
+                    self.frame_overlay_indices.extend(branch.overlay_indices.iter().map(|index| index + overlay_vertex_base));
+                    self.frame_labels.append(&mut branch.labels);
+                    new_deferred_draws.append(&mut branch.deferred_draws);
+
+                    stats.hulls_visited += branch.stats.hulls_visited;
+                    stats.hulls_culled_by_bounds += branch.stats.hulls_culled_by_bounds;
+                    stats.sides_processed += branch.stats.sides_processed;
+                    stats.sides_drawn += branch.stats.sides_drawn;
+                    stats.max_recursion_depth = stats.max_recursion_depth.max(branch.stats.max_recursion_depth);
+                    stats.portals_culled_small += branch.stats.portals_culled_small;
+                    for child in branch.children.drain(..) {
+                        if settings.skip_repeated_regions && !visited_regions.insert(visited_region_key(&child)) {
+                            stats.repeated_regions_skipped += 1;
+                            continue;
+                        }
+                        if self.impostor_capture.as_mut().is_some_and(|capture| capture.stands_in_for(&child)) {
+                            emit_polygon_on_plane(
+                                &mut self.frame_impostor_vertices, &mut self.frame_impostor_indices, camera, screen_width, screen_height,
+                                &child.screen_space_clip_polygon, None, [1.0; 4],
+                            );
+                            stats.impostors_drawn += 1;
+                            continue;
+                        }
+                        stats.portals_traversed += 1;
+                        let mask = push_portal_mask(
+                            settings, &mut self.frame_mask_vertices, &mut self.frame_mask_indices, camera, screen_width, screen_height,
+                            &child.screen_space_clip_polygon,
+                        );
+                        batch_queue.push_back(push_child_batch(&mut batches, batch, mask));
+                        traversal_queue.push_back(child);
+                    }
+                    if let Some(abort) = branch.stats.aborted {
+                        stats.aborted = Some(abort);
+                        break 'traversal;
+                    }
+                }
+            }
+
+            // Pop order is deepest first, then lowest priority, then farthest first.
+            new_deferred_draws.sort_by(|a, b| {
+                a.recursion_depth.cmp(&b.recursion_depth).then(b.priority.cmp(&a.priority)).then(a.distance.total_cmp(&b.distance))
+            });
+            deferred_draws.append(&mut new_deferred_draws);
+            let deferred = match deferred_draws.pop() {
+                Some(deferred) => deferred,
+                None => break,
+            };
+            open_deferred = Some((deferred.host_batch, self.frame_indices.len() as u32, self.frame_sky_indices.len() as u32));
+            let (host_instance_id, instance_id, side_index) = match deferred.kind {
+                DeferredKind::InstanceSide { host_instance_id, instance_id, side_index } => (host_instance_id, instance_id, side_index),
+                DeferredKind::Sprite { color, depth } => {
+                    let plane = (Vec3::Z, Vec3::new(0.0, 0.0, -depth));
+                    let first_new_vertex = self.frame_vertices.len();
+                    let first_new_index = self.frame_indices.len() as u32;
+                    emit_polygon_on_plane(
+                        &mut self.frame_vertices, &mut self.frame_indices, camera, screen_width, screen_height,
+                        &deferred.visible_screen_polygon, Some(&plane), color,
+                    );
+                    self.frame_materials.mark(first_new_index..self.frame_indices.len() as u32, Material::for_color(color));
+                    stats.entity_faces_drawn += 1;
+                    apply_depth_cue(depth_cue, &mut self.frame_vertices[first_new_vertex..], &mut [], deferred.recursion_depth);
+                    continue;
+                }
+                DeferredKind::Overlay { plane, colors } => {
+                    let first_new_vertex = self.frame_vertices.len();
+                    let first_new_index = self.frame_indices.len() as u32;
+                    emit_polygon_on_plane(
+                        &mut self.frame_vertices, &mut self.frame_indices, camera, screen_width, screen_height,
+                        &deferred.visible_screen_polygon, plane.as_ref(), [1.0; 4],
+                    );
+                    for (vertex, color) in self.frame_vertices[first_new_vertex..].iter_mut().zip(colors) {
+                        vertex.color = color;
+                    }
+                    self.frame_materials.mark(first_new_index..self.frame_indices.len() as u32, Material::ALPHA);
+                    apply_depth_cue(depth_cue, &mut self.frame_vertices[first_new_vertex..], &mut [], deferred.recursion_depth);
+                    continue;
+                }
+                DeferredKind::Translucent { vertices, indices } => {
+                    let vertex_base = self.frame_vertices.len() as u32;
+                    let first_new_index = self.frame_indices.len() as u32;
+                    self.frame_vertices.extend(vertices);
+                    self.frame_indices.extend(indices.iter().map(|index| index + vertex_base));
+                    self.frame_materials.mark(first_new_index..self.frame_indices.len() as u32, Material::ALPHA);
+                    continue;
+                }
+                DeferredKind::EntityFace { entity_id, face_index } => {
+                    let (mesh, face) = match scene.entities.get(&entity_id)
+                        .and_then(|entity| match &entity.shape {
+                            EntityShape::Mesh(mesh) => mesh.faces.get(face_index).map(|face| (mesh, face)),
+                            EntityShape::Sprite(_) => None,
+                        })
+                    {
+                        Some(pair) => pair,
+                        None => continue,
+                    };
+                    let camera_view_from_entity = camera_view_from_host_hull * deferred.transform_to_camera_host_hull;
+                    let plane = mesh.face_normal(face)
+                        .zip(face.vertex_indices.first().and_then(|&v_idx| mesh.vertices.get(v_idx)))
+                        .map(|(normal, p0)| (
+                            camera_view_from_entity.transform_vector3(normal).normalize_or_zero(),
+                            camera_view_from_entity.transform_point3(*p0),
+                        ));
+                    let first_new_vertex = self.frame_vertices.len();
+                    let first_new_index = self.frame_indices.len() as u32;
+                    emit_polygon_on_plane(
+                        &mut self.frame_vertices, &mut self.frame_indices, camera, screen_width, screen_height,
+                        &deferred.visible_screen_polygon, plane.as_ref(), face.color,
+                    );
+                    self.frame_materials.mark(first_new_index..self.frame_indices.len() as u32, Material::for_color(face.color));
+                    stats.entity_faces_drawn += 1;
+                    apply_depth_cue(depth_cue, &mut self.frame_vertices[first_new_vertex..], &mut [], deferred.recursion_depth);
+                    continue;
+                }
+            };
+            let (instance, blueprint) = match scene.instances.get(&instance_id)
+                .and_then(|instance| scene.blueprints.get(&instance.blueprint_id).map(|bp| (instance, bp)))
+            {
+                Some(pair) => pair,
+                None => continue,
+            };
+            let blueprint_side = &blueprint.sides[side_index];
+            let effective_config = instance.instance_side_handler_configs
+                .get(&side_index)
+                .unwrap_or(&blueprint_side.default_handler_config);
+
+            // An open side: look into the instance through it.
+            if effective_config.visible_portal_target().is_some() {
+                if (deferred.visible_screen_polygon.area() as f32) < settings.min_portal_area {
+                    stats.portals_culled_small += 1;
+                } else if deferred.recursion_depth < MAX_PORTAL_RECURSION_DEPTH {
+                    let state = TraversalState {
+                        current_instance_id: instance_id,
+                        accumulated_transform: deferred.transform_to_camera_host_hull,
+                        screen_space_clip_polygon: deferred.visible_screen_polygon,
+                        recursion_depth: deferred.recursion_depth + 1,
+                        entered_from_instance_id: Some(host_instance_id),
+                    };
+                    if settings.skip_repeated_regions && !visited_regions.insert(visited_region_key(&state)) {
+                        stats.repeated_regions_skipped += 1;
+                    } else if self.impostor_capture.as_mut().is_some_and(|capture| capture.stands_in_for(&state)) {
+                        emit_polygon_on_plane(
+                            &mut self.frame_impostor_vertices, &mut self.frame_impostor_indices, camera, screen_width, screen_height,
+                            &state.screen_space_clip_polygon, None, [1.0; 4],
+                        );
+                        stats.impostors_drawn += 1;
+                    } else {
+                        let mask = push_portal_mask(
+                            settings, &mut self.frame_mask_vertices, &mut self.frame_mask_indices, camera, screen_width, screen_height,
+                            &state.screen_space_clip_polygon,
+                        );
+                        batch_queue.push_back(push_child_batch(&mut batches, deferred.host_batch, mask));
+                        traversal_queue.push_back(state);
+                        stats.portals_traversed += 1;
+                    }
+                }
+                continue;
+            }
+
+            let side_to_camera_host_hull = deferred.transform_to_camera_host_hull * side_transform(instance, side_index);
+            let first_new_vertex = self.frame_vertices.len();
+            let first_new_sky_vertex = self.frame_sky_vertices.len();
+            let first_new_index = self.frame_indices.len() as u32;
+            let mut handler_ctx = HandlerContext {
+                frame_vertices: &mut self.frame_vertices,
+                frame_indices: &mut self.frame_indices,
+                frame_sky_vertices: &mut self.frame_sky_vertices,
+                frame_sky_indices: &mut self.frame_sky_indices,
+                scene,
+                camera,
+                current_instance: instance,
+                blueprint_side,
+                side_config: effective_config,
+                transform_to_camera_host_hull: &side_to_camera_host_hull,
+                camera_view_from_host_hull: &camera_view_from_host_hull,
+                screen_width,
+                screen_height,
+                visible_screen_polygon: deferred.visible_screen_polygon,
+                traversal_queue: &mut deferred_children,
+                current_recursion_depth: deferred.recursion_depth,
+                material: Material::OPAQUE,
+                overlays: &mut overlays,
+            };
+            dispatch_side_handler(
+                &self.wall_handler, &self.portal_handler, &self.skybox_handler, &self.door_handler, &self.liquid_surface_handler,
+                &self.custom_handlers, &mut handler_ctx,
+            );
+            let material = handler_ctx.material;
+            let side_visible = handler_ctx.visible_screen_polygon;
+            let translucent = material.blend == BlendMode::Alpha;
+            let mut translucent_materials = MaterialRuns::default();
+            let side_materials = if translucent { &mut translucent_materials } else { &mut self.frame_materials };
+            side_materials.mark(first_new_index..self.frame_indices.len() as u32, material);
+            new_deferred_draws.extend(overlays.drain(..).map(|overlay| DeferredDraw {
+                host_batch: deferred.host_batch,
+                ..overlay_deferred_draw(
+                    overlay, camera, screen_width, screen_height, side_to_camera_host_hull, deferred.recursion_depth,
+                    effective_config.render_priority(),
+                )
+            }));
+            emit_side_decals(
+                &mut self.frame_vertices, &mut self.frame_indices, side_materials, camera, screen_width, screen_height,
+                &(camera_view_from_host_hull * side_to_camera_host_hull), blueprint_side.local_normal, &side_visible,
+                scene.decals.values().filter(|decal| decal.instance_id == instance_id && decal.side_index == side_index),
+            );
+            if self.frame_vertices.len() > first_new_vertex || self.frame_sky_vertices.len() > first_new_sky_vertex {
+                stats.sides_drawn += 1;
+            }
+            // Held back again, now to be drawn over what its side's portals lead to.
+            if translucent {
+                apply_depth_cue(depth_cue, &mut self.frame_vertices[first_new_vertex..], &mut [], deferred.recursion_depth);
+                new_deferred_draws.push(DeferredDraw {
+                    host_batch: deferred.host_batch,
+                    ..translucent_deferred_draw(
+                        &mut self.frame_vertices, &mut self.frame_indices, first_new_vertex, first_new_index, camera, screen_width, screen_height,
+                        side_visible, side_to_camera_host_hull, deferred.recursion_depth, effective_config.render_priority(),
+                    )
+                });
+            }
+            stats.portals_traversed += deferred_children.len() as u32;
+            if let Some((batch, first_index, first_sky_index)) = open_deferred.take() {
+                batches[batch].push_geometry(first_index..self.frame_indices.len() as u32, first_sky_index..self.frame_sky_indices.len() as u32);
+            }
+            for child in &deferred_children {
+                let mask = push_portal_mask(
+                    settings, &mut self.frame_mask_vertices, &mut self.frame_mask_indices, camera, screen_width, screen_height,
+                    &child.screen_space_clip_polygon,
+                );
+                batch_queue.push_back(push_child_batch(&mut batches, deferred.host_batch, mask));
+            }
+            traversal_queue.append(&mut deferred_children);
+            apply_depth_cue(
+                depth_cue, &mut self.frame_vertices[first_new_vertex..], &mut self.frame_sky_vertices[first_new_sky_vertex..],
+                deferred.recursion_depth,
+            );
+        }
+
+        if let Some((batch, first_index, first_sky_index)) = open_deferred.take() {
+            batches[batch].push_geometry(first_index..self.frame_indices.len() as u32, first_sky_index..self.frame_sky_indices.len() as u32);
+        }
+        // From inside a liquid, everything is seen through it.
+        if let Some(liquid) = liquid_in(scene, camera_instance_id) {
+            let first_index = self.frame_indices.len() as u32;
+            emit_polygon_on_plane(
+                &mut self.frame_vertices, &mut self.frame_indices, camera, screen_width, screen_height,
+                &initial_screen_clip_polygon, None, liquid.tint,
+            );
+            self.frame_materials.mark(first_index..self.frame_indices.len() as u32, Material::for_color(liquid.tint));
+            batches[0].push_geometry(first_index..self.frame_indices.len() as u32, 0..0);
+        }
+
+        // Handlers work in viewport-local coordinates; move this view's output into place.
+        let offset = [view.viewport.x, view.viewport.y];
+        if offset != [0.0, 0.0] {
+            for vertex in &mut self.frame_vertices[first_view_vertex..] {
+                vertex.position[0] += offset[0];
+                vertex.position[1] += offset[1];
+            }
+            for vertex in &mut self.frame_sky_vertices[first_view_sky_vertex..] {
+                vertex.position[0] += offset[0];
+                vertex.position[1] += offset[1];
+            }
+            for vertex in &mut self.frame_overlay_vertices[first_view_overlay_vertex..] {
+                vertex.position[0] += offset[0];
+                vertex.position[1] += offset[1];
+            }
+            for vertex in &mut self.frame_mask_vertices[first_view_mask_vertex..] {
+                vertex.position[0] += offset[0];
+                vertex.position[1] += offset[1];
+            }
+            for vertex in &mut self.frame_impostor_vertices {
+                vertex.position[0] += offset[0];
+                vertex.position[1] += offset[1];
+            }
+            for label in &mut self.frame_labels[first_view_label..] {
+                label.position[0] += offset[0];
+                label.position[1] += offset[1];
+            }
+        }
+
+        if settings.portal_masking == PortalMasking::Stencil {
+            flatten_stencil_batches(&batches, 0, &self.frame_materials, &mut self.frame_draws);
+        }
+
+        stats.vertices_emitted = (self.frame_vertices.len() - first_view_vertex
+            + self.frame_sky_vertices.len() - first_view_sky_vertex) as u32;
+        stats.indices_emitted = (self.frame_indices.len() + self.frame_sky_indices.len() - first_view_index) as u32;
+        stats.traversal_time = traversal_start.elapsed();
+        stats
+    }
+}
+
+
+impl Renderer {
+    pub fn new(
+        device: &wgpu::Device,
+        surface_format: wgpu::TextureFormat,
+        shader_source: &str,
+        initial_screen_width: f32,
+        initial_screen_height: f32,
+    ) -> Self {
+        let settings = RenderSettings::default();
+        Self {
+            backend: WgpuBackend::new(device, surface_format, shader_source, initial_screen_width, initial_screen_height, &settings),
+            settings,
+            traversal: SceneTraversal::new(),
+            gpu_side_clipper: None,
+            gpu_timer: None,
+        }
+    }
+
+    // Debug labels visible in the last rendered frame, for the UI to draw on top.
+    pub fn frame_labels(&self) -> &[ScreenLabel] {
+        self.traversal.labels()
+    }
+
+    // Moves `settings.render_scale` a step towards the target of `settings.dynamic_resolution`,
+    // given how long the last frame (or a smoothed average) took. Does nothing without one.
+    pub fn adjust_render_scale(&mut self, frame_time: Duration) {
+        if let Some(dynamic_resolution) = self.settings.dynamic_resolution {
+            self.settings.render_scale = dynamic_resolution.next_scale(self.settings.render_scale, frame_time);
+        }
+    }
+
+    // Where user-defined side handlers are registered; see `SideHandlerRegistry`.
+    pub fn side_handlers_mut(&mut self) -> &mut SideHandlerRegistry {
+        self.traversal.side_handlers_mut()
+    }
+
+    // Starts timing a frame's passes on the GPU while `settings.gpu_timing` is on. Call before
+    // the frame's first pass and `end_gpu_frame` after its last, once per submitted frame;
+    // passes the app records itself can be timed with `gpu_timestamp_writes`.
+    pub fn begin_gpu_frame(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.settings.gpu_timing {
+            self.gpu_timer = None;
+            return;
+        }
+        if self.gpu_timer.is_none() {
+            self.gpu_timer = GpuTimer::new(device, queue);
+        }
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.begin_frame(device);
+        }
+    }
+
+    // Timestamp writes for a pass recorded outside the renderer, such as the UI.
+    pub fn gpu_timestamp_writes(&mut self, pass: GpuPass) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        self.gpu_timer.as_mut()?.timestamp_writes(pass)
+    }
+
+    // Resolves the frame's timestamps on `encoder`, which must be submitted after every timed pass.
+    pub fn end_gpu_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.end_frame(encoder);
+        }
+    }
+
+    // GPU pass times of the latest frame read back; also in `RenderStats::gpu_pass_times`.
+    pub fn gpu_pass_times(&self) -> Option<GpuPassTimes> {
+        self.gpu_timer.as_ref().and_then(GpuTimer::latest)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_scene(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+        scene: &Scene,
+        camera: &Camera,
+        screen_width: f32,
+        screen_height: f32,
+        clear_color: wgpu::Color,
+        layer_filter: RenderLayerMask,
+    ) -> RenderStats {
+        let active_camera = scene.active_camera.as_str();
+        self.render_scene_from_camera(
+            device, queue, encoder, output_view,
+            scene, camera, active_camera,
+            screen_width, screen_height, clear_color, layer_filter,
+        )
+    }
+
+    // Same as `render_scene`, but from any of the scene's named cameras, for secondary views.
+    // If there is no camera called `camera_name` the target is just cleared.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_scene_from_camera(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+        scene: &Scene,
+        camera: &Camera,
+        camera_name: &str,
+        screen_width: f32,
+        screen_height: f32,
+        clear_color: wgpu::Color,
+        layer_filter: RenderLayerMask,
+    ) -> RenderStats {
+        let view = SceneView::from_scene_camera(scene, camera_name, camera, Viewport::full(screen_width, screen_height));
+        let views: &[SceneView] = match &view {
+            Some(view) => std::slice::from_ref(view),
+            None => &[],
+        };
+        self.render_views(
+            device, queue, encoder, output_view, scene, views,
+            screen_width, screen_height, clear_color, layer_filter,
+        ).pop().unwrap_or_default()
+    }
+
+    // Same as `render_scene`, but from an arbitrary camera pose instead of one of the scene's
+    // cameras. `camera_local_transform` is relative to `camera_instance_id`'s blueprint.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_scene_from_pose(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+        scene: &Scene,
+        camera: &Camera,
+        camera_instance_id: InstanceId,
+        camera_local_transform: &Mat4,
+        screen_width: f32,
+        screen_height: f32,
+        clear_color: wgpu::Color,
+        layer_filter: RenderLayerMask,
+    ) -> RenderStats {
+        let view = SceneView {
+            camera,
+            camera_instance_id,
+            camera_local_transform: *camera_local_transform,
+            viewport: Viewport::full(screen_width, screen_height),
+        };
+        self.render_views(
+            device, queue, encoder, output_view, scene, std::slice::from_ref(&view),
+            screen_width, screen_height, clear_color, layer_filter,
+        ).pop().unwrap_or_default()
+    }
+
+    // Draws several views of the scene into one target in a single pass, e.g. for split-screen
+    // or picture-in-picture. Each view is traversed with its viewport as the initial clip
+    // polygon, and later views are drawn over earlier ones. Returns one `RenderStats` per view.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_views(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+        scene: &Scene,
+        views: &[SceneView],
+        screen_width: f32,
+        screen_height: f32,
+        clear_color: wgpu::Color,
+        layer_filter: RenderLayerMask,
+    ) -> Vec<RenderStats> {
+        // Below full scale the scene is drawn into a smaller HDR target, as if the output were that
+        // size, and the tonemap pass scales it up.
+        let render_scale = self.settings.render_scale.clamp(MIN_RENDER_SCALE, 1.0);
+        let (output_width, output_height) = (screen_width, screen_height);
+        let screen_width = (output_width * render_scale).round().max(1.0);
+        let screen_height = (output_height * render_scale).round().max(1.0);
+        let (scale_x, scale_y) = (screen_width / output_width.max(1.0), screen_height / output_height.max(1.0));
+        let views: Vec<SceneView> = views.iter().map(|view| SceneView {
+            viewport: Viewport {
+                x: view.viewport.x * scale_x,
+                y: view.viewport.y * scale_y,
+                width: view.viewport.width * scale_x,
+                height: view.viewport.height * scale_y,
+            },
+            ..*view
+        }).collect();
+        let views = views.as_slice();
+
+        // With fog enabled, clear to the fog color so geometry beyond the recursion limit
+        // is indistinguishable from fully fogged geometry instead of popping.
+        let clear_color = match &scene.fog {
+            Some(fog) => wgpu::Color {
+                r: fog.color[0] as f64, g: fog.color[1] as f64, b: fog.color[2] as f64, a: fog.color[3] as f64,
+            },
+            None => clear_color,
+        };
+
+        // Views are drawn in order, so later views (e.g. picture-in-picture insets) cover earlier
+        // ones. Each view starts with a quad in the clear color to hide what's underneath.
+        let background_color = [clear_color.r as f32, clear_color.g as f32, clear_color.b as f32, clear_color.a as f32];
+        let mut stats = if self.settings.gpu_side_clipping {
+            let clipper = self.gpu_side_clipper.get_or_insert_with(|| GpuSideClipper::new(device));
+            let mut side_clipper = clipper.with_device(device, queue);
+            self.traversal.build_frame_with_side_clipper(scene, views, &self.settings, background_color, layer_filter, Some(&mut side_clipper))
+        } else {
+            self.traversal.build_frame(scene, views, &self.settings, background_color, layer_filter)
+        };
+        // Labels are drawn over the output, not the HDR target.
+        for label in &mut self.traversal.frame_labels {
+            label.position = [label.position[0] / scale_x, label.position[1] / scale_y];
+        }
+
+        let frame = self.traversal.frame(screen_width, screen_height, background_color, scene.fog.as_ref());
+        self.backend.with_target(device, queue, encoder, output_view)
+            .timed(self.gpu_timer.as_mut())
+            .submit_frame(&frame, &self.settings);
+        let gpu_pass_times = self.gpu_timer.as_ref().and_then(GpuTimer::latest);
+        for view_stats in &mut stats {
+            view_stats.gpu_pass_times = gpu_pass_times;
+        }
+        stats
+    }
+
+    // Recompiles the scene and sky shaders and rebuilds their pipelines; see `WgpuBackend::reload_shaders`.
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, scene_source: &str, sky_source: &str) -> Result<(), String> {
+        self.backend.reload_shaders(device, scene_source, sky_source)
+    }
+}
+
+impl WgpuBackend {
+    pub fn new(
+        device: &wgpu::Device,
+        surface_format: wgpu::TextureFormat,
+        shader_source: &str,
+        initial_screen_width: f32,
+        initial_screen_height: f32,
+        settings: &RenderSettings,
+    ) -> Self {
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Renderer Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+        });
+
+        let screen_uniform_data = ScreenDimensionsUniform {
+            width: initial_screen_width,
+            height: initial_screen_height,
+            _padding1: 0.0,
+            _padding2: 0.0,
+        };
+        let screen_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Screen Dimensions Uniform Buffer"),
+            contents: bytemuck::bytes_of(&screen_uniform_data),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let fog_uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Fog Uniform Buffer"),
+            contents: bytemuck::bytes_of(&FogUniform::from_settings(None)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let screen_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("screen_dimensions_bind_group_layout"),
+        });
+
+        let screen_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &screen_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: screen_uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: fog_uniform_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("screen_dimensions_bind_group"),
+        });
+
+        let render_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Renderer Pipeline Layout"),
+            bind_group_layouts: &[&screen_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let vertex_buffer = GrowableBuffer::new(
+            device, "Scene Vertex Buffer", wgpu::BufferUsages::VERTEX,
+            (RENDERER_INITIAL_VERTICES * std::mem::size_of::<Vertex>()) as u64,
+        );
+
+        let index_buffer = GrowableBuffer::new(
+            device, "Scene Index Buffer", wgpu::BufferUsages::INDEX,
+            (RENDERER_INITIAL_INDICES * std::mem::size_of::<u32>()) as u64,
+        );
+
+        let sky_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sky Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(WGSL_SKY_SHADER_SOURCE.into()),
+        });
+
+        let impostor_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Impostor Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(WGSL_IMPOSTOR_SHADER_SOURCE.into()),
+        });
+        let impostor_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("impostor_bind_group_layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let impostor_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Impostor Pipeline Layout"),
+            bind_group_layouts: &[&screen_bind_group_layout, &impostor_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipelines = ScenePipelines::new(
+            device, &render_pipeline_layout, &shader_module, &sky_shader_module,
+            &impostor_pipeline_layout, &impostor_shader_module,
+            HDR_FORMAT, supported_sample_count(settings.msaa_samples), settings.portal_masking == PortalMasking::Stencil,
+        );
+
+        let tonemap_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Tonemap Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(WGSL_TONEMAP_SHADER_SOURCE.into()),
+        });
+        let tonemap_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("tonemap_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let tonemap_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Tonemap Pipeline Layout"),
+            bind_group_layouts: &[&tonemap_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let tonemap_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Tonemap Pipeline"),
+            layout: Some(&tonemap_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &tonemap_shader_module,
+                entry_point: "vs_fullscreen",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &tonemap_shader_module,
+                entry_point: "fs_tonemap",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: surface_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        // The HDR target matches the output size, so nearest sampling is exact.
+        let tonemap_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Tonemap Sampler"),
+            ..Default::default()
+        });
+        // For an HDR target smaller than the output; see `RenderSettings::render_scale`.
+        let tonemap_upscale_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Tonemap Upscale Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let tonemap_uniform_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tonemap Uniform Buffer"),
+            size: std::mem::size_of::<TonemapUniform>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let sky_vertex_buffer = GrowableBuffer::new(
+            device, "Sky Vertex Buffer", wgpu::BufferUsages::VERTEX,
+            (RENDERER_INITIAL_SKY_VERTICES * std::mem::size_of::<SkyVertex>()) as u64,
+        );
+
+        let sky_index_buffer = GrowableBuffer::new(
+            device, "Sky Index Buffer", wgpu::BufferUsages::INDEX,
+            (RENDERER_INITIAL_SKY_INDICES * std::mem::size_of::<u32>()) as u64,
+        );
+
+        // Wireframe mode draws each triangle as three lines, so it needs twice the index room.
+        let line_index_buffer = GrowableBuffer::new(
+            device, "Wireframe Index Buffer", wgpu::BufferUsages::INDEX,
+            (2 * RENDERER_INITIAL_INDICES * std::mem::size_of::<u32>()) as u64,
+        );
+        let sky_line_index_buffer = GrowableBuffer::new(
+            device, "Sky Wireframe Index Buffer", wgpu::BufferUsages::INDEX,
+            (2 * RENDERER_INITIAL_SKY_INDICES * std::mem::size_of::<u32>()) as u64,
+        );
+
+        let overlay_vertex_buffer = GrowableBuffer::new(
+            device, "Debug Overlay Vertex Buffer", wgpu::BufferUsages::VERTEX,
+            (RENDERER_INITIAL_OVERLAY_VERTICES * std::mem::size_of::<Vertex>()) as u64,
+        );
+        let overlay_index_buffer = GrowableBuffer::new(
+            device, "Debug Overlay Index Buffer", wgpu::BufferUsages::INDEX,
+            (2 * RENDERER_INITIAL_OVERLAY_VERTICES * std::mem::size_of::<u32>()) as u64,
+        );
+
+        let mask_vertex_buffer = GrowableBuffer::new(
+            device, "Portal Mask Vertex Buffer", wgpu::BufferUsages::VERTEX,
+            (RENDERER_INITIAL_OVERLAY_VERTICES * std::mem::size_of::<Vertex>()) as u64,
+        );
+        let mask_index_buffer = GrowableBuffer::new(
+            device, "Portal Mask Index Buffer", wgpu::BufferUsages::INDEX,
+            (3 * RENDERER_INITIAL_OVERLAY_VERTICES * std::mem::size_of::<u32>()) as u64,
+        );
+        let impostor_vertex_buffer = GrowableBuffer::new(
+            device, "Impostor Vertex Buffer", wgpu::BufferUsages::VERTEX,
+            (MAX_VERTICES * 16 * std::mem::size_of::<Vertex>()) as u64,
+        );
+        let impostor_index_buffer = GrowableBuffer::new(
+            device, "Impostor Index Buffer", wgpu::BufferUsages::INDEX,
+            (MAX_VERTICES * 3 * 16 * std::mem::size_of::<u32>()) as u64,
+        );
+
+        Self {
+            pipelines,
+            pipeline_layout: render_pipeline_layout,
+            shader_module,
+            sky_shader_module,
+            target_format: surface_format,
+            msaa_target: None,
+            stencil_target: None,
+            hdr_target: None,
+            tonemap_pipeline,
+            tonemap_bind_group_layout,
+            tonemap_sampler,
+            tonemap_upscale_sampler,
+            tonemap_uniform_buffer,
+            impostor_pipeline_layout,
+            impostor_shader_module,
+            impostor_bind_group_layout,
+            impostor_target: None,
+            impostor_vertex_buffer,
+            impostor_index_buffer,
+            vertex_buffer,
+            index_buffer,
+            sky_vertex_buffer,
+            sky_index_buffer,
+            line_index_buffer,
+            sky_line_index_buffer,
+            frame_line_indices: Vec::new(),
+            overlay_vertex_buffer,
+            overlay_index_buffer,
+            mask_vertex_buffer,
+            mask_index_buffer,
+            staging_belt: StagingBelt::new(STAGING_BELT_CHUNK_SIZE),
+            screen_uniform_buffer,
+            fog_uniform_buffer,
+            screen_bind_group,
+        }
+    }
+
+    // Recompiles the scene and sky shaders and rebuilds their pipelines. On error, such as WGSL
+    // that does not compile or no longer matches the pipeline layout, the old pipelines stay in
+    // use and the error is returned.
+    pub fn reload_shaders(&mut self, device: &wgpu::Device, scene_source: &str, sky_source: &str) -> Result<(), String> {
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Renderer Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(scene_source.into()),
+        });
+        let sky_shader_module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Sky Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(sky_source.into()),
+        });
+        let pipelines = ScenePipelines::new(
+            device, &self.pipeline_layout, &shader_module, &sky_shader_module,
+            &self.impostor_pipeline_layout, &self.impostor_shader_module,
+            HDR_FORMAT, self.pipelines.sample_count, self.pipelines.stencil(),
+        );
+        if let Some(error) = pop_error_scope_now(device) {
+            return Err(error.to_string());
+        }
+        self.shader_module = shader_module;
+        self.sky_shader_module = sky_shader_module;
+        self.pipelines = pipelines;
+        Ok(())
+    }
+
+    // Rebuilds the pipelines if the requested MSAA sample count or portal masking changed and
+    // (re)creates the HDR, multisampled color and stencil targets to match the output size.
+    fn prepare_sample_targets(&mut self, device: &wgpu::Device, settings: &RenderSettings, width: u32, height: u32, upscaled: bool) {
+        let (width, height) = (width.max(1), height.max(1));
+        let hdr_matches = self.hdr_target.as_ref().is_some_and(|target| {
+            target.width == width && target.height == height && target.upscaled == upscaled
+        });
+        if !hdr_matches {
+            self.hdr_target = Some(self.create_hdr_target(device, width, height, upscaled));
+        }
+
+        let sample_count = supported_sample_count(settings.msaa_samples);
+        let stencil = settings.portal_masking == PortalMasking::Stencil;
+        if self.pipelines.sample_count != sample_count || self.pipelines.stencil() != stencil {
+            self.pipelines = ScenePipelines::new(
+                device, &self.pipeline_layout, &self.shader_module, &self.sky_shader_module,
+                &self.impostor_pipeline_layout, &self.impostor_shader_module,
+                HDR_FORMAT, sample_count, stencil,
+            );
+        }
+        if !stencil {
+            self.stencil_target = None;
+        } else if !self.stencil_target.as_ref().is_some_and(|target| {
+            target.width == width && target.height == height && target.sample_count == sample_count
+        }) {
+            self.stencil_target = Some(StencilTarget::new(device, width, height, sample_count));
+        }
+        if sample_count == 1 {
+            self.msaa_target = None;
+            return;
+        }
+        let target_matches = self.msaa_target.as_ref().is_some_and(|target| {
+            target.width == width && target.height == height && target.sample_count == sample_count
+        });
+        if !target_matches {
+            self.msaa_target = Some(MsaaTarget::new(device, HDR_FORMAT, width, height, sample_count));
+        }
+    }
+
+    fn create_hdr_target(&self, device: &wgpu::Device, width: u32, height: u32, upscaled: bool) -> HdrTarget {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("HDR Color Target"),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("tonemap_bind_group"),
+            layout: &self.tonemap_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(if upscaled { &self.tonemap_upscale_sampler } else { &self.tonemap_sampler }),
+                },
+                wgpu::BindGroupEntry { binding: 2, resource: self.tonemap_uniform_buffer.as_entire_binding() },
+            ],
+        });
+        HdrTarget { texture, view, bind_group, width, height, upscaled }
+    }
+
+    // A `RenderBackend` drawing into `output_view`, tonemapped, with `encoder`'s passes. The
+    // frame's geometry is uploaded by copies on `encoder`, which has to be submitted before the
+    // next frame is drawn with this backend.
+    pub fn with_target<'a>(
+        &'a mut self,
+        device: &'a wgpu::Device,
+        queue: &'a wgpu::Queue,
+        encoder: &'a mut wgpu::CommandEncoder,
+        output_view: &'a wgpu::TextureView,
+    ) -> WgpuFrameTarget<'a> {
+        WgpuFrameTarget { backend: self, device, queue, encoder, output_view, gpu_timer: None }
+    }
+}
+
+pub struct WgpuFrameTarget<'a> {
+    backend: &'a mut WgpuBackend,
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+    encoder: &'a mut wgpu::CommandEncoder,
+    output_view: &'a wgpu::TextureView,
+    gpu_timer: Option<&'a mut GpuTimer>,
+}
+
+impl<'a> WgpuFrameTarget<'a> {
+    // Writes timestamps around the scene and tonemap passes with `gpu_timer`, if given.
+    pub fn timed(self, gpu_timer: Option<&'a mut GpuTimer>) -> Self {
+        Self { gpu_timer, ..self }
+    }
+}
+
+impl RenderBackend for WgpuFrameTarget<'_> {
+    fn submit_frame(&mut self, frame: &FrameGeometry, settings: &RenderSettings) {
+        let (device, queue, output_view) = (self.device, self.queue, self.output_view);
+        let encoder = &mut *self.encoder;
+        let backend = &mut *self.backend;
+        let clear_color = wgpu::Color {
+            r: frame.clear_color[0] as f64, g: frame.clear_color[1] as f64, b: frame.clear_color[2] as f64, a: frame.clear_color[3] as f64,
+        };
+        // Spans time the CPU side of recording and uploading; the GPU runs the passes later.
+        let upload_span = tracing::info_span!("upload").entered();
+        // The previous frame's encoder has been submitted, so its staging chunks can be reused
+        // once the GPU is done with them.
+        backend.staging_belt.recall();
+        let screen_uniform_data = ScreenDimensionsUniform {
+            width: frame.width,
+            height: frame.height,
+            _padding1: 0.0,
+            _padding2: 0.0,
+        };
+        queue.write_buffer(&backend.screen_uniform_buffer, 0, bytemuck::bytes_of(&screen_uniform_data));
+        queue.write_buffer(&backend.fog_uniform_buffer, 0, bytemuck::bytes_of(&FogUniform::from_settings(frame.fog)));
+
+        let wireframe = settings.wireframe;
+        // Line lists hold two indices per triangle index, in the same order.
+        let index_scale = if wireframe { 2 } else { 1 };
+
+        let draw_scene_geometry = !frame.vertices.is_empty() && !frame.indices.is_empty();
+        let mut scene_index_count = frame.indices.len() as u32;
+        if draw_scene_geometry {
+            backend.vertex_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(frame.vertices));
+            if wireframe {
+                triangle_edges_into(frame.indices, &mut backend.frame_line_indices);
+                backend.line_index_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(&backend.frame_line_indices));
+                scene_index_count = backend.frame_line_indices.len() as u32;
+            } else {
+                backend.index_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(frame.indices));
+            }
+        }
+
+        let draw_sky_geometry = !frame.sky_vertices.is_empty() && !frame.sky_indices.is_empty();
+        let mut sky_index_count = frame.sky_indices.len() as u32;
+        if draw_sky_geometry {
+            backend.sky_vertex_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(frame.sky_vertices));
+            if wireframe {
+                triangle_edges_into(frame.sky_indices, &mut backend.frame_line_indices);
+                backend.sky_line_index_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(&backend.frame_line_indices));
+                sky_index_count = backend.frame_line_indices.len() as u32;
+            } else {
+                backend.sky_index_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(frame.sky_indices));
+            }
+        }
+
+        if !frame.overlay_indices.is_empty() {
+            backend.overlay_vertex_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(frame.overlay_vertices));
+            backend.overlay_index_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(frame.overlay_indices));
+        }
+
+        if !frame.mask_indices.is_empty() {
+            backend.mask_vertex_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(frame.mask_vertices));
+            backend.mask_index_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(frame.mask_indices));
+        }
+
+        // Below full scale the target is smaller than the output and scaled up by the tonemap pass.
+        let upscaled = settings.render_scale.clamp(MIN_RENDER_SCALE, 1.0) < 1.0;
+        backend.prepare_sample_targets(device, settings, frame.width as u32, frame.height as u32, upscaled);
+        let draw_impostors = !frame.impostor_indices.is_empty() && backend.impostor_target.as_ref()
+            .is_some_and(|target| backend.hdr_target.as_ref().is_some_and(|hdr| hdr.width == target.width && hdr.height == target.height));
+        if draw_impostors {
+            backend.impostor_vertex_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(frame.impostor_vertices));
+            backend.impostor_index_buffer.write_staged(device, encoder, &mut backend.staging_belt, bytemuck::cast_slice(frame.impostor_indices));
+        }
+        backend.staging_belt.finish();
+        drop(upload_span);
+        let hdr_target = match &backend.hdr_target {
+            Some(hdr_target) => hdr_target,
+            None => return,
+        };
+        let (attachment_view, resolve_target) = match &backend.msaa_target {
+            Some(msaa_target) => (&msaa_target.view, Some(&hdr_target.view)),
+            None => (&hdr_target.view, None),
+        };
+        let scene_pass_span = tracing::info_span!("scene_pass").entered();
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Scene Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: attachment_view,
+                resolve_target,
+                // The multisampled texture is only needed until it has been resolved.
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: if resolve_target.is_some() { wgpu::StoreOp::Discard } else { wgpu::StoreOp::Store },
+                },
+            })],
+            depth_stencil_attachment: backend.stencil_target.as_ref().map(|stencil_target| wgpu::RenderPassDepthStencilAttachment {
+                view: &stencil_target.view,
+                depth_ops: None,
+                stencil_ops: Some(wgpu::Operations { load: wgpu::LoadOp::Clear(0), store: wgpu::StoreOp::Discard }),
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: self.gpu_timer.as_deref_mut().and_then(|gpu_timer| gpu_timer.timestamp_writes(GpuPass::Scene)),
+        });
+        render_pass.set_bind_group(0, &backend.screen_bind_group, &[]);
+
+        // Everything else drawn over an impostor region is in front of the portal, so impostors
+        // go first.
+        if let Some(impostor_target) = backend.impostor_target.as_ref().filter(|_| draw_impostors) {
+            render_pass.set_pipeline(&backend.pipelines.impostor);
+            render_pass.set_bind_group(1, &impostor_target.bind_group, &[]);
+            let impostor_vertex_slice_size = std::mem::size_of_val(frame.impostor_vertices) as u64;
+            render_pass.set_vertex_buffer(0, backend.impostor_vertex_buffer.slice(impostor_vertex_slice_size));
+            let impostor_index_slice_size = std::mem::size_of_val(frame.impostor_indices) as u64;
+            render_pass.set_index_buffer(backend.impostor_index_buffer.slice(impostor_index_slice_size), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..frame.impostor_indices.len() as u32, 0, 0..1);
+        }
+
+        let scene_index_buffer = if wireframe { &backend.line_index_buffer } else { &backend.index_buffer };
+        let (sky_pipeline, sky_index_buffer) = if wireframe {
+            (&backend.pipelines.sky_wireframe, &backend.sky_line_index_buffer)
+        } else {
+            (&backend.pipelines.sky, &backend.sky_index_buffer)
+        };
+        let stencil = backend.pipelines.stencil() && backend.stencil_target.is_some();
+        for draw in frame.draws {
+            let (scene_range, sky_range, stencil_reference, material) = match draw {
+                FrameDraw::Geometry { scene, sky, stencil_reference, material } => (scene, sky, *stencil_reference, material),
+                FrameDraw::PortalMask { indices, stencil_reference, increment } => {
+                    if let Some([mask_increment, mask_decrement]) = backend.pipelines.portal_masks.as_ref().filter(|_| stencil && !indices.is_empty()) {
+                        render_pass.set_pipeline(if *increment { mask_increment } else { mask_decrement });
+                        render_pass.set_stencil_reference(*stencil_reference);
+                        let mask_vertex_slice_size = std::mem::size_of_val(frame.mask_vertices) as u64;
+                        render_pass.set_vertex_buffer(0, backend.mask_vertex_buffer.slice(mask_vertex_slice_size));
+                        let mask_index_slice_size = std::mem::size_of_val(frame.mask_indices) as u64;
+                        render_pass.set_index_buffer(backend.mask_index_buffer.slice(mask_index_slice_size), wgpu::IndexFormat::Uint32);
+                        render_pass.draw_indexed(indices.clone(), 0, 0..1);
+                    }
+                    continue;
+                }
+            };
+            if stencil {
+                render_pass.set_stencil_reference(stencil_reference);
+            }
+            if draw_scene_geometry && !scene_range.is_empty() {
+                render_pass.set_pipeline(match (wireframe, material.blend) {
+                    (true, _) => &backend.pipelines.scene_wireframe,
+                    (false, BlendMode::Opaque) => &backend.pipelines.scene,
+                    (false, BlendMode::Alpha) => &backend.pipelines.scene_alpha,
+                });
+                let vertex_buffer_slice_size = std::mem::size_of_val(frame.vertices) as u64;
+                render_pass.set_vertex_buffer(0, backend.vertex_buffer.slice(vertex_buffer_slice_size));
+                let index_buffer_slice_size = scene_index_count as u64 * std::mem::size_of::<u32>() as u64;
+                render_pass.set_index_buffer(scene_index_buffer.slice(index_buffer_slice_size), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(scene_range.start * index_scale..scene_range.end * index_scale, 0, 0..1);
+            }
+
+            // Within a view, sky and scene geometry cover disjoint screen regions, so their order doesn't matter.
+            if draw_sky_geometry && !sky_range.is_empty() {
+                render_pass.set_pipeline(sky_pipeline);
+                let sky_vertex_slice_size = std::mem::size_of_val(frame.sky_vertices) as u64;
+                render_pass.set_vertex_buffer(0, backend.sky_vertex_buffer.slice(sky_vertex_slice_size));
+                let sky_index_slice_size = sky_index_count as u64 * std::mem::size_of::<u32>() as u64;
+                render_pass.set_index_buffer(sky_index_buffer.slice(sky_index_slice_size), wgpu::IndexFormat::Uint32);
+                render_pass.draw_indexed(sky_range.start * index_scale..sky_range.end * index_scale, 0, 0..1);
+            }
+        }
+
+        // Debug overlays go on top of everything else.
+        if stencil {
+            render_pass.set_stencil_reference(0);
+        }
+        if !frame.overlay_indices.is_empty() {
+            render_pass.set_pipeline(&backend.pipelines.scene_wireframe);
+            let overlay_vertex_slice_size = std::mem::size_of_val(frame.overlay_vertices) as u64;
+            render_pass.set_vertex_buffer(0, backend.overlay_vertex_buffer.slice(overlay_vertex_slice_size));
+            let overlay_index_slice_size = std::mem::size_of_val(frame.overlay_indices) as u64;
+            render_pass.set_index_buffer(backend.overlay_index_buffer.slice(overlay_index_slice_size), wgpu::IndexFormat::Uint32);
+            render_pass.draw_indexed(0..frame.overlay_indices.len() as u32, 0, 0..1);
+        }
+        drop(render_pass);
+        drop(scene_pass_span);
+
+        if frame.capture_impostor {
+            let (width, height) = (hdr_target.width, hdr_target.height);
+            if !backend.impostor_target.as_ref().is_some_and(|target| target.width == width && target.height == height) {
+                backend.impostor_target = Some(create_impostor_target(device, &backend.impostor_bind_group_layout, width, height));
+            }
+            if let Some(impostor_target) = &backend.impostor_target {
+                encoder.copy_texture_to_texture(
+                    hdr_target.texture.as_image_copy(),
+                    impostor_target.texture.as_image_copy(),
+                    wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+                );
+            }
+        }
+
+        let tonemap_span = tracing::info_span!("tonemap_pass").entered();
+        let tonemap_uniform = TonemapUniform {
+            exposure: settings.exposure.max(0.0),
+            curve: match settings.tonemap {
+                TonemapCurve::Clamp => 0,
+                TonemapCurve::Reinhard => 1,
+                TonemapCurve::Aces => 2,
+            },
+            apply_gamma: (!backend.target_format.is_srgb()) as u32,
+            bloom_threshold: settings.bloom.map_or(0.0, |bloom| bloom.threshold),
+            bloom_intensity: settings.bloom.map_or(0.0, |bloom| bloom.intensity.max(0.0)),
+            bloom_step: settings.bloom.map_or(0.0, |bloom| bloom.tap_step()),
+            _padding: [0; 2],
+        };
+        queue.write_buffer(&backend.tonemap_uniform_buffer, 0, bytemuck::bytes_of(&tonemap_uniform));
+        let mut tonemap_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tonemap Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: self.gpu_timer.as_deref_mut().and_then(|gpu_timer| gpu_timer.timestamp_writes(GpuPass::Tonemap)),
+        });
+        tonemap_pass.set_pipeline(&backend.tonemap_pipeline);
+        tonemap_pass.set_bind_group(0, &hdr_target.bind_group, &[]);
+        tonemap_pass.draw(0..3, 0..1);
+        drop(tonemap_pass);
+        drop(tonemap_span);
+    }
+}
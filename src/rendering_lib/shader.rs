@@ -1,49 +1,26 @@
-// src/shader.rs
-
-pub const WGSL_SHADER_SOURCE: &str = r#"
-struct ScreenDimensions {
-    width: f32,
-    height: f32,
-    //_padding1: f32, // Add padding if needed for 16-byte alignment for webgl2
-    //_padding2: f32,
-}
-
-@group(0) @binding(0)
-var<uniform> screen: ScreenDimensions;
-
-struct VertexInput {
-    @location(0) position: vec2<f32>, // These are screen-space coordinates
-    @location(1) color: vec4<f32>,
-}
-
-struct VertexOutput {
-    @builtin(position) clip_position: vec4<f32>, // Output to Normalized Device Coordinates
-    @location(0) color: vec4<f32>,
-}
-
-@vertex
-fn vs_main(model: VertexInput) -> VertexOutput {
-    var out: VertexOutput;
-    out.color = model.color;
-
-    // Convert screen coordinates (model.position) to NDC
-    // model.position.x is in [0, screen.width]
-    // model.position.y is in [0, screen.height] (origin top-left)
-    
-    let normalized_x = (model.position.x / (screen.width / 2.0)) - 1.0;
-    // For normalized_y, typical NDC has +Y up. Screen coords often have +Y down.
-    // If model.position.y is 0 at top and screen.height at bottom:
-    // (model.position.y / (screen.height / 2.0)) gives [0, 2]
-    // 1.0 - ... maps [0, 2] to [1.0, -1.0] (correct for NDC Y up)
-    let normalized_y = 1.0 - (model.position.y / (screen.height / 2.0)); 
-    
-    out.clip_position = vec4<f32>(normalized_x, normalized_y, 0.0, 1.0);
-    
-    return out;
-}
-
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
-}
-"#;
\ No newline at end of file
+// src/shader.rs
+
+// The shaders live in assets/shaders. Builds embed them, and development builds can reload them
+// from disk (see `Renderer::reload_shaders`).
+pub const SCENE_SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/shaders/scene.wgsl");
+pub const SKY_SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/shaders/sky.wgsl");
+
+pub const WGSL_SHADER_SOURCE: &str = include_str!("../../assets/shaders/scene.wgsl");
+
+// Procedural gradient used by the skybox handler. Directions are interpolated linearly in
+// screen space (w = 1), which is exact because the view ray is affine in screen coordinates.
+pub const WGSL_SKY_SHADER_SOURCE: &str = include_str!("../../assets/shaders/sky.wgsl");
+
+// Fullscreen post pass mapping the HDR scene target to the output. `curve`: 0 = clamp,
+// 1 = Reinhard, 2 = ACES (Narkowicz fit). `apply_gamma` is set when the output format is not
+// sRGB, so the encode has to happen here instead of in the hardware.
+pub const WGSL_TONEMAP_SHADER_SOURCE: &str = include_str!("../../assets/shaders/tonemap.wgsl");
+
+// Fills portal regions from the impostor capture, pixel for pixel.
+pub const WGSL_IMPOSTOR_SHADER_SOURCE: &str = include_str!("../../assets/shaders/impostor.wgsl");
+
+// Compute pass clipping hull sides in batches; see `GpuSideClipper`.
+pub const WGSL_SIDE_CLIP_SHADER_SOURCE: &str = include_str!("../../assets/shaders/side_clip.wgsl");
+
+// Shows a frame from `SoftwareRenderer` on the output.
+pub const WGSL_BLIT_SHADER_SOURCE: &str = include_str!("../../assets/shaders/blit.wgsl");
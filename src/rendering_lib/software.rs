@@ -0,0 +1,541 @@
+// src/rendering_lib/software.rs
+
+use super::material::BlendMode;
+use super::renderer::{triangle_edges_into, BloomSettings, BLOOM_TAP_REACH, FrameDraw, FrameGeometry, PortalMasking, RenderBackend, RenderSettings, RenderStats, SceneTraversal, SceneView, ScreenLabel, TonemapCurve, Viewport};
+use super::shader::WGSL_BLIT_SHADER_SOURCE;
+use super::vertex::{SkyVertex, Vertex};
+use crate::engine_lib::camera::Camera;
+use crate::engine_lib::scene_types::{FogSettings, RenderLayerMask, Scene};
+use crate::engine_lib::side_handler::SideHandlerRegistry;
+
+// Draws the same frames as `Renderer` entirely on the CPU, into an RGBA8 pixel buffer: the
+// traversal's triangles are filled one pixel at a time with the GPU's fill rule, fog, blending and
+// tonemapping. Slow, but it runs anywhere and serves as a reference for the GPU path.
+// MSAA, `render_scale`, impostors, stencil masking and GPU side clipping are ignored.
+pub struct SoftwareRenderer {
+    pub settings: RenderSettings,
+    traversal: SceneTraversal,
+    backend: SoftwareBackend,
+}
+
+impl Default for SoftwareRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SoftwareRenderer {
+    pub fn new() -> Self {
+        Self {
+            settings: RenderSettings::default(),
+            traversal: SceneTraversal::new(),
+            backend: SoftwareBackend::new(),
+        }
+    }
+
+    // The last frame, laid out like `HeadlessRenderer::render`'s.
+    pub fn pixels(&self) -> &[u8] {
+        self.backend.pixels()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.backend.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.backend.height()
+    }
+
+    // Debug labels visible in the last rendered frame, for the UI to draw on top.
+    pub fn frame_labels(&self) -> &[ScreenLabel] {
+        self.traversal.labels()
+    }
+
+    // Where user-defined side handlers are registered; see `SideHandlerRegistry`.
+    pub fn side_handlers_mut(&mut self) -> &mut SideHandlerRegistry {
+        self.traversal.side_handlers_mut()
+    }
+
+    // See `Renderer::render_scene`.
+    pub fn render_scene(
+        &mut self,
+        scene: &Scene,
+        camera: &Camera,
+        screen_width: f32,
+        screen_height: f32,
+        clear_color: wgpu::Color,
+        layer_filter: RenderLayerMask,
+    ) -> RenderStats {
+        let view = SceneView::from_scene_camera(scene, &scene.active_camera, camera, Viewport::full(screen_width, screen_height));
+        let views: &[SceneView] = match &view {
+            Some(view) => std::slice::from_ref(view),
+            None => &[],
+        };
+        self.render_views(scene, views, screen_width, screen_height, clear_color, layer_filter).pop().unwrap_or_default()
+    }
+
+    // See `Renderer::render_views`.
+    pub fn render_views(
+        &mut self,
+        scene: &Scene,
+        views: &[SceneView],
+        screen_width: f32,
+        screen_height: f32,
+        clear_color: wgpu::Color,
+        layer_filter: RenderLayerMask,
+    ) -> Vec<RenderStats> {
+        let clear_color = match &scene.fog {
+            Some(fog) => fog.color,
+            None => [clear_color.r as f32, clear_color.g as f32, clear_color.b as f32, clear_color.a as f32],
+        };
+        let settings = RenderSettings {
+            portal_masking: PortalMasking::Clipping,
+            impostors: None,
+            gpu_side_clipping: false,
+            gpu_timing: false,
+            ..self.settings.clone()
+        };
+        let stats = self.traversal.build_frame(scene, views, &settings, clear_color, layer_filter);
+        self.backend.submit_frame(&self.traversal.frame(screen_width, screen_height, clear_color, scene.fog.as_ref()), &settings);
+        stats
+    }
+}
+
+// The rasterizer behind `SoftwareRenderer`, as a `RenderBackend`. Portal masks and impostor regions
+// are skipped, so frames should be traversed with `PortalMasking::Clipping` and no impostors.
+pub struct SoftwareBackend {
+    // Linear colors, as the GPU's HDR target holds them.
+    color: Vec<[f32; 4]>,
+    // The tonemapped frame as sRGB-encoded RGBA8, top row first.
+    pixels: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+impl Default for SoftwareBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SoftwareBackend {
+    pub fn new() -> Self {
+        Self { color: Vec::new(), pixels: Vec::new(), width: 0, height: 0 }
+    }
+
+    // The last frame, laid out like `HeadlessRenderer::render`'s.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+}
+
+impl RenderBackend for SoftwareBackend {
+    fn submit_frame(&mut self, frame: &FrameGeometry, settings: &RenderSettings) {
+        let _span = tracing::info_span!("rasterize").entered();
+        self.width = (frame.width as u32).max(1);
+        self.height = (frame.height as u32).max(1);
+        self.color.clear();
+        self.color.resize((self.width * self.height) as usize, frame.clear_color);
+        let mut target = ColorTarget { color: &mut self.color, width: self.width, height: self.height };
+        let fog = frame.fog.filter(|fog| fog.density > 0.0);
+
+        let mut line_indices = Vec::new();
+        for draw in frame.draws {
+            let FrameDraw::Geometry { scene: scene_range, sky: sky_range, material, .. } = draw else { continue };
+            let indices = &frame.indices[scene_range.start as usize..scene_range.end as usize];
+            let vertices = frame.vertices;
+            if settings.wireframe {
+                triangle_edges_into(indices, &mut line_indices);
+                for line in line_indices.chunks_exact(2) {
+                    target.draw_scene_line(&vertices[line[0] as usize], &vertices[line[1] as usize], fog);
+                }
+            } else {
+                for triangle in indices.chunks_exact(3) {
+                    target.fill_scene_triangle(triangle.iter().map(|&index| &vertices[index as usize]), fog, material.blend);
+                }
+            }
+
+            let sky_indices = &frame.sky_indices[sky_range.start as usize..sky_range.end as usize];
+            let sky_vertices = frame.sky_vertices;
+            if settings.wireframe {
+                triangle_edges_into(sky_indices, &mut line_indices);
+                for line in line_indices.chunks_exact(2) {
+                    target.draw_sky_line(&sky_vertices[line[0] as usize], &sky_vertices[line[1] as usize]);
+                }
+            } else {
+                for triangle in sky_indices.chunks_exact(3) {
+                    target.fill_sky_triangle(triangle.iter().map(|&index| &sky_vertices[index as usize]));
+                }
+            }
+        }
+
+        // Debug overlays go on top of everything else.
+        let overlay_vertices = frame.overlay_vertices;
+        for line in frame.overlay_indices.chunks_exact(2) {
+            target.draw_scene_line(&overlay_vertices[line[0] as usize], &overlay_vertices[line[1] as usize], fog);
+        }
+
+        if let Some(bloom_settings) = settings.bloom.filter(|bloom| bloom.intensity > 0.0) {
+            self.color = bloom(&self.color, self.width, self.height, &bloom_settings);
+        }
+        let exposure = settings.exposure.max(0.0);
+        self.pixels.clear();
+        self.pixels.extend(self.color.iter().flat_map(|color| tonemap(*color, exposure, settings.tonemap)));
+    }
+}
+
+// Vertex positions are rounded to 1/256 pixel, the usual GPU subpixel precision.
+const SUBPIXEL_STEPS: f64 = 256.0;
+
+struct ColorTarget<'a> {
+    color: &'a mut [[f32; 4]],
+    width: u32,
+    height: u32,
+}
+
+impl ColorTarget<'_> {
+    // `wgpu::BlendState::ALPHA_BLENDING`.
+    fn blend(&mut self, x: u32, y: u32, source: [f32; 4]) {
+        let destination = &mut self.color[(y * self.width + x) as usize];
+        let alpha = source[3];
+        for channel in 0..3 {
+            destination[channel] = source[channel] * alpha + destination[channel] * (1.0 - alpha);
+        }
+        destination[3] = alpha + destination[3] * (1.0 - alpha);
+    }
+
+    // `blend` for `BlendMode::Alpha`, `wgpu::BlendState::REPLACE` for `BlendMode::Opaque`.
+    fn write(&mut self, x: u32, y: u32, source: [f32; 4], blend: BlendMode) {
+        match blend {
+            BlendMode::Opaque => self.color[(y * self.width + x) as usize] = source,
+            BlendMode::Alpha => self.blend(x, y, source),
+        }
+    }
+
+    // The pixels whose centers are covered by the triangle `corners`, with the barycentric weight
+    // of each corner at the center. Pixel centers on an edge belong to the triangle only if the
+    // edge is a top or left one, as on the GPU, so triangles sharing an edge never both draw a pixel.
+    fn rasterize(&mut self, corners: [[f32; 2]; 3], mut shade: impl FnMut(&mut Self, u32, u32, [f64; 3])) {
+        // Snapped to the subpixel grid first, like the GPU, so edges through pixel centers go the
+        // same way.
+        let snap = |coordinate: f32| (coordinate as f64 * SUBPIXEL_STEPS).round() / SUBPIXEL_STEPS;
+        let corners = corners.map(|[x, y]| [snap(x), snap(y)]);
+        let edge = |from: [f64; 2], to: [f64; 2], x: f64, y: f64| (to[0] - from[0]) * (y - from[1]) - (to[1] - from[1]) * (x - from[0]);
+        let area = edge(corners[0], corners[1], corners[2][0], corners[2][1]);
+        if area == 0.0 {
+            return;
+        }
+        // Corners in the winding with positive area; with y pointing down, a top edge then runs
+        // in +x and a left edge in -y.
+        let order = if area > 0.0 { [0, 1, 2] } else { [0, 2, 1] };
+        let [a, b, c] = order.map(|corner| corners[corner]);
+        let area = area.abs();
+        let top_left = |from: [f64; 2], to: [f64; 2]| (to[1] == from[1] && to[0] > from[0]) || to[1] < from[1];
+        // Each edge is opposite the corner its weight belongs to.
+        let edges = [(b, c), (c, a), (a, b)];
+        let owns = edges.map(|(from, to)| top_left(from, to));
+
+        let min_x = a[0].min(b[0]).min(c[0]).floor().max(0.0) as u32;
+        let min_y = a[1].min(b[1]).min(c[1]).floor().max(0.0) as u32;
+        let max_x = (a[0].max(b[0]).max(c[0]).ceil().max(0.0) as u32).min(self.width);
+        let max_y = (a[1].max(b[1]).max(c[1]).ceil().max(0.0) as u32).min(self.height);
+        for y in min_y..max_y {
+            let center_y = y as f64 + 0.5;
+            for x in min_x..max_x {
+                let center_x = x as f64 + 0.5;
+                let weights = edges.map(|(from, to)| edge(from, to, center_x, center_y));
+                let inside = weights.iter().zip(&owns).all(|(&weight, &owns)| weight > 0.0 || (weight == 0.0 && owns));
+                if inside {
+                    let mut by_corner = [0.0; 3];
+                    for (slot, &corner) in order.iter().enumerate() {
+                        by_corner[corner] = weights[slot] / area;
+                    }
+                    shade(self, x, y, by_corner);
+                }
+            }
+        }
+    }
+
+    // The pixels along the segment from `from` to `to`, with how far along it each one is.
+    fn rasterize_line(&mut self, from: [f32; 2], to: [f32; 2], mut shade: impl FnMut(&mut Self, u32, u32, f32)) {
+        let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+        let steps = dx.abs().max(dy.abs()).ceil().max(1.0) as u32;
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let (x, y) = ((from[0] + dx * t).floor(), (from[1] + dy * t).floor());
+            if x >= 0.0 && y >= 0.0 && (x as u32) < self.width && (y as u32) < self.height {
+                shade(self, x as u32, y as u32, t);
+            }
+        }
+    }
+
+    // `vs_main`/`fs_main`: color and depth are interpolated perspective-correctly, with the
+    // camera-space depth as w.
+    fn fill_scene_triangle<'v>(&mut self, corners: impl Iterator<Item = &'v Vertex>, fog: Option<&FogSettings>, blend: BlendMode) {
+        let corners: Vec<&Vertex> = corners.collect();
+        let [a, b, c] = [corners[0], corners[1], corners[2]];
+        let inverse_w = [a, b, c].map(|vertex| 1.0 / vertex.depth.max(1e-4) as f64);
+        self.rasterize([a.position, b.position, c.position], |target, x, y, weights| {
+            let perspective = [0, 1, 2].map(|corner| weights[corner] * inverse_w[corner]);
+            let total: f64 = perspective.iter().sum();
+            let lerp = |values: [f32; 3]| (0..3).map(|corner| perspective[corner] * values[corner] as f64).sum::<f64>() / total;
+            let color = [0, 1, 2, 3].map(|channel| lerp([a.color[channel], b.color[channel], c.color[channel]]) as f32);
+            let depth = lerp([a.depth, b.depth, c.depth]) as f32;
+            target.write(x, y, apply_fog(color, depth, fog), blend);
+        });
+    }
+
+    fn draw_scene_line(&mut self, from: &Vertex, to: &Vertex, fog: Option<&FogSettings>) {
+        self.rasterize_line(from.position, to.position, |target, x, y, t| {
+            let color = [0, 1, 2, 3].map(|channel| from.color[channel] + (to.color[channel] - from.color[channel]) * t);
+            target.blend(x, y, apply_fog(color, from.depth + (to.depth - from.depth) * t, fog));
+        });
+    }
+
+    // `vs_sky`/`fs_sky`: everything is interpolated linearly on screen.
+    fn fill_sky_triangle<'v>(&mut self, corners: impl Iterator<Item = &'v SkyVertex>) {
+        let corners: Vec<&SkyVertex> = corners.collect();
+        let [a, b, c] = [corners[0], corners[1], corners[2]];
+        self.rasterize([a.position, b.position, c.position], |target, x, y, weights| {
+            let lerp = |values: [f32; 3]| (0..3).map(|corner| weights[corner] * values[corner] as f64).sum::<f64>() as f32;
+            let sky = SkyVertex {
+                position: [x as f32, y as f32],
+                direction: [0, 1, 2].map(|axis| lerp([a.direction[axis], b.direction[axis], c.direction[axis]])),
+                zenith_color: [0, 1, 2, 3].map(|channel| lerp([a.zenith_color[channel], b.zenith_color[channel], c.zenith_color[channel]])),
+                horizon_color: [0, 1, 2, 3].map(|channel| lerp([a.horizon_color[channel], b.horizon_color[channel], c.horizon_color[channel]])),
+                nadir_color: [0, 1, 2, 3].map(|channel| lerp([a.nadir_color[channel], b.nadir_color[channel], c.nadir_color[channel]])),
+            };
+            target.blend(x, y, sky_color(&sky));
+        });
+    }
+
+    fn draw_sky_line(&mut self, from: &SkyVertex, to: &SkyVertex) {
+        self.rasterize_line(from.position, to.position, |target, x, y, t| {
+            let lerp = |from: f32, to: f32| from + (to - from) * t;
+            let sky = SkyVertex {
+                position: [x as f32, y as f32],
+                direction: [0, 1, 2].map(|axis| lerp(from.direction[axis], to.direction[axis])),
+                zenith_color: [0, 1, 2, 3].map(|channel| lerp(from.zenith_color[channel], to.zenith_color[channel])),
+                horizon_color: [0, 1, 2, 3].map(|channel| lerp(from.horizon_color[channel], to.horizon_color[channel])),
+                nadir_color: [0, 1, 2, 3].map(|channel| lerp(from.nadir_color[channel], to.nadir_color[channel])),
+            };
+            target.blend(x, y, sky_color(&sky));
+        });
+    }
+}
+
+fn apply_fog(color: [f32; 4], depth: f32, fog: Option<&FogSettings>) -> [f32; 4] {
+    let fog = match fog {
+        Some(fog) => fog,
+        None => return color,
+    };
+    let fog_amount = fog.density * depth;
+    let visibility = (-fog_amount * fog_amount).exp().clamp(0.0, 1.0);
+    let mix = |fog: f32, color: f32| fog + (color - fog) * visibility;
+    [mix(fog.color[0], color[0]), mix(fog.color[1], color[1]), mix(fog.color[2], color[2]), color[3]]
+}
+
+fn sky_color(sky: &SkyVertex) -> [f32; 4] {
+    let [x, y, z] = sky.direction;
+    let length = (x * x + y * y + z * z).sqrt();
+    let elevation = if length > 0.0 { y / length } else { 0.0 };
+    let (toward, amount) = if elevation >= 0.0 {
+        (sky.zenith_color, elevation.powf(0.6))
+    } else {
+        (sky.nadir_color, (-elevation).powf(0.6))
+    };
+    [0, 1, 2, 3].map(|channel| sky.horizon_color[channel] + (toward[channel] - sky.horizon_color[channel]) * amount)
+}
+
+// The bloom of `fs_tonemap` added to each pixel of the `width` by `height` target `color`.
+fn bloom(color: &[[f32; 4]], width: u32, height: u32, settings: &BloomSettings) -> Vec<[f32; 4]> {
+    let step = settings.tap_step();
+    let taps: Vec<(i64, i64, f32)> = (-BLOOM_TAP_REACH..=BLOOM_TAP_REACH)
+        .flat_map(|j| (-BLOOM_TAP_REACH..=BLOOM_TAP_REACH).map(move |i| (i, j)))
+        .map(|(i, j)| ((i as f32 * step + 0.5).floor() as i64, (j as f32 * step + 0.5).floor() as i64, BloomSettings::tap_weight(i, j)))
+        .collect();
+    let total_weight: f32 = taps.iter().map(|tap| tap.2).sum();
+    let excess: Vec<[f32; 3]> = color.iter().map(|texel| [0, 1, 2].map(|channel| (texel[channel] - settings.threshold).max(0.0))).collect();
+    let (width, height) = (width as i64, height as i64);
+    let mut bloomed = color.to_vec();
+    for y in 0..height {
+        for x in 0..width {
+            let mut total = [0.0f32; 3];
+            for &(dx, dy, weight) in &taps {
+                let tap = &excess[((y + dy).clamp(0, height - 1) * width + (x + dx).clamp(0, width - 1)) as usize];
+                for channel in 0..3 {
+                    total[channel] += tap[channel] * weight;
+                }
+            }
+            let pixel = &mut bloomed[(y * width + x) as usize];
+            for channel in 0..3 {
+                pixel[channel] += total[channel] / total_weight * settings.intensity;
+            }
+        }
+    }
+    bloomed
+}
+
+// `fs_tonemap` followed by the sRGB encode of an `Rgba8UnormSrgb` target.
+fn tonemap(color: [f32; 4], exposure: f32, curve: TonemapCurve) -> [u8; 4] {
+    let aces = |x: f32| ((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14)).clamp(0.0, 1.0);
+    let map = |channel: f32| {
+        let exposed = channel * exposure;
+        match curve {
+            TonemapCurve::Clamp => exposed.clamp(0.0, 1.0),
+            TonemapCurve::Reinhard => exposed / (1.0 + exposed),
+            TonemapCurve::Aces => aces(exposed),
+        }
+    };
+    let encode = |linear: f32| {
+        let linear = linear.clamp(0.0, 1.0);
+        let srgb = if linear <= 0.0031308 { linear * 12.92 } else { 1.055 * linear.powf(1.0 / 2.4) - 0.055 };
+        (srgb * 255.0).round() as u8
+    };
+    [encode(map(color[0])), encode(map(color[1])), encode(map(color[2])), (color[3].clamp(0.0, 1.0) * 255.0).round() as u8]
+}
+
+// Shows `SoftwareRenderer` frames on a GPU target, such as the window surface.
+pub struct SoftwareBlitter {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    texture_format: wgpu::TextureFormat,
+    // The frame texture and its bind group, with its size.
+    frame: Option<(wgpu::Texture, wgpu::BindGroup, u32, u32)>,
+}
+
+impl SoftwareBlitter {
+    pub fn new(device: &wgpu::Device, target_format: wgpu::TextureFormat) -> Self {
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Blit Shader Module"),
+            source: wgpu::ShaderSource::Wgsl(WGSL_BLIT_SHADER_SOURCE.into()),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("blit_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Blit Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Blit Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &module,
+                entry_point: "vs_fullscreen",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &module,
+                entry_point: "fs_blit",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+        // Nearest, for crisp pixels when the frame is smaller than the target.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Blit Sampler"),
+            ..Default::default()
+        });
+        // The frame's bytes are already sRGB-encoded: an sRGB target encodes what an sRGB texture
+        // decodes, and any other target gets the bytes as they are.
+        let texture_format = if target_format.is_srgb() { wgpu::TextureFormat::Rgba8UnormSrgb } else { wgpu::TextureFormat::Rgba8Unorm };
+        Self { pipeline, bind_group_layout, sampler, texture_format, frame: None }
+    }
+
+    // Uploads the renderer's last frame and draws it over all of `output_view`.
+    pub fn blit(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        output_view: &wgpu::TextureView,
+        renderer: &SoftwareRenderer,
+    ) {
+        let (width, height) = (renderer.width(), renderer.height());
+        if width == 0 || height == 0 {
+            return;
+        }
+        if !self.frame.as_ref().is_some_and(|(_, _, frame_width, frame_height)| *frame_width == width && *frame_height == height) {
+            let texture = device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Software Frame Texture"),
+                size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: self.texture_format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("blit_bind_group"),
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                    wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&self.sampler) },
+                ],
+            });
+            self.frame = Some((texture, bind_group, width, height));
+        }
+        let (texture, bind_group, _, _) = match &self.frame {
+            Some(frame) => frame,
+            None => return,
+        };
+        queue.write_texture(
+            texture.as_image_copy(),
+            renderer.pixels(),
+            wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(width * 4), rows_per_image: Some(height) },
+            wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+        );
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Software Blit Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_view,
+                resolve_target: None,
+                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
@@ -0,0 +1,134 @@
+// src/rendering_lib/thumbnail.rs
+
+use std::collections::HashMap;
+use glam::{Mat4, Vec3};
+
+use super::offscreen::OffscreenTarget;
+use super::renderer::Renderer;
+use super::shader::WGSL_SHADER_SOURCE;
+use crate::engine_lib::camera::Camera;
+use crate::engine_lib::scene_types::{InstanceId, Scene, SideIndex, RENDER_LAYER_ALL};
+
+#[derive(Clone, Debug)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+impl Thumbnail {
+    // Converts the thumbnail for display in an egui image widget.
+    pub fn to_color_image(&self) -> egui::ColorImage {
+        egui::ColorImage::from_rgba_unmultiplied([self.width as usize, self.height as usize], &self.rgba)
+    }
+}
+
+// Picks the pose a thumbnail is rendered from: the scene's stored camera if its host
+// instance exists, otherwise the centroid of the lowest-id instance looking at its first portal.
+pub fn thumbnail_camera_pose(scene: &Scene) -> Option<(InstanceId, Mat4)> {
+    if let Some(camera) = scene.active_camera().filter(|cam| scene.instances.contains_key(&cam.host_instance_id)) {
+        return Some((camera.host_instance_id, camera.local_transform));
+    }
+
+    let instance = scene.instances.values().min_by_key(|inst| inst.id)?;
+    let blueprint = scene.blueprints.get(&instance.blueprint_id)?;
+    if blueprint.local_vertices.is_empty() {
+        return None;
+    }
+    let centroid = blueprint.local_vertices.iter().copied().sum::<Vec3>() / blueprint.local_vertices.len() as f32;
+
+    let side_center = |side_idx: usize| -> Option<Vec3> {
+        let side = &blueprint.sides[side_idx];
+        let points: Vec<Vec3> = side.vertex_indices.iter()
+            .filter_map(|&v_idx| blueprint.local_vertices.get(v_idx).copied())
+            .collect();
+        if points.is_empty() { None } else { Some(points.iter().copied().sum::<Vec3>() / points.len() as f32) }
+    };
+    let is_portal = |side_idx: usize| {
+        let config = instance.instance_side_handler_configs
+            .get(&(side_idx as SideIndex))
+            .unwrap_or(&blueprint.sides[side_idx].default_handler_config);
+        config.visible_portal_target().is_some()
+    };
+
+    let look_target = (0..blueprint.sides.len())
+        .find(|&side_idx| is_portal(side_idx))
+        .and_then(side_center)
+        .unwrap_or(centroid + Vec3::NEG_Z);
+    let look_dir = (look_target - centroid).try_normalize().unwrap_or(Vec3::NEG_Z);
+    let up = if look_dir.y.abs() > 0.99 { Vec3::Z } else { Vec3::Y };
+
+    // look_to_rh builds a view matrix; the camera pose is its inverse.
+    Some((instance.id, Mat4::look_to_rh(centroid, look_dir, up).inverse()))
+}
+
+// Renders small preview images of scenes off-screen and caches them by key
+// (typically the scene file path), for scene pickers and asset browsers.
+pub struct ThumbnailGenerator {
+    renderer: Renderer,
+    target: OffscreenTarget,
+    camera: Camera,
+    clear_color: wgpu::Color,
+    cache: HashMap<String, Thumbnail>,
+}
+
+impl ThumbnailGenerator {
+    pub fn new(device: &wgpu::Device, width: u32, height: u32) -> Self {
+        let target = OffscreenTarget::new(device, width, height, OffscreenTarget::DEFAULT_FORMAT);
+        let renderer = Renderer::new(
+            device, target.format(), WGSL_SHADER_SOURCE,
+            target.width() as f32, target.height() as f32,
+        );
+        Self {
+            renderer,
+            target,
+            camera: Camera::new(75.0, 0.01, 100.0),
+            clear_color: wgpu::Color { r: 0.05, g: 0.05, b: 0.1, a: 1.0 },
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Thumbnail> {
+        self.cache.get(key)
+    }
+
+    pub fn invalidate(&mut self, key: &str) {
+        self.cache.remove(key);
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    // Returns the cached thumbnail for `key`, rendering it from `scene` on a cache miss.
+    // Returns None if the scene has no instance to place a camera in or readback fails.
+    pub fn get_or_render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        key: &str,
+        scene: &Scene,
+    ) -> Option<&Thumbnail> {
+        if !self.cache.contains_key(key) {
+            let thumbnail = self.render(device, queue, scene)?;
+            self.cache.insert(key.to_string(), thumbnail);
+        }
+        self.cache.get(key)
+    }
+
+    fn render(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, scene: &Scene) -> Option<Thumbnail> {
+        let (camera_instance_id, camera_pose) = thumbnail_camera_pose(scene)?;
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Thumbnail Command Encoder"),
+        });
+        self.renderer.render_scene_from_pose(
+            device, queue, &mut encoder, self.target.view(),
+            scene, &self.camera,
+            camera_instance_id, &camera_pose,
+            self.target.width() as f32, self.target.height() as f32,
+            self.clear_color, RENDER_LAYER_ALL,
+        );
+        let rgba = self.target.read_pixels(device, queue, encoder).ok()?;
+        Some(Thumbnail { width: self.target.width(), height: self.target.height(), rgba })
+    }
+}
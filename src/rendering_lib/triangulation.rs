@@ -0,0 +1,134 @@
+// src/rendering_lib/triangulation.rs
+
+use super::geometry::{Point2, Scalar};
+
+// Twice the signed area of triangle (a, b, c): positive if it turns left.
+fn cross<T: Scalar>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>) -> T {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+// Twice the signed area of a polygon; positive for the winding clip polygons use.
+pub fn signed_area_doubled<T: Scalar>(points: &[Point2<T>]) -> T {
+    (0..points.len()).fold(T::ZERO, |area, i| {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        area + a.x * b.y - b.x * a.y
+    })
+}
+
+// True if the polygon never turns against its own winding. Collinear vertices are allowed.
+pub fn is_convex<T: Scalar>(points: &[Point2<T>]) -> bool {
+    let (mut has_left, mut has_right) = (false, false);
+    for i in 0..points.len() {
+        let turn = cross(&points[i], &points[(i + 1) % points.len()], &points[(i + 2) % points.len()]);
+        has_left |= turn > T::ZERO;
+        has_right |= turn < T::ZERO;
+    }
+    !(has_left && has_right)
+}
+
+// Appends triangle-fan indices for a convex polygon whose vertices start at `start_vertex_index`.
+pub fn push_fan_indices(indices: &mut Vec<u32>, start_vertex_index: u32, vertex_count: usize) {
+    for i in 1..(vertex_count as u32).saturating_sub(1) {
+        indices.push(start_vertex_index);
+        indices.push(start_vertex_index + i);
+        indices.push(start_vertex_index + i + 1);
+    }
+}
+
+// Splits a simple polygon (no self-intersections, either winding) into triangles by ear
+// clipping, returned as indices into `points` in the polygon's winding. If the polygon is not
+// simple and no ear can be found, what is left is fanned so that something is still drawn.
+pub fn ear_clip<T: Scalar>(points: &[Point2<T>]) -> Vec<[usize; 3]> {
+    let mut triangles = Vec::with_capacity(points.len().saturating_sub(2));
+    if points.len() < 3 {
+        return triangles;
+    }
+    let winding = if signed_area_doubled(points) < T::ZERO { -1 } else { 1 };
+    let turns_with_winding = |a: usize, b: usize, c: usize| {
+        let turn = cross(&points[a], &points[b], &points[c]);
+        if winding > 0 { turn > T::ZERO } else { turn < T::ZERO }
+    };
+    // Inside or on the edges of triangle (a, b, c), which winds the polygon's way.
+    let in_triangle = |p: usize, a: usize, b: usize, c: usize| {
+        let point = &points[p];
+        [(a, b), (b, c), (c, a)].iter().all(|&(from, to)| {
+            let turn = cross(&points[from], &points[to], point);
+            if winding > 0 { turn >= T::ZERO } else { turn <= T::ZERO }
+        })
+    };
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&i| {
+            let (a, b, c) = (remaining[(i + count - 1) % count], remaining[i], remaining[(i + 1) % count]);
+            turns_with_winding(a, b, c)
+                && !remaining.iter()
+                    .filter(|&&p| p != a && p != b && p != c && points[p] != points[a] && points[p] != points[b] && points[p] != points[c])
+                    .any(|&p| in_triangle(p, a, b, c))
+        });
+        match ear {
+            Some(i) => {
+                triangles.push([remaining[(i + count - 1) % count], remaining[i], remaining[(i + 1) % count]]);
+                remaining.remove(i);
+            }
+            None => break,
+        }
+    }
+    for i in 1..remaining.len().saturating_sub(1) {
+        triangles.push([remaining[0], remaining[i], remaining[i + 1]]);
+    }
+    triangles
+}
+
+// Appends indices that triangulate the polygon whose vertices, `points`, start at
+// `start_vertex_index`: a fan if it is convex, ear clipping otherwise.
+pub fn push_polygon_indices<T: Scalar>(indices: &mut Vec<u32>, start_vertex_index: u32, points: &[Point2<T>]) {
+    if is_convex(points) {
+        push_fan_indices(indices, start_vertex_index, points.len());
+        return;
+    }
+    for triangle in ear_clip(points) {
+        indices.extend(triangle.iter().map(|&i| start_vertex_index + i as u32));
+    }
+}
+
+// Joins polygon `a` and `b` along an edge they share in opposite directions, if they have one.
+fn merge_along_shared_edge(a: &[usize], b: &[usize]) -> Option<Vec<usize>> {
+    for k in 0..a.len() {
+        let (x, y) = (a[k], a[(k + 1) % a.len()]);
+        let m = match (0..b.len()).find(|&m| b[m] == y && b[(m + 1) % b.len()] == x) {
+            Some(m) => m,
+            None => continue,
+        };
+        // a up to x, then b's other vertices from after x round to before y, then a from y on.
+        let mut merged: Vec<usize> = a[..=k].to_vec();
+        merged.extend((2..b.len()).map(|offset| b[(m + offset) % b.len()]));
+        merged.extend_from_slice(&a[k + 1..]);
+        return Some(merged);
+    }
+    None
+}
+
+// Splits a simple polygon into convex pieces (Hertel-Mehlhorn): ear clip it, then join
+// neighbouring pieces while the result stays convex. Pieces are indices into `points` and keep
+// the polygon's winding. A convex polygon comes back whole.
+pub fn convex_decomposition<T: Scalar>(points: &[Point2<T>]) -> Vec<Vec<usize>> {
+    if is_convex(points) {
+        return vec![(0..points.len()).collect()];
+    }
+    let mut pieces: Vec<Vec<usize>> = ear_clip(points).iter().map(|triangle| triangle.to_vec()).collect();
+    let is_convex_piece = |piece: &[usize]| is_convex(&piece.iter().map(|&i| points[i]).collect::<Vec<_>>());
+    'merging: loop {
+        for i in 0..pieces.len() {
+            for j in i + 1..pieces.len() {
+                if let Some(merged) = merge_along_shared_edge(&pieces[i], &pieces[j]).filter(|merged| is_convex_piece(merged)) {
+                    pieces[i] = merged;
+                    pieces.remove(j);
+                    continue 'merging;
+                }
+            }
+        }
+        return pieces;
+    }
+}
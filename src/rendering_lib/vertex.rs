@@ -1,35 +1,78 @@
-// src/vertex.rs
-
-use bytemuck::{Pod, Zeroable};
-
-#[repr(C)]
-#[derive(Clone, Copy, Debug, Pod, Zeroable)]
-pub struct Vertex {
-    pub position: [f32; 2], // Made pub
-    pub color: [f32; 4],    // Made pub
-}
-
-impl Vertex {
-    pub fn new(position: [f32; 2], color: [f32; 4]) -> Self {
-        Self { position, color }
-    }
-
-    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &[
-                wgpu::VertexAttribute {
-                    offset: 0,
-                    shader_location: 0,
-                    format: wgpu::VertexFormat::Float32x2,
-                },
-                wgpu::VertexAttribute {
-                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
-                    shader_location: 1,
-                    format: wgpu::VertexFormat::Float32x4,
-                },
-            ],
-        }
-    }
-}
\ No newline at end of file
+// src/vertex.rs
+
+use bytemuck::{Pod, Zeroable};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 2], // Made pub
+    pub color: [f32; 4],    // Made pub
+    pub depth: f32,         // Camera-space distance along the view axis (-z), used for fog
+}
+
+impl Vertex {
+    pub fn new(position: [f32; 2], color: [f32; 4]) -> Self {
+        Self { position, color, depth: 1.0 }
+    }
+
+    pub fn with_depth(position: [f32; 2], color: [f32; 4], depth: f32) -> Self {
+        Self { position, color, depth }
+    }
+
+    #[cfg(feature = "render")]
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32,
+                },
+            ],
+        }
+    }
+}
+
+// Vertex for sides rendered by the skybox handler. `direction` is the view ray in the
+// skybox hull's blueprint space; the gradient is evaluated per fragment from it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SkyVertex {
+    pub position: [f32; 2],
+    pub direction: [f32; 3],
+    pub zenith_color: [f32; 4],
+    pub horizon_color: [f32; 4],
+    pub nadir_color: [f32; 4],
+}
+
+impl SkyVertex {
+    #[cfg(feature = "render")]
+    const ATTRIBUTES: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x3,
+        2 => Float32x4,
+        3 => Float32x4,
+        4 => Float32x4,
+    ];
+
+    #[cfg(feature = "render")]
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<SkyVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
@@ -0,0 +1,225 @@
+// src/scene_editor.rs
+use egui;
+use crate::engine_lib::scene_types::{Scene, HullBlueprint, HandlerConfig, SideHandlerTypeId, InstanceId, PortalId, SideIndex};
+
+// Handler types a side can be switched to: the ones the renderer draws. Portals and doors only
+// on sides with a portal id, which the other end lines up with.
+const EDITABLE_HANDLER_TYPES: [SideHandlerTypeId; 4] = [
+    SideHandlerTypeId::StandardWall,
+    SideHandlerTypeId::StandardPortal,
+    SideHandlerTypeId::Door,
+    SideHandlerTypeId::Skybox,
+];
+
+const NEW_WALL_COLOR: [f32; 4] = [0.6, 0.6, 0.6, 1.0];
+
+// A portal on another instance that a side can be pointed at.
+struct PortalChoice {
+    instance_id: InstanceId,
+    portal_id: PortalId,
+    label: String,
+}
+
+fn portal_choices(scene: &Scene) -> Vec<PortalChoice> {
+    let mut instance_ids: Vec<InstanceId> = scene.instances.keys().copied().collect();
+    instance_ids.sort_unstable();
+    let mut choices = Vec::new();
+    for instance_id in instance_ids {
+        let instance = &scene.instances[&instance_id];
+        let blueprint = match scene.blueprints.get(&instance.blueprint_id) {
+            Some(blueprint) => blueprint,
+            None => continue,
+        };
+        for portal_id in blueprint.sides.iter().filter_map(|side| side.local_portal_id) {
+            choices.push(PortalChoice {
+                instance_id,
+                portal_id,
+                label: format!("{} ({}) portal {}", instance.name, instance_id, portal_id),
+            });
+        }
+    }
+    choices
+}
+
+// A fresh config of `handler_type` for a side, keeping whatever the old one had in common with it.
+fn config_of_type(handler_type: SideHandlerTypeId, old: &HandlerConfig, fallback_target: Option<(InstanceId, PortalId)>) -> HandlerConfig {
+    let color = match old {
+        HandlerConfig::StandardWall { color, .. } | HandlerConfig::Door { color, .. } => *color,
+        _ => NEW_WALL_COLOR,
+    };
+    let (target_instance_id, target_portal_id) = match old {
+        HandlerConfig::StandardPortal { target_instance_id, target_portal_id, .. }
+        | HandlerConfig::Door { target_instance_id, target_portal_id, .. }
+        | HandlerConfig::LiquidSurface { target_instance_id, target_portal_id, .. } => (*target_instance_id, *target_portal_id),
+        _ => fallback_target.unwrap_or((0, 0)),
+    };
+    let render_priority = old.render_priority();
+    match handler_type {
+        SideHandlerTypeId::StandardPortal => HandlerConfig::portal(target_instance_id, target_portal_id),
+        SideHandlerTypeId::Door => HandlerConfig::Door { target_instance_id, target_portal_id, color, open: false, render_priority },
+        SideHandlerTypeId::Skybox => HandlerConfig::Skybox {
+            zenith_color: [0.2, 0.4, 0.9, 1.0],
+            horizon_color: [0.8, 0.85, 0.95, 1.0],
+            nadir_color: [0.3, 0.3, 0.3, 1.0],
+        },
+        _ => HandlerConfig::StandardWall { color, texture_id: None, emissive: 0.0, render_priority },
+    }
+}
+
+fn leads_through_portal(handler_type: &SideHandlerTypeId) -> bool {
+    matches!(handler_type, SideHandlerTypeId::StandardPortal | SideHandlerTypeId::Door)
+}
+
+// Edits `config` in place. Returns true if anything changed.
+fn edit_handler_config(ui: &mut egui::Ui, id: egui::Id, config: &mut HandlerConfig, has_portal_id: bool, portals: &[PortalChoice], fallback_target: Option<(InstanceId, PortalId)>) -> bool {
+    let mut changed = false;
+    let current_type = config.get_intended_handler_type();
+    let mut new_type = current_type.clone();
+    egui::ComboBox::from_id_source(id.with("type"))
+        .selected_text(format!("{:?}", current_type))
+        .show_ui(ui, |ui| {
+            for handler_type in EDITABLE_HANDLER_TYPES {
+                if leads_through_portal(&handler_type) && !has_portal_id {
+                    continue;
+                }
+                let text = format!("{:?}", handler_type);
+                ui.selectable_value(&mut new_type, handler_type, text);
+            }
+        });
+    if new_type != current_type {
+        *config = config_of_type(new_type, config, fallback_target);
+        changed = true;
+    }
+
+    let mut edit_target = |ui: &mut egui::Ui, target_instance_id: &mut InstanceId, target_portal_id: &mut PortalId| {
+        let selected = portals.iter()
+            .find(|choice| choice.instance_id == *target_instance_id && choice.portal_id == *target_portal_id)
+            .map_or_else(|| format!("missing: {} portal {}", target_instance_id, target_portal_id), |choice| choice.label.clone());
+        egui::ComboBox::from_id_source(id.with("target"))
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for choice in portals {
+                    let is_selected = choice.instance_id == *target_instance_id && choice.portal_id == *target_portal_id;
+                    if ui.selectable_label(is_selected, &choice.label).clicked() && !is_selected {
+                        *target_instance_id = choice.instance_id;
+                        *target_portal_id = choice.portal_id;
+                        changed = true;
+                    }
+                }
+            });
+    };
+    match &mut *config {
+        HandlerConfig::StandardWall { color, emissive, .. } => {
+            changed |= ui.color_edit_button_rgba_unmultiplied(color).changed();
+            changed |= ui.add(egui::Slider::new(emissive, 0.0..=8.0).text("Glow")).changed();
+        }
+        HandlerConfig::StandardPortal { target_instance_id, target_portal_id, access } => {
+            edit_target(ui, target_instance_id, target_portal_id);
+            changed |= ui.checkbox(&mut access.visible, "Visible").changed();
+            changed |= ui.checkbox(&mut access.walkable, "Walkable").changed();
+            if !access.visible {
+                changed |= ui.color_edit_button_rgba_unmultiplied(&mut access.wall_color).changed();
+            }
+            // An empty key leaves the portal ungated.
+            let mut key = access.required_key.clone().unwrap_or_default();
+            if ui.horizontal(|ui| { ui.label("Key"); ui.text_edit_singleline(&mut key).changed() }).inner {
+                access.required_key = Some(key).filter(|key| !key.is_empty());
+                changed = true;
+            }
+        }
+        HandlerConfig::Door { target_instance_id, target_portal_id, color, open, .. } => {
+            edit_target(ui, target_instance_id, target_portal_id);
+            changed |= ui.color_edit_button_rgba_unmultiplied(color).changed();
+            changed |= ui.checkbox(open, "Open").changed();
+        }
+        HandlerConfig::LiquidSurface { target_instance_id, target_portal_id, tint, .. } => {
+            edit_target(ui, target_instance_id, target_portal_id);
+            changed |= ui.color_edit_button_rgba_unmultiplied(tint).changed();
+        }
+        HandlerConfig::Skybox { zenith_color, horizon_color, nadir_color } => {
+            changed |= ui.color_edit_button_rgba_unmultiplied(zenith_color).changed();
+            changed |= ui.color_edit_button_rgba_unmultiplied(horizon_color).changed();
+            changed |= ui.color_edit_button_rgba_unmultiplied(nadir_color).changed();
+        }
+        // Set up in code, by whoever registered the handler; shown here as JSON.
+        HandlerConfig::Custom { type_id, params } => { ui.label(format!("{}: {}", type_id, params)); }
+        // Not drawn yet, so only shown.
+        other => { ui.label(format!("{:?}", other)); }
+    }
+    if let Some(priority) = config.render_priority_mut() {
+        changed |= ui.add(egui::DragValue::new(priority).prefix("priority ")).changed();
+    }
+    changed
+}
+
+fn side_label(blueprint: &HullBlueprint, side_index: SideIndex, overridden: bool) -> String {
+    let portal = blueprint.sides[side_index].local_portal_id
+        .map_or_else(String::new, |portal_id| format!(" [portal {}]", portal_id));
+    format!("Side {}{}{}", side_index, portal, if overridden { " *" } else { "" })
+}
+
+// Lists every instance and its sides, and edits their handler configs in place. Changes are stored
+// as instance overrides, so the renderer shows them on the next frame. Sides marked * differ from
+// their blueprint and can be reset. `picked` opens the instance of the side picked in the view.
+pub fn build_scene_editor(ctx: &egui::Context, scene: &mut Scene, open: &mut bool, picked: Option<(InstanceId, SideIndex)>) {
+    let portals = portal_choices(scene);
+    let mut instance_ids: Vec<InstanceId> = scene.instances.keys().copied().collect();
+    instance_ids.sort_unstable();
+
+    egui::Window::new("Scene Editor")
+        .open(open)
+        .default_pos(egui::pos2(320.0, 10.0))
+        .default_height(480.0)
+        .show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                for instance_id in &instance_ids {
+                    let instance = match scene.instances.get_mut(instance_id) {
+                        Some(instance) => instance,
+                        None => continue,
+                    };
+                    let blueprint = match scene.blueprints.get(&instance.blueprint_id) {
+                        Some(blueprint) => blueprint,
+                        None => continue,
+                    };
+                    let header_id = egui::Id::new(("scene_editor_instance", instance_id));
+                    let picked_here = picked.filter(|(picked_id, _)| picked_id == instance_id).map(|(_, side_index)| side_index);
+                    egui::CollapsingHeader::new(format!("{} ({}) - {}", instance.name, instance_id, blueprint.name))
+                        .id_source(header_id)
+                        .default_open(picked_here.is_some())
+                        .show(ui, |ui| {
+                            egui::Grid::new(header_id.with("sides")).num_columns(3).striped(true).show(ui, |ui| {
+                                for (side_index, side) in blueprint.sides.iter().enumerate() {
+                                    let overridden = instance.instance_side_handler_configs.contains_key(&side_index);
+                                    let label = side_label(blueprint, side_index, overridden);
+                                    if picked_here == Some(side_index) {
+                                        ui.colored_label(egui::Color32::YELLOW, label);
+                                    } else {
+                                        ui.label(label);
+                                    }
+
+                                    let mut config = instance.instance_side_handler_configs.get(&side_index)
+                                        .unwrap_or(&side.default_handler_config)
+                                        .clone();
+                                    // A side switched to a portal points where the instance's connection for it does.
+                                    let fallback_target = side.local_portal_id
+                                        .and_then(|portal_id| instance.portal_connections.get(&portal_id))
+                                        .map(|connection| (connection.target_instance_id, connection.target_portal_id))
+                                        .or_else(|| portals.first().map(|choice| (choice.instance_id, choice.portal_id)));
+                                    let changed = ui.horizontal(|ui| {
+                                        edit_handler_config(ui, header_id.with(side_index), &mut config, side.local_portal_id.is_some(), &portals, fallback_target)
+                                    }).inner;
+                                    if changed {
+                                        instance.instance_side_handler_configs.insert(side_index, config);
+                                    }
+
+                                    if ui.add_enabled(overridden, egui::Button::new("Reset")).clicked() {
+                                        instance.instance_side_handler_configs.remove(&side_index);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                }
+            });
+        });
+}
@@ -0,0 +1,122 @@
+// src/settings.rs
+
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+// Where `PolygonApp` keeps its settings, relative to the working directory.
+pub const SETTINGS_PATH: &str = "engine3.toml";
+
+// Engine settings that persist between runs, stored as TOML. Keys missing from the file keep
+// their defaults, so old files keep working as settings are added.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    // Radians the camera turns per pixel of mouse motion.
+    pub mouse_sensitivity: f32,
+    // Moving the mouse forward looks down.
+    pub invert_y: bool,
+    // Vertical field of view in degrees, when not zoomed.
+    pub fov_y_deg: f32,
+    // Camera movement speed in units per second.
+    pub move_speed: f32,
+    pub vsync: bool,
+    // Logical size of the window when it opens.
+    pub window_size: [u32; 2],
+    // Fraction of the window's resolution the scene is rendered at; see
+    // `RenderSettings::render_scale`.
+    pub render_scale: f32,
+    pub wireframe: bool,
+    pub show_clip_regions: bool,
+    pub show_debug_labels: bool,
+    // Crosshair, interaction prompt and current room name over the main view.
+    pub show_hud: bool,
+    // Linear RGBA the view is cleared to where nothing is drawn. Scenes with fog clear to the
+    // fog color instead.
+    pub clear_color: [f32; 4],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: 0.002,
+            invert_y: false,
+            fov_y_deg: 75.0,
+            move_speed: 3.0,
+            vsync: true,
+            window_size: [1024, 768],
+            render_scale: 1.0,
+            wireframe: false,
+            show_clip_regions: false,
+            show_debug_labels: false,
+            show_hud: true,
+            clear_color: [0.05, 0.05, 0.1, 1.0],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(e) => write!(f, "{}", e),
+            SettingsError::Parse(e) => write!(f, "invalid settings file: {}", e),
+            SettingsError::Serialize(e) => write!(f, "cannot write settings: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl Settings {
+    pub fn from_toml(text: &str) -> Result<Self, SettingsError> {
+        toml::from_str(text).map_err(SettingsError::Parse)
+    }
+
+    pub fn to_toml(&self) -> Result<String, SettingsError> {
+        toml::to_string_pretty(self).map_err(SettingsError::Serialize)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SettingsError> {
+        let text = std::fs::read_to_string(path).map_err(SettingsError::Io)?;
+        Self::from_toml(&text)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SettingsError> {
+        std::fs::write(path, self.to_toml()?).map_err(SettingsError::Io)
+    }
+
+    // The settings in `path`, or the defaults if there are none yet, in which case they are
+    // written there to be edited. A file that cannot be read is logged and left alone.
+    pub fn load_or_create(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        match Self::load(path) {
+            Ok(settings) => settings,
+            Err(SettingsError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                let settings = Self::default();
+                if let Err(e) = settings.save(path) {
+                    log::warn!("Cannot write {}: {}", path.display(), e);
+                }
+                settings
+            }
+            Err(e) => {
+                log::warn!("Cannot load {}, using defaults: {}", path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    // `clear_color` as the renderer takes it.
+    #[cfg(feature = "render")]
+    pub fn wgpu_clear_color(&self) -> wgpu::Color {
+        let [r, g, b, a] = self.clear_color.map(f64::from);
+        wgpu::Color { r, g, b, a }
+    }
+}
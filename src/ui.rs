@@ -1,28 +1,425 @@
-﻿// src/ui.rs
-use egui;
-
-pub fn build_ui(ctx: &egui::Context) { // Removed ConvexPolygon and control bools
-    egui::Window::new("Controls & Info") // Renamed window slightly
-        .anchor(egui::Align2::LEFT_TOP, egui::vec2(10.0, 10.0))
-        .resizable(false)
-        .show(ctx, |ui| {
-            ui.vertical(|ui| {
-                ui.label("Portal Rendering Demo");
-                ui.separator();
-
-                // Add any relevant 3D app status/info here if needed in the future.
-                // For now, it will be minimal.
-
-                ui.label("🎮 Keyboard Controls:");
-                ui.label("   W/A/S/D: Move Camera");
-                ui.label("   Space: Move Up");
-                ui.label("   L-Shift/L-Ctrl: Move Down");
-                ui.label("   Arrow Keys: Look Up/Down/Left/Right");
-                ui.label("   Mouse (when grabbed): Look");
-                ui.label("   Escape: Grab/Ungrab Mouse Cursor");
-                // "T: Run performance benchmark" can be kept if you still want users to know.
-                // The benchmark itself (intersection_benchmark.rs) is separate from the app's runtime.
-                // ui.label("   T: Run performance benchmark (via 'cargo bench')");
-            });
-        });
-}
\ No newline at end of file
+// src/ui.rs
+use egui;
+use crate::app::{AppConfig, MAX_TIME_SCALE};
+use crate::settings::Settings;
+use crate::profiler::FrameTimeHistory;
+use crate::rendering_lib::renderer::{BloomSettings, DynamicResolution, ImpostorSettings, PortalMasking, RenderSettings, RenderStats, ScreenLabel, TonemapCurve, MIN_RENDER_SCALE};
+use crate::engine_lib::automap::AutomapLayout;
+use crate::engine_lib::picking::RayHit;
+use crate::engine_lib::scene_types::SideHandlerTypeId;
+use crate::engine_lib::camera::{MIN_FOV_Y_DEG, MAX_FOV_Y_DEG};
+
+// Live engine numbers shown in the stats panel, gathered by the app each frame.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DebugStats {
+    pub fps: f32,
+    pub frame_time_ms: f32,
+    pub render: RenderStats,
+}
+
+// The side last picked with the right mouse button, shown in the inspector.
+#[derive(Clone, Debug)]
+pub struct PickedSide {
+    pub hit: RayHit,
+    pub instance_name: String,
+    pub handler_type: SideHandlerTypeId,
+}
+
+// What the HUD shows over the main view, gathered by the app each frame.
+#[derive(Clone, Debug, Default)]
+pub struct HudInfo {
+    // Center of the main view in physical pixels, where the crosshair goes.
+    pub crosshair: Option<[f32; 2]>,
+    // What the crosshair is on that can be acted on, e.g. "Open door".
+    pub prompt: Option<String>,
+    // Name of the hull instance the camera is in.
+    pub room_name: Option<String>,
+}
+
+const CROSSHAIR_ARM: f32 = 8.0;
+const CROSSHAIR_GAP: f32 = 3.0;
+
+const FRAME_RATE_CAP_DEFAULT: f32 = 60.0;
+// Hulls traversed per frame when the budget is first switched on.
+const HULL_BUDGET_DEFAULT: u32 = 64;
+
+pub fn build_ui(
+    ctx: &egui::Context,
+    stats: &DebugStats,
+    render_settings: &mut RenderSettings,
+    app_config: &mut AppConfig,
+    supported_present_modes: &[wgpu::PresentMode],
+    picked_side: Option<&PickedSide>,
+    time_scale: &mut f32,
+) {
+    egui::Window::new("Controls & Info")
+        .anchor(egui::Align2::LEFT_TOP, egui::vec2(10.0, 10.0))
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.vertical(|ui| {
+                ui.label("Portal Rendering Demo");
+                ui.separator();
+
+                egui::Grid::new("engine_stats_grid")
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("FPS");
+                        ui.label(format!("{:.0}", stats.fps));
+                        ui.end_row();
+                        ui.label("Frame time");
+                        ui.label(format!("{:.2} ms", stats.frame_time_ms));
+                        ui.end_row();
+                        ui.label("Traversal time");
+                        ui.label(format!("{:.3} ms", stats.render.traversal_time.as_secs_f64() * 1000.0));
+                        ui.end_row();
+                        if let Some(gpu) = stats.render.gpu_pass_times {
+                            let ms = |time: Option<std::time::Duration>| time.map_or("-".to_string(), |time| format!("{:.3}", time.as_secs_f64() * 1000.0));
+                            ui.label("GPU scene / tonemap / UI");
+                            ui.label(format!("{} / {} / {} ms", ms(gpu.scene), ms(gpu.tonemap), ms(gpu.ui)));
+                            ui.end_row();
+                        }
+                        ui.label("Portal depth");
+                        ui.label(stats.render.max_recursion_depth.to_string());
+                        ui.end_row();
+                        ui.label("Hulls visited");
+                        ui.label(stats.render.hulls_visited.to_string());
+                        ui.end_row();
+                        ui.label("Portals traversed");
+                        ui.label(stats.render.portals_traversed.to_string());
+                        ui.end_row();
+                        ui.label("Sub-pixel portals skipped");
+                        ui.label(stats.render.portals_culled_small.to_string());
+                        ui.end_row();
+                        ui.label("Repeated regions skipped");
+                        ui.label(stats.render.repeated_regions_skipped.to_string());
+                        ui.end_row();
+                        ui.label("Hulls culled by bounds");
+                        ui.label(stats.render.hulls_culled_by_bounds.to_string());
+                        ui.end_row();
+                        ui.label("Hulls over budget");
+                        ui.label(stats.render.hulls_over_budget.to_string());
+                        ui.end_row();
+                        ui.label("Impostors drawn");
+                        ui.label(stats.render.impostors_drawn.to_string());
+                        ui.end_row();
+                        ui.label("Sides drawn");
+                        ui.label(stats.render.sides_drawn.to_string());
+                        ui.end_row();
+                        ui.label("Entity faces drawn");
+                        ui.label(stats.render.entity_faces_drawn.to_string());
+                        ui.end_row();
+                        ui.label("Sides processed");
+                        ui.label(stats.render.sides_processed.to_string());
+                        ui.end_row();
+                        ui.label("Vertices / indices");
+                        ui.label(format!("{} / {}", stats.render.vertices_emitted, stats.render.indices_emitted));
+                        ui.end_row();
+                        if let Some(abort) = stats.render.aborted {
+                            ui.colored_label(egui::Color32::LIGHT_RED, "Watchdog");
+                            ui.colored_label(egui::Color32::LIGHT_RED, format!("aborted ({:?})", abort));
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+
+                match picked_side {
+                    Some(picked) => {
+                        egui::Grid::new("picked_side").num_columns(2).show(ui, |ui| {
+                            ui.label("Picked instance");
+                            ui.label(format!("{} ({})", picked.instance_name, picked.hit.instance_id));
+                            ui.end_row();
+                            ui.label("Side");
+                            ui.label(format!("{} ({:?})", picked.hit.side_index, picked.handler_type));
+                            ui.end_row();
+                            ui.label("Distance");
+                            ui.label(format!("{:.2} through {} portal(s)", picked.hit.distance, picked.hit.portals_crossed));
+                            ui.end_row();
+                        });
+                    }
+                    None => { ui.label("Right-click a side to inspect it"); }
+                }
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let paused = *time_scale == 0.0;
+                    if ui.button(if paused { "▶" } else { "⏸" }).clicked() {
+                        *time_scale = if paused { 1.0 } else { 0.0 };
+                    }
+                    ui.add(egui::Slider::new(time_scale, 0.0..=MAX_TIME_SCALE).text("Time scale"));
+                });
+                ui.separator();
+
+                ui.checkbox(&mut render_settings.parallel_traversal, "Parallel traversal");
+                ui.checkbox(&mut render_settings.skip_repeated_regions, "Skip repeated portal regions");
+                ui.horizontal(|ui| {
+                    let mut budgeted = render_settings.budget.max_hulls.is_some();
+                    if ui.checkbox(&mut budgeted, "Hull budget").changed() {
+                        render_settings.budget.max_hulls = budgeted.then_some(HULL_BUDGET_DEFAULT);
+                    }
+                    if let Some(max_hulls) = &mut render_settings.budget.max_hulls {
+                        ui.add(egui::DragValue::new(max_hulls).clamp_range(1..=4096).suffix(" hulls"));
+                    }
+                });
+                ui.add(egui::Slider::new(&mut render_settings.min_portal_area, 0.0..=64.0).text("Min portal area").suffix(" px²"));
+                ui.horizontal(|ui| {
+                    let mut impostors = render_settings.impostors.is_some();
+                    if ui.checkbox(&mut impostors, "Portal impostors").changed() {
+                        render_settings.impostors = impostors.then(ImpostorSettings::default);
+                    }
+                    if let Some(impostors) = &mut render_settings.impostors {
+                        ui.add(egui::DragValue::new(&mut impostors.min_recursion_depth).clamp_range(1..=32).prefix("from depth "));
+                    }
+                });
+                let mut stencil = render_settings.portal_masking == PortalMasking::Stencil;
+                if ui.checkbox(&mut stencil, "Stencil portal masking").changed() {
+                    render_settings.portal_masking = if stencil { PortalMasking::Stencil } else { PortalMasking::Clipping };
+                }
+                ui.checkbox(&mut render_settings.gpu_side_clipping, "GPU side clipping");
+                ui.checkbox(&mut render_settings.gpu_timing, "GPU pass timing");
+                ui.horizontal(|ui| {
+                    let mut dynamic = render_settings.dynamic_resolution.is_some();
+                    if ui.checkbox(&mut dynamic, "Dynamic resolution").changed() {
+                        render_settings.dynamic_resolution = dynamic.then(DynamicResolution::default);
+                    }
+                    if let Some(dynamic_resolution) = &mut render_settings.dynamic_resolution {
+                        let mut target_fps = (1.0 / dynamic_resolution.target_frame_time.as_secs_f32()).round() as u32;
+                        if ui.add(egui::DragValue::new(&mut target_fps).clamp_range(10..=240).prefix("target ").suffix(" fps")).changed() {
+                            dynamic_resolution.target_frame_time = std::time::Duration::from_secs_f32(1.0 / target_fps as f32);
+                        }
+                    }
+                });
+                let mut msaa = render_settings.msaa_samples > 1;
+                if ui.checkbox(&mut msaa, "Anti-aliasing (4x MSAA)").changed() {
+                    render_settings.msaa_samples = if msaa { 4 } else { 1 };
+                }
+                egui::ComboBox::from_label("Tonemapping")
+                    .selected_text(format!("{:?}", render_settings.tonemap))
+                    .show_ui(ui, |ui| {
+                        for curve in [TonemapCurve::Clamp, TonemapCurve::Reinhard, TonemapCurve::Aces] {
+                            ui.selectable_value(&mut render_settings.tonemap, curve, format!("{:?}", curve));
+                        }
+                    });
+                ui.add(egui::Slider::new(&mut render_settings.exposure, 0.1..=4.0).text("Exposure"));
+                let mut bloom = render_settings.bloom.is_some();
+                if ui.checkbox(&mut bloom, "Bloom").changed() {
+                    render_settings.bloom = bloom.then(BloomSettings::default);
+                }
+                if let Some(bloom) = &mut render_settings.bloom {
+                    ui.add(egui::Slider::new(&mut bloom.threshold, 0.0..=4.0).text("Bloom threshold"));
+                    ui.add(egui::Slider::new(&mut bloom.intensity, 0.0..=4.0).text("Bloom intensity"));
+                    ui.add(egui::Slider::new(&mut bloom.radius, 1.0..=32.0).text("Bloom radius"));
+                }
+                ui.separator();
+
+                egui::ComboBox::from_label("Present mode")
+                    .selected_text(format!("{:?}", app_config.present_mode))
+                    .show_ui(ui, |ui| {
+                        for mode in supported_present_modes {
+                            ui.selectable_value(&mut app_config.present_mode, *mode, format!("{:?}", mode));
+                        }
+                    });
+                ui.checkbox(&mut app_config.software_rendering, "Software rasterizer");
+                ui.horizontal(|ui| {
+                    let mut capped = app_config.frame_rate_cap.is_some();
+                    if ui.checkbox(&mut capped, "Frame rate cap").changed() {
+                        app_config.frame_rate_cap = capped.then_some(FRAME_RATE_CAP_DEFAULT);
+                    }
+                    if let Some(cap) = &mut app_config.frame_rate_cap {
+                        ui.add(egui::DragValue::new(cap).clamp_range(1.0..=1000.0).suffix(" fps"));
+                    }
+                });
+                ui.separator();
+
+                ui.label("🎮 Keyboard Controls:");
+                ui.label("   F1: Settings");
+                ui.label("   W/A/S/D: Move Camera");
+                ui.label("   Space: Move Up (Jump when walking)");
+                ui.label("   L-Shift/L-Ctrl: Move Down");
+                ui.label("   Arrow Keys: Look Up/Down/Left/Right");
+                ui.label("   Mouse (when grabbed): Look");
+                ui.label("   Escape: Grab/Ungrab Mouse Cursor");
+                ui.label("   Right Click: Pick Side (view center when grabbed)");
+                ui.label("   G: Toggle Walking / Flying");
+                ui.label("   P: Pause / Resume");
+                ui.label("   Z (hold): Zoom");
+                ui.label("   F2: Toggle Split Screen");
+                ui.label("   F3: Toggle Picture-in-Picture");
+                ui.label("   F4: Toggle Scene Editor");
+                ui.label("   F5: Toggle Third-Person View");
+                ui.label("   F6: Export Scene Geometry");
+                ui.label("   F7: Frame Time Profiler");
+            });
+        });
+}
+
+// Mouse sensitivity slider range, in radians per pixel.
+const MIN_MOUSE_SENSITIVITY: f32 = 0.0005;
+const MAX_MOUSE_SENSITIVITY: f32 = 0.01;
+
+// The settings panel (F1), edited in place. The app applies changes as they happen and saves
+// them to the settings file.
+pub fn build_settings_panel(ctx: &egui::Context, settings: &mut Settings, open: &mut bool) {
+    egui::Window::new("Settings")
+        .open(open)
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::Grid::new("settings_grid").num_columns(2).show(ui, |ui| {
+                ui.label("Mouse sensitivity");
+                ui.add(egui::Slider::new(&mut settings.mouse_sensitivity, MIN_MOUSE_SENSITIVITY..=MAX_MOUSE_SENSITIVITY).logarithmic(true));
+                ui.end_row();
+                ui.label("Invert mouse Y");
+                ui.checkbox(&mut settings.invert_y, "");
+                ui.end_row();
+                ui.label("Movement speed");
+                ui.add(egui::Slider::new(&mut settings.move_speed, 0.5..=20.0).suffix(" units/s"));
+                ui.end_row();
+                ui.label("Field of view");
+                ui.add(egui::Slider::new(&mut settings.fov_y_deg, MIN_FOV_Y_DEG..=MAX_FOV_Y_DEG).suffix("°"));
+                ui.end_row();
+                ui.label("Render scale");
+                ui.add(egui::Slider::new(&mut settings.render_scale, MIN_RENDER_SCALE..=1.0));
+                ui.end_row();
+                ui.label("Vsync");
+                ui.checkbox(&mut settings.vsync, "");
+                ui.end_row();
+            });
+            ui.separator();
+            ui.checkbox(&mut settings.wireframe, "Wireframe");
+            ui.checkbox(&mut settings.show_clip_regions, "Show portal clip regions");
+            ui.checkbox(&mut settings.show_debug_labels, "Show debug labels");
+            ui.checkbox(&mut settings.show_hud, "Show HUD");
+        });
+}
+
+// The frame time profiler (F7): the recent frames' total time and the share spent in
+// traversal, waiting on the GPU and building the UI, in milliseconds per frame.
+pub fn build_profiler_window(ctx: &egui::Context, history: &FrameTimeHistory, open: &mut bool) {
+    egui::Window::new("Frame Profiler")
+        .open(open)
+        .default_size(egui::vec2(420.0, 220.0))
+        .show(ctx, |ui| {
+            let average = history.average();
+            let ms = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+            ui.label(format!(
+                "avg {:.2} ms: traversal {:.2}, GPU wait {:.2}, UI {:.2}, other {:.2} | worst {:.2} ms",
+                ms(average.frame), ms(average.traversal), ms(average.gpu_wait), ms(average.ui),
+                ms(average.other()), ms(history.max_frame_time()),
+            ));
+            let series = |name: &str, color: egui::Color32, stage: fn(&crate::profiler::FrameTimings) -> std::time::Duration| {
+                let points: egui_plot::PlotPoints = history.iter().enumerate()
+                    .map(|(index, frame)| [index as f64, ms(stage(frame))])
+                    .collect();
+                egui_plot::Line::new(points).name(name).color(color)
+            };
+            egui_plot::Plot::new("frame_profiler_plot")
+                .legend(egui_plot::Legend::default())
+                .include_x(0.0)
+                .include_x(history.capacity() as f64)
+                .include_y(0.0)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .y_axis_label("ms")
+                .show(ui, |plot_ui| {
+                    plot_ui.line(series("Frame", egui::Color32::LIGHT_GRAY, |frame| frame.frame));
+                    plot_ui.line(series("Traversal", egui::Color32::LIGHT_BLUE, |frame| frame.traversal));
+                    plot_ui.line(series("GPU wait", egui::Color32::LIGHT_RED, |frame| frame.gpu_wait));
+                    plot_ui.line(series("UI", egui::Color32::LIGHT_GREEN, |frame| frame.ui));
+                });
+        });
+}
+
+// Draws the automap scaled to fit the window, the camera's room highlighted. Portals into rooms
+// not yet visited are drawn as short stubs.
+pub fn build_automap_window(ctx: &egui::Context, layout: &AutomapLayout, open: &mut bool) {
+    egui::Window::new("Automap")
+        .open(open)
+        .default_size(egui::vec2(260.0, 260.0))
+        .show(ctx, |ui| {
+            let size = ui.available_size().max(egui::vec2(160.0, 160.0));
+            let (response, painter) = ui.allocate_painter(size, egui::Sense::hover());
+            let rect = response.rect.shrink(12.0);
+            let Some((min, max)) = layout.bounds() else {
+                painter.text(rect.center(), egui::Align2::CENTER_CENTER, "Nothing visited", egui::FontId::proportional(14.0), ui.visuals().weak_text_color());
+                return;
+            };
+            // Map y runs along +Z, which is already down the screen when looking down -Y.
+            let extent = (max - min).max(glam::Vec2::splat(1e-3));
+            let scale = (rect.width() / extent.x).min(rect.height() / extent.y);
+            let middle = (min + max) * 0.5;
+            let to_screen = |point: glam::Vec2| rect.center() + egui::vec2(point.x - middle.x, point.y - middle.y) * scale;
+
+            for (index, room) in layout.rooms.iter().enumerate() {
+                let points: Vec<egui::Pos2> = room.outline.iter().map(|&point| to_screen(point)).collect();
+                // The first room is the one the camera is in.
+                let fill = if index == 0 { egui::Color32::from_rgb(60, 80, 110) } else { egui::Color32::from_rgb(45, 45, 50) };
+                painter.add(egui::Shape::convex_polygon(points, fill, egui::Stroke::new(1.5, egui::Color32::LIGHT_GRAY)));
+            }
+            for link in &layout.links {
+                let Some(room) = layout.room(link.from_instance_id) else { continue };
+                let (from, doorway) = (to_screen(room.center), to_screen(link.doorway));
+                if link.explored {
+                    painter.line_segment([from, doorway], egui::Stroke::new(1.0, egui::Color32::from_gray(140)));
+                    painter.circle_filled(doorway, 3.0, egui::Color32::LIGHT_GREEN);
+                } else {
+                    let stub = doorway + (doorway - from).normalized() * 8.0;
+                    painter.line_segment([doorway, stub], egui::Stroke::new(1.5, egui::Color32::YELLOW));
+                    painter.circle_stroke(doorway, 3.0, egui::Stroke::new(1.0, egui::Color32::YELLOW));
+                }
+            }
+            for room in &layout.rooms {
+                painter.text(to_screen(room.center), egui::Align2::CENTER_CENTER, &room.name, egui::FontId::proportional(11.0), egui::Color32::WHITE);
+            }
+            if let Some(camera) = layout.camera {
+                let tip = to_screen(camera.position);
+                let forward = egui::vec2(camera.forward.x, camera.forward.y);
+                let side = egui::vec2(-forward.y, forward.x);
+                let arrow = vec![tip + forward * 7.0, tip - forward * 4.0 + side * 4.0, tip - forward * 4.0 - side * 4.0];
+                painter.add(egui::Shape::convex_polygon(arrow, egui::Color32::from_rgb(255, 90, 60), egui::Stroke::NONE));
+            }
+        });
+}
+
+// Paints debug labels behind the windows, at their projected positions. Labels seen through
+// deeper portals are drawn smaller and fainter.
+pub fn draw_debug_labels(ctx: &egui::Context, labels: &[ScreenLabel]) {
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("debug_labels")));
+    let pixels_per_point = ctx.pixels_per_point();
+    for label in labels {
+        let position = egui::pos2(label.position[0], label.position[1]) / pixels_per_point;
+        let size = (14.0 - 2.0 * label.recursion_depth as f32).max(8.0);
+        let fade = 0.7f32.powi(label.recursion_depth as i32);
+        let [r, g, b, a] = label.color;
+        let color = egui::Rgba::from_rgba_unmultiplied(r, g, b, a * fade);
+        let font = egui::FontId::monospace(size);
+        painter.text(position + egui::vec2(1.0, 1.0), egui::Align2::CENTER_CENTER, &label.text, font.clone(), egui::Color32::from_black_alpha((200.0 * fade) as u8));
+        painter.text(position, egui::Align2::CENTER_CENTER, &label.text, font, color.into());
+    }
+}
+
+// Paints the HUD over the scene but behind the windows: a crosshair at the main view's center,
+// the interaction prompt under it and the current room's name in the bottom left corner.
+pub fn draw_hud(ctx: &egui::Context, hud: &HudInfo) {
+    let painter = ctx.layer_painter(egui::LayerId::new(egui::Order::Background, egui::Id::new("hud")));
+    let shadow = egui::Color32::from_black_alpha(160);
+    if let Some([x, y]) = hud.crosshair {
+        let center = egui::pos2(x, y) / ctx.pixels_per_point();
+        for direction in [egui::vec2(1.0, 0.0), egui::vec2(-1.0, 0.0), egui::vec2(0.0, 1.0), egui::vec2(0.0, -1.0)] {
+            let arm = [center + direction * CROSSHAIR_GAP, center + direction * (CROSSHAIR_GAP + CROSSHAIR_ARM)];
+            painter.line_segment(arm, egui::Stroke::new(3.0, shadow));
+            painter.line_segment(arm, egui::Stroke::new(1.5, egui::Color32::WHITE));
+        }
+        if let Some(prompt) = &hud.prompt {
+            let position = center + egui::vec2(0.0, CROSSHAIR_GAP + CROSSHAIR_ARM + 12.0);
+            let text = format!("[E] {prompt}");
+            painter.text(position + egui::vec2(1.0, 1.0), egui::Align2::CENTER_TOP, &text, egui::FontId::proportional(16.0), shadow);
+            painter.text(position, egui::Align2::CENTER_TOP, &text, egui::FontId::proportional(16.0), egui::Color32::WHITE);
+        }
+    }
+    if let Some(room_name) = &hud.room_name {
+        let position = ctx.screen_rect().left_bottom() + egui::vec2(12.0, -12.0);
+        painter.text(position + egui::vec2(1.0, 1.0), egui::Align2::LEFT_BOTTOM, room_name, egui::FontId::proportional(18.0), shadow);
+        painter.text(position, egui::Align2::LEFT_BOTTOM, room_name, egui::FontId::proportional(18.0), egui::Color32::WHITE);
+    }
+}
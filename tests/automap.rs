@@ -0,0 +1,53 @@
+// tests/automap.rs
+// The automap of the demo scene: at first only Room1 is on it, with the portal to Room2 marked
+// unexplored; after walking through, Room2 is laid out beyond Room1's front wall, and each room
+// appears once however the portals loop back.
+
+use std::collections::HashSet;
+use std::f32::consts::PI;
+use glam::{Mat4, Vec3};
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::{update_camera_in_scene, Automap};
+
+#[test]
+fn unvisited_rooms_are_left_off_the_map() {
+    let scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    let mut automap = Automap::new();
+    assert!(automap.layout(&scene).rooms.is_empty());
+
+    automap.update(&scene);
+    let layout = automap.layout(&scene);
+    assert_eq!(layout.rooms.len(), 1);
+    assert_eq!(layout.rooms[0].instance_id, room1);
+    let to_room2 = layout.links.iter().find(|link| link.to_instance_id == room2).expect("a portal leads to Room2");
+    assert!(!to_room2.explored);
+    assert!((to_room2.doorway.y - 1.5).abs() < 1e-4, "doorway on the front wall: {:?}", to_room2.doorway);
+
+    let camera = layout.camera.expect("the camera is on the map");
+    assert!((camera.position - glam::Vec2::new(0.0, -1.0)).length() < 1e-4);
+    assert!((camera.forward - glam::Vec2::Y).length() < 1e-4, "facing +Z: {:?}", camera.forward);
+}
+
+#[test]
+fn visited_rooms_are_laid_out_through_their_portals() {
+    let mut scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    let mut automap = Automap::new();
+    automap.update(&scene);
+    update_camera_in_scene(&mut scene, Vec3::new(0.0, 0.0, 1.55), Mat4::from_rotation_y(PI), 0.0);
+    automap.update(&scene);
+
+    let from_room1 = automap.layout_from(&scene, room1);
+    let ids: Vec<u32> = from_room1.rooms.iter().map(|room| room.instance_id).collect();
+    assert_eq!(ids.len(), ids.iter().collect::<HashSet<_>>().len(), "rooms placed more than once: {ids:?}");
+    assert!(ids.contains(&room2));
+    let room2_outline = &from_room1.room(room2).unwrap().outline;
+    assert!(room2_outline.iter().all(|corner| corner.y >= 1.5 - 1e-3), "Room2 should lie beyond the front wall: {room2_outline:?}");
+    assert!(from_room1.links.iter().any(|link| link.from_instance_id == room1 && link.to_instance_id == room2 && link.explored));
+
+    // Laid out around the camera, which is now in Room2.
+    let layout = automap.layout(&scene);
+    assert_eq!(layout.rooms[0].instance_id, room2);
+    assert!(layout.room(room1).is_some());
+}
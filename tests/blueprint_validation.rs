@@ -0,0 +1,146 @@
+// tests/blueprint_validation.rs
+// `HullBlueprint::validate` on the demo's cuboid room broken one way at a time: each break is
+// reported as its own kind of error, the blueprints the demo ships with validate clean, and
+// `validate_blueprints` names the blueprint each error came from.
+
+use glam::Vec3;
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::scene_types::HullBlueprint;
+use engine3_refactored::engine_lib::{validate_blueprints, BlueprintError, SceneDiagnostic};
+
+const FRONT_SIDE: usize = 0;
+const RIGHT_SIDE: usize = 3;
+// Corner (+x, +y, +z), shared by the front, right and top sides.
+const FRONT_RIGHT_TOP: usize = 6;
+
+fn cuboid() -> HullBlueprint {
+    let scene = create_mvp_scene();
+    let room1 = scene.instance_by_name("Room1").unwrap();
+    scene.blueprints[&room1.blueprint_id].clone()
+}
+
+// The cuboid with one more side, a copy of the front wall with the given outline.
+fn with_extra_side(vertex_indices: Vec<usize>) -> (HullBlueprint, usize) {
+    let mut blueprint = cuboid();
+    let mut side = blueprint.sides[FRONT_SIDE].clone();
+    side.vertex_indices = vertex_indices;
+    blueprint.sides.push(side);
+    let side_index = blueprint.sides.len() - 1;
+    (blueprint, side_index)
+}
+
+fn errors(blueprint: &HullBlueprint) -> Vec<BlueprintError> {
+    blueprint.validate().expect_err("blueprint should not validate")
+}
+
+#[test]
+fn demo_blueprints_validate_clean() {
+    let scene = create_mvp_scene();
+    assert!(!scene.blueprints.is_empty());
+    for blueprint in scene.blueprints.values() {
+        assert_eq!(blueprint.validate(), Ok(()), "blueprint {}", blueprint.name);
+    }
+    assert!(validate_blueprints(&scene).is_empty());
+}
+
+#[test]
+fn side_with_two_vertices_has_too_few() {
+    let (blueprint, side_index) = with_extra_side(vec![0, 1]);
+    assert_eq!(errors(&blueprint), vec![BlueprintError::TooFewVertices { side_index }]);
+}
+
+#[test]
+fn side_naming_a_missing_vertex_is_out_of_bounds() {
+    let (blueprint, side_index) = with_extra_side(vec![0, 1, 99]);
+    assert_eq!(errors(&blueprint), vec![BlueprintError::VertexIndexOutOfBounds { side_index, vertex_index: 99 }]);
+}
+
+#[test]
+fn side_enclosing_no_area_is_degenerate() {
+    let (blueprint, side_index) = with_extra_side(vec![0, 1, 0]);
+    assert_eq!(errors(&blueprint), vec![BlueprintError::DegenerateSide { side_index }]);
+}
+
+#[test]
+fn corner_pulled_off_a_wall_makes_it_non_planar() {
+    let mut blueprint = cuboid();
+    // Along x the corner stays in the front and top planes and leaves only the right one.
+    blueprint.local_vertices[FRONT_RIGHT_TOP] += Vec3::new(-0.2, 0.0, 0.0);
+    let errors = errors(&blueprint);
+    assert!(errors.iter().any(|error| matches!(error, BlueprintError::NonPlanarSide { side_index: RIGHT_SIDE, .. })), "{:?}", errors);
+    assert!(errors.iter().all(|error| match error {
+        BlueprintError::NonPlanarSide { side_index, .. } | BlueprintError::NotConvex { side_index, .. } => *side_index == RIGHT_SIDE,
+        _ => false,
+    }), "{:?}", errors);
+}
+
+#[test]
+fn normal_pointing_out_of_the_hull_is_outward() {
+    let mut blueprint = cuboid();
+    let mut side = blueprint.sides[FRONT_SIDE].clone();
+    side.local_normal = -side.local_normal;
+    blueprint.sides.push(side);
+    assert_eq!(errors(&blueprint), vec![BlueprintError::OutwardNormal { side_index: blueprint.sides.len() - 1 }]);
+}
+
+#[test]
+fn reversed_outline_winds_against_its_normal() {
+    let mut blueprint = cuboid();
+    blueprint.sides[FRONT_SIDE].vertex_indices.reverse();
+    let errors = errors(&blueprint);
+    assert!(errors.contains(&BlueprintError::WindingAgainstNormal { side_index: FRONT_SIDE }), "{:?}", errors);
+}
+
+#[test]
+fn reversed_outline_disagrees_with_every_neighbour() {
+    let mut blueprint = cuboid();
+    blueprint.sides[FRONT_SIDE].vertex_indices.reverse();
+    let mut neighbours: Vec<usize> = errors(&blueprint).into_iter()
+        .filter_map(|error| match error {
+            BlueprintError::InconsistentWinding { side_index: FRONT_SIDE, other_side_index, .. } => Some(other_side_index),
+            _ => None,
+        })
+        .collect();
+    neighbours.sort_unstable();
+    // Left, right, top and bottom each share one edge with the front.
+    assert_eq!(neighbours, vec![2, 3, 4, 5]);
+}
+
+#[test]
+fn vertex_beyond_a_wall_is_not_convex() {
+    let mut blueprint = cuboid();
+    blueprint.local_vertices.push(Vec3::new(0.0, 0.0, 5.0));
+    let vertex_index = blueprint.local_vertices.len() - 1;
+    assert_eq!(errors(&blueprint), vec![BlueprintError::NotConvex { side_index: FRONT_SIDE, vertex_index }]);
+}
+
+#[test]
+fn missing_side_leaves_open_edges() {
+    let mut blueprint = cuboid();
+    let removed = blueprint.sides.pop().unwrap();
+    let errors = errors(&blueprint);
+    assert_eq!(errors.len(), removed.vertex_indices.len(), "{:?}", errors);
+    assert!(errors.iter().all(|error| matches!(error, BlueprintError::OpenEdge { .. })), "{:?}", errors);
+}
+
+#[test]
+fn duplicated_side_makes_its_edges_non_manifold() {
+    let mut blueprint = cuboid();
+    let duplicate = blueprint.sides[FRONT_SIDE].clone();
+    blueprint.sides.push(duplicate);
+    let errors = errors(&blueprint);
+    assert_eq!(errors.len(), 4, "{:?}", errors);
+    assert!(errors.iter().all(|error| matches!(error, BlueprintError::NonManifoldEdge { .. })), "{:?}", errors);
+}
+
+#[test]
+fn validate_blueprints_names_the_broken_blueprint() {
+    let mut scene = create_mvp_scene();
+    let blueprint_id = scene.instance_by_name("Room1").unwrap().blueprint_id;
+    scene.blueprints.get_mut(&blueprint_id).unwrap().sides[FRONT_SIDE].vertex_indices.truncate(2);
+    let diagnostics = validate_blueprints(&scene);
+    assert!(diagnostics.iter().any(|diagnostic| matches!(diagnostic,
+        SceneDiagnostic::InvalidBlueprint { blueprint_id: id, error: BlueprintError::TooFewVertices { side_index: FRONT_SIDE } } if *id == blueprint_id)),
+        "{:?}", diagnostics);
+    assert!(diagnostics.iter().all(|diagnostic| matches!(diagnostic, SceneDiagnostic::InvalidBlueprint { blueprint_id: id, .. } if *id == blueprint_id)));
+}
@@ -0,0 +1,161 @@
+// tests/clipper_properties.rs
+// Invariants of `ConvexIntersection::find_intersection_into` over random convex polygons. Clipper
+// bugs otherwise only show up as flickering portals, so these are checked on far more shapes than
+// anyone would draw by hand, including integer-grid ones full of shared and collinear edges.
+// `intersection_area` must measure the same region without building it, and refuse results
+// too large for its buffers.
+use proptest::prelude::*;
+
+use engine3_refactored::rendering_lib::geometry::{ConvexPolygon, Point2, Scalar, MAX_VERTICES};
+use engine3_refactored::rendering_lib::intersection::ConvexIntersection;
+
+// Inputs are kept small enough that their intersection always fits in `MAX_VERTICES`.
+const MAX_INPUT_VERTICES: usize = MAX_VERTICES / 2;
+const COORDINATE_RANGE: f64 = 100.0;
+
+// How far outside an input a result vertex may lie, and how much larger than an input its area
+// may be, relative to the size of the inputs.
+const F32_TOLERANCE: f64 = 1e-3;
+const F64_TOLERANCE: f64 = 1e-9;
+
+// A convex polygon with vertices on an axis-aligned ellipse, counter-clockwise.
+fn ellipse_polygon() -> impl Strategy<Value = Vec<(f64, f64)>> {
+    (
+        -COORDINATE_RANGE..COORDINATE_RANGE,
+        -COORDINATE_RANGE..COORDINATE_RANGE,
+        1e-3..COORDINATE_RANGE,
+        1e-3..COORDINATE_RANGE,
+        prop::collection::vec(0.0..std::f64::consts::TAU, 3..=MAX_INPUT_VERTICES),
+    )
+        .prop_map(|(cx, cy, rx, ry, mut angles)| {
+            angles.sort_by(f64::total_cmp);
+            angles.dedup();
+            angles.into_iter().map(|angle| (cx + rx * angle.cos(), cy + ry * angle.sin())).collect()
+        })
+}
+
+// The convex hull of a few points on a coarse integer grid, so that two such polygons often
+// share vertices, touch along edges or have collinear ones.
+fn grid_polygon() -> impl Strategy<Value = Vec<(f64, f64)>> {
+    prop::collection::vec((-4i32..=4, -4i32..=4), 3..=MAX_INPUT_VERTICES)
+        .prop_map(|points| points.into_iter().map(|(x, y)| (x as f64 * 10.0, y as f64 * 10.0)).collect())
+}
+
+fn polygon_points() -> impl Strategy<Value = Vec<(f64, f64)>> {
+    prop_oneof![ellipse_polygon(), grid_polygon()]
+}
+
+fn to_polygon<T: Scalar>(points: &[(f64, f64)]) -> Option<ConvexPolygon<T>> {
+    let points: Vec<Point2<T>> = points.iter().map(|&(x, y)| Point2::new(T::from_f64(x), T::from_f64(y))).collect();
+    ConvexPolygon::convex_hull_of(&points).filter(|polygon| polygon.count() >= 3)
+}
+
+// Largest distance from `point` to the outside of the counter-clockwise `polygon`: positive when
+// outside, zero or negative inside. Edges no longer than `min_edge_length` are skipped, since the
+// clipper may emit near-duplicate vertices whose edge direction is just rounding noise; the
+// neighbouring edges bound the polygon there anyway.
+fn distance_outside<T: Scalar>(polygon: &ConvexPolygon<T>, point: &Point2<T>, min_edge_length: f64) -> f64 {
+    let vertices = polygon.vertices();
+    let (px, py) = (point.x.to_f64(), point.y.to_f64());
+    let mut outside = f64::NEG_INFINITY;
+    for (i, start) in vertices.iter().enumerate() {
+        let end = vertices[(i + 1) % vertices.len()];
+        let (ax, ay) = (start.x.to_f64(), start.y.to_f64());
+        let (ex, ey) = (end.x.to_f64() - ax, end.y.to_f64() - ay);
+        let length = (ex * ex + ey * ey).sqrt();
+        if length <= min_edge_length {
+            continue;
+        }
+        outside = outside.max((ey * (px - ax) - ex * (py - ay)) / length);
+    }
+    outside
+}
+
+// The size the tolerances scale with: the largest coordinate of either input.
+fn extent<T: Scalar>(a: &ConvexPolygon<T>, b: &ConvexPolygon<T>) -> f64 {
+    a.vertices().iter().chain(b.vertices())
+        .map(|p| p.x.to_f64().abs().max(p.y.to_f64().abs()))
+        .fold(1.0, f64::max)
+}
+
+fn intersect<T: Scalar>(a: &ConvexPolygon<T>, b: &ConvexPolygon<T>) -> ConvexPolygon<T> {
+    let mut result = ConvexPolygon::new();
+    assert!(ConvexIntersection::find_intersection_into(a, b, &mut result), "intersection did not fit in MAX_VERTICES");
+    result
+}
+
+fn check_invariants<T: Scalar>(a_points: &[(f64, f64)], b_points: &[(f64, f64)], tolerance: f64) -> Result<(), TestCaseError> {
+    let (a, b) = match (to_polygon::<T>(a_points), to_polygon::<T>(b_points)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return Ok(()),
+    };
+    let extent = extent(&a, &b);
+    let distance_tolerance = tolerance * extent;
+    let area_tolerance = tolerance * extent * extent;
+
+    let ab = intersect(&a, &b);
+    let ba = intersect(&b, &a);
+    let ab_area = ab.area().to_f64();
+    let ba_area = ba.area().to_f64();
+    let measured_area = ConvexIntersection::intersection_area(&a, &b).expect("intersection fits in MAX_VERTICES").to_f64();
+    prop_assert!((measured_area - ab_area).abs() <= area_tolerance, "measured area {} but built {}", measured_area, ab_area);
+
+    prop_assert!(ab_area <= a.area().to_f64() + area_tolerance, "area {} exceeds first input's {}", ab_area, a.area().to_f64());
+    prop_assert!(ab_area <= b.area().to_f64() + area_tolerance, "area {} exceeds second input's {}", ab_area, b.area().to_f64());
+    for vertex in ab.vertices() {
+        prop_assert!(distance_outside(&a, vertex, distance_tolerance) <= distance_tolerance, "vertex {:?} outside first input by {}", vertex, distance_outside(&a, vertex, distance_tolerance));
+        prop_assert!(distance_outside(&b, vertex, distance_tolerance) <= distance_tolerance, "vertex {:?} outside second input by {}", vertex, distance_outside(&b, vertex, distance_tolerance));
+    }
+
+    // Clipping a by b and b by a visit the edges in different orders, but must cut out the same region.
+    prop_assert!((ab_area - ba_area).abs() <= area_tolerance, "area {} one way, {} the other", ab_area, ba_area);
+    if ab.count() >= 3 && ba.count() >= 3 {
+        for vertex in ab.vertices() {
+            prop_assert!(distance_outside(&ba, vertex, distance_tolerance) <= distance_tolerance, "vertex {:?} of a∩b outside b∩a", vertex);
+        }
+        for vertex in ba.vertices() {
+            prop_assert!(distance_outside(&ab, vertex, distance_tolerance) <= distance_tolerance, "vertex {:?} of b∩a outside a∩b", vertex);
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2048))]
+
+    #[test]
+    fn intersection_invariants_f32(a in polygon_points(), b in polygon_points()) {
+        check_invariants::<f32>(&a, &b, F32_TOLERANCE)?;
+    }
+
+    #[test]
+    fn intersection_invariants_f64(a in polygon_points(), b in polygon_points()) {
+        check_invariants::<f64>(&a, &b, F64_TOLERANCE)?;
+    }
+
+    // Anything intersected with itself is itself.
+    #[test]
+    fn self_intersection_is_identity(a in polygon_points()) {
+        if let Some(polygon) = to_polygon::<f64>(&a) {
+            let result = intersect(&polygon, &polygon);
+            let area = polygon.area();
+            prop_assert!((result.area() - area).abs() <= F64_TOLERANCE * area.max(1.0));
+        }
+    }
+}
+
+// Two squares at 45 degrees meet in an octagon, which a four-vertex polygon cannot hold.
+#[test]
+fn intersections_too_large_for_the_buffers_are_refused() {
+    let square = |points: [(f32, f32); 4]| ConvexPolygon::<f32, 4>::from_points(&points.map(|(x, y)| Point2::new(x, y)));
+    let axis_aligned = square([(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)]);
+    let diamond = square([(0.0, -1.3), (1.3, 0.0), (0.0, 1.3), (-1.3, 0.0)]);
+    let mut result = ConvexPolygon::<f32, 4>::new();
+    assert!(!ConvexIntersection::find_intersection_into(&axis_aligned, &diamond, &mut result));
+    assert_eq!(ConvexIntersection::intersection_area(&axis_aligned, &diamond), None);
+
+    let inner = square([(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]);
+    assert_eq!(ConvexIntersection::intersection_area(&axis_aligned, &inner), Some(1.0));
+    let apart = square([(3.0, 3.0), (4.0, 3.0), (4.0, 4.0), (3.0, 4.0)]);
+    assert_eq!(ConvexIntersection::intersection_area(&axis_aligned, &apart), Some(0.0));
+}
@@ -0,0 +1,26 @@
+// tests/common/mod.rs
+// Measurements of hulls shared by the tests that build them from solids, meshes and maps.
+#![allow(dead_code)]
+
+use glam::{Mat4, Vec3};
+use engine3_refactored::engine_lib::placement::hull_contains_point;
+use engine3_refactored::engine_lib::{HullBlueprint, HullInstance};
+
+// Enclosed volume, from the sides fanned into triangles with the divergence theorem.
+pub fn blueprint_volume(blueprint: &HullBlueprint) -> f32 {
+    let corner = |index: usize| blueprint.local_vertices[index];
+    let signed: f32 = blueprint.sides.iter()
+        .flat_map(|side| {
+            let indices = &side.vertex_indices;
+            (1..indices.len().saturating_sub(1)).map(move |i| (indices[0], indices[i], indices[i + 1]))
+        })
+        .map(|(a, b, c)| corner(a).dot(corner(b).cross(corner(c))) / 6.0)
+        .sum();
+    signed.abs()
+}
+
+// True if the world-space `point` is inside the placed instance, sides included.
+pub fn instance_contains(blueprint: &HullBlueprint, instance: &HullInstance, point: Vec3) -> bool {
+    let local = instance.initial_transform.unwrap_or(Mat4::IDENTITY).inverse().transform_point3(point);
+    hull_contains_point(blueprint, local)
+}
@@ -0,0 +1,72 @@
+// tests/convex_hull.rs
+// Quickhull on awkward point clouds: a cube given with points on its faces and edges, repeated
+// and nearly repeated corners and points inside still comes out as the cube, and clouds that
+// are flat, or flat to within the tolerance, are refused.
+
+mod common;
+
+use glam::Vec3;
+use engine3_refactored::engine_lib::{convex_hull_blueprint, quickhull, ConvexHullError, ConvexHullOptions};
+use common::blueprint_volume;
+
+const TOLERANCE: f32 = 1e-4;
+
+fn cube_corners() -> Vec<Vec3> {
+    (0..8).map(|i| Vec3::new((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32) * 2.0 - Vec3::ONE).collect()
+}
+
+// The cube's corners along with face centers, edge midpoints, duplicated and jittered corners
+// and interior points.
+fn cluttered_cube() -> Vec<Vec3> {
+    let mut points = cube_corners();
+    for axis in [Vec3::X, Vec3::Y, Vec3::Z] {
+        points.push(axis);
+        points.push(-axis);
+        points.push(Vec3::ONE - axis);
+        points.push(axis - Vec3::ONE);
+    }
+    points.extend(cube_corners());
+    points.extend(cube_corners().iter().map(|&corner| corner * (1.0 - 1e-6)));
+    points.extend([Vec3::ZERO, Vec3::new(0.3, -0.2, 0.5), Vec3::new(-0.9, 0.9, 0.1)]);
+    points
+}
+
+#[test]
+fn quickhull_keeps_only_the_cube_surface() {
+    let points = cluttered_cube();
+    let triangles = quickhull(&points, TOLERANCE).unwrap();
+    assert_eq!(triangles.len(), 12, "two triangles per face");
+    for [a, b, c] in &triangles {
+        let (a, b, c) = (points[*a], points[*b], points[*c]);
+        let normal = (b - a).cross(c - a);
+        assert!(normal.dot((a + b + c) / 3.0) > 0.0, "triangle faces inwards");
+        for point in &points {
+            assert!(normal.normalize().dot(*point - a) <= TOLERANCE, "{point} is outside the hull");
+        }
+    }
+
+    let blueprint = convex_hull_blueprint(0, "Cube", &points, &ConvexHullOptions::default()).unwrap();
+    assert_eq!(blueprint.sides.len(), 6, "coplanar triangles merge into one side per face");
+    assert_eq!(blueprint.local_vertices.len(), 8, "repeated corners are welded");
+    assert!((blueprint_volume(&blueprint) - 8.0).abs() < 1e-3);
+}
+
+#[test]
+fn flat_and_nearly_flat_clouds_are_refused() {
+    assert_eq!(quickhull(&cube_corners()[..3], TOLERANCE), Err(ConvexHullError::TooFewPoints));
+    let square = [Vec3::ZERO, Vec3::X, Vec3::new(1.0, 0.0, 1.0), Vec3::Z, Vec3::new(0.5, 0.0, 0.5)];
+    assert_eq!(quickhull(&square, TOLERANCE), Err(ConvexHullError::Flat));
+    let line: Vec<Vec3> = (0..6).map(|i| Vec3::splat(i as f32)).collect();
+    assert_eq!(quickhull(&line, TOLERANCE), Err(ConvexHullError::Flat));
+    assert_eq!(quickhull(&[Vec3::ONE; 5], TOLERANCE), Err(ConvexHullError::Flat));
+
+    // Lifting one point off the square by less than the tolerance is still flat; by more, it is
+    // a thin pyramid.
+    let mut pyramid = square;
+    pyramid[4].y = TOLERANCE * 0.5;
+    assert_eq!(quickhull(&pyramid, TOLERANCE), Err(ConvexHullError::Flat));
+    pyramid[4].y = 0.01;
+    let blueprint = convex_hull_blueprint(0, "Pyramid", &pyramid, &ConvexHullOptions::default()).unwrap();
+    assert_eq!(blueprint.sides.len(), 5);
+    assert!((blueprint_volume(&blueprint) - 0.01 / 3.0).abs() < 1e-5);
+}
@@ -0,0 +1,106 @@
+// tests/convex_polygon.rs
+// `ConvexPolygon` helpers on small hand-made shapes: hulls of point sets with duplicate and
+// collinear points, inflation and Minkowski sums, and changing precision or capacity.
+
+use engine3_refactored::rendering_lib::geometry::{ConvexPolygon, Point2};
+
+fn points(coordinates: &[(f32, f32)]) -> Vec<Point2> {
+    coordinates.iter().map(|&(x, y)| Point2::new(x, y)).collect()
+}
+
+// Positive when every turn is to the left, as `ConvexIntersection` wants of a clip polygon.
+fn signed_area(polygon: &ConvexPolygon) -> f32 {
+    let vertices = polygon.vertices();
+    (0..vertices.len())
+        .map(|i| {
+            let (a, b) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum::<f32>() * 0.5
+}
+
+#[test]
+fn hulls_drop_duplicate_collinear_and_inner_points() {
+    // A 2x2 square given clockwise, with a repeated corner, midpoints on two edges and a point
+    // inside.
+    let input = points(&[
+        (0.0, 0.0), (0.0, 2.0), (0.0, 1.0), (2.0, 2.0), (2.0, 2.0), (1.0, 2.0), (2.0, 0.0), (1.0, 1.0), (0.0, 0.0),
+    ]);
+    let hull = ConvexPolygon::<f32>::convex_hull_of(&input).unwrap();
+    assert_eq!(hull.count(), 4, "hull {:?}", hull.vertices());
+    for corner in points(&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)]) {
+        assert!(hull.vertices().contains(&corner), "missing {corner:?}");
+    }
+    assert!((signed_area(&hull) - 4.0).abs() < 1e-6, "wound the wrong way: {:?}", hull.vertices());
+    for &point in &input {
+        assert!(hull.contains_point(&point));
+    }
+}
+
+#[test]
+fn hulls_of_degenerate_point_sets() {
+    assert_eq!(ConvexPolygon::<f32>::convex_hull_of(&[]).unwrap().count(), 0);
+    let single = ConvexPolygon::<f32>::convex_hull_of(&points(&[(1.0, 1.0), (1.0, 1.0)])).unwrap();
+    assert_eq!(single.vertices(), &points(&[(1.0, 1.0)])[..]);
+    assert_eq!(ConvexPolygon::<f32>::convex_hull_of(&points(&[(0.0, 0.0), (3.0, 1.0)])).unwrap().count(), 2);
+    // All on one line: only the two ends are left, with no area.
+    let line = ConvexPolygon::<f32>::convex_hull_of(&points(&[(0.0, 0.0), (2.0, 2.0), (1.0, 1.0), (3.0, 3.0)])).unwrap();
+    assert_eq!(line.count(), 2, "line {:?}", line.vertices());
+    assert_eq!(line.area(), 0.0);
+}
+
+#[test]
+fn hulls_that_do_not_fit_are_refused() {
+    let circle: Vec<Point2> = (0..12)
+        .map(|i| {
+            let angle = i as f32 * std::f32::consts::TAU / 12.0;
+            Point2::new(angle.cos(), angle.sin())
+        })
+        .collect();
+    assert_eq!(ConvexPolygon::<f32, 12>::convex_hull_of(&circle).unwrap().count(), 12);
+    assert!(ConvexPolygon::<f32, 8>::convex_hull_of(&circle).is_none());
+}
+
+fn square(min: f32, max: f32) -> ConvexPolygon {
+    ConvexPolygon::from_points(&points(&[(min, min), (max, min), (max, max), (min, max)]))
+}
+
+#[test]
+fn inflating_moves_every_edge_out() {
+    let unit = square(0.0, 1.0);
+    assert_eq!(unit.inflated(0.0).vertices(), unit.vertices());
+
+    let grown = unit.inflated(0.5);
+    assert_eq!(grown.count(), 4);
+    for (corner, expected) in grown.vertices().iter().zip(square(-0.5, 1.5).vertices()) {
+        assert!((corner.x - expected.x).abs() < 1e-6 && (corner.y - expected.y).abs() < 1e-6, "{:?}", grown.vertices());
+    }
+    // Clockwise input grows outward too.
+    let clockwise = ConvexPolygon::<f32>::from_points(&points(&[(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)]));
+    assert!((clockwise.inflated(0.5).area() - 4.0).abs() < 1e-5);
+    assert!((unit.inflated(-0.25).area() - 0.25).abs() < 1e-5);
+}
+
+#[test]
+fn minkowski_sum_of_squares() {
+    let sum = square(0.0, 1.0).minkowski_sum(&square(-1.0, 1.0)).unwrap();
+    assert_eq!(sum.count(), 4, "sum {:?}", sum.vertices());
+    assert!((sum.area() - 9.0).abs() < 1e-5);
+    for corner in points(&[(-1.0, -1.0), (2.0, -1.0), (2.0, 2.0), (-1.0, 2.0)]) {
+        assert!(sum.vertices().contains(&corner), "missing {corner:?}");
+    }
+    // Adding a single point only moves the square.
+    let point = ConvexPolygon::<f32, 1>::from_points(&points(&[(3.0, -2.0)]));
+    let moved = square(0.0, 1.0).minkowski_sum(&point).unwrap();
+    assert!((moved.area() - 1.0).abs() < 1e-6);
+    assert!(moved.vertices().contains(&Point2::new(3.0, -2.0)));
+}
+
+#[test]
+fn cast_refuses_polygons_that_do_not_fit() {
+    let hexagon: ConvexPolygon = ConvexPolygon::from_points(&points(&[(2.0, 0.0), (1.0, 2.0), (-1.0, 2.0), (-2.0, 0.0), (-1.0, -2.0), (1.0, -2.0)]));
+    let wide = hexagon.cast::<f64, 8>().expect("six vertices fit in eight");
+    assert_eq!(wide.count(), 6);
+    assert_eq!(wide.vertices()[1], Point2::new(1.0, 2.0));
+    assert!(hexagon.cast::<f32, 4>().is_none(), "cutting it to four vertices would be another polygon");
+}
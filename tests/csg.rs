@@ -0,0 +1,50 @@
+// tests/csg.rs
+// Carving convex solids into cells: a pillar through a room and a crate floating in it are cut
+// out, leaving disjoint cells that fill the rest of the room exactly, with portals wherever they
+// touch.
+
+mod common;
+
+use glam::Vec3;
+use engine3_refactored::engine_lib::{CsgModel, ConvexSolid, HandlerConfig};
+use common::{blueprint_volume, instance_contains};
+
+fn wall() -> HandlerConfig {
+    HandlerConfig::StandardWall { color: [0.6, 0.6, 0.6, 1.0], texture_id: None, emissive: 0.0, render_priority: 0 }
+}
+
+// A 4x3x4 room with `solid` carved out of it, as (hull count, total volume, portal count) plus
+// how many hulls each of `points` is in.
+fn carve(solid: ConvexSolid, points: &[Vec3]) -> (usize, f32, usize, Vec<usize>) {
+    let mut model = CsgModel::new();
+    model.add(&ConvexSolid::cuboid(Vec3::new(-2.0, 0.0, -2.0), Vec3::new(2.0, 3.0, 2.0), wall()));
+    model.subtract(&solid);
+    let hulls = model.build_hulls();
+    let volume = hulls.instances.values().map(|instance| blueprint_volume(&hulls.blueprints[&instance.blueprint_id])).sum();
+    let portals = hulls.instances.values().map(|instance| instance.portal_connections.len()).sum();
+    let containing = points.iter()
+        .map(|&point| hulls.instances.values().filter(|instance| instance_contains(&hulls.blueprints[&instance.blueprint_id], instance, point)).count())
+        .collect();
+    (hulls.instances.len(), volume, portals, containing)
+}
+
+#[test]
+fn subtracting_a_pillar_leaves_four_cells_around_it() {
+    let pillar = ConvexSolid::cuboid(Vec3::new(-0.5, 0.0, -0.5), Vec3::new(0.5, 3.0, 0.5), wall());
+    let points = [Vec3::new(0.0, 1.5, 0.0), Vec3::new(1.5, 1.5, 1.5), Vec3::new(-1.5, 0.5, 0.0)];
+    let (hulls, volume, portals, containing) = carve(pillar, &points);
+    assert_eq!(hulls, 4);
+    assert!((volume - (48.0 - 3.0)).abs() < 1e-3, "volume {volume}");
+    assert!(portals >= 2 * 3, "the four cells should be joined up: {portals} portal ends");
+    assert_eq!(containing, vec![0, 1, 1], "the pillar is solid and the rest of the room open once");
+}
+
+#[test]
+fn subtracting_a_floating_box_encloses_it_on_every_side() {
+    let crate_box = ConvexSolid::cuboid(Vec3::new(-0.5, 1.0, -0.5), Vec3::new(0.5, 2.0, 0.5), wall());
+    let points = [Vec3::new(0.0, 1.5, 0.0), Vec3::new(0.0, 0.5, 0.0), Vec3::new(0.0, 2.5, 0.0), Vec3::new(1.5, 1.5, 0.0)];
+    let (hulls, volume, _, containing) = carve(crate_box, &points);
+    assert_eq!(hulls, 6);
+    assert!((volume - (48.0 - 1.0)).abs() < 1e-3, "volume {volume}");
+    assert_eq!(containing, vec![0, 1, 1, 1]);
+}
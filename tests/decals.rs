@@ -0,0 +1,86 @@
+// tests/decals.rs
+// Decals stuck on the demo's Room1 left wall where a ray from the start camera hits it: the decal
+// lies flat on the wall, and when drawn it is clipped to the wall however large it is.
+
+use glam::{Mat4, Vec3, Vec4Swizzles};
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::scene_types::Scene;
+use engine3_refactored::engine_lib::{raycast_scene, update_camera_in_scene, DecalId};
+
+const LEFT_SIDE: usize = 2;
+const DECAL_COLOR: [f32; 4] = [0.9, 0.1, 0.1, 0.75];
+
+// Shoots a ray from the camera towards -X and sticks a decal of `size` where it lands.
+fn scene_with_left_decal(size: f32) -> (Scene, DecalId) {
+    let mut scene = create_mvp_scene();
+    let camera = scene.active_camera().unwrap();
+    let origin = camera.local_transform.w_axis.xyz();
+    let hit = raycast_scene(&scene, camera.host_instance_id, origin, Vec3::NEG_X, 4).expect("the left wall is hit");
+    assert_eq!(hit.side_index, LEFT_SIDE);
+    let id = scene.place_decal_at_hit(&hit, size, DECAL_COLOR).unwrap();
+    (scene, id)
+}
+
+#[test]
+fn decals_lie_on_the_hit_side() {
+    let (scene, id) = scene_with_left_decal(0.5);
+    let decal = &scene.decals[&id];
+    let instance = &scene.instances[&decal.instance_id];
+    let side = &scene.blueprints[&instance.blueprint_id].sides[decal.side_index];
+    assert!((decal.local_position.x + 1.5).abs() < 1e-4, "decal at {:?}", decal.local_position);
+    for corner in decal.corners(side.local_normal) {
+        assert!((corner.x + 1.5).abs() < 1e-4, "corner off the wall: {corner:?}");
+        assert!((corner - decal.local_position).length() < 0.5);
+    }
+
+    let mut scene = scene;
+    assert!(scene.remove_decal(id).is_some());
+    assert!(scene.decals.is_empty());
+}
+
+#[cfg(feature = "render")]
+fn decal_screen_area(scene: &mut Scene) -> f32 {
+    use engine3_refactored::engine_lib::camera::Camera;
+    use engine3_refactored::engine_lib::scene_types::RENDER_LAYER_ALL;
+    use engine3_refactored::rendering_lib::renderer::{SceneView, Viewport};
+    use engine3_refactored::rendering_lib::{RenderSettings, SceneTraversal};
+
+    // Face the left wall head on.
+    update_camera_in_scene(scene, Vec3::new(0.0, 0.0, -1.0), Mat4::from_rotation_y(std::f32::consts::FRAC_PI_2), 0.0);
+    let camera = Camera::new(70.0, 0.1, 100.0);
+    let view = SceneView::from_scene_camera(scene, &scene.active_camera, &camera, Viewport::full(320.0, 240.0)).unwrap();
+    let mut traversal = SceneTraversal::new();
+    traversal.build_frame(scene, &[view], &RenderSettings::default(), [0.0, 0.0, 0.0, 1.0], RENDER_LAYER_ALL);
+
+    let vertices = traversal.vertices();
+    traversal
+        .indices()
+        .chunks_exact(3)
+        .filter(|triangle| triangle.iter().all(|&index| vertices[index as usize].color[3] == DECAL_COLOR[3]))
+        .map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|corner| vertices[triangle[corner] as usize].position);
+            ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])).abs() * 0.5
+        })
+        .sum()
+}
+
+#[cfg(feature = "render")]
+#[test]
+fn decals_are_drawn_clipped_to_their_side() {
+    let (mut small_scene, _) = scene_with_left_decal(0.3);
+    let small = decal_screen_area(&mut small_scene);
+    let (mut medium_scene, _) = scene_with_left_decal(0.6);
+    let medium = decal_screen_area(&mut medium_scene);
+    assert!(small > 0.0, "the decal is drawn");
+    assert!((medium / small - 4.0).abs() < 0.2, "doubling the size should quadruple the area: {small} vs {medium}");
+
+    // Far larger than the wall: only the wall's share of the view is covered, the same as by a
+    // decal just big enough to cover the part of the wall in view, and the neighbouring walls
+    // are left alone.
+    let (mut huge_scene, _) = scene_with_left_decal(50.0);
+    let huge = decal_screen_area(&mut huge_scene);
+    let (mut wall_scene, _) = scene_with_left_decal(3.0);
+    let wall = decal_screen_area(&mut wall_scene);
+    assert!((huge - wall).abs() <= wall * 0.01, "decal spilled off its side: {huge} vs {wall}");
+    assert!(huge < 320.0 * 240.0 * 0.9, "decal covers the whole view: {huge}");
+}
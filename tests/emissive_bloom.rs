@@ -0,0 +1,69 @@
+// tests/emissive_bloom.rs
+// An emissive wall in the demo's Room1 seen from the start camera: with bloom on, it glows onto
+// the pixels around it and nothing else changes, while the plain demo has nothing bright enough
+// to bloom; the GPU's tonemap pass blooms like the software reference wherever an adapter is
+// available.
+#![cfg(feature = "render")]
+
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::camera::Camera;
+use engine3_refactored::engine_lib::scene_types::{Scene, RENDER_LAYER_ALL};
+use engine3_refactored::engine_lib::HandlerConfig;
+use engine3_refactored::rendering_lib::{BloomSettings, HeadlessError, HeadlessRenderer, SoftwareRenderer};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+const CLEAR_COLOR: wgpu::Color = wgpu::Color::BLACK;
+const LEFT_SIDE: usize = 2;
+
+fn scene_with_glowing_left_wall(emissive: f32) -> Scene {
+    let mut scene = create_mvp_scene();
+    let room1 = scene.instance_by_name("Room1").unwrap().id;
+    let panel = HandlerConfig::StandardWall { color: [0.9, 0.8, 0.4, 1.0], texture_id: None, emissive, render_priority: 0 };
+    scene.instances.get_mut(&room1).unwrap().instance_side_handler_configs.insert(LEFT_SIDE, panel);
+    scene
+}
+
+fn software_frame(scene: &Scene, bloom: Option<BloomSettings>) -> Vec<u8> {
+    let mut renderer = SoftwareRenderer::new();
+    renderer.settings.bloom = bloom;
+    renderer.render_scene(scene, &Camera::new(70.0, 0.1, 100.0), WIDTH as f32, HEIGHT as f32, CLEAR_COLOR, RENDER_LAYER_ALL);
+    renderer.pixels().to_vec()
+}
+
+#[test]
+fn only_emissive_surfaces_bloom() {
+    let plain = scene_with_glowing_left_wall(0.0);
+    assert_eq!(software_frame(&plain, Some(BloomSettings::default())), software_frame(&plain, None));
+
+    let glowing = scene_with_glowing_left_wall(3.0);
+    let bloomed = software_frame(&glowing, Some(BloomSettings::default()));
+    let unbloomed = software_frame(&glowing, None);
+    assert!(bloomed.iter().zip(&unbloomed).all(|(with, without)| with >= without), "bloom only adds light");
+    let brightened = bloomed.chunks_exact(4).zip(unbloomed.chunks_exact(4)).filter(|(with, without)| with != without).count();
+    assert!(brightened > 0, "the emissive wall should glow onto its surroundings");
+}
+
+#[test]
+fn gpu_bloom_matches_software() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let mut headless = match runtime.block_on(HeadlessRenderer::new(WIDTH, HEIGHT)) {
+        Ok(headless) => headless,
+        Err(HeadlessError::NoAdapter) => {
+            eprintln!("no GPU adapter, skipping");
+            return;
+        }
+        Err(e) => panic!("{e}"),
+    };
+    headless.renderer_mut().settings.msaa_samples = 1;
+    headless.renderer_mut().settings.bloom = Some(BloomSettings::default());
+    let scene = scene_with_glowing_left_wall(3.0);
+    let (gpu_frame, _) = headless.render(&scene, &Camera::new(70.0, 0.1, 100.0), CLEAR_COLOR, RENDER_LAYER_ALL).unwrap();
+
+    // Half floats round the bloom a little differently from the software target's f32s.
+    let max_difference = gpu_frame.iter().zip(&software_frame(&scene, Some(BloomSettings::default())))
+        .map(|(gpu, software)| gpu.abs_diff(*software))
+        .max()
+        .unwrap_or(0);
+    assert!(max_difference <= 3, "frames differ by up to {max_difference}");
+}
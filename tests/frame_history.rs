@@ -0,0 +1,44 @@
+// tests/frame_history.rs
+// The profiler's rolling frame history: it keeps only the newest frames, and its averages and
+// unaccounted time add up.
+
+use std::time::Duration;
+
+use engine3_refactored::profiler::{FrameTimeHistory, FrameTimings};
+
+fn frame(frame_ms: u64, traversal_ms: u64, gpu_wait_ms: u64, ui_ms: u64) -> FrameTimings {
+    FrameTimings {
+        frame: Duration::from_millis(frame_ms),
+        traversal: Duration::from_millis(traversal_ms),
+        gpu_wait: Duration::from_millis(gpu_wait_ms),
+        ui: Duration::from_millis(ui_ms),
+    }
+}
+
+#[test]
+fn drops_oldest_frames_when_full() {
+    let mut history = FrameTimeHistory::new(3);
+    for frame_ms in 1..=5 {
+        history.push(frame(frame_ms, 0, 0, 0));
+    }
+    let kept: Vec<u64> = history.iter().map(|timings| timings.frame.as_millis() as u64).collect();
+    assert_eq!(kept, vec![3, 4, 5]);
+    assert_eq!(history.latest().unwrap().frame, Duration::from_millis(5));
+    assert_eq!(history.max_frame_time(), Duration::from_millis(5));
+}
+
+#[test]
+fn averages_each_stage() {
+    let mut history = FrameTimeHistory::new(8);
+    assert_eq!(history.average(), FrameTimings::default());
+    history.push(frame(10, 2, 4, 1));
+    history.push(frame(20, 4, 8, 3));
+    assert_eq!(history.average(), frame(15, 3, 6, 2));
+}
+
+#[test]
+fn other_is_the_unmeasured_remainder() {
+    assert_eq!(frame(16, 3, 8, 2).other(), Duration::from_millis(3));
+    // Stages measured across a frame boundary can add up to more than the frame.
+    assert_eq!(frame(5, 3, 8, 2).other(), Duration::ZERO);
+}
@@ -0,0 +1,33 @@
+// tests/hot_reload.rs
+// Reloading the demo scene over a running copy of it: cameras keep where they were, the file's
+// camera settings win over the old ones, runtime-only cameras come along, and cameras whose
+// room is gone are dropped.
+
+use glam::{Mat4, Vec3};
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::carry_over_runtime_state;
+use engine3_refactored::engine_lib::scene_types::{SceneCamera, DEFAULT_CAMERA_NAME};
+
+#[test]
+fn reloads_keep_camera_poses_but_take_the_files_settings() {
+    let mut old = create_mvp_scene();
+    let room1 = old.instance_by_name("Room1").unwrap().id;
+    let moved = Mat4::from_translation(Vec3::new(0.5, 0.0, 0.25)) * Mat4::from_rotation_y(1.0);
+    old.active_camera_mut().unwrap().local_transform = moved;
+    old.cameras.insert("spectator".to_string(), SceneCamera { host_instance_id: room1, local_transform: Mat4::IDENTITY, collision_radius: 0.1 });
+    old.cameras.insert("lost".to_string(), SceneCamera { host_instance_id: 999, local_transform: Mat4::IDENTITY, collision_radius: 0.1 });
+    old.active_camera = "spectator".to_string();
+
+    let mut new = create_mvp_scene();
+    let file_camera = new.cameras.get_mut(DEFAULT_CAMERA_NAME).unwrap();
+    file_camera.collision_radius = 0.5;
+    let file_host = file_camera.host_instance_id;
+    carry_over_runtime_state(&mut old, &mut new);
+
+    let main = &new.cameras[DEFAULT_CAMERA_NAME];
+    assert_eq!((main.host_instance_id, main.local_transform), (file_host, moved));
+    assert_eq!(main.collision_radius, 0.5, "the file's setting is kept");
+    assert_eq!(new.cameras["spectator"].host_instance_id, room1);
+    assert!(!new.cameras.contains_key("lost"));
+    assert_eq!(new.active_camera, "spectator");
+}
@@ -0,0 +1,51 @@
+// tests/hud_interaction.rs
+// What the HUD reads from the scene: the name of the room the camera is in, and the prompt for a
+// closed door on the demo's Room1 front side, which is only offered within reach and opens the
+// door when acted on.
+
+use std::f32::consts::PI;
+use glam::{Mat4, Vec3};
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::scene_types::Scene;
+use engine3_refactored::engine_lib::{
+    interaction_at, raycast_scene, update_camera_in_scene, HandlerConfig, Interaction, INTERACTION_REACH,
+};
+
+const FRONT_SIDE: usize = 0;
+const BACK_PORTAL: u32 = 1;
+
+fn active_room_name(scene: &Scene) -> String {
+    scene.active_instance().expect("the camera is in a room").name.clone()
+}
+
+#[test]
+fn active_instance_follows_the_camera_through_portals() {
+    let mut scene = create_mvp_scene();
+    assert_eq!(active_room_name(&scene), "Room1");
+
+    update_camera_in_scene(&mut scene, Vec3::new(0.0, 0.0, 1.55), Mat4::from_rotation_y(PI), 0.0);
+    assert_eq!(active_room_name(&scene), "Room2");
+}
+
+#[test]
+fn closed_doors_within_reach_can_be_opened() {
+    let mut scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    let door = HandlerConfig::Door { target_instance_id: room2, target_portal_id: BACK_PORTAL, color: [0.5, 0.3, 0.1, 1.0], open: false, render_priority: 0 };
+    scene.instances.get_mut(&room1).unwrap().instance_side_handler_configs.insert(FRONT_SIDE, door);
+
+    // From the start position the door is 2.5 away, too far to reach.
+    let far = raycast_scene(&scene, room1, Vec3::new(0.0, 0.0, -1.0), Vec3::Z, 4).unwrap();
+    assert_eq!((far.instance_id, far.side_index), (room1, FRONT_SIDE));
+    assert_eq!(interaction_at(&scene, &far, INTERACTION_REACH), None);
+
+    let near = raycast_scene(&scene, room1, Vec3::ZERO, Vec3::Z, 4).unwrap();
+    let interaction = interaction_at(&scene, &near, INTERACTION_REACH).expect("the door is in reach");
+    assert_eq!(interaction, Interaction::OpenDoor { instance_id: room1, side_index: FRONT_SIDE });
+    assert_eq!(interaction.prompt(), "Open door");
+
+    assert!(interaction.perform(&mut scene));
+    let through = raycast_scene(&scene, room1, Vec3::ZERO, Vec3::Z, 4).unwrap();
+    assert_eq!(through.portals_crossed, 1, "the open door lets the ray through");
+    assert_eq!(interaction_at(&scene, &through, INTERACTION_REACH), None);
+}
@@ -0,0 +1,65 @@
+// tests/hull_gravity.rs
+// Hulls with their own gravity: a walking camera falls towards, and stands on, whichever wall
+// its hull's gravity points at, and one crossing into a hull with another "down" turns upright
+// in it and keeps its controls level there.
+
+use glam::{Vec3, Vec4Swizzles};
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::controller::CameraController;
+use engine3_refactored::engine_lib::scene_types::Scene;
+
+const DT: f32 = 0.05;
+// Room half size in the demo.
+const HALF_SIZE: f32 = 1.5;
+
+fn camera_pose(scene: &Scene) -> glam::Mat4 {
+    scene.active_camera().unwrap().local_transform
+}
+
+#[test]
+fn walking_cameras_stand_on_the_wall_gravity_points_at() {
+    let mut scene = create_mvp_scene();
+    scene.instance_by_name_mut("Room1").unwrap().gravity = Vec3::new(-9.8, 0.0, 0.0);
+    let mut controller = CameraController::new(std::f32::consts::PI, 0.0, false, 0.002);
+    controller.toggle_walking();
+    for _ in 0..60 {
+        controller.apply_to_transform(&mut scene, DT);
+    }
+    assert!(controller.is_grounded());
+    let pose = camera_pose(&scene);
+    assert!((pose.w_axis.x - (-HALF_SIZE + controller.eye_height)).abs() < 0.05, "camera at {}", pose.w_axis.xyz());
+    assert!(pose.transform_vector3(Vec3::Y).distance(Vec3::X) < 1e-3, "camera up is {}", pose.transform_vector3(Vec3::Y));
+}
+
+#[test]
+fn crossing_into_another_gravity_rebases_the_view() {
+    let mut scene = create_mvp_scene();
+    let room2 = scene.instance_by_name("Room2").unwrap().id;
+    scene.instances.get_mut(&room2).unwrap().gravity = Vec3::new(-9.8, 0.0, 0.0);
+    let mut controller = CameraController::new(std::f32::consts::PI, 0.0, false, 0.002);
+
+    // Fly forward through Room1's front portal.
+    controller.camera_pos_delta.z = -1.0;
+    for _ in 0..100 {
+        controller.apply_to_transform(&mut scene, DT);
+        if scene.active_camera().unwrap().host_instance_id == room2 {
+            break;
+        }
+    }
+    controller.camera_pos_delta.z = 0.0;
+    assert_eq!(scene.active_camera().unwrap().host_instance_id, room2);
+    let forward_before = camera_pose(&scene).transform_vector3(Vec3::NEG_Z);
+
+    // Upright against Room2's gravity, still looking the way it was.
+    controller.apply_to_transform(&mut scene, DT);
+    let pose = camera_pose(&scene);
+    assert!(pose.transform_vector3(Vec3::Y).distance(Vec3::X) < 1e-3, "camera up is {}", pose.transform_vector3(Vec3::Y));
+    assert!(pose.transform_vector3(Vec3::NEG_Z).distance(forward_before) < 1e-3);
+
+    // Turning turns about the new up.
+    controller.mouse_dx_accum = 100.0;
+    controller.apply_to_transform(&mut scene, DT);
+    let turned = camera_pose(&scene);
+    assert!(turned.transform_vector3(Vec3::Y).distance(Vec3::X) < 1e-3);
+    assert!(turned.transform_vector3(Vec3::NEG_Z).x.abs() < 1e-3);
+}
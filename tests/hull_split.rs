@@ -0,0 +1,67 @@
+// tests/hull_split.rs
+// Cutting hulls in two: a cube split off-center leaves two parts that fill it exactly and meet
+// on a shared side in the cutting plane, planes that miss the interior are refused, and the
+// demo's Room1 cannot be cut through its front portal.
+
+mod common;
+
+use glam::Vec3;
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::scene_types::HullBlueprint;
+use engine3_refactored::engine_lib::{convex_hull_blueprint, split_blueprint, ConvexHullOptions, SplitError};
+use common::blueprint_volume;
+
+fn cube() -> HullBlueprint {
+    let corners: Vec<Vec3> = (0..8)
+        .map(|i| Vec3::new((i & 1) as f32, ((i >> 1) & 1) as f32, ((i >> 2) & 1) as f32) * 2.0 - Vec3::ONE)
+        .collect();
+    convex_hull_blueprint(0, "Cube", &corners, &ConvexHullOptions::default()).unwrap()
+}
+
+#[test]
+fn split_parts_fill_the_hull_and_share_the_cut() {
+    let cube = cube();
+    let normal = Vec3::new(1.0, 0.0, 0.0);
+    let point = Vec3::new(0.25, 0.0, 0.0);
+    let (back, front) = split_blueprint(&cube, normal, point).unwrap();
+
+    assert!((blueprint_volume(&back) - 5.0).abs() < 1e-4);
+    assert!((blueprint_volume(&front) - 3.0).abs() < 1e-4);
+    assert!((blueprint_volume(&back) + blueprint_volume(&front) - blueprint_volume(&cube)).abs() < 1e-4);
+    assert_eq!((back.sides.len(), front.sides.len()), (6, 6));
+    assert!(back.local_vertices.iter().all(|&v| normal.dot(v - point) <= 1e-4));
+    assert!(front.local_vertices.iter().all(|&v| normal.dot(v - point) >= -1e-4));
+
+    // Each part closes the cut with a side lying in the plane, facing into the part, and both
+    // carry the same portal id, which no side of the original used.
+    let cap = |part: &HullBlueprint| {
+        let side = part.sides.iter().find(|side| {
+            side.vertex_indices.iter().all(|&i| normal.dot(part.local_vertices[i] - point).abs() <= 1e-4)
+        }).expect("part has a side on the cut");
+        assert_eq!(side.vertex_indices.len(), 4);
+        (side.local_normal, side.local_portal_id)
+    };
+    let (back_normal, back_portal) = cap(&back);
+    let (front_normal, front_portal) = cap(&front);
+    assert!(back_normal.abs_diff_eq(-normal, 1e-5) && front_normal.abs_diff_eq(normal, 1e-5));
+    assert!(back_portal.is_some() && back_portal == front_portal);
+    assert!(cube.sides.iter().all(|side| side.local_portal_id != back_portal));
+}
+
+#[test]
+fn planes_that_miss_the_interior_are_refused() {
+    let cube = cube();
+    assert_eq!(split_blueprint(&cube, Vec3::X, Vec3::new(2.0, 0.0, 0.0)).err(), Some(SplitError::MissesHull));
+    assert_eq!(split_blueprint(&cube, Vec3::Y, Vec3::new(0.0, 1.0, 0.0)).err(), Some(SplitError::MissesHull));
+    assert_eq!(split_blueprint(&cube, Vec3::Y, Vec3::new(0.0, 1.0 - 1e-5, 0.0)).err(), Some(SplitError::MissesHull));
+    assert_eq!(split_blueprint(&cube, Vec3::ZERO, Vec3::ZERO).err(), Some(SplitError::MissesHull));
+}
+
+#[test]
+fn portals_cannot_be_cut() {
+    let mut scene = create_mvp_scene();
+    let room1 = scene.instance_by_name("Room1").unwrap().id;
+    let instance_count = scene.instances.len();
+    assert_eq!(scene.split_instance(room1, Vec3::X, Vec3::ZERO), Err(SplitError::CutsPortal { side_index: 0 }));
+    assert_eq!(scene.instances.len(), instance_count, "nothing changes on error");
+}
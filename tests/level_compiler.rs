@@ -0,0 +1,68 @@
+// tests/level_compiler.rs
+// Compiling a closed mesh with the solid-leaf BSP: an L-shaped room one unit high becomes convex
+// cells that fill the L exactly, every point in it lands in one cell and the notch in none, and
+// a mesh with a hole in it is refused.
+
+mod common;
+
+use glam::Vec3;
+use engine3_refactored::engine_lib::{compile_level, ColoredTriangle, LevelCompileError, LevelCompileOptions};
+use engine3_refactored::rendering_lib::geometry::Point2;
+use engine3_refactored::rendering_lib::triangulation::ear_clip;
+use common::{blueprint_volume, instance_contains};
+
+const COLOR: [f32; 4] = [0.5, 0.5, 0.5, 1.0];
+
+// Wound counter-clockwise seen from the `outward` side.
+fn facing(corners: [Vec3; 3], outward: Vec3) -> ColoredTriangle {
+    let [a, b, c] = corners;
+    let corners = if (b - a).cross(c - a).dot(outward) >= 0.0 { corners } else { [a, c, b] };
+    ColoredTriangle { corners, color: COLOR }
+}
+
+// The L of x, z in [0, 2] without the [1, 2] x [1, 2] corner, extruded from y = 0 to 1.
+fn l_room() -> Vec<ColoredTriangle> {
+    let outline = [(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)];
+    let at = |(x, z): (f32, f32), y: f32| Vec3::new(x, y, z);
+    let mut triangles = Vec::new();
+    let flat: Vec<Point2> = outline.iter().map(|&(x, z)| Point2::new(x, z)).collect();
+    for [a, b, c] in ear_clip(&flat) {
+        triangles.push(facing([at(outline[a], 1.0), at(outline[b], 1.0), at(outline[c], 1.0)], Vec3::Y));
+        triangles.push(facing([at(outline[a], 0.0), at(outline[b], 0.0), at(outline[c], 0.0)], Vec3::NEG_Y));
+    }
+    // The outline runs counter-clockwise in (x, z), so outward is to the right of each edge.
+    for i in 0..outline.len() {
+        let (p, q) = (outline[i], outline[(i + 1) % outline.len()]);
+        let outward = Vec3::new(q.1 - p.1, 0.0, -(q.0 - p.0));
+        triangles.push(facing([at(p, 0.0), at(q, 0.0), at(q, 1.0)], outward));
+        triangles.push(facing([at(p, 0.0), at(q, 1.0), at(p, 1.0)], outward));
+    }
+    triangles
+}
+
+#[test]
+fn closed_meshes_compile_into_cells_filling_them() {
+    let hulls = compile_level(&l_room(), &LevelCompileOptions::default()).expect("the L is closed");
+    assert!(hulls.instances.len() >= 2, "an L is not convex: {} cells", hulls.instances.len());
+    let volume: f32 = hulls.instances.values().map(|instance| blueprint_volume(&hulls.blueprints[&instance.blueprint_id])).sum();
+    assert!((volume - 3.0).abs() < 1e-3, "volume {volume}");
+    assert!(hulls.instances.values().any(|instance| !instance.portal_connections.is_empty()), "the cells are joined by portals");
+
+    let cells_at = |point: Vec3| {
+        hulls.instances.values().filter(|instance| instance_contains(&hulls.blueprints[&instance.blueprint_id], instance, point)).count()
+    };
+    for point in [Vec3::new(0.3, 0.5, 0.3), Vec3::new(1.7, 0.5, 0.3), Vec3::new(0.3, 0.5, 1.7), Vec3::new(0.9, 0.2, 0.6)] {
+        assert_eq!(cells_at(point), 1, "{point} should be in exactly one cell");
+    }
+    for point in [Vec3::new(1.5, 0.5, 1.5), Vec3::new(0.5, 1.5, 0.5), Vec3::new(-0.5, 0.5, 0.5)] {
+        assert_eq!(cells_at(point), 0, "{point} is outside the L");
+    }
+}
+
+#[test]
+fn open_meshes_are_refused() {
+    let mut room = l_room();
+    room.pop();
+    assert!(matches!(compile_level(&room, &LevelCompileOptions::default()), Err(LevelCompileError::OpenMesh { .. })));
+    assert_eq!(compile_level(&[], &LevelCompileOptions::default()).unwrap_err(), LevelCompileError::NoTriangles);
+}
@@ -0,0 +1,117 @@
+// tests/liquid_volumes.rs
+// The demo's Room2 flooded, with the Room1 front portal as its surface: a camera in the water
+// moves slower and, walking, sinks gently, the surface lets it through, and what is under the
+// surface is drawn through the surface's tint.
+
+use glam::{Mat4, Vec3, Vec4Swizzles};
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::controller::CameraController;
+use engine3_refactored::engine_lib::scene_types::Scene;
+use engine3_refactored::engine_lib::{submerged_liquid, update_camera_in_scene, HandlerConfig, LiquidVolume};
+
+const DT: f32 = 0.05;
+const FRONT_SIDE: usize = 0;
+const BACK_PORTAL: u32 = 1;
+const SURFACE_TINT: [f32; 4] = [0.2, 0.5, 0.6, 0.5];
+
+fn flooded_scene() -> Scene {
+    let mut scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    scene.instances.get_mut(&room2).unwrap().liquid = Some(LiquidVolume::water());
+    let surface = HandlerConfig::LiquidSurface { target_instance_id: room2, target_portal_id: BACK_PORTAL, tint: SURFACE_TINT, render_priority: 0 };
+    scene.instances.get_mut(&room1).unwrap().instance_side_handler_configs.insert(FRONT_SIDE, surface);
+    scene
+}
+
+fn camera_position(scene: &Scene) -> Vec3 {
+    scene.active_camera().unwrap().local_transform.w_axis.xyz()
+}
+
+// The same scene with the camera's own room, Room1, flooded or not.
+fn room1_scene(flooded: bool) -> Scene {
+    let mut scene = create_mvp_scene();
+    scene.instance_by_name_mut("Room1").unwrap().liquid = flooded.then(LiquidVolume::water);
+    scene
+}
+
+#[test]
+fn submerged_cameras_move_slower_and_sink_gently() {
+    let mut moved = Vec::new();
+    let mut fallen = Vec::new();
+    for flooded in [false, true] {
+        let mut scene = room1_scene(flooded);
+        assert_eq!(submerged_liquid(&scene).is_some(), flooded);
+        let start = camera_position(&scene);
+        let mut controller = CameraController::new(std::f32::consts::PI, 0.0, false, 0.002);
+        controller.camera_pos_delta.z = -1.0;
+        controller.apply_to_transform(&mut scene, DT);
+        moved.push(camera_position(&scene).distance(start));
+
+        let mut scene = room1_scene(flooded);
+        let mut controller = CameraController::new(std::f32::consts::PI, 0.0, false, 0.002);
+        controller.toggle_walking();
+        for _ in 0..3 {
+            controller.apply_to_transform(&mut scene, DT);
+        }
+        fallen.push(start.y - camera_position(&scene).y);
+    }
+    let water = LiquidVolume::water();
+    assert!((moved[1] - moved[0] * water.movement_scale).abs() < 1e-4, "moved {moved:?}");
+    assert!(fallen[0] > 0.0 && fallen[1] > 0.0 && fallen[1] < fallen[0], "fell {fallen:?}");
+}
+
+#[test]
+fn flying_cameras_only_slow_down_in_liquids() {
+    let mut scene = room1_scene(true);
+    let start = camera_position(&scene);
+    let mut controller = CameraController::new(std::f32::consts::PI, 0.0, false, 0.002);
+    for _ in 0..10 {
+        controller.apply_to_transform(&mut scene, DT);
+    }
+    assert!(camera_position(&scene).distance(start) < 1e-5, "a flying camera neither sinks nor drifts");
+
+    let mut rises = Vec::new();
+    for flooded in [false, true] {
+        let mut scene = room1_scene(flooded);
+        let mut controller = CameraController::new(std::f32::consts::PI, 0.0, false, 0.002);
+        controller.camera_pos_delta.y = 1.0;
+        controller.apply_to_transform(&mut scene, DT);
+        rises.push(camera_position(&scene).y - start.y);
+    }
+    assert!((rises[1] - rises[0] * LiquidVolume::water().movement_scale).abs() < 1e-4, "rose {rises:?}");
+}
+
+#[test]
+fn liquid_surfaces_do_not_block() {
+    let mut scene = flooded_scene();
+    update_camera_in_scene(&mut scene, Vec3::new(0.0, 0.0, 1.55), Mat4::from_rotation_y(std::f32::consts::PI), 0.0);
+    assert_eq!(scene.active_camera().unwrap().host_instance_id, scene.instance_by_name("Room2").unwrap().id);
+    assert!(submerged_liquid(&scene).is_some());
+}
+
+#[cfg(feature = "render")]
+#[test]
+fn liquid_surfaces_are_drawn_over_what_is_under_them() {
+    use engine3_refactored::engine_lib::camera::Camera;
+    use engine3_refactored::engine_lib::scene_types::RENDER_LAYER_ALL;
+    use engine3_refactored::rendering_lib::renderer::{SceneView, Viewport};
+    use engine3_refactored::rendering_lib::{RenderSettings, SceneTraversal};
+
+    let mut scene = flooded_scene();
+    // Only the props under the water, which have to be drawn before the surface.
+    let room2 = scene.instance_by_name("Room2").unwrap().id;
+    scene.entities.retain(|_, entity| entity.host_instance_id == room2);
+    let camera = Camera::new(70.0, 0.1, 100.0);
+    let view = SceneView::from_scene_camera(&scene, &scene.active_camera, &camera, Viewport::full(320.0, 240.0)).unwrap();
+    let mut traversal = SceneTraversal::new();
+    let stats = traversal.build_frame(&scene, &[view], &RenderSettings::default(), [0.0, 0.0, 0.0, 1.0], RENDER_LAYER_ALL);
+    assert!(stats[0].portals_traversed > 0, "Room2 should be seen through the surface");
+
+    // The surface tint is the last thing drawn, over Room2's walls and props.
+    let vertices = traversal.vertices();
+    let is_surface = |triangle: &[u32]| triangle.iter().all(|&index| vertices[index as usize].color[3] == SURFACE_TINT[3]);
+    let triangles: Vec<&[u32]> = traversal.indices().chunks_exact(3).collect();
+    let first_surface = triangles.iter().position(|triangle| is_surface(triangle)).expect("the surface is drawn");
+    assert!(first_surface > 0);
+    assert!(triangles[first_surface..].iter().all(|triangle| is_surface(triangle)));
+}
@@ -0,0 +1,64 @@
+// tests/map_import.rs
+// Importing a brush map: tests/fixtures/corridor.map has three world brushes in a row joined
+// through a narrower passage, a door entity whose brush is skipped, and a player start. Each
+// world brush becomes one hull, each touching pair one pair of portals, and the camera starts
+// in the last hall looking along map +Y.
+
+use glam::Vec3;
+use engine3_refactored::engine_lib::scene_types::Scene;
+use engine3_refactored::engine_lib::{import_map, HandlerConfig, MapImportError, MapImportOptions};
+
+const CORRIDOR: &str = include_str!("fixtures/corridor.map");
+
+fn side_configs(scene: &Scene, name: &str) -> Vec<HandlerConfig> {
+    let instance = scene.instance_by_name(name).unwrap();
+    let blueprint = &scene.blueprints[&instance.blueprint_id];
+    (0..blueprint.sides.len()).map(|side_index| scene.side_config(instance.id, side_index).unwrap().clone()).collect()
+}
+
+fn portal_targets(scene: &Scene, name: &str) -> Vec<u32> {
+    side_configs(scene, name).iter()
+        .filter_map(|config| match config {
+            HandlerConfig::StandardPortal { target_instance_id, .. } => Some(*target_instance_id),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn brushes_become_hulls_joined_by_portals() {
+    let scene = import_map(CORRIDOR, &MapImportOptions::default()).unwrap();
+    assert_eq!(scene.instances.len(), 3, "the door brush is not part of the world");
+    let ids: Vec<u32> = ["Brush0", "Brush1", "Brush2"].iter().map(|name| scene.instance_by_name(name).unwrap().id).collect();
+
+    assert_eq!(portal_targets(&scene, "Brush0"), [ids[1]]);
+    let mut passage = portal_targets(&scene, "Brush1");
+    passage.sort();
+    assert_eq!(passage, [ids[0], ids[2]]);
+    assert_eq!(portal_targets(&scene, "Brush2"), [ids[1]]);
+
+    // The hall's +X wall is cut around the passage's end: one portal and the
+    // wall around it in three pieces, as the passage sits on the floor.
+    let hall = side_configs(&scene, "Brush0");
+    assert_eq!(hall.len(), 9);
+    assert_eq!(hall.iter().filter(|config| matches!(config, HandlerConfig::Skybox { .. })).count(), 1);
+    assert_eq!(hall.iter().filter(|config| matches!(config, HandlerConfig::StandardWall { .. })).count(), 7);
+}
+
+#[test]
+fn the_camera_starts_at_the_player_start() {
+    let scene = import_map(CORRIDOR, &MapImportOptions::default()).unwrap();
+    let camera = scene.active_camera().unwrap();
+    assert_eq!(camera.host_instance_id, scene.instance_by_name("Brush2").unwrap().id);
+    // 8 map units below the hall's middle at 32 units to the meter, facing map +Y, which is -Z.
+    assert!(camera.local_transform.w_axis.truncate().abs_diff_eq(Vec3::new(0.0, -0.25, 0.0), 1e-5));
+    assert!(camera.local_transform.transform_vector3(Vec3::NEG_Z).abs_diff_eq(Vec3::NEG_Z, 1e-5));
+}
+
+#[test]
+fn maps_without_world_brushes_are_refused() {
+    let entities_only = "{\n\"classname\" \"worldspawn\"\n}\n{\n\"classname\" \"info_player_start\"\n\"origin\" \"0 0 0\"\n}\n";
+    assert!(matches!(import_map(entities_only, &MapImportOptions::default()), Err(MapImportError::NoBrushes)));
+    let unclosed = "{\n\"classname\" \"worldspawn\"\n{\n( 0 0 0 ) ( 0 64 0 ) ( 0 0 64 ) brick\n";
+    assert!(matches!(import_map(unclosed, &MapImportOptions::default()), Err(MapImportError::Syntax { line: 4, .. })));
+}
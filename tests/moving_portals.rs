@@ -0,0 +1,74 @@
+// tests/moving_portals.rs
+// Portals on animated sides: whatever the two sides' animations have done, the target's portal
+// side is brought onto the source's, and a camera crossing the moved portal lands where the view
+// through it showed it would.
+
+use glam::{Mat4, Quat, Vec3, Vec4Swizzles};
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::scene_types::Scene;
+use engine3_refactored::engine_lib::{side_portal_transform, update_camera_in_scene, SideAnimation};
+
+const FRONT_SIDE: usize = 0;
+const BACK_SIDE: usize = 1;
+const BACK_PORTAL: u32 = 1;
+
+fn animate(scene: &mut Scene, instance_id: u32, side_index: usize, mut animation: SideAnimation, progress: f32) {
+    animation.progress = progress;
+    animation.target_progress = progress;
+    scene.instances.get_mut(&instance_id).unwrap().side_animations.insert(side_index, animation);
+}
+
+// The side's corners as they currently stand, in its instance's blueprint space.
+fn side_corners(scene: &Scene, instance_id: u32, side_index: usize) -> Vec<Vec3> {
+    let instance = &scene.instances[&instance_id];
+    let blueprint = &scene.blueprints[&instance.blueprint_id];
+    let motion = instance.side_animations.get(&side_index).map_or(Mat4::IDENTITY, SideAnimation::transform);
+    blueprint.sides[side_index].vertex_indices.iter().map(|&v| motion.transform_point3(blueprint.local_vertices[v])).collect()
+}
+
+#[test]
+fn moved_portal_sides_stay_aligned() {
+    let mut scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    // Both ends of the portal ride up together, like the doors of an elevator car and its shaft,
+    // and the far end also turns in its plane.
+    animate(&mut scene, room1, FRONT_SIDE, SideAnimation::sliding(Vec3::new(0.0, 2.0, 0.0), 1.0), 0.25);
+    let mut far_end = SideAnimation::swinging(Vec3::new(0.0, 0.0, -1.5), Vec3::Z, 0.6, 1.0);
+    far_end.open_translation = Vec3::new(0.0, 0.5, 0.0);
+    animate(&mut scene, room2, BACK_SIDE, far_end, 1.0);
+
+    let target_to_source = side_portal_transform(&scene, room1, FRONT_SIDE, room2, BACK_PORTAL).unwrap();
+    let source_corners = side_corners(&scene, room1, FRONT_SIDE);
+    for corner in side_corners(&scene, room2, BACK_SIDE) {
+        let carried = target_to_source.transform_point3(corner);
+        assert!(
+            source_corners.iter().any(|source| source.distance(carried) < 1e-4),
+            "{carried} is not a corner of {source_corners:?}",
+        );
+    }
+}
+
+#[test]
+fn crossing_a_turned_portal_keeps_the_view() {
+    let mut scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    // A square portal turned a quarter in its own plane still fills the same doorway, but what is
+    // behind it is seen turned with it.
+    let turn = SideAnimation::swinging(Vec3::new(0.0, 0.0, 1.5), Vec3::Z, std::f32::consts::FRAC_PI_2, 1.0);
+    animate(&mut scene, room1, FRONT_SIDE, turn, 1.0);
+
+    let rotation = Mat4::from_rotation_y(std::f32::consts::PI);
+    let crossed_pose = Mat4::from_translation(Vec3::new(0.2, 0.1, 1.55)) * rotation;
+    update_camera_in_scene(&mut scene, Vec3::new(0.2, 0.1, 1.55), rotation, 0.0);
+    let camera = scene.active_camera().unwrap();
+    assert_eq!(camera.host_instance_id, room2);
+
+    // The pose the renderer showed the camera at in Room2, from Room1's side of the portal.
+    let expected = Mat4::from_rotation_translation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_2), Vec3::new(0.0, 0.0, 1.5))
+        * Mat4::from_translation(Vec3::new(0.0, 0.0, 1.5));
+    let expected = expected.inverse() * crossed_pose;
+    // Crossing also nudges the camera a hair forward.
+    assert!(camera.local_transform.w_axis.xyz().distance(expected.w_axis.xyz()) < 1e-2);
+    let up = camera.local_transform.transform_vector3(Vec3::Y);
+    assert!(up.distance(expected.transform_vector3(Vec3::Y)) < 1e-4, "camera up is {up}");
+}
@@ -0,0 +1,65 @@
+// tests/portal_access.rs
+// Portals that can only be looked through, only be walked through, or only be walked through
+// while holding a key: the demo's Room1 front portal is given each access in turn, then looked
+// at from the start camera and walked into.
+
+use glam::{Mat4, Vec3};
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::scene_types::Scene;
+use engine3_refactored::engine_lib::{update_camera_in_scene, HandlerConfig, PortalAccess};
+
+const FRONT_SIDE: usize = 0;
+const BACK_PORTAL: u32 = 1;
+
+fn scene_with_front_access(access: PortalAccess) -> (Scene, u32, u32) {
+    let mut scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    let config = HandlerConfig::StandardPortal { target_instance_id: room2, target_portal_id: BACK_PORTAL, access };
+    scene.instances.get_mut(&room1).unwrap().instance_side_handler_configs.insert(FRONT_SIDE, config);
+    (scene, room1, room2)
+}
+
+// Steps the active camera just past Room1's front portal and returns the instance it ends in.
+fn walk_through_front(scene: &mut Scene) -> u32 {
+    update_camera_in_scene(scene, Vec3::new(0.0, 0.0, 1.55), Mat4::from_rotation_y(std::f32::consts::PI), 0.0);
+    scene.active_camera().unwrap().host_instance_id
+}
+
+#[cfg(feature = "render")]
+fn portals_seen(scene: &Scene) -> u32 {
+    use engine3_refactored::engine_lib::camera::Camera;
+    use engine3_refactored::engine_lib::scene_types::RENDER_LAYER_ALL;
+    use engine3_refactored::rendering_lib::renderer::{SceneView, Viewport};
+    use engine3_refactored::rendering_lib::{RenderSettings, SceneTraversal};
+
+    let camera = Camera::new(70.0, 0.1, 100.0);
+    let view = SceneView::from_scene_camera(scene, &scene.active_camera, &camera, Viewport::full(320.0, 240.0)).unwrap();
+    let mut traversal = SceneTraversal::new();
+    let stats = traversal.build_frame(scene, &[view], &RenderSettings::default(), [0.0, 0.0, 0.0, 1.0], RENDER_LAYER_ALL);
+    stats[0].portals_traversed
+}
+
+#[test]
+fn visual_only_portals_are_seen_through_but_block() {
+    let (mut scene, room1, _) = scene_with_front_access(PortalAccess::visual_only());
+    #[cfg(feature = "render")]
+    assert!(portals_seen(&scene) > 0);
+    assert_eq!(walk_through_front(&mut scene), room1);
+}
+
+#[test]
+fn walk_only_portals_are_walls_that_let_you_through() {
+    let (mut scene, _, room2) = scene_with_front_access(PortalAccess::walk_only([0.5, 0.5, 0.5, 1.0]));
+    #[cfg(feature = "render")]
+    assert_eq!(portals_seen(&scene), 0);
+    assert_eq!(walk_through_front(&mut scene), room2);
+}
+
+#[test]
+fn gated_portals_open_for_the_key() {
+    let (mut scene, room1, room2) = scene_with_front_access(PortalAccess::gated("brass key"));
+    assert_eq!(walk_through_front(&mut scene), room1);
+
+    scene.grant_key("brass key");
+    assert_eq!(walk_through_front(&mut scene), room2);
+}
@@ -0,0 +1,58 @@
+// tests/portal_graph.rs
+// Portal graph queries on tests/fixtures/corridor.map, three rooms in a row: adjacency, the
+// shortest way across, reach limited by portal count, and how a closed door in the passage
+// splits the graph until it opens.
+
+use engine3_refactored::engine_lib::scene_types::Scene;
+use engine3_refactored::engine_lib::{import_map, HandlerConfig, MapImportOptions, PortalGraph};
+
+fn corridor() -> (Scene, [u32; 3]) {
+    let scene = import_map(include_str!("fixtures/corridor.map"), &MapImportOptions::default()).unwrap();
+    let ids = ["Brush0", "Brush1", "Brush2"].map(|name| scene.instance_by_name(name).unwrap().id);
+    (scene, ids)
+}
+
+#[test]
+fn rooms_in_a_row_are_adjacent_in_order() {
+    let (scene, [hall, passage, far_hall]) = corridor();
+    let graph = PortalGraph::from_scene(&scene);
+    assert_eq!(graph.neighbors(hall), [passage]);
+    assert_eq!(graph.neighbors(far_hall), [passage]);
+    let mut passage_neighbors = graph.neighbors(passage);
+    passage_neighbors.sort();
+    assert_eq!(passage_neighbors, [hall, far_hall]);
+
+    let path = graph.shortest_path(hall, far_hall).unwrap();
+    let rooms: Vec<(u32, u32)> = path.iter().map(|edge| (edge.from_instance_id, edge.to_instance_id)).collect();
+    assert_eq!(rooms, [(hall, passage), (passage, far_hall)]);
+    // Each step arrives through the portal that leads straight back.
+    for edge in &path {
+        assert!(graph.edges_from(edge.to_instance_id).iter().any(|back| {
+            back.portal_id == edge.target_portal_id && back.to_instance_id == edge.from_instance_id && back.target_portal_id == edge.portal_id
+        }));
+    }
+    assert_eq!(graph.shortest_path(hall, hall), Some(Vec::new()));
+    assert_eq!(graph.shortest_path(hall, 99), None);
+
+    let near = graph.reachable_within(hall, 1);
+    assert_eq!((near.len(), near[&hall], near[&passage]), (2, 0, 1));
+    assert_eq!(graph.reachable_within(hall, 5)[&far_hall], 2);
+}
+
+#[test]
+fn closed_doors_cut_the_graph() {
+    let (mut scene, [hall, passage, far_hall]) = corridor();
+    let edge = *PortalGraph::from_scene(&scene).edges_from(passage).iter().find(|edge| edge.to_instance_id == far_hall).unwrap();
+    let door = |open| HandlerConfig::Door { target_instance_id: far_hall, target_portal_id: edge.target_portal_id, color: [0.5, 0.3, 0.2, 1.0], open, render_priority: 0 };
+    scene.instances.get_mut(&passage).unwrap().instance_side_handler_configs.insert(edge.side_index, door(false));
+
+    let graph = PortalGraph::from_scene(&scene);
+    assert_eq!(graph.neighbors(passage), [hall]);
+    assert_eq!(graph.shortest_path(hall, far_hall), None);
+    assert!(!graph.reachable_within(hall, 5).contains_key(&far_hall));
+    // Only the passage's side is a door; the far hall still leads back into it.
+    assert_eq!(graph.neighbors(far_hall), [passage]);
+
+    scene.instances.get_mut(&passage).unwrap().instance_side_handler_configs.insert(edge.side_index, door(true));
+    assert_eq!(scene.shortest_portal_path(hall, far_hall).map(|path| path.len()), Some(2));
+}
@@ -0,0 +1,99 @@
+// tests/portal_pairing.rs
+// `pair_coincident_portals` on copies of the demo's cuboid room placed in world space: a wall
+// shared by two rooms becomes a portal from both ends, walls that are apart or face the same
+// way stay walls, and sides already leading somewhere keep their connection.
+
+use glam::{Mat4, Vec3};
+use engine3_refactored::demo_scene::{create_mvp_scene, PORTAL_ID_BACK, PORTAL_ID_FRONT};
+use engine3_refactored::engine_lib::scene_types::{HandlerConfig, HullBlueprint, Scene};
+use engine3_refactored::engine_lib::{pair_coincident_portals, PortalPairing, SceneBuilder, DEFAULT_PAIRING_TOLERANCE};
+
+const FRONT_SIDE: usize = 0;
+const BACK_SIDE: usize = 1;
+// The cuboid is 3 units deep, so a room this far along +Z shares Room A's front wall.
+const ROOM_DEPTH: f32 = 3.0;
+
+fn cuboid() -> HullBlueprint {
+    let scene = create_mvp_scene();
+    let room1 = scene.instance_by_name("Room1").unwrap();
+    scene.blueprints[&room1.blueprint_id].clone()
+}
+
+// Room A at the origin and room B at `b_transform`, both placed in world space and unconnected.
+fn two_rooms(b_transform: Mat4) -> (Scene, u32, u32) {
+    let mut builder = SceneBuilder::new();
+    let room = builder.blueprint(cuboid());
+    let (a, b) = (builder.instance(room, "A"), builder.instance(room, "B"));
+    builder.place(a, Mat4::IDENTITY).place(b, b_transform);
+    (builder.build().unwrap(), a.id(), b.id())
+}
+
+fn leads_to(scene: &Scene, instance_id: u32, side_index: usize) -> Option<(u32, u32)> {
+    scene.side_config(instance_id, side_index).and_then(HandlerConfig::passable_portal_target)
+}
+
+#[test]
+fn coincident_opposite_sides_are_paired_both_ways() {
+    let (mut scene, a, b) = two_rooms(Mat4::from_translation(Vec3::new(0.0, 0.0, ROOM_DEPTH)));
+    let pairings = pair_coincident_portals(&mut scene, DEFAULT_PAIRING_TOLERANCE);
+
+    assert_eq!(pairings, vec![PortalPairing {
+        instance_a: a, side_a: FRONT_SIDE, portal_a: PORTAL_ID_FRONT,
+        instance_b: b, side_b: BACK_SIDE, portal_b: PORTAL_ID_BACK,
+    }]);
+    assert_eq!(leads_to(&scene, a, FRONT_SIDE), Some((b, PORTAL_ID_BACK)));
+    assert_eq!(leads_to(&scene, b, BACK_SIDE), Some((a, PORTAL_ID_FRONT)));
+    let a_front = &scene.instances[&a].portal_connections[&PORTAL_ID_FRONT];
+    assert_eq!((a_front.target_instance_id, a_front.target_portal_id), (b, PORTAL_ID_BACK));
+    let b_back = &scene.instances[&b].portal_connections[&PORTAL_ID_BACK];
+    assert_eq!((b_back.target_instance_id, b_back.target_portal_id), (a, PORTAL_ID_FRONT));
+}
+
+#[test]
+fn sides_within_tolerance_are_paired() {
+    let (mut scene, _, _) = two_rooms(Mat4::from_translation(Vec3::new(0.0, 0.0, ROOM_DEPTH + DEFAULT_PAIRING_TOLERANCE * 0.5)));
+    assert_eq!(pair_coincident_portals(&mut scene, DEFAULT_PAIRING_TOLERANCE).len(), 1);
+}
+
+#[test]
+fn sides_that_are_not_coplanar_stay_walls() {
+    let (mut scene, a, b) = two_rooms(Mat4::from_translation(Vec3::new(0.0, 0.0, ROOM_DEPTH + 0.5)));
+    assert!(pair_coincident_portals(&mut scene, DEFAULT_PAIRING_TOLERANCE).is_empty());
+    assert_eq!(leads_to(&scene, a, FRONT_SIDE), None);
+    assert_eq!(leads_to(&scene, b, BACK_SIDE), None);
+    assert!(scene.instances.values().all(|instance| instance.portal_connections.is_empty()));
+}
+
+#[test]
+fn coincident_sides_facing_the_same_way_stay_walls() {
+    // Two rooms in the same place: every side lies on one of the other room's, facing the same way.
+    let (mut scene, _, _) = two_rooms(Mat4::IDENTITY);
+    assert!(pair_coincident_portals(&mut scene, DEFAULT_PAIRING_TOLERANCE).is_empty());
+    assert!(scene.instances.values().all(|instance| instance.portal_connections.is_empty()));
+}
+
+#[test]
+fn sides_already_in_use_are_not_re_paired() {
+    let mut builder = SceneBuilder::new();
+    let room = builder.blueprint(cuboid());
+    let (a, b, c) = (builder.instance(room, "A"), builder.instance(room, "B"), builder.instance(room, "C"));
+    // C has no world placement, so only A and B could be paired by position.
+    builder.place(a, Mat4::IDENTITY)
+        .place(b, Mat4::from_translation(Vec3::new(0.0, 0.0, ROOM_DEPTH)))
+        .connect(a.side(FRONT_SIDE), c.side(BACK_SIDE));
+    let mut scene = builder.build().unwrap();
+
+    assert!(pair_coincident_portals(&mut scene, DEFAULT_PAIRING_TOLERANCE).is_empty());
+    assert_eq!(leads_to(&scene, a.id(), FRONT_SIDE), Some((c.id(), PORTAL_ID_BACK)));
+    assert_eq!(leads_to(&scene, b.id(), BACK_SIDE), None);
+    assert!(scene.instances[&b.id()].portal_connections.is_empty());
+}
+
+#[test]
+fn pairing_again_changes_nothing() {
+    let (mut scene, a, b) = two_rooms(Mat4::from_translation(Vec3::new(0.0, 0.0, ROOM_DEPTH)));
+    assert_eq!(pair_coincident_portals(&mut scene, DEFAULT_PAIRING_TOLERANCE).len(), 1);
+    assert!(pair_coincident_portals(&mut scene, DEFAULT_PAIRING_TOLERANCE).is_empty());
+    assert_eq!(leads_to(&scene, a, FRONT_SIDE), Some((b, PORTAL_ID_BACK)));
+    assert_eq!(leads_to(&scene, b, BACK_SIDE), Some((a, PORTAL_ID_FRONT)));
+}
@@ -0,0 +1,125 @@
+// tests/render_backend.rs
+// The portal traversal checked through a `RenderBackend` that only records what it is handed, with
+// no `wgpu::Device`: every draw stays inside the frame's buffers and, between them, the draws
+// cover all of the traversed geometry, batched by material.
+#![cfg(feature = "render")]
+
+use std::ops::Range;
+
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::camera::Camera;
+use engine3_refactored::engine_lib::scene_types::{HandlerConfig, Scene, RENDER_LAYER_ALL};
+use engine3_refactored::rendering_lib::renderer::{SceneView, Viewport};
+use engine3_refactored::rendering_lib::{BlendMode, FrameDraw, FrameGeometry, PortalMasking, RenderBackend, RenderSettings, SceneTraversal};
+
+const WIDTH: f32 = 320.0;
+const HEIGHT: f32 = 240.0;
+const BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+
+#[derive(Default)]
+struct RecordingBackend {
+    frames: usize,
+    scene_triangles: usize,
+    sky_triangles: usize,
+    // Scene triangles drawn with `BlendMode::Alpha`, and those of them that are see-through.
+    alpha_triangles: usize,
+    translucent_triangles: usize,
+    portal_masks: usize,
+    // Net stencil steps of the portal masks; each mask drawn in is drawn out again.
+    mask_balance: i64,
+}
+
+fn check_range(range: &Range<u32>, indices: &[u32], vertex_count: usize) {
+    assert!(range.start <= range.end && range.end as usize <= indices.len(), "{range:?} is outside {} indices", indices.len());
+    assert_eq!(range.len() % 3, 0, "{range:?} is not a triangle list");
+    for &index in &indices[range.start as usize..range.end as usize] {
+        assert!((index as usize) < vertex_count, "index {index} is past {vertex_count} vertices");
+    }
+}
+
+impl RenderBackend for RecordingBackend {
+    fn submit_frame(&mut self, frame: &FrameGeometry, _settings: &RenderSettings) {
+        self.frames += 1;
+        assert_eq!((frame.width, frame.height), (WIDTH, HEIGHT));
+        let mut previous_batch = None;
+        for draw in frame.draws {
+            match draw {
+                FrameDraw::Geometry { scene, sky, stencil_reference, material } => {
+                    check_range(scene, frame.indices, frame.vertices.len());
+                    check_range(sky, frame.sky_indices, frame.sky_vertices.len());
+                    // Neighbouring scene ranges in one material and stencil state would be one draw.
+                    if previous_batch.is_some_and(|(end, previous)| end == scene.start && previous == (*stencil_reference, *material)) {
+                        panic!("draws at {} share a material and could have been batched", scene.start);
+                    }
+                    previous_batch = (!scene.is_empty()).then_some((scene.end, (*stencil_reference, *material)));
+                    let triangles = frame.indices[scene.start as usize..scene.end as usize].chunks_exact(3);
+                    let translucent = triangles.filter(|triangle| frame.vertices[triangle[0] as usize].color[3] < 1.0).count();
+                    match material.blend {
+                        BlendMode::Opaque => assert_eq!(translucent, 0, "see-through triangles drawn opaque"),
+                        BlendMode::Alpha => self.alpha_triangles += scene.len() / 3,
+                    }
+                    self.translucent_triangles += translucent;
+                    self.scene_triangles += scene.len() / 3;
+                    self.sky_triangles += sky.len() / 3;
+                }
+                FrameDraw::PortalMask { indices, increment, .. } => {
+                    previous_batch = None;
+                    check_range(indices, frame.mask_indices, frame.mask_vertices.len());
+                    self.portal_masks += 1;
+                    self.mask_balance += if *increment { 1 } else { -1 };
+                }
+            }
+        }
+        assert_eq!(self.scene_triangles, frame.indices.len() / 3, "scene triangles left undrawn");
+        assert_eq!(self.sky_triangles, frame.sky_indices.len() / 3, "sky triangles left undrawn");
+    }
+}
+
+fn record_frame(scene: &Scene, settings: &RenderSettings) -> RecordingBackend {
+    let camera = Camera::new(70.0, 0.1, 100.0);
+    let view = SceneView::from_scene_camera(scene, &scene.active_camera, &camera, Viewport::full(WIDTH, HEIGHT))
+        .expect("the demo scene has an active camera");
+    let mut traversal = SceneTraversal::new();
+    let stats = traversal.build_frame(scene, &[view], settings, BACKGROUND, RENDER_LAYER_ALL);
+    assert!(stats[0].sides_drawn > 0);
+
+    let mut backend = RecordingBackend::default();
+    backend.submit_frame(&traversal.frame(WIDTH, HEIGHT, BACKGROUND, scene.fog.as_ref()), settings);
+    backend
+}
+
+#[test]
+fn clipped_frame_draws_all_geometry() {
+    let scene = create_mvp_scene();
+    let backend = record_frame(&scene, &RenderSettings::default());
+    assert_eq!(backend.frames, 1);
+    assert!(backend.scene_triangles > 0);
+    assert_eq!(backend.portal_masks, 0);
+}
+
+#[test]
+fn stencil_frame_masks_are_balanced() {
+    let scene = create_mvp_scene();
+    let settings = RenderSettings { portal_masking: PortalMasking::Stencil, ..RenderSettings::default() };
+    let backend = record_frame(&scene, &settings);
+    assert!(backend.scene_triangles > 0);
+    assert!(backend.portal_masks > 0);
+    assert_eq!(backend.mask_balance, 0, "portal masks drawn in were not all drawn out");
+}
+
+#[test]
+fn translucent_walls_are_drawn_alpha_blended() {
+    let mut scene = create_mvp_scene();
+    for side in scene.blueprints.values_mut().flat_map(|blueprint| &mut blueprint.sides) {
+        if let HandlerConfig::StandardWall { color, .. } = &mut side.default_handler_config {
+            color[3] = 0.5;
+        }
+    }
+    for masking in [PortalMasking::Clipping, PortalMasking::Stencil] {
+        let settings = RenderSettings { portal_masking: masking, ..RenderSettings::default() };
+        let backend = record_frame(&scene, &settings);
+        assert!(backend.translucent_triangles > 0);
+        assert_eq!(backend.alpha_triangles, backend.translucent_triangles);
+        assert!(backend.alpha_triangles < backend.scene_triangles, "opaque sides should stay opaque");
+    }
+}
@@ -0,0 +1,55 @@
+// tests/render_priority.rs
+// The demo's Room1 front portal turned into a liquid surface, with Room1's crate standing in
+// front of it: by distance the crate is drawn over the surface's tint, and raising the surface
+// config's render priority puts the tint on top instead, whether the config is the instance's
+// or the blueprint's default.
+#![cfg(feature = "render")]
+
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::camera::Camera;
+use engine3_refactored::engine_lib::scene_types::RENDER_LAYER_ALL;
+use engine3_refactored::engine_lib::HandlerConfig;
+use engine3_refactored::rendering_lib::renderer::{SceneView, Viewport};
+use engine3_refactored::rendering_lib::{RenderSettings, SceneTraversal};
+
+const FRONT_SIDE: usize = 0;
+const BACK_PORTAL: u32 = 1;
+const SURFACE_TINT: [f32; 4] = [0.2, 0.5, 0.6, 0.5];
+
+// Whether the surface's tint is the last thing drawn, with the front side at `priority`, set
+// on Room1 or, when `on_blueprint`, as its blueprint's default (Room2 overrides its front side).
+fn surface_drawn_last(priority: i32, on_blueprint: bool) -> bool {
+    let mut scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    // Only Room1's crate, which stands between the camera and the surface.
+    scene.entities.retain(|_, entity| entity.host_instance_id == room1);
+    let surface = HandlerConfig::LiquidSurface { target_instance_id: room2, target_portal_id: BACK_PORTAL, tint: SURFACE_TINT, render_priority: priority };
+    let room1 = scene.instances.get_mut(&room1).unwrap();
+    if on_blueprint {
+        room1.instance_side_handler_configs.remove(&FRONT_SIDE);
+        let blueprint_id = room1.blueprint_id;
+        scene.blueprints.get_mut(&blueprint_id).unwrap().sides[FRONT_SIDE].default_handler_config = surface;
+    } else {
+        room1.instance_side_handler_configs.insert(FRONT_SIDE, surface);
+    }
+
+    let camera = Camera::new(70.0, 0.1, 100.0);
+    let view = SceneView::from_scene_camera(&scene, &scene.active_camera, &camera, Viewport::full(320.0, 240.0)).unwrap();
+    let mut traversal = SceneTraversal::new();
+    let stats = traversal.build_frame(&scene, &[view], &RenderSettings::default(), [0.0, 0.0, 0.0, 1.0], RENDER_LAYER_ALL);
+    assert!(stats[0].entity_faces_drawn > 0, "the crate is in view");
+
+    let vertices = traversal.vertices();
+    let is_surface = |triangle: &[u32]| triangle.iter().all(|&index| vertices[index as usize].color[3] == SURFACE_TINT[3]);
+    let triangles: Vec<&[u32]> = traversal.indices().chunks_exact(3).collect();
+    let first_surface = triangles.iter().position(|triangle| is_surface(triangle)).expect("the surface is drawn");
+    triangles[first_surface..].iter().all(|triangle| is_surface(triangle))
+}
+
+#[test]
+fn render_priority_overrides_distance() {
+    assert!(!surface_drawn_last(0, false), "the nearer crate should cover the surface by default");
+    assert!(surface_drawn_last(1, false));
+    assert!(!surface_drawn_last(0, true));
+    assert!(surface_drawn_last(1, true));
+}
@@ -0,0 +1,109 @@
+// tests/scene_builder.rs
+// Two rooms assembled with `SceneBuilder` from the demo's cuboid blueprint: a connection is
+// wired up from both ends, a door set up beforehand stays a door, ids and the active camera
+// come out as documented, and each kind of connection mistake is reported by `build`.
+
+use glam::Mat4;
+use engine3_refactored::demo_scene::{create_mvp_scene, PORTAL_ID_BACK, PORTAL_ID_FRONT};
+use engine3_refactored::engine_lib::scene_types::{HandlerConfig, HullBlueprint, Scene};
+use engine3_refactored::engine_lib::{SceneBuildError, SceneBuilder};
+
+const FRONT_SIDE: usize = 0;
+const BACK_SIDE: usize = 1;
+const LEFT_SIDE: usize = 2;
+
+fn cuboid() -> HullBlueprint {
+    let scene = create_mvp_scene();
+    let room1 = scene.instance_by_name("Room1").unwrap();
+    scene.blueprints[&room1.blueprint_id].clone()
+}
+
+fn leads_to(scene: &Scene, instance_id: u32, side_index: usize) -> Option<(u32, u32)> {
+    scene.side_config(instance_id, side_index).and_then(HandlerConfig::passable_portal_target)
+}
+
+#[test]
+fn connected_rooms_lead_into_each_other() {
+    let mut builder = SceneBuilder::new();
+    let room = builder.blueprint(cuboid());
+    let (a, b) = (builder.instance(room, "A"), builder.instance(room, "B"));
+    builder.connect(a.side(FRONT_SIDE), b.side(BACK_SIDE)).camera("main", a, Mat4::IDENTITY);
+    let scene = builder.build().unwrap();
+
+    let a_front = &scene.instances[&a.id()].portal_connections[&PORTAL_ID_FRONT];
+    assert_eq!((a_front.target_instance_id, a_front.target_portal_id), (b.id(), PORTAL_ID_BACK));
+    let b_back = &scene.instances[&b.id()].portal_connections[&PORTAL_ID_BACK];
+    assert_eq!((b_back.target_instance_id, b_back.target_portal_id), (a.id(), PORTAL_ID_FRONT));
+
+    assert_eq!(leads_to(&scene, a.id(), FRONT_SIDE), Some((b.id(), PORTAL_ID_BACK)));
+    assert_eq!(leads_to(&scene, b.id(), BACK_SIDE), Some((a.id(), PORTAL_ID_FRONT)));
+    // Sides left unconnected keep the blueprint's walls.
+    assert_eq!(leads_to(&scene, a.id(), BACK_SIDE), None);
+    assert!(!scene.instances[&a.id()].portal_connections.contains_key(&PORTAL_ID_BACK));
+    assert_eq!(scene.shortest_portal_path(a.id(), b.id()).map(|path| path.len()), Some(1));
+}
+
+#[test]
+fn ids_follow_insertion_order_and_first_camera_is_active() {
+    let mut builder = SceneBuilder::new();
+    let mut blueprint = cuboid();
+    blueprint.id = 7;
+    let room = builder.blueprint(blueprint);
+    let (a, b) = (builder.instance(room, "A"), builder.instance(room, "B"));
+    builder.camera("first", b, Mat4::IDENTITY).camera("second", a, Mat4::IDENTITY);
+    let scene = builder.build().unwrap();
+
+    assert_eq!(room.id(), 0);
+    assert_eq!(scene.blueprints[&room.id()].id, room.id());
+    assert_eq!((a.id(), b.id()), (0, 1));
+    assert_eq!(scene.instances[&b.id()].name, "B");
+    assert_eq!(scene.active_camera, "first");
+    assert_eq!(scene.cameras["first"].host_instance_id, b.id());
+}
+
+#[test]
+fn door_set_up_before_connecting_stays_a_door() {
+    let mut builder = SceneBuilder::new();
+    let room = builder.blueprint(cuboid());
+    let (a, b) = (builder.instance(room, "A"), builder.instance(room, "B"));
+    let door = HandlerConfig::Door { target_instance_id: 0, target_portal_id: 0, color: [0.4, 0.2, 0.1, 1.0], open: true, render_priority: 2 };
+    builder.side_config(a.side(FRONT_SIDE), door).connect(a.side(FRONT_SIDE), b.side(BACK_SIDE));
+    let scene = builder.build().unwrap();
+
+    match scene.side_config(a.id(), FRONT_SIDE) {
+        Some(HandlerConfig::Door { target_instance_id, target_portal_id, open: true, render_priority: 2, .. }) => {
+            assert_eq!((*target_instance_id, *target_portal_id), (b.id(), PORTAL_ID_BACK));
+        }
+        other => panic!("expected an open door, got {:?}", other),
+    }
+    assert_eq!(leads_to(&scene, b.id(), BACK_SIDE), Some((a.id(), PORTAL_ID_FRONT)));
+}
+
+#[test]
+fn connecting_a_missing_side_is_an_error() {
+    let mut builder = SceneBuilder::new();
+    let room = builder.blueprint(cuboid());
+    let (a, b) = (builder.instance(room, "A"), builder.instance(room, "B"));
+    builder.connect(a.side(FRONT_SIDE), b.side(99));
+    assert_eq!(builder.build().unwrap_err(), SceneBuildError::MissingSide(b.side(99)));
+}
+
+#[test]
+fn connecting_a_side_without_portal_id_is_an_error() {
+    let mut builder = SceneBuilder::new();
+    let mut blueprint = cuboid();
+    blueprint.sides[LEFT_SIDE].local_portal_id = None;
+    let room = builder.blueprint(blueprint);
+    let (a, b) = (builder.instance(room, "A"), builder.instance(room, "B"));
+    builder.connect(a.side(LEFT_SIDE), b.side(BACK_SIDE));
+    assert_eq!(builder.build().unwrap_err(), SceneBuildError::SideWithoutPortalId(a.side(LEFT_SIDE)));
+}
+
+#[test]
+fn connecting_a_side_twice_is_an_error() {
+    let mut builder = SceneBuilder::new();
+    let room = builder.blueprint(cuboid());
+    let (a, b, c) = (builder.instance(room, "A"), builder.instance(room, "B"), builder.instance(room, "C"));
+    builder.connect(a.side(FRONT_SIDE), b.side(BACK_SIDE)).connect(c.side(FRONT_SIDE), b.side(BACK_SIDE));
+    assert_eq!(builder.build().unwrap_err(), SceneBuildError::SideConnectedTwice(b.side(BACK_SIDE)));
+}
@@ -0,0 +1,125 @@
+// tests/scene_repair.rs
+// Loading the demo scene with broken references: each kind of dangling reference is reported
+// once and patched with a magenta placeholder, a parent cycle is broken, a missing active camera
+// is re-created, and the repaired scene can still be drawn and walked through into a placeholder.
+
+use glam::{Mat4, Vec3};
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::scene_types::{HandlerConfig, Scene, SceneCamera};
+use engine3_refactored::engine_lib::scene_loading::ERROR_WALL_COLOR;
+use engine3_refactored::engine_lib::{instance_world_transform, repair_missing_references, update_camera_in_scene, SceneDiagnostic};
+
+const FRONT_SIDE: usize = 0;
+const BACK_PORTAL: u32 = 1;
+const MISSING: u32 = 42;
+
+fn demo() -> (Scene, u32, u32) {
+    let scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    (scene, room1, room2)
+}
+
+fn is_error_wall(scene: &Scene, instance_id: u32) -> bool {
+    let blueprint = &scene.blueprints[&scene.instances[&instance_id].blueprint_id];
+    blueprint.sides.iter().all(|side| matches!(side.default_handler_config, HandlerConfig::StandardWall { color: ERROR_WALL_COLOR, .. }))
+}
+
+#[test]
+fn intact_scenes_need_no_repair() {
+    let (mut scene, ..) = demo();
+    assert_eq!(repair_missing_references(&mut scene), []);
+}
+
+#[test]
+fn missing_blueprints_get_a_placeholder_under_the_same_id() {
+    let (mut scene, _, room2) = demo();
+    scene.instances.get_mut(&room2).unwrap().blueprint_id = 77;
+    assert_eq!(repair_missing_references(&mut scene), [SceneDiagnostic::MissingBlueprint { instance_id: room2, blueprint_id: 77 }]);
+    assert!(is_error_wall(&scene, room2));
+}
+
+#[test]
+fn missing_parents_and_parent_cycles_leave_roots() {
+    let (mut scene, room1, room2) = demo();
+    scene.instances.get_mut(&room1).unwrap().parent_instance_id = Some(MISSING);
+    let blueprint_id = scene.instances[&room1].blueprint_id;
+    let a = scene.spawn_instance(blueprint_id, []).unwrap();
+    let b = scene.spawn_instance(blueprint_id, []).unwrap();
+    for (child, parent) in [(a, b), (b, a)] {
+        let instance = scene.instances.get_mut(&child).unwrap();
+        instance.parent_instance_id = Some(parent);
+        instance.initial_transform = Some(Mat4::from_translation(Vec3::X));
+    }
+
+    let diagnostics = repair_missing_references(&mut scene);
+    assert_eq!(diagnostics, [
+        SceneDiagnostic::MissingParentInstance { instance_id: room1, parent_instance_id: MISSING },
+        SceneDiagnostic::ParentCycle { instance_id: a },
+    ]);
+    assert_eq!(scene.instances[&room1].parent_instance_id, None);
+    assert!(!scene.instances.contains_key(&MISSING), "a missing parent is dropped, not replaced");
+    // Breaking the cycle at `a` makes it the root that `b` hangs from.
+    assert_eq!((scene.instances[&a].parent_instance_id, scene.instances[&b].parent_instance_id), (None, Some(a)));
+    assert!(instance_world_transform(&scene, b).unwrap().abs_diff_eq(Mat4::from_translation(Vec3::X * 2.0), 1e-6));
+    assert!(scene.instances.contains_key(&room2));
+}
+
+#[test]
+fn missing_portal_targets_become_placeholders_that_can_be_walked_into() {
+    let (mut scene, room1, _) = demo();
+    let room1_instance = scene.instances.get_mut(&room1).unwrap();
+    room1_instance.instance_side_handler_configs.insert(FRONT_SIDE, HandlerConfig::portal(MISSING, BACK_PORTAL));
+    room1_instance.portal_connections.get_mut(&0).unwrap().target_instance_id = MISSING;
+
+    assert_eq!(repair_missing_references(&mut scene), [
+        SceneDiagnostic::MissingPortalTarget { instance_id: room1, side_index: FRONT_SIDE, target_instance_id: MISSING },
+        SceneDiagnostic::MissingConnectionTarget { instance_id: room1, target_instance_id: MISSING },
+    ]);
+    assert!(is_error_wall(&scene, MISSING));
+    assert_eq!(scene.portal_graph().neighbors(room1), [MISSING]);
+
+    #[cfg(feature = "render")]
+    assert!(frame_stats(&scene).portals_traversed > 0);
+    update_camera_in_scene(&mut scene, Vec3::new(0.0, 0.0, 1.55), Mat4::from_rotation_y(std::f32::consts::PI), 0.0);
+    assert_eq!(scene.active_camera().unwrap().host_instance_id, MISSING);
+}
+
+#[test]
+fn entities_and_cameras_in_missing_instances_get_a_placeholder() {
+    let (mut scene, ..) = demo();
+    let entity_id = *scene.entities.keys().min().unwrap();
+    scene.entities.get_mut(&entity_id).unwrap().host_instance_id = MISSING;
+    let moved = Mat4::from_translation(Vec3::new(0.5, 0.0, 0.5));
+    scene.cameras.insert("lost".to_string(), SceneCamera { host_instance_id: MISSING + 1, local_transform: moved, collision_radius: 0.1 });
+
+    assert_eq!(repair_missing_references(&mut scene), [
+        SceneDiagnostic::MissingEntityInstance { entity_id, instance_id: MISSING },
+        SceneDiagnostic::MissingCameraInstance { camera: "lost".to_string(), instance_id: MISSING + 1 },
+    ]);
+    assert!(is_error_wall(&scene, MISSING) && is_error_wall(&scene, MISSING + 1));
+    assert_eq!(scene.cameras["lost"].local_transform, Mat4::IDENTITY, "moved to the placeholder's center");
+}
+
+#[test]
+fn a_missing_active_camera_is_recreated() {
+    let (mut scene, room1, _) = demo();
+    scene.active_camera = "director".to_string();
+    assert_eq!(repair_missing_references(&mut scene), [SceneDiagnostic::MissingActiveCamera { camera: "director".to_string() }]);
+    assert_eq!(scene.active_camera().unwrap().host_instance_id, room1);
+    #[cfg(feature = "render")]
+    assert!(frame_stats(&scene).sides_drawn > 0);
+}
+
+#[cfg(feature = "render")]
+fn frame_stats(scene: &Scene) -> engine3_refactored::rendering_lib::renderer::RenderStats {
+    use engine3_refactored::engine_lib::camera::Camera;
+    use engine3_refactored::engine_lib::scene_types::RENDER_LAYER_ALL;
+    use engine3_refactored::rendering_lib::renderer::{SceneView, Viewport};
+    use engine3_refactored::rendering_lib::{RenderSettings, SceneTraversal};
+
+    let camera = Camera::new(70.0, 0.1, 100.0);
+    let view = SceneView::from_scene_camera(scene, &scene.active_camera, &camera, Viewport::full(320.0, 240.0)).unwrap();
+    let mut traversal = SceneTraversal::new();
+    let mut stats = traversal.build_frame(scene, &[view], &RenderSettings::default(), [0.0, 0.0, 0.0, 1.0], RENDER_LAYER_ALL);
+    stats.remove(0)
+}
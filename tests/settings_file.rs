@@ -0,0 +1,36 @@
+// tests/settings_file.rs
+// The settings file: what is saved loads back the same, and files written before a setting
+// existed still load, with that setting at its default.
+
+use engine3_refactored::settings::{Settings, SettingsError};
+
+#[test]
+fn round_trips_through_toml() {
+    let settings = Settings {
+        mouse_sensitivity: 0.004,
+        invert_y: true,
+        fov_y_deg: 90.0,
+        move_speed: 5.5,
+        vsync: false,
+        window_size: [1920, 1080],
+        render_scale: 0.75,
+        wireframe: true,
+        show_clip_regions: true,
+        show_debug_labels: true,
+        show_hud: false,
+        clear_color: [0.2, 0.3, 0.4, 1.0],
+    };
+    let text = settings.to_toml().unwrap();
+    assert_eq!(Settings::from_toml(&text).unwrap(), settings);
+}
+
+#[test]
+fn missing_keys_keep_defaults() {
+    let settings = Settings::from_toml("fov_y_deg = 100.0\n").unwrap();
+    assert_eq!(settings, Settings { fov_y_deg: 100.0, ..Settings::default() });
+}
+
+#[test]
+fn rejects_malformed_values() {
+    assert!(matches!(Settings::from_toml("window_size = \"large\"\n"), Err(SettingsError::Parse(_))));
+}
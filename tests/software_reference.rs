@@ -0,0 +1,70 @@
+// tests/software_reference.rs
+// `SoftwareRenderer` as a reference for the GPU path: its frames of the demo scene leave no gaps
+// between the fan triangles of neighbouring sides, and match `HeadlessRenderer`'s to within
+// rounding wherever an adapter is available. A portal config on a side with no portal id, as
+// the scene editor could once make, draws without panicking.
+#![cfg(feature = "render")]
+
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::camera::Camera;
+use engine3_refactored::engine_lib::scene_types::{Scene, RENDER_LAYER_ALL};
+use engine3_refactored::rendering_lib::{HeadlessError, HeadlessRenderer, SoftwareRenderer};
+
+const WIDTH: u32 = 160;
+const HEIGHT: u32 = 120;
+// Nothing in the demo scene has this color, so any pixel left at it was not drawn.
+const CLEAR_COLOR: wgpu::Color = wgpu::Color { r: 1.0, g: 0.0, b: 1.0, a: 1.0 };
+
+fn software_frame() -> Vec<u8> {
+    render_software(&create_mvp_scene())
+}
+
+fn render_software(scene: &Scene) -> Vec<u8> {
+    let camera = Camera::new(70.0, 0.1, 100.0);
+    let mut renderer = SoftwareRenderer::new();
+    renderer.render_scene(scene, &camera, WIDTH as f32, HEIGHT as f32, CLEAR_COLOR, RENDER_LAYER_ALL);
+    renderer.pixels().to_vec()
+}
+
+#[test]
+fn closed_room_covers_every_pixel() {
+    let frame = software_frame();
+    assert_eq!(frame.len(), (WIDTH * HEIGHT * 4) as usize);
+    let gaps = frame.chunks_exact(4).filter(|pixel| *pixel == [255, 0, 255, 255]).count();
+    assert_eq!(gaps, 0, "{gaps} pixels were left at the clear color");
+}
+
+#[test]
+fn portals_on_sides_without_a_portal_id_are_not_followed() {
+    let mut scene = create_mvp_scene();
+    let room1 = scene.instance_by_name("Room1").unwrap();
+    let (front_config, blueprint_id) = (scene.side_config(room1.id, 0).unwrap().clone(), room1.blueprint_id);
+    assert!(front_config.passable_portal_target().is_some());
+    scene.blueprints.get_mut(&blueprint_id).unwrap().sides[0].local_portal_id = None;
+    assert_eq!(render_software(&scene).len(), (WIDTH * HEIGHT * 4) as usize);
+}
+
+#[test]
+fn matches_gpu_frame() {
+    let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+    let mut headless = match runtime.block_on(HeadlessRenderer::new(WIDTH, HEIGHT)) {
+        Ok(headless) => headless,
+        Err(HeadlessError::NoAdapter) => {
+            eprintln!("no GPU adapter, skipping");
+            return;
+        }
+        Err(e) => panic!("{e}"),
+    };
+    // The software renderer draws aliased edges.
+    headless.renderer_mut().settings.msaa_samples = 1;
+    let scene = create_mvp_scene();
+    let camera = Camera::new(70.0, 0.1, 100.0);
+    let (gpu_frame, _) = headless.render(&scene, &camera, CLEAR_COLOR, RENDER_LAYER_ALL).unwrap();
+
+    // The GPU's float target and interpolation round slightly differently.
+    let max_difference = gpu_frame.iter().zip(&software_frame())
+        .map(|(gpu, software)| gpu.abs_diff(*software))
+        .max()
+        .unwrap_or(0);
+    assert!(max_difference <= 2, "frames differ by up to {max_difference}");
+}
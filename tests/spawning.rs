@@ -0,0 +1,111 @@
+// tests/spawning.rs
+// Adding and removing instances at runtime in the demo scene: a spawned room is joined to
+// Room1 both ways, portals already in use are refused, removal turns portals back into walls,
+// re-roots children where they were and refuses rooms holding a camera, and ids are never
+// handed out twice.
+
+use glam::{Mat4, Vec3};
+use engine3_refactored::demo_scene::{create_mvp_scene, PORTAL_ID_BACK, PORTAL_ID_FRONT, PORTAL_ID_LEFT, PORTAL_ID_RIGHT, ROOM2_CAMERA_NAME};
+use engine3_refactored::engine_lib::scene_types::{PortalConnectionInfo, Scene};
+use engine3_refactored::engine_lib::{
+    instance_world_transform, set_instance_parent, set_instance_world_transform, HandlerConfig, RemoveInstanceError, SpawnError,
+};
+
+const LEFT_SIDE: usize = 2;
+const RIGHT_SIDE: usize = 3;
+
+fn link(target_instance_id: u32, target_portal_id: u32) -> PortalConnectionInfo {
+    PortalConnectionInfo { target_instance_id, target_portal_id }
+}
+
+// A room spawned off Room1's left wall, entered through its own right wall.
+fn scene_with_annex() -> (Scene, u32, u32) {
+    let mut scene = create_mvp_scene();
+    let room1 = scene.instance_by_name("Room1").unwrap();
+    let (room1, blueprint_id) = (room1.id, room1.blueprint_id);
+    let annex = scene.spawn_instance(blueprint_id, [(PORTAL_ID_RIGHT, link(room1, PORTAL_ID_LEFT))]).unwrap();
+    (scene, room1, annex)
+}
+
+#[test]
+fn spawned_instances_are_linked_both_ways() {
+    let (scene, room1, annex) = scene_with_annex();
+    assert!(scene.instances.keys().all(|&id| id <= annex));
+    assert_eq!(scene.side_config(annex, RIGHT_SIDE).unwrap().passable_portal_target(), Some((room1, PORTAL_ID_LEFT)));
+    assert_eq!(scene.side_config(room1, LEFT_SIDE).unwrap().passable_portal_target(), Some((annex, PORTAL_ID_RIGHT)));
+    let connection = &scene.instances[&room1].portal_connections[&PORTAL_ID_LEFT];
+    assert_eq!((connection.target_instance_id, connection.target_portal_id), (annex, PORTAL_ID_RIGHT));
+    let connection = &scene.instances[&annex].portal_connections[&PORTAL_ID_RIGHT];
+    assert_eq!((connection.target_instance_id, connection.target_portal_id), (room1, PORTAL_ID_LEFT));
+    assert_eq!(scene.portal_graph().neighbors(annex), [room1]);
+}
+
+#[test]
+fn portals_in_use_are_refused() {
+    let mut scene = create_mvp_scene();
+    let room1 = scene.instance_by_name("Room1").unwrap();
+    let (room1, blueprint_id) = (room1.id, room1.blueprint_id);
+    let instance_count = scene.instances.len();
+
+    // Room1's front portal already leads into Room2.
+    assert_eq!(
+        scene.spawn_instance(blueprint_id, [(PORTAL_ID_BACK, link(room1, PORTAL_ID_FRONT))]),
+        Err(SpawnError::TargetPortalInUse { instance_id: room1, portal_id: PORTAL_ID_FRONT }),
+    );
+    // Two portals of the new room cannot share one target.
+    assert_eq!(
+        scene.spawn_instance(blueprint_id, [(PORTAL_ID_BACK, link(room1, PORTAL_ID_LEFT)), (PORTAL_ID_FRONT, link(room1, PORTAL_ID_LEFT))]),
+        Err(SpawnError::TargetPortalInUse { instance_id: room1, portal_id: PORTAL_ID_LEFT }),
+    );
+    assert_eq!(scene.instances.len(), instance_count, "nothing changes on error");
+    assert!(!scene.instances[&room1].portal_connections.contains_key(&PORTAL_ID_LEFT));
+}
+
+#[test]
+fn removed_instances_leave_walls_behind() {
+    let (mut scene, room1, annex) = scene_with_annex();
+    scene.remove_instance(annex).unwrap();
+    // The blueprint's own white wall is back.
+    assert!(matches!(scene.side_config(room1, LEFT_SIDE), Some(HandlerConfig::StandardWall { color: [1.0, 1.0, 1.0, 1.0], .. })));
+    assert!(!scene.instances[&room1].portal_connections.contains_key(&PORTAL_ID_LEFT));
+    assert!(scene.portal_graph().neighbors(room1).iter().all(|&id| id != annex));
+}
+
+#[test]
+fn instances_holding_a_camera_are_not_removed() {
+    let mut scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    assert_eq!(scene.remove_instance(room2).err(), Some(RemoveInstanceError::HostsCamera { camera: ROOM2_CAMERA_NAME.to_string() }));
+    assert!(scene.instances.contains_key(&room2));
+
+    scene.cameras.remove(ROOM2_CAMERA_NAME);
+    scene.remove_instance(room2).unwrap();
+    assert!(matches!(scene.side_config(room1, 0), Some(HandlerConfig::StandardWall { .. })));
+    assert_eq!(scene.remove_instance(room2).err(), Some(RemoveInstanceError::MissingInstance(room2)));
+}
+
+#[test]
+fn children_of_removed_instances_keep_their_place() {
+    let mut scene = create_mvp_scene();
+    let blueprint_id = scene.instance_by_name("Room1").unwrap().blueprint_id;
+    let parent = scene.spawn_instance(blueprint_id, []).unwrap();
+    let child = scene.spawn_instance(blueprint_id, []).unwrap();
+    let child_world = Mat4::from_translation(Vec3::new(12.0, 1.0, 0.0)) * Mat4::from_rotation_y(0.5);
+    assert!(set_instance_world_transform(&mut scene, parent, Mat4::from_translation(Vec3::new(10.0, 0.0, 0.0)) * Mat4::from_rotation_y(1.0)));
+    assert!(set_instance_world_transform(&mut scene, child, child_world));
+    assert!(set_instance_parent(&mut scene, child, Some(parent)));
+
+    scene.remove_instance(parent).unwrap();
+    assert_eq!(scene.instances[&child].parent_instance_id, None);
+    assert!(instance_world_transform(&scene, child).unwrap().abs_diff_eq(child_world, 1e-5));
+}
+
+#[test]
+fn removed_ids_are_not_reused() {
+    let (mut scene, _, annex) = scene_with_annex();
+    let blueprint_id = scene.instances[&annex].blueprint_id;
+    scene.remove_instance(annex).unwrap();
+    assert!(scene.next_instance_id().unwrap() > annex);
+    let next = scene.spawn_instance(blueprint_id, []).unwrap();
+    assert!(next > annex);
+}
@@ -0,0 +1,57 @@
+// tests/translucent_sorting.rs
+// A tinted glass portal on the demo's Room1 front side, drawn by a custom handler that queues
+// Room2 behind it and emits a translucent polygon: the glass is held back and blended over
+// Room2 once that has been drawn, rather than drawn first in side order and covered by it.
+#![cfg(feature = "render")]
+
+use serde::{Deserialize, Serialize};
+use engine3_refactored::demo_scene::create_mvp_scene;
+use engine3_refactored::engine_lib::camera::Camera;
+use engine3_refactored::engine_lib::scene_types::RENDER_LAYER_ALL;
+use engine3_refactored::engine_lib::{queue_portal_traversal, HandlerConfig, HandlerContext, SideHandler};
+use engine3_refactored::rendering_lib::renderer::{SceneView, Viewport};
+use engine3_refactored::rendering_lib::{RenderSettings, SceneTraversal};
+
+const FRONT_SIDE: usize = 0;
+const BACK_PORTAL: u32 = 1;
+const GLASS_TINT: [f32; 4] = [0.6, 0.8, 0.9, 0.35];
+
+#[derive(Serialize, Deserialize)]
+struct GlassParams {
+    target_instance_id: u32,
+    target_portal_id: u32,
+}
+
+struct GlassPortalHandler;
+
+impl SideHandler for GlassPortalHandler {
+    fn process_render(&self, ctx: &mut HandlerContext) {
+        let Some(params) = ctx.side_config.custom_params::<GlassParams>() else { return };
+        queue_portal_traversal(ctx, params.target_instance_id, params.target_portal_id);
+        ctx.emit_visible_polygon(GLASS_TINT);
+    }
+}
+
+#[test]
+fn glass_is_blended_over_what_is_behind_it() {
+    let mut scene = create_mvp_scene();
+    let (room1, room2) = (scene.instance_by_name("Room1").unwrap().id, scene.instance_by_name("Room2").unwrap().id);
+    let glass = HandlerConfig::custom("glass", &GlassParams { target_instance_id: room2, target_portal_id: BACK_PORTAL }).unwrap();
+    scene.instances.get_mut(&room1).unwrap().instance_side_handler_configs.insert(FRONT_SIDE, glass);
+    // Only what is behind the glass, which has to be drawn before it.
+    scene.entities.retain(|_, entity| entity.host_instance_id == room2);
+
+    let camera = Camera::new(70.0, 0.1, 100.0);
+    let view = SceneView::from_scene_camera(&scene, &scene.active_camera, &camera, Viewport::full(320.0, 240.0)).unwrap();
+    let mut traversal = SceneTraversal::new();
+    traversal.side_handlers_mut().register("glass", GlassPortalHandler);
+    let stats = traversal.build_frame(&scene, &[view], &RenderSettings::default(), [0.0, 0.0, 0.0, 1.0], RENDER_LAYER_ALL);
+    assert!(stats[0].portals_traversed > 0, "Room2 should be seen through the glass");
+
+    let vertices = traversal.vertices();
+    let is_glass = |triangle: &[u32]| triangle.iter().all(|&index| vertices[index as usize].color[3] == GLASS_TINT[3]);
+    let triangles: Vec<&[u32]> = traversal.indices().chunks_exact(3).collect();
+    let first_glass = triangles.iter().position(|triangle| is_glass(triangle)).expect("the glass is drawn");
+    assert!(first_glass > 0);
+    assert!(triangles[first_glass..].iter().all(|triangle| is_glass(triangle)), "Room2 was drawn over the glass");
+}
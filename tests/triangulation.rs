@@ -0,0 +1,84 @@
+// tests/triangulation.rs
+// Ear clipping and convex decomposition of hand-made polygons: a concave L in both windings, one
+// with collinear vertices along its edges, and degenerate ones with no area. The triangles must
+// cover the polygon exactly, keep its winding and only use its vertices.
+
+use engine3_refactored::rendering_lib::geometry::Point2;
+use engine3_refactored::rendering_lib::triangulation::{
+    convex_decomposition, ear_clip, is_convex, push_polygon_indices, signed_area_doubled,
+};
+
+fn points(coordinates: &[(f32, f32)]) -> Vec<Point2> {
+    coordinates.iter().map(|&(x, y)| Point2::new(x, y)).collect()
+}
+
+// An L of area 3, counter-clockwise.
+fn l_shape() -> Vec<Point2> {
+    points(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0)])
+}
+
+// Checks that `triangles` tile `polygon` and returns how many there are.
+fn check_tiling(polygon: &[Point2], triangles: &[[usize; 3]]) -> usize {
+    let polygon_area = signed_area_doubled(polygon);
+    let mut total = 0.0;
+    for triangle in triangles {
+        assert!(triangle.iter().all(|&i| i < polygon.len()), "index out of range: {triangle:?}");
+        let area = signed_area_doubled(&triangle.map(|i| polygon[i]));
+        assert!(area * polygon_area.signum() >= -1e-6, "triangle {triangle:?} winds against the polygon");
+        total += area;
+    }
+    assert!((total - polygon_area).abs() < 1e-5, "triangles cover {total}, polygon {polygon_area}");
+    triangles.len()
+}
+
+#[test]
+fn concave_polygons_are_ear_clipped_in_either_winding() {
+    let l = l_shape();
+    assert!(!is_convex(&l));
+    assert_eq!(check_tiling(&l, &ear_clip(&l)), 4);
+
+    let mut clockwise = l.clone();
+    clockwise.reverse();
+    assert!(signed_area_doubled(&clockwise) < 0.0);
+    assert_eq!(check_tiling(&clockwise, &ear_clip(&clockwise)), 4);
+
+    let pieces = convex_decomposition(&l);
+    assert_eq!(pieces.len(), 2, "pieces {pieces:?}");
+    for piece in &pieces {
+        assert!(is_convex(&piece.iter().map(|&i| l[i]).collect::<Vec<_>>()));
+    }
+}
+
+#[test]
+fn collinear_vertices_are_kept() {
+    // The L with extra vertices halfway along its two long edges.
+    let l = points(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0), (1.0, 2.0), (0.0, 2.0), (0.0, 1.0)]);
+    check_tiling(&l, &ear_clip(&l));
+
+    // A square with a vertex on each edge is still convex and is fanned.
+    let square = points(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0), (0.0, 2.0), (0.0, 1.0)]);
+    assert!(is_convex(&square));
+    let mut indices = Vec::new();
+    push_polygon_indices(&mut indices, 10, &square);
+    assert_eq!(indices.len(), 3 * (square.len() - 2));
+    assert!(indices.iter().all(|&index| (10..10 + square.len() as u32).contains(&index)));
+}
+
+#[test]
+fn degenerate_polygons_do_not_panic() {
+    assert!(ear_clip::<f32>(&[]).is_empty());
+    assert!(ear_clip(&points(&[(0.0, 0.0), (1.0, 1.0)])).is_empty());
+
+    // Zero area: every vertex on one line, or the same point repeated.
+    for polygon in [
+        points(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0), (3.0, 0.0), (1.5, 0.0)]),
+        points(&[(1.0, 1.0), (1.0, 1.0), (1.0, 1.0), (1.0, 1.0)]),
+    ] {
+        let triangles = ear_clip(&polygon);
+        assert!(triangles.len() <= polygon.len() - 2);
+        check_tiling(&polygon, &triangles);
+        let mut indices = Vec::new();
+        push_polygon_indices(&mut indices, 0, &polygon);
+        assert!(indices.iter().all(|&index| (index as usize) < polygon.len()));
+    }
+}